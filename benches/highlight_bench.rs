@@ -0,0 +1,96 @@
+// パフォーマンステスト: エディタのシンタックスハイライト
+//
+// 全体を毎回ハイライトする場合と、キャッシュによる差分ハイライトを比較する。
+// `cargo bench --bench highlight_bench`
+use eframe::egui::FontId;
+use lala::gui::{HighlightCache, SyntaxHighlighter};
+use std::time::{Duration, Instant};
+
+/// 約20,000行のRustソース
+fn sample(lines: usize) -> String {
+    let block = r#"/// Adds two numbers
+pub fn add(a: i32, b: i32) -> i32 {
+    let sum = a + b; // comment
+    println!("{} + {} = {}", a, b, sum);
+    sum
+}
+
+"#;
+    let count = block.lines().count();
+    block.repeat(lines / count + 1)
+}
+
+fn report(label: &str, elapsed: Duration, limit: Duration) {
+    let mark = if elapsed <= limit { "✓" } else { "✗" };
+    println!("{mark} {label}: {elapsed:?}（目標 {limit:?} 以下）");
+}
+
+fn main() {
+    let highlighter = SyntaxHighlighter::new();
+    let syntax = highlighter.find_language("Rust").unwrap();
+    let theme = highlighter.theme("base16-ocean.dark");
+    let text = sample(20_000);
+    let lines = text.lines().count();
+    let viewport = 0..60;
+
+    println!("シンタックスハイライトのパフォーマンステスト（{lines}行）\n");
+
+    // 従来の方式: フレームごとに全体をハイライト
+    let start = Instant::now();
+    let job = highlighter.highlight_with(&text, syntax, theme, FontId::monospace(14.0));
+    let full = start.elapsed();
+    println!(
+        "全体のハイライト（1フレームあたり）: {full:?}（{} セクション）",
+        job.sections.len()
+    );
+
+    // 初回表示: 画面内の行だけを同期でハイライト
+    let mut cache = HighlightCache::new();
+    let start = Instant::now();
+    cache.update(&highlighter, &text, syntax, theme, viewport.clone());
+    report(
+        "初回表示（画面内のみ）",
+        start.elapsed(),
+        Duration::from_millis(50),
+    );
+
+    // 残りはバックグラウンドスレッドで処理
+    let start = Instant::now();
+    while cache.update(&highlighter, &text, syntax, theme, viewport.clone()) {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    println!("バックグラウンドで全体を完了: {:?}", start.elapsed());
+
+    // 変更のないフレーム
+    let start = Instant::now();
+    cache.update(&highlighter, &text, syntax, theme, viewport.clone());
+    report(
+        "変更なしのフレーム",
+        start.elapsed(),
+        Duration::from_millis(16),
+    );
+
+    // 中央の1文字を変更: 次の行で状態が収束する
+    let middle = text.len() / 2;
+    let at = text[middle..].find("sum\n").unwrap() + middle;
+    let mut edited = text.clone();
+    edited.insert(at, 'x');
+    let start = Instant::now();
+    cache.update(&highlighter, &edited, syntax, theme, viewport.clone());
+    report("1文字の編集", start.elapsed(), Duration::from_millis(16));
+
+    // 先頭でブロックコメントを開く: 以降すべての行が変わる
+    let commented = format!("/*\n{edited}");
+    let start = Instant::now();
+    cache.update(&highlighter, &commented, syntax, theme, viewport.clone());
+    report(
+        "コメント開始（フレーム）",
+        start.elapsed(),
+        Duration::from_millis(16),
+    );
+    let start = Instant::now();
+    while cache.update(&highlighter, &commented, syntax, theme, viewport.clone()) {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    println!("コメント開始（全体の再ハイライト）: {:?}", start.elapsed());
+}
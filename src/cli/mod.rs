@@ -9,6 +9,7 @@
 //! - バージョン情報の表示 (`-v` / `--version`)
 //! - ファイルパス、ディレクトリパス、または空のエディタ起動の判定
 //! - Markdownプレビュー機能
+//! - HTML / PDF / SVG / PNG への書き出し (`lala export`)
 
 pub mod html_view;
 pub mod latex_view;
@@ -18,6 +19,8 @@ pub mod mermaid_view;
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
+use crate::export::Format;
+
 #[cfg(test)]
 mod tests;
 
@@ -35,9 +38,26 @@ pub enum StartupMode {
     /// HTMLプレビュー（CLIモード）
     HtmlPreview { file: PathBuf, no_color: bool },
     /// Mermaidダイアグラムプレビュー（CLIモード）
-    MermaidPreview { file: PathBuf, no_color: bool },
+    MermaidPreview {
+        file: PathBuf,
+        no_color: bool,
+        /// Write the diagram as SVG to this path instead of printing it
+        svg: Option<PathBuf>,
+        /// Only report syntax problems; exit non-zero on errors
+        check: bool,
+    },
     /// LaTeXプレビュー（CLIモード）
     LatexPreview { file: PathBuf, no_color: bool },
+    /// HTML / PDF / SVG / PNG への書き出し（CLIモード）
+    Export {
+        file: PathBuf,
+        format: Format,
+        /// Output path; next to the input with the format's extension
+        /// when not given, `-` for standard output
+        output: Option<PathBuf>,
+    },
+    /// Markdownのリンク切れチェック（CLIモード）
+    CheckLinks { path: PathBuf },
     /// ファイル表示（CLIモード）
     ViewFile { file: PathBuf, line_numbers: bool },
     /// CLIコマンドが実行された（GUIを起動しない）
@@ -102,6 +122,14 @@ enum Commands {
         /// カラー出力を無効化
         #[arg(long)]
         no_color: bool,
+
+        /// SVGファイルとして書き出す
+        #[arg(long, value_name = "OUT")]
+        svg: Option<PathBuf>,
+
+        /// 構文チェックのみ行う（エラーがあれば終了コード1）
+        #[arg(long, conflicts_with = "svg")]
+        check: bool,
     },
 
     /// LaTeXドキュメントをプレビュー表示
@@ -116,6 +144,30 @@ enum Commands {
         no_color: bool,
     },
 
+    /// ドキュメントをファイルに書き出す
+    #[command(about = "Export a Markdown, HTML, LaTeX or Mermaid file to HTML, PDF, SVG or PNG")]
+    Export {
+        /// 書き出すファイルのパス
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// 出力形式（省略時は出力ファイルの拡張子、なければ html）
+        #[arg(long, value_name = "FORMAT", value_parser = ["html", "pdf", "svg", "png"])]
+        to: Option<String>,
+
+        /// 出力先（`-` で標準出力）
+        #[arg(short, long, value_name = "OUT")]
+        output: Option<PathBuf>,
+    },
+
+    /// リンク切れを報告する
+    #[command(about = "Report broken links and images in Markdown files")]
+    Links {
+        /// Markdownファイルまたはディレクトリのパス
+        #[arg(value_name = "PATH", default_value = ".")]
+        path: PathBuf,
+    },
+
     /// ファイルを表示
     #[command(about = "View file content")]
     View {
@@ -157,8 +209,31 @@ where
                 StartupMode::MarkdownPreview { file, no_color }
             }
             Commands::Html { file, no_color } => StartupMode::HtmlPreview { file, no_color },
-            Commands::Mermaid { file, no_color } => StartupMode::MermaidPreview { file, no_color },
+            Commands::Mermaid {
+                file,
+                no_color,
+                svg,
+                check,
+            } => StartupMode::MermaidPreview {
+                file,
+                no_color,
+                svg,
+                check,
+            },
             Commands::Latex { file, no_color } => StartupMode::LatexPreview { file, no_color },
+            Commands::Export { file, to, output } => {
+                let format = to
+                    .as_deref()
+                    .or_else(|| output.as_ref()?.extension()?.to_str())
+                    .and_then(Format::from_name)
+                    .unwrap_or(Format::Html);
+                StartupMode::Export {
+                    file,
+                    format,
+                    output,
+                }
+            }
+            Commands::Links { path } => StartupMode::CheckLinks { path },
             Commands::View { file, line_numbers } => StartupMode::ViewFile { file, line_numbers },
         };
     }
@@ -71,3 +71,61 @@ fn test_parse_args_with_no_extension() {
 // これらのフラグの動作確認は、以下のコマンドで手動で実行してください：
 // cargo run -- -h
 // cargo run -- -v
+
+#[test]
+fn test_parse_args_export() {
+    // --to が優先され、省略時は出力ファイルの拡張子、どちらもなければ HTML
+    let mode = parse_args(vec!["lala", "export", "doc.md", "--to", "pdf"]);
+    assert_eq!(
+        mode,
+        StartupMode::Export {
+            file: PathBuf::from("doc.md"),
+            format: Format::Pdf,
+            output: None,
+        }
+    );
+
+    let mode = parse_args(vec![
+        "lala",
+        "export",
+        "diagram.mmd",
+        "-o",
+        "out/diagram.png",
+    ]);
+    assert_eq!(
+        mode,
+        StartupMode::Export {
+            file: PathBuf::from("diagram.mmd"),
+            format: Format::Png,
+            output: Some(PathBuf::from("out/diagram.png")),
+        }
+    );
+
+    let mode = parse_args(vec!["lala", "export", "paper.tex"]);
+    assert!(matches!(
+        mode,
+        StartupMode::Export {
+            format: Format::Html,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_links_command() {
+    let mode = parse_args(vec!["lala", "links", "docs"]);
+    assert_eq!(
+        mode,
+        StartupMode::CheckLinks {
+            path: PathBuf::from("docs")
+        }
+    );
+
+    let mode = parse_args(vec!["lala", "links"]);
+    assert_eq!(
+        mode,
+        StartupMode::CheckLinks {
+            path: PathBuf::from(".")
+        }
+    );
+}
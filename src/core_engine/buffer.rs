@@ -28,6 +28,34 @@ impl Range {
     }
 }
 
+/// An edit reported by a [`Buffer`]: `removed` lines starting at `line`
+/// were replaced by `inserted` lines
+///
+/// Lines that were only partly edited count as both removed and inserted,
+/// so typing within a line is `{ line, removed: 1, inserted: 1 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextChange {
+    pub line: usize,
+    pub removed: usize,
+    pub inserted: usize,
+}
+
+impl TextChange {
+    /// Where `line` (0-based, before the edit) ends up, or `None` if it was
+    /// removed. An edited first line stays in place.
+    pub fn map_line(&self, line: usize) -> Option<usize> {
+        if line < self.line {
+            Some(line)
+        } else if line >= self.line + self.removed {
+            Some(line - self.removed + self.inserted)
+        } else if line == self.line && self.inserted > 0 {
+            Some(line)
+        } else {
+            None
+        }
+    }
+}
+
 /// A text buffer backed by a Rope for efficient editing
 pub struct Buffer {
     #[allow(dead_code)]
@@ -36,6 +64,8 @@ pub struct Buffer {
     #[allow(dead_code)]
     file_path: Option<PathBuf>,
     dirty: bool,
+    /// Edits not yet collected with [`Buffer::take_changes`]
+    changes: Vec<TextChange>,
 }
 
 impl Buffer {
@@ -46,6 +76,7 @@ impl Buffer {
             rope: Rope::new(),
             file_path: None,
             dirty: false,
+            changes: Vec::new(),
         }
     }
 
@@ -55,6 +86,7 @@ impl Buffer {
             rope: Rope::from_str(&content),
             file_path,
             dirty: false,
+            changes: Vec::new(),
         }
     }
 
@@ -133,10 +165,52 @@ impl Buffer {
         self.rope.remove(start_char..end_char);
         self.rope.insert(start_char, new_text);
         self.dirty = true;
+        self.changes.push(TextChange {
+            line: range.start.line,
+            removed: range.end.line - range.start.line + 1,
+            inserted: new_text.matches('\n').count() + 1,
+        });
 
         Ok(())
     }
 
+    /// Replace the whole content with `content`, such as the text of an
+    /// editor widget, recording only the part that differs
+    pub fn set_content(&mut self, content: &str) {
+        if self.rope == content {
+            return;
+        }
+        let old = self.rope.to_string();
+        let prefix = common_prefix(&old, content);
+        let suffix = common_suffix(&old[prefix..], &content[prefix..]);
+        let removed = &old[prefix..old.len() - suffix];
+        let inserted = &content[prefix..content.len() - suffix];
+
+        let start_char = self.rope.byte_to_char(prefix);
+        let end_char = start_char + removed.chars().count();
+        self.rope.remove(start_char..end_char);
+        self.rope.insert(start_char, inserted);
+        self.dirty = true;
+
+        // Whole lines inserted or deleted at a line start leave the line
+        // after them untouched
+        let at_line_start = prefix == 0 || old.as_bytes()[prefix - 1] == b'\n';
+        let whole_lines = at_line_start
+            && (removed.is_empty() || removed.ends_with('\n'))
+            && (inserted.is_empty() || inserted.ends_with('\n'));
+        let partial = usize::from(!whole_lines);
+        self.changes.push(TextChange {
+            line: old[..prefix].matches('\n').count(),
+            removed: removed.matches('\n').count() + partial,
+            inserted: inserted.matches('\n').count() + partial,
+        });
+    }
+
+    /// Edits since the last call, oldest first
+    pub fn take_changes(&mut self) -> Vec<TextChange> {
+        std::mem::take(&mut self.changes)
+    }
+
     /// Convert a Position to a character index in the rope
     pub fn position_to_char_idx(&self, pos: Position) -> Result<usize, String> {
         if pos.line >= self.rope.len_lines() {
@@ -171,6 +245,24 @@ impl Buffer {
     }
 }
 
+/// Length in bytes of the common prefix, on a char boundary
+fn common_prefix(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, x), y)| x != y)
+        .map_or(a.len().min(b.len()), |((i, _), _)| i)
+}
+
+/// Length in bytes of the common suffix, on a char boundary
+fn common_suffix(a: &str, b: &str) -> usize {
+    a.chars()
+        .rev()
+        .zip(b.chars().rev())
+        .take_while(|(x, y)| x == y)
+        .map(|(x, _)| x.len_utf8())
+        .sum()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -225,5 +317,49 @@ mod tests {
 
         assert_eq!(buffer.content(), "Hello Rust");
         assert!(buffer.is_dirty());
+        assert_eq!(
+            buffer.take_changes(),
+            vec![TextChange {
+                line: 0,
+                removed: 1,
+                inserted: 1
+            }]
+        );
+        assert!(buffer.take_changes().is_empty());
+    }
+
+    #[test]
+    fn test_set_content_changes() {
+        let change = |old: &str, new: &str| {
+            let mut buffer = Buffer::from_string(BufferId(0), old.to_string(), None);
+            buffer.set_content(new);
+            assert_eq!(buffer.content(), new);
+            let changes = buffer.take_changes();
+            (changes.len() == 1).then(|| {
+                let c = changes[0];
+                (c.line, c.removed, c.inserted)
+            })
+        };
+        assert_eq!(change("a\nb\n", "a\nb\n"), None);
+        // Typing within a line
+        assert_eq!(change("a\nbé\nc\n", "a\nbéx\nc\n"), Some((1, 1, 1)));
+        // Splitting a line
+        assert_eq!(change("a\nbc\nd\n", "a\nb\nc\nd\n"), Some((1, 1, 2)));
+        // A whole line inserted before line 1 and one deleted
+        assert_eq!(change("a\n# b\n", "a\nnew\n# b\n"), Some((1, 0, 1)));
+        assert_eq!(change("a\nx\n# b\n", "a\n# b\n"), Some((1, 1, 0)));
+    }
+
+    #[test]
+    fn test_map_line() {
+        let change = TextChange {
+            line: 2,
+            removed: 2,
+            inserted: 1,
+        };
+        assert_eq!(change.map_line(1), Some(1));
+        assert_eq!(change.map_line(2), Some(2));
+        assert_eq!(change.map_line(3), None);
+        assert_eq!(change.map_line(4), Some(3));
     }
 }
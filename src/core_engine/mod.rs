@@ -2,4 +2,4 @@ pub mod buffer;
 
 #[allow(unused_imports)]
 pub use buffer::Position;
-pub use buffer::{Buffer, BufferId, Range};
+pub use buffer::{Buffer, BufferId, Range, TextChange};
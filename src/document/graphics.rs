@@ -0,0 +1,259 @@
+//! Images in the terminal
+//!
+//! Terminals that speak the kitty graphics protocol (kitty, WezTerm,
+//! Ghostty) or sixel (foot, mlterm, iTerm2, ...) can draw pictures between
+//! lines of text. Support is guessed from the environment, because asking
+//! the terminal would need raw mode; `LALA_GRAPHICS=kitty|sixel|none`
+//! overrides the guess. Inside tmux and screen images are off, since they
+//! do not pass the escapes through by default.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::IsTerminal;
+
+use base64::Engine as _;
+use image::RgbaImage;
+
+/// Assumed size of a terminal cell in pixels, used to turn columns into
+/// pixels for sixel (kitty scales to a column count itself)
+pub const CELL_WIDTH: u32 = 10;
+
+/// Graphics protocol of the terminal
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Graphics {
+    /// Text only: images are shown as alt text and path
+    #[default]
+    None,
+    Kitty,
+    Sixel,
+}
+
+impl Graphics {
+    /// Protocol of the terminal on standard output
+    pub fn detect() -> Graphics {
+        if !std::io::stdout().is_terminal() {
+            return Graphics::None;
+        }
+        Graphics::from_env(|name| std::env::var(name).ok())
+    }
+
+    /// Guess from environment variables (`var` looks one up)
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Graphics {
+        if let Some(choice) = var("LALA_GRAPHICS") {
+            return match choice.to_ascii_lowercase().as_str() {
+                "kitty" => Graphics::Kitty,
+                "sixel" => Graphics::Sixel,
+                _ => Graphics::None,
+            };
+        }
+        if var("TMUX").is_some() || var("STY").is_some() {
+            return Graphics::None;
+        }
+
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        if var("KITTY_WINDOW_ID").is_some()
+            || term == "xterm-kitty"
+            || term == "xterm-ghostty"
+            || matches!(program.as_str(), "WezTerm" | "ghostty")
+        {
+            Graphics::Kitty
+        } else if term.starts_with("foot")
+            || term.starts_with("mlterm")
+            || term.starts_with("contour")
+            || term.contains("sixel")
+            || matches!(program.as_str(), "iTerm.app" | "mintty")
+        {
+            Graphics::Sixel
+        } else {
+            Graphics::None
+        }
+    }
+
+    /// Escape sequence drawing `image` `columns` cells wide, followed by a
+    /// newline; `None` for [`Graphics::None`]
+    pub fn encode(self, image: &RgbaImage, columns: usize) -> Option<String> {
+        let columns = columns.max(1) as u32;
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let target_width = columns * CELL_WIDTH;
+        let target_height =
+            ((height as f32 * target_width as f32 / width as f32).round() as u32).max(1);
+        let resized = image::imageops::resize(
+            image,
+            target_width,
+            target_height,
+            image::imageops::FilterType::Triangle,
+        );
+        match self {
+            Graphics::None => None,
+            Graphics::Kitty => Some(kitty(&resized, columns)),
+            Graphics::Sixel => Some(sixel(&resized)),
+        }
+    }
+}
+
+/// PNG payload in 4096-byte chunks, displayed `columns` cells wide
+fn kitty(image: &RgbaImage, columns: u32) -> String {
+    let mut png = Vec::new();
+    if image
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .is_err()
+    {
+        return String::new();
+    }
+    let data = base64::engine::general_purpose::STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+
+    let mut out = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(index + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+        if index == 0 {
+            // a=T: transmit and display, f=100: PNG, q=2: no replies
+            let _ = write!(
+                out,
+                "\x1b_Ga=T,f=100,q=2,c={columns},m={more};{chunk}\x1b\\"
+            );
+        } else {
+            let _ = write!(out, "\x1b_Gm={more};{chunk}\x1b\\");
+        }
+    }
+    out.push('\n');
+    out
+}
+
+/// Sixel with a 6×6×6 color cube; transparent pixels are left untouched
+fn sixel(image: &RgbaImage) -> String {
+    let (width, height) = image.dimensions();
+    let color = |x: u32, y: u32| -> Option<usize> {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        let level = |c: u8| (c as usize * 5 + 127) / 255;
+        (a >= 128).then(|| level(r) * 36 + level(g) * 6 + level(b))
+    };
+
+    // P2=1: pixels with no color set keep the background
+    let mut out = format!("\x1bP0;1;0q\"1;1;{width};{height}");
+    for index in 0..216 {
+        let percent = |level: usize| level * 100 / 5;
+        let _ = write!(
+            out,
+            "#{index};2;{};{};{}",
+            percent(index / 36),
+            percent(index / 6 % 6),
+            percent(index % 6)
+        );
+    }
+
+    for band in (0..height).step_by(6) {
+        // Sixel bits per column, for each color used in this band
+        let mut layers: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
+        for y in band..(band + 6).min(height) {
+            for x in 0..width {
+                if let Some(index) = color(x, y) {
+                    let bits = layers
+                        .entry(index)
+                        .or_insert_with(|| vec![0; width as usize]);
+                    bits[x as usize] |= 1 << (y - band);
+                }
+            }
+        }
+        for (layer, (index, bits)) in layers.iter().enumerate() {
+            if layer > 0 {
+                // Back to the start of the band for the next color
+                out.push('$');
+            }
+            let _ = write!(out, "#{index}");
+            push_run_length(&mut out, bits);
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\\n");
+    out
+}
+
+/// Sixel characters with `!count` for runs
+fn push_run_length(out: &mut String, bits: &[u8]) {
+    let mut i = 0;
+    while i < bits.len() {
+        let run = bits[i..].iter().take_while(|&&b| b == bits[i]).count();
+        let ch = (63 + bits[i]) as char;
+        if run > 3 {
+            let _ = write!(out, "!{run}{ch}");
+        } else {
+            out.extend(std::iter::repeat_n(ch, run));
+        }
+        i += run;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn test_detect_from_environment() {
+        assert_eq!(
+            Graphics::from_env(env(&[("TERM", "xterm-kitty")])),
+            Graphics::Kitty
+        );
+        assert_eq!(
+            Graphics::from_env(env(&[("TERM_PROGRAM", "WezTerm")])),
+            Graphics::Kitty
+        );
+        assert_eq!(
+            Graphics::from_env(env(&[("TERM", "foot")])),
+            Graphics::Sixel
+        );
+        assert_eq!(
+            Graphics::from_env(env(&[("TERM", "xterm-256color")])),
+            Graphics::None
+        );
+        assert_eq!(
+            Graphics::from_env(env(&[("TERM", "xterm-kitty"), ("TMUX", "/tmp/tmux")])),
+            Graphics::None
+        );
+        assert_eq!(
+            Graphics::from_env(env(&[("TERM", "xterm"), ("LALA_GRAPHICS", "sixel")])),
+            Graphics::Sixel
+        );
+    }
+
+    #[test]
+    fn test_kitty_chunks() {
+        let image = RgbaImage::from_pixel(400, 400, image::Rgba([1, 2, 3, 255]));
+        let out = Graphics::Kitty.encode(&image, 40).unwrap();
+        assert!(out.starts_with("\x1b_Ga=T,f=100,q=2,c=40,m="));
+        assert!(out.ends_with("\x1b\\\n"));
+        // Every chunk is terminated, the last one says no more follow
+        assert_eq!(out.matches("\x1b_G").count(), out.matches("\x1b\\").count());
+        assert!(out.contains("m=0;"));
+    }
+
+    #[test]
+    fn test_sixel_bands() {
+        // 2 columns → 20 pixels wide, red on top of a transparent half
+        let mut image = RgbaImage::new(20, 12);
+        for x in 0..20 {
+            for y in 0..6 {
+                image.put_pixel(x, y, image::Rgba([255, 0, 0, 255]));
+            }
+        }
+        let out = Graphics::Sixel.encode(&image, 2).unwrap();
+        assert!(out.starts_with("\x1bP0;1;0q\"1;1;20;12"));
+        // Red (index 180) fills the first band, the second is empty
+        assert!(out.contains("#180;2;100;0;0"));
+        assert!(out.ends_with("#180!20~--\x1b\\\n"));
+        assert_eq!(Graphics::None.encode(&image, 2), None);
+    }
+}
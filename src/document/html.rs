@@ -0,0 +1,662 @@
+//! HTML → [`Document`] parser
+//!
+//! Walks the DOM produced by `scraper` in document order, so headings,
+//! paragraphs, lists, tables and code keep their original sequence.
+//! Unknown container elements (`div`, `section`, ...) are flattened into
+//! their children; `head`, `script` and `style` are skipped.
+//!
+//! The DOM keeps no source positions, so the start tags of block elements
+//! are found with a small tokenizer (skipping comments and raw text such as
+//! `<script>`) and paired with the elements in document order to build the
+//! [`Document::source_map`].
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use ego_tree::{NodeId, NodeRef};
+use scraper::{ElementRef, Html, Node, Selector};
+
+use super::{Alignment, Block, Document, Inline, List, ListItem, Table};
+
+/// Elements whose content is never rendered
+const SKIPPED: &[&str] = &[
+    "head", "script", "style", "noscript", "template", "title", "meta", "link",
+];
+
+/// Elements treated as block containers
+const CONTAINERS: &[&str] = &[
+    "html",
+    "body",
+    "div",
+    "section",
+    "article",
+    "main",
+    "header",
+    "footer",
+    "nav",
+    "aside",
+    "figure",
+    "figcaption",
+    "details",
+    "summary",
+    "form",
+    "dl",
+    "dt",
+    "dd",
+    "center",
+];
+
+/// Elements whose content is raw text, where `<` does not start a tag
+const RAW_TEXT: &[&str] = &[
+    "script", "style", "textarea", "title", "xmp", "iframe", "noembed", "noframes",
+];
+
+/// Source offsets of block elements' start tags, by DOM node
+type Offsets = HashMap<NodeId, usize>;
+
+/// Parse an HTML document or fragment
+pub fn parse(html: &str) -> Document {
+    let dom = Html::parse_document(html);
+
+    let title = Selector::parse("title")
+        .ok()
+        .and_then(|selector| dom.select(&selector).next())
+        .map(|title| collapse_whitespace(&title.text().collect::<String>()))
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty());
+
+    let root = Selector::parse("body")
+        .ok()
+        .and_then(|selector| dom.select(&selector).next())
+        .unwrap_or_else(|| dom.root_element());
+
+    let offsets = block_offsets(&dom, html);
+    let positioned = positioned_blocks(*root, &offsets);
+
+    // Loose text has no tag of its own and starts where the block before it
+    let mut starts = Vec::with_capacity(positioned.len());
+    let mut previous = 0;
+    for (start, _) in &positioned {
+        previous = start.unwrap_or(previous).max(previous);
+        starts.push(previous);
+    }
+    let source_map: Vec<Range<usize>> = starts
+        .iter()
+        .enumerate()
+        .map(|(index, &start)| start..starts.get(index + 1).copied().unwrap_or(html.len()))
+        .collect();
+
+    Document {
+        title,
+        blocks: positioned.into_iter().map(|(_, block)| block).collect(),
+        source_map,
+        ..Default::default()
+    }
+}
+
+/// Offsets of the start tags of block elements, paired with the DOM
+/// elements in document order
+fn block_offsets(dom: &Html, html: &str) -> Offsets {
+    let tags: Vec<(usize, String)> = start_tags(html)
+        .into_iter()
+        .filter(|(_, name)| tracks_position(name))
+        .collect();
+    let mut next = 0;
+    let mut offsets = Offsets::new();
+    for node in dom.tree.root().descendants() {
+        let Some(element) = ElementRef::wrap(node) else {
+            continue;
+        };
+        let name = element.value().name();
+        if !tracks_position(name) {
+            continue;
+        }
+        // Tags the parser dropped (a stray `</p>` creates an element
+        // without one) are skipped until the names line up again
+        if let Some(found) = tags[next..].iter().position(|(_, tag)| tag == name) {
+            offsets.insert(node.id(), tags[next + found].0);
+            next += found + 1;
+        }
+    }
+    offsets
+}
+
+/// Block elements whose start tags are located in the source; `html` and
+/// `body` are often implied and have no tag
+fn tracks_position(name: &str) -> bool {
+    is_block(name) && !matches!(name, "html" | "body")
+}
+
+/// Offset and lower-case name of each start tag in `html`
+///
+/// Comments, doctypes and the content of [`RAW_TEXT`] elements are
+/// skipped, and quoted attribute values may contain `>`.
+fn start_tags(html: &str) -> Vec<(usize, String)> {
+    // ASCII case folding keeps byte offsets
+    let html = html.to_ascii_lowercase();
+    let bytes = html.as_bytes();
+    let mut tags = Vec::new();
+    let mut at = 0;
+    while let Some(found) = html[at..].find('<') {
+        let start = at + found;
+        let rest = &html[start..];
+        if rest.starts_with("<!--") {
+            at = rest
+                .find("-->")
+                .map_or(html.len(), |end| start + end + "-->".len());
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            at = rest.find('>').map_or(html.len(), |end| start + end + 1);
+            continue;
+        }
+        if !bytes.get(start + 1).is_some_and(u8::is_ascii_alphabetic) {
+            at = start + 1;
+            continue;
+        }
+
+        let name_end = rest
+            .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+            .unwrap_or(rest.len());
+        let name = rest[1..name_end].to_string();
+
+        // The end of the tag, past any quoted attribute values
+        let mut quote = None;
+        let mut end = html.len();
+        for (index, c) in rest[name_end..].char_indices() {
+            match (quote, c) {
+                (None, '"' | '\'') => quote = Some(c),
+                (Some(open), c) if c == open => quote = None,
+                (None, '>') => {
+                    end = start + name_end + index + 1;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        at = end;
+
+        if RAW_TEXT.contains(&name.as_str()) {
+            let close = format!("</{name}");
+            at = html[at..]
+                .find(&close)
+                .map_or(html.len(), |found| at + found);
+        }
+        tags.push((start, name));
+    }
+    tags
+}
+
+/// Parse the children of a node as a sequence of blocks
+fn parse_blocks(parent: NodeRef<Node>, offsets: &Offsets) -> Vec<Block> {
+    positioned_blocks(parent, offsets)
+        .into_iter()
+        .map(|(_, block)| block)
+        .collect()
+}
+
+/// [`parse_blocks`] with the source offset of each block, where known
+fn positioned_blocks(parent: NodeRef<Node>, offsets: &Offsets) -> Vec<(Option<usize>, Block)> {
+    let mut blocks = Vec::new();
+    let mut pending = Vec::new();
+
+    for child in parent.children() {
+        let Some(element) = ElementRef::wrap(child) else {
+            parse_inline(child, &mut pending);
+            continue;
+        };
+
+        let name = element.value().name();
+        if SKIPPED.contains(&name) {
+            continue;
+        }
+        if !is_block(name) {
+            parse_inline(child, &mut pending);
+            continue;
+        }
+
+        flush_paragraph(&mut pending, &mut blocks);
+        parse_block(element, offsets, &mut blocks);
+    }
+
+    flush_paragraph(&mut pending, &mut blocks);
+    blocks
+}
+
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "p" | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "ul"
+            | "ol"
+            | "pre"
+            | "blockquote"
+            | "table"
+            | "hr"
+    ) || CONTAINERS.contains(&name)
+}
+
+fn parse_block(element: ElementRef, offsets: &Offsets, blocks: &mut Vec<(Option<usize>, Block)>) {
+    let start = offsets.get(&element.id()).copied();
+    let name = element.value().name();
+    match name {
+        "p" => {
+            let content = parse_inline_children(*element);
+            if !content.is_empty() {
+                blocks.push((start, Block::Paragraph(content)));
+            }
+        }
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let content = parse_inline_children(*element);
+            if !content.is_empty() {
+                let level = name[1..].parse().unwrap_or(1);
+                blocks.push((start, Block::Heading { level, content }));
+            }
+        }
+        "ul" | "ol" => blocks.push((start, Block::List(parse_list(element, offsets)))),
+        "pre" => blocks.push((start, parse_pre(element))),
+        "blockquote" => blocks.push((start, Block::BlockQuote(parse_blocks(*element, offsets)))),
+        "table" => blocks.push((start, Block::Table(parse_table(element)))),
+        "hr" => blocks.push((start, Block::Rule)),
+        _ => {
+            let inner = positioned_blocks(*element, offsets);
+            // Loose text at the start of a container begins at its tag
+            blocks.extend(
+                inner
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, (at, block))| (at.or(start.filter(|_| index == 0)), block)),
+            );
+        }
+    }
+}
+
+fn parse_list(element: ElementRef, offsets: &Offsets) -> List {
+    let start = if element.value().name() == "ol" {
+        Some(
+            element
+                .value()
+                .attr("start")
+                .and_then(|s| s.trim().parse().ok())
+                .unwrap_or(1),
+        )
+    } else {
+        None
+    };
+
+    let items = element
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|child| child.value().name() == "li")
+        .map(|li| ListItem {
+            checked: task_checkbox(li),
+            task_offset: None,
+            blocks: parse_blocks(*li, offsets),
+        })
+        .collect();
+
+    List { start, items }
+}
+
+/// State of a leading `<input type="checkbox">` in a list item
+fn task_checkbox(li: ElementRef) -> Option<bool> {
+    let selector = Selector::parse(r#"input[type="checkbox"]"#).ok()?;
+    li.select(&selector)
+        .next()
+        .map(|input| input.value().attr("checked").is_some())
+}
+
+fn parse_pre(element: ElementRef) -> Block {
+    let code_element = element
+        .children()
+        .filter_map(ElementRef::wrap)
+        .find(|child| child.value().name() == "code");
+
+    let lang = code_element
+        .into_iter()
+        .chain(std::iter::once(element))
+        .flat_map(|e| e.value().classes())
+        .find_map(|class| {
+            class
+                .strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+        })
+        .unwrap_or("")
+        .to_string();
+
+    let mut code = element.text().collect::<String>();
+    // A newline directly after <pre> is not part of the content
+    if code.starts_with('\n') {
+        code.remove(0);
+    }
+
+    Block::CodeBlock { lang, code }
+}
+
+fn parse_table(element: ElementRef) -> Table {
+    let mut rows: Vec<(bool, Vec<ElementRef>)> = Vec::new();
+    collect_rows(element, &mut rows);
+
+    let mut table = Table::default();
+    let mut rows = rows.into_iter().peekable();
+
+    if let Some((true, cells)) = rows.peek() {
+        table.alignments = cells.iter().map(|cell| cell_alignment(*cell)).collect();
+        table.header = cells
+            .iter()
+            .map(|cell| parse_inline_children(**cell))
+            .collect();
+        rows.next();
+    }
+
+    for (_, cells) in rows {
+        if table.alignments.is_empty() {
+            table.alignments = cells.iter().map(|cell| cell_alignment(*cell)).collect();
+        }
+        table.rows.push(
+            cells
+                .iter()
+                .map(|cell| parse_inline_children(**cell))
+                .collect(),
+        );
+    }
+
+    table
+}
+
+/// Collect `tr` rows below a table, flagging rows made only of `th` cells
+fn collect_rows<'a>(element: ElementRef<'a>, rows: &mut Vec<(bool, Vec<ElementRef<'a>>)>) {
+    for child in element.children().filter_map(ElementRef::wrap) {
+        match child.value().name() {
+            "tr" => {
+                let cells: Vec<_> = child
+                    .children()
+                    .filter_map(ElementRef::wrap)
+                    .filter(|cell| matches!(cell.value().name(), "th" | "td"))
+                    .collect();
+                let is_header =
+                    !cells.is_empty() && cells.iter().all(|cell| cell.value().name() == "th");
+                rows.push((is_header, cells));
+            }
+            "thead" | "tbody" | "tfoot" => collect_rows(child, rows),
+            _ => {}
+        }
+    }
+}
+
+fn cell_alignment(cell: ElementRef) -> Alignment {
+    let align = cell
+        .value()
+        .attr("align")
+        .map(str::to_ascii_lowercase)
+        .or_else(|| {
+            cell.value().attr("style").and_then(|style| {
+                style.split(';').find_map(|decl| {
+                    let (key, value) = decl.split_once(':')?;
+                    (key.trim() == "text-align").then(|| value.trim().to_ascii_lowercase())
+                })
+            })
+        });
+
+    match align.as_deref() {
+        Some("left") => Alignment::Left,
+        Some("center") => Alignment::Center,
+        Some("right") => Alignment::Right,
+        _ => Alignment::None,
+    }
+}
+
+/// Parse the children of a node as trimmed inline content
+fn parse_inline_children(parent: NodeRef<Node>) -> Vec<Inline> {
+    let mut inlines = Vec::new();
+    for child in parent.children() {
+        parse_inline(child, &mut inlines);
+    }
+    normalize_inlines(inlines)
+}
+
+fn parse_inline(node: NodeRef<Node>, out: &mut Vec<Inline>) {
+    match node.value() {
+        Node::Text(text) => {
+            let text = collapse_whitespace(text);
+            if !text.is_empty() {
+                out.push(Inline::Text(text));
+            }
+        }
+        Node::Element(element) => {
+            let name = element.name();
+            if SKIPPED.contains(&name) {
+                return;
+            }
+
+            let children = || {
+                let mut inner = Vec::new();
+                for child in node.children() {
+                    parse_inline(child, &mut inner);
+                }
+                inner
+            };
+
+            match name {
+                "strong" | "b" => out.push(Inline::Strong(children())),
+                "em" | "i" | "cite" => out.push(Inline::Emphasis(children())),
+                "del" | "s" | "strike" => out.push(Inline::Strikethrough(children())),
+                "code" | "kbd" | "samp" | "tt" => {
+                    let text = ElementRef::wrap(node)
+                        .map(|e| e.text().collect::<String>())
+                        .unwrap_or_default();
+                    out.push(Inline::Code(text));
+                }
+                "a" => match element.attr("href") {
+                    Some(href) => out.push(Inline::Link {
+                        url: href.to_string(),
+                        content: children(),
+                    }),
+                    None => out.extend(children()),
+                },
+                "img" => out.push(Inline::Image {
+                    src: element.attr("src").unwrap_or_default().to_string(),
+                    alt: element.attr("alt").unwrap_or_default().to_string(),
+                    width: element.attr("width").and_then(pixel_length),
+                    height: element.attr("height").and_then(pixel_length),
+                }),
+                "br" => out.push(Inline::LineBreak),
+                "input" => {}
+                _ => out.extend(children()),
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Flush pending inline content as a paragraph
+/// `200` or `200px`; percentages and other units are ignored
+fn pixel_length(value: &str) -> Option<u32> {
+    let value = value.trim();
+    let number = value.strip_suffix("px").unwrap_or(value).trim();
+    number.parse::<f32>().ok().map(|px| px.round() as u32)
+}
+
+/// Inline content of an HTML fragment such as a single `<img ...>` tag
+pub(crate) fn parse_inline_fragment(html: &str) -> Vec<Inline> {
+    let fragment = Html::parse_fragment(html);
+    normalize_inlines(parse_inline_children(*fragment.root_element()))
+}
+
+fn flush_paragraph(pending: &mut Vec<Inline>, blocks: &mut Vec<(Option<usize>, Block)>) {
+    let content = normalize_inlines(std::mem::take(pending));
+    if !content.is_empty() {
+        blocks.push((None, Block::Paragraph(content)));
+    }
+}
+
+/// Trim surrounding whitespace and drop empty text runs
+fn normalize_inlines(mut inlines: Vec<Inline>) -> Vec<Inline> {
+    if let Some(Inline::Text(text)) = inlines.first_mut() {
+        *text = text.trim_start().to_string();
+    }
+    if let Some(Inline::Text(text)) = inlines.last_mut() {
+        *text = text.trim_end().to_string();
+    }
+    inlines.retain(|inline| !matches!(inline, Inline::Text(t) if t.is_empty()));
+    inlines
+}
+
+/// Collapse runs of HTML whitespace into single spaces
+fn collapse_whitespace(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut last_was_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !last_was_space {
+                result.push(' ');
+            }
+            last_was_space = true;
+        } else {
+            result.push(c);
+            last_was_space = false;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Inline {
+        Inline::Text(s.to_string())
+    }
+
+    #[test]
+    fn test_document_order_is_preserved() {
+        let doc = parse("<h1>Title</h1><p>First</p><h2>Sub</h2><p>Second</p>");
+        assert_eq!(
+            doc.blocks,
+            vec![
+                Block::Heading {
+                    level: 1,
+                    content: vec![text("Title")]
+                },
+                Block::Paragraph(vec![text("First")]),
+                Block::Heading {
+                    level: 2,
+                    content: vec![text("Sub")]
+                },
+                Block::Paragraph(vec![text("Second")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_title_and_body() {
+        let doc =
+            parse("<html><head><title> My  Page </title></head><body><p>Hi</p></body></html>");
+        assert_eq!(doc.title.as_deref(), Some("My Page"));
+        assert_eq!(doc.blocks.len(), 1);
+    }
+
+    #[test]
+    fn test_inline_styles_and_links() {
+        let doc = parse(r#"<p>A <strong>bold</strong> <a href="https://x.org">link</a></p>"#);
+        assert_eq!(
+            doc.blocks,
+            vec![Block::Paragraph(vec![
+                text("A "),
+                Inline::Strong(vec![text("bold")]),
+                text(" "),
+                Inline::Link {
+                    url: "https://x.org".to_string(),
+                    content: vec![text("link")]
+                },
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_lists_and_tasks() {
+        let doc = parse(
+            r#"<ol start="3"><li>Three</li></ol>
+               <ul><li><input type="checkbox" checked> Done</li><li>Plain</li></ul>"#,
+        );
+        let Block::List(ordered) = &doc.blocks[0] else {
+            panic!("expected list");
+        };
+        assert_eq!(ordered.start, Some(3));
+
+        let Block::List(tasks) = &doc.blocks[1] else {
+            panic!("expected list");
+        };
+        assert_eq!(tasks.start, None);
+        assert_eq!(tasks.items[0].checked, Some(true));
+        assert_eq!(tasks.items[1].checked, None);
+        assert_eq!(
+            tasks.items[0].blocks,
+            vec![Block::Paragraph(vec![text("Done")])]
+        );
+    }
+
+    #[test]
+    fn test_code_block_language() {
+        let doc = parse("<pre><code class=\"language-rust\">\nfn main() {}\n</code></pre>");
+        assert_eq!(
+            doc.blocks,
+            vec![Block::CodeBlock {
+                lang: "rust".to_string(),
+                code: "fn main() {}\n".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_table_with_header_and_alignment() {
+        let doc = parse(
+            r#"<table>
+                 <thead><tr><th>Name</th><th align="right">Age</th></tr></thead>
+                 <tbody><tr><td>Alice</td><td>30</td></tr></tbody>
+               </table>"#,
+        );
+        let Block::Table(table) = &doc.blocks[0] else {
+            panic!("expected table");
+        };
+        assert_eq!(table.alignments, vec![Alignment::None, Alignment::Right]);
+        assert_eq!(table.header, vec![vec![text("Name")], vec![text("Age")]]);
+        assert_eq!(table.rows.len(), 1);
+    }
+
+    #[test]
+    fn test_container_text_becomes_paragraph() {
+        let doc = parse("<div>loose <em>text</em><p>para</p></div>");
+        assert_eq!(
+            doc.blocks,
+            vec![
+                Block::Paragraph(vec![text("loose "), Inline::Emphasis(vec![text("text")])]),
+                Block::Paragraph(vec![text("para")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_source_map_skips_comments_and_raw_text() {
+        let html = "<!-- <h1>old</h1> -->\n<script>let s = \"<h2>\";</script>\n\
+                    <div><h1 class=\"a>b\">Title</h1>\n<pre>&lt;h3&gt;</pre>\n<p>Text</p></div>";
+        let doc = parse(html);
+        assert_eq!(doc.blocks.len(), 3);
+        assert_eq!(doc.source_map.len(), 3);
+        assert!(html[doc.source_map[0].clone()].starts_with("<h1 class"));
+        assert!(html[doc.source_map[1].clone()].starts_with("<pre>"));
+        assert!(html[doc.source_map[2].clone()].starts_with("<p>Text"));
+        assert_eq!(doc.source_map[2].end, html.len());
+    }
+
+    #[test]
+    fn test_scripts_are_skipped() {
+        let doc = parse("<body><script>alert(1)</script><p>ok</p></body>");
+        assert_eq!(doc.blocks, vec![Block::Paragraph(vec![text("ok")])]);
+    }
+}
@@ -0,0 +1,420 @@
+//! HTML backend for [`Document`]
+//!
+//! Writes semantic HTML: the same document model that drives the terminal
+//! and egui previews can be saved or shared as a web page. The standalone
+//! page used by `lala export` additionally highlights code with syntect's
+//! classed output and writes formulas as MathML, with all CSS inlined.
+
+use std::sync::OnceLock;
+
+use syntect::highlighting::ThemeSet;
+use syntect::html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+use super::{Alignment, Block, Diagram, Document, Inline, List, Table};
+use crate::math;
+
+/// Prefix of the highlighting classes, so they cannot clash with others
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+/// Page style of standalone HTML; light, or dark when the reader prefers it
+const PAGE_CSS: &str = r#"
+:root { color-scheme: light dark; }
+body { max-width: 46em; margin: 2em auto; padding: 0 1em; font: 16px/1.6 system-ui, -apple-system, "Segoe UI", sans-serif; color: #24292f; background: #ffffff; }
+h1, h2, h3, h4, h5, h6 { line-height: 1.25; margin: 1.4em 0 0.6em; }
+h1, h2 { border-bottom: 1px solid #d0d7de; padding-bottom: 0.3em; }
+a { color: #0969da; }
+code, pre { font-family: ui-monospace, "SF Mono", Menlo, Consolas, monospace; font-size: 0.9em; }
+code { background: #f0f2f4; padding: 0.1em 0.3em; border-radius: 4px; }
+pre { background: #f6f8fa; padding: 0.8em 1em; border-radius: 6px; overflow-x: auto; line-height: 1.45; }
+pre code { background: none; padding: 0; }
+blockquote { margin: 0; padding: 0 1em; color: #57606a; border-left: 4px solid #d0d7de; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #d0d7de; padding: 0.3em 0.8em; }
+hr { border: none; border-top: 1px solid #d0d7de; }
+img, figure svg { max-width: 100%; height: auto; }
+figure { margin: 1em 0; text-align: center; }
+math[display="block"] { margin: 1em 0; }
+dl.metadata dt { font-weight: bold; }
+.footnote { font-size: 0.9em; }
+@media (prefers-color-scheme: dark) {
+  body { color: #e6edf3; background: #0d1117; }
+  h1, h2, hr, th, td { border-color: #30363d; }
+  a { color: #4493f8; }
+  code { background: #262c36; }
+  pre { background: #161b22; }
+  blockquote { color: #9198a1; border-color: #30363d; }
+}
+"#;
+
+/// How code and math are written
+#[derive(Debug, Clone, Copy, Default)]
+struct Options {
+    /// Highlighted code and MathML, as in [`render_standalone`]
+    standalone: bool,
+}
+
+/// Render the document body as an HTML fragment
+pub fn render(doc: &Document) -> String {
+    render_with(doc, Options::default())
+}
+
+fn render_with(doc: &Document, options: Options) -> String {
+    let mut html = String::new();
+
+    if let Some(title) = &doc.title {
+        html.push_str(&format!("<h1 class=\"title\">{}</h1>\n", escape(title)));
+    }
+    if !doc.metadata.is_empty() {
+        html.push_str("<dl class=\"metadata\">\n");
+        for (key, value) in &doc.metadata {
+            html.push_str(&format!(
+                "<dt>{}</dt><dd>{}</dd>\n",
+                escape(key),
+                escape(value)
+            ));
+        }
+        html.push_str("</dl>\n");
+    }
+
+    render_blocks(&doc.blocks, options, &mut html);
+    html
+}
+
+/// Render a complete HTML page
+pub fn render_page(doc: &Document) -> String {
+    let title = doc.title.as_deref().unwrap_or("Document");
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        render(doc)
+    )
+}
+
+/// Render a self-contained page: inlined CSS, highlighted code and MathML
+///
+/// Diagrams are inline SVG already; images are written as they are in the
+/// document, so callers embed local files as `data:` URIs beforehand.
+pub fn render_standalone(doc: &Document) -> String {
+    // Without front matter the first heading names the page
+    let heading = doc.blocks.iter().find_map(|block| match block {
+        Block::Heading { content, .. } => Some(super::plain_text(content)),
+        _ => None,
+    });
+    let title = doc
+        .title
+        .clone()
+        .or(heading)
+        .unwrap_or_else(|| "Document".to_string());
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{}</title>\n<style>{PAGE_CSS}{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(&title),
+        highlight_css(),
+        render_with(doc, Options { standalone: true })
+    )
+}
+
+pub(crate) fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+/// Colors of the highlighting classes for light and dark pages
+fn highlight_css() -> String {
+    let themes = ThemeSet::load_defaults();
+    let css = |name: &str| {
+        themes
+            .themes
+            .get(name)
+            .and_then(|theme| css_for_theme_with_class_style(theme, CLASS_STYLE).ok())
+            .unwrap_or_default()
+    };
+    format!(
+        "{}\n@media (prefers-color-scheme: dark) {{\n{}}}\n",
+        css("InspiredGitHub"),
+        css("base16-ocean.dark")
+    )
+}
+
+/// Highlighted code as `<span>`s with classes, `None` for unknown languages
+fn highlight(lang: &str, code: &str) -> Option<String> {
+    let syntaxes = syntax_set();
+    let syntax = syntaxes.find_syntax_by_token(lang)?;
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, syntaxes, CLASS_STYLE);
+    for line in LinesWithEndings::from(code) {
+        generator
+            .parse_html_for_line_which_includes_newline(line)
+            .ok()?;
+    }
+    Some(generator.finalize())
+}
+
+fn render_blocks(blocks: &[Block], options: Options, html: &mut String) {
+    for block in blocks {
+        render_block(block, options, html);
+    }
+}
+
+fn render_block(block: &Block, options: Options, html: &mut String) {
+    match block {
+        Block::Heading { level, content } => {
+            html.push_str(&format!(
+                "<h{level}>{}</h{level}>\n",
+                inlines(content, options)
+            ));
+        }
+        Block::Paragraph(content) => {
+            html.push_str(&format!("<p>{}</p>\n", inlines(content, options)));
+        }
+        Block::List(list) => render_list(list, options, html),
+        Block::CodeBlock { lang, code } => {
+            let highlighted = options.standalone.then(|| highlight(lang, code)).flatten();
+            if let Some(highlighted) = highlighted {
+                html.push_str(&format!(
+                    "<pre class=\"hl-code\"><code class=\"language-{}\">{highlighted}</code></pre>\n",
+                    escape(lang)
+                ));
+            } else if lang.is_empty() {
+                html.push_str(&format!("<pre><code>{}</code></pre>\n", escape(code)));
+            } else {
+                html.push_str(&format!(
+                    "<pre><code class=\"language-{}\">{}</code></pre>\n",
+                    escape(lang),
+                    escape(code)
+                ));
+            }
+        }
+        Block::BlockQuote(blocks) => {
+            html.push_str("<blockquote>\n");
+            render_blocks(blocks, options, html);
+            html.push_str("</blockquote>\n");
+        }
+        Block::Table(table) => render_table(table, options, html),
+        Block::Math(tex) if options.standalone => {
+            html.push_str(&math::mathml::render(tex, true));
+            html.push('\n');
+        }
+        Block::Math(tex) => {
+            html.push_str(&format!(
+                "<div class=\"math\" title=\"{}\">{}</div>\n",
+                escape(tex),
+                escape(&math::to_unicode(tex))
+            ));
+        }
+        Block::Diagram(diagram) => render_diagram(diagram, html),
+        Block::Rule => html.push_str("<hr>\n"),
+        Block::Footnote { label, blocks } => {
+            let label = escape(label);
+            html.push_str(&format!(
+                "<div class=\"footnote\" id=\"fn-{label}\">\n<sup>{label}</sup>\n"
+            ));
+            render_blocks(blocks, options, html);
+            html.push_str(&format!(
+                "<a href=\"#fnref-{label}\" class=\"footnote-backref\">↩</a>\n</div>\n"
+            ));
+        }
+    }
+}
+
+fn render_list(list: &List, options: Options, html: &mut String) {
+    let tag = match list.start {
+        Some(1) => "<ol>".to_string(),
+        Some(start) => format!("<ol start=\"{start}\">"),
+        None => "<ul>".to_string(),
+    };
+    html.push_str(&tag);
+    html.push('\n');
+
+    for item in &list.items {
+        html.push_str("<li>");
+        match item.checked {
+            Some(true) => html.push_str("<input type=\"checkbox\" checked disabled> "),
+            Some(false) => html.push_str("<input type=\"checkbox\" disabled> "),
+            None => {}
+        }
+        // Tight items: a single paragraph is written inline
+        match item.blocks.as_slice() {
+            [Block::Paragraph(content)] => html.push_str(&inlines(content, options)),
+            blocks => {
+                html.push('\n');
+                render_blocks(blocks, options, html);
+            }
+        }
+        html.push_str("</li>\n");
+    }
+
+    html.push_str(if list.start.is_some() {
+        "</ol>\n"
+    } else {
+        "</ul>\n"
+    });
+}
+
+fn render_table(table: &Table, options: Options, html: &mut String) {
+    let cell = |tag: &str, column: usize, content: &[Inline]| {
+        let style = match table.alignments.get(column).copied().unwrap_or_default() {
+            Alignment::None => "",
+            Alignment::Left => " style=\"text-align: left\"",
+            Alignment::Center => " style=\"text-align: center\"",
+            Alignment::Right => " style=\"text-align: right\"",
+        };
+        format!("<{tag}{style}>{}</{tag}>", inlines(content, options))
+    };
+
+    html.push_str("<table>\n");
+    if !table.header.is_empty() {
+        html.push_str("<thead>\n<tr>");
+        for (column, content) in table.header.iter().enumerate() {
+            html.push_str(&cell("th", column, content));
+        }
+        html.push_str("</tr>\n</thead>\n");
+    }
+    html.push_str("<tbody>\n");
+    for row in &table.rows {
+        html.push_str("<tr>");
+        for (column, content) in row.iter().enumerate() {
+            html.push_str(&cell("td", column, content));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>\n");
+}
+
+/// Diagrams are embedded as SVG, so the page needs no Mermaid script
+fn render_diagram(diagram: &Diagram, html: &mut String) {
+    html.push_str(&format!(
+        "<figure class=\"mermaid\" title=\"{}\">\n{}</figure>\n",
+        diagram.kind.label(),
+        crate::mermaid::svg::render(diagram)
+    ));
+}
+
+fn inlines(content: &[Inline], options: Options) -> String {
+    let mut html = String::new();
+
+    for inline in content {
+        match inline {
+            Inline::Text(text) => html.push_str(&escape(text)),
+            Inline::Code(code) => html.push_str(&format!("<code>{}</code>", escape(code))),
+            Inline::Emphasis(inner) => {
+                html.push_str(&format!("<em>{}</em>", inlines(inner, options)))
+            }
+            Inline::Strong(inner) => {
+                html.push_str(&format!("<strong>{}</strong>", inlines(inner, options)))
+            }
+            Inline::Strikethrough(inner) => {
+                html.push_str(&format!("<del>{}</del>", inlines(inner, options)))
+            }
+            Inline::Link { url, content } => html.push_str(&format!(
+                "<a href=\"{}\">{}</a>",
+                escape(url),
+                inlines(content, options)
+            )),
+            Inline::Image {
+                src,
+                alt,
+                width,
+                height,
+            } => {
+                html.push_str(&format!(
+                    "<img src=\"{}\" alt=\"{}\"",
+                    escape(src),
+                    escape(alt)
+                ));
+                if let Some(width) = width {
+                    html.push_str(&format!(" width=\"{width}\""));
+                }
+                if let Some(height) = height {
+                    html.push_str(&format!(" height=\"{height}\""));
+                }
+                html.push('>');
+            }
+            Inline::Math(tex) if options.standalone => {
+                html.push_str(&math::mathml::render(tex, false))
+            }
+            Inline::Math(tex) => html.push_str(&format!(
+                "<span class=\"math\" title=\"{}\">{}</span>",
+                escape(tex),
+                escape(&math::to_unicode(tex))
+            )),
+            Inline::FootnoteReference(label) => {
+                let label = escape(label);
+                html.push_str(&format!(
+                    "<sup class=\"footnote-ref\"><a href=\"#fn-{label}\" id=\"fnref-{label}\">{label}</a></sup>"
+                ));
+            }
+            Inline::LineBreak => html.push_str("<br>\n"),
+        }
+    }
+
+    html
+}
+
+/// Escape text for use in HTML content and attribute values
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::{html as html_parser, markdown};
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("<a href=\"x\">&</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_markdown_to_html() {
+        let html = render(&markdown::parse("# Hi\n\n- [x] **done**\n"));
+        assert_eq!(
+            html,
+            "<h1>Hi</h1>\n<ul>\n<li><input type=\"checkbox\" checked disabled> <strong>done</strong></li>\n</ul>\n"
+        );
+    }
+
+    #[test]
+    fn test_footnote_links() {
+        let html = render(&markdown::parse("See[^a].\n\n[^a]: Note.\n"));
+        assert!(html.contains("<a href=\"#fn-a\" id=\"fnref-a\">a</a>"));
+        assert!(html.contains("<div class=\"footnote\" id=\"fn-a\">"));
+        assert!(html.contains("<a href=\"#fnref-a\" class=\"footnote-backref\">"));
+    }
+
+    #[test]
+    fn test_html_round_trip() {
+        // Writing and re-parsing keeps the document model intact
+        let doc = markdown::parse(
+            "## Table\n\n| a | b |\n|:-:|--:|\n| `x` | [l](u) |\n\n> quote\n\n```rs\nlet x = 1;\n```\n\n![a](a.png) <img src=\"b.png\" alt=\"b\" width=\"80\">\n",
+        );
+        assert_eq!(html_parser::parse(&render(&doc)).blocks, doc.blocks);
+    }
+
+    #[test]
+    fn test_standalone_page() {
+        let html = render_standalone(&markdown::parse(
+            "# Title\n\n```rust\nfn main() {}\n```\n\n$x^2$\n",
+        ));
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Title</title>"));
+        assert!(html.contains("<pre class=\"hl-code\"><code class=\"language-rust\">"));
+        assert!(html.contains("<span class=\"hl-"));
+        assert!(html.contains("<msup><mi>x</mi><mn>2</mn></msup>"));
+    }
+}
@@ -0,0 +1,397 @@
+//! Images referenced by documents
+//!
+//! One loader for the GUI preview, the terminal viewer and the exporters,
+//! so an `![alt](src)` shows the same picture everywhere. A `src` is a path
+//! relative to the document, an absolute path or `file://` URL, a `data:`
+//! URI, or a remote URL. Remote images are never fetched: lala works
+//! offline, and previews should not phone home.
+//!
+//! PNG, JPEG and GIF are decoded with `image`, SVG is rasterized with
+//! `resvg` using the fonts egui ships with. Files over [`MAX_BYTES`] are
+//! refused and pictures larger than [`MAX_SIDE`] are scaled down.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use base64::Engine as _;
+use eframe::egui::FontDefinitions;
+use image::RgbaImage;
+use resvg::usvg;
+
+/// Largest image file (or data URI payload) that is read
+pub const MAX_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Longest side of a decoded picture, in pixels
+pub const MAX_SIDE: u32 = 4096;
+
+/// SVGs are rasterized at this many pixels per CSS pixel to stay sharp
+/// on high-DPI screens
+pub const SVG_SCALE: f32 = 2.0;
+
+/// Where an image `src` points
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    File(PathBuf),
+    Data {
+        mime: String,
+        bytes: Vec<u8>,
+    },
+    /// `http(s)://` and other URLs, shown as placeholders
+    Remote(String),
+}
+
+/// A decoded image and the size it is displayed at by default
+#[derive(Debug, Clone)]
+pub struct Picture {
+    pub pixels: RgbaImage,
+    /// Natural width in CSS pixels; differs from the pixel width for SVGs
+    /// and scaled-down pictures
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Picture {
+    /// Display size for the requested `width`/`height` hints
+    pub fn size(&self, width: Option<u32>, height: Option<u32>) -> (f32, f32) {
+        display_size((self.width, self.height), width, height)
+    }
+}
+
+/// Size of an image with `natural` size shown with `width`/`height` hints,
+/// keeping the aspect ratio when only one is given
+pub fn display_size(natural: (f32, f32), width: Option<u32>, height: Option<u32>) -> (f32, f32) {
+    let ratio = natural.1 / natural.0.max(1.0);
+    match (width, height) {
+        (Some(w), Some(h)) => (w as f32, h as f32),
+        (Some(w), None) => (w as f32, w as f32 * ratio),
+        (None, Some(h)) => (h as f32 / ratio.max(f32::EPSILON), h as f32),
+        (None, None) => natural,
+    }
+}
+
+/// Resolve `src` against the directory of the document
+///
+/// Returns `None` for an empty `src` or a malformed data URI.
+pub fn resolve(src: &str, base_dir: Option<&Path>) -> Option<Source> {
+    let src = src.trim();
+    if src.is_empty() {
+        return None;
+    }
+    if let Some(data) = src.strip_prefix("data:") {
+        return parse_data_uri(data);
+    }
+
+    let src = src.strip_prefix("file://").unwrap_or(src);
+    if src.contains("://") || src.starts_with("//") {
+        return Some(Source::Remote(src.to_string()));
+    }
+    let path = Path::new(src);
+    Some(Source::File(if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        base_dir.unwrap_or(Path::new(".")).join(path)
+    }))
+}
+
+/// `image/png;base64,...` or `image/svg+xml,%3Csvg...`
+fn parse_data_uri(data: &str) -> Option<Source> {
+    let (header, payload) = data.split_once(',')?;
+    let (mime, base64) = match header.strip_suffix(";base64") {
+        Some(mime) => (mime, true),
+        None => (header, false),
+    };
+    let bytes = if base64 {
+        let payload: String = payload.split_whitespace().collect();
+        base64::engine::general_purpose::STANDARD
+            .decode(payload)
+            .ok()?
+    } else {
+        percent_decode(payload)
+    };
+    // Parameters such as `;charset=utf-8` do not change the type
+    let mime = mime.split(';').next().unwrap_or_default().trim();
+    Some(Source::Data {
+        mime: mime.to_ascii_lowercase(),
+        bytes,
+    })
+}
+
+fn percent_decode(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+impl Source {
+    /// Bytes and MIME type of the image
+    pub fn read(&self) -> Result<(Vec<u8>, String), String> {
+        match self {
+            Source::File(path) => {
+                let size = std::fs::metadata(path)
+                    .map_err(|err| format!("{}: {err}", path.display()))?
+                    .len();
+                if size > MAX_BYTES {
+                    return Err(too_large(size));
+                }
+                let bytes =
+                    std::fs::read(path).map_err(|err| format!("{}: {err}", path.display()))?;
+                let mime = mime_type(path).unwrap_or("application/octet-stream");
+                Ok((bytes, mime.to_string()))
+            }
+            Source::Data { mime, bytes } => {
+                if bytes.len() as u64 > MAX_BYTES {
+                    return Err(too_large(bytes.len() as u64));
+                }
+                Ok((bytes.clone(), mime.clone()))
+            }
+            Source::Remote(url) => Err(format!("remote images are not loaded: {url}")),
+        }
+    }
+}
+
+fn too_large(size: u64) -> String {
+    format!(
+        "image is too large ({} MB, limit {} MB)",
+        size.div_ceil(1024 * 1024),
+        MAX_BYTES / (1024 * 1024)
+    )
+}
+
+/// MIME type of an image file, from its extension
+pub fn mime_type(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "svg" => Some("image/svg+xml"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// Load and decode the image `src` refers to
+pub fn load(src: &str, base_dir: Option<&Path>) -> Result<Picture, String> {
+    let source = resolve(src, base_dir).ok_or_else(|| format!("invalid image source: {src}"))?;
+    let (bytes, mime) = source.read()?;
+    decode(&bytes, &mime)
+}
+
+/// Decode image bytes; SVG is recognized by MIME type or content
+pub fn decode(bytes: &[u8], mime: &str) -> Result<Picture, String> {
+    if mime == "image/svg+xml" || looks_like_svg(bytes) {
+        decode_svg(bytes)
+    } else {
+        decode_raster(bytes)
+    }
+}
+
+fn looks_like_svg(bytes: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(512)]);
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    head.starts_with("<svg") || (head.starts_with("<?xml") && head.contains("<svg"))
+}
+
+fn decode_raster(bytes: &[u8]) -> Result<Picture, String> {
+    let mut reader = image::ImageReader::new(std::io::Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|err| err.to_string())?;
+    // Refuse decompression bombs before allocating the pixels
+    let mut limits = image::Limits::default();
+    limits.max_image_width = Some(MAX_SIDE * 8);
+    limits.max_image_height = Some(MAX_SIDE * 8);
+    reader.limits(limits);
+    let image = reader.decode().map_err(|err| err.to_string())?;
+
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    let image = if image.width().max(image.height()) > MAX_SIDE {
+        image.thumbnail(MAX_SIDE, MAX_SIDE)
+    } else {
+        image
+    };
+    Ok(Picture {
+        pixels: image.to_rgba8(),
+        width,
+        height,
+    })
+}
+
+fn decode_svg(bytes: &[u8]) -> Result<Picture, String> {
+    let options = usvg::Options {
+        fontdb: font_database(),
+        ..usvg::Options::default()
+    };
+    let tree = usvg::Tree::from_data(bytes, &options).map_err(|err| err.to_string())?;
+    let size = tree.size();
+    let (width, height) = (size.width(), size.height());
+    let scale = SVG_SCALE.min(MAX_SIDE as f32 / width.max(height));
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(
+        (width * scale).ceil().max(1.0) as u32,
+        (height * scale).ceil().max(1.0) as u32,
+    )
+    .ok_or("SVG has no area")?;
+    resvg::render(
+        &tree,
+        resvg::tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    // tiny-skia pixels are premultiplied
+    let (columns, rows) = (pixmap.width(), pixmap.height());
+    let data = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    let pixels = RgbaImage::from_raw(columns, rows, data).ok_or("could not rasterize SVG")?;
+    Ok(Picture {
+        pixels,
+        width,
+        height,
+    })
+}
+
+/// egui's built-in fonts, used for text in SVGs (no system fonts are read)
+fn font_database() -> Arc<usvg::fontdb::Database> {
+    static DATABASE: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+    DATABASE
+        .get_or_init(|| {
+            let definitions = FontDefinitions::default();
+            let mut database = usvg::fontdb::Database::new();
+            let mut family_of = |name: &str| {
+                let data = definitions.font_data.get(name)?;
+                let before = database.len();
+                database.load_font_data(data.font.to_vec());
+                let face = database.faces().nth(before)?;
+                face.families.first().map(|(family, _)| family.clone())
+            };
+            let sans = family_of("Ubuntu-Light");
+            let mono = family_of("Hack");
+            for name in definitions.font_data.keys() {
+                if name != "Ubuntu-Light" && name != "Hack" {
+                    family_of(name);
+                }
+            }
+            if let Some(sans) = sans {
+                database.set_sans_serif_family(sans.clone());
+                database.set_serif_family(sans);
+            }
+            if let Some(mono) = mono {
+                database.set_monospace_family(mono);
+            }
+            Arc::new(database)
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = Vec::new();
+        RgbaImage::from_pixel(width, height, image::Rgba([255, 0, 0, 255]))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    #[test]
+    fn test_resolve_sources() {
+        let base = Path::new("/docs");
+        assert_eq!(
+            resolve("img/a.png", Some(base)),
+            Some(Source::File(PathBuf::from("/docs/img/a.png")))
+        );
+        assert_eq!(
+            resolve("/abs/b.png", Some(base)),
+            Some(Source::File(PathBuf::from("/abs/b.png")))
+        );
+        assert_eq!(
+            resolve("file:///abs/b.png", Some(base)),
+            Some(Source::File(PathBuf::from("/abs/b.png")))
+        );
+        assert_eq!(
+            resolve("https://example.com/c.png", Some(base)),
+            Some(Source::Remote("https://example.com/c.png".to_string()))
+        );
+        assert_eq!(
+            resolve("data:image/svg+xml;charset=utf-8,%3Csvg%3E", None),
+            Some(Source::Data {
+                mime: "image/svg+xml".to_string(),
+                bytes: b"<svg>".to_vec()
+            })
+        );
+        assert_eq!(resolve("data:image/png;base64,%%%", None), None);
+        assert_eq!(resolve("  ", None), None);
+    }
+
+    #[test]
+    fn test_load_files_and_data_uris() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("red.png"), png(3, 2)).unwrap();
+        let picture = load("red.png", Some(dir.path())).unwrap();
+        assert_eq!((picture.width, picture.height), (3.0, 2.0));
+        assert_eq!(picture.pixels.get_pixel(0, 0).0, [255, 0, 0, 255]);
+
+        let uri = format!(
+            "data:image/png;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(png(4, 4))
+        );
+        assert_eq!(load(&uri, None).unwrap().pixels.dimensions(), (4, 4));
+
+        assert!(load("missing.png", Some(dir.path())).is_err());
+        assert!(load("https://example.com/x.png", None)
+            .unwrap_err()
+            .contains("remote"));
+    }
+
+    #[test]
+    fn test_svg_is_rasterized_at_scale() {
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20">
+            <rect width="40" height="20" fill="#0000ff"/>
+            <text x="2" y="15" font-family="sans-serif" font-size="12">Hi</text>
+        </svg>"##;
+        let picture = decode(svg, "application/octet-stream").unwrap();
+        assert_eq!((picture.width, picture.height), (40.0, 20.0));
+        assert_eq!(picture.pixels.dimensions(), (80, 40));
+        assert_eq!(picture.pixels.get_pixel(79, 0).0, [0, 0, 255, 255]);
+        // The text is drawn with the built-in fonts
+        let dark = picture
+            .pixels
+            .pixels()
+            .filter(|pixel| pixel.0[2] < 128)
+            .count();
+        assert!(dark > 0);
+    }
+
+    #[test]
+    fn test_large_images_are_scaled_down() {
+        let picture = decode(&png(MAX_SIDE + 100, 10), "image/png").unwrap();
+        assert_eq!(picture.width, (MAX_SIDE + 100) as f32);
+        assert_eq!(picture.pixels.width(), MAX_SIDE);
+        assert_eq!(
+            picture.size(Some(100), None),
+            (100.0, 100.0 * 10.0 / 4196.0)
+        );
+    }
+}
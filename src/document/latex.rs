@@ -0,0 +1,1931 @@
+//! LaTeX → [`Document`] parser
+//!
+//! Converts the syntax tree from [`crate::latex`]. Conversion runs twice:
+//! the first pass numbers sections, equations, floats, theorems and list
+//! items and collects `\label`s and citations; the second renders with
+//! every `\ref` and `\cite` resolved, forward references included.
+//!
+//! Given the directory of the document, `\input` / `\include` are followed
+//! and the `.bib` files named by `\bibliography` or `\addbibresource` are
+//! read. Preamble information (`\documentclass`, packages, `\title`,
+//! `\author`, `\date`) becomes the document title and metadata.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{merge_text, Alignment, Block, Document, Inline, List, ListItem, Table};
+use crate::latex::counters::Counters;
+use crate::latex::{self, bib, Body, Command, Environment, Node, NodeKind};
+
+/// Environments rendered as block quotes
+const QUOTE_ENVIRONMENTS: &[&str] = &["quote", "quotation", "abstract", "verse"];
+
+/// Theorem-like environments available without `\newtheorem`
+const THEOREMS: &[(&str, &str)] = &[
+    ("theorem", "Theorem"),
+    ("lemma", "Lemma"),
+    ("proposition", "Proposition"),
+    ("corollary", "Corollary"),
+    ("conjecture", "Conjecture"),
+    ("definition", "Definition"),
+    ("example", "Example"),
+    ("remark", "Remark"),
+    ("exercise", "Exercise"),
+    ("claim", "Claim"),
+];
+
+/// Rules between table rows
+const TABLE_RULES: &[&str] = &[
+    "hline",
+    "toprule",
+    "midrule",
+    "bottomrule",
+    "cline",
+    "cmidrule",
+    "specialrule",
+];
+
+/// Parse a LaTeX document without reading other files
+pub fn parse(source: &str) -> Document {
+    parse_with_base(source, None)
+}
+
+/// Parse a LaTeX document, resolving `\input` and `.bib` files in `base_dir`
+pub fn parse_with_base(source: &str, base_dir: Option<&Path>) -> Document {
+    let nodes = latex::parse(source);
+    let chapters = uses_chapters(&nodes);
+
+    let mut first = Converter::new(base_dir, chapters, References::default());
+    first.document(&nodes, source.len());
+    let references = first.references();
+
+    Converter::new(base_dir, chapters, references).document(&nodes, source.len())
+}
+
+/// Book-like classes number sections within chapters
+fn uses_chapters(nodes: &[Node]) -> bool {
+    nodes.iter().any(|node| {
+        node.command("documentclass")
+            .and_then(|command| command.text_arg(1))
+            .is_some_and(|class| {
+                matches!(
+                    class.as_str(),
+                    "book" | "report" | "memoir" | "scrbook" | "scrreprt"
+                )
+            })
+            || node.command("chapter").is_some()
+    })
+}
+
+/// What a `\label` points at
+#[derive(Debug, Clone, Default)]
+struct Label {
+    number: String,
+    /// Name used by `\autoref` / `\cref`, e.g. `Section`
+    name: String,
+    /// Heading text, for `\nameref`
+    title: String,
+}
+
+/// A reference list entry
+#[derive(Debug, Clone)]
+struct Reference {
+    key: String,
+    /// Printed label, e.g. `1`
+    label: String,
+    /// Author last names, for author–year citations
+    authors: String,
+    year: String,
+    content: Vec<Inline>,
+}
+
+/// Results of the first pass, used by the second
+#[derive(Debug, Default)]
+struct References {
+    labels: HashMap<String, Label>,
+    bibliography: Vec<Reference>,
+    /// Numbered headings for `\tableofcontents`: level, number and title
+    sections: Vec<(u8, String, Vec<Inline>)>,
+}
+
+#[derive(Debug, Clone)]
+struct Theorem {
+    title: String,
+    /// Counter stepped by the environment; `None` for `\newtheorem*`
+    counter: Option<String>,
+}
+
+struct Converter<'a> {
+    base_dir: Option<&'a Path>,
+    /// Labels and citations found by the previous pass
+    resolved: References,
+    counters: Counters,
+    /// Target of a `\label` at this point (`\@currentlabel`)
+    current: Label,
+    labels: HashMap<String, Label>,
+    sections: Vec<(u8, String, Vec<Inline>)>,
+    /// Cited keys in order of first citation; `*` for `\nocite{*}`
+    cited: Vec<String>,
+    bib_files: Vec<PathBuf>,
+    /// Entries of a `thebibliography` environment
+    bibitems: Vec<Reference>,
+    theorems: HashMap<String, Theorem>,
+    footnotes: Vec<Block>,
+    /// Files being included, to stop `\input` cycles
+    includes: Vec<PathBuf>,
+    /// Enclosing `figure` or `table`, numbering its `\caption`
+    float: Option<&'static str>,
+    enumerate_depth: usize,
+    /// Combining mark of an accent command waiting for its letter
+    accent: Option<char>,
+}
+
+impl<'a> Converter<'a> {
+    fn new(base_dir: Option<&'a Path>, chapters: bool, resolved: References) -> Self {
+        let theorems = THEOREMS
+            .iter()
+            .map(|(name, title)| {
+                let theorem = Theorem {
+                    title: title.to_string(),
+                    counter: Some(name.to_string()),
+                };
+                (name.to_string(), theorem)
+            })
+            .collect();
+        let mut counters = Counters::new(chapters);
+        for (name, _) in THEOREMS {
+            counters.define(name, None);
+        }
+        Converter {
+            base_dir,
+            resolved,
+            counters,
+            current: Label::default(),
+            labels: HashMap::new(),
+            sections: Vec::new(),
+            cited: Vec::new(),
+            bib_files: Vec::new(),
+            bibitems: Vec::new(),
+            theorems,
+            footnotes: Vec::new(),
+            includes: Vec::new(),
+            float: None,
+            enumerate_depth: 0,
+            accent: None,
+        }
+    }
+
+    fn document(&mut self, nodes: &[Node], len: usize) -> Document {
+        let document = nodes.iter().find_map(|node| match &node.kind {
+            NodeKind::Environment(env) if env.name == "document" => Some(env),
+            _ => None,
+        });
+        let body: &[Node] = match document.map(|env| &env.body) {
+            Some(Body::Nodes(body)) => body,
+            _ => nodes,
+        };
+
+        for node in nodes {
+            if let NodeKind::Command(command) = &node.kind {
+                self.declare(command);
+            }
+        }
+        let title = nodes
+            .iter()
+            .find_map(|node| node.command("title"))
+            .and_then(|command| command.arg(1))
+            .map(|title| super::plain_text(&self.inlines(title)).trim().to_string());
+        let metadata = self.metadata(nodes);
+
+        let mut positioned = self.blocks(body);
+        let footnotes = std::mem::take(&mut self.footnotes);
+        positioned.extend(footnotes.into_iter().map(|block| (len, block)));
+
+        let starts: Vec<usize> = positioned.iter().map(|(start, _)| *start).collect();
+        let source_map = starts
+            .iter()
+            .enumerate()
+            .map(|(index, &start)| start..starts.get(index + 1).copied().unwrap_or(len))
+            .collect();
+
+        Document {
+            title,
+            metadata,
+            blocks: positioned.into_iter().map(|(_, block)| block).collect(),
+            source_map,
+        }
+    }
+
+    /// Labels, citations and headings found by this pass
+    fn references(self) -> References {
+        let bibliography = if self.bibitems.is_empty() {
+            self.bib_references()
+        } else {
+            self.bibitems
+        };
+        References {
+            labels: self.labels,
+            bibliography,
+            sections: self.sections,
+        }
+    }
+
+    /// Cited `.bib` entries, numbered in order of citation
+    fn bib_references(&self) -> Vec<Reference> {
+        let entries: Vec<bib::Entry> = self
+            .bib_files
+            .iter()
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .flat_map(|source| bib::parse(&source))
+            .collect();
+
+        let mut keys: Vec<&str> = Vec::new();
+        for key in &self.cited {
+            if key == "*" {
+                keys.extend(entries.iter().map(|entry| entry.key.as_str()));
+            } else {
+                keys.push(key);
+            }
+        }
+
+        let mut references: Vec<Reference> = Vec::new();
+        for key in keys {
+            if references.iter().any(|reference| reference.key == key) {
+                continue;
+            }
+            let Some(entry) = entries.iter().find(|entry| entry.key == key) else {
+                continue;
+            };
+            let label = (references.len() + 1).to_string();
+            references.push(self.bib_reference(entry, label));
+        }
+        references
+    }
+
+    fn bib_reference(&self, entry: &bib::Entry, label: String) -> Reference {
+        let field = |name: &str| {
+            entry
+                .field(name)
+                .map(parse_inlines)
+                .filter(|value| !value.is_empty())
+        };
+
+        let mut content = Vec::new();
+        let authors = join_names(&entry.authors());
+        if !authors.is_empty() {
+            content.extend(parse_inlines(&authors));
+            content.push(Inline::Text(". ".to_string()));
+        }
+        if let Some(title) = field("title") {
+            content.extend(title);
+            content.push(Inline::Text(". ".to_string()));
+        }
+        let venue = ["journal", "booktitle", "publisher", "school", "institution"]
+            .into_iter()
+            .find_map(field)
+            .or_else(|| field("howpublished"));
+        let year = entry.field("year").unwrap_or_default().to_string();
+        match (venue, year.is_empty()) {
+            (Some(venue), false) => {
+                content.push(Inline::Emphasis(venue));
+                content.push(Inline::Text(format!(", {year}.")));
+            }
+            (Some(venue), true) => {
+                content.push(Inline::Emphasis(venue));
+                content.push(Inline::Text(".".to_string()));
+            }
+            (None, false) => content.push(Inline::Text(format!("{year}."))),
+            (None, true) => {}
+        }
+
+        let last_names = entry.last_names();
+        let authors = match last_names.as_slice() {
+            [] => String::new(),
+            [one] => one.clone(),
+            [first, second] => format!("{first} and {second}"),
+            [first, ..] => format!("{first} et al."),
+        };
+        Reference {
+            key: entry.key.clone(),
+            label,
+            authors: super::plain_text(&parse_inlines(&authors)),
+            year,
+            content: merge_text(content),
+        }
+    }
+
+    /// Preamble declarations that affect the whole document
+    fn declare(&mut self, command: &Command) {
+        match command.name.as_str() {
+            "newtheorem" => {
+                let (Some(name), Some(title)) = (command.text_arg(1), command.arg(3)) else {
+                    return;
+                };
+                let title = super::plain_text(&self.inlines(title));
+                let counter = if command.starred() {
+                    None
+                } else if let Some(shared) = command.text_arg(2) {
+                    Some(shared)
+                } else {
+                    let within = command.text_arg(4);
+                    self.counters.define(&name, within.as_deref());
+                    Some(name.clone())
+                };
+                self.theorems.insert(name, Theorem { title, counter });
+            }
+            "addbibresource" | "bibliography" => {
+                let Some(base_dir) = self.base_dir else {
+                    return;
+                };
+                let index = command.args.len() - 1;
+                for name in command.text_arg(index).unwrap_or_default().split(',') {
+                    let path = latex::bib_path(base_dir, name);
+                    if !self.bib_files.contains(&path) {
+                        self.bib_files.push(path);
+                    }
+                }
+            }
+            "setcounter" => {
+                let (Some(name), Some(value)) = (command.text_arg(0), command.text_arg(1)) else {
+                    return;
+                };
+                if let Ok(value) = value.parse() {
+                    self.counters.set(&name, value);
+                }
+            }
+            "appendix" => {
+                // Chapters (or sections) restart as A, B, ...
+                let top = if self.counters.value("chapter") > 0 {
+                    "chapter"
+                } else {
+                    "section"
+                };
+                self.counters.set(top, 0);
+                self.counters
+                    .set_style(top, latex::counters::Style::UpperAlpha);
+            }
+            _ => {}
+        }
+    }
+
+    fn metadata(&mut self, nodes: &[Node]) -> Vec<(String, String)> {
+        let mut metadata = Vec::new();
+
+        if let Some(class) = nodes.iter().find_map(|node| node.command("documentclass")) {
+            metadata.push((
+                "Document Type".to_string(),
+                class.text_arg(1).unwrap_or_default(),
+            ));
+            if let Some(options) = class.text_arg(0).filter(|options| !options.is_empty()) {
+                metadata.push(("Options".to_string(), options));
+            }
+        }
+
+        let packages: Vec<String> = nodes
+            .iter()
+            .filter_map(|node| node.command("usepackage"))
+            .filter_map(|command| command.text_arg(1))
+            .flat_map(|names| {
+                names
+                    .split(',')
+                    .map(|name| name.trim().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|name| !name.is_empty())
+            .collect();
+        if !packages.is_empty() {
+            metadata.push(("Packages".to_string(), packages.join(", ")));
+        }
+
+        for (command, index, key) in [("author", 1, "Author"), ("date", 0, "Date")] {
+            let value = nodes
+                .iter()
+                .find_map(|node| node.command(command))
+                .and_then(|command| command.arg(index))
+                .map(|value| super::plain_text(&self.inlines(value)).trim().to_string());
+            if let Some(value) = value.filter(|value| !value.is_empty()) {
+                metadata.push((key.to_string(), value));
+            }
+        }
+
+        metadata
+    }
+
+    /// Blocks of a node sequence with the source offset each starts at
+    fn blocks(&mut self, nodes: &[Node]) -> Vec<(usize, Block)> {
+        let mut blocks = Vec::new();
+        let mut paragraph = Vec::new();
+        let mut start = None;
+
+        let flush = |paragraph: &mut Vec<Inline>,
+                     start: &mut Option<usize>,
+                     blocks: &mut Vec<(usize, Block)>| {
+            let content = trim_inlines(std::mem::take(paragraph));
+            if let (false, Some(start)) = (content.is_empty(), start.take()) {
+                blocks.push((start, Block::Paragraph(content)));
+            }
+            *start = None;
+        };
+
+        for node in nodes {
+            if matches!(node.kind, NodeKind::Par) || node.command("par").is_some() {
+                flush(&mut paragraph, &mut start, &mut blocks);
+                continue;
+            }
+            if is_block(node) {
+                flush(&mut paragraph, &mut start, &mut blocks);
+                let produced = self.block(node);
+                blocks.extend(produced);
+                continue;
+            }
+            if start.is_none() && !node.is_space() {
+                start = Some(node.span.start);
+            }
+            self.inline(node, &mut paragraph);
+        }
+        flush(&mut paragraph, &mut start, &mut blocks);
+        blocks
+    }
+
+    /// Blocks of a node sequence inside a container
+    fn block_list(&mut self, nodes: &[Node]) -> Vec<Block> {
+        self.blocks(nodes)
+            .into_iter()
+            .map(|(_, block)| block)
+            .collect()
+    }
+
+    fn block(&mut self, node: &Node) -> Vec<(usize, Block)> {
+        let start = node.span.start;
+        let at = |blocks: Vec<Block>| blocks.into_iter().map(|block| (start, block)).collect();
+
+        match &node.kind {
+            NodeKind::Math { tex, .. } => {
+                let tex = self.equation_row(tex, false);
+                at(vec![Block::Math(tex.trim().to_string())])
+            }
+            NodeKind::Command(command) => match command.name.as_str() {
+                "part" | "chapter" | "section" | "subsection" | "subsubsection" | "paragraph"
+                | "subparagraph" => at(vec![self.heading(command)]),
+                "input" | "include" | "subfile" => at(self.include(command)),
+                "bibliography" | "printbibliography" => {
+                    self.declare(command);
+                    at(self.bibliography())
+                }
+                "tableofcontents" => at(self.table_of_contents()),
+                "caption" => at(self.caption(command)),
+                _ => Vec::new(),
+            },
+            NodeKind::Environment(env) => {
+                let saved = self.current.clone();
+                let blocks = self.environment(env, start);
+                if env.name != "document" {
+                    self.current = saved;
+                }
+                blocks
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn environment(&mut self, env: &Environment, start: usize) -> Vec<(usize, Block)> {
+        let at = |blocks: Vec<Block>| blocks.into_iter().map(|block| (start, block)).collect();
+        let name = env.name.as_str();
+        let nodes: &[Node] = match &env.body {
+            Body::Nodes(nodes) => nodes,
+            Body::Raw(raw) => {
+                return at(self.raw_environment(env, raw));
+            }
+        };
+
+        match name {
+            "itemize" | "enumerate" | "description" => at(vec![Block::List(self.list(env))]),
+            "tabular" | "tabular*" | "tabularx" | "longtable" | "array" => {
+                at(vec![Block::Table(self.table(env))])
+            }
+            "thebibliography" => {
+                self.bibitems(nodes);
+                at(self.reference_list())
+            }
+            "proof" => at(self.proof(nodes)),
+            "figure" | "figure*" | "table" | "table*" | "wrapfigure" => {
+                let kind = if name.starts_with("table") {
+                    "table"
+                } else {
+                    "figure"
+                };
+                let outer = self.float.replace(kind);
+                let blocks = self.blocks(nodes);
+                self.float = outer;
+                blocks
+            }
+            _ if QUOTE_ENVIRONMENTS.contains(&name) => {
+                let inner = self.block_list(nodes);
+                let mut blocks = Vec::new();
+                if name == "abstract" {
+                    blocks.push(Block::Heading {
+                        level: 3,
+                        content: vec![Inline::Text("Abstract".to_string())],
+                    });
+                }
+                blocks.push(Block::BlockQuote(inner));
+                at(blocks)
+            }
+            _ if self.theorems.contains_key(name) => at(self.theorem(name, nodes)),
+            // Any other environment is transparent: its body is read as usual
+            _ => self.blocks(nodes),
+        }
+    }
+
+    /// Math and verbatim environments
+    fn raw_environment(&mut self, env: &Environment, raw: &str) -> Vec<Block> {
+        let name = env.name.as_str();
+        if latex::parser::is_verbatim(name) {
+            if name == "comment" {
+                return Vec::new();
+            }
+            let lang = match name {
+                "minted" => env.text_arg(1).unwrap_or_default(),
+                _ => env
+                    .text_arg(0)
+                    .and_then(|options| language_option(&options))
+                    .unwrap_or_default(),
+            };
+            return vec![Block::CodeBlock {
+                lang: lang.to_lowercase(),
+                code: verbatim_body(raw),
+            }];
+        }
+
+        let base = name.trim_end_matches('*');
+        let numbered = !name.ends_with('*') && !matches!(base, "displaymath" | "math");
+        let tex = match base {
+            "align" | "alignat" | "flalign" | "eqnarray" | "gather" => {
+                let rows: Vec<String> = split_rows(raw)
+                    .iter()
+                    .filter(|row| !row.trim().is_empty())
+                    .map(|row| self.equation_row(row.trim(), numbered))
+                    .collect();
+                let wrapper = if base == "gather" {
+                    "gathered"
+                } else {
+                    "aligned"
+                };
+                if rows.len() == 1 {
+                    rows[0].clone()
+                } else {
+                    format!(
+                        "\\begin{{{wrapper}}}\n{}\n\\end{{{wrapper}}}",
+                        rows.join(" \\\\\n")
+                    )
+                }
+            }
+            _ => self.equation_row(raw.trim(), numbered),
+        };
+        vec![Block::Math(tex)]
+    }
+
+    /// One numbered line of display math: `\label`s are recorded and the
+    /// number is added as `\tag`
+    fn equation_row(&mut self, tex: &str, numbered: bool) -> String {
+        let (mut tex, keys) = take_labels(tex);
+        let tagged = tex.contains(r"\tag");
+        if let Some(tag) = tagged.then(|| command_argument(&tex, "tag")).flatten() {
+            self.current = Label {
+                number: tag,
+                name: "Equation".to_string(),
+                title: String::new(),
+            };
+        } else if numbered && !tex.contains(r"\nonumber") && !tex.contains(r"\notag") {
+            let number = self.counters.step("equation");
+            tex = format!("{} \\tag{{{number}}}", tex.trim_end());
+            self.current = Label {
+                number,
+                name: "Equation".to_string(),
+                title: String::new(),
+            };
+        }
+        for key in keys {
+            self.labels.insert(key, self.current.clone());
+        }
+        tex
+    }
+
+    fn heading(&mut self, command: &Command) -> Block {
+        // Depth as in LaTeX's `secnumdepth`, and the document heading level
+        let (depth, level) = match command.name.as_str() {
+            "part" => (-1, 1),
+            "chapter" => (0, 1),
+            "section" => (1, 2),
+            "subsection" => (2, 3),
+            "subsubsection" => (3, 4),
+            "paragraph" => (4, 5),
+            _ => (5, 6),
+        };
+        let title = command
+            .arg(2)
+            .map(|title| self.inlines(title))
+            .unwrap_or_default();
+
+        let numbered = !command.starred() && depth <= self.counters.value("secnumdepth") as i32;
+        if !numbered {
+            return Block::Heading {
+                level,
+                content: title,
+            };
+        }
+
+        let number = self.counters.step(&command.name);
+        let name = match command.name.as_str() {
+            "part" => "Part",
+            "chapter" => "Chapter",
+            _ => "Section",
+        };
+        self.current = Label {
+            number: number.clone(),
+            name: name.to_string(),
+            title: super::plain_text(&title).trim().to_string(),
+        };
+        self.sections.push((level, number.clone(), title.clone()));
+
+        let prefix = if command.name == "part" {
+            format!("Part {number} ")
+        } else {
+            format!("{number} ")
+        };
+        let mut content = vec![Inline::Text(prefix)];
+        content.extend(title);
+        Block::Heading {
+            level,
+            content: merge_text(content),
+        }
+    }
+
+    fn list(&mut self, env: &Environment) -> List {
+        let Body::Nodes(nodes) = &env.body else {
+            return List {
+                start: None,
+                items: Vec::new(),
+            };
+        };
+        let enumerate = env.name == "enumerate";
+        let counter = if enumerate {
+            self.enumerate_depth += 1;
+            let counter = ["enumi", "enumii", "enumiii", "enumiv"][self.enumerate_depth.min(4) - 1];
+            self.counters.set(counter, 0);
+            Some(counter)
+        } else {
+            None
+        };
+
+        let mut items = Vec::new();
+        for (item, content) in split_at_command(nodes, "item") {
+            let Some(item) = item else {
+                // Anything before the first `\item` (labels, spacing) is dropped
+                continue;
+            };
+            let label = item.arg(0);
+            if let (Some(counter), None) = (counter, label) {
+                let number = self.counters.step(counter);
+                self.current = Label {
+                    number,
+                    name: "Item".to_string(),
+                    title: String::new(),
+                };
+            }
+
+            let mut blocks = self.block_list(content);
+            if let Some(label) = label {
+                let mut label = self.inlines(label);
+                if env.name == "description" {
+                    label = vec![Inline::Strong(label)];
+                }
+                label.push(Inline::Text(" ".to_string()));
+                prepend(&mut blocks, label);
+            }
+            items.push(ListItem {
+                checked: None,
+                task_offset: None,
+                blocks,
+            });
+        }
+
+        if enumerate {
+            self.enumerate_depth -= 1;
+        }
+        List {
+            start: enumerate.then_some(1),
+            items,
+        }
+    }
+
+    fn table(&mut self, env: &Environment) -> Table {
+        let Body::Nodes(nodes) = &env.body else {
+            return Table::default();
+        };
+        let spec_index = if env.name == "tabular" || env.name == "longtable" || env.name == "array"
+        {
+            1
+        } else {
+            2
+        };
+        let alignments = column_alignments(&env.text_arg(spec_index).unwrap_or_default());
+
+        // Rows end at `\\`; a rule before a row is remembered to find the header
+        let mut rows: Vec<(bool, Vec<Vec<Inline>>)> = Vec::new();
+        for (_, row) in split_at_command(nodes, "\\") {
+            let rule_at = row
+                .iter()
+                .position(|node| {
+                    !node.is_space() && !TABLE_RULES.iter().any(|rule| node.command(rule).is_some())
+                })
+                .unwrap_or(row.len());
+            let ruled = row[..rule_at].iter().any(|node| !node.is_space());
+            let cells = self.table_cells(&row[rule_at..]);
+            rows.push((ruled, cells));
+        }
+        rows.retain(|(_, cells)| cells.iter().any(|cell| !cell.is_empty()));
+
+        let header = if rows.len() > 1 && rows[1].0 {
+            rows.remove(0).1
+        } else {
+            Vec::new()
+        };
+        let mut table = Table {
+            alignments,
+            header,
+            rows: rows.into_iter().map(|(_, cells)| cells).collect(),
+        };
+        table
+            .alignments
+            .resize(table.column_count(), Alignment::None);
+        table
+    }
+
+    fn table_cells(&mut self, nodes: &[Node]) -> Vec<Vec<Inline>> {
+        let mut cells = Vec::new();
+        for cell in nodes.split(|node| matches!(node.kind, NodeKind::Tab)) {
+            let span = cell
+                .iter()
+                .find_map(|node| node.command("multicolumn"))
+                .and_then(|command| command.text_arg(0))
+                .and_then(|count| count.parse::<usize>().ok())
+                .unwrap_or(1);
+            cells.push(trim_inlines(self.inlines(cell)));
+            cells.extend(std::iter::repeat_n(Vec::new(), span.saturating_sub(1)));
+        }
+        if cells.len() == 1 && cells[0].is_empty() {
+            cells.clear();
+        }
+        cells
+    }
+
+    fn theorem(&mut self, name: &str, nodes: &[Node]) -> Vec<Block> {
+        let theorem = self.theorems[name].clone();
+        let (note, body) = leading_optional(nodes);
+
+        let mut heading = theorem.title.clone();
+        if let Some(counter) = &theorem.counter {
+            if !self.counters.contains(counter) {
+                self.counters.define(counter, None);
+            }
+            let number = self.counters.step(counter);
+            heading = format!("{heading} {number}");
+            self.current = Label {
+                number,
+                name: theorem.title.clone(),
+                title: String::new(),
+            };
+        }
+
+        let mut prefix = vec![Inline::Strong(vec![Inline::Text(heading)])];
+        if let Some(note) = note {
+            prefix.push(Inline::Text(" (".to_string()));
+            prefix.extend(self.inlines(note));
+            prefix.push(Inline::Text(")".to_string()));
+        }
+        prefix.push(Inline::Text(". ".to_string()));
+
+        let mut blocks = self.block_list(body);
+        prepend(&mut blocks, prefix);
+        blocks
+    }
+
+    fn proof(&mut self, nodes: &[Node]) -> Vec<Block> {
+        let (note, body) = leading_optional(nodes);
+        let title = match note {
+            Some(note) => self.inlines(note),
+            None => vec![Inline::Text("Proof".to_string())],
+        };
+        let mut blocks = self.block_list(body);
+        prepend(
+            &mut blocks,
+            vec![Inline::Emphasis(title), Inline::Text(". ".to_string())],
+        );
+        match blocks.last_mut() {
+            Some(Block::Paragraph(content)) => content.push(Inline::Text(" ∎".to_string())),
+            _ => blocks.push(Block::Paragraph(vec![Inline::Text("∎".to_string())])),
+        }
+        blocks
+    }
+
+    fn caption(&mut self, command: &Command) -> Vec<Block> {
+        let kind = self.float.unwrap_or("figure");
+        let number = self.counters.step(kind);
+        let name = if kind == "table" { "Table" } else { "Figure" };
+        self.current = Label {
+            number: number.clone(),
+            name: name.to_string(),
+            title: String::new(),
+        };
+
+        let mut content = vec![
+            Inline::Strong(vec![Inline::Text(format!("{name} {number}:"))]),
+            Inline::Text(" ".to_string()),
+        ];
+        content.extend(self.inlines(command.arg(1).unwrap_or_default()));
+        vec![Block::Paragraph(trim_inlines(content))]
+    }
+
+    fn include(&mut self, command: &Command) -> Vec<Block> {
+        let name = command.text_arg(0).unwrap_or_default();
+        let note = |text: String| {
+            vec![Block::Paragraph(vec![Inline::Emphasis(vec![
+                Inline::Text(text),
+            ])])]
+        };
+        let call = format!("\\{}{{{name}}}", command.name);
+
+        let Some(base_dir) = self.base_dir else {
+            return note(call);
+        };
+        let path = latex::input_path(base_dir, &name);
+        if self.includes.contains(&path) || self.includes.len() >= 16 {
+            return note(format!("{call}: recursive include"));
+        }
+        let Ok(source) = std::fs::read_to_string(&path) else {
+            return note(format!("{call}: file not found"));
+        };
+
+        let nodes = latex::parse(&source);
+        // A subfile has its own preamble; only its body is included
+        let body = nodes.iter().find_map(|node| match &node.kind {
+            NodeKind::Environment(env) if env.name == "document" => match &env.body {
+                Body::Nodes(body) => Some(body.as_slice()),
+                Body::Raw(_) => None,
+            },
+            _ => None,
+        });
+        self.includes.push(path);
+        let blocks = self.block_list(body.unwrap_or(&nodes));
+        self.includes.pop();
+        blocks
+    }
+
+    fn bibitems(&mut self, nodes: &[Node]) {
+        for (item, content) in split_at_command(nodes, "bibitem") {
+            let Some(item) = item else { continue };
+            let key = item.text_arg(1).unwrap_or_default();
+            let label = item
+                .text_arg(0)
+                .unwrap_or_else(|| (self.bibitems.len() + 1).to_string());
+            let content = trim_inlines(self.inlines(content));
+            let authors = item.text_arg(0).unwrap_or_else(|| key.clone());
+            self.bibitems.push(Reference {
+                key,
+                label,
+                authors,
+                year: String::new(),
+                content,
+            });
+        }
+    }
+
+    fn bibliography(&mut self) -> Vec<Block> {
+        if self.resolved.bibliography.is_empty() {
+            return Vec::new();
+        }
+        let mut blocks = vec![Block::Heading {
+            level: 2,
+            content: vec![Inline::Text("References".to_string())],
+        }];
+        blocks.extend(self.reference_list());
+        blocks
+    }
+
+    fn reference_list(&self) -> Vec<Block> {
+        self.resolved
+            .bibliography
+            .iter()
+            .map(|reference| {
+                let mut content = vec![Inline::Text(format!("[{}] ", reference.label))];
+                content.extend(reference.content.iter().cloned());
+                Block::Paragraph(merge_text(content))
+            })
+            .collect()
+    }
+
+    fn table_of_contents(&self) -> Vec<Block> {
+        let sections = &self.resolved.sections;
+        if sections.is_empty() {
+            return Vec::new();
+        }
+        vec![
+            Block::Heading {
+                level: 2,
+                content: vec![Inline::Text("Contents".to_string())],
+            },
+            Block::List(contents_list(sections)),
+        ]
+    }
+
+    /// Inline content of nodes
+    fn inlines(&mut self, nodes: &[Node]) -> Vec<Inline> {
+        let mut out = Vec::new();
+        for node in nodes {
+            self.inline(node, &mut out);
+        }
+        merge_text(out)
+    }
+
+    fn inline(&mut self, node: &Node, out: &mut Vec<Inline>) {
+        match &node.kind {
+            NodeKind::Text(text) => self.text(&ligatures(text), out),
+            NodeKind::Space | NodeKind::Tie | NodeKind::Par | NodeKind::Tab => {
+                out.push(Inline::Text(" ".to_string()))
+            }
+            NodeKind::Raw(text) => out.push(Inline::Code(text.clone())),
+            NodeKind::Math { tex, .. } => {
+                let (tex, _) = take_labels(tex);
+                out.push(Inline::Math(tex.trim().to_string()));
+            }
+            NodeKind::Group(nodes) => self.group(nodes, out),
+            NodeKind::Command(command) => self.command(command, out),
+            NodeKind::Environment(env) => {
+                // Block content in an inline position (a table cell, a caption)
+                let blocks = self.block(node);
+                for (_, block) in blocks {
+                    if let Block::Paragraph(content) = block {
+                        out.extend(content);
+                    } else if let (Block::Math(tex), true) = (&block, env.name == "math") {
+                        out.push(Inline::Math(tex.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Text, with a pending accent put on its first letter
+    fn text(&mut self, text: &str, out: &mut Vec<Inline>) {
+        let mut text = text.to_string();
+        if let Some(mark) = self.accent.take() {
+            let mut chars = text.chars();
+            if let Some(first) = chars.next() {
+                text = format!("{first}{mark}{}", chars.as_str());
+            }
+        }
+        out.push(Inline::Text(text));
+    }
+
+    /// `{...}`, where a leading declaration such as `\bf` styles the rest
+    fn group(&mut self, nodes: &[Node], out: &mut Vec<Inline>) {
+        let first = nodes.iter().position(|node| !node.is_space());
+        let declaration = first.and_then(|index| match &nodes[index].kind {
+            NodeKind::Command(command) => Some((index, command.name.as_str())),
+            _ => None,
+        });
+        let rest = |index: usize| &nodes[index + 1..];
+        match declaration {
+            Some((index, "bf" | "bfseries")) => {
+                out.push(Inline::Strong(trim_inlines(self.inlines(rest(index)))))
+            }
+            Some((index, "it" | "itshape" | "em" | "sl" | "slshape")) => {
+                out.push(Inline::Emphasis(trim_inlines(self.inlines(rest(index)))))
+            }
+            Some((index, "tt" | "ttfamily")) => {
+                let code = super::plain_text(&self.inlines(rest(index)));
+                out.push(Inline::Code(code.trim().to_string()));
+            }
+            _ => {
+                for node in nodes {
+                    self.inline(node, out);
+                }
+            }
+        }
+    }
+
+    fn command(&mut self, command: &Command, out: &mut Vec<Inline>) {
+        let arg = |index: usize| command.arg(index).unwrap_or_default();
+        let name = command.name.as_str();
+
+        if let Some(mark) = accent(name) {
+            self.accent = Some(mark);
+            return;
+        }
+        if let Some(text) = symbol(name) {
+            out.push(Inline::Text(text.to_string()));
+            return;
+        }
+
+        match name {
+            "textbf" => {
+                let content = self.inlines(arg(0));
+                out.push(Inline::Strong(content));
+            }
+            "textit" | "emph" | "textsl" => {
+                let content = self.inlines(arg(0));
+                out.push(Inline::Emphasis(content));
+            }
+            "sout" | "st" => {
+                let content = self.inlines(arg(0));
+                out.push(Inline::Strikethrough(content));
+            }
+            "texttt" => {
+                let code = super::plain_text(&self.inlines(arg(0)));
+                out.push(Inline::Code(code));
+            }
+            "textsc" | "textrm" | "textsf" | "textup" | "textmd" | "underline" | "mbox" => {
+                let content = self.inlines(arg(0));
+                out.extend(content);
+            }
+            "multicolumn" | "multirow" => {
+                let content = self.inlines(arg(2));
+                out.extend(content);
+            }
+            "href" => {
+                let url = latex::model::text(arg(0));
+                let content = self.inlines(arg(1));
+                out.push(Inline::Link { url, content });
+            }
+            "url" => {
+                let url = latex::model::text(arg(0));
+                out.push(Inline::Link {
+                    content: vec![Inline::Text(url.clone())],
+                    url,
+                });
+            }
+            "includegraphics" => {
+                let src = latex::model::text(arg(2));
+                let (width, height) = graphics_size(arg(1));
+                out.push(Inline::Image {
+                    alt: src.clone(),
+                    src,
+                    width,
+                    height,
+                });
+            }
+            "\\" | "newline" | "linebreak" => out.push(Inline::LineBreak),
+            "label" => {
+                let key = latex::model::text(arg(0));
+                self.labels.insert(key, self.current.clone());
+            }
+            "ref" | "pageref" | "eqref" | "autoref" | "cref" | "Cref" | "nameref" => {
+                let keys = latex::model::text(arg(command.args.len() - 1));
+                out.push(Inline::Text(self.reference(name, &keys)));
+            }
+            "cite" | "citep" | "citet" | "parencite" | "textcite" | "autocite" | "citeauthor"
+            | "citeyear" | "nocite" => {
+                let keys = latex::model::text(arg(command.args.len() - 1));
+                let text = self.citation(command, &keys);
+                out.push(Inline::Text(text));
+            }
+            "footnote" => {
+                let number = self.counters.step("footnote");
+                let content = trim_inlines(self.inlines(arg(1)));
+                self.footnotes.push(Block::Footnote {
+                    label: number.clone(),
+                    blocks: vec![Block::Paragraph(content)],
+                });
+                out.push(Inline::FootnoteReference(number));
+            }
+            "and" => out.push(Inline::Text(", ".to_string())),
+            // Declarations and commands without visible output
+            _ if !latex::parser::signature(name).is_empty() => self.declare(command),
+            _ => {}
+        }
+    }
+
+    /// Text of `\ref` and friends; unknown labels print `??` as in LaTeX
+    fn reference(&self, command: &str, keys: &str) -> String {
+        let texts: Vec<String> = keys
+            .split(',')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(|key| {
+                let Some(label) = self.resolved.labels.get(key) else {
+                    return "??".to_string();
+                };
+                let number = if label.name == "Equation" && command != "ref" {
+                    format!("({})", label.number)
+                } else {
+                    label.number.clone()
+                };
+                match command {
+                    "autoref" | "Cref" => format!("{} {number}", label.name),
+                    "cref" => format!("{} {number}", label.name.to_lowercase()),
+                    "nameref" if !label.title.is_empty() => label.title.clone(),
+                    _ => number,
+                }
+            })
+            .collect();
+        texts.join(", ")
+    }
+
+    /// Text of `\cite` and friends; unknown keys print as `key?`
+    fn citation(&mut self, command: &Command, keys: &str) -> String {
+        let keys: Vec<&str> = keys
+            .split(',')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .collect();
+        for key in &keys {
+            if !self.cited.iter().any(|cited| cited == key) {
+                self.cited.push(key.to_string());
+            }
+        }
+
+        let references: Vec<Result<&Reference, &str>> = keys
+            .iter()
+            .map(|key| {
+                self.resolved
+                    .bibliography
+                    .iter()
+                    .find(|reference| reference.key == *key)
+                    .ok_or(*key)
+            })
+            .collect();
+        let label = |reference: &Result<&Reference, &str>| match reference {
+            Ok(reference) => reference.label.clone(),
+            Err(key) => format!("{key}?"),
+        };
+
+        match command.name.as_str() {
+            "nocite" => String::new(),
+            "citeauthor" => references
+                .iter()
+                .map(|reference| match reference {
+                    Ok(reference) => reference.authors.clone(),
+                    Err(key) => format!("{key}?"),
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            "citeyear" => references
+                .iter()
+                .map(|reference| match reference {
+                    Ok(reference) => reference.year.clone(),
+                    Err(key) => format!("{key}?"),
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            "citet" | "textcite" => references
+                .iter()
+                .map(|reference| match reference {
+                    Ok(reference) => format!("{} [{}]", reference.authors, reference.label),
+                    Err(key) => format!("[{key}?]"),
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            _ => {
+                let mut parts: Vec<String> = references.iter().map(label).collect();
+                // `\cite[post]{k}` or natbib's `\citep[pre][post]{k}`
+                let notes: Vec<String> = command.args[..command.args.len() - 1]
+                    .iter()
+                    .flatten()
+                    .map(|note| super::plain_text(&self.inlines(note)).trim().to_string())
+                    .collect();
+                match notes.as_slice() {
+                    [post] if !post.is_empty() => parts.push(post.clone()),
+                    [pre, post] => {
+                        if !pre.is_empty() {
+                            parts[0] = format!("{pre} {}", parts[0]);
+                        }
+                        if !post.is_empty() {
+                            parts.push(post.clone());
+                        }
+                    }
+                    _ => {}
+                }
+                format!("[{}]", parts.join(", "))
+            }
+        }
+    }
+}
+
+/// Nodes that end a paragraph and become blocks of their own
+fn is_block(node: &Node) -> bool {
+    match &node.kind {
+        NodeKind::Math { display, .. } => *display,
+        NodeKind::Environment(env) => env.name != "math",
+        NodeKind::Command(command) => matches!(
+            command.name.as_str(),
+            "part"
+                | "chapter"
+                | "section"
+                | "subsection"
+                | "subsubsection"
+                | "paragraph"
+                | "subparagraph"
+                | "input"
+                | "include"
+                | "subfile"
+                | "bibliography"
+                | "printbibliography"
+                | "tableofcontents"
+                | "caption"
+        ),
+        _ => false,
+    }
+}
+
+/// Split `nodes` before each `\name`; the first part has no command
+fn split_at_command<'n>(nodes: &'n [Node], name: &str) -> Vec<(Option<&'n Command>, &'n [Node])> {
+    let mut parts = Vec::new();
+    let mut command = None;
+    let mut start = 0;
+    for (index, node) in nodes.iter().enumerate() {
+        if let Some(next) = node.command(name) {
+            parts.push((command, &nodes[start..index]));
+            command = Some(next);
+            start = index + 1;
+        }
+    }
+    parts.push((command, &nodes[start..]));
+    parts
+}
+
+/// A `[...]` at the start of an environment body whose signature is not
+/// known to the parser (`\begin{theorem}[Name]`)
+fn leading_optional(nodes: &[Node]) -> (Option<&[Node]>, &[Node]) {
+    let first = nodes
+        .iter()
+        .position(|node| !node.is_space())
+        .unwrap_or(nodes.len());
+    let opens = matches!(nodes.get(first).map(|node| &node.kind), Some(NodeKind::Text(text)) if text == "[");
+    if !opens {
+        return (None, nodes);
+    }
+    let close = nodes[first..]
+        .iter()
+        .position(|node| matches!(&node.kind, NodeKind::Text(text) if text == "]"));
+    match close {
+        Some(close) => (
+            Some(&nodes[first + 1..first + close]),
+            &nodes[first + close + 1..],
+        ),
+        None => (None, nodes),
+    }
+}
+
+/// Put `inlines` in front of the first paragraph
+fn prepend(blocks: &mut Vec<Block>, inlines: Vec<Inline>) {
+    match blocks.first_mut() {
+        Some(Block::Paragraph(content)) => {
+            let rest = std::mem::take(content);
+            *content = merge_text(inlines.into_iter().chain(rest).collect());
+        }
+        _ => blocks.insert(0, Block::Paragraph(trim_inlines(inlines))),
+    }
+}
+
+/// Nested list of numbered headings
+fn contents_list(sections: &[(u8, String, Vec<Inline>)]) -> List {
+    let top = sections.iter().map(|(level, ..)| *level).min().unwrap_or(1);
+    let mut items: Vec<ListItem> = Vec::new();
+    let mut index = 0;
+    while index < sections.len() {
+        let (level, number, title) = &sections[index];
+        let children = sections[index + 1..]
+            .iter()
+            .take_while(|(child, ..)| *child > top)
+            .count();
+        if *level == top {
+            let mut content = vec![Inline::Text(format!("{number} "))];
+            content.extend(title.iter().cloned());
+            let mut blocks = vec![Block::Paragraph(merge_text(content))];
+            if children > 0 {
+                blocks.push(Block::List(contents_list(
+                    &sections[index + 1..index + 1 + children],
+                )));
+            }
+            items.push(ListItem {
+                checked: None,
+                task_offset: None,
+                blocks,
+            });
+            index += children + 1;
+        } else {
+            index += 1;
+        }
+    }
+    List { start: None, items }
+}
+
+/// Column alignments of a `tabular` specification such as `|l|c|p{3cm}|`
+fn column_alignments(spec: &str) -> Vec<Alignment> {
+    let chars: Vec<char> = spec.chars().collect();
+    let mut alignments = Vec::new();
+    let mut index = 0;
+    let skip_group = |index: &mut usize| {
+        let mut depth = 0;
+        while *index < chars.len() {
+            match chars[*index] {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        *index += 1;
+                        return;
+                    }
+                }
+                _ => {}
+            }
+            *index += 1;
+        }
+    };
+    while index < chars.len() {
+        let c = chars[index];
+        index += 1;
+        match c {
+            'l' => alignments.push(Alignment::Left),
+            'c' => alignments.push(Alignment::Center),
+            'r' => alignments.push(Alignment::Right),
+            'p' | 'm' | 'b' => {
+                alignments.push(Alignment::Left);
+                skip_group(&mut index);
+            }
+            'X' => alignments.push(Alignment::Left),
+            '@' | '!' | '>' | '<' => skip_group(&mut index),
+            '*' => {
+                // `*{3}{c}` repeats a column specification
+                let start = index;
+                skip_group(&mut index);
+                let count: usize = chars[start..index]
+                    .iter()
+                    .filter(|c| c.is_ascii_digit())
+                    .collect::<String>()
+                    .parse()
+                    .unwrap_or(1);
+                let start = index;
+                skip_group(&mut index);
+                let inner: String = chars[start..index].iter().collect();
+                let inner = column_alignments(inner.trim_matches(|c| c == '{' || c == '}'));
+                for _ in 0..count {
+                    alignments.extend(inner.iter().copied());
+                }
+            }
+            _ => {}
+        }
+    }
+    alignments
+}
+
+/// Split the body of an `align`-like environment into rows at top-level `\\`
+fn split_rows(tex: &str) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut row = String::new();
+    let mut depth = 0i32;
+    let mut chars = tex.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let rest: String = chars.clone().take(6).collect();
+                if chars.peek() == Some(&'\\') && depth == 0 {
+                    chars.next();
+                    rows.push(std::mem::take(&mut row));
+                    continue;
+                }
+                if rest.starts_with("begin{") {
+                    depth += 1;
+                } else if rest.starts_with("end{") {
+                    depth -= 1;
+                }
+                row.push(c);
+                if let Some(next) = chars.next() {
+                    row.push(next);
+                }
+            }
+            '{' => {
+                depth += 1;
+                row.push(c);
+            }
+            '}' => {
+                depth -= 1;
+                row.push(c);
+            }
+            c => row.push(c),
+        }
+    }
+    rows.push(row);
+    rows
+}
+
+/// Remove `\label{...}` from math, returning the keys
+fn take_labels(tex: &str) -> (String, Vec<String>) {
+    let mut rest = tex;
+    let mut out = String::new();
+    let mut keys = Vec::new();
+    while let Some(index) = rest.find(r"\label{") {
+        out.push_str(&rest[..index]);
+        let after = &rest[index + r"\label{".len()..];
+        let end = after.find('}').unwrap_or(after.len());
+        keys.push(after[..end].trim().to_string());
+        rest = after.get(end + 1..).unwrap_or("");
+    }
+    out.push_str(rest);
+    (out, keys)
+}
+
+/// Braced argument of the first `\command{...}` in TeX source
+fn command_argument(tex: &str, command: &str) -> Option<String> {
+    let needle = format!("\\{command}{{");
+    let start = tex.find(&needle)? + needle.len();
+    let end = tex[start..].find('}')?;
+    Some(tex[start..start + end].to_string())
+}
+
+/// Code of a verbatim environment without the rest of the `\begin` line
+fn verbatim_body(raw: &str) -> String {
+    let body = match raw.split_once('\n') {
+        Some((first, rest)) if first.trim().is_empty() => rest,
+        _ => raw,
+    };
+    // Indentation before `\end{...}`
+    body.trim_end_matches([' ', '\t']).to_string()
+}
+
+/// `language=...` option of `lstlisting`
+fn language_option(options: &str) -> Option<String> {
+    options.split(',').find_map(|option| {
+        let (key, value) = option.split_once('=')?;
+        (key.trim() == "language").then(|| value.trim().to_string())
+    })
+}
+
+/// `width=`/`height=` options of `\includegraphics`, in CSS pixels
+///
+/// Absolute lengths convert at 96 dpi; `\textwidth` and friends count as
+/// a 6.5in text block, so `0.5\linewidth` previews at half that.
+fn graphics_size(options: &[Node]) -> (Option<u32>, Option<u32>) {
+    let mut source = String::new();
+    for node in options {
+        match &node.kind {
+            NodeKind::Text(text) | NodeKind::Raw(text) => source.push_str(text),
+            NodeKind::Command(command) => {
+                source.push('\\');
+                source.push_str(&command.name);
+            }
+            _ => {}
+        }
+    }
+    let length = |key: &str| {
+        source.split(',').find_map(|option| {
+            let (name, value) = option.split_once('=')?;
+            (name.trim() == key).then(|| css_pixels(value.trim()))?
+        })
+    };
+    (length("width"), length("height"))
+}
+
+fn css_pixels(length: &str) -> Option<u32> {
+    let split = length
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(length.len());
+    let (number, unit) = length.split_at(split);
+    let number: f32 = if number.is_empty() {
+        1.0
+    } else {
+        number.parse().ok()?
+    };
+    let per_unit = match unit.trim() {
+        "px" => 1.0,
+        "pt" => 96.0 / 72.27,
+        "bp" => 96.0 / 72.0,
+        "in" => 96.0,
+        "cm" => 96.0 / 2.54,
+        "mm" => 96.0 / 25.4,
+        "em" => 16.0,
+        "\\textwidth" | "\\linewidth" | "\\columnwidth" | "\\hsize" => 6.5 * 96.0,
+        "\\textheight" => 9.0 * 96.0,
+        _ => return None,
+    };
+    Some((number * per_unit).round() as u32)
+}
+
+/// `A`, `A and B`, `A, B, and C`
+fn join_names(names: &[String]) -> String {
+    match names {
+        [] => String::new(),
+        [one] => one.clone(),
+        [first, second] => format!("{first} and {second}"),
+        [init @ .., last] => format!("{}, and {last}", init.join(", ")),
+    }
+}
+
+/// TeX ligatures for dashes and quotes
+fn ligatures(text: &str) -> String {
+    text.replace("---", "—")
+        .replace("--", "–")
+        .replace("``", "“")
+        .replace("''", "”")
+}
+
+/// Combining mark of an accent command (`\'e` → é)
+fn accent(name: &str) -> Option<char> {
+    Some(match name {
+        "'" => '\u{301}',
+        "`" => '\u{300}',
+        "^" => '\u{302}',
+        "\"" => '\u{308}',
+        "~" => '\u{303}',
+        "=" => '\u{304}',
+        "." => '\u{307}',
+        "u" => '\u{306}',
+        "v" => '\u{30C}',
+        "H" => '\u{30B}',
+        "r" => '\u{30A}',
+        "c" => '\u{327}',
+        "k" => '\u{328}',
+        _ => return None,
+    })
+}
+
+/// Text of symbol and escape commands
+fn symbol(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "%" => "%",
+        "&" => "&",
+        "$" => "$",
+        "#" => "#",
+        "_" => "_",
+        "{" => "{",
+        "}" => "}",
+        " " | "," | ";" | ":" | "quad" | "qquad" | "enspace" | "thinspace" => " ",
+        "-" | "/" | "@" | "today" => "",
+        "LaTeX" => "LaTeX",
+        "LaTeXe" => "LaTeX2ε",
+        "TeX" => "TeX",
+        "BibTeX" => "BibTeX",
+        "ldots" | "dots" | "textellipsis" => "…",
+        "textbackslash" => "\\",
+        "textasciitilde" => "~",
+        "textasciicircum" => "^",
+        "textless" => "<",
+        "textgreater" => ">",
+        "textbar" => "|",
+        "textbullet" => "•",
+        "textendash" => "–",
+        "textemdash" => "—",
+        "textquoteleft" => "‘",
+        "textquoteright" => "’",
+        "S" => "§",
+        "P" => "¶",
+        "dag" => "†",
+        "ddag" => "‡",
+        "copyright" => "©",
+        "textregistered" => "®",
+        "texttrademark" => "™",
+        "euro" => "€",
+        "pounds" => "£",
+        "ss" => "ß",
+        "ae" => "æ",
+        "AE" => "Æ",
+        "oe" => "œ",
+        "OE" => "Œ",
+        "aa" => "å",
+        "AA" => "Å",
+        "o" => "ø",
+        "O" => "Ø",
+        "l" => "ł",
+        "L" => "Ł",
+        "i" => "ı",
+        "j" => "ȷ",
+        _ => return None,
+    })
+}
+
+fn trim_inlines(inlines: Vec<Inline>) -> Vec<Inline> {
+    let mut inlines = merge_text(inlines);
+    if let Some(Inline::Text(text)) = inlines.first_mut() {
+        *text = text.trim_start().to_string();
+    }
+    if let Some(Inline::Text(text)) = inlines.last_mut() {
+        *text = text.trim_end().to_string();
+    }
+    // Collapse runs of spaces left by adjacent space nodes
+    for inline in &mut inlines {
+        if let Inline::Text(text) = inline {
+            while text.contains("  ") {
+                *text = text.replace("  ", " ");
+            }
+        }
+    }
+    merge_text(inlines)
+}
+
+/// Parse inline LaTeX (font commands, inline math, links, escapes)
+pub fn parse_inlines(source: &str) -> Vec<Inline> {
+    let nodes = latex::parse(source);
+    let mut converter = Converter::new(None, false, References::default());
+    trim_inlines(converter.inlines(&nodes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Inline {
+        Inline::Text(s.to_string())
+    }
+
+    const SAMPLE: &str = r"\documentclass[12pt]{article}
+\usepackage{amsmath}
+\title{Sample \emph{Doc}}
+\author{Jane}
+\begin{document}
+\maketitle
+\section{Intro}
+Text with \textbf{bold} and $x^2$. % comment
+Second line.
+
+\begin{itemize}
+  \item One
+  \item Two
+\end{itemize}
+
+\begin{equation}
+E = mc^2
+\end{equation}
+\end{document}
+";
+
+    #[test]
+    fn test_preamble_metadata() {
+        let doc = parse(SAMPLE);
+        assert_eq!(doc.title.as_deref(), Some("Sample Doc"));
+        assert_eq!(
+            doc.metadata,
+            vec![
+                ("Document Type".to_string(), "article".to_string()),
+                ("Options".to_string(), "12pt".to_string()),
+                ("Packages".to_string(), "amsmath".to_string()),
+                ("Author".to_string(), "Jane".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_body_blocks_in_order() {
+        let doc = parse(SAMPLE);
+        assert_eq!(
+            doc.blocks,
+            vec![
+                Block::Heading {
+                    level: 2,
+                    content: vec![text("1 Intro")]
+                },
+                Block::Paragraph(vec![
+                    text("Text with "),
+                    Inline::Strong(vec![text("bold")]),
+                    text(" and "),
+                    Inline::Math("x^2".to_string()),
+                    text(". Second line."),
+                ]),
+                Block::List(List {
+                    start: None,
+                    items: vec![
+                        ListItem {
+                            checked: None,
+                            task_offset: None,
+                            blocks: vec![Block::Paragraph(vec![text("One")])]
+                        },
+                        ListItem {
+                            checked: None,
+                            task_offset: None,
+                            blocks: vec![Block::Paragraph(vec![text("Two")])]
+                        },
+                    ]
+                }),
+                Block::Math(r"E = mc^2 \tag{1}".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_source_map_lines() {
+        let source =
+            "\\begin{document}\n\\section{A}\nfirst\nline % note\n\n\\[ x \\]\n\\end{document}\n";
+        let doc = parse(source);
+        assert_eq!(doc.blocks.len(), 3);
+        let starts: Vec<&str> = doc
+            .source_map
+            .iter()
+            .map(|range| source[range.clone()].lines().next().unwrap())
+            .collect();
+        assert_eq!(starts, vec!["\\section{A}", "first", "\\[ x \\]"]);
+    }
+
+    #[test]
+    fn test_inline_commands() {
+        assert_eq!(
+            parse_inlines(r"\href{https://x.org}{site} \texttt{code} 50\% --- {\bf ok} caf\'e"),
+            vec![
+                Inline::Link {
+                    url: "https://x.org".to_string(),
+                    content: vec![text("site")]
+                },
+                text(" "),
+                Inline::Code("code".to_string()),
+                text(" 50% — "),
+                Inline::Strong(vec![text("ok")]),
+                text(" cafe\u{301}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verbatim_and_nested_lists() {
+        let doc = parse(
+            "\\begin{lstlisting}[language=Rust]\nfn main() {}\n\\end{lstlisting}\n\
+             \\begin{enumerate}\n\\item A\n\\begin{itemize}\n\\item B\n\\end{itemize}\n\\end{enumerate}\n",
+        );
+        assert_eq!(
+            doc.blocks[0],
+            Block::CodeBlock {
+                lang: "rust".to_string(),
+                code: "fn main() {}\n".to_string()
+            }
+        );
+        let Block::List(list) = &doc.blocks[1] else {
+            panic!("expected list");
+        };
+        assert_eq!(list.start, Some(1));
+        assert!(matches!(list.items[0].blocks[1], Block::List(_)));
+    }
+
+    #[test]
+    fn test_numbering_and_forward_references() {
+        let doc = parse(
+            r"See Section~\ref{sec:b}, \eqref{eq:x} and \autoref{fig:plot}.
+\section{A}
+\subsection*{Unnumbered}
+\subsection{A.1}
+\section{B}\label{sec:b}
+\begin{align}
+a &= b \label{eq:x} \\
+c &= d \nonumber
+\end{align}
+\begin{figure}
+\includegraphics[width=3cm]{plot.png}
+\caption{A plot}\label{fig:plot}
+\end{figure}
+Missing \ref{nope}.",
+        );
+        assert_eq!(
+            doc.blocks[0],
+            Block::Paragraph(vec![text("See Section 2, (1) and Figure 1.")])
+        );
+        let headings: Vec<String> = doc
+            .blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Heading { content, .. } => Some(super::super::plain_text(content)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(headings, vec!["1 A", "Unnumbered", "1.1 A.1", "2 B"]);
+        assert!(doc.blocks.contains(&Block::Math(
+            "\\begin{aligned}\na &= b \\tag{1} \\\\\nc &= d \\nonumber\n\\end{aligned}".to_string()
+        )));
+        assert!(doc.blocks.contains(&Block::Paragraph(vec![
+            Inline::Strong(vec![text("Figure 1:")]),
+            text(" A plot"),
+        ])));
+        assert_eq!(
+            doc.blocks.last(),
+            Some(&Block::Paragraph(vec![text("Missing ??.")]))
+        );
+        assert!(doc.blocks.contains(&Block::Paragraph(vec![Inline::Image {
+            src: "plot.png".to_string(),
+            alt: "plot.png".to_string(),
+            width: Some(113),
+            height: None,
+        }])));
+    }
+
+    #[test]
+    fn test_theorems_and_tables() {
+        let doc = parse(
+            r"\newtheorem{thm}{Theorem}[section]
+\newtheorem{lem}[thm]{Lemma}
+\section{S}
+\begin{thm}[Main]\label{t} True. \end{thm}
+\begin{lem} Also. \end{lem}
+Use \ref{t}.
+\begin{tabular}{|l|r|}
+\hline Name & Qty \\ \hline
+Apple & 3 \\
+\multicolumn{2}{c}{Total} \\ \hline
+\end{tabular}",
+        );
+        assert_eq!(
+            doc.blocks[1],
+            Block::Paragraph(vec![
+                Inline::Strong(vec![text("Theorem 1.1")]),
+                text(" (Main). True."),
+            ])
+        );
+        assert_eq!(
+            doc.blocks[2],
+            Block::Paragraph(vec![
+                Inline::Strong(vec![text("Lemma 1.2")]),
+                text(". Also."),
+            ])
+        );
+        assert_eq!(doc.blocks[3], Block::Paragraph(vec![text("Use 1.1.")]));
+        let Block::Table(table) = &doc.blocks[4] else {
+            panic!("expected table");
+        };
+        assert_eq!(table.alignments, vec![Alignment::Left, Alignment::Right]);
+        assert_eq!(table.header, vec![vec![text("Name")], vec![text("Qty")]]);
+        assert_eq!(
+            table.rows,
+            vec![
+                vec![vec![text("Apple")], vec![text("3")]],
+                vec![vec![text("Total")], vec![]],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_thebibliography_citations() {
+        let doc = parse(
+            r"As shown~\cite[p.~5]{knuth}, see also \cite{lamport,missing}.
+\begin{thebibliography}{9}
+\bibitem{lamport} L. Lamport. \emph{\LaTeX}.
+\bibitem{knuth} D. Knuth. The \TeX book.
+\end{thebibliography}",
+        );
+        assert_eq!(
+            doc.blocks[0],
+            Block::Paragraph(vec![text("As shown [2, p. 5], see also [1, missing?].")])
+        );
+        assert_eq!(
+            doc.blocks[1],
+            Block::Paragraph(vec![
+                text("[1] L. Lamport. "),
+                Inline::Emphasis(vec![text("LaTeX")]),
+                text("."),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_includes_and_bib_files() {
+        let dir = std::env::temp_dir().join(format!("lala-latex-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("chapters")).unwrap();
+        std::fs::write(
+            dir.join("chapters/intro.tex"),
+            "\\section{Intro}\\label{intro}\nBy \\citet{knuth84}.\n\\input{chapters/intro}",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("refs.bib"),
+            "@book{knuth84, author = {Knuth, Donald}, title = {The {\\TeX}book}, \
+             publisher = {Addison-Wesley}, year = 1984}",
+        )
+        .unwrap();
+
+        let doc = parse_with_base(
+            "\\input{chapters/intro}\nSee \\ref{intro}.\n\\bibliography{refs}",
+            Some(&dir),
+        );
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(
+            doc.blocks,
+            vec![
+                Block::Heading {
+                    level: 2,
+                    content: vec![text("1 Intro")]
+                },
+                Block::Paragraph(vec![text("By Knuth [1].")]),
+                Block::Paragraph(vec![Inline::Emphasis(vec![text(
+                    "\\input{chapters/intro}: recursive include"
+                )])]),
+                Block::Paragraph(vec![text("See 1.")]),
+                Block::Heading {
+                    level: 2,
+                    content: vec![text("References")]
+                },
+                Block::Paragraph(vec![
+                    text("[1] Donald Knuth. The TeXbook. "),
+                    Inline::Emphasis(vec![text("Addison-Wesley")]),
+                    text(", 1984."),
+                ]),
+            ]
+        );
+        // Included blocks map to the `\input` line of the main file
+        assert_eq!(doc.source_map[0].start, 0);
+        assert_eq!(doc.source_map[1].start, 0);
+    }
+}
@@ -0,0 +1,424 @@
+//! Links between documents
+//!
+//! Decides where a link goes the way the previews follow it: `#anchor`
+//! scrolls to a heading of the same document, a relative path opens another
+//! file (optionally at one of its headings), and anything with a URL scheme
+//! is external. Heading anchors are GitHub's: lowercase, punctuation
+//! dropped, spaces as `-`, and `-1`, `-2`, ... for repeated headings.
+//!
+//! [`check_dir`] uses the same rules to report broken links in a docs
+//! directory (`lala links`). External URLs are not checked, since lala
+//! works offline.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+
+use super::{image, plain_text, Block, Document, Inline};
+
+/// Where a link points
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    /// Heading of the current document
+    Anchor(String),
+    /// Local file, and a heading in it
+    File {
+        path: PathBuf,
+        anchor: Option<String>,
+    },
+    /// `https:`, `mailto:` and other URLs, opened by the system
+    External(String),
+}
+
+/// Classify a link `url` of a document in `base_dir`
+pub fn classify(url: &str, base_dir: Option<&Path>) -> Target {
+    let url = url.trim();
+    if let Some(anchor) = url.strip_prefix('#') {
+        return Target::Anchor(percent_decode(anchor));
+    }
+    if let Some(path) = url.strip_prefix("file://") {
+        return file_target(path, None);
+    }
+    if has_scheme(url) {
+        return Target::External(url.to_string());
+    }
+    file_target(url, base_dir)
+}
+
+fn file_target(url: &str, base_dir: Option<&Path>) -> Target {
+    let (path, anchor) = match url.split_once('#') {
+        Some((path, anchor)) => (path, Some(percent_decode(anchor))),
+        None => (url, None),
+    };
+    let path = path.split('?').next().unwrap_or_default();
+    let path = PathBuf::from(percent_decode(path));
+    let path = match base_dir {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path,
+    };
+    Target::File {
+        path,
+        anchor: anchor.filter(|anchor| !anchor.is_empty()),
+    }
+}
+
+/// `scheme:` as in RFC 3986, but not a Windows drive letter (`C:\`)
+fn has_scheme(url: &str) -> bool {
+    let Some((scheme, _)) = url.split_once(':') else {
+        return false;
+    };
+    scheme.len() > 1
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+fn percent_decode(text: &str) -> String {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let hex = tail
+            .get(..2)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (byte, hex) {
+            (b'%', Some(decoded)) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// GitHub anchor of a heading text
+pub fn slug(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            c if c.is_whitespace() => Some('-'),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Anchors of all headings, in document order
+///
+/// Nested headings (in quotes, lists and footnotes) count too, in the
+/// order the previews render them.
+pub fn anchors(doc: &Document) -> Vec<String> {
+    fn collect(blocks: &[Block], seen: &mut HashMap<String, usize>, out: &mut Vec<String>) {
+        for block in blocks {
+            match block {
+                Block::Heading { content, .. } => {
+                    let slug = slug(&plain_text(content));
+                    let count = seen.entry(slug.clone()).or_default();
+                    out.push(match *count {
+                        0 => slug,
+                        n => format!("{slug}-{n}"),
+                    });
+                    *count += 1;
+                }
+                Block::BlockQuote(blocks) | Block::Footnote { blocks, .. } => {
+                    collect(blocks, seen, out)
+                }
+                Block::List(list) => {
+                    for item in &list.items {
+                        collect(&item.blocks, seen, out);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    let mut out = Vec::new();
+    collect(&doc.blocks, &mut HashMap::new(), &mut out);
+    out
+}
+
+/// A link or image whose target does not exist
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokenLink {
+    pub file: PathBuf,
+    /// 1-based position of the link destination in the source
+    pub line: usize,
+    pub column: usize,
+    pub url: String,
+    pub reason: String,
+}
+
+impl fmt::Display for BrokenLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: broken link `{}`: {}",
+            self.file.display(),
+            self.line,
+            self.column,
+            self.url,
+            self.reason
+        )
+    }
+}
+
+/// Check the Markdown files under `path` (a directory or a single file)
+///
+/// Files ignored by `.gitignore` are skipped; results are sorted by file.
+pub fn check_dir(path: &Path) -> Vec<BrokenLink> {
+    let mut files: Vec<PathBuf> = WalkBuilder::new(path)
+        .hidden(false)
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_some_and(|kind| kind.is_file()))
+        .map(|entry| entry.into_path())
+        .filter(|path| is_markdown(path))
+        .collect();
+    files.sort();
+
+    let mut anchors = AnchorCache::default();
+    let mut broken = Vec::new();
+    for file in files {
+        if let Ok(source) = std::fs::read_to_string(&file) {
+            broken.extend(check(&file, &source, &mut anchors));
+        }
+    }
+    broken
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext.to_ascii_lowercase().as_str(), "md" | "markdown"))
+}
+
+/// Heading anchors of the Markdown files links point into
+#[derive(Default)]
+struct AnchorCache {
+    files: HashMap<PathBuf, Option<Vec<String>>>,
+}
+
+impl AnchorCache {
+    fn get(&mut self, path: &Path) -> Option<&[String]> {
+        self.files
+            .entry(path.to_path_buf())
+            .or_insert_with(|| {
+                let source = std::fs::read_to_string(path).ok()?;
+                Some(anchors(&super::markdown::parse(&source)))
+            })
+            .as_deref()
+    }
+}
+
+/// Broken links of one Markdown document
+fn check(file: &Path, source: &str, cache: &mut AnchorCache) -> Vec<BrokenLink> {
+    let doc = super::markdown::parse(source);
+    let own_anchors = anchors(&doc);
+    let base_dir = file.parent();
+
+    let mut broken = Vec::new();
+    for (index, block) in doc.blocks.iter().enumerate() {
+        let range = doc.source_map.get(index).cloned().unwrap_or(0..0);
+        // Destinations are searched for in order, so repeated URLs in one
+        // block are reported at their own positions
+        let mut cursor = range.start;
+        let mut report = |url: &str, reason: String| {
+            let block_source = source.get(cursor..range.end).unwrap_or_default();
+            let offset = match block_source.find(url) {
+                Some(found) => cursor + found,
+                None => range.start,
+            };
+            cursor = (offset + url.len()).min(range.end.max(offset));
+            let (line, column) = line_column(source, offset);
+            broken.push(BrokenLink {
+                file: file.to_path_buf(),
+                line,
+                column,
+                url: url.to_string(),
+                reason,
+            });
+        };
+
+        for (url, is_image) in destinations(block) {
+            let problem = if is_image {
+                check_image(url, base_dir)
+            } else {
+                check_link(url, base_dir, &own_anchors, cache)
+            };
+            if let Some(reason) = problem {
+                report(url, reason);
+            }
+        }
+    }
+    broken
+}
+
+fn check_link(
+    url: &str,
+    base_dir: Option<&Path>,
+    own_anchors: &[String],
+    cache: &mut AnchorCache,
+) -> Option<String> {
+    match classify(url, base_dir) {
+        Target::Anchor(anchor) => (!own_anchors.contains(&anchor))
+            .then(|| format!("no heading `#{anchor}` in this document")),
+        Target::File { path, anchor } => {
+            if !path.exists() {
+                return Some("no such file".to_string());
+            }
+            let anchor = anchor.filter(|_| is_markdown(&path))?;
+            let known = cache.get(&path).unwrap_or_default();
+            (!known.contains(&anchor)).then(|| {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                format!("no heading `#{anchor}` in {name}")
+            })
+        }
+        Target::External(_) => None,
+    }
+}
+
+fn check_image(src: &str, base_dir: Option<&Path>) -> Option<String> {
+    match image::resolve(src, base_dir)? {
+        image::Source::File(path) if !path.exists() => Some("no such image".to_string()),
+        _ => None,
+    }
+}
+
+/// Link and image destinations of a block, in order (`true` for images)
+fn destinations(block: &Block) -> Vec<(&str, bool)> {
+    fn inlines<'a>(content: &'a [Inline], out: &mut Vec<(&'a str, bool)>) {
+        for inline in content {
+            match inline {
+                Inline::Link { url, content } => {
+                    out.push((url, false));
+                    inlines(content, out);
+                }
+                Inline::Image { src, .. } => out.push((src, true)),
+                Inline::Emphasis(content)
+                | Inline::Strong(content)
+                | Inline::Strikethrough(content) => inlines(content, out),
+                _ => {}
+            }
+        }
+    }
+    fn blocks<'a>(block: &'a Block, out: &mut Vec<(&'a str, bool)>) {
+        match block {
+            Block::Heading { content, .. } | Block::Paragraph(content) => inlines(content, out),
+            Block::BlockQuote(inner) | Block::Footnote { blocks: inner, .. } => {
+                inner.iter().for_each(|block| blocks(block, out))
+            }
+            Block::List(list) => list
+                .items
+                .iter()
+                .flat_map(|item| &item.blocks)
+                .for_each(|block| blocks(block, out)),
+            Block::Table(table) => table
+                .header
+                .iter()
+                .chain(table.rows.iter().flatten())
+                .for_each(|cell| inlines(cell, out)),
+            _ => {}
+        }
+    }
+    let mut out = Vec::new();
+    blocks(block, &mut out);
+    out
+}
+
+/// 1-based line and column (in characters) of a byte offset
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let base = Path::new("/docs");
+        assert_eq!(
+            classify("#getting-started", Some(base)),
+            Target::Anchor("getting-started".to_string())
+        );
+        assert_eq!(
+            classify("guide/setup.md#install%20it", Some(base)),
+            Target::File {
+                path: PathBuf::from("/docs/guide/setup.md"),
+                anchor: Some("install it".to_string())
+            }
+        );
+        assert_eq!(
+            classify("https://example.com/a#b", Some(base)),
+            Target::External("https://example.com/a#b".to_string())
+        );
+        assert_eq!(
+            classify("mailto:me@example.com", None),
+            Target::External("mailto:me@example.com".to_string())
+        );
+        assert_eq!(
+            classify("C:/notes.md", None),
+            Target::File {
+                path: PathBuf::from("C:/notes.md"),
+                anchor: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_github_anchors() {
+        assert_eq!(slug("Hello, World!"), "hello-world");
+        assert_eq!(slug("API `v2` — Overview"), "api-v2--overview");
+        assert_eq!(slug("日本語の見出し"), "日本語の見出し");
+
+        let doc = super::super::markdown::parse("# Intro\n\n## Setup\n\n> ## Setup\n\n# Intro\n");
+        assert_eq!(anchors(&doc), ["intro", "setup", "setup-1", "intro-1"]);
+    }
+
+    #[test]
+    fn test_check_dir_reports_broken_links() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("other.md"), "# Other page\n").unwrap();
+        std::fs::write(dir.path().join("logo.png"), b"").unwrap();
+        std::fs::write(
+            dir.path().join("index.md"),
+            "# Index\n\n\
+             [ok](#index) [bad](#nowhere)\n\n\
+             - [page](other.md#other-page) and [wrong](other.md#missing)\n\
+             - [gone](missing.md) ![logo](logo.png) ![x](x.png)\n\n\
+             [web](https://example.com/missing)\n",
+        )
+        .unwrap();
+
+        let report: Vec<String> = check_dir(dir.path())
+            .iter()
+            .map(|link| {
+                let file = link.file.file_name().unwrap().to_string_lossy();
+                format!(
+                    "{file}:{}:{}: {} ({})",
+                    link.line, link.column, link.url, link.reason
+                )
+            })
+            .collect();
+        assert_eq!(
+            report,
+            [
+                "index.md:3:20: #nowhere (no heading `#nowhere` in this document)",
+                "index.md:5:43: other.md#missing (no heading `#missing` in other.md)",
+                "index.md:6:10: missing.md (no such file)",
+                "index.md:6:45: x.png (no such image)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,673 @@
+//! Markdown → [`Document`] parser
+//!
+//! Folds the `pulldown-cmark` event stream into the block/inline tree.
+//! Raw HTML blocks are handed to the [`html`](super::html) parser so that
+//! embedded markup renders the same way as in an `.html` file.
+//!
+//! GitHub extensions are mapped as well: YAML (`---`) or TOML (`+++`) front
+//! matter becomes [`Document::metadata`], footnote definitions are moved to
+//! the end of the document like GitHub does, and task list items remember
+//! where their marker is so a preview can toggle it with [`toggle_task`].
+//!
+//! Fenced blocks are rendered the way GitHub shows them: ```` ```mermaid ````
+//! goes through the [`mermaid`](super::mermaid) parser, and ```` ```math ````
+//! / ```` ```latex ```` become display math like `$$...$$` does.
+
+use pulldown_cmark::{
+    CodeBlockKind, Event, MetadataBlockKind, OffsetIter, Options, Parser, Tag, TagEnd,
+};
+
+use std::ops::Range;
+
+use super::{merge_text, Alignment, Block, Document, Inline, List, ListItem, Table};
+
+/// Parse a Markdown document
+pub fn parse(markdown: &str) -> Document {
+    let mut builder = Builder {
+        events: Parser::new_ext(markdown, Options::all()).into_offset_iter(),
+        position: 0..0,
+        task_markers: Vec::new(),
+        metadata: Vec::new(),
+        footnotes: Vec::new(),
+        source_map: Vec::new(),
+    };
+
+    let mut blocks = builder.blocks_until(None);
+    for (footnote, range) in builder.footnotes {
+        blocks.push(footnote);
+        builder.source_map.push(range);
+    }
+
+    Document {
+        title: None,
+        metadata: builder.metadata,
+        blocks,
+        source_map: builder.source_map,
+    }
+}
+
+/// Flip the task marker at `offset` between `[ ]` and `[x]`
+///
+/// `offset` is a [`ListItem::task_offset`] from [`parse`]. Returns `false`
+/// (and leaves the source alone) when there is no marker at that position,
+/// e.g. because the text changed since it was parsed.
+pub fn toggle_task(markdown: &mut String, offset: usize) -> bool {
+    let replacement = match markdown.get(offset..offset + 3) {
+        Some("[ ]") => "[x]",
+        Some("[x]") | Some("[X]") => "[ ]",
+        _ => return false,
+    };
+    markdown.replace_range(offset..offset + 3, replacement);
+    true
+}
+
+struct Builder<'a> {
+    events: OffsetIter<'a>,
+    /// Source range of the most recent event
+    position: Range<usize>,
+    /// Task marker of each open list item, innermost last
+    task_markers: Vec<Option<(bool, usize)>>,
+    metadata: Vec<(String, String)>,
+    footnotes: Vec<(Block, Range<usize>)>,
+    /// Source range of each top-level block
+    source_map: Vec<Range<usize>>,
+}
+
+impl<'a> Builder<'a> {
+    fn next(&mut self) -> Option<Event<'a>> {
+        let (event, range) = self.events.next()?;
+        self.position = range;
+        Some(event)
+    }
+
+    /// Collect blocks until `end` (or the end of input)
+    ///
+    /// Inline events that appear directly inside a container (tight list
+    /// items, table-less text) are gathered into an implicit paragraph.
+    fn blocks_until(&mut self, end: Option<TagEnd>) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        let mut pending = Vec::new();
+
+        while let Some(event) = self.next() {
+            // A start event's range spans the whole element
+            let range = self.position.clone();
+            match event {
+                Event::End(tag) if Some(tag) == end => break,
+                Event::Start(tag) if is_block_tag(&tag) => {
+                    flush_paragraph(&mut pending, &mut blocks);
+                    self.block(tag, &mut blocks);
+                }
+                Event::Rule => {
+                    flush_paragraph(&mut pending, &mut blocks);
+                    blocks.push(Block::Rule);
+                }
+                Event::DisplayMath(tex) => {
+                    flush_paragraph(&mut pending, &mut blocks);
+                    blocks.push(Block::Math(tex.trim().to_string()));
+                }
+                Event::Html(html) => {
+                    flush_paragraph(&mut pending, &mut blocks);
+                    blocks.extend(super::html::parse(&html).blocks);
+                }
+                event => self.inline(event, &mut pending),
+            }
+            if end.is_none() {
+                self.source_map.resize(blocks.len(), range);
+            }
+        }
+
+        flush_paragraph(&mut pending, &mut blocks);
+        if end.is_none() {
+            let range = self.position.clone();
+            self.source_map.resize(blocks.len(), range);
+        }
+        blocks
+    }
+
+    fn block(&mut self, tag: Tag<'a>, blocks: &mut Vec<Block>) {
+        match tag {
+            Tag::Paragraph => self.paragraph(blocks),
+            Tag::Heading { level, .. } => {
+                let content = self.inlines_until(TagEnd::Heading(level));
+                blocks.push(Block::Heading {
+                    level: level as u8,
+                    content,
+                });
+            }
+            Tag::BlockQuote(kind) => {
+                let inner = self.blocks_until(Some(TagEnd::BlockQuote(kind)));
+                blocks.push(Block::BlockQuote(inner));
+            }
+            Tag::CodeBlock(kind) => {
+                let lang = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().unwrap_or("").to_string()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
+                let code = self.text_until(TagEnd::CodeBlock);
+                blocks.extend(fenced_block(lang, code));
+            }
+            Tag::HtmlBlock => {
+                let html = self.text_until(TagEnd::HtmlBlock);
+                blocks.extend(super::html::parse(&html).blocks);
+            }
+            Tag::List(start) => {
+                let ordered = start.is_some();
+                let items = self.list_items(ordered);
+                blocks.push(Block::List(List { start, items }));
+            }
+            Tag::Table(alignments) => {
+                let table = self.table(alignments.into_iter().map(alignment).collect());
+                blocks.push(Block::Table(table));
+            }
+            Tag::MetadataBlock(kind) => {
+                let text = self.text_until(TagEnd::MetadataBlock(kind));
+                self.metadata.extend(front_matter(&text, kind));
+            }
+            Tag::FootnoteDefinition(label) => {
+                let range = self.position.clone();
+                let inner = self.blocks_until(Some(TagEnd::FootnoteDefinition));
+                let footnote = Block::Footnote {
+                    label: label.to_string(),
+                    blocks: inner,
+                };
+                self.footnotes.push((footnote, range));
+            }
+            Tag::DefinitionList => {
+                blocks.extend(self.blocks_until(Some(TagEnd::DefinitionList)));
+            }
+            Tag::DefinitionListTitle => {
+                let content = self.inlines_until(TagEnd::DefinitionListTitle);
+                blocks.push(Block::Paragraph(vec![Inline::Strong(content)]));
+            }
+            Tag::DefinitionListDefinition => {
+                let inner = self.blocks_until(Some(TagEnd::DefinitionListDefinition));
+                blocks.push(Block::BlockQuote(inner));
+            }
+            // Items, table parts and inline tags are consumed by their parents
+            _ => {}
+        }
+    }
+
+    /// Paragraph content; `$$...$$` splits it into a display math block
+    fn paragraph(&mut self, blocks: &mut Vec<Block>) {
+        let mut pending = Vec::new();
+
+        while let Some(event) = self.next() {
+            match event {
+                Event::End(TagEnd::Paragraph) => break,
+                Event::DisplayMath(tex) => {
+                    flush_paragraph(&mut pending, blocks);
+                    blocks.push(Block::Math(tex.trim().to_string()));
+                }
+                event => self.inline(event, &mut pending),
+            }
+        }
+
+        flush_paragraph(&mut pending, blocks);
+    }
+
+    fn list_items(&mut self, ordered: bool) -> Vec<ListItem> {
+        let mut items = Vec::new();
+
+        while let Some(event) = self.next() {
+            match event {
+                Event::Start(Tag::Item) => {
+                    self.task_markers.push(None);
+                    let blocks = self.blocks_until(Some(TagEnd::Item));
+                    let marker = self.task_markers.pop().flatten();
+                    items.push(ListItem {
+                        checked: marker.map(|(checked, _)| checked),
+                        task_offset: marker.map(|(_, offset)| offset),
+                        blocks,
+                    });
+                }
+                Event::End(TagEnd::List(o)) if o == ordered => break,
+                _ => {}
+            }
+        }
+
+        items
+    }
+
+    fn table(&mut self, alignments: Vec<Alignment>) -> Table {
+        let mut table = Table {
+            alignments,
+            ..Default::default()
+        };
+        let mut row = Vec::new();
+
+        while let Some(event) = self.next() {
+            match event {
+                Event::Start(Tag::TableCell) => row.push(self.inlines_until(TagEnd::TableCell)),
+                Event::End(TagEnd::TableHead) => table.header = std::mem::take(&mut row),
+                Event::End(TagEnd::TableRow) => table.rows.push(std::mem::take(&mut row)),
+                Event::End(TagEnd::Table) => break,
+                _ => {}
+            }
+        }
+
+        table
+    }
+
+    fn inlines_until(&mut self, end: TagEnd) -> Vec<Inline> {
+        let mut inlines = Vec::new();
+
+        while let Some(event) = self.next() {
+            match event {
+                Event::End(tag) if tag == end => break,
+                event => self.inline(event, &mut inlines),
+            }
+        }
+
+        merge_text(inlines)
+    }
+
+    fn inline(&mut self, event: Event<'a>, out: &mut Vec<Inline>) {
+        match event {
+            Event::Text(text) => out.push(Inline::Text(text.to_string())),
+            Event::Code(code) => out.push(Inline::Code(code.to_string())),
+            Event::InlineMath(tex) | Event::DisplayMath(tex) => {
+                out.push(Inline::Math(tex.trim().to_string()))
+            }
+            Event::SoftBreak => out.push(Inline::Text(" ".to_string())),
+            Event::HardBreak => out.push(Inline::LineBreak),
+            Event::InlineHtml(html) if is_line_break_tag(&html) => out.push(Inline::LineBreak),
+            // `<img src=... width=...>` is how Markdown sizes images
+            Event::InlineHtml(html) if is_image_tag(&html) => {
+                out.extend(super::html::parse_inline_fragment(&html))
+            }
+            Event::FootnoteReference(label) => {
+                out.push(Inline::FootnoteReference(label.to_string()))
+            }
+            Event::TaskListMarker(checked) => {
+                if let Some(marker) = self.task_markers.last_mut() {
+                    *marker = Some((checked, self.position.start));
+                }
+            }
+            Event::Start(Tag::Emphasis) => {
+                out.push(Inline::Emphasis(self.inlines_until(TagEnd::Emphasis)))
+            }
+            Event::Start(Tag::Strong) => {
+                out.push(Inline::Strong(self.inlines_until(TagEnd::Strong)))
+            }
+            Event::Start(Tag::Strikethrough) => {
+                out.push(Inline::Strikethrough(
+                    self.inlines_until(TagEnd::Strikethrough),
+                ));
+            }
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                let content = self.inlines_until(TagEnd::Link);
+                out.push(Inline::Link {
+                    url: dest_url.to_string(),
+                    content,
+                });
+            }
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                let alt = super::plain_text(&self.inlines_until(TagEnd::Image));
+                out.push(Inline::Image {
+                    src: dest_url.to_string(),
+                    alt,
+                    width: None,
+                    height: None,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Concatenate raw text up to `end` (code blocks, HTML blocks)
+    fn text_until(&mut self, end: TagEnd) -> String {
+        let mut text = String::new();
+
+        while let Some(event) = self.next() {
+            match event {
+                Event::End(tag) if tag == end => break,
+                Event::Text(t) | Event::Html(t) => text.push_str(&t),
+                _ => {}
+            }
+        }
+
+        text
+    }
+}
+
+/// Route a fenced block to the renderer for its language
+fn fenced_block(lang: String, code: String) -> Vec<Block> {
+    match lang.to_ascii_lowercase().as_str() {
+        "mermaid" => super::mermaid::parse(&code).blocks,
+        "math" => vec![Block::Math(code.trim().to_string())],
+        // A whole LaTeX document is source to show, not a formula
+        "latex" | "tex"
+            if !code.contains("\\documentclass") && !code.contains("\\begin{document}") =>
+        {
+            vec![Block::Math(code.trim().to_string())]
+        }
+        _ => vec![Block::CodeBlock { lang, code }],
+    }
+}
+
+fn is_block_tag(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::Paragraph
+            | Tag::Heading { .. }
+            | Tag::BlockQuote(_)
+            | Tag::CodeBlock(_)
+            | Tag::HtmlBlock
+            | Tag::List(_)
+            | Tag::Table(_)
+            | Tag::MetadataBlock(_)
+            | Tag::FootnoteDefinition(_)
+            | Tag::DefinitionList
+            | Tag::DefinitionListTitle
+            | Tag::DefinitionListDefinition
+    )
+}
+
+fn is_image_tag(html: &str) -> bool {
+    html.get(..4)
+        .is_some_and(|tag| tag.eq_ignore_ascii_case("<img"))
+}
+
+fn is_line_break_tag(html: &str) -> bool {
+    let tag: String = html
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '/')
+        .collect();
+    tag.eq_ignore_ascii_case("<br>")
+}
+
+/// Key/value pairs of YAML or TOML front matter
+///
+/// Only the top level is read: nested YAML mappings and TOML tables are
+/// skipped, YAML `- item` lists and TOML arrays are joined with commas.
+fn front_matter(text: &str, kind: MetadataBlockKind) -> Vec<(String, String)> {
+    let separator = match kind {
+        MetadataBlockKind::YamlStyle => ':',
+        MetadataBlockKind::PlusesStyle => '=',
+    };
+    let mut entries: Vec<(String, String)> = Vec::new();
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        // Continuation of a YAML list under the previous key
+        if line.starts_with(char::is_whitespace) {
+            if let (Some(item), Some((_, value))) = (trimmed.strip_prefix("- "), entries.last_mut())
+            {
+                if !value.is_empty() {
+                    value.push_str(", ");
+                }
+                value.push_str(unquote(item.trim()));
+            }
+            continue;
+        }
+
+        // TOML table headers start a nested section
+        if trimmed.starts_with('[') {
+            break;
+        }
+
+        if let Some((key, value)) = trimmed.split_once(separator) {
+            let value = value.trim();
+            let value = match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                Some(items) => items
+                    .split(',')
+                    .map(|item| unquote(item.trim()))
+                    .filter(|item| !item.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                None => unquote(value).to_string(),
+            };
+            entries.push((unquote(key.trim()).to_string(), value));
+        }
+    }
+
+    entries
+}
+
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+        .unwrap_or(value)
+}
+
+fn alignment(alignment: pulldown_cmark::Alignment) -> Alignment {
+    match alignment {
+        pulldown_cmark::Alignment::None => Alignment::None,
+        pulldown_cmark::Alignment::Left => Alignment::Left,
+        pulldown_cmark::Alignment::Center => Alignment::Center,
+        pulldown_cmark::Alignment::Right => Alignment::Right,
+    }
+}
+
+fn flush_paragraph(pending: &mut Vec<Inline>, blocks: &mut Vec<Block>) {
+    let content = merge_text(std::mem::take(pending));
+    let blank = content
+        .iter()
+        .all(|inline| matches!(inline, Inline::Text(t) if t.trim().is_empty()));
+    if !blank {
+        blocks.push(Block::Paragraph(content));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Inline {
+        Inline::Text(s.to_string())
+    }
+
+    #[test]
+    fn test_headings_and_paragraphs() {
+        let doc = parse("# Title\n\nSome *emphasis* and **strong** text.");
+        assert_eq!(
+            doc.blocks,
+            vec![
+                Block::Heading {
+                    level: 1,
+                    content: vec![text("Title")]
+                },
+                Block::Paragraph(vec![
+                    text("Some "),
+                    Inline::Emphasis(vec![text("emphasis")]),
+                    text(" and "),
+                    Inline::Strong(vec![text("strong")]),
+                    text(" text."),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_and_task_lists() {
+        let doc = parse("- [x] done\n- [ ] todo\n  - child\n");
+        let Block::List(list) = &doc.blocks[0] else {
+            panic!("expected list");
+        };
+        assert_eq!(list.start, None);
+        assert_eq!(list.items[0].checked, Some(true));
+        assert_eq!(list.items[1].checked, Some(false));
+        assert!(matches!(list.items[1].blocks[1], Block::List(_)));
+    }
+
+    #[test]
+    fn test_ordered_list_start() {
+        let doc = parse("3. three\n4. four\n");
+        let Block::List(list) = &doc.blocks[0] else {
+            panic!("expected list");
+        };
+        assert_eq!(list.start, Some(3));
+        assert_eq!(list.items.len(), 2);
+    }
+
+    #[test]
+    fn test_code_block_language() {
+        let doc = parse("```rust ignore\nfn main() {}\n```\n");
+        assert_eq!(
+            doc.blocks,
+            vec![Block::CodeBlock {
+                lang: "rust".to_string(),
+                code: "fn main() {}\n".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_fenced_diagrams_and_math() {
+        let doc = parse(
+            "```mermaid\ngraph LR\n  A --> B\n```\n\n```math\n\\frac{a}{b}\n```\n\n```latex\n\\documentclass{article}\n```\n",
+        );
+        assert_eq!(doc.blocks.len(), 3);
+        assert!(matches!(&doc.blocks[0], Block::Diagram(d) if d.nodes.len() == 2));
+        assert_eq!(doc.blocks[1], Block::Math("\\frac{a}{b}".to_string()));
+        assert!(matches!(&doc.blocks[2], Block::CodeBlock { lang, .. } if lang == "latex"));
+        assert_eq!(doc.source_map.len(), 3);
+
+        // Gantt charts expand into several blocks
+        let doc = parse("```mermaid\ngantt\n  section A\n  Task :a1, 2024-01-01, 3d\n```\n");
+        assert!(doc.blocks.len() > 1);
+        assert_eq!(doc.source_map.len(), doc.blocks.len());
+    }
+
+    #[test]
+    fn test_table_alignment() {
+        let doc = parse("| a | b |\n|:--|--:|\n| 1 | 2 |\n");
+        let Block::Table(table) = &doc.blocks[0] else {
+            panic!("expected table");
+        };
+        assert_eq!(table.alignments, vec![Alignment::Left, Alignment::Right]);
+        assert_eq!(table.header, vec![vec![text("a")], vec![text("b")]]);
+        assert_eq!(table.rows, vec![vec![vec![text("1")], vec![text("2")]]]);
+    }
+
+    #[test]
+    fn test_links_images_and_math() {
+        let doc = parse("[site](https://example.com) ![logo](logo.png) $x^2$\n\n$$\ny = mx\n$$\n");
+        assert_eq!(
+            doc.blocks[0],
+            Block::Paragraph(vec![
+                Inline::Link {
+                    url: "https://example.com".to_string(),
+                    content: vec![text("site")]
+                },
+                text(" "),
+                Inline::Image {
+                    src: "logo.png".to_string(),
+                    alt: "logo".to_string(),
+                    width: None,
+                    height: None,
+                },
+                text(" "),
+                Inline::Math("x^2".to_string()),
+            ])
+        );
+        assert_eq!(doc.blocks[1], Block::Math("y = mx".to_string()));
+    }
+
+    #[test]
+    fn test_task_offsets_toggle_source() {
+        let mut source = "- [ ] todo\n- [x] done\n".to_string();
+        let doc = parse(&source);
+        let Block::List(list) = &doc.blocks[0] else {
+            panic!("expected list");
+        };
+        let offsets: Vec<_> = list.items.iter().map(|item| item.task_offset).collect();
+        assert_eq!(offsets, vec![Some(2), Some(13)]);
+
+        assert!(toggle_task(&mut source, 2));
+        assert!(toggle_task(&mut source, 13));
+        assert_eq!(source, "- [x] todo\n- [ ] done\n");
+        assert!(!toggle_task(&mut source, 0));
+    }
+
+    #[test]
+    fn test_front_matter() {
+        let doc = parse("---\ntitle: \"Notes\"\ntags:\n  - a\n  - b\n---\n\n# Body\n");
+        assert_eq!(
+            doc.metadata,
+            vec![
+                ("title".to_string(), "Notes".to_string()),
+                ("tags".to_string(), "a, b".to_string()),
+            ]
+        );
+        assert_eq!(doc.blocks.len(), 1);
+
+        let doc = parse("+++\ntitle = 'Notes'\ntags = [\"a\", \"b\"]\n[extra]\nx = 1\n+++\n");
+        assert_eq!(
+            doc.metadata,
+            vec![
+                ("title".to_string(), "Notes".to_string()),
+                ("tags".to_string(), "a, b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_footnotes_move_to_end() {
+        let doc = parse("Text[^1] here.\n\n[^1]: The note.\n\nMore.\n");
+        assert_eq!(
+            doc.blocks,
+            vec![
+                Block::Paragraph(vec![
+                    text("Text"),
+                    Inline::FootnoteReference("1".to_string()),
+                    text(" here."),
+                ]),
+                Block::Paragraph(vec![text("More.")]),
+                Block::Footnote {
+                    label: "1".to_string(),
+                    blocks: vec![Block::Paragraph(vec![text("The note.")])],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_source_map() {
+        let source = "# Title\n\nText[^n].\n\n- a\n- b\n\n[^n]: Note.\n";
+        let doc = parse(source);
+        assert_eq!(doc.source_map.len(), doc.blocks.len());
+        let sources: Vec<&str> = doc
+            .source_map
+            .iter()
+            .map(|range| source[range.clone()].trim_end())
+            .collect();
+        assert_eq!(
+            sources,
+            vec!["# Title", "Text[^n].", "- a\n- b", "[^n]: Note."]
+        );
+        assert_eq!(doc.block_at(source.find("- b").unwrap()), Some(2));
+    }
+
+    #[test]
+    fn test_html_block_is_parsed() {
+        let doc = parse("<div>\n<p>inside</p>\n</div>\n");
+        assert_eq!(doc.blocks, vec![Block::Paragraph(vec![text("inside")])]);
+    }
+
+    #[test]
+    fn test_inline_img_tag_keeps_size() {
+        let doc =
+            parse("Logo: <img src=\"logo.svg\" alt=\"Logo\" width=\"120px\" height=\"40%\">\n");
+        assert_eq!(
+            doc.blocks,
+            vec![Block::Paragraph(vec![
+                text("Logo: "),
+                Inline::Image {
+                    src: "logo.svg".to_string(),
+                    alt: "Logo".to_string(),
+                    width: Some(120),
+                    height: None,
+                },
+            ])]
+        );
+    }
+}
@@ -0,0 +1,212 @@
+//! Mermaid → [`Document`] parser
+//!
+//! Graph-like diagrams (flowcharts, sequence, class, state and ER
+//! diagrams) are parsed by [`crate::mermaid`] into a [`Block::Diagram`].
+//! Gantt and pie charts are data rather than graphs, so they map onto
+//! headings, lists and tables. Anything unrecognised is kept as a `mermaid` code block.
+
+use regex::Regex;
+
+use super::{Alignment, Block, Document, Inline, List, ListItem, Table};
+
+pub use crate::mermaid::{Diagram, DiagramKind};
+
+/// Parse a Mermaid diagram
+pub fn parse(source: &str) -> Document {
+    if let Some(diagram) = crate::mermaid::parse(source) {
+        return Document {
+            blocks: vec![Block::Diagram(diagram)],
+            ..Default::default()
+        };
+    }
+
+    let lines: Vec<&str> = crate::mermaid::parser::statements(source)
+        .into_iter()
+        .map(|statement| statement.text)
+        .collect();
+    let header = lines.first().copied().unwrap_or("");
+    let body = lines.get(1..).unwrap_or_default();
+    let keyword = header.split_whitespace().next().unwrap_or("");
+
+    let blocks = match keyword {
+        "gantt" => gantt(body),
+        "pie" => pie(header, body),
+        _ => vec![Block::CodeBlock {
+            lang: "mermaid".to_string(),
+            code: source.to_string(),
+        }],
+    };
+
+    Document {
+        blocks,
+        ..Default::default()
+    }
+}
+
+fn gantt(body: &[&str]) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut items = Vec::new();
+
+    let flush = |items: &mut Vec<ListItem>, blocks: &mut Vec<Block>| {
+        if !items.is_empty() {
+            blocks.push(Block::List(List {
+                start: None,
+                items: std::mem::take(items),
+            }));
+        }
+    };
+
+    for line in body {
+        if let Some(title) = line.strip_prefix("title") {
+            blocks.push(heading(1, title.trim()));
+        } else if let Some(section) = line.strip_prefix("section") {
+            flush(&mut items, &mut blocks);
+            blocks.push(heading(2, section.trim()));
+        } else if let Some((task, details)) = line.split_once(':') {
+            items.push(ListItem {
+                checked: None,
+                task_offset: None,
+                blocks: vec![Block::Paragraph(vec![
+                    Inline::Strong(vec![Inline::Text(task.trim().to_string())]),
+                    Inline::Text(format!(": {}", details.trim())),
+                ])],
+            });
+        }
+    }
+
+    flush(&mut items, &mut blocks);
+    blocks
+}
+
+fn pie(header: &str, body: &[&str]) -> Vec<Block> {
+    let slice_re = Regex::new(r#"^"([^"]+)"\s*:\s*([\d.]+)"#).unwrap();
+    let mut blocks = Vec::new();
+
+    let title = header
+        .split_once("title")
+        .map(|(_, title)| title.trim().to_string())
+        .or_else(|| {
+            body.iter()
+                .find_map(|line| line.strip_prefix("title"))
+                .map(|title| title.trim().to_string())
+        });
+    if let Some(title) = title {
+        blocks.push(heading(1, &title));
+    }
+
+    let slices: Vec<(String, f64)> = body
+        .iter()
+        .filter_map(|line| slice_re.captures(line))
+        .map(|cap| (cap[1].to_string(), cap[2].parse().unwrap_or(0.0)))
+        .collect();
+    let total: f64 = slices.iter().map(|(_, value)| value).sum();
+
+    let cell = |text: String| vec![Inline::Text(text)];
+    blocks.push(Block::Table(Table {
+        alignments: vec![Alignment::Left, Alignment::Right, Alignment::Left],
+        header: vec![
+            cell("Label".into()),
+            cell("Value".into()),
+            cell("Share".into()),
+        ],
+        rows: slices
+            .iter()
+            .map(|(label, value)| {
+                let share = if total > 0.0 { value / total } else { 0.0 };
+                vec![
+                    cell(label.clone()),
+                    cell(format!("{value}")),
+                    cell(format!(
+                        "{} {:.1}%",
+                        "█".repeat((share * 25.0).round() as usize),
+                        share * 100.0
+                    )),
+                ]
+            })
+            .collect(),
+    }));
+
+    blocks
+}
+
+fn heading(level: u8, text: &str) -> Block {
+    Block::Heading {
+        level,
+        content: vec![Inline::Text(text.to_string())],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagram(source: &str) -> Diagram {
+        match parse(source).blocks.into_iter().next() {
+            Some(Block::Diagram(diagram)) => diagram,
+            other => panic!("expected diagram, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_flowchart_nodes_and_chains() {
+        let d = diagram("graph TD\n  A[Start] --> B{Ok?}\n  B -->|yes| C[Done] --> D\n");
+        assert_eq!(d.kind, DiagramKind::Flowchart);
+        assert_eq!(d.label_of("A"), "Start");
+        assert_eq!(d.label_of("B"), "Ok?");
+        assert_eq!(d.nodes.len(), 4);
+        assert_eq!(d.edges.len(), 3);
+        assert_eq!(d.edges[1].label.as_deref(), Some("yes"));
+        assert_eq!(
+            (d.edges[2].from.as_str(), d.edges[2].to.as_str()),
+            ("C", "D")
+        );
+    }
+
+    #[test]
+    fn test_sequence_messages() {
+        let d = diagram(
+            "sequenceDiagram\n  participant A as Alice\n  A->>B: Hello\n  B-->>A: Hi back\n",
+        );
+        assert_eq!(d.label_of("A"), "Alice");
+        assert_eq!(d.edges.len(), 2);
+        assert_eq!(d.edges[1].label.as_deref(), Some("Hi back"));
+    }
+
+    #[test]
+    fn test_class_members_and_relations() {
+        let d = diagram(
+            "classDiagram\n  class Animal {\n    +String name\n    +eat()\n  }\n  Animal <|-- Dog\n",
+        );
+        assert_eq!(d.nodes[0].details, vec!["+String name", "+eat()"]);
+        assert_eq!(
+            (d.edges[0].from.as_str(), d.edges[0].to.as_str()),
+            ("Animal", "Dog")
+        );
+        assert_eq!(d.edges[0].start, crate::mermaid::Marker::Triangle);
+    }
+
+    #[test]
+    fn test_state_and_er() {
+        let d = diagram("stateDiagram-v2\n  [*] --> Idle\n  Idle --> Busy : start\n");
+        assert_eq!(d.nodes[0].shape, crate::mermaid::NodeShape::Start);
+        assert_eq!(d.edges[1].label.as_deref(), Some("start"));
+
+        let d =
+            diagram("erDiagram\n  USER ||--o{ ORDER : places\n  USER {\n    string name\n  }\n");
+        assert_eq!(d.edges[0].label.as_deref(), Some("places"));
+        assert_eq!(d.nodes[0].details, vec!["string name"]);
+    }
+
+    #[test]
+    fn test_pie_and_unknown() {
+        let doc = parse("pie title Pets\n  \"Dogs\" : 3\n  \"Cats\" : 1\n");
+        assert!(matches!(&doc.blocks[0], Block::Heading { .. }));
+        let Block::Table(table) = &doc.blocks[1] else {
+            panic!("expected table");
+        };
+        assert_eq!(table.rows.len(), 2);
+
+        let doc = parse("journey\n  title x\n");
+        assert!(matches!(&doc.blocks[0], Block::CodeBlock { lang, .. } if lang == "mermaid"));
+    }
+}
@@ -0,0 +1,257 @@
+//! # Document model
+//!
+//! Format-independent representation of a rendered document. Parsers turn
+//! source formats into a [`Document`], and rendering backends consume it,
+//! so every preview shares the same notion of headings, lists, tables,
+//! links, math and code. A difference between two backends is a bug in
+//! one of them, not a design decision.
+//!
+//! ```text
+//! Markdown ─▶ markdown::parse ─┐              ┌─▶ terminal::render    (lala markdown, ...)
+//! HTML     ─▶ html::parse     ─┤              ├─▶ plain::render       (--no-color)
+//! LaTeX    ─▶ latex::parse    ─┼─▶ Document ─┼─▶ html_writer::render (HTML output)
+//! Mermaid  ─▶ mermaid::parse  ─┘              └─▶ gui::document_view  (preview pane)
+//! ```
+
+pub mod graphics;
+pub mod html;
+pub mod html_writer;
+pub mod image;
+pub mod latex;
+pub mod links;
+pub mod markdown;
+pub mod mermaid;
+pub mod plain;
+pub mod terminal;
+
+pub use mermaid::Diagram;
+
+use std::ops::Range;
+
+/// A parsed document
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Document {
+    /// Document title (e.g. HTML `<title>`)
+    pub title: Option<String>,
+    /// Key/value information shown under the title (author, date, ...)
+    pub metadata: Vec<(String, String)>,
+    pub blocks: Vec<Block>,
+    /// Source byte range of each top-level block, parallel to `blocks`
+    ///
+    /// Empty when the parser does not track positions. Used to keep an
+    /// editor and its preview in sync.
+    pub source_map: Vec<Range<usize>>,
+}
+
+impl Document {
+    /// Index of the top-level block at (or last starting before) `offset`
+    pub fn block_at(&self, offset: usize) -> Option<usize> {
+        match self
+            .source_map
+            .partition_point(|range| range.start <= offset)
+        {
+            0 => None,
+            index => Some(index - 1),
+        }
+    }
+}
+
+/// Block-level element
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Heading {
+        level: u8,
+        content: Vec<Inline>,
+    },
+    Paragraph(Vec<Inline>),
+    List(List),
+    CodeBlock {
+        lang: String,
+        code: String,
+    },
+    BlockQuote(Vec<Block>),
+    Table(Table),
+    /// Display math in TeX notation
+    Math(String),
+    Diagram(Diagram),
+    Rule,
+    /// Footnote definition, referenced by [`Inline::FootnoteReference`]
+    Footnote {
+        label: String,
+        blocks: Vec<Block>,
+    },
+}
+
+/// Ordered or unordered list
+#[derive(Debug, Clone, PartialEq)]
+pub struct List {
+    /// First number of an ordered list, `None` for bullet lists
+    pub start: Option<u64>,
+    pub items: Vec<ListItem>,
+}
+
+/// List item; `checked` is set for task list items
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListItem {
+    pub checked: Option<bool>,
+    /// Byte offset of the `[ ]` / `[x]` marker in the source, when known
+    pub task_offset: Option<usize>,
+    pub blocks: Vec<Block>,
+}
+
+/// Column alignment of a table
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Alignment {
+    #[default]
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+/// Table with an optional header row
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Table {
+    pub alignments: Vec<Alignment>,
+    pub header: Vec<Vec<Inline>>,
+    pub rows: Vec<Vec<Vec<Inline>>>,
+}
+
+impl Table {
+    /// Number of columns across header and body rows
+    pub fn column_count(&self) -> usize {
+        self.rows
+            .iter()
+            .map(Vec::len)
+            .chain(std::iter::once(self.header.len()))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Inline (span-level) element
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inline {
+    Text(String),
+    Code(String),
+    Emphasis(Vec<Inline>),
+    Strong(Vec<Inline>),
+    Strikethrough(Vec<Inline>),
+    Link {
+        url: String,
+        content: Vec<Inline>,
+    },
+    Image {
+        src: String,
+        alt: String,
+        /// Requested display size in CSS pixels (`<img width>`,
+        /// `\includegraphics[width=...]`); the natural size when `None`
+        width: Option<u32>,
+        height: Option<u32>,
+    },
+    /// Inline math in TeX notation
+    Math(String),
+    /// Reference to the [`Block::Footnote`] with this label
+    FootnoteReference(String),
+    LineBreak,
+}
+
+/// Concatenate the plain text of inline content
+pub fn plain_text(inlines: &[Inline]) -> String {
+    let mut text = String::new();
+    push_plain_text(inlines, &mut text);
+    text
+}
+
+fn push_plain_text(inlines: &[Inline], out: &mut String) {
+    for inline in inlines {
+        match inline {
+            Inline::Text(t) | Inline::Code(t) | Inline::Math(t) => out.push_str(t),
+            Inline::Emphasis(c) | Inline::Strong(c) | Inline::Strikethrough(c) => {
+                push_plain_text(c, out)
+            }
+            Inline::Link { content, .. } => push_plain_text(content, out),
+            Inline::Image { alt, .. } => out.push_str(alt),
+            Inline::FootnoteReference(label) => {
+                out.push('[');
+                out.push_str(label);
+                out.push(']');
+            }
+            Inline::LineBreak => out.push('\n'),
+        }
+    }
+}
+
+/// Merge adjacent text runs and drop empty ones
+pub(crate) fn merge_text(inlines: Vec<Inline>) -> Vec<Inline> {
+    let mut merged: Vec<Inline> = Vec::with_capacity(inlines.len());
+    for inline in inlines {
+        match (merged.last_mut(), inline) {
+            (_, Inline::Text(text)) if text.is_empty() => {}
+            (Some(Inline::Text(last)), Inline::Text(text)) => last.push_str(&text),
+            (_, inline) => merged.push(inline),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_text_nested() {
+        let inlines = vec![
+            Inline::Text("a ".to_string()),
+            Inline::Strong(vec![Inline::Emphasis(vec![Inline::Text("b".to_string())])]),
+            Inline::Link {
+                url: "x".to_string(),
+                content: vec![Inline::Code(" c".to_string())],
+            },
+        ];
+        assert_eq!(plain_text(&inlines), "a b c");
+    }
+
+    #[test]
+    fn test_merge_text() {
+        let merged = merge_text(vec![
+            Inline::Text("a".to_string()),
+            Inline::Text(String::new()),
+            Inline::Text("b".to_string()),
+            Inline::LineBreak,
+            Inline::Text("c".to_string()),
+        ]);
+        assert_eq!(
+            merged,
+            vec![
+                Inline::Text("ab".to_string()),
+                Inline::LineBreak,
+                Inline::Text("c".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_block_at() {
+        let doc = Document {
+            blocks: vec![Block::Rule; 3],
+            source_map: vec![2..5, 7..9, 9..12],
+            ..Default::default()
+        };
+        assert_eq!(doc.block_at(0), None);
+        assert_eq!(doc.block_at(2), Some(0));
+        assert_eq!(doc.block_at(6), Some(0));
+        assert_eq!(doc.block_at(9), Some(2));
+        assert_eq!(doc.block_at(100), Some(2));
+    }
+
+    #[test]
+    fn test_table_column_count() {
+        let table = Table {
+            alignments: vec![],
+            header: vec![vec![]; 2],
+            rows: vec![vec![vec![]; 3]],
+        };
+        assert_eq!(table.column_count(), 3);
+    }
+}
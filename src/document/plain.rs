@@ -0,0 +1,52 @@
+//! Plain text backend for [`Document`]
+//!
+//! Uses the terminal layout with all styling removed, so `--no-color`
+//! output lines up exactly with the colored rendering.
+
+use super::{terminal, Document};
+
+/// Render a document as plain text
+pub fn render(doc: &Document) -> String {
+    // Without terminal graphics: image escapes are not ANSI styling
+    strip_ansi(&terminal::render_with_width(
+        doc,
+        terminal::terminal_width(),
+    ))
+}
+
+/// Render a document as plain text for a given terminal width
+pub fn render_with_width(doc: &Document, width: usize) -> String {
+    strip_ansi(&terminal::render_with_width(doc, width))
+}
+
+/// Remove ANSI SGR escape sequences (`ESC [ ... m`)
+pub fn strip_ansi(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' && chars.peek() == Some(&'[') {
+            chars.next();
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("\x1b[1;34mTitle\x1b[0m plain"), "Title plain");
+        assert_eq!(strip_ansi("no escapes"), "no escapes");
+    }
+}
@@ -0,0 +1,712 @@
+//! Terminal (ANSI) backend for [`Document`]
+//!
+//! Produces colored text with the same conventions the CLI viewers have
+//! always used: underlined headings, `•` bullets, shaded code blocks and
+//! link targets in parentheses. Terminals on standard output get OSC 8
+//! hyperlinks instead, so links can be clicked.
+
+use colored::*;
+use std::io::IsTerminal;
+use std::path::Path;
+use unicode_width::UnicodeWidthStr;
+
+use super::graphics::{Graphics, CELL_WIDTH};
+use super::links::{self, Target};
+use super::{image, plain_text, Alignment, Block, Diagram, Document, Inline, List, Table};
+use crate::math;
+use crate::mermaid::grid::{self, Ink};
+use crate::mermaid::{DiagramKind, NodeShape};
+
+/// How [`render_with`] lays out a document
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options<'a> {
+    /// Terminal width in columns
+    pub width: usize,
+    /// Directory relative image paths are resolved against
+    pub base_dir: Option<&'a Path>,
+    /// How images are drawn; alt text and path with [`Graphics::None`]
+    pub graphics: Graphics,
+    /// Emit links as OSC 8 hyperlinks instead of `text (url)`
+    pub hyperlinks: bool,
+}
+
+impl<'a> Options<'a> {
+    /// Options for the terminal on standard output
+    pub fn detect(base_dir: Option<&'a Path>) -> Self {
+        Options {
+            width: terminal_width(),
+            base_dir,
+            graphics: Graphics::detect(),
+            hyperlinks: std::io::stdout().is_terminal()
+                && std::env::var("TERM").is_ok_and(|term| term != "dumb"),
+        }
+    }
+
+    fn narrower(self, columns: usize) -> Self {
+        Options {
+            width: self.width.saturating_sub(columns),
+            ..self
+        }
+    }
+}
+
+/// Render a document to an ANSI-colored string, sized to the terminal
+pub fn render(doc: &Document) -> String {
+    render_with(doc, &Options::detect(None))
+}
+
+/// Width of the attached terminal, or 80 columns when not a terminal
+pub fn terminal_width() -> usize {
+    terminal_size::terminal_size()
+        .map(|(terminal_size::Width(width), _)| width as usize)
+        .unwrap_or(80)
+}
+
+/// Render a document for a terminal `width` columns wide, without images
+/// or hyperlinks
+///
+/// Only diagrams depend on the width: they are drawn in two dimensions
+/// when they fit and listed vertically otherwise.
+pub fn render_with_width(doc: &Document, width: usize) -> String {
+    render_with(
+        doc,
+        &Options {
+            width,
+            ..Options::default()
+        },
+    )
+}
+
+/// Render a document with explicit [`Options`]
+pub fn render_with(doc: &Document, options: &Options) -> String {
+    let mut output = String::new();
+
+    if let Some(title) = &doc.title {
+        output.push_str(&format!("{}\n", title.bold().bright_blue().underline()));
+        output.push_str(&format!("{}\n\n", "=".repeat(title.width()).bright_blue()));
+    }
+
+    for (key, value) in &doc.metadata {
+        output.push_str(&format!("{} {value}\n", format!("{key}:").bold()));
+    }
+    if !doc.metadata.is_empty() {
+        output.push('\n');
+    }
+
+    render_blocks(&doc.blocks, options, &mut output);
+    output
+}
+
+fn render_blocks(blocks: &[Block], options: &Options, output: &mut String) {
+    for block in blocks {
+        render_block(block, options, output);
+    }
+}
+
+fn render_block(block: &Block, options: &Options, output: &mut String) {
+    match block {
+        Block::Heading { level, content } => {
+            let text = render_inlines(content, Style::default(), options);
+            let width = plain_text(content).width();
+            output.push('\n');
+            match level {
+                1 => {
+                    output.push_str(&format!("{}\n", text.bold().bright_blue().underline()));
+                    output.push_str(&format!("{}\n", "=".repeat(width).bright_blue()));
+                }
+                2 => {
+                    output.push_str(&format!("{}\n", text.bold().bright_cyan()));
+                    output.push_str(&format!("{}\n", "-".repeat(width).bright_cyan()));
+                }
+                3 => output.push_str(&format!("{}\n", text.bold().green())),
+                4 => output.push_str(&format!("{}\n", text.bold().yellow())),
+                5 => output.push_str(&format!("{}\n", text.yellow())),
+                _ => output.push_str(&format!("{}\n", text.dimmed())),
+            }
+            output.push('\n');
+        }
+
+        Block::Paragraph(content) if render_pictures(content, options, output) => {
+            output.push('\n');
+        }
+
+        Block::Paragraph(content) => {
+            output.push_str(&render_inlines(content, Style::default(), options));
+            output.push_str("\n\n");
+        }
+
+        Block::List(list) => {
+            render_list(list, 1, options, output);
+            output.push('\n');
+        }
+
+        Block::CodeBlock { lang, code } => {
+            output.push('\n');
+            output.push_str(&format!("{}\n", format!("```{lang}").dimmed()));
+            for line in code.lines() {
+                output.push_str(&format!("{}\n", line.on_truecolor(40, 44, 52)));
+            }
+            output.push_str(&format!("{}\n\n", "```".dimmed()));
+        }
+
+        Block::BlockQuote(blocks) => {
+            let mut inner = String::new();
+            render_blocks(blocks, &options.narrower(2), &mut inner);
+            for line in inner.trim_end().lines() {
+                if line.is_empty() {
+                    output.push_str(&format!("{}\n", "│".bright_black()));
+                } else {
+                    output.push_str(&format!("{} {}\n", "│".bright_black(), line.italic()));
+                }
+            }
+            output.push('\n');
+        }
+
+        Block::Table(table) => {
+            render_table(table, output);
+            output.push('\n');
+        }
+
+        Block::Math(tex) => {
+            for line in math::grid::render(tex, true) {
+                output.push_str(&format!("    {}\n", line.bright_cyan()));
+            }
+            output.push('\n');
+        }
+
+        Block::Diagram(diagram) => {
+            render_diagram(diagram, options.width, output);
+            output.push('\n');
+        }
+
+        Block::Rule => {
+            output.push_str(&format!("{}\n\n", "─".repeat(80).bright_black()));
+        }
+
+        Block::Footnote { label, blocks } => {
+            let marker = footnote_marker(label);
+            let mut inner = String::new();
+            render_blocks(blocks, options, &mut inner);
+            for (index, line) in inner.trim_matches('\n').lines().enumerate() {
+                if index == 0 {
+                    output.push_str(&format!("{} {line}\n", marker.bright_blue()));
+                } else if line.is_empty() {
+                    output.push('\n');
+                } else {
+                    output.push_str(&format!("{}{line}\n", " ".repeat(marker.width() + 1)));
+                }
+            }
+            output.push('\n');
+        }
+    }
+}
+
+fn render_diagram(diagram: &Diagram, width: usize, output: &mut String) {
+    output.push_str(&format!(
+        "{}\n\n",
+        format!("{}:", diagram.kind.label()).bold().green()
+    ));
+
+    // Indented by two columns like the listing below
+    if let Some(grid) = grid::render(diagram, width.saturating_sub(2)) {
+        for line in grid.lines() {
+            output.push_str("  ");
+            for (ink, text) in line {
+                let text = match ink {
+                    Ink::Plain => text.normal(),
+                    Ink::Border => text.bright_blue(),
+                    Ink::Text => text.bold(),
+                    Ink::Edge => text.bright_yellow(),
+                    Ink::Label => text.italic(),
+                    Ink::Frame => text.bright_black(),
+                };
+                output.push_str(&text.to_string());
+            }
+            output.push('\n');
+        }
+        return;
+    }
+
+    if diagram.kind == DiagramKind::Sequence {
+        let participants: Vec<String> = diagram
+            .nodes
+            .iter()
+            .map(|node| format!("{}", node.label.bold().cyan()))
+            .collect();
+        output.push_str(&format!("  {}\n\n", participants.join("  ")));
+    } else {
+        let boxes = diagram
+            .nodes
+            .iter()
+            .filter(|node| !matches!(node.shape, NodeShape::Start | NodeShape::End));
+        for node in boxes {
+            let width = std::iter::once(&node.label)
+                .chain(&node.details)
+                .map(|line| line.width())
+                .max()
+                .unwrap_or(0);
+            output.push_str(&format!(
+                "  {}\n",
+                format!("┌{}┐", "─".repeat(width + 2)).bright_blue()
+            ));
+            output.push_str(&format!(
+                "  {} {} {}\n",
+                "│".bright_blue(),
+                pad(&node.label, width, Alignment::Left).bold(),
+                "│".bright_blue()
+            ));
+            if !node.details.is_empty() {
+                output.push_str(&format!(
+                    "  {}\n",
+                    format!("├{}┤", "─".repeat(width + 2)).bright_blue()
+                ));
+                for detail in &node.details {
+                    output.push_str(&format!(
+                        "  {} {} {}\n",
+                        "│".bright_blue(),
+                        pad(detail, width, Alignment::Left).dimmed(),
+                        "│".bright_blue()
+                    ));
+                }
+            }
+            output.push_str(&format!(
+                "  {}\n",
+                format!("└{}┘", "─".repeat(width + 2)).bright_blue()
+            ));
+        }
+        output.push('\n');
+    }
+
+    for edge in &diagram.edges {
+        output.push_str(&format!(
+            "  {} {} {}",
+            node_name(diagram, &edge.from).bright_green(),
+            "─→".bright_yellow(),
+            node_name(diagram, &edge.to).bright_green()
+        ));
+        if let Some(label) = &edge.label {
+            output.push_str(&format!(": {}", label.italic()));
+        }
+        output.push('\n');
+    }
+}
+
+/// Node label, with state start/end points shown as symbols
+fn node_name<'a>(diagram: &'a Diagram, id: &'a str) -> &'a str {
+    match diagram.node_by_id(id).map(|node| node.shape) {
+        Some(NodeShape::Start) => "●",
+        Some(NodeShape::End) => "◉",
+        _ => diagram.label_of(id),
+    }
+}
+
+/// Draw a paragraph of images with terminal graphics
+///
+/// Returns `false`, leaving the paragraph to the text renderer, when the
+/// terminal has no graphics or the paragraph has text besides images.
+fn render_pictures(content: &[Inline], options: &Options, output: &mut String) -> bool {
+    let only_images = content.iter().all(|inline| match inline {
+        Inline::Image { .. } | Inline::LineBreak => true,
+        Inline::Text(text) => text.trim().is_empty(),
+        _ => false,
+    });
+    if options.graphics == Graphics::None || !only_images {
+        return false;
+    }
+
+    for inline in content {
+        let Inline::Image {
+            src,
+            alt,
+            width,
+            height,
+        } = inline
+        else {
+            continue;
+        };
+        let drawn = image::load(src, options.base_dir).ok().and_then(|picture| {
+            let (display_width, _) = picture.size(*width, *height);
+            let columns = (display_width / CELL_WIDTH as f32).ceil() as usize;
+            let columns = columns.clamp(1, options.width.max(1));
+            options.graphics.encode(&picture.pixels, columns)
+        });
+        match drawn {
+            Some(escape) => output.push_str(&escape),
+            None => output.push_str(&format!("{}\n", image_text(src, alt))),
+        }
+    }
+    true
+}
+
+/// Alt text and path, for terminals without graphics
+/// Superscript digits for numbered footnotes, `^label` otherwise, so
+/// footnotes do not read like `[1]` citations
+fn footnote_marker(label: &str) -> String {
+    const SUPERSCRIPTS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+    if !label.is_empty() && label.chars().all(|c| c.is_ascii_digit()) {
+        label
+            .chars()
+            .map(|c| SUPERSCRIPTS[c as usize - '0' as usize])
+            .collect()
+    } else {
+        format!("^{label}")
+    }
+}
+
+fn image_text(src: &str, alt: &str) -> String {
+    format!("{}", format!("[image: {alt}] ({src})").dimmed())
+}
+
+fn render_list(list: &List, depth: usize, options: &Options, output: &mut String) {
+    let indent = "  ".repeat(depth);
+
+    for (index, item) in list.items.iter().enumerate() {
+        let marker = match (item.checked, list.start) {
+            (Some(true), _) => format!("{}", "☑".bright_green()),
+            (Some(false), _) => format!("{}", "☐".bright_black()),
+            (None, Some(start)) => {
+                format!("{}.", (start + index as u64).to_string().bright_yellow())
+            }
+            (None, None) => format!("{}", "•".bright_green()),
+        };
+
+        let mut first_line = true;
+        for block in &item.blocks {
+            match block {
+                Block::List(nested) => render_list(nested, depth + 1, options, output),
+                Block::Paragraph(content) => {
+                    let text = render_inlines(content, Style::default(), options);
+                    if first_line {
+                        output.push_str(&format!("{indent}{marker} {text}\n"));
+                    } else {
+                        output.push_str(&format!("{indent}  {text}\n"));
+                    }
+                    first_line = false;
+                }
+                other => {
+                    if first_line {
+                        output.push_str(&format!("{indent}{marker}\n"));
+                        first_line = false;
+                    }
+                    let mut inner = String::new();
+                    render_block(other, &options.narrower(indent.len() + 2), &mut inner);
+                    for line in inner.trim_matches('\n').lines() {
+                        output.push_str(&format!("{indent}  {line}\n"));
+                    }
+                }
+            }
+        }
+
+        if first_line {
+            output.push_str(&format!("{indent}{marker}\n"));
+        }
+    }
+}
+
+fn render_table(table: &Table, output: &mut String) {
+    let columns = table.column_count();
+    if columns == 0 {
+        return;
+    }
+
+    let cell_text = |cells: &[Vec<Inline>], column: usize| {
+        cells
+            .get(column)
+            .map(|cell| plain_text(cell).replace('\n', " "))
+            .unwrap_or_default()
+    };
+
+    let mut widths = vec![0; columns];
+    for row in std::iter::once(&table.header).chain(&table.rows) {
+        for (column, width) in widths.iter_mut().enumerate() {
+            *width = (*width).max(cell_text(row, column).width());
+        }
+    }
+
+    let format_row = |cells: &[Vec<Inline>]| {
+        let mut row = (0..columns)
+            .map(|column| {
+                let alignment = table.alignments.get(column).copied().unwrap_or_default();
+                pad(&cell_text(cells, column), widths[column], alignment)
+            })
+            .collect::<Vec<_>>();
+        // No trailing padding after the last column
+        if let Some(last) = row.last_mut() {
+            *last = last.trim_end().to_string();
+        }
+        row
+    };
+
+    let separator = format!("{}", " │ ".bright_black());
+
+    if !table.header.is_empty() {
+        let header: Vec<String> = format_row(&table.header)
+            .into_iter()
+            .map(|cell| format!("{}", cell.bold()))
+            .collect();
+        output.push_str(&format!("  {}\n", header.join(&separator)));
+        let rule: Vec<String> = widths.iter().map(|w| "─".repeat(*w)).collect();
+        output.push_str(&format!("  {}\n", rule.join("─┼─").bright_black()));
+    }
+
+    for row in &table.rows {
+        output.push_str(&format!("  {}\n", format_row(row).join(&separator)));
+    }
+}
+
+/// Pad `text` to `width` display columns according to `alignment`
+pub(crate) fn pad(text: &str, width: usize, alignment: Alignment) -> String {
+    let fill = width.saturating_sub(text.width());
+    match alignment {
+        Alignment::Right => format!("{}{text}", " ".repeat(fill)),
+        Alignment::Center => {
+            let left = fill / 2;
+            format!("{}{text}{}", " ".repeat(left), " ".repeat(fill - left))
+        }
+        Alignment::None | Alignment::Left => format!("{text}{}", " ".repeat(fill)),
+    }
+}
+
+/// Inline formatting inherited from enclosing elements
+#[derive(Debug, Clone, Copy, Default)]
+struct Style {
+    bold: bool,
+    italic: bool,
+    strikethrough: bool,
+}
+
+fn render_inlines(inlines: &[Inline], style: Style, options: &Options) -> String {
+    let mut result = String::new();
+
+    for inline in inlines {
+        match inline {
+            Inline::Text(text) => result.push_str(&styled(text, style)),
+            Inline::Code(code) => result.push_str(&format!(
+                "{}",
+                code.bright_magenta().on_truecolor(50, 50, 50)
+            )),
+            Inline::Emphasis(content) => result.push_str(&render_inlines(
+                content,
+                Style {
+                    italic: true,
+                    ..style
+                },
+                options,
+            )),
+            Inline::Strong(content) => result.push_str(&render_inlines(
+                content,
+                Style {
+                    bold: true,
+                    ..style
+                },
+                options,
+            )),
+            Inline::Strikethrough(content) => result.push_str(&render_inlines(
+                content,
+                Style {
+                    strikethrough: true,
+                    ..style
+                },
+                options,
+            )),
+            Inline::Link { url, content } => {
+                let text = plain_text(content);
+                let styled = format!("{}", text.bright_blue().underline());
+                match hyperlink_target(url, options) {
+                    Some(target) => {
+                        result.push_str(&format!("\x1b]8;;{target}\x1b\\{styled}\x1b]8;;\x1b\\"))
+                    }
+                    None => {
+                        result.push_str(&styled);
+                        if &text != url {
+                            result.push_str(&format!("{}", format!(" ({url})").dimmed()));
+                        }
+                    }
+                }
+            }
+            Inline::Image { src, alt, .. } => result.push_str(&image_text(src, alt)),
+            Inline::Math(tex) => {
+                result.push_str(&format!("{}", math::to_unicode(tex).bright_cyan()));
+            }
+            Inline::FootnoteReference(label) => {
+                result.push_str(&format!("{}", footnote_marker(label).bright_blue()));
+            }
+            Inline::LineBreak => result.push('\n'),
+        }
+    }
+
+    result
+}
+
+/// URI for an OSC 8 hyperlink to `url`, with local files made absolute
+///
+/// `None` when hyperlinks are off, and for `#anchor` links, which have
+/// nowhere to go in a terminal.
+fn hyperlink_target(url: &str, options: &Options) -> Option<String> {
+    if !options.hyperlinks {
+        return None;
+    }
+    match links::classify(url, options.base_dir) {
+        Target::Anchor(_) => None,
+        Target::External(url) => Some(url),
+        Target::File { path, anchor } => {
+            let path = std::path::absolute(&path).unwrap_or(path);
+            let fragment = anchor.map(|anchor| format!("#{anchor}"));
+            Some(format!(
+                "file://{}{}",
+                path.display(),
+                fragment.unwrap_or_default()
+            ))
+        }
+    }
+}
+
+fn styled(text: &str, style: Style) -> String {
+    let mut styled = text.normal();
+    if style.bold {
+        styled = styled.bold();
+    }
+    if style.italic {
+        styled = styled.italic();
+    }
+    if style.strikethrough {
+        styled = styled.strikethrough();
+    }
+    format!("{styled}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::{html, plain};
+
+    fn render_plain(html_content: &str) -> String {
+        plain::render(&html::parse(html_content))
+    }
+
+    #[test]
+    fn test_render_in_document_order() {
+        let output = render_plain("<h1>Title</h1><p>Body</p><h2>Next</h2>");
+        let title = output.find("Title").unwrap();
+        let body = output.find("Body").unwrap();
+        let next = output.find("Next").unwrap();
+        assert!(title < body && body < next);
+    }
+
+    #[test]
+    fn test_render_list_markers() {
+        let output = render_plain("<ol start=\"2\"><li>a</li><li>b</li></ol><ul><li>c</li></ul>");
+        assert!(output.contains("2. a"));
+        assert!(output.contains("3. b"));
+        assert!(output.contains("• c"));
+    }
+
+    #[test]
+    fn test_render_table_alignment() {
+        let output = render_plain(
+            "<table><tr><th>Name</th><th align=\"right\">Age</th></tr>\
+             <tr><td>Alice</td><td>7</td></tr></table>",
+        );
+        assert!(output.contains("Name  │ Age"));
+        assert!(output.contains("Alice │   7"));
+    }
+
+    #[test]
+    fn test_render_footnotes() {
+        let output = plain::render(&crate::document::markdown::parse(
+            "Claim[^src].\n\n[^src]: Source.\n",
+        ));
+        assert_eq!(output, "Claim^src.\n\n^src Source.\n\n");
+    }
+
+    #[test]
+    fn test_footnotes_differ_from_citations() {
+        let output = plain::render(&crate::document::latex::parse(
+            "\\begin{document}\nAs \\cite{knuth} shows, it matters\\footnote{Really.}.\n\
+             \\begin{thebibliography}{9}\\bibitem{knuth} Knuth.\\end{thebibliography}\n\
+             \\end{document}\n",
+        ));
+        assert!(output.contains("As [1] shows"), "{output}");
+        assert!(output.contains("matters¹."), "{output}");
+        assert!(output.contains("¹ Really."), "{output}");
+    }
+
+    #[test]
+    fn test_diagram_layout_follows_width() {
+        let doc = crate::document::mermaid::parse("graph LR\n  Start --> Middle --> Finish\n");
+        let wide = plain::render_with_width(&doc, 80);
+        assert!(wide.contains("│ Start ├"), "{wide}");
+        assert!(wide.contains('▶'));
+
+        // Top-down when left-to-right does not fit
+        let narrow = plain::render_with_width(&doc, 20);
+        assert!(narrow.contains('▼'), "{narrow}");
+        assert!(narrow.lines().all(|line| line.width() <= 20));
+
+        // Listed vertically when nothing fits
+        let tiny = plain::render_with_width(&doc, 6);
+        assert!(tiny.contains("Start ─→ Middle"), "{tiny}");
+    }
+
+    #[test]
+    fn test_pad_alignment() {
+        assert_eq!(pad("ab", 4, Alignment::Left), "ab  ");
+        assert_eq!(pad("ab", 4, Alignment::Right), "  ab");
+        assert_eq!(pad("ab", 5, Alignment::Center), " ab  ");
+        assert_eq!(pad("日本", 6, Alignment::Left), "日本  ");
+    }
+
+    #[test]
+    fn test_images_with_and_without_graphics() {
+        let dir = tempfile::tempdir().unwrap();
+        ::image::RgbaImage::new(40, 20)
+            .save(dir.path().join("pic.png"))
+            .unwrap();
+        let doc = crate::document::markdown::parse("![pic](pic.png)\n\nSee ![pic](pic.png).\n");
+
+        let text_only = plain::render_with_width(&doc, 80);
+        assert!(text_only.contains("[image: pic] (pic.png)"));
+
+        let options = Options {
+            width: 80,
+            base_dir: Some(dir.path()),
+            graphics: Graphics::Kitty,
+            hyperlinks: false,
+        };
+        let output = render_with(&doc, &options);
+        // 40px is 4 cells; an image inside text stays text
+        assert!(output.contains("\x1b_Ga=T,f=100,q=2,c=4,"));
+        assert_eq!(output.matches("\x1b_Ga=T").count(), 1);
+        assert!(output.contains("[image: pic] (pic.png)"));
+
+        // Missing files fall back to alt text and path
+        let missing = crate::document::markdown::parse("![gone](gone.png)\n");
+        let output = render_with(&missing, &options);
+        assert!(!output.contains("\x1b_G"));
+        assert!(output.contains("[image: gone] (gone.png)"));
+    }
+
+    #[test]
+    fn test_osc8_hyperlinks() {
+        let doc = crate::document::markdown::parse(
+            "[site](https://example.com), [guide](docs/guide.md#setup) and [top](#top)\n",
+        );
+        let options = Options {
+            width: 80,
+            base_dir: Some(Path::new("/notes")),
+            hyperlinks: true,
+            ..Options::default()
+        };
+        let output = render_with(&doc, &options);
+        assert!(output.contains("\x1b]8;;https://example.com\x1b\\"));
+        assert!(output.contains("\x1b]8;;file:///notes/docs/guide.md#setup\x1b\\"));
+        assert!(!output.contains("(https://example.com)"));
+        // Anchors have no target in a terminal and keep the old form
+        assert!(output.contains("(#top)"));
+        assert_eq!(output.matches("\x1b]8;;\x1b\\").count(), 2);
+
+        let plain = render_with_width(&doc, 80);
+        assert!(!plain.contains("\x1b]8;;"));
+        assert!(plain.contains("(https://example.com)"));
+    }
+}
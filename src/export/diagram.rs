@@ -0,0 +1,267 @@
+//! Mermaid diagrams on pages
+//!
+//! Lays a diagram out with the export fonts and converts the layout into
+//! page shapes, following the SVG writer in [`crate::mermaid::svg`] so the
+//! exported pages and the SVG export draw the same picture.
+
+use crate::mermaid::geometry::{marker, outline, Fill, Primitive};
+use crate::mermaid::layout::{self, Layout, Size, Spacing};
+use crate::mermaid::model::{LineStyle, Marker, NodeShape};
+use crate::mermaid::Diagram;
+
+use super::fonts::Fonts;
+use super::page::{Color, Face, Point, Rect, Shape};
+
+/// Label size in layout units (points before scaling)
+const FONT_SIZE: f32 = 11.0;
+const LINE_HEIGHT: f32 = FONT_SIZE * 1.3;
+
+const STROKE: Color = Color(0x33, 0x33, 0x33);
+/// Cluster yellow at 60% over white, as in the SVG
+const CLUSTER: Color = Color(0xff, 0xff, 0xe5);
+const NOTE: Color = Color(0xff, 0xf5, 0xad);
+
+/// Lay out `diagram`, measuring labels with the export fonts
+pub fn layout(diagram: &Diagram) -> Layout {
+    let fonts = Fonts::shared();
+    let measure = |text: &str| {
+        let lines: Vec<&str> = text.split('\n').collect();
+        let width = lines
+            .iter()
+            .map(|line| fonts.width(line, Face::Regular, FONT_SIZE))
+            .fold(0.0, f32::max);
+        Size::new(width, lines.len() as f32 * LINE_HEIGHT)
+    };
+    layout::layout(diagram, &measure, &Spacing::pixels())
+}
+
+/// Shapes of `layout` drawn at `origin`, scaled by `scale`
+pub fn shapes(layout: &Layout, origin: Point, scale: f32) -> Vec<Shape> {
+    Painter {
+        origin,
+        scale,
+        shapes: Vec::new(),
+    }
+    .paint(layout)
+}
+
+struct Painter {
+    origin: Point,
+    scale: f32,
+    shapes: Vec<Shape>,
+}
+
+impl Painter {
+    fn point(&self, point: layout::Point) -> Point {
+        Point::new(
+            self.origin.x + point.x * self.scale,
+            self.origin.y + point.y * self.scale,
+        )
+    }
+
+    fn rect(&self, rect: layout::Rect) -> Rect {
+        let at = self.point(layout::Point::new(rect.x, rect.y));
+        Rect::new(
+            at.x,
+            at.y,
+            rect.width * self.scale,
+            rect.height * self.scale,
+        )
+    }
+
+    fn paint(mut self, layout: &Layout) -> Vec<Shape> {
+        self.shapes.push(Shape::Rect {
+            rect: self.rect(layout::Rect::new(0.0, 0.0, layout.width, layout.height)),
+            fill: Color::WHITE,
+        });
+
+        for cluster in &layout.clusters {
+            let r = cluster.rect;
+            self.polygon(corners(r), Some(CLUSTER));
+            self.text(&cluster.label, cluster.label_at, true);
+            for (y, label) in &cluster.dividers {
+                let line = vec![
+                    layout::Point::new(r.x, *y),
+                    layout::Point::new(r.right(), *y),
+                ];
+                self.line(&line, 1.0, true);
+                let at = layout::Point::new(r.center().x, y + LINE_HEIGHT / 2.0 + 4.0);
+                self.text(label, at, true);
+            }
+        }
+
+        for (top, bottom) in &layout.lifelines {
+            self.line(&[*top, *bottom], 1.0, true);
+        }
+
+        for edge in &layout.edges {
+            let (width, dashed) = match edge.line {
+                LineStyle::Solid | LineStyle::Invisible => (1.2, false),
+                LineStyle::Dotted => (1.2, true),
+                LineStyle::Thick => (3.0, false),
+            };
+            self.line(&edge.points, width, dashed);
+            let count = edge.points.len();
+            let ends = [
+                (edge.start, edge.points[0], edge.points[1]),
+                (edge.end, edge.points[count - 1], edge.points[count - 2]),
+            ];
+            for (kind, tip, from) in ends {
+                if kind != Marker::None {
+                    for primitive in marker(kind, tip, from, 9.0) {
+                        self.primitive(&primitive, Color::DIAGRAM);
+                    }
+                }
+            }
+            for (label, at) in [&edge.label, &edge.start_label, &edge.end_label]
+                .into_iter()
+                .flatten()
+            {
+                let fonts = Fonts::shared();
+                let width = label
+                    .split('\n')
+                    .map(|line| fonts.width(line, Face::Regular, FONT_SIZE))
+                    .fold(0.0, f32::max);
+                let height = label.split('\n').count() as f32 * LINE_HEIGHT;
+                self.shapes.push(Shape::Rect {
+                    rect: self.rect(layout::Rect::new(
+                        at.x - width / 2.0 - 2.0,
+                        at.y - height / 2.0,
+                        width + 4.0,
+                        height,
+                    )),
+                    fill: Color::WHITE,
+                });
+                self.text(label, *at, true);
+            }
+        }
+
+        for node in &layout.nodes {
+            let fill = if node.shape == NodeShape::Note {
+                NOTE
+            } else {
+                Color::DIAGRAM
+            };
+            for primitive in outline(node.shape, node.rect) {
+                self.primitive(&primitive, fill);
+            }
+            self.text(&node.label, node.label_at, true);
+            for section in &node.sections {
+                let r = section.rect;
+                let line = [
+                    layout::Point::new(r.x, r.y),
+                    layout::Point::new(r.right(), r.y),
+                ];
+                self.line(&line, 1.0, false);
+                for (text, at) in &section.lines {
+                    self.text(text, *at, false);
+                }
+            }
+        }
+
+        self.shapes
+    }
+
+    fn line(&mut self, points: &[layout::Point], width: f32, dashed: bool) {
+        self.shapes.push(Shape::Line {
+            points: points.iter().map(|p| self.point(*p)).collect(),
+            width: width * self.scale,
+            color: STROKE,
+            dashed,
+        });
+    }
+
+    fn polygon(&mut self, points: Vec<layout::Point>, fill: Option<Color>) {
+        self.shapes.push(Shape::Polygon {
+            points: points.into_iter().map(|p| self.point(p)).collect(),
+            fill,
+            stroke: Some(STROKE),
+        });
+    }
+
+    fn primitive(&mut self, primitive: &Primitive, background: Color) {
+        let fill = |fill: &Fill| match fill {
+            Fill::None => None,
+            Fill::Background => Some(background),
+            Fill::Solid => Some(STROKE),
+        };
+        match primitive {
+            Primitive::Polygon { points, fill: f } => self.polygon(points.clone(), fill(f)),
+            Primitive::Polyline(points) => self.line(points, 1.0, false),
+            Primitive::Ellipse {
+                center,
+                rx,
+                ry,
+                fill: f,
+            } => {
+                let points = (0..32)
+                    .map(|i| {
+                        let angle = i as f32 / 32.0 * std::f32::consts::TAU;
+                        layout::Point::new(center.x + rx * angle.cos(), center.y + ry * angle.sin())
+                    })
+                    .collect();
+                self.polygon(points, fill(f));
+            }
+            Primitive::RoundedRect { rect, radius } => {
+                self.polygon(rounded(*rect, *radius), Some(background))
+            }
+        }
+    }
+
+    /// Text centered vertically on `at`, centered or starting at `at.x`
+    fn text(&mut self, text: &str, at: layout::Point, centered: bool) {
+        if text.is_empty() {
+            return;
+        }
+        let fonts = Fonts::shared();
+        let size = FONT_SIZE * self.scale;
+        let lines: Vec<&str> = text.split('\n').collect();
+        let first = at.y - (lines.len() - 1) as f32 * LINE_HEIGHT / 2.0;
+        for (i, line) in lines.iter().enumerate() {
+            let center = self.point(layout::Point::new(at.x, first + i as f32 * LINE_HEIGHT));
+            let width = fonts.width(line, Face::Regular, size);
+            self.shapes.push(Shape::Text {
+                text: line.to_string(),
+                x: if centered {
+                    center.x - width / 2.0
+                } else {
+                    center.x
+                },
+                // Baseline of a line centered on the point
+                y: center.y + size * 0.35,
+                size,
+                face: Face::Regular,
+                color: STROKE,
+            });
+        }
+    }
+}
+
+fn corners(r: layout::Rect) -> Vec<layout::Point> {
+    vec![
+        layout::Point::new(r.x, r.y),
+        layout::Point::new(r.right(), r.y),
+        layout::Point::new(r.right(), r.bottom()),
+        layout::Point::new(r.x, r.bottom()),
+    ]
+}
+
+/// Outline of a rounded rectangle, corners as short arcs
+fn rounded(r: layout::Rect, radius: f32) -> Vec<layout::Point> {
+    let radius = radius.min(r.width / 2.0).min(r.height / 2.0);
+    let centers = [
+        (r.right() - radius, r.y + radius, -90.0_f32),
+        (r.right() - radius, r.bottom() - radius, 0.0),
+        (r.x + radius, r.bottom() - radius, 90.0),
+        (r.x + radius, r.y + radius, 180.0),
+    ];
+    centers
+        .iter()
+        .flat_map(|&(cx, cy, start)| {
+            (0..=4).map(move |i| {
+                let angle = (start + i as f32 * 22.5).to_radians();
+                layout::Point::new(cx + radius * angle.cos(), cy + radius * angle.sin())
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,240 @@
+//! Fonts for exported pages
+//!
+//! Export uses the fonts egui ships with (Ubuntu Light, Hack and the emoji
+//! fonts), so Latin text looks the same on every machine. Characters missing
+//! from the first font of a family fall back to the next one, in egui's own
+//! order, and then to system CJK and symbol fonts like the GUI's (see
+//! [`SYSTEM_FALLBACKS`]). There are no bold or italic files: writers
+//! embolden by stroking the outline and slant it.
+
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use ab_glyph::{Font as _, FontArc, GlyphId};
+use eframe::egui::{FontDefinitions, FontFamily};
+
+use super::page::Face;
+
+/// Slant of the synthesized italic (tan of about 12°)
+pub const ITALIC_SKEW: f32 = 0.21;
+
+/// Outline stroke of the synthesized bold, relative to the font size
+pub const BOLD_STROKE: f32 = 0.035;
+
+/// System fonts tried after egui's, for Japanese text and symbols such as
+/// `∎`; the first CJK paths are the ones the GUI loads
+///
+/// PDFs embed fonts as TrueType, so only single `glyf` fonts are used:
+/// collections (`.ttc`) and CFF-based OpenType files are skipped.
+pub const SYSTEM_FALLBACKS: &[&str] = &[
+    // CJK
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+    "/usr/share/fonts/google-droid-sans-fonts/DroidSansFallbackFull.ttf",
+    "/usr/share/fonts/truetype/fonts-japanese-gothic.ttf",
+    "/usr/share/fonts/opentype/ipafont-gothic/ipag.ttf",
+    "/usr/share/fonts/truetype/takao-gothic/TakaoPGothic.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    "/Library/Fonts/Arial Unicode.ttf",
+    // Symbols
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSansSymbols2-Regular.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSansMath-Regular.ttf",
+    "/System/Library/Fonts/Apple Symbols.ttf",
+    "C:\\Windows\\Fonts\\seguisym.ttf",
+];
+
+pub struct FontFile {
+    /// Name without spaces, usable as a PDF font name
+    pub name: String,
+    pub data: Cow<'static, [u8]>,
+    pub font: FontArc,
+    /// Font units per em
+    pub units_per_em: f32,
+}
+
+/// A character resolved to a glyph of one of the [`Fonts`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glyph {
+    pub ch: char,
+    /// Index into [`Fonts::files`]
+    pub file: usize,
+    pub id: GlyphId,
+    /// Advance width in em
+    pub advance: f32,
+}
+
+pub struct Fonts {
+    pub files: Vec<FontFile>,
+    proportional: Vec<usize>,
+    monospace: Vec<usize>,
+}
+
+impl Fonts {
+    /// The built-in fonts, loaded once
+    pub fn shared() -> &'static Fonts {
+        static FONTS: OnceLock<Fonts> = OnceLock::new();
+        FONTS.get_or_init(Fonts::load)
+    }
+
+    fn load() -> Fonts {
+        let definitions = FontDefinitions::default();
+        let mut files = Vec::new();
+        let mut family = |family: FontFamily| -> Vec<usize> {
+            let names = definitions
+                .families
+                .get(&family)
+                .cloned()
+                .unwrap_or_default();
+            names
+                .iter()
+                .filter_map(|name| {
+                    if let Some(index) = files.iter().position(|file: &FontFile| &file.name == name)
+                    {
+                        return Some(index);
+                    }
+                    let data = definitions.font_data.get(name)?.font.clone();
+                    let font = match &data {
+                        Cow::Borrowed(bytes) => FontArc::try_from_slice(bytes).ok()?,
+                        Cow::Owned(bytes) => FontArc::try_from_vec(bytes.clone()).ok()?,
+                    };
+                    files.push(FontFile {
+                        name: name.replace(' ', ""),
+                        units_per_em: font.units_per_em().unwrap_or(1000.0),
+                        data,
+                        font,
+                    });
+                    Some(files.len() - 1)
+                })
+                .collect()
+        };
+        let mut proportional = family(FontFamily::Proportional);
+        let mut monospace = family(FontFamily::Monospace);
+
+        for path in SYSTEM_FALLBACKS {
+            let Some(file) = load_system_font(Path::new(path)) else {
+                continue;
+            };
+            // The same font installed in two places
+            if files.iter().any(|loaded| loaded.name == file.name) {
+                continue;
+            }
+            files.push(file);
+            proportional.push(files.len() - 1);
+            monospace.push(files.len() - 1);
+        }
+
+        Fonts {
+            files,
+            proportional,
+            monospace,
+        }
+    }
+
+    fn family(&self, face: Face) -> &[usize] {
+        if face == Face::Mono {
+            &self.monospace
+        } else {
+            &self.proportional
+        }
+    }
+
+    /// Whether some font of the family has a glyph for `ch`
+    pub fn has_glyph(&self, ch: char, face: Face) -> bool {
+        self.family(face)
+            .iter()
+            .any(|&file| self.files[file].font.glyph_id(ch).0 != 0)
+    }
+
+    /// Glyphs of `text`, each from the first font of the family that has it
+    pub fn glyphs(&self, text: &str, face: Face) -> Vec<Glyph> {
+        let family = self.family(face);
+        text.chars()
+            .filter_map(|ch| {
+                let found = family.iter().find_map(|&file| {
+                    let id = self.files[file].font.glyph_id(ch);
+                    (id.0 != 0).then_some((file, id))
+                });
+                // Missing everywhere: the primary font's .notdef box
+                let (file, id) = found.or_else(|| {
+                    let file = *family.first()?;
+                    Some((file, self.files[file].font.glyph_id(ch)))
+                })?;
+                let font = &self.files[file];
+                Some(Glyph {
+                    ch,
+                    file,
+                    id,
+                    advance: font.font.h_advance_unscaled(id) / font.units_per_em,
+                })
+            })
+            .collect()
+    }
+
+    /// Width of `text` at `size`
+    pub fn width(&self, text: &str, face: Face, size: f32) -> f32 {
+        self.glyphs(text, face)
+            .iter()
+            .map(|glyph| glyph.advance)
+            .sum::<f32>()
+            * size
+    }
+
+    /// Ascent and descent of the primary font of `face`, in em
+    pub fn extent(&self, face: Face) -> (f32, f32) {
+        match self.family(face).first() {
+            Some(&file) => {
+                let file = &self.files[file];
+                (
+                    file.font.ascent_unscaled() / file.units_per_em,
+                    -file.font.descent_unscaled() / file.units_per_em,
+                )
+            }
+            None => (0.8, 0.2),
+        }
+    }
+}
+
+/// A single TrueType font file, or `None` if it is missing or unusable
+fn load_system_font(path: &Path) -> Option<FontFile> {
+    let data = std::fs::read(path).ok()?;
+    // `\0\x01\0\0` and `true` are TrueType outlines; `OTTO` is CFF and
+    // `ttcf` a collection
+    if !(data.starts_with(&[0, 1, 0, 0]) || data.starts_with(b"true")) {
+        return None;
+    }
+    let font = FontArc::try_from_vec(data.clone()).ok()?;
+    let name = path.file_stem()?.to_string_lossy().replace(' ', "");
+    Some(FontFile {
+        name,
+        units_per_em: font.units_per_em().unwrap_or(1000.0),
+        data: Cow::Owned(data),
+        font,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_and_widths() {
+        let fonts = Fonts::shared();
+        let mono = fonts.glyphs("iW", Face::Mono);
+        assert_eq!(mono.len(), 2);
+        assert!((mono[0].advance - mono[1].advance).abs() < 1e-4);
+        assert!(fonts.width("iii", Face::Regular, 10.0) < fonts.width("WWW", Face::Regular, 10.0));
+
+        // An emoji is not in Ubuntu Light and comes from a later font
+        let primary = fonts.glyphs("a", Face::Regular)[0].file;
+        let emoji = fonts.glyphs("😀", Face::Regular)[0];
+        assert_ne!(emoji.file, primary);
+        assert_ne!(emoji.id.0, 0);
+        assert!(fonts.has_glyph('😀', Face::Regular));
+
+        // A private use character no font has
+        assert!(!fonts.has_glyph('\u{10fffd}', Face::Regular));
+    }
+}
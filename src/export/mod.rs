@@ -0,0 +1,320 @@
+//! # Export
+//!
+//! 文書を単体で共有できるファイル (HTML / PDF / SVG / PNG) に書き出すモジュール。
+//! 外部ツールやネットワークは使わず、すべて Rust だけで描画する。
+//!
+//! ```text
+//!                                  ┌─▶ html_writer::render_standalone ─▶ HTML
+//! source ─▶ document::*::parse ─▶ Document
+//!                                  └─▶ paginate::paginate ─▶ Page ─┬─▶ pdf::write ─▶ PDF
+//!                                                                  ├─▶ svg::write ─▶ SVG
+//!                                                                  └─▶ png::write ─▶ PNG
+//! ```
+//!
+//! - [`page`] — ページ上の図形 (テキスト・矩形・線・多角形・画像)。全ライターの共通モデル
+//! - [`fonts`] — egui 同梱フォント (Ubuntu Light / Hack / 絵文字) とシステムの CJK・記号フォントによる計測とフォールバック
+//! - [`paginate`] — 段落の行分割と A4 ページへの割り付け (コード・表・数式・図を含む)
+//! - [`diagram`] — Mermaid レイアウトをページ上の図形に変換
+//! - [`pdf`] — フォントを埋め込んだ PDF の出力
+//! - [`svg`] — ページの SVG 出力
+//! - [`png`] — tiny-skia によるラスタライズ
+//!
+//! HTML はインライン CSS・syntect のクラス付きハイライト・MathML・SVG の図を含み、
+//! ローカル画像も `data:` URI として埋め込むので単体で表示できる。
+//! PDF と PNG でどのフォントにもない文字は `.notdef` の箱で描き、[`Exported::warnings`] で知らせる。
+
+pub mod diagram;
+pub mod fonts;
+pub mod page;
+pub mod paginate;
+pub mod pdf;
+pub mod png;
+pub mod svg;
+
+use std::path::Path;
+
+use base64::Engine as _;
+
+use crate::document::{self, html_writer, image, Block, Document, Inline};
+
+pub use page::Page;
+pub use paginate::paginate;
+
+/// Resolution of PNG output, in pixels per point (2 = 144 dpi)
+const PNG_SCALE: f32 = 2.0;
+
+/// Output file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Html,
+    Pdf,
+    Svg,
+    Png,
+}
+
+impl Format {
+    pub const ALL: [Format; 4] = [Format::Html, Format::Pdf, Format::Svg, Format::Png];
+
+    /// Parse `html`, `pdf`, `svg` or `png`
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name.to_ascii_lowercase().as_str() {
+            "html" | "htm" => Some(Format::Html),
+            "pdf" => Some(Format::Pdf),
+            "svg" => Some(Format::Svg),
+            "png" => Some(Format::Png),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Html => "html",
+            Format::Pdf => "pdf",
+            Format::Svg => "svg",
+            Format::Png => "png",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Format::Html => "HTML",
+            Format::Pdf => "PDF",
+            Format::Svg => "SVG",
+            Format::Png => "PNG",
+        }
+    }
+}
+
+/// An exported file and the problems found while writing it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exported {
+    pub bytes: Vec<u8>,
+    /// Things the output could not show, such as characters no font has
+    pub warnings: Vec<String>,
+}
+
+/// Export the document `source`, read from `path`, to `format`
+///
+/// The document kind follows the file extension as in the previews;
+/// includes and images are resolved against the file's directory.
+pub fn export(path: &Path, source: &str, format: Format) -> Result<Exported, String> {
+    let base_dir = path.parent();
+    let doc = parse(path, source)?;
+    let mut warnings = Vec::new();
+    let bytes = match format {
+        Format::Html => {
+            let doc = embed_images(doc, base_dir);
+            html_writer::render_standalone(&doc).into_bytes()
+        }
+        Format::Svg => match single_diagram(&doc) {
+            // Diagrams keep the SVG of `lala mermaid --svg`
+            Some(diagram) => crate::mermaid::svg::render(diagram).into_bytes(),
+            None => svg::write(&pages(&doc, base_dir)).into_bytes(),
+        },
+        Format::Pdf => {
+            let pages = pages(&doc, base_dir);
+            warnings.extend(missing_glyphs(&pages));
+            pdf::write(&pages, doc.title.as_deref())
+        }
+        Format::Png => {
+            let pages = pages(&doc, base_dir);
+            warnings.extend(missing_glyphs(&pages));
+            png::write(&pages, PNG_SCALE)?
+        }
+    };
+    Ok(Exported { bytes, warnings })
+}
+
+/// Warning for text with no glyph in any font, drawn as `.notdef` boxes
+///
+/// SVG is not checked: its text is drawn with the viewer's fonts.
+fn missing_glyphs(pages: &[Page]) -> Option<String> {
+    const SHOWN: usize = 10;
+
+    let fonts = fonts::Fonts::shared();
+    let mut missing: Vec<char> = Vec::new();
+    for shape in pages.iter().flat_map(|page| &page.shapes) {
+        if let page::Shape::Text { text, face, .. } = shape {
+            for ch in text.chars() {
+                if !ch.is_whitespace()
+                    && !ch.is_control()
+                    && !missing.contains(&ch)
+                    && !fonts.has_glyph(ch, *face)
+                {
+                    missing.push(ch);
+                }
+            }
+        }
+    }
+    if missing.is_empty() {
+        return None;
+    }
+
+    let mut shown: String = missing.iter().take(SHOWN).collect();
+    if missing.len() > SHOWN {
+        shown.push_str(&format!(" and {} more", missing.len() - SHOWN));
+    }
+    Some(format!(
+        "no installed font has the characters {shown}, drawn as boxes; install a font that \
+         covers them (for Japanese, Droid Sans Fallback or IPAGothic)"
+    ))
+}
+
+/// Parse `source` according to the extension of `path`
+pub fn parse(path: &Path, source: &str) -> Result<Document, String> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "md" | "markdown" => Ok(document::markdown::parse(source)),
+        "html" | "htm" => Ok(document::html::parse(source)),
+        "tex" | "latex" => Ok(document::latex::parse_with_base(source, path.parent())),
+        "mmd" | "mermaid" => Ok(document::mermaid::parse(source)),
+        "" => Err(format!("cannot tell the format of {}", path.display())),
+        other => Err(format!("cannot export .{other} files")),
+    }
+}
+
+/// Pages of a document; a lone diagram gets a page of its own size
+fn pages(doc: &Document, base_dir: Option<&Path>) -> Vec<Page> {
+    match single_diagram(doc) {
+        Some(diagram) => {
+            let layout = diagram::layout(diagram);
+            vec![Page {
+                width: layout.width.ceil().max(1.0),
+                height: layout.height.ceil().max(1.0),
+                shapes: diagram::shapes(&layout, page::Point::default(), 1.0),
+            }]
+        }
+        None => paginate(doc, base_dir),
+    }
+}
+
+fn single_diagram(doc: &Document) -> Option<&document::Diagram> {
+    match doc.blocks.as_slice() {
+        [Block::Diagram(diagram)] if doc.title.is_none() => Some(diagram),
+        _ => None,
+    }
+}
+
+/// Replace local image paths with `data:` URIs
+fn embed_images(mut doc: Document, base_dir: Option<&Path>) -> Document {
+    fn inlines(content: &mut [Inline], base_dir: Option<&Path>) {
+        for inline in content {
+            match inline {
+                Inline::Image { src, .. } if !src.starts_with("data:") => {
+                    let local = match image::resolve(src, base_dir) {
+                        Some(source @ image::Source::File(_)) => source.read().ok(),
+                        _ => None,
+                    };
+                    if let Some((bytes, mime)) = local {
+                        let data = base64::engine::general_purpose::STANDARD.encode(bytes);
+                        *src = format!("data:{mime};base64,{data}");
+                    }
+                }
+                Inline::Emphasis(inner)
+                | Inline::Strong(inner)
+                | Inline::Strikethrough(inner)
+                | Inline::Link { content: inner, .. } => inlines(inner, base_dir),
+                _ => {}
+            }
+        }
+    }
+    fn blocks(content: &mut [Block], base_dir: Option<&Path>) {
+        for block in content {
+            match block {
+                Block::Heading { content, .. } | Block::Paragraph(content) => {
+                    inlines(content, base_dir)
+                }
+                Block::List(list) => {
+                    for item in &mut list.items {
+                        blocks(&mut item.blocks, base_dir);
+                    }
+                }
+                Block::BlockQuote(inner) | Block::Footnote { blocks: inner, .. } => {
+                    blocks(inner, base_dir)
+                }
+                Block::Table(table) => {
+                    for cell in table
+                        .header
+                        .iter_mut()
+                        .chain(table.rows.iter_mut().flatten())
+                    {
+                        inlines(cell, base_dir);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    blocks(&mut doc.blocks, base_dir);
+    doc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_names() {
+        for format in Format::ALL {
+            assert_eq!(Format::from_name(format.extension()), Some(format));
+        }
+        assert_eq!(Format::from_name("PDF"), Some(Format::Pdf));
+        assert_eq!(Format::from_name("docx"), None);
+    }
+
+    #[test]
+    fn test_embed_local_images() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut png = Vec::new();
+        ::image::RgbaImage::new(2, 2)
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                ::image::ImageFormat::Png,
+            )
+            .unwrap();
+        std::fs::write(dir.path().join("dot.png"), &png).unwrap();
+
+        let source = "![dot](dot.png) ![remote](https://example.com/x.png)\n";
+        let html = export(&dir.path().join("doc.md"), source, Format::Html).unwrap();
+        let html = String::from_utf8(html.bytes).unwrap();
+        assert!(html.contains("src=\"data:image/png;base64,"));
+        assert!(html.contains("src=\"https://example.com/x.png\""));
+
+        // The data URI decodes back to the same image
+        let src = html
+            .split("src=\"")
+            .nth(1)
+            .unwrap()
+            .split('"')
+            .next()
+            .unwrap();
+        let source = image::resolve(src, None).unwrap();
+        assert_eq!(source.read().unwrap(), (png, "image/png".to_string()));
+    }
+
+    #[test]
+    fn test_missing_glyphs_warn() {
+        let source = "# Private \u{10fffd}\n";
+        for format in [Format::Png, Format::Pdf] {
+            let exported = export(Path::new("doc.md"), source, format).unwrap();
+            assert!(!exported.bytes.is_empty());
+            assert_eq!(exported.warnings.len(), 1);
+            assert!(exported.warnings[0].contains('\u{10fffd}'));
+        }
+
+        // SVG text is drawn with the viewer's fonts
+        let svg = export(Path::new("doc.md"), source, Format::Svg).unwrap();
+        assert!(svg.warnings.is_empty());
+        let plain = export(Path::new("doc.md"), "# Plain\n", Format::Png).unwrap();
+        assert!(plain.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_extension() {
+        assert!(export(Path::new("notes.txt"), "x", Format::Pdf).is_err());
+    }
+}
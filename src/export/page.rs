@@ -0,0 +1,142 @@
+//! Laid out pages
+//!
+//! The paginator turns a document into pages of absolutely positioned
+//! [`Shape`]s in points (1/72 inch), with `y` growing downwards. The PDF,
+//! SVG and PNG writers only draw these shapes, so all three produce the
+//! same pages.
+
+use std::sync::Arc;
+
+use image::RgbaImage;
+
+/// A4 in points
+pub const A4: (f32, f32) = (595.0, 842.0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color(pub u8, pub u8, pub u8);
+
+impl Color {
+    pub const TEXT: Color = Color(0x24, 0x29, 0x2f);
+    pub const MUTED: Color = Color(0x57, 0x60, 0x6a);
+    pub const LINK: Color = Color(0x09, 0x69, 0xda);
+    pub const BORDER: Color = Color(0xd0, 0xd7, 0xde);
+    pub const CODE_BACKGROUND: Color = Color(0xf6, 0xf8, 0xfa);
+    pub const DIAGRAM: Color = Color(0xec, 0xec, 0xff);
+    pub const WHITE: Color = Color(0xff, 0xff, 0xff);
+
+    /// `#rrggbb`
+    pub fn hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+/// Typeface of a text run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Face {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+    Mono,
+}
+
+impl Face {
+    pub fn is_bold(self) -> bool {
+        matches!(self, Face::Bold | Face::BoldItalic)
+    }
+
+    pub fn is_italic(self) -> bool {
+        matches!(self, Face::Italic | Face::BoldItalic)
+    }
+
+    pub fn with_bold(self) -> Face {
+        match self {
+            Face::Regular => Face::Bold,
+            Face::Italic => Face::BoldItalic,
+            face => face,
+        }
+    }
+
+    pub fn with_italic(self) -> Face {
+        match self {
+            Face::Regular => Face::Italic,
+            Face::Bold => Face::BoldItalic,
+            face => face,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Point {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// A drawing primitive on a page
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    /// A single line of text starting at `x` on the baseline `y`
+    Text {
+        text: String,
+        x: f32,
+        y: f32,
+        size: f32,
+        face: Face,
+        color: Color,
+    },
+    Rect {
+        rect: Rect,
+        fill: Color,
+    },
+    /// Open polyline
+    Line {
+        points: Vec<Point>,
+        width: f32,
+        color: Color,
+        dashed: bool,
+    },
+    /// Closed polygon, filled and/or outlined
+    Polygon {
+        points: Vec<Point>,
+        fill: Option<Color>,
+        stroke: Option<Color>,
+    },
+    /// Raster image scaled into `rect`
+    Image {
+        rect: Rect,
+        image: Arc<RgbaImage>,
+    },
+}
+
+/// A page of shapes, sized in points
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    pub width: f32,
+    pub height: f32,
+    pub shapes: Vec<Shape>,
+}
@@ -0,0 +1,1124 @@
+//! Page layout of documents
+//!
+//! Breaks a [`Document`] into A4 pages the way a word processor would:
+//! paragraphs are filled line by line with the export fonts, headings are
+//! kept with the text that follows them, code blocks, tables, formulas and
+//! diagrams are broken between lines and rows. Every block draws its own
+//! shapes, so the writers only need to know about [`Shape`]s.
+
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use ::image::RgbaImage;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::util::LinesWithEndings;
+use unicode_width::UnicodeWidthChar;
+
+use crate::document::{
+    html_writer, image, Alignment, Block, Diagram, Document, Inline, List, Table,
+};
+use crate::math::layout::{self as math_layout, Item as MathItem, Metrics, RuleKind};
+use crate::math::{self, geometry as math_geometry};
+
+use super::diagram;
+use super::fonts::Fonts;
+use super::page::{Color, Face, Page, Point, Rect, Shape, A4};
+
+const MARGIN: f32 = 56.0;
+const BODY: f32 = 10.5;
+const CODE: f32 = 9.0;
+const INDENT: f32 = 18.0;
+const HEADINGS: [f32; 6] = [20.0, 16.0, 13.5, 12.0, 11.0, 10.5];
+/// Space between blocks
+const BLOCK_GAP: f32 = BODY * 0.7;
+/// Padding inside code blocks and table cells
+const PADDING: f32 = 5.0;
+
+const INLINE_CODE_BACKGROUND: Color = Color(0xee, 0xf0, 0xf2);
+const TABLE_HEADER_BACKGROUND: Color = Color(0xf3, 0xf5, 0xf7);
+
+/// Lay out `doc` on A4 pages; images are resolved against `base_dir`
+pub fn paginate(doc: &Document, base_dir: Option<&Path>) -> Vec<Page> {
+    let mut paginator = Paginator {
+        fonts: Fonts::shared(),
+        base_dir,
+        pages: Vec::new(),
+        shapes: Vec::new(),
+        y: MARGIN,
+        quote_bars: Vec::new(),
+        marker: None,
+    };
+    let width = A4.0 - 2.0 * MARGIN;
+
+    if let Some(title) = &doc.title {
+        let style = Style {
+            face: Face::Bold,
+            size: 24.0,
+            ..Style::body()
+        };
+        paginator.paragraph(&[Inline::Text(title.clone())], style, MARGIN, width, 0.0);
+    }
+    for (key, value) in &doc.metadata {
+        let style = Style {
+            color: Color::MUTED,
+            ..Style::body()
+        };
+        let line = [Inline::Text(format!("{key}: {value}"))];
+        paginator.paragraph(&line, style, MARGIN, width, 0.0);
+    }
+    if doc.title.is_some() || !doc.metadata.is_empty() {
+        paginator.gap(BODY);
+    }
+    paginator.blocks(&doc.blocks, MARGIN, width);
+    paginator.new_page();
+
+    // Page numbers
+    let mut pages = paginator.pages;
+    let count = pages.len();
+    let fonts = Fonts::shared();
+    for (index, page) in pages.iter_mut().enumerate() {
+        let text = format!("{} / {count}", index + 1);
+        let width = fonts.width(&text, Face::Regular, 8.0);
+        page.shapes.push(Shape::Text {
+            x: (page.width - width) / 2.0,
+            y: page.height - MARGIN / 2.0,
+            text,
+            size: 8.0,
+            face: Face::Regular,
+            color: Color::MUTED,
+        });
+    }
+    pages
+}
+
+/// Look of a run of text
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Style {
+    face: Face,
+    size: f32,
+    color: Color,
+    /// Inline code, drawn on a shaded background
+    code: bool,
+    underline: bool,
+    strike: bool,
+    /// Baseline shift of superscripts
+    raise: f32,
+}
+
+impl Style {
+    fn body() -> Self {
+        Self {
+            face: Face::Regular,
+            size: BODY,
+            color: Color::TEXT,
+            code: false,
+            underline: false,
+            strike: false,
+            raise: 0.0,
+        }
+    }
+}
+
+/// Unbreakable unit of a paragraph
+#[derive(Debug, Clone)]
+enum Piece {
+    Word {
+        text: String,
+        style: Style,
+    },
+    Math {
+        layout: math_layout::Layout,
+        size: f32,
+        color: Color,
+    },
+    Image {
+        image: Arc<RgbaImage>,
+        width: f32,
+        height: f32,
+    },
+    Break,
+}
+
+/// A piece and whether a space separates it from the previous one
+struct Item {
+    piece: Piece,
+    space: bool,
+}
+
+/// A line of pieces at their horizontal offsets
+#[derive(Default)]
+struct Line {
+    pieces: Vec<(f32, Piece)>,
+    width: f32,
+    ascent: f32,
+    descent: f32,
+    /// Largest text size on the line, which sets the leading
+    size: f32,
+}
+
+impl Line {
+    fn leading(&self) -> f32 {
+        self.size.max(BODY * 0.8) * 0.4
+    }
+
+    fn height(&self) -> f32 {
+        self.ascent + self.descent + self.leading()
+    }
+
+    /// Distance from the top of the line to its baseline
+    fn baseline(&self) -> f32 {
+        self.leading() / 2.0 + self.ascent
+    }
+}
+
+/// Mark drawn left of the first line of a list item
+enum Marker {
+    Text(String),
+    Task(bool),
+}
+
+struct Paginator<'a> {
+    fonts: &'static Fonts,
+    base_dir: Option<&'a Path>,
+    pages: Vec<Page>,
+    shapes: Vec<Shape>,
+    /// Top of the free space on the current page
+    y: f32,
+    /// Left edges of the bars of enclosing block quotes
+    quote_bars: Vec<f32>,
+    /// Marker waiting for the first line of a list item, with the left
+    /// edge of the item's content
+    marker: Option<(Marker, f32)>,
+}
+
+impl Paginator<'_> {
+    fn bottom(&self) -> f32 {
+        A4.1 - MARGIN
+    }
+
+    fn new_page(&mut self) {
+        if self.shapes.is_empty() && !self.pages.is_empty() {
+            return;
+        }
+        self.pages.push(Page {
+            width: A4.0,
+            height: A4.1,
+            shapes: std::mem::take(&mut self.shapes),
+        });
+        self.y = MARGIN;
+    }
+
+    /// Start a new page unless `height` fits on this one
+    fn ensure(&mut self, height: f32) {
+        if self.y + height > self.bottom() && self.y > MARGIN {
+            self.new_page();
+        }
+    }
+
+    /// Claim `height` of vertical space, returning its top
+    fn reserve(&mut self, height: f32) -> f32 {
+        self.ensure(height);
+        let top = self.y;
+        for &x in &self.quote_bars {
+            self.shapes.push(Shape::Rect {
+                rect: Rect::new(x, top, 3.0, height),
+                fill: Color::BORDER,
+            });
+        }
+        self.y += height;
+        top
+    }
+
+    /// Vertical space between blocks, dropped at the top of a page
+    fn gap(&mut self, height: f32) {
+        if self.y <= MARGIN {
+            return;
+        }
+        if self.y + height > self.bottom() {
+            self.new_page();
+        } else {
+            self.reserve(height);
+        }
+    }
+
+    /// Draw a pending list marker on the line with this baseline
+    fn take_marker(&mut self, baseline: f32) {
+        let Some((marker, content_x)) = self.marker.take() else {
+            return;
+        };
+        match marker {
+            Marker::Text(text) => {
+                let width = self.fonts.width(&text, Face::Regular, BODY);
+                self.shapes.push(Shape::Text {
+                    text,
+                    x: content_x - width - 5.0,
+                    y: baseline,
+                    size: BODY,
+                    face: Face::Regular,
+                    color: Color::TEXT,
+                });
+            }
+            Marker::Task(checked) => {
+                let (x, y, side) = (content_x - 14.0, baseline - 8.0, 8.5);
+                self.shapes.push(Shape::Polygon {
+                    points: vec![
+                        Point::new(x, y),
+                        Point::new(x + side, y),
+                        Point::new(x + side, y + side),
+                        Point::new(x, y + side),
+                    ],
+                    fill: None,
+                    stroke: Some(Color::MUTED),
+                });
+                if checked {
+                    self.shapes.push(Shape::Line {
+                        points: vec![
+                            Point::new(x + 1.8, y + 4.5),
+                            Point::new(x + 3.6, y + 6.6),
+                            Point::new(x + 7.0, y + 1.8),
+                        ],
+                        width: 1.3,
+                        color: Color::TEXT,
+                        dashed: false,
+                    });
+                }
+            }
+        }
+    }
+
+    fn blocks(&mut self, blocks: &[Block], x: f32, width: f32) {
+        for (index, block) in blocks.iter().enumerate() {
+            if index > 0 {
+                self.gap(BLOCK_GAP);
+            }
+            self.block(block, x, width);
+        }
+    }
+
+    fn block(&mut self, block: &Block, x: f32, width: f32) {
+        match block {
+            Block::Heading { level, content } => {
+                let size = HEADINGS[(*level as usize).clamp(1, 6) - 1];
+                self.gap(size * 0.6);
+                // Keep the heading with a few lines of what follows
+                self.ensure(size * 2.0 + BODY * 4.5);
+                let style = Style {
+                    face: Face::Bold,
+                    size,
+                    ..Style::body()
+                };
+                self.paragraph(content, style, x, width, 0.0);
+                if *level <= 2 {
+                    let top = self.reserve(4.0);
+                    self.rule(x, width, top + 2.0);
+                }
+            }
+            Block::Paragraph(content) => self.paragraph(content, Style::body(), x, width, 0.0),
+            Block::List(list) => self.list(list, x, width),
+            Block::CodeBlock { lang, code } => self.code(lang, code, x, width),
+            Block::BlockQuote(blocks) => {
+                self.quote_bars.push(x);
+                self.blocks(blocks, x + 14.0, width - 14.0);
+                self.quote_bars.pop();
+            }
+            Block::Table(table) => self.table(table, x, width),
+            Block::Math(tex) => self.display_math(tex, x, width),
+            Block::Diagram(diagram) => self.diagram(diagram, x, width),
+            Block::Rule => {
+                let top = self.reserve(BODY);
+                self.rule(x, width, top + BODY / 2.0);
+            }
+            Block::Footnote { label, blocks } => {
+                self.marker = Some((Marker::Text(format!("{label}.")), x + INDENT));
+                self.blocks(blocks, x + INDENT, width - INDENT);
+                self.marker = None;
+            }
+        }
+    }
+
+    fn rule(&mut self, x: f32, width: f32, y: f32) {
+        self.shapes.push(Shape::Line {
+            points: vec![Point::new(x, y), Point::new(x + width, y)],
+            width: 0.75,
+            color: Color::BORDER,
+            dashed: false,
+        });
+    }
+
+    fn list(&mut self, list: &List, x: f32, width: f32) {
+        for (index, item) in list.items.iter().enumerate() {
+            if index > 0 {
+                self.gap(BODY * 0.2);
+            }
+            let marker = match (item.checked, list.start) {
+                (Some(checked), _) => Marker::Task(checked),
+                (None, Some(start)) => Marker::Text(format!("{}.", start + index as u64)),
+                (None, None) => Marker::Text("•".to_string()),
+            };
+            self.marker = Some((marker, x + INDENT));
+            self.blocks(&item.blocks, x + INDENT, width - INDENT);
+            self.marker = None;
+        }
+    }
+
+    /// Fill lines with `content`; `align` is 0 for left, 0.5 for centered
+    /// and 1 for right aligned text
+    fn paragraph(&mut self, content: &[Inline], style: Style, x: f32, width: f32, align: f32) {
+        let mut items = Vec::new();
+        self.inlines(content, style, &mut items, &mut false);
+        for line in self.break_lines(items, width) {
+            let top = self.reserve(line.height());
+            let baseline = top + line.baseline();
+            self.take_marker(baseline);
+            let x = x + (width - line.width).max(0.0) * align;
+            self.emit(&line.pieces, x, baseline);
+        }
+    }
+
+    fn inlines(&self, content: &[Inline], style: Style, out: &mut Vec<Item>, space: &mut bool) {
+        for inline in content {
+            match inline {
+                Inline::Text(text) => self.words(text, style, out, space),
+                Inline::Code(code) => {
+                    let style = Style {
+                        face: Face::Mono,
+                        size: style.size * 0.9,
+                        code: true,
+                        ..style
+                    };
+                    self.words(code, style, out, space);
+                }
+                Inline::Emphasis(inner) => {
+                    let style = Style {
+                        face: style.face.with_italic(),
+                        ..style
+                    };
+                    self.inlines(inner, style, out, space);
+                }
+                Inline::Strong(inner) => {
+                    let style = Style {
+                        face: style.face.with_bold(),
+                        ..style
+                    };
+                    self.inlines(inner, style, out, space);
+                }
+                Inline::Strikethrough(inner) => {
+                    let style = Style {
+                        strike: true,
+                        ..style
+                    };
+                    self.inlines(inner, style, out, space);
+                }
+                Inline::Link { content, .. } => {
+                    let style = Style {
+                        color: Color::LINK,
+                        underline: true,
+                        ..style
+                    };
+                    self.inlines(content, style, out, space);
+                }
+                Inline::Image {
+                    src,
+                    alt,
+                    width,
+                    height,
+                } => match image::load(src, self.base_dir) {
+                    Ok(picture) => {
+                        // CSS pixels at 96 dpi, shrunk to fit the text block
+                        let size = picture.size(*width, *height);
+                        let (mut width, mut height) = (size.0 * 0.75, size.1 * 0.75);
+                        let image = Arc::new(picture.pixels);
+                        let limit = (A4.0 - 2.0 * MARGIN) / width;
+                        let limit = limit.min((A4.1 - 2.0 * MARGIN) * 0.6 / height);
+                        if limit < 1.0 {
+                            width *= limit;
+                            height *= limit;
+                        }
+                        out.push(Item {
+                            piece: Piece::Image {
+                                image,
+                                width,
+                                height,
+                            },
+                            space: std::mem::take(space),
+                        });
+                    }
+                    Err(_) => {
+                        let style = Style {
+                            face: style.face.with_italic(),
+                            color: Color::MUTED,
+                            ..style
+                        };
+                        self.words(&format!("[{alt}]"), style, out, space);
+                    }
+                },
+                Inline::Math(tex) => {
+                    let layout = self.math_layout(tex, false, style.size);
+                    out.push(Item {
+                        piece: Piece::Math {
+                            layout,
+                            size: style.size,
+                            color: style.color,
+                        },
+                        space: std::mem::take(space),
+                    });
+                }
+                Inline::FootnoteReference(label) => out.push(Item {
+                    piece: Piece::Word {
+                        text: label.clone(),
+                        style: Style {
+                            size: style.size * 0.7,
+                            raise: style.size * 0.35,
+                            color: Color::LINK,
+                            ..style
+                        },
+                    },
+                    space: std::mem::take(space),
+                }),
+                Inline::LineBreak => {
+                    out.push(Item {
+                        piece: Piece::Break,
+                        space: false,
+                    });
+                    *space = false;
+                }
+            }
+        }
+    }
+
+    /// Split text into words; wide (CJK) characters break anywhere
+    fn words(&self, text: &str, style: Style, out: &mut Vec<Item>, space: &mut bool) {
+        let mut push = |word: &mut String, space: &mut bool| {
+            if !word.is_empty() {
+                out.push(Item {
+                    piece: Piece::Word {
+                        text: std::mem::take(word),
+                        style,
+                    },
+                    space: std::mem::take(space),
+                });
+            }
+        };
+        let mut word = String::new();
+        for c in text.chars() {
+            if c.is_whitespace() {
+                push(&mut word, space);
+                *space = true;
+            } else if c.width() == Some(2) {
+                push(&mut word, space);
+                word.push(c);
+                push(&mut word, space);
+            } else {
+                word.push(c);
+            }
+        }
+        push(&mut word, space);
+    }
+
+    fn math_layout(&self, tex: &str, display: bool, size: f32) -> math_layout::Layout {
+        let fonts = self.fonts;
+        let measure = move |text: &str, font: math::Font, scale: f32| {
+            fonts.width(text, math_face(font), size * scale)
+        };
+        math_layout::layout(&math::parse(tex), display, &measure, &Metrics::pixels(size))
+    }
+
+    fn piece_width(&self, piece: &Piece) -> f32 {
+        match piece {
+            Piece::Word { text, style } => self.fonts.width(text, style.face, style.size),
+            Piece::Math { layout, .. } => layout.width,
+            Piece::Image { width, .. } => *width,
+            Piece::Break => 0.0,
+        }
+    }
+
+    fn space_width(&self, piece: &Piece) -> f32 {
+        let (face, size) = match piece {
+            Piece::Word { style, .. } => (style.face, style.size),
+            Piece::Math { size, .. } => (Face::Regular, *size),
+            _ => (Face::Regular, BODY),
+        };
+        self.fonts.width(" ", face, size)
+    }
+
+    fn break_lines(&self, items: Vec<Item>, width: f32) -> Vec<Line> {
+        let mut lines = Vec::new();
+        let mut line = Line::default();
+        for item in items {
+            if let Piece::Break = item.piece {
+                lines.push(self.finish(std::mem::take(&mut line)));
+                continue;
+            }
+            for (index, piece) in self.split(item.piece, width).into_iter().enumerate() {
+                let piece_width = self.piece_width(&piece);
+                let mut space = if index == 0 && item.space && !line.pieces.is_empty() {
+                    self.space_width(&piece)
+                } else {
+                    0.0
+                };
+                if !line.pieces.is_empty() && line.width + space + piece_width > width + 0.01 {
+                    lines.push(self.finish(std::mem::take(&mut line)));
+                    space = 0.0;
+                }
+                line.pieces.push((line.width + space, piece));
+                line.width += space + piece_width;
+            }
+        }
+        if !line.pieces.is_empty() {
+            lines.push(self.finish(line));
+        }
+        lines
+    }
+
+    /// Break a word wider than a whole line between characters
+    fn split(&self, piece: Piece, width: f32) -> Vec<Piece> {
+        let Piece::Word { text, style } = &piece else {
+            return vec![piece];
+        };
+        if self.fonts.width(text, style.face, style.size) <= width {
+            return vec![piece];
+        }
+        let mut pieces = Vec::new();
+        let mut chunk = String::new();
+        for c in text.chars() {
+            chunk.push(c);
+            if self.fonts.width(&chunk, style.face, style.size) > width && chunk.chars().count() > 1
+            {
+                chunk.pop();
+                pieces.push(Piece::Word {
+                    text: std::mem::replace(&mut chunk, c.to_string()),
+                    style: *style,
+                });
+            }
+        }
+        pieces.push(Piece::Word {
+            text: chunk,
+            style: *style,
+        });
+        pieces
+    }
+
+    /// Compute the vertical extent of a line
+    fn finish(&self, mut line: Line) -> Line {
+        let (ascent, descent) = self.fonts.extent(Face::Regular);
+        line.size = BODY;
+        line.ascent = BODY * ascent;
+        line.descent = BODY * descent;
+        for (_, piece) in &line.pieces {
+            let (above, below, size) = match piece {
+                Piece::Word { style, .. } => {
+                    let (ascent, descent) = self.fonts.extent(style.face);
+                    (
+                        style.size * ascent + style.raise,
+                        style.size * descent - style.raise,
+                        style.size,
+                    )
+                }
+                Piece::Math { layout, size, .. } => (layout.height, layout.depth, *size),
+                Piece::Image { height, .. } => (*height, 0.0, 0.0),
+                Piece::Break => (0.0, 0.0, 0.0),
+            };
+            line.ascent = line.ascent.max(above);
+            line.descent = line.descent.max(below);
+            line.size = line.size.max(size);
+        }
+        // Lines of a single size use that size
+        if line.pieces.iter().all(|(_, piece)| match piece {
+            Piece::Word { style, .. } => style.raise == 0.0,
+            _ => false,
+        }) {
+            if let Some(size) = line
+                .pieces
+                .iter()
+                .filter_map(|(_, piece)| match piece {
+                    Piece::Word { style, .. } => Some(style.size),
+                    _ => None,
+                })
+                .reduce(f32::max)
+            {
+                line.size = size;
+                line.ascent = size * ascent;
+                line.descent = size * descent;
+            }
+        }
+        line
+    }
+
+    /// Draw the pieces of a line; neighbouring words of one style become a
+    /// single text run so the text can be copied from the PDF
+    fn emit(&mut self, pieces: &[(f32, Piece)], x: f32, baseline: f32) {
+        let mut index = 0;
+        while index < pieces.len() {
+            let (offset, piece) = &pieces[index];
+            index += 1;
+            match piece {
+                Piece::Word { text, style } => {
+                    let mut run = text.clone();
+                    let mut end = offset + self.fonts.width(text, style.face, style.size);
+                    let space = self.fonts.width(" ", style.face, style.size);
+                    while let Some((next_offset, Piece::Word { text, style: next })) =
+                        pieces.get(index)
+                    {
+                        let gap = next_offset - end;
+                        if next != style || !(gap.abs() < 0.01 || (gap - space).abs() < 0.01) {
+                            break;
+                        }
+                        if gap > 0.01 {
+                            run.push(' ');
+                        }
+                        run.push_str(text);
+                        end = next_offset + self.fonts.width(text, style.face, style.size);
+                        index += 1;
+                    }
+                    self.text_run(run, x + offset, x + end, baseline, *style);
+                }
+                Piece::Math {
+                    layout,
+                    size,
+                    color,
+                } => {
+                    let shapes = math_shapes(layout, x + offset, baseline, *size, *color);
+                    self.shapes.extend(shapes);
+                }
+                Piece::Image {
+                    image,
+                    width,
+                    height,
+                } => self.shapes.push(Shape::Image {
+                    rect: Rect::new(x + offset, baseline - height, *width, *height),
+                    image: image.clone(),
+                }),
+                Piece::Break => {}
+            }
+        }
+    }
+
+    fn text_run(&mut self, text: String, start: f32, end: f32, baseline: f32, style: Style) {
+        let size = style.size;
+        let baseline = baseline - style.raise;
+        if style.code {
+            self.shapes.push(Shape::Rect {
+                rect: Rect::new(
+                    start - 1.5,
+                    baseline - size * 0.9,
+                    end - start + 3.0,
+                    size * 1.2,
+                ),
+                fill: INLINE_CODE_BACKGROUND,
+            });
+        }
+        let line = |y: f32| Shape::Line {
+            points: vec![Point::new(start, y), Point::new(end, y)],
+            width: size * 0.06,
+            color: style.color,
+            dashed: false,
+        };
+        let decorations: Vec<Shape> = [
+            style.underline.then(|| line(baseline + size * 0.12)),
+            style.strike.then(|| line(baseline - size * 0.28)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        self.shapes.push(Shape::Text {
+            text,
+            x: start,
+            y: baseline,
+            size,
+            face: style.face,
+            color: style.color,
+        });
+        self.shapes.extend(decorations);
+    }
+
+    fn code(&mut self, lang: &str, code: &str, x: f32, width: f32) {
+        let line_height = CODE * 1.45;
+        let inner = width - 2.0 * PADDING;
+        let syntaxes = html_writer::syntax_set();
+        let syntax = syntaxes
+            .find_syntax_by_token(lang)
+            .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+        let mut highlighter = HighlightLines::new(syntax, code_theme());
+
+        // Visual lines of colored tokens, long lines wrapped by character
+        let mut lines: Vec<Vec<(Color, String)>> = Vec::new();
+        for source in LinesWithEndings::from(code.trim_end_matches('\n')) {
+            let ranges = highlighter
+                .highlight_line(source, syntaxes)
+                .unwrap_or_else(|_| vec![(Default::default(), source)]);
+            let mut line: Vec<(Color, String)> = Vec::new();
+            let mut line_width = 0.0;
+            for (style, text) in ranges {
+                let c = style.foreground;
+                let color = if c.a == 0 {
+                    Color::TEXT
+                } else {
+                    Color(c.r, c.g, c.b)
+                };
+                for ch in text.replace('\t', "    ").chars().filter(|c| *c != '\n') {
+                    let advance = self
+                        .fonts
+                        .width(ch.encode_utf8(&mut [0; 4]), Face::Mono, CODE);
+                    if line_width + advance > inner && !line.is_empty() {
+                        lines.push(std::mem::take(&mut line));
+                        line_width = 0.0;
+                    }
+                    line_width += advance;
+                    match line.last_mut() {
+                        Some((last, run)) if *last == color => run.push(ch),
+                        _ => line.push((color, ch.to_string())),
+                    }
+                }
+            }
+            lines.push(line);
+        }
+
+        let background = |paginator: &mut Self, top: f32, height: f32| {
+            paginator.shapes.push(Shape::Rect {
+                rect: Rect::new(x, top, width, height),
+                fill: Color::CODE_BACKGROUND,
+            });
+        };
+        let top = self.reserve(PADDING);
+        background(self, top, PADDING);
+        for line in lines {
+            let top = self.reserve(line_height);
+            background(self, top, line_height);
+            let baseline = top + line_height * 0.75;
+            self.take_marker(baseline);
+            let mut offset = x + PADDING;
+            for (color, text) in line {
+                let advance = self.fonts.width(&text, Face::Mono, CODE);
+                self.shapes.push(Shape::Text {
+                    text,
+                    x: offset,
+                    y: baseline,
+                    size: CODE,
+                    face: Face::Mono,
+                    color,
+                });
+                offset += advance;
+            }
+        }
+        let top = self.reserve(PADDING);
+        background(self, top, PADDING);
+    }
+
+    fn table(&mut self, table: &Table, x: f32, width: f32) {
+        let columns = table.column_count();
+        if columns == 0 {
+            return;
+        }
+        let header = Style {
+            face: Face::Bold,
+            ..Style::body()
+        };
+        let rows: Vec<(bool, &Vec<Vec<Inline>>)> = std::iter::once((true, &table.header))
+            .filter(|(_, row)| !row.is_empty())
+            .chain(table.rows.iter().map(|row| (false, row)))
+            .collect();
+        let cell_items = |paginator: &Self, cell: &[Inline], is_header: bool| {
+            let mut items = Vec::new();
+            let style = if is_header { header } else { Style::body() };
+            paginator.inlines(cell, style, &mut items, &mut false);
+            items
+        };
+
+        // Natural (one line) and minimum (longest word) column widths
+        let mut natural = vec![0.0f32; columns];
+        let mut minimum = vec![0.0f32; columns];
+        for (is_header, row) in &rows {
+            for (column, cell) in row.iter().enumerate() {
+                let items = cell_items(self, cell, *is_header);
+                let mut line = 0.0;
+                for (index, item) in items.iter().enumerate() {
+                    let piece = self.piece_width(&item.piece);
+                    if index > 0 && item.space {
+                        line += self.space_width(&item.piece);
+                    }
+                    line += piece;
+                    minimum[column] = minimum[column].max(piece.min(width / columns as f32));
+                }
+                natural[column] = natural[column].max(line);
+            }
+        }
+        let padded = |widths: &[f32]| widths.iter().map(|w| w + 2.0 * PADDING).collect::<Vec<_>>();
+        let (natural, minimum) = (padded(&natural), padded(&minimum));
+        let widths: Vec<f32> = if natural.iter().sum::<f32>() <= width {
+            natural
+        } else {
+            let spare = width - minimum.iter().sum::<f32>();
+            let stretch: f32 = natural.iter().zip(&minimum).map(|(n, m)| n - m).sum();
+            if spare <= 0.0 || stretch <= 0.0 {
+                let total: f32 = minimum.iter().sum();
+                minimum.iter().map(|m| m * width / total).collect()
+            } else {
+                natural
+                    .iter()
+                    .zip(&minimum)
+                    .map(|(n, m)| m + spare * (n - m) / stretch)
+                    .collect()
+            }
+        };
+        let table_width: f32 = widths.iter().sum();
+
+        for (row_index, (is_header, row)) in rows.iter().enumerate() {
+            let cells: Vec<Vec<Line>> = (0..columns)
+                .map(|column| {
+                    let content = row.get(column).map_or(&[][..], Vec::as_slice);
+                    let items = cell_items(self, content, *is_header);
+                    self.break_lines(items, widths[column] - 2.0 * PADDING)
+                })
+                .collect();
+            let height = cells
+                .iter()
+                .map(|lines| lines.iter().map(Line::height).sum::<f32>())
+                .fold(BODY, f32::max)
+                + 2.0 * PADDING;
+
+            let top = self.reserve(height);
+            if *is_header {
+                self.shapes.push(Shape::Rect {
+                    rect: Rect::new(x, top, table_width, height),
+                    fill: TABLE_HEADER_BACKGROUND,
+                });
+            }
+            let mut left = x;
+            for (column, lines) in cells.into_iter().enumerate() {
+                let align = match table.alignments.get(column).copied().unwrap_or_default() {
+                    Alignment::None | Alignment::Left => 0.0,
+                    Alignment::Center => 0.5,
+                    Alignment::Right => 1.0,
+                };
+                let inner = widths[column] - 2.0 * PADDING;
+                let mut y = top + PADDING;
+                for line in lines {
+                    let baseline = y + line.baseline();
+                    if row_index == 0 && column == 0 {
+                        self.take_marker(baseline);
+                    }
+                    let offset = left + PADDING + (inner - line.width).max(0.0) * align;
+                    self.emit(&line.pieces, offset, baseline);
+                    y += line.height();
+                }
+                left += widths[column];
+            }
+
+            // Borders: the top of every row, the sides of every cell
+            self.border(x, top, x + table_width, top);
+            let mut left = x;
+            for width in std::iter::once(0.0).chain(widths.iter().copied()) {
+                left += width;
+                self.border(left, top, left, top + height);
+            }
+            if row_index + 1 == rows.len() {
+                self.border(x, top + height, x + table_width, top + height);
+            }
+        }
+    }
+
+    fn border(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        self.shapes.push(Shape::Line {
+            points: vec![Point::new(x1, y1), Point::new(x2, y2)],
+            width: 0.6,
+            color: Color::BORDER,
+            dashed: false,
+        });
+    }
+
+    fn display_math(&mut self, tex: &str, x: f32, width: f32) {
+        let mut size = BODY * 1.1;
+        let mut layout = self.math_layout(tex, true, size);
+        if layout.width > width {
+            size *= width / layout.width;
+            layout = self.math_layout(tex, true, size);
+        }
+        let padding = BODY * 0.3;
+        let top = self.reserve(layout.height + layout.depth + 2.0 * padding);
+        let baseline = top + padding + layout.height;
+        self.take_marker(baseline);
+        let left = x + (width - layout.width).max(0.0) / 2.0;
+        let shapes = math_shapes(&layout, left, baseline, size, Color::TEXT);
+        self.shapes.extend(shapes);
+    }
+
+    fn diagram(&mut self, diagram: &Diagram, x: f32, width: f32) {
+        let layout = diagram::layout(diagram);
+        if layout.width <= 0.0 || layout.height <= 0.0 {
+            return;
+        }
+        let scale = (width / layout.width)
+            .min((self.bottom() - MARGIN) / layout.height)
+            .min(1.0);
+        let top = self.reserve(layout.height * scale);
+        self.take_marker(top + BODY);
+        let origin = Point::new(x + (width - layout.width * scale) / 2.0, top);
+        self.shapes.extend(diagram::shapes(&layout, origin, scale));
+    }
+}
+
+fn math_face(font: math::Font) -> Face {
+    match font {
+        math::Font::Italic => Face::Italic,
+        math::Font::Upright => Face::Regular,
+        math::Font::Bold => Face::Bold,
+    }
+}
+
+/// Shapes of a formula with its baseline starting at (`x`, `baseline`)
+fn math_shapes(
+    layout: &math_layout::Layout,
+    x: f32,
+    baseline: f32,
+    size: f32,
+    color: Color,
+) -> Vec<Shape> {
+    let rule = Metrics::pixels(size).rule;
+    let at = |(px, py): math_geometry::Point| Point::new(x + px, baseline + py);
+    let lines = |lines: Vec<Vec<math_geometry::Point>>| {
+        lines
+            .into_iter()
+            .map(|line| Shape::Line {
+                points: line.into_iter().map(at).collect(),
+                width: rule,
+                color,
+                dashed: false,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut shapes = Vec::new();
+    for item in &layout.items {
+        match item {
+            MathItem::Glyph {
+                text,
+                x: gx,
+                y: gy,
+                scale,
+                font,
+            } => shapes.push(Shape::Text {
+                text: text.clone(),
+                x: x + gx,
+                y: baseline + gy,
+                size: size * scale,
+                face: math_face(*font),
+                color,
+            }),
+            MathItem::Rule { rect, kind } if *kind == RuleKind::Arrow => {
+                shapes.extend(lines(math_geometry::arrow(*rect)))
+            }
+            MathItem::Rule { rect, .. } => shapes.push(Shape::Rect {
+                rect: Rect::new(x + rect.x, baseline + rect.y, rect.width, rect.height),
+                fill: color,
+            }),
+            MathItem::Radical { rect } => {
+                shapes.extend(lines(vec![math_geometry::radical(*rect, rule)]))
+            }
+            MathItem::Delimiter { text, rect } => match math_geometry::delimiter(text, *rect) {
+                Some(strokes) => shapes.extend(lines(strokes)),
+                None => shapes.push(Shape::Text {
+                    text: text.clone(),
+                    x: x + rect.x,
+                    y: baseline + rect.y + rect.height / 2.0 + size * 0.3,
+                    size,
+                    face: Face::Regular,
+                    color,
+                }),
+            },
+        }
+    }
+    shapes
+}
+
+/// Light theme for code, matching the standalone HTML
+fn code_theme() -> &'static Theme {
+    static THEME: OnceLock<Theme> = OnceLock::new();
+    THEME.get_or_init(|| {
+        let mut themes = ThemeSet::load_defaults();
+        themes.themes.remove("InspiredGitHub").unwrap_or_default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::markdown;
+
+    fn texts(pages: &[Page]) -> Vec<String> {
+        pages
+            .iter()
+            .flat_map(|page| &page.shapes)
+            .filter_map(|shape| match shape {
+                Shape::Text { text, .. } => Some(text.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_paragraph_runs_and_wrapping() {
+        let long = "word ".repeat(200);
+        let pages = paginate(
+            &markdown::parse(&format!("Some **bold** text.\n\n{long}\n")),
+            None,
+        );
+        let texts = texts(&pages);
+        assert_eq!(texts[..3], ["Some", "bold", "text."]);
+        // The long paragraph is filled into several lines of whole words
+        let lines: Vec<&String> = texts.iter().filter(|t| t.starts_with("word")).collect();
+        assert!(lines.len() > 5);
+        assert!(lines
+            .iter()
+            .all(|line| line.split(' ').all(|w| w == "word")));
+
+        // Every shape stays inside the page margins
+        for shape in pages.iter().flat_map(|page| &page.shapes) {
+            if let Shape::Text { x, y, .. } = shape {
+                assert!(*x >= MARGIN - 0.01 && *x < A4.0 - MARGIN);
+                assert!(*y > 0.0 && *y < A4.1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_page_breaks_and_numbers() {
+        let source: String = (1..=120).map(|i| format!("Paragraph {i}.\n\n")).collect();
+        let pages = paginate(&markdown::parse(&source), None);
+        assert!(pages.len() >= 3);
+        let count = pages.len();
+        for (index, page) in pages.iter().enumerate() {
+            assert!(texts(std::slice::from_ref(page)).contains(&format!("{} / {count}", index + 1)));
+        }
+        // Paragraphs stay in order across pages
+        let texts = texts(&pages);
+        let first = texts.iter().position(|t| t == "Paragraph 1.").unwrap();
+        let last = texts.iter().position(|t| t == "Paragraph 120.").unwrap();
+        assert!(first < last);
+    }
+
+    #[test]
+    fn test_code_math_and_diagram_shapes() {
+        let doc = markdown::parse(
+            "```rust\nfn main() {}\n```\n\n$$\\frac{a}{b}$$\n\n```mermaid\ngraph LR\n  A --> B\n```\n",
+        );
+        let pages = paginate(&doc, None);
+        let shapes = &pages[0].shapes;
+        // Highlighted code: the keyword has another color than the name
+        let color = |word: &str| {
+            shapes.iter().find_map(|shape| match shape {
+                Shape::Text { text, color, .. } if text.trim() == word => Some(*color),
+                _ => None,
+            })
+        };
+        assert_ne!(color("fn"), color("main"));
+        // The fraction bar and the diagram outline
+        assert!(shapes
+            .iter()
+            .any(|shape| matches!(shape, Shape::Rect { fill, .. } if *fill == Color::TEXT)));
+        assert!(texts(&pages).contains(&"A".to_string()));
+        assert!(shapes
+            .iter()
+            .any(|shape| matches!(shape, Shape::Polygon { fill: Some(fill), .. } if *fill == Color::DIAGRAM)));
+    }
+}
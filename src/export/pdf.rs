@@ -0,0 +1,503 @@
+//! PDF writer
+//!
+//! Writes [`Page`]s as PDF 1.7 without any external tool. Fonts are
+//! embedded whole as CID-keyed TrueType fonts addressed by glyph id, with a
+//! `ToUnicode` map so text can be searched and copied. Bold is drawn by
+//! also stroking the outlines, italic with a slanted text matrix. Images
+//! are stored as compressed RGB with a soft mask for transparency.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::sync::Arc;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use image::RgbaImage;
+
+use super::fonts::{Fonts, BOLD_STROKE, ITALIC_SKEW};
+use super::page::{Color, Page, Point, Shape};
+
+/// Write `pages` as a PDF document
+pub fn write(pages: &[Page], title: Option<&str>) -> Vec<u8> {
+    let fonts = Fonts::shared();
+    let mut writer = Writer::default();
+    let catalog = writer.reserve();
+    let tree = writer.reserve();
+    let info = writer.reserve();
+
+    // Contents first: they decide which glyphs and images are needed
+    let mut resources = Resources::default();
+    let mut page_objects = Vec::new();
+    for page in pages {
+        let mut content = String::new();
+        for shape in &page.shapes {
+            draw(&mut content, shape, page.height, fonts, &mut resources);
+        }
+        let content_id = writer.reserve();
+        writer.stream(content_id, "", content.as_bytes());
+        page_objects.push((
+            writer.reserve(),
+            content_id,
+            std::mem::take(&mut resources.page_fonts),
+            std::mem::take(&mut resources.page_images),
+        ));
+    }
+
+    let font_ids: HashMap<usize, usize> = resources
+        .glyphs
+        .iter()
+        .map(|(&file, glyphs)| (file, write_font(&mut writer, fonts, file, glyphs)))
+        .collect();
+    let image_ids: Vec<usize> = resources
+        .images
+        .iter()
+        .map(|image| write_image(&mut writer, image))
+        .collect();
+
+    for ((page_id, content_id, page_fonts, page_images), page) in page_objects.iter().zip(pages) {
+        let fonts: String = page_fonts
+            .iter()
+            .map(|file| format!("/F{file} {} 0 R ", font_ids[file]))
+            .collect();
+        let images: String = page_images
+            .iter()
+            .map(|&index| format!("/Im{index} {} 0 R ", image_ids[index]))
+            .collect();
+        writer.object(
+            *page_id,
+            &format!(
+                "<< /Type /Page /Parent {tree} 0 R /MediaBox [0 0 {} {}] /Contents {content_id} 0 R /Resources << /Font << {fonts}>> /XObject << {images}>> >> >>",
+                number(page.width),
+                number(page.height)
+            ),
+        );
+    }
+
+    let kids: Vec<String> = page_objects
+        .iter()
+        .map(|(page, ..)| format!("{page} 0 R"))
+        .collect();
+    writer.object(
+        tree,
+        &format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        ),
+    );
+    writer.object(catalog, &format!("<< /Type /Catalog /Pages {tree} 0 R >>"));
+    let title = title
+        .map(|title| format!(" /Title {}", text_string(title)))
+        .unwrap_or_default();
+    writer.object(info, &format!("<< /Producer (lala){title} >>"));
+    writer.finish(catalog, info)
+}
+
+/// Fonts and images referenced by the content streams
+#[derive(Default)]
+struct Resources {
+    /// Glyphs used per font file, with the character each one stands for
+    glyphs: BTreeMap<usize, BTreeMap<u16, char>>,
+    images: Vec<Arc<RgbaImage>>,
+    /// Font files and image indices of the page being drawn
+    page_fonts: BTreeSet<usize>,
+    page_images: BTreeSet<usize>,
+}
+
+/// Append the drawing operators of `shape` to `content`
+fn draw(
+    content: &mut String,
+    shape: &Shape,
+    height: f32,
+    fonts: &Fonts,
+    resources: &mut Resources,
+) {
+    // PDF has its origin at the bottom left
+    let y = |y: f32| number(height - y);
+    let path = |content: &mut String, points: &[Point]| {
+        for (index, point) in points.iter().enumerate() {
+            let op = if index == 0 { "m" } else { "l" };
+            let _ = writeln!(content, "{} {} {op}", number(point.x), y(point.y));
+        }
+    };
+    match shape {
+        Shape::Text {
+            text,
+            x,
+            y: baseline,
+            size,
+            face,
+            color,
+        } => {
+            let glyphs = fonts.glyphs(text, *face);
+            let _ = writeln!(content, "BT {} {}", fill(*color), stroke(*color));
+            // The rendering mode is graphics state and outlives `ET`
+            if face.is_bold() {
+                let _ = writeln!(content, "2 Tr {} w", number(size * BOLD_STROKE));
+            } else {
+                content.push_str("0 Tr\n");
+            }
+            let skew = if face.is_italic() { ITALIC_SKEW } else { 0.0 };
+            let mut pen = *x;
+            for run in glyphs.chunk_by(|a, b| a.file == b.file) {
+                let file = run[0].file;
+                let _ = write!(
+                    content,
+                    "/F{file} {} Tf 1 0 {} 1 {} {} Tm <",
+                    number(*size),
+                    number(skew),
+                    number(pen),
+                    y(*baseline)
+                );
+                resources.page_fonts.insert(file);
+                let entry = resources.glyphs.entry(file).or_default();
+                for glyph in run {
+                    let _ = write!(content, "{:04X}", glyph.id.0);
+                    entry.entry(glyph.id.0).or_insert(glyph.ch);
+                    pen += glyph.advance * size;
+                }
+                content.push_str("> Tj\n");
+            }
+            content.push_str("ET\n");
+        }
+        Shape::Rect { rect, fill: color } => {
+            let _ = writeln!(
+                content,
+                "{} {} {} {} {} re f",
+                fill(*color),
+                number(rect.x),
+                y(rect.y + rect.height),
+                number(rect.width),
+                number(rect.height)
+            );
+        }
+        Shape::Line {
+            points,
+            width,
+            color,
+            dashed,
+        } => {
+            let dash = if *dashed { "[3 3] 0 d" } else { "[] 0 d" };
+            let _ = writeln!(
+                content,
+                "{} {} w 1 J 1 j {dash}",
+                stroke(*color),
+                number(*width)
+            );
+            path(content, points);
+            content.push_str("S\n");
+        }
+        Shape::Polygon {
+            points,
+            fill: fill_color,
+            stroke: stroke_color,
+        } => {
+            let _ = writeln!(content, "0.75 w [] 0 d 1 j");
+            if let Some(color) = fill_color {
+                let _ = writeln!(content, "{}", fill(*color));
+            }
+            if let Some(color) = stroke_color {
+                let _ = writeln!(content, "{}", stroke(*color));
+            }
+            path(content, points);
+            let op = match (fill_color, stroke_color) {
+                (Some(_), Some(_)) => "b",
+                (Some(_), None) => "f",
+                (None, Some(_)) => "s",
+                (None, None) => "n",
+            };
+            let _ = writeln!(content, "{op}");
+        }
+        Shape::Image { rect, image } => {
+            let index = match resources
+                .images
+                .iter()
+                .position(|seen| Arc::ptr_eq(seen, image))
+            {
+                Some(index) => index,
+                None => {
+                    resources.images.push(image.clone());
+                    resources.images.len() - 1
+                }
+            };
+            resources.page_images.insert(index);
+            let _ = writeln!(
+                content,
+                "q {} 0 0 {} {} {} cm /Im{index} Do Q",
+                number(rect.width),
+                number(rect.height),
+                number(rect.x),
+                y(rect.y + rect.height)
+            );
+        }
+    }
+}
+
+/// Type 0 font with an embedded TrueType descendant; returns its id
+fn write_font(
+    writer: &mut Writer,
+    fonts: &Fonts,
+    file: usize,
+    glyphs: &BTreeMap<u16, char>,
+) -> usize {
+    use ab_glyph::{Font as _, GlyphId};
+
+    let font = &fonts.files[file];
+    let per_mille = |units: f32| (units * 1000.0 / font.units_per_em).round() as i32;
+    let ascent = per_mille(font.font.ascent_unscaled());
+    let descent = per_mille(font.font.descent_unscaled());
+
+    let font_file = writer.reserve();
+    writer.stream(
+        font_file,
+        &format!("/Length1 {}", font.data.len()),
+        &font.data,
+    );
+    let descriptor = writer.reserve();
+    writer.object(
+        descriptor,
+        &format!(
+            "<< /Type /FontDescriptor /FontName /{name} /Flags 32 /FontBBox [-200 {descent} 1200 {ascent}] /ItalicAngle 0 /Ascent {ascent} /Descent {descent} /CapHeight {ascent} /StemV 80 /FontFile2 {font_file} 0 R >>",
+            name = font.name
+        ),
+    );
+
+    let widths: Vec<String> = glyphs
+        .keys()
+        .map(|&id| {
+            let advance = per_mille(font.font.h_advance_unscaled(GlyphId(id)));
+            format!("{id} [{advance}]")
+        })
+        .collect();
+    let cid_font = writer.reserve();
+    writer.object(
+        cid_font,
+        &format!(
+            "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /{name} /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> /FontDescriptor {descriptor} 0 R /CIDToGIDMap /Identity /W [{widths}] >>",
+            name = font.name,
+            widths = widths.join(" ")
+        ),
+    );
+
+    let to_unicode = writer.reserve();
+    writer.stream(to_unicode, "", to_unicode_cmap(glyphs).as_bytes());
+
+    let id = writer.reserve();
+    writer.object(
+        id,
+        &format!(
+            "<< /Type /Font /Subtype /Type0 /BaseFont /{name} /Encoding /Identity-H /DescendantFonts [{cid_font} 0 R] /ToUnicode {to_unicode} 0 R >>",
+            name = font.name
+        ),
+    );
+    id
+}
+
+/// CMap from glyph ids back to the characters they were used for
+fn to_unicode_cmap(glyphs: &BTreeMap<u16, char>) -> String {
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n/CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n/CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    let entries: Vec<(&u16, &char)> = glyphs.iter().collect();
+    // At most 100 entries per block
+    for block in entries.chunks(100) {
+        let _ = writeln!(cmap, "{} beginbfchar", block.len());
+        for (id, ch) in block {
+            let mut units = [0u16; 2];
+            let hex: String = ch
+                .encode_utf16(&mut units)
+                .iter()
+                .map(|unit| format!("{unit:04X}"))
+                .collect();
+            let _ = writeln!(cmap, "<{id:04X}> <{hex}>");
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    cmap
+}
+
+/// RGB image with a soft mask when it has transparent pixels
+fn write_image(writer: &mut Writer, image: &RgbaImage) -> usize {
+    let (width, height) = image.dimensions();
+    let rgb: Vec<u8> = image
+        .pixels()
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+    let mask = if image.pixels().any(|pixel| pixel[3] < 255) {
+        let alpha: Vec<u8> = image.pixels().map(|pixel| pixel[3]).collect();
+        let id = writer.reserve();
+        writer.stream(
+            id,
+            &format!("/Type /XObject /Subtype /Image /Width {width} /Height {height} /ColorSpace /DeviceGray /BitsPerComponent 8"),
+            &alpha,
+        );
+        format!(" /SMask {id} 0 R")
+    } else {
+        String::new()
+    };
+    let id = writer.reserve();
+    writer.stream(
+        id,
+        &format!("/Type /XObject /Subtype /Image /Width {width} /Height {height} /ColorSpace /DeviceRGB /BitsPerComponent 8{mask}"),
+        &rgb,
+    );
+    id
+}
+
+fn fill(color: Color) -> String {
+    format!("{} rg", rgb(color))
+}
+
+fn stroke(color: Color) -> String {
+    format!("{} RG", rgb(color))
+}
+
+fn rgb(Color(r, g, b): Color) -> String {
+    [r, g, b].map(|c| number(c as f32 / 255.0)).join(" ")
+}
+
+/// Compact decimal with at most three fractional digits
+fn number(value: f32) -> String {
+    let text = format!("{value:.3}");
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// PDF text string; non-ASCII titles are written as UTF-16
+fn text_string(text: &str) -> String {
+    if text.is_ascii() {
+        let escaped = text
+            .replace('\\', "\\\\")
+            .replace('(', "\\(")
+            .replace(')', "\\)");
+        format!("({escaped})")
+    } else {
+        let hex: String = text
+            .encode_utf16()
+            .map(|unit| format!("{unit:04X}"))
+            .collect();
+        format!("<FEFF{hex}>")
+    }
+}
+
+/// Objects of the file being written, with their byte offsets
+#[derive(Default)]
+struct Writer {
+    out: Vec<u8>,
+    /// Offset of each object by id; id 0 is the free list head
+    offsets: Vec<Option<usize>>,
+}
+
+impl Writer {
+    fn reserve(&mut self) -> usize {
+        if self.offsets.is_empty() {
+            self.out.extend_from_slice(b"%PDF-1.7\n%\xe2\xe3\xcf\xd3\n");
+            self.offsets.push(None);
+        }
+        self.offsets.push(None);
+        self.offsets.len() - 1
+    }
+
+    fn object(&mut self, id: usize, body: &str) {
+        self.offsets[id] = Some(self.out.len());
+        self.out
+            .extend_from_slice(format!("{id} 0 obj\n{body}\nendobj\n").as_bytes());
+    }
+
+    /// Flate-compressed stream with extra dictionary entries
+    fn stream(&mut self, id: usize, entries: &str, data: &[u8]) {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        let compressed = encoder
+            .write_all(data)
+            .and_then(|_| encoder.finish())
+            .unwrap_or_default();
+        self.offsets[id] = Some(self.out.len());
+        let header = format!(
+            "{id} 0 obj\n<< /Length {} /Filter /FlateDecode {entries} >>\nstream\n",
+            compressed.len()
+        );
+        self.out.extend_from_slice(header.as_bytes());
+        self.out.extend_from_slice(&compressed);
+        self.out.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    fn finish(mut self, root: usize, info: usize) -> Vec<u8> {
+        let xref = self.out.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len());
+        for offset in &self.offsets[1..] {
+            let _ = writeln!(table, "{:010} 00000 n ", offset.unwrap_or(0));
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root {root} 0 R /Info {info} 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            self.offsets.len()
+        );
+        self.out.extend_from_slice(table.as_bytes());
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::page::{Face, Rect};
+
+    #[test]
+    fn test_structure_and_fonts() {
+        let page = Page {
+            width: 200.0,
+            height: 100.0,
+            shapes: vec![
+                Shape::Text {
+                    text: "Hi ☺".to_string(),
+                    x: 10.0,
+                    y: 20.0,
+                    size: 12.0,
+                    face: Face::Bold,
+                    color: Color::TEXT,
+                },
+                Shape::Rect {
+                    rect: Rect::new(0.0, 0.0, 10.0, 10.0),
+                    fill: Color::BORDER,
+                },
+            ],
+        };
+        let pdf = write(&[page.clone(), page], Some("Résumé"));
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.7"));
+        assert!(text.trim_end().ends_with("%%EOF"));
+        assert!(text.contains("/Type /Pages /Kids [5 0 R 7 0 R] /Count 2"));
+        assert!(text.contains("/Subtype /CIDFontType2 /BaseFont /Ubuntu-Light"));
+        assert!(text.contains("/Title <FEFF"));
+
+        // Every xref entry points at the start of its object
+        let xref = text.rfind("xref\n").unwrap();
+        for (index, line) in text[xref..].lines().skip(3).enumerate() {
+            let Some(offset) = line.strip_suffix(" 00000 n ") else {
+                break;
+            };
+            let offset: usize = offset.parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj", index + 1).as_bytes()));
+        }
+    }
+
+    #[test]
+    fn test_to_unicode() {
+        let glyphs = BTreeMap::from([(3, 'A'), (7, '😀')]);
+        let cmap = to_unicode_cmap(&glyphs);
+        assert!(cmap.contains("2 beginbfchar\n<0003> <0041>\n<0007> <D83DDE00>\n"));
+    }
+
+    #[test]
+    fn test_number() {
+        assert_eq!(number(1.0), "1");
+        assert_eq!(number(0.12345), "0.123");
+        assert_eq!(number(-0.0001), "0");
+    }
+}
@@ -0,0 +1,269 @@
+//! PNG writer
+//!
+//! Rasterizes pages with tiny-skia. Glyphs are filled from their font
+//! outlines, so the image matches the PDF; pages are stacked top to bottom
+//! on a grey background in one image.
+
+use ab_glyph::{Font as _, OutlineCurve};
+use tiny_skia::{
+    FillRule, IntSize, LineCap, LineJoin, Paint, PathBuilder, Pixmap, PixmapPaint, Stroke,
+    StrokeDash, Transform,
+};
+
+use super::fonts::{Fonts, BOLD_STROKE, ITALIC_SKEW};
+use super::page::{Color, Page, Point, Shape};
+use super::svg::PAGE_GAP;
+
+/// Largest image side; bigger documents are scaled down to fit
+const MAX_SIDE: f32 = 16384.0;
+
+/// Render `pages` at `scale` pixels per point and encode them as PNG
+pub fn write(pages: &[Page], scale: f32) -> Result<Vec<u8>, String> {
+    let width = pages.iter().map(|page| page.width).fold(0.0, f32::max);
+    let height = pages.iter().map(|page| page.height).sum::<f32>()
+        + PAGE_GAP * pages.len().saturating_sub(1) as f32;
+    let scale = scale.min(MAX_SIDE / width.max(height).max(1.0));
+    let mut pixmap = Pixmap::new(
+        (width * scale).ceil().max(1.0) as u32,
+        (height * scale).ceil().max(1.0) as u32,
+    )
+    .ok_or("the image would be empty or too large")?;
+    pixmap.fill(tiny_skia::Color::from_rgba8(0xe0, 0xe0, 0xe0, 0xff));
+
+    let mut top = 0.0;
+    for page in pages {
+        let transform = Transform::from_row(scale, 0.0, 0.0, scale, 0.0, top * scale);
+        if let Some(rect) = tiny_skia::Rect::from_xywh(0.0, 0.0, page.width, page.height) {
+            pixmap.fill_rect(rect, &paint(Color::WHITE), transform, None);
+        }
+        for shape in &page.shapes {
+            draw(&mut pixmap, shape, transform);
+        }
+        top += page.height + PAGE_GAP;
+    }
+
+    // The background is opaque, so premultiplied pixels are plain RGBA
+    let image = image::RgbaImage::from_raw(pixmap.width(), pixmap.height(), pixmap.take())
+        .ok_or("could not convert the rendered pages")?;
+    let mut png = Vec::new();
+    image
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|err| err.to_string())?;
+    Ok(png)
+}
+
+fn paint(Color(r, g, b): Color) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(r, g, b, 0xff);
+    paint.anti_alias = true;
+    paint
+}
+
+fn polyline(points: &[Point], close: bool) -> Option<tiny_skia::Path> {
+    let mut builder = PathBuilder::new();
+    let (first, rest) = points.split_first()?;
+    builder.move_to(first.x, first.y);
+    for point in rest {
+        builder.line_to(point.x, point.y);
+    }
+    if close {
+        builder.close();
+    }
+    builder.finish()
+}
+
+fn draw(pixmap: &mut Pixmap, shape: &Shape, transform: Transform) {
+    match shape {
+        Shape::Text {
+            text,
+            x,
+            y,
+            size,
+            face,
+            color,
+        } => {
+            let fonts = Fonts::shared();
+            let skew = if face.is_italic() { ITALIC_SKEW } else { 0.0 };
+            let bold = face.is_bold().then(|| Stroke {
+                width: size * BOLD_STROKE,
+                line_join: LineJoin::Round,
+                ..Stroke::default()
+            });
+            let mut pen = *x;
+            for glyph in fonts.glyphs(text, *face) {
+                let file = &fonts.files[glyph.file];
+                if let Some(path) = glyph_path(&file.font, glyph.id) {
+                    // Font units (y up) to page points, slanted for italic
+                    let s = size / file.units_per_em;
+                    let glyph_transform =
+                        Transform::from_row(s, 0.0, skew * s, -s, pen, *y).post_concat(transform);
+                    let paint = paint(*color);
+                    pixmap.fill_path(&path, &paint, FillRule::Winding, glyph_transform, None);
+                    if let Some(stroke) = &bold {
+                        // The stroke width is in points; undo the glyph scale
+                        let stroke = Stroke {
+                            width: stroke.width / s,
+                            ..stroke.clone()
+                        };
+                        pixmap.stroke_path(&path, &paint, &stroke, glyph_transform, None);
+                    }
+                }
+                pen += glyph.advance * size;
+            }
+        }
+        Shape::Rect { rect, fill } => {
+            if let Some(rect) = tiny_skia::Rect::from_xywh(rect.x, rect.y, rect.width, rect.height)
+            {
+                pixmap.fill_rect(rect, &paint(*fill), transform, None);
+            }
+        }
+        Shape::Line {
+            points,
+            width,
+            color,
+            dashed,
+        } => {
+            if let Some(path) = polyline(points, false) {
+                let stroke = Stroke {
+                    width: *width,
+                    line_cap: LineCap::Round,
+                    line_join: LineJoin::Round,
+                    dash: dashed
+                        .then(|| StrokeDash::new(vec![3.0, 3.0], 0.0))
+                        .flatten(),
+                    ..Stroke::default()
+                };
+                pixmap.stroke_path(&path, &paint(*color), &stroke, transform, None);
+            }
+        }
+        Shape::Polygon {
+            points,
+            fill,
+            stroke,
+        } => {
+            let Some(path) = polyline(points, true) else {
+                return;
+            };
+            if let Some(fill) = fill {
+                pixmap.fill_path(&path, &paint(*fill), FillRule::Winding, transform, None);
+            }
+            if let Some(color) = stroke {
+                let stroke = Stroke {
+                    width: 0.75,
+                    line_join: LineJoin::Round,
+                    ..Stroke::default()
+                };
+                pixmap.stroke_path(&path, &paint(*color), &stroke, transform, None);
+            }
+        }
+        Shape::Image { rect, image } => {
+            let (width, height) = image.dimensions();
+            // tiny-skia wants premultiplied alpha
+            let data: Vec<u8> = image
+                .pixels()
+                .flat_map(|pixel| {
+                    let [r, g, b, a] = pixel.0;
+                    let premultiply = |c: u8| (c as u16 * a as u16 / 255) as u8;
+                    [premultiply(r), premultiply(g), premultiply(b), a]
+                })
+                .collect();
+            let Some(source) =
+                IntSize::from_wh(width, height).and_then(|size| Pixmap::from_vec(data, size))
+            else {
+                return;
+            };
+            let placement = Transform::from_row(
+                rect.width / width as f32,
+                0.0,
+                0.0,
+                rect.height / height as f32,
+                rect.x,
+                rect.y,
+            )
+            .post_concat(transform);
+            let paint = PixmapPaint {
+                quality: tiny_skia::FilterQuality::Bilinear,
+                ..PixmapPaint::default()
+            };
+            pixmap.draw_pixmap(0, 0, source.as_ref(), &paint, placement, None);
+        }
+    }
+}
+
+/// Outline of a glyph in font units; `None` for blank glyphs
+fn glyph_path(font: &ab_glyph::FontArc, id: ab_glyph::GlyphId) -> Option<tiny_skia::Path> {
+    let outline = font.outline(id)?;
+    let mut builder = PathBuilder::new();
+    let mut last: Option<ab_glyph::Point> = None;
+    for curve in &outline.curves {
+        let start = match curve {
+            OutlineCurve::Line(p, _) | OutlineCurve::Quad(p, _, _) | OutlineCurve::Cubic(p, ..) => {
+                *p
+            }
+        };
+        // A curve that does not continue the last one starts a contour
+        if last != Some(start) {
+            if last.is_some() {
+                builder.close();
+            }
+            builder.move_to(start.x, start.y);
+        }
+        last = Some(match curve {
+            OutlineCurve::Line(_, end) => {
+                builder.line_to(end.x, end.y);
+                *end
+            }
+            OutlineCurve::Quad(_, control, end) => {
+                builder.quad_to(control.x, control.y, end.x, end.y);
+                *end
+            }
+            OutlineCurve::Cubic(_, c1, c2, end) => {
+                builder.cubic_to(c1.x, c1.y, c2.x, c2.y, end.x, end.y);
+                *end
+            }
+        });
+    }
+    builder.close();
+    builder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::page::{Face, Rect};
+
+    #[test]
+    fn test_rasterize_text_and_shapes() {
+        let page = Page {
+            width: 60.0,
+            height: 30.0,
+            shapes: vec![
+                Shape::Rect {
+                    rect: Rect::new(0.0, 0.0, 10.0, 10.0),
+                    fill: Color(255, 0, 0),
+                },
+                Shape::Text {
+                    text: "Hg".to_string(),
+                    x: 20.0,
+                    y: 20.0,
+                    size: 14.0,
+                    face: Face::Regular,
+                    color: Color(0, 0, 0),
+                },
+            ],
+        };
+        let png = write(&[page], 2.0).unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(image.dimensions(), (120, 60));
+        assert_eq!(image.get_pixel(5, 5).0, [255, 0, 0, 255]);
+        // Some dark pixels where the text is, none in the empty corner
+        let dark = |x0: u32, x1: u32| {
+            (x0..x1)
+                .flat_map(|x| (0..60).map(move |y| (x, y)))
+                .filter(|&(x, y)| image.get_pixel(x, y).0[0] < 100)
+                .count()
+        };
+        assert!(dark(40, 80) > 20);
+        assert_eq!(dark(100, 120), 0);
+    }
+}
@@ -0,0 +1,185 @@
+//! SVG writer for pages
+//!
+//! Pages are stacked top to bottom in one drawing. Text uses generic font
+//! families and is stretched to the width the paginator measured, so lines
+//! keep their layout whatever font the viewer substitutes.
+
+use std::fmt::Write as _;
+
+use base64::Engine as _;
+
+use super::fonts::Fonts;
+use super::page::{Face, Page, Point, Shape};
+
+/// Space between stacked pages
+pub const PAGE_GAP: f32 = 16.0;
+
+/// Write `pages` as one SVG document
+pub fn write(pages: &[Page]) -> String {
+    let width = pages.iter().map(|page| page.width).fold(0.0, f32::max);
+    let height = pages.iter().map(|page| page.height).sum::<f32>()
+        + PAGE_GAP * pages.len().saturating_sub(1) as f32;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.0}" height="{h:.0}" viewBox="0 0 {w:.0} {h:.0}">"#,
+        w = width.ceil(),
+        h = height.ceil(),
+    );
+    let mut top = 0.0;
+    for page in pages {
+        let _ = writeln!(svg, r#"<g transform="translate(0 {top})">"#);
+        let _ = writeln!(
+            svg,
+            r##"<rect width="{}" height="{}" fill="#ffffff"/>"##,
+            page.width, page.height
+        );
+        for shape in &page.shapes {
+            self::shape(&mut svg, shape);
+        }
+        svg.push_str("</g>\n");
+        top += page.height + PAGE_GAP;
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn shape(svg: &mut String, shape: &Shape) {
+    let _ = match shape {
+        Shape::Text {
+            text,
+            x,
+            y,
+            size,
+            face,
+            color,
+        } => {
+            if text.trim().is_empty() {
+                return;
+            }
+            let width = Fonts::shared().width(text, *face, *size);
+            let family = if *face == Face::Mono {
+                "monospace"
+            } else {
+                "sans-serif"
+            };
+            let weight = if face.is_bold() {
+                r#" font-weight="bold""#
+            } else {
+                ""
+            };
+            let style = if face.is_italic() {
+                r#" font-style="italic""#
+            } else {
+                ""
+            };
+            writeln!(
+                svg,
+                r#"<text x="{x:.2}" y="{y:.2}" font-family="{family}" font-size="{size:.2}"{weight}{style} fill="{}" textLength="{width:.2}" lengthAdjust="spacingAndGlyphs" xml:space="preserve">{}</text>"#,
+                color.hex(),
+                escape(text)
+            )
+        }
+        Shape::Rect { rect, fill } => writeln!(
+            svg,
+            r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="{}"/>"#,
+            rect.x,
+            rect.y,
+            rect.width,
+            rect.height,
+            fill.hex()
+        ),
+        Shape::Line {
+            points: p,
+            width,
+            color,
+            dashed,
+        } => {
+            let dash = if *dashed {
+                r#" stroke-dasharray="3 3""#
+            } else {
+                ""
+            };
+            writeln!(
+                svg,
+                r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{width:.2}" stroke-linecap="round" stroke-linejoin="round"{dash}/>"#,
+                points(p),
+                color.hex()
+            )
+        }
+        Shape::Polygon {
+            points: p,
+            fill,
+            stroke,
+        } => writeln!(
+            svg,
+            r#"<polygon points="{}" fill="{}" stroke="{}" stroke-width="0.75"/>"#,
+            points(p),
+            fill.map_or("none".to_string(), |c| c.hex()),
+            stroke.map_or("none".to_string(), |c| c.hex())
+        ),
+        Shape::Image { rect, image } => {
+            let mut png = Vec::new();
+            if image
+                .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+                .is_err()
+            {
+                return;
+            }
+            writeln!(
+                svg,
+                r#"<image x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" preserveAspectRatio="none" href="data:image/png;base64,{}"/>"#,
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+                base64::engine::general_purpose::STANDARD.encode(png)
+            )
+        }
+    };
+}
+
+fn points(points: &[Point]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{:.2},{:.2}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::page::Color;
+
+    #[test]
+    fn test_pages_are_stacked() {
+        let page = Page {
+            width: 100.0,
+            height: 50.0,
+            shapes: vec![Shape::Text {
+                text: "a < b".to_string(),
+                x: 5.0,
+                y: 20.0,
+                size: 10.0,
+                face: Face::BoldItalic,
+                color: Color::TEXT,
+            }],
+        };
+        let svg = write(&[page.clone(), page]);
+        assert!(
+            svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="116""#)
+        );
+        assert!(svg.contains(r#"<g transform="translate(0 66)">"#));
+        assert!(svg.contains(r#"font-weight="bold" font-style="italic""#));
+        assert!(svg.contains(">a &lt; b</text>"));
+    }
+}
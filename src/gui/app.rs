@@ -1,21 +1,43 @@
 use eframe::egui;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use egui::text::CCursor;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use crate::core_engine::{Buffer, BufferId};
+use crate::document::markdown;
+use crate::export;
 use crate::file_tree::FileTree;
 use crate::llm::GeminiClient;
 use crate::search::GrepEngine;
 
+use super::chat_panel::{ChatAction, ChatContext, ChatPanel};
+use super::diagnostics_view;
+use super::document_view::{self, DocumentAction, ViewOptions};
+use super::editing;
+use super::editor_cursor;
+use super::editorconfig::{self, EditorConfig, EditorSettings, Properties};
 use super::grep_panel::GrepPanel;
+use super::highlight_cache::HighlightCache;
+use super::highlighting::SyntaxHighlighter;
+use super::latex_build::LatexBuild;
 use super::markdown_preview;
 use super::search_panel::SearchPanel;
 
 // Import new modules
 use super::dialogs;
+use super::folding::{self, FoldCommand, FoldOwner, FoldRegion, FoldState};
+use super::gutter::{self, LineNumbers};
 use super::menu;
+use super::minimap::{self, MINIMAP_WIDTH, RULER_WIDTH};
+use super::outline::OutlinePanel;
+use super::panes::{self, PaneAction, PaneId, Panes, SplitDirection};
 use super::previews::{self, PreviewMode};
-use super::theme;
+use super::scroll_sync::ScrollSync;
+use super::text_info::{self, CursorInfo, Indentation};
+use super::theme::{self, ActiveTheme, ThemeSettings};
+use super::vcs;
 
 pub struct LalaApp {
     // Core components
@@ -27,6 +49,13 @@ pub struct LalaApp {
     // Editor state
     current_text: String,
     text_changed: bool,
+    highlighter: SyntaxHighlighter,
+    /// Languages picked in the status bar, overriding detection
+    languages: HashMap<BufferId, String>,
+    highlight_caches: HashMap<Option<BufferId>, HighlightCache>,
+    /// Editor lines on screen in each view in the last frame, highlighted
+    /// first
+    visible_lines: HashMap<PaneId, Range<usize>>,
 
     // Search components
     search_panel: SearchPanel,
@@ -39,17 +68,65 @@ pub struct LalaApp {
     show_file_dialog: bool,
     show_save_as_dialog: bool,
     file_path_input: String,
+    /// External link from a preview waiting for confirmation
+    pending_url: Option<String>,
 
     // Preview state
     show_preview: bool,
     preview_mode: PreviewMode,
+    scroll_sync: ScrollSync,
+    latex_build: LatexBuild,
+
+    // Gutter and folding
+    line_numbers: LineNumbers,
+    fold_states: HashMap<FoldOwner, FoldState>,
+    fold_command: Option<FoldCommand>,
+    /// Fold regions of each buffer and the language they were found for,
+    /// dropped when the buffer reports edits
+    fold_regions: HashMap<BufferId, (String, Arc<Vec<FoldRegion>>)>,
+    /// Buffer whose text `current_text` holds
+    shown_buffer: Option<BufferId>,
+    /// Committed contents of open files, `None` when not tracked by git
+    vcs_bases: HashMap<PathBuf, Option<String>>,
+    /// Changed lines of each buffer and the file they were compared for,
+    /// dropped when the buffer reports edits
+    vcs_changes: HashMap<BufferId, (PathBuf, LineChanges)>,
+
+    // Minimap and overview ruler
+    show_minimap: bool,
+    show_ruler: bool,
+    /// Scroll offset for the focused editor's next frame
+    editor_scroll: Option<f32>,
+
+    // Split views
+    panes: Panes,
+    /// Layout change requested by a tab bar, menu or shortcut
+    pane_action: Option<PaneAction>,
+
+    // Outline
+    outline: OutlinePanel,
+    show_outline: bool,
+
+    /// Move the cursor to the bracket matching the one beside it
+    go_to_bracket: bool,
+
+    // Whitespace settings
+    editor_settings: EditorSettings,
+    /// `.editorconfig` settings by file, looked up when first needed
+    editorconfigs: HashMap<PathBuf, EditorConfig>,
 
     // Theme state
-    is_light_theme: bool,
+    theme_settings: ThemeSettings,
+    theme_names: Vec<String>,
+    active_theme: ActiveTheme,
+    /// Settings and OS preference `active_theme` was resolved from
+    theme_source: Option<(ThemeSettings, Option<egui::Theme>)>,
 
     // LLM integration (optional)
     llm_client: Option<GeminiClient>,
     llm_status: String,
+    chat_panel: ChatPanel,
+    show_chat_panel: bool,
 
     // Settings
     show_settings: bool,
@@ -63,66 +140,20 @@ impl LalaApp {
         let mut buffers = HashMap::new();
         let buffer_id = BufferId(0);
 
-        let (initial_text, file_path) = match mode {
-            crate::cli::StartupMode::OpenFile(path) => {
-                // Check file size before opening
-                const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024; // 10 MB limit
-                const MAX_LINES: usize = 50000;
-
-                if let Ok(metadata) = std::fs::metadata(&path) {
-                    if metadata.len() > MAX_FILE_SIZE {
-                        eprintln!(
-                            "Error: File is too large ({} MB). Maximum supported size is {} MB.",
-                            metadata.len() / (1024 * 1024),
-                            MAX_FILE_SIZE / (1024 * 1024)
-                        );
-                        eprintln!("Opening empty editor instead.");
-                        (String::new(), None)
-                    } else {
-                        // Continue to load the file
-                        load_file_with_checks(&path, MAX_LINES)
-                    }
-                } else {
-                    // Could not get metadata, try to load anyway
-                    load_file_with_checks(&path, MAX_LINES)
-                }
-            }
+        let open_path = match mode {
+            crate::cli::StartupMode::OpenFile(path) => Some(path),
             crate::cli::StartupMode::OpenDir(path) => {
                 // TODO: Implement directory opening with file tree
                 eprintln!("Directory opening not yet fully implemented: {:?}", path);
-                (String::new(), None)
+                None
             }
             _ => {
                 // Empty editor or other modes handled elsewhere
-                (String::new(), None)
+                None
             }
         };
 
-        // Helper function to load file with line count check
-        fn load_file_with_checks(path: &PathBuf, max_lines: usize) -> (String, Option<PathBuf>) {
-            match std::fs::read_to_string(path) {
-                Ok(content) => {
-                    let line_count = content.lines().count();
-                    if line_count > max_lines {
-                        eprintln!(
-                            "Error: File has too many lines ({} lines). Maximum supported is {} lines.",
-                            line_count, max_lines
-                        );
-                        eprintln!("Opening empty editor instead.");
-                        (String::new(), None)
-                    } else {
-                        eprintln!("File loaded successfully ({} lines)", line_count);
-                        (content, Some(path.clone()))
-                    }
-                }
-                Err(err) => {
-                    eprintln!("Warning: Could not read file {:?}: {}", path, err);
-                    (String::new(), None)
-                }
-            }
-        }
-
-        let buffer = Buffer::from_string(buffer_id, initial_text.clone(), file_path);
+        let buffer = Buffer::from_string(buffer_id, String::new(), None);
         buffers.insert(buffer_id, buffer);
 
         // Try to initialize LLM client from environment
@@ -139,13 +170,23 @@ impl LalaApp {
             ),
         };
 
-        Self {
+        let file_tree = FileTree::default();
+        let chat_panel = ChatPanel::new(file_tree.root());
+        let highlighter = SyntaxHighlighter::new();
+        let theme_names = theme::theme_names(&highlighter);
+        let active_theme = ActiveTheme::named(theme::LALA_DARK, &highlighter);
+
+        let mut app = Self {
             buffers,
             active_buffer_id: Some(buffer_id),
             next_buffer_id: 1,
-            file_tree: FileTree::default(),
-            current_text: initial_text,
+            file_tree,
+            current_text: String::new(),
             text_changed: false,
+            highlighter,
+            languages: HashMap::new(),
+            highlight_caches: HashMap::new(),
+            visible_lines: HashMap::new(),
             search_panel: SearchPanel::new(),
             grep_panel: GrepPanel::new(),
             grep_engine: GrepEngine::new(),
@@ -154,15 +195,63 @@ impl LalaApp {
             show_file_dialog: false,
             show_save_as_dialog: false,
             file_path_input: String::new(),
+            pending_url: None,
             show_preview: false,
             preview_mode: PreviewMode::None,
-            is_light_theme: false, // Default to dark theme
+            scroll_sync: ScrollSync::new(),
+            latex_build: LatexBuild::new(),
+            line_numbers: LineNumbers::default(),
+            fold_states: HashMap::new(),
+            fold_command: None,
+            fold_regions: HashMap::new(),
+            shown_buffer: Some(buffer_id),
+            vcs_bases: HashMap::new(),
+            vcs_changes: HashMap::new(),
+            show_minimap: true,
+            show_ruler: true,
+            editor_scroll: None,
+            panes: Panes::new(buffer_id),
+            pane_action: None,
+            outline: OutlinePanel::new(),
+            show_outline: false,
+            go_to_bracket: false,
+            editor_settings: EditorSettings::load(),
+            editorconfigs: HashMap::new(),
+            theme_settings: ThemeSettings::load(),
+            theme_names,
+            active_theme,
+            theme_source: None,
             llm_client,
             llm_status,
+            chat_panel,
+            show_chat_panel: false,
             show_settings: false,
             api_key_input: String::new(),
             ai_enabled,
+        };
+
+        // Load the file like File→Open, with its `.editorconfig` charset and
+        // whitespace settings
+        if let Some(path) = open_path {
+            match app.read_file(&path) {
+                Ok(content) => {
+                    eprintln!(
+                        "File loaded successfully ({} lines)",
+                        content.lines().count()
+                    );
+                    app.buffers.insert(
+                        buffer_id,
+                        Buffer::from_string(buffer_id, content.clone(), Some(path)),
+                    );
+                    app.current_text = content;
+                }
+                Err(err) => {
+                    eprintln!("Error: {err}");
+                    eprintln!("Opening empty editor instead.");
+                }
+            }
         }
+        app
     }
 
     fn detect_preview_mode(&self) -> PreviewMode {
@@ -174,11 +263,510 @@ impl LalaApp {
         PreviewMode::None
     }
 
+    /// Re-resolve the theme when the settings or the OS preference change,
+    /// saving changed settings
+    fn update_theme(&mut self, ctx: &egui::Context) {
+        let source = (self.theme_settings.clone(), ctx.system_theme());
+        if self.theme_source.as_ref() == Some(&source) {
+            return;
+        }
+
+        let (active, error) = theme::resolve(&source.0, source.1, &self.highlighter);
+        if let Some(error) = error {
+            self.llm_status = error;
+        }
+        if let Some((previous, _)) = &self.theme_source {
+            if *previous != source.0 {
+                if let Err(err) = source.0.save() {
+                    self.llm_status = err;
+                }
+            }
+        }
+        theme::set_syntax_theme(
+            ctx,
+            Arc::new(self.highlighter.theme(&active.syntax).clone()),
+        );
+        self.active_theme = active;
+        self.theme_source = Some(source);
+    }
+
+    /// Keep the active buffer in step with the editor text, moving its fold
+    /// state along with the edits the buffer reports; `true` after edits
+    fn sync_active_buffer(&mut self) -> bool {
+        let Some(buffer_id) = self.active_buffer_id else {
+            return false;
+        };
+        let Some(buffer) = self.buffers.get_mut(&buffer_id) else {
+            return false;
+        };
+
+        // Panels such as grep switch buffers without loading their text
+        if self.shown_buffer != Some(buffer_id) {
+            self.shown_buffer = Some(buffer_id);
+            if buffer.rope() != self.current_text.as_str() {
+                self.current_text = buffer.content();
+                self.text_changed = buffer.is_dirty();
+            }
+            if !buffer.take_changes().is_empty() {
+                self.fold_regions.remove(&buffer_id);
+                self.vcs_changes.remove(&buffer_id);
+            }
+            return false;
+        }
+
+        buffer.set_content(&self.current_text);
+        let changes = buffer.take_changes();
+        if changes.is_empty() {
+            return false;
+        }
+        self.fold_regions.remove(&buffer_id);
+        self.vcs_changes.remove(&buffer_id);
+        let owner = fold_owner(buffer_id, buffer);
+        if let Some(state) = self.fold_states.get_mut(&owner) {
+            for change in &changes {
+                state.apply(change);
+            }
+        }
+        true
+    }
+
+    /// Fold regions of the editor text, kept per buffer until it is edited
+    /// or its language changes
+    fn fold_regions(&mut self, language: &str) -> Arc<Vec<FoldRegion>> {
+        let Some(buffer_id) = self.active_buffer_id else {
+            return Arc::new(folding::regions(&self.current_text, language));
+        };
+        if let Some((cached_language, regions)) = self.fold_regions.get(&buffer_id) {
+            if cached_language == language {
+                return regions.clone();
+            }
+        }
+        let regions = Arc::new(folding::regions(&self.current_text, language));
+        self.fold_regions
+            .insert(buffer_id, (language.to_string(), regions.clone()));
+        regions
+    }
+
+    /// Run a folding command on the active buffer's fold state
+    fn run_fold_command(
+        &mut self,
+        ctx: &egui::Context,
+        command: FoldCommand,
+        regions: &[FoldRegion],
+    ) {
+        let Some(owner) = self.active_fold_owner() else {
+            return;
+        };
+        let state = self.fold_states.entry(owner).or_default();
+        let cursor = editor_cursor::char_range(ctx).map_or(0, |range| range.end);
+        let cursor_line = self
+            .current_text
+            .chars()
+            .take(cursor)
+            .filter(|&c| c == '\n')
+            .count();
+        match command {
+            FoldCommand::FoldAll => state.fold_all(regions),
+            FoldCommand::UnfoldAll => state.unfold_all(),
+            FoldCommand::FoldToLevel(level) => state.fold_to_level(regions, level),
+            FoldCommand::FoldAtCursor => {
+                state.fold_at(regions, cursor_line);
+            }
+            FoldCommand::UnfoldAtCursor => {
+                state.unfold_at(regions, cursor_line);
+            }
+        }
+    }
+
+    fn active_fold_owner(&self) -> Option<FoldOwner> {
+        let buffer_id = self.active_buffer_id?;
+        let buffer = self.buffers.get(&buffer_id)?;
+        Some(fold_owner(buffer_id, buffer))
+    }
+
+    /// Language of the active buffer: picked in the status bar, or detected
+    fn active_language(&self, file_path: Option<&Path>) -> String {
+        self.language_of(self.active_buffer_id, file_path, &self.current_text)
+    }
+
+    /// `.editorconfig` settings of the file at `path`
+    fn editorconfig(&mut self, path: &Path) -> &EditorConfig {
+        self.editorconfigs
+            .entry(path.to_path_buf())
+            .or_insert_with(|| {
+                editorconfig::lookup(path).unwrap_or_else(|err| {
+                    eprintln!("{err}");
+                    EditorConfig::default()
+                })
+            })
+    }
+
+    /// Whitespace settings in effect for a file: its `.editorconfig`, then
+    /// the defaults for its language
+    fn whitespace(&mut self, path: Option<&Path>, language: &str) -> Properties {
+        let defaults = self.editor_settings.for_language(language);
+        match path {
+            Some(path) => self.editorconfig(path).properties.or(&defaults),
+            None => defaults,
+        }
+    }
+
+    /// Language picked for a buffer in the status bar, or detected
+    fn language_of(
+        &self,
+        buffer_id: Option<BufferId>,
+        file_path: Option<&Path>,
+        text: &str,
+    ) -> String {
+        buffer_id
+            .and_then(|id| self.languages.get(&id))
+            .and_then(|name| self.highlighter.find_language(name))
+            .unwrap_or_else(|| self.highlighter.detect(file_path, text))
+            .name
+            .clone()
+    }
+
+    /// Show the focused pane's buffer and apply a pending layout change,
+    /// moving the active buffer and editor focus along with it
+    fn update_panes(&mut self, ctx: &egui::Context) {
+        // Buffers opened by menus and panels show in the focused pane
+        if let Some(buffer_id) = self.active_buffer_id {
+            if self.panes.focused_buffer() != Some(buffer_id) {
+                self.panes.show_buffer(buffer_id);
+            }
+        }
+        let Some(action) = self.pane_action.take() else {
+            return;
+        };
+
+        let before = self.panes.clone();
+        self.panes.apply(action);
+        // Closing the last view of an unsaved buffer would lose its edits
+        let closed: Vec<BufferId> = self
+            .buffers
+            .keys()
+            .copied()
+            .filter(|&id| before.shows(id) && !self.panes.shows(id))
+            .collect();
+        if let Some(&dirty) = closed
+            .iter()
+            .find(|id| self.buffers.get(id).is_some_and(Buffer::is_dirty))
+        {
+            self.llm_status = format!("Save {} before closing it", self.tab_title(dirty));
+            self.panes = before;
+            return;
+        }
+        for buffer_id in closed {
+            self.close_buffer(buffer_id);
+        }
+
+        if self.panes.focused() != before.focused() {
+            panes::move_editor_focus(ctx, before.focused(), self.panes.focused());
+        }
+        match self.panes.focused_buffer() {
+            Some(buffer_id) => {
+                if self.active_buffer_id != Some(buffer_id) {
+                    self.active_buffer_id = Some(buffer_id);
+                    self.sync_active_buffer();
+                }
+            }
+            // The last tab was closed
+            None => {
+                self.new_file();
+                self.sync_active_buffer();
+                if let Some(buffer_id) = self.active_buffer_id {
+                    self.panes.show_buffer(buffer_id);
+                }
+            }
+        }
+    }
+
+    /// Keep search matches in step with edits, and select the match the
+    /// search panel moved to
+    fn update_search(&mut self, ctx: &egui::Context, edited: bool) {
+        if !self.show_search_panel {
+            return;
+        }
+        let Some(buffer) = self.active_buffer_id.and_then(|id| self.buffers.get(&id)) else {
+            return;
+        };
+        if edited {
+            self.search_panel.refresh(buffer);
+        }
+        if let Some(result) = self.search_panel.take_reveal() {
+            let (start, end) = (result.range.start, result.range.end);
+            let line_start = |line: usize| {
+                editor_cursor::char_index(
+                    &self.current_text,
+                    line_offset(&self.current_text, line + 1),
+                )
+            };
+            let start = line_start(start.line) + start.col;
+            let end = line_start(end.line) + end.col;
+            editor_cursor::set_char_range(ctx, start..end);
+            self.scroll_sync
+                .jump_to(editor_cursor::byte_index(&self.current_text, start));
+        }
+    }
+
+    /// Forget a buffer no view shows any more
+    fn close_buffer(&mut self, buffer_id: BufferId) {
+        if let Some(buffer) = self.buffers.remove(&buffer_id) {
+            self.fold_states.remove(&FoldOwner::Untitled(buffer_id));
+            if let Some(path) = buffer.file_path() {
+                self.vcs_bases.remove(path);
+            }
+        }
+        self.languages.remove(&buffer_id);
+        self.highlight_caches.remove(&Some(buffer_id));
+        self.fold_regions.remove(&buffer_id);
+        self.vcs_changes.remove(&buffer_id);
+        if self.active_buffer_id == Some(buffer_id) {
+            self.active_buffer_id = None;
+            self.shown_buffer = None;
+        }
+    }
+
+    /// File name of a buffer's tab, marked while it has unsaved edits
+    fn tab_title(&self, buffer_id: BufferId) -> String {
+        let Some(buffer) = self.buffers.get(&buffer_id) else {
+            return "Untitled".to_string();
+        };
+        let name = buffer
+            .file_path()
+            .and_then(|path| path.file_name())
+            .and_then(|name| name.to_str())
+            .unwrap_or("Untitled");
+        if buffer.is_dirty() {
+            format!("{name} ●")
+        } else {
+            name.to_string()
+        }
+    }
+
+    fn tab_bar(
+        &self,
+        ui: &mut egui::Ui,
+        pane: PaneId,
+        focused: bool,
+        detachable: bool,
+    ) -> Option<PaneAction> {
+        let tabs = self.panes.pane(pane)?;
+        panes::tab_bar(ui, pane, tabs, focused, detachable, |buffer_id| {
+            self.tab_title(buffer_id)
+        })
+    }
+
+    /// Editor for a view other than the focused one, sharing the text of
+    /// the active buffer when it shows the same one; `true` once it takes
+    /// the keyboard focus
+    fn show_view(&mut self, ui: &mut egui::Ui, pane: PaneId, editor_id: egui::Id) -> bool {
+        let Some(buffer_id) = self.panes.pane(pane).and_then(|tabs| tabs.buffer()) else {
+            ui.centered_and_justified(|ui| ui.weak("No file"));
+            return false;
+        };
+        let Some(buffer) = self.buffers.get(&buffer_id) else {
+            return false;
+        };
+        let shared = self.active_buffer_id == Some(buffer_id);
+        let mut text = if shared {
+            std::mem::take(&mut self.current_text)
+        } else {
+            buffer.content()
+        };
+        let language = self.language_of(
+            Some(buffer_id),
+            buffer.file_path().map(PathBuf::as_path),
+            &text,
+        );
+        let bg_color = self.active_theme.editor_background;
+        let text_color = self.active_theme.editor_text;
+        let available_height = ui.available_height();
+
+        let highlighter = &self.highlighter;
+        let syntax = highlighter
+            .find_language(&language)
+            .unwrap_or_else(|| highlighter.detect(None, ""));
+        let syntax_theme = highlighter.theme(&self.active_theme.syntax);
+        let default_color = SyntaxHighlighter::default_color(syntax_theme);
+        let cache = self.highlight_caches.entry(Some(buffer_id)).or_default();
+        let visible = self.visible_lines.get(&pane).cloned().unwrap_or(0..100);
+        let mut highlighting = false;
+        let mut layouter = |ui: &egui::Ui, text: &dyn egui::TextBuffer, wrap_width| {
+            let text = text.as_str();
+            highlighting = cache.update(highlighter, text, syntax, syntax_theme, visible.clone());
+            let font = egui::TextStyle::Monospace.resolve(ui.style());
+            let mut job = cache.layout_job(text, font, default_color);
+            job.wrap.max_width = wrap_width;
+            ui.fonts_mut(|fonts| fonts.layout_job(job))
+        };
+
+        let line_numbers = self.line_numbers;
+        let mut gutter_clicked = false;
+        let scroll = egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                ui.spacing_mut().item_spacing.x = 0.0;
+                let gutter_width = gutter::width(ui, text.lines().count());
+                let current_line = ui.painter().add(egui::Shape::Noop);
+                let (output, gutter_rect) = ui
+                    .horizontal_top(|ui| {
+                        let (gutter_rect, _) = ui.allocate_exact_size(
+                            egui::vec2(gutter_width, available_height),
+                            egui::Sense::hover(),
+                        );
+                        let output = egui::TextEdit::multiline(&mut text)
+                            .id(editor_id)
+                            .font(egui::TextStyle::Monospace)
+                            .desired_width(f32::INFINITY)
+                            .min_size(egui::vec2(0.0, available_height))
+                            .frame(false)
+                            .background_color(bg_color)
+                            .text_color(text_color)
+                            .layouter(&mut layouter)
+                            .show(ui);
+                        (output, gutter_rect)
+                    })
+                    .inner;
+                let cursor_line = output
+                    .cursor_range
+                    .map(|range| {
+                        let index = editor_cursor::byte_index(&text, range.primary.index);
+                        text[..index].matches('\n').count()
+                    })
+                    .unwrap_or(0);
+                if output.response.has_focus() {
+                    gutter::paint_current_line(
+                        ui,
+                        current_line,
+                        &output,
+                        gutter_rect,
+                        cursor_line,
+                        bg_color.lerp_to_gamma(text_color, 0.06),
+                    );
+                }
+                gutter_clicked = gutter::show(
+                    ui,
+                    gutter_rect,
+                    &output,
+                    cursor_line,
+                    line_numbers,
+                    &gutter::Marks::default(),
+                    text_color,
+                )
+                .is_some();
+                output
+            });
+        if highlighting {
+            ui.ctx()
+                .request_repaint_after(std::time::Duration::from_millis(16));
+        }
+        self.visible_lines
+            .insert(pane, visible_lines(&scroll.inner, scroll.inner_rect));
+
+        let response = &scroll.inner.response;
+        let focused = response.has_focus() || gutter_clicked;
+        if shared {
+            self.current_text = text;
+            self.text_changed |= response.changed();
+        } else if response.changed() {
+            if let Some(buffer) = self.buffers.get_mut(&buffer_id) {
+                buffer.set_content(&text);
+                let changes = buffer.take_changes();
+                if !changes.is_empty() {
+                    self.fold_regions.remove(&buffer_id);
+                    self.vcs_changes.remove(&buffer_id);
+                }
+                if let Some(state) = self.fold_states.get_mut(&fold_owner(buffer_id, buffer)) {
+                    for change in &changes {
+                        state.apply(change);
+                    }
+                }
+            }
+        }
+        focused
+    }
+
+    /// Panes moved into windows of their own; closing a window returns its
+    /// tabs to the focused pane
+    fn show_windows(&mut self, ctx: &egui::Context) {
+        for pane in self.panes.windows().to_vec() {
+            let title = self
+                .panes
+                .pane(pane)
+                .and_then(|tabs| tabs.buffer())
+                .map_or_else(
+                    || "lala".to_string(),
+                    |id| format!("{} - lala", self.tab_title(id)),
+                );
+            let viewport = egui::ViewportId::from_hash_of(("pane_window", pane));
+            let builder = egui::ViewportBuilder::default()
+                .with_title(title.clone())
+                .with_inner_size([800.0, 600.0]);
+            let bg_color = self.active_theme.editor_background;
+            let mut action = None;
+            ctx.show_viewport_immediate(viewport, builder, |ctx, class| {
+                let mut show = |ui: &mut egui::Ui| {
+                    if let Some(tab_action) = self.tab_bar(ui, pane, false, false) {
+                        action = Some(tab_action);
+                    }
+                    self.show_view(ui, pane, panes::editor_id(pane));
+                };
+                if class == egui::ViewportClass::Embedded {
+                    // Without native windows, the pane floats in the main one
+                    let mut open = true;
+                    egui::Window::new(&title)
+                        .id(egui::Id::new(viewport))
+                        .open(&mut open)
+                        .default_size([600.0, 400.0])
+                        .show(ctx, |ui| show(ui));
+                    if !open {
+                        action = Some(PaneAction::ClosePane(pane));
+                    }
+                } else {
+                    egui::CentralPanel::default()
+                        .frame(egui::Frame::default().fill(bg_color).inner_margin(0.0))
+                        .show(ctx, |ui| show(ui));
+                    if ctx.input(|i| i.viewport().close_requested()) {
+                        action = Some(PaneAction::ClosePane(pane));
+                    }
+                }
+            });
+            if let Some(action) = action {
+                self.pane_action = Some(action);
+                ctx.request_repaint();
+            }
+        }
+    }
+
+    /// Put the editor cursor at byte `offset` and scroll it into view
+    fn jump_to(&mut self, ctx: &egui::Context, offset: usize) {
+        let char_index = editor_cursor::char_index(&self.current_text, offset);
+        editor_cursor::set_cursor(ctx, char_index);
+        ctx.memory_mut(|memory| memory.request_focus(editor_cursor::main_editor_id()));
+        self.scroll_sync.jump_to(offset);
+    }
+
+    fn active_file_path(&self) -> Option<PathBuf> {
+        self.active_buffer_id
+            .and_then(|id| self.buffers.get(&id))
+            .and_then(|buffer| buffer.file_path())
+            .cloned()
+    }
+
+    /// Directory of the active buffer's file, for resolving relative paths
+    fn active_file_dir(&self) -> Option<PathBuf> {
+        self.active_buffer_id
+            .and_then(|id| self.buffers.get(&id))
+            .and_then(|buffer| buffer.file_path())
+            .and_then(|path| path.parent())
+            .map(Path::to_path_buf)
+    }
+
     fn handle_keyboard_shortcuts(&mut self, ctx: &egui::Context) {
         // Ctrl+S: Save file
-        if ctx.input(|i| i.modifiers.command && !i.modifiers.shift && i.key_pressed(egui::Key::S))
-        {
-            self.save_file();
+        if ctx.input(|i| i.modifiers.command && !i.modifiers.shift && i.key_pressed(egui::Key::S)) {
+            self.save_file(ctx);
         }
 
         // Ctrl+Shift+S: Save as
@@ -187,18 +775,22 @@ impl LalaApp {
         }
 
         // Ctrl+O: Open file
-        if ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::O)) {
+        if ctx.input(|i| i.modifiers.command && !i.modifiers.shift && i.key_pressed(egui::Key::O)) {
             self.show_file_dialog = true;
         }
 
+        // Ctrl+Shift+O: Go to symbol
+        if ctx.input(|i| i.modifiers.command && i.modifiers.shift && i.key_pressed(egui::Key::O)) {
+            self.outline.open_quick_pick();
+        }
+
         // Ctrl+N: New file
         if ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::N)) {
             self.new_file();
         }
 
         // Ctrl+F: Open search panel
-        if ctx.input(|i| i.modifiers.command && !i.modifiers.shift && i.key_pressed(egui::Key::F))
-        {
+        if ctx.input(|i| i.modifiers.command && !i.modifiers.shift && i.key_pressed(egui::Key::F)) {
             self.show_search_panel = true;
         }
 
@@ -228,6 +820,54 @@ impl LalaApp {
             }
         }
 
+        // Ctrl+Shift+[ / Ctrl+Shift+]: Fold / unfold at the cursor
+        if ctx.input(|i| {
+            i.modifiers.command
+                && i.modifiers.shift
+                && (i.key_pressed(egui::Key::OpenBracket)
+                    || i.key_pressed(egui::Key::OpenCurlyBracket))
+        }) {
+            self.fold_command = Some(FoldCommand::FoldAtCursor);
+        }
+        if ctx.input(|i| {
+            i.modifiers.command
+                && i.modifiers.shift
+                && (i.key_pressed(egui::Key::CloseBracket)
+                    || i.key_pressed(egui::Key::CloseCurlyBracket))
+        }) {
+            self.fold_command = Some(FoldCommand::UnfoldAtCursor);
+        }
+
+        // Ctrl+\ / Ctrl+Shift+\: Split right / down (Shift turns `\` into
+        // `|` on many layouts)
+        if ctx.input(|i| {
+            i.modifiers.command && !i.modifiers.shift && i.key_pressed(egui::Key::Backslash)
+        }) {
+            self.pane_action = Some(PaneAction::Split(SplitDirection::Right));
+        }
+        if ctx.input(|i| {
+            i.modifiers.command
+                && i.modifiers.shift
+                && (i.key_pressed(egui::Key::Backslash) || i.key_pressed(egui::Key::Pipe))
+        }) {
+            self.pane_action = Some(PaneAction::Split(SplitDirection::Down));
+        }
+
+        // Ctrl+W: Close tab
+        if ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::W)) {
+            self.pane_action = Some(PaneAction::CloseFocusedTab);
+        }
+
+        // Ctrl+M: Go to matching bracket
+        if ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::M)) {
+            self.go_to_bracket = true;
+        }
+
+        // Ctrl+L: Toggle AI chat panel
+        if ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::L)) {
+            self.show_chat_panel = !self.show_chat_panel;
+        }
+
         // Escape: Close panels
         if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
             self.show_search_panel = false;
@@ -237,7 +877,7 @@ impl LalaApp {
         }
     }
 
-    fn save_file(&mut self) {
+    fn save_file(&mut self, ctx: &egui::Context) {
         if let Some(buffer_id) = self.active_buffer_id {
             let file_path = self
                 .buffers
@@ -247,9 +887,10 @@ impl LalaApp {
 
             if let Some(file_path) = file_path {
                 // Save to existing file
-                if let Err(e) = std::fs::write(&file_path, &self.current_text) {
+                if let Err(e) = self.write_file(&file_path) {
                     eprintln!("Failed to save file: {e}");
                 } else {
+                    self.latex_build.file_saved(ctx, &file_path);
                     // Update buffer and mark as clean
                     if let Some(buffer) = self.buffers.get_mut(&buffer_id) {
                         *buffer = Buffer::from_string(
@@ -267,12 +908,13 @@ impl LalaApp {
         }
     }
 
-    fn save_file_as(&mut self, path: PathBuf) {
+    fn save_file_as(&mut self, ctx: &egui::Context, path: PathBuf) {
         if let Some(buffer_id) = self.active_buffer_id {
             // Save to new file
-            if let Err(e) = std::fs::write(&path, &self.current_text) {
+            if let Err(e) = self.write_file(&path) {
                 eprintln!("Failed to save file: {e}");
             } else {
+                self.latex_build.file_saved(ctx, &path);
                 // Update buffer with new path and mark as clean
                 if let Some(buffer) = self.buffers.get_mut(&buffer_id) {
                     *buffer = Buffer::from_string(buffer_id, self.current_text.clone(), Some(path));
@@ -282,54 +924,100 @@ impl LalaApp {
         }
     }
 
-    fn open_file(&mut self, path: PathBuf) {
-        // Check file size before opening
-        if let Ok(metadata) = std::fs::metadata(&path) {
-            const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024; // 10 MB limit
+    /// Write the editor text to `path` with the file's whitespace settings
+    /// applied, keeping the saved text in the editor
+    fn write_file(&mut self, path: &Path) -> Result<(), String> {
+        let language = self.active_language(Some(path));
+        let whitespace = self.whitespace(Some(path), &language);
+        let text = whitespace.normalize(&self.current_text);
+        let bytes = whitespace.encode(&text)?;
+        std::fs::write(path, bytes).map_err(|e| e.to_string())?;
+        self.current_text = text;
+        if path.file_name() == Some(editorconfig::FILE_NAME.as_ref()) {
+            self.editorconfigs.clear();
+        }
+        Ok(())
+    }
+
+    /// Export the active buffer next to its file, e.g. `notes.md` to `notes.pdf`
+    fn export_file(&mut self, format: export::Format) {
+        let Some(path) = self.active_file_path() else {
+            // The document kind comes from the file name, so save first
+            self.llm_status = "Save the file before exporting it".to_string();
+            self.show_save_as_dialog = true;
+            return;
+        };
+        let output = path.with_extension(format.extension());
+        self.llm_status =
+            match export::export(&path, &self.current_text, format).and_then(|exported| {
+                std::fs::write(&output, exported.bytes).map_err(|err| err.to_string())?;
+                Ok(exported.warnings)
+            }) {
+                Ok(warnings) if warnings.is_empty() => format!("Exported {}", output.display()),
+                Ok(warnings) => format!(
+                    "Exported {} (warning: {})",
+                    output.display(),
+                    warnings.join("; ")
+                ),
+                Err(err) => format!("Error: export failed: {err}"),
+            };
+    }
+
+    /// Read and decode a file with the charset of its `.editorconfig`,
+    /// refusing files too large for the editor
+    fn read_file(&mut self, path: &Path) -> Result<String, String> {
+        const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024; // 10 MB limit
+        const MAX_LINES: usize = 50000;
+
+        if let Ok(metadata) = std::fs::metadata(path) {
             if metadata.len() > MAX_FILE_SIZE {
-                eprintln!(
-                    "Warning: File is too large ({} MB). Maximum supported size is {} MB.",
+                return Err(format!(
+                    "File too large ({} MB). Max: {} MB",
                     metadata.len() / (1024 * 1024),
                     MAX_FILE_SIZE / (1024 * 1024)
-                );
-                self.llm_status = format!(
-                    "Error: File too large ({} MB). Max: {} MB",
-                    metadata.len() / (1024 * 1024),
-                    MAX_FILE_SIZE / (1024 * 1024)
-                );
-                return;
+                ));
             }
         }
 
-        if let Ok(content) = std::fs::read_to_string(&path) {
-            // Additional check for line count
-            let line_count = content.lines().count();
-            const MAX_LINES: usize = 50000;
-            if line_count > MAX_LINES {
-                eprintln!(
-                    "Warning: File has too many lines ({} lines). Maximum supported is {} lines.",
-                    line_count, MAX_LINES
-                );
-                self.llm_status = format!(
-                    "Error: Too many lines ({} lines). Max: {} lines",
-                    line_count, MAX_LINES
-                );
+        // Read the configuration again, it may have changed since
+        self.editorconfigs.remove(path);
+        let charset = self.editorconfig(path).properties.charset;
+        let content = std::fs::read(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))
+            .and_then(|bytes| editorconfig::decode(bytes, charset))?;
+
+        let line_count = content.lines().count();
+        if line_count > MAX_LINES {
+            return Err(format!(
+                "Too many lines ({} lines). Max: {} lines",
+                line_count, MAX_LINES
+            ));
+        }
+        Ok(content)
+    }
+
+    fn open_file(&mut self, path: PathBuf) {
+        let content = match self.read_file(&path) {
+            Ok(content) => content,
+            Err(err) => {
+                eprintln!("Warning: {err}");
+                self.llm_status = format!("Error: {err}");
                 return;
             }
+        };
+        let line_count = content.lines().count();
 
-            let buffer_id = BufferId(self.next_buffer_id);
-            self.next_buffer_id += 1;
+        let buffer_id = BufferId(self.next_buffer_id);
+        self.next_buffer_id += 1;
 
-            let buffer = Buffer::from_string(buffer_id, content.clone(), Some(path));
-            self.buffers.insert(buffer_id, buffer);
-            self.active_buffer_id = Some(buffer_id);
-            self.current_text = content;
-            self.text_changed = false;
-            self.llm_status = format!("File loaded ({} lines)", line_count);
-        } else {
-            eprintln!("Failed to read file: {:?}", path);
-            self.llm_status = format!("Error: Failed to read file");
-        }
+        // Reread the committed version, it may have changed since
+        self.vcs_bases.remove(&path);
+        let buffer = Buffer::from_string(buffer_id, content.clone(), Some(path));
+        self.buffers.insert(buffer_id, buffer);
+        self.active_buffer_id = Some(buffer_id);
+        self.current_text = content;
+        self.text_changed = false;
+        self.llm_status = format!("File loaded ({} lines)", line_count);
     }
 
     fn new_file(&mut self) {
@@ -343,6 +1031,30 @@ impl LalaApp {
         self.text_changed = false;
     }
 
+    fn show_chat_panel(&mut self, ctx: &egui::Context) {
+        let file_name = self
+            .active_buffer_id
+            .and_then(|id| self.buffers.get(&id))
+            .and_then(|b| b.file_path())
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Untitled".to_string());
+        let selection = editor_cursor::selected_text(ctx, &self.current_text);
+
+        let llm_client = self.llm_client.as_ref().filter(|_| self.ai_enabled);
+        let context = ChatContext {
+            file_name: &file_name,
+            file_text: &self.current_text,
+            selection: selection.as_deref(),
+            grep_results: self.grep_panel.results(),
+        };
+
+        if let Some(ChatAction::InsertCode(code)) = self.chat_panel.show(ctx, llm_client, context) {
+            editor_cursor::insert_at_cursor(ctx, &mut self.current_text, &code);
+            self.text_changed = true;
+        }
+    }
+
     fn show_main_editor(&mut self, ctx: &egui::Context) {
         // Update preview mode when file changes
         if self.show_preview && self.preview_mode == PreviewMode::None {
@@ -355,7 +1067,9 @@ impl LalaApp {
             let mut open_file = false;
             let mut save_file = false;
             let mut save_as = false;
+            let mut export = None;
             let mut show_replace = false;
+            let mut go_to_symbol = false;
 
             menu::render_menu_bar(
                 ui,
@@ -363,13 +1077,26 @@ impl LalaApp {
                 &mut open_file,
                 &mut save_file,
                 &mut save_as,
+                &mut export,
                 &mut self.show_search_panel,
                 &mut show_replace,
                 &mut self.show_grep_panel,
                 &mut self.show_settings,
-                &mut self.is_light_theme,
+                &mut self.show_chat_panel,
+                &mut self.show_outline,
+                &mut go_to_symbol,
+                &mut self.go_to_bracket,
+                &mut self.pane_action,
+                &mut self.line_numbers,
+                &mut self.show_minimap,
+                &mut self.show_ruler,
+                &mut self.fold_command,
+                &mut self.theme_settings,
+                &self.theme_names,
+                ctx.system_theme(),
                 &mut self.show_preview,
                 &mut self.preview_mode,
+                &mut self.latex_build,
                 &self.llm_status,
                 &self.llm_client,
                 self.ai_enabled,
@@ -387,16 +1114,33 @@ impl LalaApp {
                 self.show_file_dialog = true;
             }
             if save_file {
-                self.save_file();
+                self.save_file(ctx);
             }
             if save_as {
                 self.show_save_as_dialog = true;
             }
+            if let Some(format) = export {
+                self.export_file(format);
+            }
             if show_replace {
                 self.search_panel.set_replace_mode(true);
             }
+            if go_to_symbol {
+                self.outline.open_quick_pick();
+            }
         });
 
+        // Mermaid sources are checked as they are edited, LaTeX sources by
+        // the last compilation
+        let lint_mermaid = self.detect_preview_mode() == PreviewMode::Mermaid;
+        let edited = self.sync_active_buffer();
+        self.update_panes(ctx);
+        self.update_search(ctx, edited);
+        let file_path = self.active_file_path();
+        let language = self.active_language(file_path.as_deref());
+        let whitespace = self.whitespace(file_path.as_deref(), &language);
+        let indentation = whitespace.indentation(Indentation::detect(&self.current_text));
+
         // Status bar
         egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
             ui.horizontal(|ui| {
@@ -407,12 +1151,123 @@ impl LalaApp {
                 if self.text_changed {
                     ui.label("| Modified");
                 }
+
+                if lint_mermaid {
+                    let diagnostics =
+                        diagnostics_view::mermaid_diagnostics(ctx, &self.current_text);
+                    if let Some(summary) = diagnostics_view::summary(&diagnostics) {
+                        let color = diagnostics_view::severity_color(diagnostics[0].severity);
+                        let details: Vec<String> =
+                            diagnostics.iter().map(ToString::to_string).collect();
+                        ui.label("|");
+                        ui.colored_label(color, summary)
+                            .on_hover_text(details.join("\n"));
+                    }
+                }
+
+                if let Some((status, severity)) = self.latex_build.status() {
+                    ui.label("|");
+                    let label = match severity {
+                        Some(severity) => {
+                            ui.colored_label(diagnostics_view::severity_color(severity), status)
+                        }
+                        None => ui.label(status),
+                    };
+                    if label.interact(egui::Sense::click()).clicked() {
+                        self.latex_build.show_problems = !self.latex_build.show_problems;
+                    }
+                }
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    // Language picker
+                    if let Some(buffer_id) = self.active_buffer_id {
+                        let mut choice = self.languages.get(&buffer_id).cloned();
+                        egui::ComboBox::from_id_salt("language_picker")
+                            .selected_text(&language)
+                            .height(400.0)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut choice, None, "Auto-detect");
+                                ui.separator();
+                                for name in self.highlighter.language_names() {
+                                    ui.selectable_value(&mut choice, Some(name.to_string()), name);
+                                }
+                            });
+                        match choice {
+                            Some(name) => self.languages.insert(buffer_id, name),
+                            None => self.languages.remove(&buffer_id),
+                        };
+                        ui.label("|");
+                    }
+
+                    // Right to left: the leftmost label comes last
+                    ui.label(indentation.to_string());
+                    ui.label("|");
+                    ui.label(whitespace.end_of_line.map_or_else(
+                        || text_info::line_ending(&self.current_text).to_string(),
+                        |end_of_line| end_of_line.to_string(),
+                    ));
+                    ui.label("|");
+                    ui.label(whitespace.charset.map_or_else(
+                        || text_info::encoding(&self.current_text).to_string(),
+                        |charset| charset.to_string(),
+                    ));
+                    ui.label("|");
+                    if let Some(path) = &file_path {
+                        let config = self.editorconfig(path);
+                        if !config.files.is_empty() {
+                            ui.label("EditorConfig")
+                                .on_hover_text(editorconfig::describe(config, &whitespace));
+                            ui.label("|");
+                        }
+                    }
+                    let selection = editor_cursor::char_range(ctx).unwrap_or(0..0);
+                    ui.label(CursorInfo::new(&self.current_text, selection).to_string());
+                });
             });
         });
 
+        // LaTeX problems from the last compilation
+        if let Some(problem) = self.latex_build.show_problems_panel(ctx) {
+            if file_path.as_deref() != Some(problem.file.as_path()) {
+                self.open_file(problem.file);
+            }
+            let offset = line_offset(&self.current_text, problem.line);
+            self.jump_to(ctx, offset);
+        }
+
+        // AI chat panel
+        if self.show_chat_panel {
+            self.show_chat_panel(ctx);
+        }
+
+        // Outline panel and "go to symbol"
+        if self.show_outline || self.outline.is_picking() {
+            self.outline.update(
+                ctx,
+                &self.current_text,
+                &language,
+                self.highlighter.syntax_set(),
+            );
+        }
+        let mut outline_jump = self.outline.show_quick_pick(ctx);
+        if self.show_outline {
+            let cursor = editor_cursor::char_range(ctx)
+                .map(|range| editor_cursor::byte_index(&self.current_text, range.end));
+            outline_jump = outline_jump.or(self.outline.show(ctx, cursor, &mut self.show_outline));
+        }
+        if let Some(offset) = outline_jump {
+            self.jump_to(ctx, offset);
+        }
+
         // Main editor with optional preview
         if self.show_preview && self.preview_mode != PreviewMode::None {
             // Split view: Editor on left, Preview on right
+            let base_dir = self.active_file_dir();
+            let options = ViewOptions {
+                base_dir: base_dir.as_deref(),
+                reveal: self.scroll_sync.take_reveal(),
+            };
+            let mut action = None;
             egui::SidePanel::right("preview_panel")
                 .default_width(ctx.available_rect().width() * 0.5)
                 .resizable(true)
@@ -422,76 +1277,409 @@ impl LalaApp {
 
                     egui::ScrollArea::vertical()
                         .auto_shrink([false; 2])
-                        .show(ui, |ui| match self.preview_mode {
-                            PreviewMode::Markdown => {
-                                markdown_preview::render_markdown_preview(ui, &self.current_text);
-                            }
-                            PreviewMode::Html => {
-                                previews::render_html_preview(ui, &self.current_text);
-                            }
-                            PreviewMode::Latex => {
-                                previews::render_latex_preview(ui, &self.current_text);
-                            }
-                            PreviewMode::Mermaid => {
-                                previews::render_mermaid_preview(ui, &self.current_text);
-                            }
-                            PreviewMode::None => {}
+                        .show(ui, |ui| {
+                            action = match self.preview_mode {
+                                PreviewMode::Markdown => markdown_preview::render_markdown_preview(
+                                    ui,
+                                    &self.current_text,
+                                    options,
+                                ),
+                                PreviewMode::Html => {
+                                    previews::render_html_preview(ui, &self.current_text, options)
+                                }
+                                PreviewMode::Latex => {
+                                    if self.latex_build.show_pages(ui, file_path.as_deref()) {
+                                        None
+                                    } else {
+                                        previews::render_latex_preview(
+                                            ui,
+                                            &self.current_text,
+                                            self.active_buffer_id,
+                                            options,
+                                        )
+                                    }
+                                }
+                                PreviewMode::Mermaid => previews::render_mermaid_preview(
+                                    ui,
+                                    &self.current_text,
+                                    options,
+                                ),
+                                PreviewMode::None => None,
+                            };
                         });
                 });
+            match action {
+                Some(DocumentAction::ToggleTask { offset })
+                    if markdown::toggle_task(&mut self.current_text, offset) =>
+                {
+                    self.text_changed = true;
+                }
+                Some(DocumentAction::JumpToSource { offset }) => self.jump_to(ctx, offset),
+                Some(DocumentAction::OpenFile { path, anchor }) => {
+                    if path.is_file() {
+                        self.open_file(path);
+                        self.show_preview = true;
+                        self.preview_mode = self.detect_preview_mode();
+                        if let Some(anchor) = anchor {
+                            document_view::scroll_to_anchor(ctx, anchor);
+                        }
+                    } else {
+                        self.llm_status = format!("Link target not found: {}", path.display());
+                    }
+                }
+                Some(DocumentAction::OpenUrl(url)) => self.pending_url = Some(url),
+                _ => {}
+            }
         }
 
         // Main editor
-        let (bg_color, text_color) = if self.is_light_theme {
-            (
-                egui::Color32::from_rgb(255, 255, 255), // White background
-                egui::Color32::from_rgb(0, 0, 0),       // Black text
-            )
-        } else {
-            (
-                egui::Color32::from_rgb(30, 30, 30),    // Dark gray background
-                egui::Color32::from_rgb(255, 255, 255), // White text
+        let bg_color = self.active_theme.editor_background;
+        let text_color = self.active_theme.editor_text;
+
+        // Brackets, auto-closing pairs and indentation act on the keys the
+        // editor is about to get
+        let editing = editing::config(&language);
+        let has_focus = ctx.memory(|memory| memory.has_focus(editor_cursor::main_editor_id()));
+        let line_break = whitespace.line_break(text_info::line_ending(&self.current_text));
+        if has_focus
+            && editing::handle_input(
+                ctx,
+                &mut self.current_text,
+                &editing,
+                indentation,
+                line_break,
             )
-        };
+        {
+            self.text_changed = true;
+        }
+        if std::mem::take(&mut self.go_to_bracket) {
+            let pair = editor_cursor::char_range(ctx).and_then(|range| {
+                editing::matching_bracket(&self.current_text, range.start, &editing)
+            });
+            if let Some((_, other)) = pair {
+                let offset = editor_cursor::byte_index(&self.current_text, other);
+                self.jump_to(ctx, offset);
+            }
+        }
 
+        // Folding
+        let fold_regions = self.fold_regions(&language);
+        if let Some(command) = self.fold_command.take() {
+            self.run_fold_command(ctx, command, &fold_regions);
+        }
+        let fold_owner = self.active_fold_owner();
+        let mut fold_state = fold_owner
+            .as_ref()
+            .and_then(|owner| self.fold_states.get(owner))
+            .cloned()
+            .unwrap_or_default();
+        if edited {
+            fold_state.retain_regions(&fold_regions);
+        }
+        let hidden_lines = fold_state.hidden_lines(&fold_regions);
+        let fold_markers = fold_state.markers(&fold_regions);
+        let mut cursor_line = 0;
+        let mut pane_action = None;
         egui::CentralPanel::default()
             .frame(egui::Frame::default().fill(bg_color).inner_margin(0.0))
             .show(ctx, |ui| {
-                let available_height = ui.available_height();
-
-                egui::ScrollArea::vertical()
-                    .auto_shrink([false; 2])
-                    .show(ui, |ui| {
-                        let response = ui.add(
-                            egui::TextEdit::multiline(&mut self.current_text)
-                                .font(egui::TextStyle::Monospace)
-                                .desired_width(f32::INFINITY)
-                                .min_size(egui::vec2(f32::INFINITY, available_height))
-                                .frame(false)
-                                .background_color(bg_color)
-                                .text_color(text_color),
+                let focused = self.panes.focused();
+                for (pane, rect) in self.panes.layout(ui, ui.max_rect()) {
+                    let mut pane_ui = ui.new_child(
+                        egui::UiBuilder::new()
+                            .id_salt(("pane", pane))
+                            .max_rect(rect)
+                            .layout(egui::Layout::top_down(egui::Align::Min)),
+                    );
+                    let ui = &mut pane_ui;
+                    ui.set_clip_rect(rect.intersect(ui.clip_rect()));
+                    if let Some(action) = self.tab_bar(ui, pane, pane == focused, true) {
+                        pane_action = Some(action);
+                    }
+                    if pane != focused {
+                        if self.show_view(ui, pane, panes::editor_id(pane)) {
+                            pane_action.get_or_insert(PaneAction::Focus(pane));
+                        }
+                        continue;
+                    }
+
+                    let available_height = ui.available_height();
+
+                    let highlighter = &self.highlighter;
+                    let syntax = highlighter
+                        .find_language(&language)
+                        .unwrap_or_else(|| highlighter.detect(None, ""));
+                    let syntax_theme = highlighter.theme(&self.active_theme.syntax);
+                    let default_color = SyntaxHighlighter::default_color(syntax_theme);
+                    let cache = self
+                        .highlight_caches
+                        .entry(self.active_buffer_id)
+                        .or_default();
+                    let visible = self.visible_lines.get(&pane).cloned().unwrap_or(0..100);
+                    let mut highlighting = false;
+                    let mut layouter = |ui: &egui::Ui, text: &dyn egui::TextBuffer, wrap_width| {
+                        let text = text.as_str();
+                        highlighting =
+                            cache.update(highlighter, text, syntax, syntax_theme, visible.clone());
+                        let font = egui::TextStyle::Monospace.resolve(ui.style());
+                        let mut job = cache.layout_job(text, font, default_color);
+                        folding::hide_lines(&mut job, text, &hidden_lines);
+                        job.wrap.max_width = wrap_width;
+                        ui.fonts_mut(|fonts| fonts.layout_job(job))
+                    };
+
+                    // Minimap and overview ruler on the right
+                    let area = ui.available_rect_before_wrap();
+                    let minimap_width = if self.show_minimap {
+                        MINIMAP_WIDTH
+                    } else {
+                        0.0
+                    };
+                    let ruler_width = if self.show_ruler { RULER_WIDTH } else { 0.0 };
+                    let (editor_rect, side_rect) =
+                        area.split_left_right_at_x(area.right() - minimap_width - ruler_width);
+                    let mut editor_ui = ui.new_child(
+                        egui::UiBuilder::new()
+                            .max_rect(editor_rect)
+                            .layout(egui::Layout::top_down(egui::Align::Min)),
+                    );
+
+                    let mut gutter_action = None;
+                    let mut diagnostics = Vec::new();
+                    let mut changes = None;
+                    let mut scroll_area = egui::ScrollArea::vertical().auto_shrink([false; 2]);
+                    if let Some(offset) = self.editor_scroll.take() {
+                        scroll_area = scroll_area.vertical_scroll_offset(offset);
+                    }
+                    let scroll = scroll_area.show(&mut editor_ui, |ui| {
+                        ui.spacing_mut().item_spacing.x = 0.0;
+                        let line_count = self.current_text.lines().count();
+                        let gutter_width = gutter::width(ui, line_count);
+                        let current_line = ui.painter().add(egui::Shape::Noop);
+                        let (output, gutter_rect) = ui
+                            .horizontal_top(|ui| {
+                                let (gutter_rect, _) = ui.allocate_exact_size(
+                                    egui::vec2(gutter_width, available_height),
+                                    egui::Sense::hover(),
+                                );
+                                let output = egui::TextEdit::multiline(&mut self.current_text)
+                                    .id(editor_cursor::main_editor_id())
+                                    .font(egui::TextStyle::Monospace)
+                                    .desired_width(f32::INFINITY)
+                                    .min_size(egui::vec2(0.0, available_height))
+                                    .frame(false)
+                                    .background_color(bg_color)
+                                    .text_color(text_color)
+                                    .lock_focus(true)
+                                    .layouter(&mut layouter)
+                                    .show(ui);
+                                (output, gutter_rect)
+                            })
+                            .inner;
+
+                        // Gutter and current line, from the laid out text
+                        cursor_line = output
+                            .cursor_range
+                            .map(|range| {
+                                let index = editor_cursor::byte_index(
+                                    &self.current_text,
+                                    range.primary.index,
+                                );
+                                self.current_text[..index].matches('\n').count()
+                            })
+                            .unwrap_or(0);
+                        gutter::paint_current_line(
+                            ui,
+                            current_line,
+                            &output,
+                            gutter_rect,
+                            cursor_line,
+                            bg_color.lerp_to_gamma(text_color, 0.06),
+                        );
+                        if lint_mermaid {
+                            diagnostics =
+                                diagnostics_view::mermaid_diagnostics(ui.ctx(), &self.current_text);
+                        }
+                        if let Some(path) = &file_path {
+                            diagnostics
+                                .extend(self.latex_build.diagnostics(path, &self.current_text));
+                        }
+                        changes = file_path.as_ref().and_then(|path| {
+                            let base = self
+                                .vcs_bases
+                                .entry(path.clone())
+                                .or_insert_with(|| vcs::head_text(path))
+                                .as_deref()?;
+                            Some(vcs_changes(
+                                &mut self.vcs_changes,
+                                self.active_buffer_id,
+                                path,
+                                base,
+                                &self.current_text,
+                            ))
+                        });
+                        let marks = gutter::Marks {
+                            diagnostics: &diagnostics,
+                            changes: changes.as_deref(),
+                            folds: Some(&fold_markers),
+                        };
+                        gutter_action = gutter::show(
+                            ui,
+                            gutter_rect,
+                            &output,
+                            cursor_line,
+                            self.line_numbers,
+                            &marks,
+                            text_color,
+                        );
+                        gutter::paint_fold_placeholders(
+                            ui,
+                            &output,
+                            fold_markers
+                                .iter()
+                                .filter(|(_, folded)| **folded)
+                                .map(|(line, _)| *line),
+                            text_color,
                         );
 
                         // Request focus on first frame
                         if self.current_text.is_empty() && !self.text_changed {
-                            response.request_focus();
+                            output.response.request_focus();
                         }
 
-                        if response.changed() {
+                        if output.response.changed() {
                             self.text_changed = true;
                         }
+
+                        diagnostics_view::paint_squiggles(
+                            ui,
+                            &output,
+                            &self.current_text,
+                            &diagnostics,
+                        );
+                        let pair = output
+                            .cursor_range
+                            .filter(|_| output.response.has_focus())
+                            .and_then(|range| {
+                                editing::matching_bracket(
+                                    &self.current_text,
+                                    range.primary.index,
+                                    &editing,
+                                )
+                            });
+                        if let Some(pair) = pair {
+                            editing::paint_bracket_match(ui, &output, pair, text_color);
+                        }
+
+                        // Jump requested by clicking a preview block
+                        if let Some(offset) = self.scroll_sync.take_jump() {
+                            let char_index = editor_cursor::char_index(&self.current_text, offset);
+                            let rect = output
+                                .galley
+                                .pos_from_cursor(CCursor::new(char_index))
+                                .translate(output.galley_pos.to_vec2());
+                            ui.scroll_to_rect(rect, Some(egui::Align::TOP));
+                        }
+
+                        output
                     });
+
+                    if highlighting {
+                        ctx.request_repaint_after(std::time::Duration::from_millis(16));
+                    }
+                    match gutter_action {
+                        Some(gutter::GutterAction::SelectLine(line)) => {
+                            let start = line_offset(&self.current_text, line + 1);
+                            let end = line_offset(&self.current_text, line + 2);
+                            editor_cursor::set_char_range(
+                                ctx,
+                                editor_cursor::char_index(&self.current_text, start)
+                                    ..editor_cursor::char_index(&self.current_text, end),
+                            );
+                            ctx.memory_mut(|memory| {
+                                memory.request_focus(editor_cursor::main_editor_id())
+                            });
+                        }
+                        Some(gutter::GutterAction::ToggleFold(line)) => fold_state.toggle(line),
+                        None => {}
+                    }
+                    // Reveal the cursor when it moves into a folded region
+                    if fold_state.is_hidden(&fold_regions, cursor_line) {
+                        fold_state.unfold_at(&fold_regions, cursor_line);
+                    }
+                    self.visible_lines
+                        .insert(pane, visible_lines(&scroll.inner, scroll.inner_rect));
+
+                    let (minimap_rect, ruler_rect) =
+                        side_rect.split_left_right_at_x(side_rect.left() + minimap_width);
+                    if self.show_minimap {
+                        if let Some(offset) = minimap::show(ui, minimap_rect, &scroll, text_color) {
+                            self.editor_scroll = Some(offset);
+                        }
+                    }
+                    if self.show_ruler {
+                        let search = if self.show_search_panel {
+                            self.search_panel.match_lines()
+                        } else {
+                            Vec::new()
+                        };
+                        let marks = minimap::RulerMarks {
+                            search: &search,
+                            current_match: self
+                                .search_panel
+                                .current_match()
+                                .filter(|_| self.show_search_panel)
+                                .map(|result| result.range.start.line),
+                            diagnostics: &diagnostics,
+                            changes: changes.as_deref(),
+                            cursor_line,
+                            line_count: self.current_text.lines().count(),
+                        };
+                        if let Some(offset) =
+                            minimap::show_ruler(ui, ruler_rect, &scroll, &marks, text_color)
+                        {
+                            self.editor_scroll = Some(offset);
+                        }
+                    }
+                    if self.editor_scroll.is_some() {
+                        ctx.request_repaint();
+                    }
+
+                    // Report the editor position for the preview to follow
+                    if self.show_preview {
+                        let output = &scroll.inner;
+                        let top = output
+                            .galley
+                            .cursor_from_pos(egui::vec2(
+                                0.0,
+                                scroll.inner_rect.top() - output.galley_pos.y,
+                            ))
+                            .index;
+                        let cursor = output.cursor_range.map(|range| range.primary.index);
+                        self.scroll_sync.editor_moved(
+                            cursor
+                                .map(|index| editor_cursor::byte_index(&self.current_text, index)),
+                            editor_cursor::byte_index(&self.current_text, top),
+                        );
+                    }
+                }
             });
+        if pane_action.is_some() {
+            self.pane_action = pane_action;
+            ctx.request_repaint();
+        }
+
+        if let Some(owner) = fold_owner {
+            self.fold_states.insert(owner, fold_state);
+        }
     }
 }
 
 impl eframe::App for LalaApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // Apply theme
-        if self.is_light_theme {
-            ctx.set_visuals(theme::custom_light_theme());
-        } else {
-            ctx.set_visuals(egui::Visuals::dark());
-        }
+        self.update_theme(ctx);
+        ctx.set_visuals(self.active_theme.visuals.clone());
 
         // Handle keyboard shortcuts
         self.handle_keyboard_shortcuts(ctx);
@@ -504,9 +1692,15 @@ impl eframe::App for LalaApp {
             ctx.request_repaint();
         }
 
+        // Collect a finished LaTeX compilation
+        self.latex_build.poll(ctx);
+
         // Show main editor
         self.show_main_editor(ctx);
 
+        // Tabs moved into windows of their own
+        self.show_windows(ctx);
+
         // Show search panel
         if self.show_search_panel {
             if let Some(buffer_id) = self.active_buffer_id {
@@ -533,9 +1727,11 @@ impl eframe::App for LalaApp {
 
         // Show dialogs
         if self.show_file_dialog {
-            if let Some(path) =
-                dialogs::show_file_dialog(ctx, &mut self.show_file_dialog, &mut self.file_path_input)
-            {
+            if let Some(path) = dialogs::show_file_dialog(
+                ctx,
+                &mut self.show_file_dialog,
+                &mut self.file_path_input,
+            ) {
                 self.open_file(path);
             }
         }
@@ -546,10 +1742,14 @@ impl eframe::App for LalaApp {
                 &mut self.show_save_as_dialog,
                 &mut self.file_path_input,
             ) {
-                self.save_file_as(path);
+                self.save_file_as(ctx, path);
             }
         }
 
+        if self.pending_url.is_some() {
+            dialogs::show_open_url_dialog(ctx, &mut self.pending_url);
+        }
+
         if self.show_settings {
             dialogs::show_settings(
                 ctx,
@@ -562,3 +1762,64 @@ impl eframe::App for LalaApp {
         }
     }
 }
+
+/// Range of 0-based lines of the editor `output` that intersect `viewport`
+fn visible_lines(output: &egui::text_edit::TextEditOutput, viewport: egui::Rect) -> Range<usize> {
+    let mut line = 0;
+    let mut first = None;
+    let mut last = 0;
+    for placed in &output.galley.rows {
+        let rect = placed.rect().translate(output.galley_pos.to_vec2());
+        if rect.top() > viewport.bottom() {
+            break;
+        }
+        if rect.bottom() >= viewport.top() {
+            first.get_or_insert(line);
+            last = line;
+        }
+        if placed.row.ends_with_newline {
+            line += 1;
+        }
+    }
+    first.unwrap_or(last)..last + 1
+}
+
+/// Files keep their folds by path, untitled buffers by id
+fn fold_owner(buffer_id: BufferId, buffer: &Buffer) -> FoldOwner {
+    match buffer.file_path() {
+        Some(path) => FoldOwner::File(path.clone()),
+        None => FoldOwner::Untitled(buffer_id),
+    }
+}
+
+type LineChanges = Arc<BTreeMap<usize, vcs::LineChange>>;
+
+/// Changed lines of a buffer against the committed `base` of `path`, kept
+/// in `cache` until the buffer is edited
+fn vcs_changes(
+    cache: &mut HashMap<BufferId, (PathBuf, LineChanges)>,
+    buffer_id: Option<BufferId>,
+    path: &Path,
+    base: &str,
+    text: &str,
+) -> LineChanges {
+    let Some(buffer_id) = buffer_id else {
+        return Arc::new(vcs::line_changes(base, text));
+    };
+    if let Some((cached_path, changes)) = cache.get(&buffer_id) {
+        if cached_path == path {
+            return changes.clone();
+        }
+    }
+    let changes = Arc::new(vcs::line_changes(base, text));
+    cache.insert(buffer_id, (path.to_path_buf(), changes.clone()));
+    changes
+}
+
+/// Byte offset of the start of 1-based `line`
+fn line_offset(text: &str, line: usize) -> usize {
+    text.split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum()
+}
@@ -0,0 +1,349 @@
+//! AI chat side panel
+//!
+//! Docked to the left or right of the editor, the panel keeps a
+//! conversation per workspace and can attach the current file, the
+//! selection or grep results as context. Code blocks in replies can be
+//! inserted at the editor cursor.
+
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+use crate::llm::chat::{
+    build_request, extract_code_blocks, ChatMessage, ChatRole, ContextAttachment, ContextKind,
+    Conversation,
+};
+use crate::llm::GeminiClient;
+use crate::search::GrepResult;
+
+/// Default token budget for a single chat request
+const DEFAULT_TOKEN_BUDGET: usize = 8000;
+
+/// Side of the window the chat panel is docked to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatDock {
+    Left,
+    Right,
+}
+
+/// Actions requested by the chat panel that the app has to apply
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChatAction {
+    /// Insert the given code at the editor cursor
+    InsertCode(String),
+}
+
+/// Document state the chat panel can attach as context
+pub struct ChatContext<'a> {
+    pub file_name: &'a str,
+    pub file_text: &'a str,
+    pub selection: Option<&'a str>,
+    pub grep_results: &'a [GrepResult],
+}
+
+pub struct ChatPanel {
+    // Conversation state
+    conversation: Conversation,
+    workspace: PathBuf,
+    input: String,
+
+    // Context options
+    attach_file: bool,
+    attach_selection: bool,
+    attach_grep: bool,
+    token_budget: usize,
+
+    // Layout
+    dock: ChatDock,
+
+    // Request in flight
+    pending: Option<flume::Receiver<Result<String, String>>>,
+
+    // Error state
+    error_message: Option<String>,
+}
+
+impl ChatPanel {
+    pub fn new(workspace: &Path) -> Self {
+        Self {
+            conversation: Conversation::load_for_workspace(workspace),
+            workspace: workspace.to_path_buf(),
+            input: String::new(),
+            attach_file: true,
+            attach_selection: false,
+            attach_grep: false,
+            token_budget: DEFAULT_TOKEN_BUDGET,
+            dock: ChatDock::Right,
+            pending: None,
+            error_message: None,
+        }
+    }
+
+    pub fn is_waiting(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Show the docked chat panel
+    ///
+    /// Must be called before the central panel is added.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        llm_client: Option<&GeminiClient>,
+        context: ChatContext,
+    ) -> Option<ChatAction> {
+        self.poll_reply();
+        if self.is_waiting() {
+            ctx.request_repaint();
+        }
+
+        let panel = match self.dock {
+            ChatDock::Left => egui::SidePanel::left("chat_panel"),
+            ChatDock::Right => egui::SidePanel::right("chat_panel"),
+        };
+
+        let mut action = None;
+        panel.default_width(360.0).resizable(true).show(ctx, |ui| {
+            action = self.show_content(ui, llm_client, &context);
+        });
+        action
+    }
+
+    fn show_content(
+        &mut self,
+        ui: &mut egui::Ui,
+        llm_client: Option<&GeminiClient>,
+        context: &ChatContext,
+    ) -> Option<ChatAction> {
+        let mut action = None;
+
+        ui.horizontal(|ui| {
+            ui.heading("AI Chat");
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let (dock_label, other) = match self.dock {
+                    ChatDock::Left => ("Dock ▶", ChatDock::Right),
+                    ChatDock::Right => ("◀ Dock", ChatDock::Left),
+                };
+                if ui.small_button(dock_label).clicked() {
+                    self.dock = other;
+                }
+                if ui.small_button("🗑 Clear").clicked() {
+                    self.conversation.clear();
+                    self.save_conversation();
+                }
+            });
+        });
+
+        ui.separator();
+
+        // Context options
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Context:");
+            ui.checkbox(&mut self.attach_file, ContextKind::File.label());
+            ui.add_enabled(
+                context.selection.is_some(),
+                egui::Checkbox::new(&mut self.attach_selection, ContextKind::Selection.label()),
+            );
+            ui.add_enabled(
+                !context.grep_results.is_empty(),
+                egui::Checkbox::new(&mut self.attach_grep, ContextKind::GrepResults.label()),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Token budget:");
+            ui.add(
+                egui::DragValue::new(&mut self.token_budget)
+                    .range(500..=128_000)
+                    .speed(100),
+            );
+        });
+
+        ui.separator();
+
+        // Error message
+        if let Some(error) = &self.error_message {
+            ui.colored_label(egui::Color32::RED, error);
+            ui.separator();
+        }
+
+        // Input area is laid out bottom-up so the history fills the rest
+        egui::TopBottomPanel::bottom("chat_input")
+            .show_separator_line(false)
+            .show_inside(ui, |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.input)
+                        .hint_text("Ask about the document... (Ctrl+Enter to send)")
+                        .desired_rows(3)
+                        .desired_width(f32::INFINITY),
+                );
+
+                let send_shortcut =
+                    ui.input(|i| i.modifiers.command && i.key_pressed(egui::Key::Enter));
+                ui.horizontal(|ui| {
+                    let can_send =
+                        llm_client.is_some() && !self.is_waiting() && !self.input.trim().is_empty();
+                    if ui
+                        .add_enabled(can_send, egui::Button::new("Send"))
+                        .clicked()
+                        || (can_send && send_shortcut)
+                    {
+                        if let Some(client) = llm_client {
+                            self.send(client, context);
+                        }
+                    }
+                    if self.is_waiting() {
+                        ui.spinner();
+                        ui.label("Waiting for reply...");
+                    } else if llm_client.is_none() {
+                        ui.label("💡 Tip: Enable AI in Settings");
+                    }
+                });
+            });
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for (index, message) in self.conversation.messages.iter().enumerate() {
+                    ui.push_id(index, |ui| {
+                        if let Some(insert) = show_message(ui, message) {
+                            action = Some(ChatAction::InsertCode(insert));
+                        }
+                    });
+                }
+            });
+
+        action
+    }
+
+    fn send(&mut self, client: &GeminiClient, context: &ChatContext) {
+        let question = std::mem::take(&mut self.input);
+        self.conversation.push(ChatMessage::user(question.trim()));
+        self.error_message = None;
+
+        let attachments = self.collect_attachments(context);
+        let request = build_request(&self.conversation.messages, &attachments, self.token_budget);
+
+        let (tx, rx) = flume::bounded(1);
+        let client = client.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(client.chat(&request));
+        });
+        self.pending = Some(rx);
+    }
+
+    fn collect_attachments(&self, context: &ChatContext) -> Vec<ContextAttachment> {
+        let mut attachments = Vec::new();
+
+        if self.attach_file && !context.file_text.is_empty() {
+            attachments.push(ContextAttachment {
+                kind: ContextKind::File,
+                name: context.file_name.to_string(),
+                content: context.file_text.to_string(),
+            });
+        }
+
+        if self.attach_selection {
+            if let Some(selection) = context.selection.filter(|s| !s.is_empty()) {
+                attachments.push(ContextAttachment {
+                    kind: ContextKind::Selection,
+                    name: context.file_name.to_string(),
+                    content: selection.to_string(),
+                });
+            }
+        }
+
+        if self.attach_grep && !context.grep_results.is_empty() {
+            let content = context
+                .grep_results
+                .iter()
+                .map(|r| {
+                    format!(
+                        "{}:{}: {}",
+                        r.file_path.display(),
+                        r.line_number,
+                        r.line_content.trim()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            attachments.push(ContextAttachment {
+                kind: ContextKind::GrepResults,
+                name: format!("{} matches", context.grep_results.len()),
+                content,
+            });
+        }
+
+        attachments
+    }
+
+    fn poll_reply(&mut self) {
+        let Some(rx) = &self.pending else {
+            return;
+        };
+
+        match rx.try_recv() {
+            Ok(Ok(reply)) => {
+                self.conversation.push(ChatMessage::assistant(reply));
+                self.pending = None;
+                self.save_conversation();
+            }
+            Ok(Err(e)) => {
+                self.error_message = Some(format!("LLM Error: {e}"));
+                self.pending = None;
+            }
+            Err(flume::TryRecvError::Empty) => {}
+            Err(flume::TryRecvError::Disconnected) => {
+                self.error_message = Some("LLM request was interrupted".to_string());
+                self.pending = None;
+            }
+        }
+    }
+
+    fn save_conversation(&mut self) {
+        if let Err(e) = self.conversation.save_for_workspace(&self.workspace) {
+            self.error_message = Some(e);
+        }
+    }
+}
+
+/// Render a single message; returns code to insert if requested
+fn show_message(ui: &mut egui::Ui, message: &ChatMessage) -> Option<String> {
+    let mut insert = None;
+
+    let (title, fill) = match message.role {
+        ChatRole::User => ("You", ui.visuals().faint_bg_color),
+        ChatRole::Assistant => ("Assistant", ui.visuals().extreme_bg_color),
+    };
+
+    egui::Frame::NONE
+        .fill(fill)
+        .inner_margin(egui::Margin::same(6))
+        .corner_radius(4.0)
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            ui.label(egui::RichText::new(title).strong());
+            ui.label(&message.content);
+
+            if message.role == ChatRole::Assistant {
+                for (index, block) in extract_code_blocks(&message.content).iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        let name = if block.lang.is_empty() {
+                            format!("Code block {}", index + 1)
+                        } else {
+                            format!("Code block {} ({})", index + 1, block.lang)
+                        };
+                        ui.label(egui::RichText::new(name).small());
+                        if ui.small_button("⤵ Insert").clicked() {
+                            insert = Some(block.code.clone());
+                        }
+                        if ui.small_button("📋 Copy").clicked() {
+                            ui.ctx().copy_text(block.code.clone());
+                        }
+                    });
+                }
+            }
+        });
+    ui.add_space(4.0);
+
+    insert
+}
@@ -0,0 +1,133 @@
+//! Squiggly underlines for source diagnostics in the main editor
+//!
+//! Diagnostics are recomputed only when the text changes (the last result
+//! is kept in egui memory with a hash of its text); hovering an underline
+//! shows its message.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use eframe::egui::{self, text::CCursor, Color32, Pos2, Shape, Stroke};
+
+use crate::mermaid::{self, Diagnostic, Severity};
+
+/// Diagnostics for a Mermaid source, reused while the text is unchanged
+pub fn mermaid_diagnostics(ctx: &egui::Context, text: &str) -> Vec<Diagnostic> {
+    let id = egui::Id::new("mermaid_diagnostics");
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    let key = hasher.finish();
+
+    let cached = ctx.data(|data| data.get_temp::<(u64, Vec<Diagnostic>)>(id));
+    if let Some((_, diagnostics)) = cached.filter(|(cached_key, _)| *cached_key == key) {
+        return diagnostics;
+    }
+    let diagnostics = mermaid::check(text);
+    ctx.data_mut(|data| data.insert_temp(id, (key, diagnostics.clone())));
+    diagnostics
+}
+
+/// Short summary for the status bar, e.g. `2 errors, 1 warning`
+pub fn summary(diagnostics: &[Diagnostic]) -> Option<String> {
+    severity_summary(diagnostics.iter().map(|d| d.severity))
+}
+
+/// [`summary`] of any problems with a severity, such as TeX log messages
+pub fn severity_summary(severities: impl Iterator<Item = Severity>) -> Option<String> {
+    let severities: Vec<Severity> = severities.collect();
+    let count = |severity| severities.iter().filter(|s| **s == severity).count();
+    let plural = |n: usize, word: &str| format!("{n} {word}{}", if n == 1 { "" } else { "s" });
+    let parts: Vec<String> = [
+        (count(Severity::Error), "error"),
+        (count(Severity::Warning), "warning"),
+    ]
+    .into_iter()
+    .filter(|(n, _)| *n > 0)
+    .map(|(n, word)| plural(n, word))
+    .collect();
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+pub fn severity_color(severity: Severity) -> Color32 {
+    match severity {
+        Severity::Error => Color32::from_rgb(230, 70, 70),
+        Severity::Warning => Color32::from_rgb(220, 170, 40),
+    }
+}
+
+/// Underline each diagnostic in a shown `TextEdit`
+pub fn paint_squiggles(
+    ui: &egui::Ui,
+    output: &egui::text_edit::TextEditOutput,
+    text: &str,
+    diagnostics: &[Diagnostic],
+) {
+    let painter = ui.painter_at(output.text_clip_rect);
+    let offset = output.galley_pos.to_vec2();
+    let char_width = ui
+        .fonts_mut(|fonts| fonts.glyph_width(&egui::TextStyle::Monospace.resolve(ui.style()), 'x'));
+
+    for (index, diagnostic) in diagnostics.iter().enumerate() {
+        let range = diagnostic.char_range(text);
+        let start = output
+            .galley
+            .pos_from_cursor(CCursor::new(range.start))
+            .translate(offset);
+        let end = output
+            .galley
+            .pos_from_cursor(CCursor::new(range.end))
+            .translate(offset);
+        // Wrapped spans are underlined to the end of their first row
+        let right = if (end.top() - start.top()).abs() < 1.0 {
+            end.left()
+        } else {
+            output.galley_pos.x + output.galley.rect.width()
+        };
+        let right = right.max(start.left() + char_width);
+        let y = start.bottom() - 1.0;
+        let color = severity_color(diagnostic.severity);
+        painter.add(wave(start.left(), right, y, Stroke::new(1.0, color)));
+
+        let area = egui::Rect::from_min_max(start.left_top(), Pos2::new(right, start.bottom()));
+        if ui.rect_contains_pointer(area) {
+            let id = egui::Id::new(("diagnostic_tooltip", index));
+            egui::Tooltip::always_open(
+                ui.ctx().clone(),
+                ui.layer_id(),
+                id,
+                egui::PopupAnchor::Pointer,
+            )
+            .gap(12.0)
+            .show(|ui| {
+                ui.colored_label(color, diagnostic.severity.label());
+                ui.label(&diagnostic.message);
+            });
+        }
+    }
+}
+
+fn wave(left: f32, right: f32, y: f32, stroke: Stroke) -> Shape {
+    const STEP: f32 = 2.0;
+    let mut points = Vec::new();
+    let mut x = left;
+    let mut up = false;
+    while x < right {
+        points.push(Pos2::new(x, if up { y - 1.5 } else { y + 0.5 }));
+        x += STEP;
+        up = !up;
+    }
+    points.push(Pos2::new(right, y));
+    Shape::line(points, stroke)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(summary(&[]), None);
+        let diagnostics = mermaid::check("graph TD\n  A -> B\n  C -> D\n");
+        assert_eq!(summary(&diagnostics).as_deref(), Some("2 errors"));
+    }
+}
@@ -0,0 +1,263 @@
+//! egui painter for Mermaid diagrams
+//!
+//! Lays a [`Diagram`] out with the native engine in [`crate::mermaid`],
+//! measuring text with the UI font, and paints the shared geometry
+//! primitives. The layout is cached in egui memory per diagram source.
+
+use eframe::egui::{self, Color32, Pos2, Shape, Stroke, Vec2};
+
+use crate::mermaid::geometry::{self, Fill, Primitive};
+use crate::mermaid::layout::{self, Layout, Point, Size, Spacing};
+use crate::mermaid::{svg, Diagram, LineStyle, Marker, NodeShape};
+
+const FONT_SIZE: f32 = 14.0;
+
+/// Paint a diagram, scrolling horizontally when it is wider than the panel
+pub fn render_diagram(ui: &mut egui::Ui, diagram: &Diagram) {
+    let font = egui::FontId::proportional(FONT_SIZE);
+    let layout = cached_layout(ui, diagram, &font);
+
+    egui::ScrollArea::horizontal()
+        .id_salt(("mermaid_diagram", &diagram.source))
+        .show(ui, |ui| {
+            let (response, painter) =
+                ui.allocate_painter(Vec2::new(layout.width, layout.height), egui::Sense::click());
+            Painter {
+                painter,
+                origin: response.rect.min,
+                font: font.clone(),
+                stroke: ui.visuals().widgets.noninteractive.fg_stroke.color,
+                text: ui.visuals().text_color(),
+                fill: ui.visuals().faint_bg_color,
+                background: ui.visuals().panel_fill,
+            }
+            .paint(&layout);
+
+            response.context_menu(|ui| {
+                if ui.button("Copy as SVG").clicked() {
+                    ui.ctx().copy_text(svg::render(diagram));
+                    ui.close();
+                }
+            });
+        });
+}
+
+fn cached_layout(ui: &egui::Ui, diagram: &Diagram, font: &egui::FontId) -> Layout {
+    let id = egui::Id::new(("mermaid_layout", &diagram.source, font.size.to_bits()));
+    if let Some(layout) = ui.ctx().data(|data| data.get_temp::<Layout>(id)) {
+        return layout;
+    }
+
+    let measure = |text: &str| {
+        let size = ui.fonts_mut(|fonts| {
+            fonts
+                .layout_no_wrap(text.to_string(), font.clone(), Color32::WHITE)
+                .size()
+        });
+        Size::new(size.x, size.y)
+    };
+    let layout = layout::layout(diagram, &measure, &Spacing::pixels());
+    ui.ctx()
+        .data_mut(|data| data.insert_temp(id, layout.clone()));
+    layout
+}
+
+struct Painter {
+    painter: egui::Painter,
+    origin: Pos2,
+    font: egui::FontId,
+    stroke: Color32,
+    text: Color32,
+    fill: Color32,
+    background: Color32,
+}
+
+impl Painter {
+    fn pos(&self, point: Point) -> Pos2 {
+        self.origin + Vec2::new(point.x, point.y)
+    }
+
+    fn rect(&self, rect: geometry::Rect) -> egui::Rect {
+        egui::Rect::from_min_size(
+            self.pos(Point::new(rect.x, rect.y)),
+            Vec2::new(rect.width, rect.height),
+        )
+    }
+
+    fn paint(&self, layout: &Layout) {
+        let stroke = Stroke::new(1.0, self.stroke);
+
+        for cluster in &layout.clusters {
+            let rect = self.rect(cluster.rect);
+            self.painter.rect(
+                rect,
+                2.0,
+                self.fill.gamma_multiply(0.6),
+                stroke,
+                egui::StrokeKind::Inside,
+            );
+            self.label(
+                &cluster.label,
+                cluster.label_at,
+                egui::Align2::CENTER_CENTER,
+            );
+            for (y, text) in &cluster.dividers {
+                let y = self.origin.y + y;
+                self.painter.extend(Shape::dashed_line(
+                    &[Pos2::new(rect.left(), y), Pos2::new(rect.right(), y)],
+                    stroke,
+                    4.0,
+                    3.0,
+                ));
+                let at = Point::new(cluster.label_at.x, y - self.origin.y + FONT_SIZE * 0.8);
+                self.label(text, at, egui::Align2::CENTER_CENTER);
+            }
+        }
+
+        for (top, bottom) in &layout.lifelines {
+            self.painter.extend(Shape::dashed_line(
+                &[self.pos(*top), self.pos(*bottom)],
+                stroke,
+                4.0,
+                3.0,
+            ));
+        }
+
+        for edge in &layout.edges {
+            let points: Vec<Pos2> = edge.points.iter().map(|p| self.pos(*p)).collect();
+            match edge.line {
+                LineStyle::Invisible => continue,
+                LineStyle::Dotted => self
+                    .painter
+                    .extend(Shape::dashed_line(&points, stroke, 3.0, 3.0)),
+                LineStyle::Thick => {
+                    self.painter
+                        .add(Shape::line(points, Stroke::new(2.5, self.stroke)));
+                }
+                LineStyle::Solid => {
+                    self.painter.add(Shape::line(points, stroke));
+                }
+            }
+
+            let count = edge.points.len();
+            for (kind, tip, from) in [
+                (edge.start, edge.points[0], edge.points[1]),
+                (edge.end, edge.points[count - 1], edge.points[count - 2]),
+            ] {
+                if kind != Marker::None {
+                    for primitive in geometry::marker(kind, tip, from, 9.0) {
+                        self.primitive(&primitive, self.fill);
+                    }
+                }
+            }
+            for (text, at) in [&edge.label, &edge.start_label, &edge.end_label]
+                .into_iter()
+                .flatten()
+            {
+                let galley = self.galley(text);
+                let rect = egui::Align2::CENTER_CENTER
+                    .anchor_size(self.pos(*at), galley.size())
+                    .expand2(Vec2::new(2.0, 0.0));
+                self.painter.rect_filled(rect, 2.0, self.background);
+                self.painter
+                    .galley(rect.min + Vec2::new(2.0, 0.0), galley, self.text);
+            }
+        }
+
+        for node in &layout.nodes {
+            let fill = if node.shape == NodeShape::Note {
+                Color32::from_rgb(255, 245, 173).gamma_multiply(0.35)
+            } else {
+                self.fill
+            };
+            for primitive in geometry::outline(node.shape, node.rect) {
+                self.primitive(&primitive, fill);
+            }
+            self.label(&node.label, node.label_at, egui::Align2::CENTER_CENTER);
+            for section in &node.sections {
+                let rect = self.rect(section.rect);
+                self.painter.hline(rect.x_range(), rect.top(), stroke);
+                for (line, at) in &section.lines {
+                    self.label(line, *at, egui::Align2::LEFT_CENTER);
+                }
+            }
+        }
+    }
+
+    fn galley(&self, text: &str) -> std::sync::Arc<egui::Galley> {
+        self.painter
+            .layout_no_wrap(text.to_string(), self.font.clone(), self.text)
+    }
+
+    fn label(&self, text: &str, at: Point, align: egui::Align2) {
+        if text.is_empty() {
+            return;
+        }
+        let galley = self.galley(text);
+        let rect = align.anchor_size(self.pos(at), galley.size());
+        self.painter.galley(rect.min, galley, self.text);
+    }
+
+    fn primitive(&self, primitive: &Primitive, background: Color32) {
+        let stroke = Stroke::new(1.0, self.stroke);
+        let fill = |fill: &Fill| match fill {
+            Fill::None => Color32::TRANSPARENT,
+            Fill::Background => background,
+            Fill::Solid => self.stroke,
+        };
+
+        match primitive {
+            Primitive::Polygon { points, fill: kind } => {
+                let points: Vec<Pos2> = points.iter().map(|p| self.pos(*p)).collect();
+                if is_convex(&points) {
+                    self.painter
+                        .add(Shape::convex_polygon(points, fill(kind), stroke));
+                } else {
+                    // egui only fills convex polygons
+                    self.painter.add(Shape::closed_line(points, stroke));
+                }
+            }
+            Primitive::Polyline(points) => {
+                let points = points.iter().map(|p| self.pos(*p)).collect();
+                self.painter.add(Shape::line(points, stroke));
+            }
+            Primitive::Ellipse {
+                center,
+                rx,
+                ry,
+                fill: kind,
+            } => {
+                let (center, radius) = (self.pos(*center), Vec2::new(*rx, *ry));
+                self.painter
+                    .add(Shape::ellipse_filled(center, radius, fill(kind)));
+                self.painter
+                    .add(Shape::ellipse_stroke(center, radius, stroke));
+            }
+            Primitive::RoundedRect { rect, radius } => {
+                self.painter.rect(
+                    self.rect(*rect),
+                    *radius,
+                    background,
+                    stroke,
+                    egui::StrokeKind::Inside,
+                );
+            }
+        }
+    }
+}
+
+fn is_convex(points: &[Pos2]) -> bool {
+    let n = points.len();
+    let mut sign = 0.0f32;
+    for i in 0..n {
+        let (a, b, c) = (points[i], points[(i + 1) % n], points[(i + 2) % n]);
+        let cross = (b - a).x * (c - b).y - (b - a).y * (c - b).x;
+        if cross.abs() > f32::EPSILON {
+            if sign != 0.0 && cross.signum() != sign {
+                return false;
+            }
+            sign = cross.signum();
+        }
+    }
+    true
+}
@@ -111,6 +111,39 @@ pub fn show_file_dialog(
     should_open
 }
 
+/// Confirmation before opening a link from a preview in the system browser
+///
+/// Clears `url` when the user answers (or closes the window).
+pub fn show_open_url_dialog(ctx: &egui::Context, url: &mut Option<String>) {
+    let Some(target) = url.clone() else { return };
+    let mut answered = false;
+
+    let mut is_open = true;
+    egui::Window::new("Open Link")
+        .open(&mut is_open)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.label("Open this link in the system browser?");
+            ui.monospace(&target);
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Open").clicked() {
+                    ctx.open_url(egui::OpenUrl::new_tab(&target));
+                    answered = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    answered = true;
+                }
+            });
+        });
+
+    if answered || !is_open {
+        *url = None;
+    }
+}
+
 /// Save as dialog
 pub fn show_save_as_dialog(
     ctx: &egui::Context,
@@ -249,7 +282,8 @@ pub fn show_settings(
                         .desired_width(300.0),
                 );
 
-                if ui.button("Apply").clicked() || response.lost_focus() && !api_key_input.is_empty()
+                if ui.button("Apply").clicked()
+                    || response.lost_focus() && !api_key_input.is_empty()
                 {
                     // Try to create client with new API key
                     match GeminiClient::new(api_key_input.clone()) {
@@ -0,0 +1,526 @@
+//! egui backend for [`crate::document::Document`]
+//!
+//! Renders the shared document model with real layout: sized headings,
+//! wrapped paragraphs with inline styles, clickable links, nested lists,
+//! tables, block quotes, math, diagrams and images.
+//!
+//! The view is read-only; interactions that should change the source
+//! (ticking a task checkbox), move the editor (clicking a block) or leave
+//! the document (following a link to another file or a URL) are returned
+//! as a [`DocumentAction`] for the caller to apply. `#anchor` links scroll
+//! within the view itself.
+
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+use super::diagram_view::render_diagram;
+use super::image_cache;
+use super::markdown_preview::render_highlighted_code;
+use super::math_view::render_math;
+use crate::document::image::display_size;
+use crate::document::links::{self, Target};
+use crate::document::{Alignment, Block, Document, Inline, List, Table};
+
+/// Source edit or navigation requested by clicking in the rendered document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocumentAction {
+    /// Toggle the task marker at this byte offset
+    /// (see [`crate::document::markdown::toggle_task`])
+    ToggleTask { offset: usize },
+    /// Move the editor to this source byte offset
+    JumpToSource { offset: usize },
+    /// Open a local file, scrolled to a heading anchor
+    OpenFile {
+        path: PathBuf,
+        anchor: Option<String>,
+    },
+    /// Open an external URL (after asking the user)
+    OpenUrl(String),
+}
+
+/// Per-frame options for [`render_document`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ViewOptions<'a> {
+    /// Directory used to resolve relative image and link paths
+    pub base_dir: Option<&'a Path>,
+    /// Source byte offset to scroll into view (editor → preview sync)
+    pub reveal: Option<usize>,
+}
+
+/// Render a document into the given UI
+///
+/// Top-level blocks with a [`Document::source_map`] entry are clickable and
+/// report [`DocumentAction::JumpToSource`]; `options.reveal` scrolls the
+/// enclosing `ScrollArea` to the matching position within its block.
+pub fn render_document(
+    ui: &mut egui::Ui,
+    doc: &Document,
+    options: ViewOptions,
+) -> Option<DocumentAction> {
+    if let Some(title) = &doc.title {
+        ui.label(egui::RichText::new(title).size(30.0).strong());
+        ui.separator();
+    }
+
+    if !doc.metadata.is_empty() {
+        egui::Frame::NONE
+            .fill(ui.visuals().faint_bg_color)
+            .inner_margin(egui::Margin::same(6))
+            .show(ui, |ui| {
+                egui::Grid::new("document_metadata").show(ui, |ui| {
+                    for (key, value) in &doc.metadata {
+                        ui.label(egui::RichText::new(key).strong());
+                        ui.label(value);
+                        ui.end_row();
+                    }
+                });
+            });
+        ui.add_space(5.0);
+    }
+
+    let mut view = View {
+        base_dir: options.base_dir,
+        action: None,
+        anchors: links::anchors(doc),
+        next_heading: 0,
+        scroll_target: ui
+            .ctx()
+            .data_mut(|data| data.remove_temp::<String>(scroll_target_id())),
+    };
+    let reveal = options
+        .reveal
+        .and_then(|offset| Some((doc.block_at(offset)?, offset)));
+
+    for (index, block) in doc.blocks.iter().enumerate() {
+        let source = doc.source_map.get(index);
+        let sense = match source {
+            Some(_) => egui::Sense::click(),
+            None => egui::Sense::hover(),
+        };
+        let response = ui
+            .push_id(index, |ui| {
+                ui.scope_builder(egui::UiBuilder::new().sense(sense), |ui| {
+                    view.block(ui, block)
+                })
+                .response
+            })
+            .inner;
+
+        let Some(range) = source else { continue };
+        if response.clicked() && view.action.is_none() {
+            view.action = Some(DocumentAction::JumpToSource {
+                offset: range.start,
+            });
+        }
+        if let Some((_, offset)) = reveal.filter(|(block, _)| *block == index) {
+            // Interpolate within the block so long blocks scroll smoothly
+            let fraction = if range.end > range.start {
+                (offset.saturating_sub(range.start) as f32 / (range.end - range.start) as f32)
+                    .min(1.0)
+            } else {
+                0.0
+            };
+            let rect = response.rect;
+            let y = rect.top() + rect.height() * fraction;
+            ui.scroll_to_rect(
+                egui::Rect::from_x_y_ranges(rect.x_range(), y..=y),
+                Some(egui::Align::TOP),
+            );
+        }
+    }
+
+    view.action
+}
+
+/// Memory slot holding the anchor to scroll to on the next frame
+fn scroll_target_id() -> egui::Id {
+    egui::Id::new("document_view_scroll_target")
+}
+
+/// Per-frame rendering state
+struct View<'a> {
+    base_dir: Option<&'a Path>,
+    action: Option<DocumentAction>,
+    /// Heading anchors in render order, see [`links::anchors`]
+    anchors: Vec<String>,
+    next_heading: usize,
+    /// Anchor requested by a footnote or `#anchor` link on the previous frame
+    scroll_target: Option<String>,
+}
+
+impl View<'_> {
+    fn blocks(&mut self, ui: &mut egui::Ui, blocks: &[Block]) {
+        for (index, block) in blocks.iter().enumerate() {
+            ui.push_id(index, |ui| self.block(ui, block));
+        }
+    }
+
+    fn block(&mut self, ui: &mut egui::Ui, block: &Block) {
+        match block {
+            Block::Heading { level, content } => {
+                let size = match level {
+                    1 => 30.0,
+                    2 => 24.0,
+                    3 => 20.0,
+                    4 => 18.0,
+                    5 => 16.0,
+                    _ => 14.0,
+                };
+                ui.add_space(10.0);
+                let style = InlineStyle {
+                    size: Some(size),
+                    strong: true,
+                    ..Default::default()
+                };
+                let response = self.paragraph(ui, content, style);
+                if let Some(anchor) = self.anchors.get(self.next_heading).cloned() {
+                    self.scroll_if_target(&response, &anchor);
+                }
+                self.next_heading += 1;
+                ui.add_space(5.0);
+            }
+
+            Block::Paragraph(content) => {
+                ui.add_space(5.0);
+                self.paragraph(ui, content, InlineStyle::default());
+                ui.add_space(5.0);
+            }
+
+            Block::List(list) => {
+                ui.add_space(5.0);
+                self.list(ui, list);
+                ui.add_space(5.0);
+            }
+
+            Block::CodeBlock { lang, code } => {
+                ui.add_space(5.0);
+                egui::Frame::NONE
+                    .fill(ui.style().visuals.code_bg_color)
+                    .inner_margin(egui::Margin::same(8))
+                    .show(ui, |ui| {
+                        if !lang.is_empty() && !code.is_empty() {
+                            render_highlighted_code(ui, code, lang);
+                        } else {
+                            ui.label(
+                                egui::RichText::new(code.trim_end_matches('\n'))
+                                    .monospace()
+                                    .color(egui::Color32::from_rgb(200, 200, 200)),
+                            );
+                        }
+                    });
+                ui.add_space(5.0);
+            }
+
+            Block::BlockQuote(blocks) => {
+                let response = egui::Frame::NONE
+                    .inner_margin(egui::Margin {
+                        left: 12,
+                        ..Default::default()
+                    })
+                    .show(ui, |ui| self.blocks(ui, blocks))
+                    .response;
+                let rect = response.rect;
+                ui.painter().vline(
+                    rect.left() + 3.0,
+                    rect.y_range(),
+                    egui::Stroke::new(3.0, ui.visuals().weak_text_color()),
+                );
+            }
+
+            Block::Table(table) => {
+                ui.add_space(5.0);
+                self.table(ui, table);
+                ui.add_space(5.0);
+            }
+
+            Block::Math(tex) => {
+                ui.add_space(5.0);
+                ui.vertical_centered(|ui| {
+                    render_math(ui, tex, true, 18.0).on_hover_text(tex);
+                });
+                ui.add_space(5.0);
+            }
+
+            Block::Diagram(diagram) => {
+                ui.add_space(5.0);
+                render_diagram(ui, diagram);
+                ui.add_space(5.0);
+            }
+
+            Block::Rule => {
+                ui.add_space(5.0);
+                ui.separator();
+                ui.add_space(5.0);
+            }
+
+            Block::Footnote { label, blocks } => {
+                let response = ui
+                    .horizontal(|ui| {
+                        if ui
+                            .link(egui::RichText::new(format!("{label}.")).small())
+                            .on_hover_text("Back to reference")
+                            .clicked()
+                        {
+                            request_scroll(ui, format!("fnref-{label}"));
+                        }
+                        ui.vertical(|ui| self.blocks(ui, blocks));
+                    })
+                    .response;
+                self.scroll_if_target(&response, &format!("fn-{label}"));
+            }
+        }
+    }
+
+    fn list(&mut self, ui: &mut egui::Ui, list: &List) {
+        for (index, item) in list.items.iter().enumerate() {
+            ui.push_id(index, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add_space(8.0);
+                    match (item.checked, list.start) {
+                        (Some(checked), _) => {
+                            let mut checked = checked;
+                            let response = ui.add_enabled(
+                                item.task_offset.is_some(),
+                                egui::Checkbox::without_text(&mut checked),
+                            );
+                            if response.changed() {
+                                if let Some(offset) = item.task_offset {
+                                    self.action = Some(DocumentAction::ToggleTask { offset });
+                                }
+                            }
+                        }
+                        (None, Some(start)) => {
+                            ui.label(format!("{}.", start + index as u64));
+                        }
+                        (None, None) => {
+                            ui.label("•");
+                        }
+                    }
+                    ui.vertical(|ui| self.blocks(ui, &item.blocks));
+                });
+            });
+        }
+    }
+
+    fn table(&mut self, ui: &mut egui::Ui, table: &Table) {
+        let columns = table.column_count();
+        if columns == 0 {
+            return;
+        }
+
+        egui::Frame::NONE
+            .stroke(ui.visuals().widgets.noninteractive.bg_stroke)
+            .inner_margin(egui::Margin::same(4))
+            .show(ui, |ui| {
+                egui::Grid::new("table")
+                    .striped(true)
+                    .spacing([16.0, 4.0])
+                    .show(ui, |ui| {
+                        let header_style = InlineStyle {
+                            strong: true,
+                            ..Default::default()
+                        };
+                        if !table.header.is_empty() {
+                            self.table_row(ui, table, &table.header, header_style);
+                        }
+                        for row in &table.rows {
+                            self.table_row(ui, table, row, InlineStyle::default());
+                        }
+                    });
+            });
+    }
+
+    fn table_row(
+        &mut self,
+        ui: &mut egui::Ui,
+        table: &Table,
+        cells: &[Vec<Inline>],
+        style: InlineStyle,
+    ) {
+        for column in 0..table.column_count() {
+            let align = match table.alignments.get(column).copied().unwrap_or_default() {
+                Alignment::Center => egui::Align::Center,
+                Alignment::Right => egui::Align::Max,
+                Alignment::None | Alignment::Left => egui::Align::Min,
+            };
+            let content = cells.get(column).map(Vec::as_slice).unwrap_or_default();
+            ui.with_layout(egui::Layout::top_down(align), |ui| {
+                self.paragraph(ui, content, style);
+            });
+        }
+        ui.end_row();
+    }
+
+    fn paragraph(
+        &mut self,
+        ui: &mut egui::Ui,
+        inlines: &[Inline],
+        style: InlineStyle,
+    ) -> egui::Response {
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing.x = 0.0;
+            self.inlines(ui, inlines, style);
+        })
+        .response
+    }
+
+    fn inlines(&mut self, ui: &mut egui::Ui, inlines: &[Inline], style: InlineStyle) {
+        for inline in inlines {
+            match inline {
+                Inline::Text(text) => {
+                    ui.label(style.rich_text(text));
+                }
+                Inline::Code(code) => {
+                    ui.label(
+                        style
+                            .rich_text(code)
+                            .monospace()
+                            .background_color(ui.style().visuals.code_bg_color),
+                    );
+                }
+                Inline::Emphasis(content) => {
+                    let style = InlineStyle {
+                        italics: true,
+                        ..style
+                    };
+                    self.inlines(ui, content, style);
+                }
+                Inline::Strong(content) => {
+                    let style = InlineStyle {
+                        strong: true,
+                        ..style
+                    };
+                    self.inlines(ui, content, style);
+                }
+                Inline::Strikethrough(content) => {
+                    let style = InlineStyle {
+                        strikethrough: true,
+                        ..style
+                    };
+                    self.inlines(ui, content, style);
+                }
+                Inline::Link { url, content } => {
+                    let text = crate::document::plain_text(content);
+                    let response = ui.link(style.rich_text(&text)).on_hover_text(url);
+                    if response.clicked() {
+                        self.follow_link(ui, url);
+                    }
+                }
+                Inline::Image {
+                    src,
+                    alt,
+                    width,
+                    height,
+                } => render_image(ui, src, alt, (*width, *height), self.base_dir),
+                Inline::Math(tex) => {
+                    let size = style
+                        .size
+                        .unwrap_or_else(|| egui::TextStyle::Body.resolve(ui.style()).size);
+                    render_math(ui, tex, false, size).on_hover_text(tex);
+                }
+                Inline::FootnoteReference(label) => {
+                    let response = ui
+                        .link(egui::RichText::new(format!("[{label}]")).small_raised())
+                        .on_hover_text("Go to footnote");
+                    if response.clicked() {
+                        request_scroll(ui, format!("fn-{label}"));
+                    }
+                    self.scroll_if_target(&response, &format!("fnref-{label}"));
+                }
+                Inline::LineBreak => {
+                    ui.end_row();
+                }
+            }
+        }
+    }
+
+    fn follow_link(&mut self, ui: &egui::Ui, url: &str) {
+        match links::classify(url, self.base_dir) {
+            Target::Anchor(anchor) => request_scroll(ui, anchor),
+            Target::File { path, anchor } => {
+                self.action = Some(DocumentAction::OpenFile { path, anchor });
+            }
+            Target::External(url) => self.action = Some(DocumentAction::OpenUrl(url)),
+        }
+    }
+
+    fn scroll_if_target(&mut self, response: &egui::Response, anchor: &str) {
+        if self.scroll_target.as_deref() == Some(anchor) {
+            response.scroll_to_me(Some(egui::Align::TOP));
+            self.scroll_target = None;
+        }
+    }
+}
+
+/// Scroll to `anchor` once it is laid out on the next frame
+fn request_scroll(ui: &egui::Ui, anchor: String) {
+    scroll_to_anchor(ui.ctx(), anchor);
+}
+
+/// Scroll the next rendered document to a heading (or footnote) anchor,
+/// e.g. after opening a file through a `other.md#anchor` link
+pub fn scroll_to_anchor(ctx: &egui::Context, anchor: String) {
+    ctx.data_mut(|data| data.insert_temp(scroll_target_id(), anchor));
+    ctx.request_repaint();
+}
+
+/// Inline formatting inherited from enclosing elements
+#[derive(Debug, Clone, Copy, Default)]
+struct InlineStyle {
+    size: Option<f32>,
+    strong: bool,
+    italics: bool,
+    strikethrough: bool,
+}
+
+impl InlineStyle {
+    fn rich_text(&self, text: &str) -> egui::RichText {
+        let mut rich = egui::RichText::new(text);
+        if let Some(size) = self.size {
+            rich = rich.size(size);
+        }
+        if self.strong {
+            rich = rich.strong();
+        }
+        if self.italics {
+            rich = rich.italics();
+        }
+        if self.strikethrough {
+            rich = rich.strikethrough();
+        }
+        rich
+    }
+}
+
+fn render_image(
+    ui: &mut egui::Ui,
+    src: &str,
+    alt: &str,
+    (width, height): (Option<u32>, Option<u32>),
+    base_dir: Option<&Path>,
+) {
+    let placeholder = |ui: &mut egui::Ui, icon: &str, hover: &str| {
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            ui.label(egui::RichText::new(format!("{icon} {alt}")).weak())
+                .on_hover_text(hover);
+        });
+    };
+
+    match image_cache::load(ui.ctx(), src, base_dir) {
+        image_cache::ImageState::Loaded { texture, size } => {
+            // The image never overflows the preview
+            let (x, y) = display_size((size.x, size.y), width, height);
+            let mut size = egui::vec2(x, y);
+            let available = ui.available_width().max(1.0);
+            if size.x > available {
+                size *= available / size.x;
+            }
+            ui.add(egui::Image::new(&texture).fit_to_exact_size(size))
+                .on_hover_text(alt);
+        }
+        image_cache::ImageState::Remote(url) => {
+            placeholder(ui, "🌐", &format!("{url}\nRemote images are not loaded"))
+        }
+        image_cache::ImageState::Failed(err) => placeholder(ui, "🖼", &err),
+    }
+}
@@ -0,0 +1,803 @@
+//! Language-aware editing helpers for the main editor
+//!
+//! Bracket pairs are matched and highlighted around the cursor. Typing an
+//! opening bracket or quote closes it, typing the closer over one steps
+//! past it, and Backspace between an empty pair deletes both. Enter keeps
+//! the line's indentation, adds a level after an opening bracket (or `:` in
+//! Python and YAML), and continues Markdown lists, renumbering the ordered
+//! items that follow. Tab and Shift+Tab indent and outdent the selected
+//! lines.
+//!
+//! What applies is chosen per language by [`config`]. The helpers work on
+//! the text and a char selection and return an [`Edit`]; [`handle_input`]
+//! applies them to the editor's pending events before the `TextEdit` sees
+//! them.
+
+use std::ops::Range;
+
+use eframe::egui::{self, text::CCursor, Color32, Event, Key, Rect, Stroke};
+
+use super::editor_cursor::{self, byte_index, char_index};
+use super::text_info::Indentation;
+
+/// How far from the cursor to look for a matching bracket, in bytes
+const MAX_BRACKET_SCAN: usize = 100_000;
+
+/// Editing behavior of one language
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LanguageConfig {
+    /// Pairs highlighted and jumped between
+    pub brackets: &'static [(char, char)],
+    /// Pairs closed as the opener is typed
+    pub auto_close: &'static [(char, char)],
+    /// Line endings that indent the next line
+    pub indent_after: &'static [char],
+    /// Continue `-`, `1.` and `- [ ]` list items on Enter
+    pub lists: bool,
+}
+
+const BRACKETS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}')];
+
+/// Editing behavior for the syntax called `language`
+pub fn config(language: &str) -> LanguageConfig {
+    match language {
+        "Markdown" | "MultiMarkdown" => LanguageConfig {
+            brackets: BRACKETS,
+            auto_close: &[('(', ')'), ('[', ']'), ('{', '}'), ('`', '`'), ('"', '"')],
+            indent_after: &[],
+            lists: true,
+        },
+        "LaTeX" | "TeX" => LanguageConfig {
+            brackets: BRACKETS,
+            auto_close: &[('(', ')'), ('[', ']'), ('{', '}'), ('$', '$')],
+            indent_after: &[],
+            lists: false,
+        },
+        "HTML" | "XML" => LanguageConfig {
+            brackets: &[('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')],
+            auto_close: &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')],
+            indent_after: &['{', '[', '('],
+            lists: false,
+        },
+        "Python" | "YAML" => LanguageConfig {
+            brackets: BRACKETS,
+            auto_close: &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')],
+            indent_after: &['{', '[', '(', ':'],
+            lists: false,
+        },
+        // Lifetimes and char literals make a lone `'` common
+        "Rust" => LanguageConfig {
+            brackets: BRACKETS,
+            auto_close: &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"')],
+            indent_after: &['{', '[', '('],
+            lists: false,
+        },
+        "Plain Text" => LanguageConfig {
+            brackets: BRACKETS,
+            auto_close: BRACKETS,
+            indent_after: &[],
+            lists: false,
+        },
+        _ => LanguageConfig {
+            brackets: BRACKETS,
+            auto_close: &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')],
+            indent_after: &['{', '[', '('],
+            lists: false,
+        },
+    }
+}
+
+/// Replace the chars in `range` with `insert`, leaving `selection` (char
+/// indices into the edited text) selected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub range: Range<usize>,
+    pub insert: String,
+    pub selection: Range<usize>,
+}
+
+impl Edit {
+    /// Apply to `text`; `false` when the text is unchanged
+    pub fn apply(&self, text: &mut String) -> bool {
+        let start = byte_index(text, self.range.start);
+        let end = byte_index(text, self.range.end);
+        if start == end && self.insert.is_empty() {
+            return false;
+        }
+        text.replace_range(start..end, &self.insert);
+        true
+    }
+}
+
+/// Byte offset of the start of the line holding byte `at`
+fn line_start(text: &str, at: usize) -> usize {
+    text[..at].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// Byte offset of the end (before the line break) of the line holding `at`
+fn line_end(text: &str, at: usize) -> usize {
+    text[at..].find('\n').map_or(text.len(), |i| at + i)
+}
+
+fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Typing `c` over `selection`: wrap the selection in a pair, step over a
+/// closer, or insert the opener with its closer
+pub fn type_char(
+    text: &str,
+    selection: Range<usize>,
+    c: char,
+    config: &LanguageConfig,
+) -> Option<Edit> {
+    let start = byte_index(text, selection.start);
+    let end = byte_index(text, selection.end);
+    let pair = config.auto_close.iter().find(|(open, _)| *open == c);
+
+    if start != end {
+        let &(open, close) = pair?;
+        return Some(Edit {
+            insert: format!("{open}{}{close}", &text[start..end]),
+            selection: selection.start + 1..selection.end + 1,
+            range: selection,
+        });
+    }
+
+    let prev = text[..start].chars().next_back();
+    let next = text[start..].chars().next();
+    let cursor = selection.start;
+    if next == Some(c) && config.auto_close.iter().any(|&(_, close)| close == c) {
+        return Some(Edit {
+            range: selection,
+            insert: String::new(),
+            selection: cursor + 1..cursor + 1,
+        });
+    }
+
+    let &(open, close) = pair?;
+    // Only close before whitespace, punctuation or a closing bracket, so
+    // typing in front of a word doesn't leave a stray closer
+    let closes_here = next.is_none_or(|next| {
+        next.is_whitespace()
+            || ",;:.".contains(next)
+            || config.brackets.iter().any(|&(_, close)| close == next)
+    });
+    // Quotes after a word are apostrophes or closing quotes
+    let after_word = prev.is_some_and(|prev| prev.is_alphanumeric() || prev == open);
+    if !closes_here || (open == close && after_word) {
+        return None;
+    }
+    Some(Edit {
+        range: selection,
+        insert: format!("{open}{close}"),
+        selection: cursor + 1..cursor + 1,
+    })
+}
+
+/// Backspace between an empty auto-closed pair deletes both halves
+pub fn backspace(text: &str, selection: Range<usize>, config: &LanguageConfig) -> Option<Edit> {
+    if !selection.is_empty() || selection.start == 0 {
+        return None;
+    }
+    let at = byte_index(text, selection.start);
+    let prev = text[..at].chars().next_back()?;
+    let next = text[at..].chars().next()?;
+    let cursor = selection.start - 1;
+    config.auto_close.contains(&(prev, next)).then(|| Edit {
+        range: cursor..selection.start + 1,
+        insert: String::new(),
+        selection: cursor..cursor,
+    })
+}
+
+/// A Markdown list item's marker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Marker {
+    Bullet(char),
+    /// Number and the `.` or `)` after it
+    Number(u64, char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ListItem<'a> {
+    indent: &'a str,
+    marker: Marker,
+    task: bool,
+    /// Byte offset of the item's text in the line
+    content: usize,
+}
+
+impl ListItem<'_> {
+    /// Marker (and unchecked box) for the item after this one
+    fn next_marker(&self) -> String {
+        let marker = match self.marker {
+            Marker::Bullet(bullet) => bullet.to_string(),
+            Marker::Number(number, delimiter) => format!("{}{delimiter}", number + 1),
+        };
+        if self.task {
+            format!("{marker} [ ] ")
+        } else {
+            format!("{marker} ")
+        }
+    }
+}
+
+/// Parse a `-`, `*`, `+`, `1.` or `1)` list item, with an optional task box
+fn list_item(line: &str) -> Option<ListItem<'_>> {
+    let indent = leading_whitespace(line);
+    let rest = &line[indent.len()..];
+    let (marker, marker_len) = match rest.chars().next()? {
+        bullet @ ('-' | '*' | '+') => (Marker::Bullet(bullet), 1),
+        _ => {
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let delimiter = rest[digits..].chars().next()?;
+            if !(1..=9).contains(&digits) || !matches!(delimiter, '.' | ')') {
+                return None;
+            }
+            (
+                Marker::Number(rest[..digits].parse().ok()?, delimiter),
+                digits + 1,
+            )
+        }
+    };
+    let after = &rest[marker_len..];
+    if !(after.is_empty() || after.starts_with([' ', '\t'])) {
+        return None;
+    }
+    let text = after.trim_start_matches([' ', '\t']);
+    let (task, text) = match text.get(..3) {
+        Some("[ ]" | "[x]" | "[X]") => (true, text[3..].trim_start_matches([' ', '\t'])),
+        _ => (false, text),
+    };
+    Some(ListItem {
+        indent,
+        marker,
+        task,
+        content: line.len() - text.len(),
+    })
+}
+
+/// Renumber the `indent`ed items numbered with `delimiter` at the start of
+/// `lines` from `number`, stopping at the first line that isn't one of them
+/// or nested under them; returns the rewritten lines and how many bytes of
+/// `lines` they replace
+fn renumber(lines: &str, indent: &str, delimiter: char, mut number: u64) -> (String, usize) {
+    let mut out = String::new();
+    let mut consumed = 0;
+    for line in lines.split_inclusive('\n') {
+        let body = line.trim_end_matches(['\r', '\n']);
+        let nested = !body.trim().is_empty() && leading_whitespace(body).len() > indent.len();
+        match list_item(body) {
+            Some(ListItem {
+                indent: item_indent,
+                marker: Marker::Number(_, item_delimiter),
+                ..
+            }) if item_indent == indent && item_delimiter == delimiter => {
+                let marker = line[indent.len()..].find(delimiter).unwrap_or(0);
+                let rest = &line[indent.len() + marker + 1..];
+                out.push_str(&format!("{indent}{number}{delimiter}{rest}"));
+                number += 1;
+            }
+            _ if nested => out.push_str(line),
+            _ => break,
+        }
+        consumed += line.len();
+    }
+    (out, consumed)
+}
+
+/// Enter over `selection`: a `line_break` keeping the indentation, one
+/// level deeper after an opener, or the next list item
+pub fn newline(
+    text: &str,
+    selection: Range<usize>,
+    config: &LanguageConfig,
+    indentation: Indentation,
+    line_break: &str,
+) -> Edit {
+    let start = byte_index(text, selection.start);
+    let end = byte_index(text, selection.end);
+    let line_start = line_start(text, start);
+    let line = &text[line_start..line_end(text, start)];
+    let before = &text[line_start..start];
+    let indent = leading_whitespace(before);
+    let replace = |insert: String, cursor: usize| Edit {
+        range: selection.clone(),
+        selection: cursor..cursor,
+        insert,
+    };
+
+    if let Some(item) = list_item(line).filter(|item| config.lists && before.len() >= item.content)
+    {
+        let line_range = selection.start - before.chars().count()
+            ..selection.start + line[before.len()..].chars().count();
+        // Enter on an empty item ends the list
+        if line[item.content..].trim().is_empty() && start == end {
+            return Edit {
+                range: line_range.clone(),
+                insert: String::new(),
+                selection: line_range.start..line_range.start,
+            };
+        }
+        let insert = format!("{line_break}{}{}", item.indent, item.next_marker());
+        let cursor = selection.start + insert.chars().count();
+        let Marker::Number(number, delimiter) = item.marker else {
+            return replace(insert, cursor);
+        };
+        // Later items of the same list move up by one
+        let rest_start = line_end(text, end);
+        let following = text[rest_start..].strip_prefix('\n').unwrap_or("");
+        let (renumbered, consumed) = renumber(following, item.indent, delimiter, number + 2);
+        if consumed == 0 {
+            return replace(insert, cursor);
+        }
+        let block_end = rest_start + 1 + consumed;
+        return Edit {
+            range: selection.start..char_index(text, block_end),
+            insert: format!("{insert}{}\n{renumbered}", &text[end..rest_start]),
+            selection: cursor..cursor,
+        };
+    }
+
+    let prev = before.trim_end().chars().next_back();
+    let next = text[end..].chars().next();
+    if !prev.is_some_and(|prev| config.indent_after.contains(&prev)) {
+        let insert = format!("{line_break}{indent}");
+        let cursor = selection.start + insert.chars().count();
+        return replace(insert, cursor);
+    }
+    let inner = format!("{line_break}{indent}{}", indentation.unit());
+    let cursor = selection.start + inner.chars().count();
+    // Between a pair the closer goes on its own line
+    let between = prev
+        .zip(next)
+        .is_some_and(|pair| config.brackets.contains(&pair));
+    if between {
+        replace(format!("{inner}{line_break}{indent}"), cursor)
+    } else {
+        replace(inner, cursor)
+    }
+}
+
+/// Byte range of the whole lines touched by `start..end`; a selection
+/// ending at the start of a line leaves that line out
+fn selected_lines(text: &str, start: usize, end: usize) -> Range<usize> {
+    let end = if end > start && text[..end].ends_with('\n') {
+        end - 1
+    } else {
+        end
+    };
+    line_start(text, start)..line_end(text, end)
+}
+
+/// Rewrite the selected lines with `shift`, which returns the line's new
+/// leading text and the chars it removed from the old start; the selection
+/// moves with the text
+fn shift_lines(
+    text: &str,
+    selection: Range<usize>,
+    shift: impl Fn(&str) -> (String, usize),
+) -> Edit {
+    let start = byte_index(text, selection.start);
+    let end = byte_index(text, selection.end);
+    let lines = selected_lines(text, start, end);
+    let first = char_index(text, lines.start);
+
+    let mut insert = String::new();
+    // Per line: first char, chars added and chars removed
+    let mut changes = Vec::new();
+    let mut at = first;
+    for line in text[lines.clone()].split('\n') {
+        if !changes.is_empty() {
+            insert.push('\n');
+        }
+        let (added, removed) = shift(line);
+        let kept: String = line.chars().skip(removed).collect();
+        changes.push((at, added.chars().count(), removed));
+        at += line.chars().count() + 1;
+        insert.push_str(&added);
+        insert.push_str(&kept);
+    }
+
+    // Lines before the position's own line shift it by their whole change;
+    // its own line only by what was added or removed left of it
+    let map = |position: usize| {
+        let mut moved = 0isize;
+        for &(line, added, removed) in &changes {
+            if position < line {
+                break;
+            }
+            // A position at the start of its line stays there
+            let column = position - line;
+            if column > 0 {
+                moved += added as isize - removed.min(column) as isize;
+            }
+        }
+        (position as isize + moved).max(0) as usize
+    };
+    Edit {
+        range: first..char_index(text, lines.end),
+        selection: map(selection.start)..map(selection.end),
+        insert,
+    }
+}
+
+/// Indent the lines of `selection` by one level; blank lines are left
+pub fn indent_lines(text: &str, selection: Range<usize>, indentation: Indentation) -> Edit {
+    let unit = indentation.unit();
+    shift_lines(text, selection, |line| {
+        if line.trim().is_empty() {
+            (String::new(), 0)
+        } else {
+            (unit.clone(), 0)
+        }
+    })
+}
+
+/// Remove up to one level of indentation from the lines of `selection`
+pub fn outdent_lines(text: &str, selection: Range<usize>, indentation: Indentation) -> Edit {
+    let width = indentation.width();
+    shift_lines(text, selection, |line| {
+        let removed = if line.starts_with('\t') {
+            1
+        } else {
+            line.chars().take(width).take_while(|&c| c == ' ').count()
+        };
+        (String::new(), removed)
+    })
+}
+
+/// Tab over `selection`: indent the selected lines when they span more
+/// than one (or the cursor is in a list item), otherwise insert one level
+/// of indentation, spaces stopping at the next tab stop
+pub fn tab(
+    text: &str,
+    selection: Range<usize>,
+    config: &LanguageConfig,
+    indentation: Indentation,
+) -> Edit {
+    let start = byte_index(text, selection.start);
+    let end = byte_index(text, selection.end);
+    let lines = selected_lines(text, start, end);
+    let in_list = config.lists && selection.is_empty() && list_item(&text[lines.clone()]).is_some();
+    if text[lines].contains('\n') || in_list {
+        return indent_lines(text, selection, indentation);
+    }
+    let insert = match indentation {
+        Indentation::Tabs => "\t".to_string(),
+        Indentation::Spaces(width) => {
+            let column = text[line_start(text, start)..start].chars().count();
+            " ".repeat(width - column % width.max(1))
+        }
+    };
+    let cursor = selection.start + insert.chars().count();
+    Edit {
+        range: selection,
+        insert,
+        selection: cursor..cursor,
+    }
+}
+
+/// The bracket next to char `cursor` (before it first) and its match, as
+/// char indices
+pub fn matching_bracket(
+    text: &str,
+    cursor: usize,
+    config: &LanguageConfig,
+) -> Option<(usize, usize)> {
+    let at = byte_index(text, cursor);
+    let before = text[..at].char_indices().next_back();
+    let after = text[at..].char_indices().next().map(|(i, c)| (at + i, c));
+    [before, after]
+        .into_iter()
+        .flatten()
+        .find_map(|(position, c)| {
+            let found = config.brackets.iter().find_map(|&(open, close)| {
+                if open == close {
+                    None
+                } else if c == open {
+                    let from = position + c.len_utf8();
+                    let scan = &text[from..(from + MAX_BRACKET_SCAN).min(text.len())];
+                    find_closer(scan, open, close).map(|i| from + i)
+                } else if c == close {
+                    let from = position.saturating_sub(MAX_BRACKET_SCAN);
+                    let from = (from..position)
+                        .find(|&i| text.is_char_boundary(i))
+                        .unwrap_or(position);
+                    find_opener(&text[from..position], open, close).map(|i| from + i)
+                } else {
+                    None
+                }
+            })?;
+            Some((char_index(text, position), char_index(text, found)))
+        })
+}
+
+/// Byte offset of the `close` balancing an `open` just before `text`
+fn find_closer(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in text.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            if depth == 0 {
+                return Some(i);
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+/// Byte offset of the `open` balancing a `close` just after `text`
+fn find_opener(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in text.char_indices().rev() {
+        if c == close {
+            depth += 1;
+        } else if c == open {
+            if depth == 0 {
+                return Some(i);
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+/// The edit a pending editor event makes, if the helpers handle it
+fn edit_for(
+    event: &Event,
+    text: &str,
+    selection: Range<usize>,
+    config: &LanguageConfig,
+    indentation: Indentation,
+    line_break: &str,
+) -> Option<Edit> {
+    match event {
+        Event::Text(typed) => {
+            let mut chars = typed.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => type_char(text, selection, c, config),
+                _ => None,
+            }
+        }
+        Event::Key {
+            key,
+            pressed: true,
+            modifiers,
+            ..
+        } => match key {
+            Key::Enter if modifiers.is_none() => {
+                Some(newline(text, selection, config, indentation, line_break))
+            }
+            Key::Tab if modifiers.is_none() => Some(tab(text, selection, config, indentation)),
+            Key::Tab if modifiers.matches_exact(egui::Modifiers::SHIFT) => {
+                Some(outdent_lines(text, selection, indentation))
+            }
+            Key::Backspace if modifiers.is_none() => backspace(text, selection, config),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether the `TextEdit` would act on `event`; the helpers stop at the
+/// first such event they don't handle so edits keep their order
+fn reaches_editor(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Text(_)
+            | Event::Paste(_)
+            | Event::Cut
+            | Event::Key { pressed: true, .. }
+            | Event::PointerButton { pressed: true, .. }
+    )
+}
+
+/// Apply the helpers to this frame's events for the main editor, removing
+/// the ones they handle; `true` when the text changed
+pub fn handle_input(
+    ctx: &egui::Context,
+    text: &mut String,
+    config: &LanguageConfig,
+    indentation: Indentation,
+    line_break: &str,
+) -> bool {
+    let events = ctx.input_mut(|input| std::mem::take(&mut input.events));
+    let mut remaining = Vec::with_capacity(events.len());
+    let mut handling = true;
+    let mut changed = false;
+    for event in events {
+        if handling {
+            let edit = editor_cursor::char_range(ctx).and_then(|selection| {
+                edit_for(&event, text, selection, config, indentation, line_break)
+            });
+            if let Some(edit) = edit {
+                changed |= edit.apply(text);
+                editor_cursor::set_char_range(ctx, edit.selection);
+                continue;
+            }
+            handling = !reaches_editor(&event);
+        }
+        remaining.push(event);
+    }
+    ctx.input_mut(|input| input.events = remaining);
+    changed
+}
+
+/// Outline the brackets at chars `pair` in the editor's laid out text
+pub fn paint_bracket_match(
+    ui: &egui::Ui,
+    output: &egui::text_edit::TextEditOutput,
+    pair: (usize, usize),
+    color: Color32,
+) {
+    let galley = &output.galley;
+    for index in [pair.0, pair.1] {
+        let left = galley.pos_from_cursor(CCursor::new(index));
+        let right = galley.pos_from_cursor(CCursor::new(index + 1));
+        let right_x = if right.top() == left.top() {
+            right.left()
+        } else {
+            left.left() + 6.0
+        };
+        let rect = Rect::from_min_max(left.min, egui::pos2(right_x, left.bottom()))
+            .translate(output.galley_pos.to_vec2());
+        ui.painter()
+            .rect_filled(rect, 1.0, color.gamma_multiply(0.15));
+        ui.painter().rect_stroke(
+            rect,
+            1.0,
+            Stroke::new(1.0, color.gamma_multiply(0.6)),
+            egui::StrokeKind::Inside,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Apply `edit` and mark its selection with `|` (or `[`, `]`)
+    fn show(text: &str, edit: Edit) -> String {
+        let mut text = text.to_string();
+        edit.apply(&mut text);
+        let mut chars: Vec<char> = text.chars().collect();
+        if edit.selection.is_empty() {
+            chars.insert(edit.selection.start, '|');
+        } else {
+            chars.insert(edit.selection.end, ']');
+            chars.insert(edit.selection.start, '[');
+        }
+        chars.into_iter().collect()
+    }
+
+    /// Split a text marked with `|` into the text and cursor
+    fn cursor(marked: &str) -> (String, Range<usize>) {
+        let at = marked.chars().position(|c| c == '|').unwrap();
+        (marked.replacen('|', "", 1), at..at)
+    }
+
+    #[test]
+    fn test_auto_close_and_typeover() {
+        let rust = config("Rust");
+        let type_at = |marked: &str, c: char| {
+            let (text, selection) = cursor(marked);
+            type_char(&text, selection, c, &rust).map(|edit| show(&text, edit))
+        };
+        assert_eq!(type_at("f|", '(').as_deref(), Some("f(|)"));
+        assert_eq!(type_at("f(|)", ')').as_deref(), Some("f()|"));
+        assert_eq!(type_at("x = |", '"').as_deref(), Some("x = \"|\""));
+        assert_eq!(type_at("\"ab|\"", '"').as_deref(), Some("\"ab\"|"));
+        // Not in front of a word, and no quotes closed after one
+        assert_eq!(type_at("|word", '('), None);
+        assert_eq!(type_at("it|", '"'), None);
+        assert_eq!(type_at("a|", '\''), None);
+
+        // A selection is wrapped
+        let edit = type_char("a bc d", 2..4, '[', &rust).unwrap();
+        assert_eq!(show("a bc d", edit), "a [[bc]] d");
+
+        // Backspace inside an empty pair removes both halves
+        let (text, selection) = cursor("x(|)");
+        assert_eq!(
+            show(&text, backspace(&text, selection, &rust).unwrap()),
+            "x|"
+        );
+        let (text, selection) = cursor("x(|a)");
+        assert_eq!(backspace(&text, selection, &rust), None);
+    }
+
+    #[test]
+    fn test_newline_indents() {
+        let enter = |marked: &str, language: &str| {
+            let (text, selection) = cursor(marked);
+            show(
+                &text,
+                newline(
+                    &text,
+                    selection,
+                    &config(language),
+                    Indentation::Spaces(4),
+                    "\n",
+                ),
+            )
+        };
+        assert_eq!(enter("    let x = 1;|", "Rust"), "    let x = 1;\n    |");
+        assert_eq!(enter("fn f() {|}", "Rust"), "fn f() {\n    |\n}");
+        assert_eq!(enter("  if x:|", "Python"), "  if x:\n      |");
+        assert_eq!(enter("  note:|", "Plain Text"), "  note:\n  |");
+
+        let (text, selection) = cursor("  a|\r\nb");
+        let edit = newline(&text, selection, &config("Rust"), Indentation::Tabs, "\r\n");
+        assert_eq!(show(&text, edit), "  a\r\n  |\r\nb");
+    }
+
+    #[test]
+    fn test_markdown_list_continuation() {
+        let enter = |marked: &str| {
+            let (text, selection) = cursor(marked);
+            show(
+                &text,
+                newline(
+                    &text,
+                    selection,
+                    &config("Markdown"),
+                    Indentation::Spaces(2),
+                    "\n",
+                ),
+            )
+        };
+        assert_eq!(enter("- one|"), "- one\n- |");
+        assert_eq!(enter("  * [x] done|"), "  * [x] done\n  * [ ] |");
+        // An empty item ends the list
+        assert_eq!(enter("- one\n- |"), "- one\n|");
+        // Ordered items after the new one are renumbered
+        assert_eq!(
+            enter("1. a|\n2. b\n   more\n  - nested\n3) c\n3. d\n\n1. other"),
+            "1. a\n2. |\n3. b\n   more\n  - nested\n3) c\n3. d\n\n1. other"
+        );
+        assert_eq!(enter("1. a|b\n2. c"), "1. a\n2. |b\n3. c");
+        // Before the marker it is a plain line break
+        assert_eq!(enter("|- one"), "\n|- one");
+    }
+
+    #[test]
+    fn test_block_indent_and_outdent() {
+        let text = "a\n\n  b\nc";
+        let all = 0..text.chars().count();
+        let indented = indent_lines(text, all.clone(), Indentation::Spaces(2));
+        assert_eq!(show(text, indented), "[  a\n\n    b\n  c]");
+        let outdented = outdent_lines(text, 1..6, Indentation::Spaces(4));
+        assert_eq!(show(text, outdented), "a[\n\nb]\nc");
+        let tabs = outdent_lines("\t\tx", 3..3, Indentation::Tabs);
+        assert_eq!(show("\t\tx", tabs), "\tx|");
+
+        // Tab on one line stops at the next tab stop; in a list item it
+        // indents the item
+        let (text, selection) = cursor("ab|c");
+        let edit = tab(&text, selection, &config("Rust"), Indentation::Spaces(4));
+        assert_eq!(show(&text, edit), "ab  |c");
+        let (text, selection) = cursor("- it|em");
+        let edit = tab(
+            &text,
+            selection,
+            &config("Markdown"),
+            Indentation::Spaces(2),
+        );
+        assert_eq!(show(&text, edit), "  - it|em");
+    }
+
+    #[test]
+    fn test_matching_bracket() {
+        let rust = config("Rust");
+        let text = "f(a, [b], (c)) ü)";
+        assert_eq!(matching_bracket(text, 2, &rust), Some((1, 13)));
+        assert_eq!(matching_bracket(text, 14, &rust), Some((13, 1)));
+        assert_eq!(matching_bracket(text, 5, &rust), Some((5, 7)));
+        assert_eq!(matching_bracket(text, 17, &rust), None);
+        assert_eq!(matching_bracket("abc", 1, &rust), None);
+    }
+}
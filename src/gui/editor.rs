@@ -5,7 +5,6 @@ use egui::{ScrollArea, TextEdit};
 /// EditorPanel manages the text editor UI and integrates syntax highlighting
 pub struct EditorPanel {
     buffer: TextBuffer,
-    #[allow(dead_code)] // TODO: Re-enable when syntax highlighting is fixed for egui 0.33
     highlighter: SyntaxHighlighter,
     current_text: String,
 }
@@ -87,21 +86,18 @@ impl Point {
         ScrollArea::vertical()
             .auto_shrink([false; 2])
             .show(ui, |ui| {
-                // TODO: Re-enable syntax highlighting after fixing egui 0.33 API compatibility
-                // Temporarily disabled to allow IME testing
+                let highlighter = &self.highlighter;
+                let file_path = self.buffer.file_path();
+                let mut layouter = |ui: &egui::Ui, text: &dyn egui::TextBuffer, wrap_width| {
+                    let mut layout_job = highlighter.highlight(text.as_str(), file_path);
+                    layout_job.wrap.max_width = wrap_width;
+                    ui.fonts_mut(|fonts| fonts.layout_job(layout_job))
+                };
                 let response = TextEdit::multiline(&mut self.current_text)
                     .font(egui::TextStyle::Monospace)
                     .code_editor()
                     .desired_width(f32::INFINITY)
-                    // .layouter(&mut |ui, text, wrap_width| {
-                    //     // Convert TextBuffer to &str for highlighting
-                    //     let text_str = text.as_str();
-                    //     // Use the highlighter to create a layout job
-                    //     let mut layout_job =
-                    //         self.highlighter.highlight(text_str, self.buffer.file_path());
-                    //     layout_job.wrap.max_width = wrap_width;
-                    //     ui.fonts(|f| f.layout_job(layout_job))
-                    // })
+                    .layouter(&mut layouter)
                     .show(ui);
 
                 // Update buffer if text changed
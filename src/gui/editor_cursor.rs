@@ -0,0 +1,127 @@
+//! Helpers for reading and moving the main editor's cursor
+//!
+//! The main editor is an `egui::TextEdit` whose cursor lives in egui's
+//! memory; these helpers load and store that state by the editor's id.
+
+use eframe::egui;
+use egui::text::{CCursor, CCursorRange};
+
+/// Widget id of the main editor `TextEdit`
+pub fn main_editor_id() -> egui::Id {
+    egui::Id::new("main_editor")
+}
+
+/// Current selection as a char range, if the editor has a cursor
+pub fn char_range(ctx: &egui::Context) -> Option<std::ops::Range<usize>> {
+    egui::TextEdit::load_state(ctx, main_editor_id())
+        .and_then(|state| state.cursor.char_range())
+        .map(|range| range.as_sorted_char_range())
+}
+
+/// Selected text, or `None` when nothing is selected
+pub fn selected_text(ctx: &egui::Context, text: &str) -> Option<String> {
+    let range = char_range(ctx)?;
+    if range.is_empty() {
+        return None;
+    }
+    Some(
+        text.chars()
+            .skip(range.start)
+            .take(range.end - range.start)
+            .collect(),
+    )
+}
+
+/// Move the cursor (collapsing any selection) to a char index
+pub fn set_cursor(ctx: &egui::Context, char_index: usize) {
+    set_char_range(ctx, char_index..char_index);
+}
+
+/// Select a char range in the editor
+pub fn set_char_range(ctx: &egui::Context, range: std::ops::Range<usize>) {
+    let id = main_editor_id();
+    let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+    state.cursor.set_char_range(Some(CCursorRange::two(
+        CCursor::new(range.start),
+        CCursor::new(range.end),
+    )));
+    state.store(ctx, id);
+}
+
+/// Replace the selection (or insert at the cursor) with `insert`
+///
+/// Falls back to appending when the editor has no cursor yet. The cursor
+/// is moved to the end of the inserted text.
+pub fn insert_at_cursor(ctx: &egui::Context, text: &mut String, insert: &str) {
+    let total_chars = text.chars().count();
+    let range = char_range(ctx).unwrap_or(total_chars..total_chars);
+    let start = byte_index(text, range.start.min(total_chars));
+    let end = byte_index(text, range.end.min(total_chars));
+
+    text.replace_range(start..end, insert);
+    set_cursor(ctx, range.start + insert.chars().count());
+}
+
+/// Convert a char index into a byte index within `text`
+pub fn byte_index(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map(|(i, _)| i)
+        .unwrap_or(text.len())
+}
+
+/// Convert a byte index into a char index within `text`
+///
+/// Out-of-range or mid-character indices are clamped to the nearest
+/// preceding character boundary.
+pub fn char_index(text: &str, byte_index: usize) -> usize {
+    let mut byte_index = byte_index.min(text.len());
+    while !text.is_char_boundary(byte_index) {
+        byte_index -= 1;
+    }
+    text[..byte_index].chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_index_multibyte() {
+        let text = "aあb";
+        assert_eq!(byte_index(text, 0), 0);
+        assert_eq!(byte_index(text, 1), 1);
+        assert_eq!(byte_index(text, 2), 4);
+        assert_eq!(byte_index(text, 3), 5);
+        assert_eq!(byte_index(text, 10), 5);
+    }
+
+    #[test]
+    fn test_char_index_clamps() {
+        let text = "aあb";
+        assert_eq!(char_index(text, 1), 1);
+        assert_eq!(char_index(text, 2), 1);
+        assert_eq!(char_index(text, 4), 2);
+        assert_eq!(char_index(text, 100), 3);
+    }
+
+    #[test]
+    fn test_insert_without_cursor_appends() {
+        let ctx = egui::Context::default();
+        let mut text = "hello".to_string();
+        insert_at_cursor(&ctx, &mut text, " world");
+        assert_eq!(text, "hello world");
+    }
+
+    #[test]
+    fn test_insert_replaces_selection() {
+        let ctx = egui::Context::default();
+        let mut text = "hello world".to_string();
+        set_char_range(&ctx, 6..11);
+        assert_eq!(selected_text(&ctx, &text).as_deref(), Some("world"));
+
+        insert_at_cursor(&ctx, &mut text, "there");
+        assert_eq!(text, "hello there");
+        assert_eq!(char_range(&ctx), Some(11..11));
+    }
+}
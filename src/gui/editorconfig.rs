@@ -0,0 +1,733 @@
+//! EditorConfig and per-language whitespace settings
+//!
+//! A file's `.editorconfig` files are found by walking up from its
+//! directory until one says `root = true`; sections of outer files apply
+//! first and later sections win. The supported properties are
+//! `indent_style`, `indent_size`, `tab_width`, `end_of_line`, `charset`,
+//! `trim_trailing_whitespace` and `insert_final_newline`.
+//!
+//! Whatever EditorConfig leaves unset comes from the per-language defaults
+//! in `<config_dir>/lala/editor.json`, then its `default` entry:
+//!
+//! ```json
+//! {
+//!   "default": { "insert_final_newline": true },
+//!   "languages": {
+//!     "Python": { "indent_style": "space", "indent_size": 4 },
+//!     "Makefile": { "indent_style": "tab" }
+//!   }
+//! }
+//! ```
+//!
+//! Indentation applies while editing; line endings, trailing whitespace,
+//! the final newline and the charset when saving.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::highlighting::user_config_dir;
+use super::text_info::Indentation;
+
+/// Name of the files looked up
+pub const FILE_NAME: &str = ".editorconfig";
+
+/// Ranges like `{1..12}` with more numbers than this match any number
+const MAX_RANGE: i64 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndentStyle {
+    Tab,
+    Space,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EndOfLine {
+    Lf,
+    Crlf,
+    Cr,
+}
+
+impl EndOfLine {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+            Self::Cr => "\r",
+        }
+    }
+}
+
+impl fmt::Display for EndOfLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lf => write!(f, "LF"),
+            Self::Crlf => write!(f, "CRLF"),
+            Self::Cr => write!(f, "CR"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Charset {
+    #[serde(rename = "latin1")]
+    Latin1,
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-8-bom")]
+    Utf8Bom,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+}
+
+impl fmt::Display for Charset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Latin1 => write!(f, "ISO 8859-1"),
+            Self::Utf8 => write!(f, "UTF-8"),
+            Self::Utf8Bom => write!(f, "UTF-8 with BOM"),
+            Self::Utf16Be => write!(f, "UTF-16 BE"),
+            Self::Utf16Le => write!(f, "UTF-16 LE"),
+        }
+    }
+}
+
+/// Whitespace properties; `None` leaves the file as it is
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Properties {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indent_style: Option<IndentStyle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indent_size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tab_width: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_of_line: Option<EndOfLine>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charset: Option<Charset>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trim_trailing_whitespace: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub insert_final_newline: Option<bool>,
+}
+
+impl Properties {
+    /// Set from an `.editorconfig` `key = value`; unknown keys and values
+    /// are ignored and `unset` clears a property
+    fn set(&mut self, key: &str, value: &str) {
+        let value = value.to_lowercase();
+        let flag = match value.as_str() {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        };
+        match key {
+            "indent_style" => {
+                self.indent_style = match value.as_str() {
+                    "tab" => Some(IndentStyle::Tab),
+                    "space" => Some(IndentStyle::Space),
+                    _ => None,
+                }
+            }
+            // `tab` means the tab width
+            "indent_size" => self.indent_size = value.parse().ok().filter(|&size| size > 0),
+            "tab_width" => self.tab_width = value.parse().ok().filter(|&size| size > 0),
+            "end_of_line" => {
+                self.end_of_line = match value.as_str() {
+                    "lf" => Some(EndOfLine::Lf),
+                    "crlf" => Some(EndOfLine::Crlf),
+                    "cr" => Some(EndOfLine::Cr),
+                    _ => None,
+                }
+            }
+            "charset" => {
+                self.charset = match value.as_str() {
+                    "latin1" => Some(Charset::Latin1),
+                    "utf-8" => Some(Charset::Utf8),
+                    "utf-8-bom" => Some(Charset::Utf8Bom),
+                    "utf-16be" => Some(Charset::Utf16Be),
+                    "utf-16le" => Some(Charset::Utf16Le),
+                    _ => None,
+                }
+            }
+            "trim_trailing_whitespace" => self.trim_trailing_whitespace = flag,
+            "insert_final_newline" => self.insert_final_newline = flag,
+            _ => {}
+        }
+    }
+
+    /// These properties, with unset ones taken from `fallback`
+    pub fn or(&self, fallback: &Properties) -> Properties {
+        Properties {
+            indent_style: self.indent_style.or(fallback.indent_style),
+            indent_size: self.indent_size.or(fallback.indent_size),
+            tab_width: self.tab_width.or(fallback.tab_width),
+            end_of_line: self.end_of_line.or(fallback.end_of_line),
+            charset: self.charset.or(fallback.charset),
+            trim_trailing_whitespace: self
+                .trim_trailing_whitespace
+                .or(fallback.trim_trailing_whitespace),
+            insert_final_newline: self.insert_final_newline.or(fallback.insert_final_newline),
+        }
+    }
+
+    /// Indentation to edit with; what isn't set comes from `detected`
+    pub fn indentation(&self, detected: Indentation) -> Indentation {
+        let size = self.indent_size.or(self.tab_width);
+        match (self.indent_style, detected) {
+            (Some(IndentStyle::Tab), _) | (None, Indentation::Tabs) => Indentation::Tabs,
+            (_, detected) => Indentation::Spaces(size.unwrap_or(detected.width())),
+        }
+    }
+
+    /// Line break to insert, `detected` (`LF`, `CRLF` or `Mixed`) unless set
+    pub fn line_break(&self, detected: &str) -> &'static str {
+        match (self.end_of_line, detected) {
+            (Some(end_of_line), _) => end_of_line.as_str(),
+            (None, "CRLF") => "\r\n",
+            (None, _) => "\n",
+        }
+    }
+
+    /// `text` as saved: line endings converted, trailing whitespace trimmed
+    /// and the final newline added or removed as set
+    pub fn normalize(&self, text: &str) -> String {
+        let trim = self.trim_trailing_whitespace == Some(true);
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        let mut last_break = self.end_of_line.map_or("\n", |eol| eol.as_str());
+        loop {
+            let Some(at) = rest.find(['\r', '\n']) else {
+                out.push_str(if trim {
+                    rest.trim_end_matches([' ', '\t'])
+                } else {
+                    rest
+                });
+                break;
+            };
+            let line = &rest[..at];
+            let line_break = if rest[at..].starts_with("\r\n") {
+                "\r\n"
+            } else {
+                &rest[at..at + 1]
+            };
+            out.push_str(if trim {
+                line.trim_end_matches([' ', '\t'])
+            } else {
+                line
+            });
+            last_break = self.end_of_line.map_or(line_break, |eol| eol.as_str());
+            out.push_str(last_break);
+            rest = &rest[at + line_break.len()..];
+        }
+
+        match self.insert_final_newline {
+            Some(true) if !out.is_empty() && !out.ends_with(['\r', '\n']) => {
+                out.push_str(last_break);
+            }
+            Some(false) => out.truncate(out.trim_end_matches(['\r', '\n']).len()),
+            _ => {}
+        }
+        out
+    }
+
+    /// Bytes of `text` in the charset; unset keeps the text's UTF-8 (and
+    /// any byte order mark it starts with)
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, String> {
+        let Some(charset) = self.charset else {
+            return Ok(text.as_bytes().to_vec());
+        };
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let utf16 = |to_bytes: fn(u16) -> [u8; 2]| {
+            std::iter::once(0xfeff)
+                .chain(text.encode_utf16())
+                .flat_map(to_bytes)
+                .collect()
+        };
+        Ok(match charset {
+            Charset::Utf8 => text.as_bytes().to_vec(),
+            Charset::Utf8Bom => ["\u{feff}", text].concat().into_bytes(),
+            Charset::Utf16Be => utf16(u16::to_be_bytes),
+            Charset::Utf16Le => utf16(u16::to_le_bytes),
+            Charset::Latin1 => text
+                .chars()
+                .map(|c| {
+                    u8::try_from(u32::from(c))
+                        .map_err(|_| format!("'{c}' cannot be saved in {charset}"))
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// Text of a file's `bytes` in `charset`, UTF-8 when unset
+pub fn decode(bytes: Vec<u8>, charset: Option<Charset>) -> Result<String, String> {
+    let utf16 = |from_bytes: fn([u8; 2]) -> u16| {
+        if !bytes.len().is_multiple_of(2) {
+            return Err("UTF-16 text with an odd number of bytes".to_string());
+        }
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| from_bytes([pair[0], pair[1]]))
+            .collect();
+        let text = String::from_utf16(&units).map_err(|e| e.to_string())?;
+        Ok(text
+            .strip_prefix('\u{feff}')
+            .map(str::to_string)
+            .unwrap_or(text))
+    };
+    match charset {
+        Some(Charset::Latin1) => Ok(bytes.iter().map(|&byte| char::from(byte)).collect()),
+        Some(Charset::Utf16Be) => utf16(u16::from_be_bytes),
+        Some(Charset::Utf16Le) => utf16(u16::from_le_bytes),
+        _ => String::from_utf8(bytes).map_err(|e| e.to_string()),
+    }
+}
+
+/// One `[glob]` section of an `.editorconfig`
+#[derive(Debug, Clone)]
+struct Section {
+    glob: Option<Regex>,
+    properties: Vec<(String, String)>,
+}
+
+/// A parsed `.editorconfig`
+#[derive(Debug, Clone)]
+struct ConfigFile {
+    root: bool,
+    sections: Vec<Section>,
+}
+
+/// Parse an `.editorconfig`; malformed lines and globs are skipped
+fn parse(text: &str) -> ConfigFile {
+    let mut root = false;
+    let mut sections: Vec<Section> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(glob) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push(Section {
+                glob: glob_regex(glob),
+                properties: Vec::new(),
+            });
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim().to_lowercase(), value.trim().to_string());
+        match sections.last_mut() {
+            Some(section) => section.properties.push((key, value)),
+            None if key == "root" => root = value.eq_ignore_ascii_case("true"),
+            None => {}
+        }
+    }
+    ConfigFile { root, sections }
+}
+
+/// Regex for an EditorConfig glob, matched against paths relative to the
+/// config's directory with `/` separators
+///
+/// `*` stops at `/`, `**` doesn't; `?`, `[chars]`, `[!chars]`, `{a,b}` and
+/// `{1..3}` work as in the shell. Globs without a `/` match file names in
+/// any subdirectory.
+fn glob_regex(glob: &str) -> Option<Regex> {
+    let anchored = glob.contains('/');
+    let glob = glob.strip_prefix('/').unwrap_or(glob);
+    let chars: Vec<char> = glob.chars().collect();
+    let balanced = {
+        let mut depth = 0i32;
+        chars.iter().all(|&c| {
+            depth += match c {
+                '{' => 1,
+                '}' => -1,
+                _ => 0,
+            };
+            depth >= 0
+        }) && depth == 0
+    };
+
+    let mut pattern = String::from(if anchored { "^" } else { "^(?:.*/)?" });
+    let mut depth = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                pattern.push_str(".*");
+                i += 1;
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            '[' => match chars[i + 1..].iter().position(|&c| c == ']') {
+                Some(length) => {
+                    let class: String = chars[i + 1..i + 1 + length].iter().collect();
+                    let (negated, class) = match class.strip_prefix('!') {
+                        Some(class) => (true, class.to_string()),
+                        None => (false, class),
+                    };
+                    let class = class.replace('\\', "\\\\").replace('[', "\\[");
+                    pattern.push_str(&format!("[{}{class}]", if negated { "^" } else { "" }));
+                    i += length + 1;
+                }
+                None => pattern.push_str("\\["),
+            },
+            '{' if balanced => {
+                let close = chars[i..].iter().position(|&c| c == '}').map(|p| i + p);
+                let body: String = close
+                    .map(|close| chars[i + 1..close].iter().collect())
+                    .unwrap_or_default();
+                if let Some(range) = number_range(&body) {
+                    pattern.push_str(&range);
+                    i = close.unwrap_or(i);
+                } else {
+                    pattern.push_str("(?:");
+                    depth += 1;
+                }
+            }
+            '}' if balanced && depth > 0 => {
+                pattern.push(')');
+                depth -= 1;
+            }
+            ',' if depth > 0 => pattern.push('|'),
+            '\\' if i + 1 < chars.len() => {
+                pattern.push_str(&regex::escape(&chars[i + 1].to_string()));
+                i += 1;
+            }
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    pattern.push('$');
+    Regex::new(&pattern).ok()
+}
+
+/// Regex for `{from..to}` integer ranges
+fn number_range(body: &str) -> Option<String> {
+    let (from, to) = body.split_once("..")?;
+    let (from, to): (i64, i64) = (from.parse().ok()?, to.parse().ok()?);
+    let (from, to) = (from.min(to), from.max(to));
+    if to - from > MAX_RANGE {
+        return Some("[+-]?[0-9]+".to_string());
+    }
+    let numbers: Vec<String> = (from..=to).map(|n| n.to_string()).collect();
+    Some(format!("(?:{})", numbers.join("|")))
+}
+
+/// `.editorconfig` settings found for one file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EditorConfig {
+    pub properties: Properties,
+    /// Files that contributed, innermost first
+    pub files: Vec<PathBuf>,
+}
+
+/// Look up the `.editorconfig` properties of the file at `path`
+///
+/// Relative paths are resolved against the current directory, so files
+/// given by a bare name still see the configuration above it.
+pub fn lookup(path: &Path) -> Result<EditorConfig, String> {
+    let cwd = std::env::current_dir()
+        .map_err(|e| format!("Failed to read the current directory: {e}"))?;
+    lookup_from(&cwd, path)
+}
+
+/// [`lookup`] with relative paths resolved against `cwd`
+fn lookup_from(cwd: &Path, path: &Path) -> Result<EditorConfig, String> {
+    let path = &cwd.join(path);
+    let mut found = Vec::new();
+    for dir in path.ancestors().skip(1) {
+        let config_path = dir.join(FILE_NAME);
+        if !config_path.is_file() {
+            continue;
+        }
+        let text = std::fs::read_to_string(&config_path)
+            .map_err(|e| format!("Failed to read {}: {e}", config_path.display()))?;
+        let config = parse(&text);
+        let root = config.root;
+        found.push((dir, config_path, config));
+        if root {
+            break;
+        }
+    }
+
+    let mut result = EditorConfig::default();
+    let mut values: BTreeMap<String, String> = BTreeMap::new();
+    for (dir, config_path, config) in found.into_iter().rev() {
+        let Ok(relative) = path.strip_prefix(dir) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let mut matched = false;
+        for section in &config.sections {
+            if section
+                .glob
+                .as_ref()
+                .is_some_and(|glob| glob.is_match(&relative))
+            {
+                matched = true;
+                for (key, value) in &section.properties {
+                    values.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        if matched {
+            result.files.insert(0, config_path);
+        }
+    }
+    for (key, value) in &values {
+        result.properties.set(key, value);
+    }
+    Ok(result)
+}
+
+/// Status bar tooltip: the `.editorconfig` files of `config` and the
+/// `effective` settings
+pub fn describe(config: &EditorConfig, effective: &Properties) -> String {
+    let mut lines: Vec<String> = config
+        .files
+        .iter()
+        .map(|file| file.display().to_string())
+        .collect();
+    let flag = |value: Option<bool>| match value {
+        Some(true) => "yes",
+        Some(false) => "no",
+        None => "unchanged",
+    };
+    lines.push(format!(
+        "Trim trailing whitespace: {}",
+        flag(effective.trim_trailing_whitespace)
+    ));
+    lines.push(format!(
+        "Final newline: {}",
+        flag(effective.insert_final_newline)
+    ));
+    lines.join("\n")
+}
+
+/// Per-language defaults persisted in `editor.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EditorSettings {
+    /// For every language
+    pub default: Properties,
+    /// By syntax name, over `default`
+    pub languages: BTreeMap<String, Properties>,
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        let indent = |style, size| Properties {
+            indent_style: Some(style),
+            indent_size: size,
+            ..Properties::default()
+        };
+        let languages = [
+            ("Go", indent(IndentStyle::Tab, None)),
+            ("Makefile", indent(IndentStyle::Tab, None)),
+            ("Python", indent(IndentStyle::Space, Some(4))),
+            ("Rust", indent(IndentStyle::Space, Some(4))),
+            ("YAML", indent(IndentStyle::Space, Some(2))),
+        ];
+        Self {
+            default: Properties::default(),
+            languages: languages
+                .into_iter()
+                .map(|(name, properties)| (name.to_string(), properties))
+                .collect(),
+        }
+    }
+}
+
+impl EditorSettings {
+    /// Defaults for the syntax called `language`
+    pub fn for_language(&self, language: &str) -> Properties {
+        self.languages.get(language).map_or_else(
+            || self.default.clone(),
+            |properties| properties.or(&self.default),
+        )
+    }
+
+    /// Load settings from a JSON file
+    pub fn load_from(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        serde_json::from_str(&json).map_err(|e| format!("Failed to parse editor settings: {e}"))
+    }
+
+    /// Load the user's settings, or the defaults
+    pub fn load() -> Self {
+        match user_config_dir("editor.json") {
+            Some(path) if path.exists() => Self::load_from(&path).unwrap_or_else(|err| {
+                eprintln!("{err}");
+                Self::default()
+            }),
+            _ => Self::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(glob: &str, path: &str) -> bool {
+        glob_regex(glob).is_some_and(|regex| regex.is_match(path))
+    }
+
+    #[test]
+    fn test_globs() {
+        assert!(matches("*", "a/b/file.rs"));
+        assert!(matches("*.md", "docs/readme.md"));
+        assert!(!matches("*.md", "readme.mdx"));
+        assert!(matches("*.{js,ts}", "src/app.ts"));
+        assert!(matches("{package.json,.travis.yml}", ".travis.yml"));
+        assert!(matches("lib/**.js", "lib/a/b.js"));
+        assert!(!matches("lib/*.js", "lib/a/b.js"));
+        assert!(matches("/Makefile", "Makefile"));
+        assert!(!matches("/Makefile", "sub/Makefile"));
+        assert!(matches("file[0-9].txt", "file3.txt"));
+        assert!(!matches("file[!0-9].txt", "file3.txt"));
+        assert!(matches("v{1..12}.txt", "v10.txt"));
+        assert!(!matches("v{1..12}.txt", "v13.txt"));
+        assert!(matches("a?c", "abc"));
+        assert!(matches("{a", "{a"));
+    }
+
+    #[test]
+    fn test_lookup_merges_up_to_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let sub = dir.path().join("project/src");
+        std::fs::create_dir_all(&sub).unwrap();
+        std::fs::write(
+            dir.path().join(FILE_NAME),
+            "[*]\nindent_style = tab\ncharset = latin1\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("project").join(FILE_NAME),
+            "root = true\n\n[*]\nindent_style = space\nindent_size = 2\nend_of_line = CRLF\n\n\
+             # Later sections win\n[src/*.py]\nindent_size = 4\n\n[*.md]\ntrim_trailing_whitespace = false\n",
+        )
+        .unwrap();
+
+        let config = lookup(&sub.join("main.py")).unwrap();
+        assert_eq!(
+            config.files,
+            vec![dir.path().join("project").join(FILE_NAME)]
+        );
+        let properties = &config.properties;
+        assert_eq!(properties.indent_style, Some(IndentStyle::Space));
+        assert_eq!(properties.indent_size, Some(4));
+        assert_eq!(properties.end_of_line, Some(EndOfLine::Crlf));
+        // The outer file is past the root
+        assert_eq!(properties.charset, None);
+        assert_eq!(properties.trim_trailing_whitespace, None);
+
+        let other = tempfile::tempdir().unwrap();
+        let none = lookup(&other.path().join("a.txt")).unwrap();
+        assert!(none.files.is_empty());
+    }
+
+    #[test]
+    fn test_lookup_relative_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(
+            dir.path().join(FILE_NAME),
+            "root = true\n\n[*.rs]\nindent_size = 4\n",
+        )
+        .unwrap();
+
+        // From the project root and from inside `src`
+        for (cwd, path) in [(dir.path(), "src/main.rs"), (src.as_path(), "main.rs")] {
+            let config = lookup_from(cwd, Path::new(path)).unwrap();
+            assert_eq!(config.files, vec![dir.path().join(FILE_NAME)], "{path}");
+            assert_eq!(config.properties.indent_size, Some(4));
+        }
+    }
+
+    #[test]
+    fn test_fallback_and_indentation() {
+        let settings = EditorSettings::default();
+        let editorconfig = Properties {
+            indent_size: Some(8),
+            ..Properties::default()
+        };
+        let python = editorconfig.or(&settings.for_language("Python"));
+        assert_eq!(
+            python.indentation(Indentation::Tabs),
+            Indentation::Spaces(8)
+        );
+        let go = settings.for_language("Go");
+        assert_eq!(go.indentation(Indentation::Spaces(2)), Indentation::Tabs);
+        let unknown = settings.for_language("Plain Text");
+        assert_eq!(
+            unknown.indentation(Indentation::Spaces(3)),
+            Indentation::Spaces(3)
+        );
+        assert_eq!(unknown.line_break("CRLF"), "\r\n");
+    }
+
+    #[test]
+    fn test_normalize() {
+        let properties = Properties {
+            end_of_line: Some(EndOfLine::Lf),
+            trim_trailing_whitespace: Some(true),
+            insert_final_newline: Some(true),
+            ..Properties::default()
+        };
+        assert_eq!(properties.normalize("a  \r\nb\t\rc "), "a\nb\nc\n");
+        assert_eq!(properties.normalize(""), "");
+        let keep = Properties {
+            insert_final_newline: Some(false),
+            ..Properties::default()
+        };
+        assert_eq!(keep.normalize("a \r\nb\r\n\r\n"), "a \r\nb");
+        assert_eq!(Properties::default().normalize("x \r\n"), "x \r\n");
+        let crlf = Properties {
+            insert_final_newline: Some(true),
+            ..Properties::default()
+        };
+        assert_eq!(crlf.normalize("a\r\nb"), "a\r\nb\r\n");
+    }
+
+    #[test]
+    fn test_charsets_round_trip() {
+        let text = "héllo";
+        for charset in [
+            Charset::Latin1,
+            Charset::Utf8,
+            Charset::Utf16Be,
+            Charset::Utf16Le,
+        ] {
+            let properties = Properties {
+                charset: Some(charset),
+                ..Properties::default()
+            };
+            let bytes = properties.encode(text).unwrap();
+            assert_eq!(decode(bytes, Some(charset)).unwrap(), text, "{charset}");
+        }
+        let bom = Properties {
+            charset: Some(Charset::Utf8Bom),
+            ..Properties::default()
+        };
+        assert_eq!(bom.encode("a").unwrap(), b"\xef\xbb\xbfa");
+        let latin1 = Properties {
+            charset: Some(Charset::Latin1),
+            ..Properties::default()
+        };
+        assert!(latin1.encode("日本").is_err());
+    }
+}
@@ -0,0 +1,546 @@
+//! Code folding: fold regions per language and the folded state of a file
+//!
+//! Regions are computed from the text alone: the heading hierarchy and code
+//! fences for Markdown, sectioning commands and environments for LaTeX, and
+//! indentation blocks and bracket pairs for everything else. A folded region
+//! keeps its first line visible; the editor hides the rest by laying those
+//! lines out with no height ([`hide_lines`]).
+//!
+//! Fold state is a set of folded start lines, moved along with edits by the
+//! [`TextChange`]s a [`Buffer`](crate::core_engine::Buffer) reports.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+use std::path::PathBuf;
+
+use eframe::egui::{text::LayoutJob, Color32};
+
+use crate::core_engine::{BufferId, TextChange};
+
+/// Whose fold state: files keep theirs for the session, even when closed
+/// and opened again
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FoldOwner {
+    File(PathBuf),
+    Untitled(BufferId),
+}
+
+/// Folding commands from the menu and shortcuts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldCommand {
+    FoldAll,
+    UnfoldAll,
+    FoldToLevel(usize),
+    FoldAtCursor,
+    UnfoldAtCursor,
+}
+
+/// Lines `start..=end` can be folded to just `start`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoldRegion {
+    pub start: usize,
+    pub end: usize,
+    /// Nesting depth, 1 for outermost regions
+    pub level: usize,
+}
+
+/// Fold regions of `text` in the syntax called `language`, ordered by start
+pub fn regions(text: &str, language: &str) -> Vec<FoldRegion> {
+    let lines: Vec<&str> = text.lines().collect();
+    let spans = match language {
+        "Markdown" | "MultiMarkdown" => markdown_spans(&lines),
+        "LaTeX" | "TeX" => latex_spans(&lines),
+        _ => {
+            let mut spans = indent_spans(&lines);
+            spans.extend(bracket_spans(&lines));
+            spans
+        }
+    };
+    with_levels(spans)
+}
+
+/// Keep the longest span per start line, sort and assign nesting levels
+fn with_levels(spans: Vec<(usize, usize)>) -> Vec<FoldRegion> {
+    let mut longest: BTreeMap<usize, usize> = BTreeMap::new();
+    for (start, end) in spans.into_iter().filter(|(start, end)| end > start) {
+        let entry = longest.entry(start).or_insert(end);
+        *entry = (*entry).max(end);
+    }
+
+    let mut regions = Vec::with_capacity(longest.len());
+    let mut open: Vec<usize> = Vec::new();
+    for (start, end) in longest {
+        while open.last().is_some_and(|&outer| outer < start) {
+            open.pop();
+        }
+        regions.push(FoldRegion {
+            start,
+            end,
+            level: open.len() + 1,
+        });
+        open.push(end);
+    }
+    regions
+}
+
+/// Last non-blank line in `start..=end`, at least `start`
+fn trim_blank(lines: &[&str], start: usize, end: usize) -> usize {
+    (start..=end)
+        .rev()
+        .find(|&line| !lines[line].trim().is_empty())
+        .unwrap_or(start)
+}
+
+/// Headings up to the next heading of the same or a higher level, and
+/// fenced code blocks
+fn markdown_spans(lines: &[&str]) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut headings: Vec<(usize, usize)> = Vec::new(); // (level, line)
+    let mut fence: Option<(usize, &str)> = None;
+
+    let mut close_headings = |headings: &mut Vec<(usize, usize)>, level: usize, line: usize| {
+        while headings.last().is_some_and(|&(open, _)| open >= level) {
+            let (_, start) = headings.pop().unwrap();
+            if line > start + 1 {
+                spans.push((start, trim_blank(lines, start, line - 1)));
+            }
+        }
+    };
+    let mut fences = Vec::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if let Some((start, marker)) = fence {
+            if trimmed.starts_with(marker) {
+                fences.push((start, index));
+                fence = None;
+            }
+            continue;
+        }
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            fence = Some((index, marker));
+            continue;
+        }
+        let level = trimmed.chars().take_while(|&c| c == '#').count();
+        if (1..=6).contains(&level)
+            && trimmed[level..]
+                .chars()
+                .next()
+                .is_none_or(char::is_whitespace)
+        {
+            close_headings(&mut headings, level, index);
+            headings.push((level, index));
+        }
+    }
+    close_headings(&mut headings, 1, lines.len());
+    spans.extend(fences);
+    spans
+}
+
+/// Nesting level of a LaTeX sectioning command
+fn section_level(command: &str) -> Option<usize> {
+    let level = match command {
+        "part" => 0,
+        "chapter" => 1,
+        "section" => 2,
+        "subsection" => 3,
+        "subsubsection" => 4,
+        "paragraph" => 5,
+        "subparagraph" => 6,
+        _ => return None,
+    };
+    Some(level)
+}
+
+/// `line` without its `%` comment
+fn strip_tex_comment(line: &str) -> &str {
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '%' if !escaped => return &line[..index],
+            '\\' => escaped = !escaped,
+            _ => escaped = false,
+        }
+    }
+    line
+}
+
+/// Sections up to the next section of the same or a higher level, and
+/// `\begin{..}`/`\end{..}` environments
+fn latex_spans(lines: &[&str]) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut sections: Vec<(usize, usize)> = Vec::new(); // (level, line)
+    let mut environments: Vec<(&str, usize)> = Vec::new();
+
+    let close_sections =
+        |sections: &mut Vec<(usize, usize)>, spans: &mut Vec<_>, level: usize, line: usize| {
+            while sections.last().is_some_and(|&(open, _)| open >= level) {
+                let (_, start) = sections.pop().unwrap();
+                if line > start + 1 {
+                    spans.push((start, trim_blank(lines, start, line - 1)));
+                }
+            }
+        };
+
+    for (index, line) in lines.iter().enumerate() {
+        let code = strip_tex_comment(line);
+        let trimmed = code.trim_start();
+        if let Some(command) = trimmed.strip_prefix('\\') {
+            let name: String = command
+                .chars()
+                .take_while(char::is_ascii_alphabetic)
+                .collect();
+            if let Some(level) = section_level(&name) {
+                close_sections(&mut sections, &mut spans, level, index);
+                sections.push((level, index));
+            }
+        }
+
+        let mut rest = code;
+        while let Some(at) = rest.find('\\') {
+            rest = &rest[at + 1..];
+            let (is_begin, name) = if let Some(name) = rest.strip_prefix("begin{") {
+                (true, name)
+            } else if let Some(name) = rest.strip_prefix("end{") {
+                (false, name)
+            } else {
+                continue;
+            };
+            let Some(close) = name.find('}') else {
+                continue;
+            };
+            let name = &name[..close];
+            if is_begin {
+                environments.push((name, index));
+            } else if let Some(open) = environments.iter().rposition(|(env, _)| *env == name) {
+                let (_, start) = environments[open];
+                environments.truncate(open);
+                if name == "document" {
+                    // Sections end with the document
+                    close_sections(&mut sections, &mut spans, 0, index);
+                }
+                let at_line_start = trimmed.starts_with("\\end{");
+                spans.push((start, if at_line_start { index - 1 } else { index }));
+            }
+        }
+    }
+    close_sections(&mut sections, &mut spans, 0, lines.len());
+    spans
+}
+
+/// Indentation width of a line, tabs counting as four columns
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// Lines followed by more indented ones, up to the last of those
+fn indent_spans(lines: &[&str]) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut open: Vec<(usize, usize)> = Vec::new(); // (indent, line)
+    let mut last = 0;
+    for (index, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let indent = indent_width(line);
+        while open.last().is_some_and(|&(width, _)| width >= indent) {
+            let (_, start) = open.pop().unwrap();
+            spans.push((start, last));
+        }
+        open.push((indent, index));
+        last = index;
+    }
+    spans.extend(open.into_iter().map(|(_, start)| (start, last)));
+    spans
+}
+
+/// Bracket pairs spanning lines; a closing bracket that starts its line
+/// stays visible. Brackets in string literals and `//` comments are skipped.
+fn bracket_spans(lines: &[&str]) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut open: Vec<(char, usize)> = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let mut quote = None;
+        let mut escaped = false;
+        let mut chars = line.chars().peekable();
+        let first = line.trim_start().chars().next();
+        while let Some(c) = chars.next() {
+            if let Some(q) = quote {
+                match c {
+                    '\\' => escaped = !escaped,
+                    c if c == q && !escaped => quote = None,
+                    _ => escaped = false,
+                }
+                continue;
+            }
+            match c {
+                '"' => quote = Some('"'),
+                '/' if chars.peek() == Some(&'/') => break,
+                '{' | '[' | '(' => open.push((c, index)),
+                '}' | ']' | ')' => {
+                    let expected = match c {
+                        '}' => '{',
+                        ']' => '[',
+                        _ => '(',
+                    };
+                    if let Some(at) = open.iter().rposition(|(o, _)| *o == expected) {
+                        let (_, start) = open[at];
+                        open.truncate(at);
+                        if index > start {
+                            let end = if first == Some(c) { index - 1 } else { index };
+                            spans.push((start, end));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    spans
+}
+
+/// Folded start lines of one file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FoldState {
+    folded: BTreeSet<usize>,
+}
+
+impl FoldState {
+    pub fn is_folded(&self, line: usize) -> bool {
+        self.folded.contains(&line)
+    }
+
+    pub fn toggle(&mut self, line: usize) {
+        if !self.folded.remove(&line) {
+            self.folded.insert(line);
+        }
+    }
+
+    pub fn fold_all(&mut self, regions: &[FoldRegion]) {
+        self.folded
+            .extend(regions.iter().map(|region| region.start));
+    }
+
+    pub fn unfold_all(&mut self) {
+        self.folded.clear();
+    }
+
+    /// Fold every region at `level` or deeper, unfolding the outer ones
+    pub fn fold_to_level(&mut self, regions: &[FoldRegion], level: usize) {
+        self.folded = regions
+            .iter()
+            .filter(|region| region.level >= level)
+            .map(|region| region.start)
+            .collect();
+    }
+
+    /// Fold the innermost region containing `line`; `false` if there is none
+    pub fn fold_at(&mut self, regions: &[FoldRegion], line: usize) -> bool {
+        let innermost = regions
+            .iter()
+            .filter(|region| (region.start..=region.end).contains(&line))
+            .filter(|region| !self.is_folded(region.start))
+            .max_by_key(|region| region.level);
+        innermost.is_some_and(|region| self.folded.insert(region.start))
+    }
+
+    /// Unfold every region containing `line`; `true` if anything changed
+    pub fn unfold_at(&mut self, regions: &[FoldRegion], line: usize) -> bool {
+        let before = self.folded.len();
+        for region in regions {
+            if (region.start..=region.end).contains(&line) {
+                self.folded.remove(&region.start);
+            }
+        }
+        self.folded.len() != before
+    }
+
+    /// Whether `line` is hidden inside a folded region
+    pub fn is_hidden(&self, regions: &[FoldRegion], line: usize) -> bool {
+        regions
+            .iter()
+            .any(|region| self.is_folded(region.start) && line > region.start && line <= region.end)
+    }
+
+    /// Move folded lines along with an edit
+    pub fn apply(&mut self, change: &TextChange) {
+        self.folded = self
+            .folded
+            .iter()
+            .filter_map(|&line| change.map_line(line))
+            .collect();
+    }
+
+    /// Forget folded lines that no longer start a region
+    pub fn retain_regions(&mut self, regions: &[FoldRegion]) {
+        let starts: BTreeSet<usize> = regions.iter().map(|region| region.start).collect();
+        self.folded.retain(|line| starts.contains(line));
+    }
+
+    /// Hidden line ranges, merged and sorted
+    pub fn hidden_lines(&self, regions: &[FoldRegion]) -> Vec<Range<usize>> {
+        let mut hidden: Vec<Range<usize>> = Vec::new();
+        for region in regions.iter().filter(|r| self.is_folded(r.start)) {
+            let range = region.start + 1..region.end + 1;
+            match hidden.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => hidden.push(range),
+            }
+        }
+        hidden
+    }
+
+    /// Start lines of the regions and whether each is folded, for the gutter
+    pub fn markers(&self, regions: &[FoldRegion]) -> BTreeMap<usize, bool> {
+        regions
+            .iter()
+            .map(|region| (region.start, self.is_folded(region.start)))
+            .collect()
+    }
+}
+
+/// Lay out the `hidden` line ranges of `text` with no height and no color
+///
+/// A hidden range takes the newline ending the line before it, so empty
+/// hidden lines collapse too, while the newline ending it stays visible.
+pub fn hide_lines(job: &mut LayoutJob, text: &str, hidden: &[Range<usize>]) {
+    if hidden.is_empty() {
+        return;
+    }
+    let mut line_starts = vec![0];
+    line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+    let bytes: Vec<Range<usize>> = hidden
+        .iter()
+        .filter(|lines| lines.start > 0 && lines.start < line_starts.len())
+        .map(|lines| {
+            let start = line_starts[lines.start] - 1;
+            let end = line_starts
+                .get(lines.end)
+                .map_or(text.len(), |&next| next - 1);
+            start..end
+        })
+        .collect();
+
+    let mut sections = Vec::with_capacity(job.sections.len() + bytes.len() * 2);
+    let mut ranges = bytes.iter().peekable();
+    for section in job.sections.drain(..) {
+        let mut start = section.byte_range.start;
+        let end = section.byte_range.end;
+        while start < end {
+            while ranges.next_if(|range| range.end <= start).is_some() {}
+            let mut piece = section.clone();
+            let stop = match ranges.peek() {
+                Some(range) if range.start <= start => {
+                    piece.format.line_height = Some(0.0);
+                    piece.format.color = Color32::TRANSPARENT;
+                    piece.format.background = Color32::TRANSPARENT;
+                    range.end.min(end)
+                }
+                Some(range) if range.start < end => range.start,
+                _ => end,
+            };
+            if start != section.byte_range.start {
+                piece.leading_space = 0.0;
+            }
+            piece.byte_range = start..stop;
+            sections.push(piece);
+            start = stop;
+        }
+    }
+    job.sections = sections;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(text: &str, language: &str) -> Vec<(usize, usize, usize)> {
+        regions(text, language)
+            .into_iter()
+            .map(|r| (r.start, r.end, r.level))
+            .collect()
+    }
+
+    #[test]
+    fn test_markdown_headings() {
+        let text = "# A\ntext\n\n## B\nmore\n```\n# not a heading\n```\n\n# C\nend\n";
+        assert_eq!(
+            spans(text, "Markdown"),
+            vec![(0, 7, 1), (3, 7, 2), (5, 7, 3), (9, 10, 1)]
+        );
+    }
+
+    #[test]
+    fn test_latex_sections_and_environments() {
+        let text = "\\begin{document}\n\\section{A}\nText % \\section{not}\n\\begin{itemize}\n\\item x\n\\end{itemize}\n\\subsection{B}\nMore\n\\section{C}\nEnd\n\\end{document}\n";
+        assert_eq!(
+            spans(text, "LaTeX"),
+            vec![(0, 9, 1), (1, 7, 2), (3, 4, 3), (6, 7, 3), (8, 9, 2)]
+        );
+    }
+
+    #[test]
+    fn test_code_indentation_and_brackets() {
+        let text =
+            "fn main() {\n    let v = [\n        1,\n    ];\n    if x {\n        y();\n    }\n}\n";
+        assert_eq!(spans(text, "Rust"), vec![(0, 6, 1), (1, 2, 2), (4, 5, 2)]);
+        let python = "def f():\n    if x:\n        pass\n\n    return 1\nprint()\n";
+        assert_eq!(spans(python, "Python"), vec![(0, 4, 1), (1, 2, 2)]);
+    }
+
+    #[test]
+    fn test_fold_commands() {
+        let regions = regions("# A\n## B\nx\n## C\ny\n# D\nz\n", "Markdown");
+        let mut state = FoldState::default();
+        state.fold_to_level(&regions, 2);
+        assert_eq!(state.hidden_lines(&regions), vec![2..3, 4..5]);
+        state.fold_all(&regions);
+        assert_eq!(state.hidden_lines(&regions), vec![1..5, 6..7]);
+        assert!(state.is_hidden(&regions, 2));
+        assert!(state.unfold_at(&regions, 2));
+        assert!(!state.is_hidden(&regions, 2));
+        state.unfold_all();
+        assert!(state.fold_at(&regions, 4));
+        assert_eq!(state.hidden_lines(&regions), vec![4..5]);
+    }
+
+    #[test]
+    fn test_folds_follow_edits() {
+        let mut state = FoldState::default();
+        state.toggle(3);
+        state.toggle(8);
+        // Two lines inserted above, then the line of the second fold removed
+        state.apply(&TextChange {
+            line: 0,
+            removed: 0,
+            inserted: 2,
+        });
+        assert!(state.is_folded(5));
+        state.apply(&TextChange {
+            line: 10,
+            removed: 1,
+            inserted: 0,
+        });
+        assert!(state.is_folded(5));
+        assert!(!state.is_folded(10));
+    }
+
+    #[test]
+    fn test_hide_lines() {
+        let text = "a\nb\n\nc\nd";
+        let mut job = LayoutJob::single_section(text.to_string(), Default::default());
+        hide_lines(&mut job, text, std::slice::from_ref(&(1..4)));
+        let ranges: Vec<_> = job
+            .sections
+            .iter()
+            .map(|s| (s.byte_range.clone(), s.format.line_height))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![(0..1, None), (1..6, Some(0.0)), (6..text.len(), None)]
+        );
+    }
+}
@@ -37,6 +37,11 @@ impl GrepPanel {
         self.results.push(result);
     }
 
+    /// Results of the last search
+    pub fn results(&self) -> &[GrepResult] {
+        &self.results
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn show(
         &mut self,
@@ -0,0 +1,290 @@
+//! Editor gutter: line numbers, fold arrows and diagnostic / VCS markers
+//!
+//! The gutter is painted beside the main editor's `TextEdit` once it has
+//! been laid out, so its rows follow the galley, wrapped lines included.
+//! Only the rows inside the scroll area's clip rect are drawn.
+
+use std::collections::BTreeMap;
+
+use eframe::egui::{self, Color32, Pos2, Rect, Stroke};
+
+use super::diagnostics_view::severity_color;
+use super::vcs::LineChange;
+use crate::mermaid::{Diagnostic, Severity};
+
+/// Width of the VCS change bar
+const CHANGE_WIDTH: f32 = 3.0;
+/// Column for diagnostic dots
+const MARKER_WIDTH: f32 = 12.0;
+/// Column for fold arrows
+const FOLD_WIDTH: f32 = 14.0;
+/// Space between the gutter and the text
+const PADDING: f32 = 6.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineNumbers {
+    #[default]
+    Absolute,
+    /// Distance from the cursor line; the cursor line shows its number
+    Relative,
+}
+
+/// Number shown for 0-based `line` with the cursor on `cursor_line`
+pub fn line_label(line: usize, cursor_line: usize, mode: LineNumbers) -> String {
+    match mode {
+        LineNumbers::Relative if line != cursor_line => line.abs_diff(cursor_line).to_string(),
+        _ => (line + 1).to_string(),
+    }
+}
+
+/// Per-line markers to draw, all keyed by 0-based line
+#[derive(Default)]
+pub struct Marks<'a> {
+    pub diagnostics: &'a [Diagnostic],
+    pub changes: Option<&'a BTreeMap<usize, LineChange>>,
+    /// Lines starting a fold region, `true` when folded
+    pub folds: Option<&'a BTreeMap<usize, bool>>,
+}
+
+/// Clicks in the gutter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GutterAction {
+    /// A line number was clicked: select the line
+    SelectLine(usize),
+    ToggleFold(usize),
+}
+
+/// Marker color of a VCS change
+pub fn change_color(change: LineChange) -> Color32 {
+    match change {
+        LineChange::Added => Color32::from_rgb(80, 170, 80),
+        LineChange::Modified => Color32::from_rgb(70, 130, 210),
+        LineChange::Removed => Color32::from_rgb(210, 70, 70),
+    }
+}
+
+/// Width of the gutter for a text of `line_count` lines
+pub fn width(ui: &egui::Ui, line_count: usize) -> f32 {
+    let digits = line_count.max(999).to_string().len() as f32;
+    let char_width = ui
+        .fonts_mut(|fonts| fonts.glyph_width(&egui::TextStyle::Monospace.resolve(ui.style()), '0'));
+    CHANGE_WIDTH + MARKER_WIDTH + digits * char_width + FOLD_WIDTH + PADDING
+}
+
+/// First galley row of each logical line, with the row's screen rect
+fn line_rows(output: &egui::text_edit::TextEditOutput) -> Vec<(usize, Rect)> {
+    let offset = output.galley_pos.to_vec2();
+    let mut rows = Vec::new();
+    let mut line = 0;
+    let mut line_start = true;
+    for row in &output.galley.rows {
+        if line_start {
+            rows.push((line, row.rect().translate(offset)));
+        }
+        line_start = row.row.ends_with_newline;
+        if line_start {
+            line += 1;
+        }
+    }
+    rows
+}
+
+/// Fill the placeholder shape `shape` behind the cursor line
+pub fn paint_current_line(
+    ui: &egui::Ui,
+    shape: egui::layers::ShapeIdx,
+    output: &egui::text_edit::TextEditOutput,
+    gutter: Rect,
+    cursor_line: usize,
+    color: Color32,
+) {
+    let rows = line_rows(output);
+    let Some(index) = rows.iter().position(|(line, _)| *line == cursor_line) else {
+        return;
+    };
+    // Wrapped lines: cover every row up to the next line
+    let top = rows[index].1.top();
+    let bottom = rows.get(index + 1).map_or(
+        output.galley_pos.y + output.galley.rect.bottom(),
+        |(_, next)| next.top(),
+    );
+    let highlight =
+        Rect::from_x_y_ranges(gutter.left()..=output.response.rect.right(), top..=bottom);
+    ui.painter()
+        .set(shape, egui::Shape::rect_filled(highlight, 0.0, color));
+}
+
+/// Mark folded lines with `⋯` after their text
+pub fn paint_fold_placeholders(
+    ui: &egui::Ui,
+    output: &egui::text_edit::TextEditOutput,
+    folded: impl Iterator<Item = usize>,
+    color: Color32,
+) {
+    let rows = line_rows(output);
+    let painter = ui.painter_at(output.text_clip_rect);
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    for line in folded {
+        let Some((_, rect)) = rows.iter().find(|(row_line, _)| *row_line == line) else {
+            continue;
+        };
+        let text = painter.layout_no_wrap(" ⋯ ".to_string(), font.clone(), color);
+        let pos = Pos2::new(rect.right() + 8.0, rect.center().y - text.size().y / 2.0);
+        let frame = Rect::from_min_size(pos, text.size());
+        painter.rect_filled(frame, 3.0, color.gamma_multiply(0.15));
+        painter.galley(pos, text, color);
+    }
+}
+
+/// Draw the gutter in `gutter`, beside the editor whose `output` is given
+pub fn show(
+    ui: &mut egui::Ui,
+    gutter: Rect,
+    output: &egui::text_edit::TextEditOutput,
+    cursor_line: usize,
+    mode: LineNumbers,
+    marks: &Marks,
+    text_color: Color32,
+) -> Option<GutterAction> {
+    let gutter = Rect::from_x_y_ranges(gutter.x_range(), output.response.rect.y_range());
+    let response = ui.interact(gutter, ui.id().with("gutter"), egui::Sense::click());
+    let painter = ui.painter_at(gutter.intersect(ui.clip_rect()));
+    let clip = ui.clip_rect();
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    let weak = text_color.gamma_multiply(0.45);
+
+    let numbers_right = gutter.right() - FOLD_WIDTH - PADDING;
+    let marker_x = gutter.left() + CHANGE_WIDTH + MARKER_WIDTH / 2.0;
+    let fold_x = gutter.right() - PADDING - FOLD_WIDTH / 2.0;
+
+    let mut diagnostics: BTreeMap<usize, Vec<&Diagnostic>> = BTreeMap::new();
+    for diagnostic in marks.diagnostics {
+        diagnostics
+            .entry(diagnostic.line.saturating_sub(1))
+            .or_default()
+            .push(diagnostic);
+    }
+
+    let mut action = None;
+    let pointer = response.interact_pointer_pos();
+    let hover = response.hover_pos();
+    for (line, rect) in line_rows(output) {
+        // Lines hidden by folding are laid out with no height
+        if rect.height() <= 0.0 || rect.bottom() < clip.top() || rect.top() > clip.bottom() {
+            continue;
+        }
+        let center_y = rect.center().y;
+
+        if let Some(change) = marks.changes.and_then(|changes| changes.get(&line)) {
+            let bar =
+                Rect::from_x_y_ranges(gutter.left()..=gutter.left() + CHANGE_WIDTH, rect.y_range());
+            match change {
+                LineChange::Added | LineChange::Modified => {
+                    painter.rect_filled(bar, 0.0, change_color(*change));
+                }
+                LineChange::Removed => {
+                    let top = rect.top();
+                    painter.add(egui::Shape::convex_polygon(
+                        vec![
+                            Pos2::new(gutter.left(), top - 4.0),
+                            Pos2::new(gutter.left() + 6.0, top),
+                            Pos2::new(gutter.left(), top + 4.0),
+                        ],
+                        change_color(*change),
+                        Stroke::NONE,
+                    ));
+                }
+            }
+        }
+
+        if let Some(problems) = diagnostics.get(&line) {
+            let severity = if problems.iter().any(|d| d.severity == Severity::Error) {
+                Severity::Error
+            } else {
+                Severity::Warning
+            };
+            let center = Pos2::new(marker_x, center_y);
+            painter.circle_filled(center, 3.5, severity_color(severity));
+            let area = Rect::from_center_size(center, egui::vec2(MARKER_WIDTH, rect.height()));
+            if hover.is_some_and(|pos| area.contains(pos)) {
+                egui::Tooltip::always_open(
+                    ui.ctx().clone(),
+                    ui.layer_id(),
+                    ui.id().with(("gutter_diagnostics", line)),
+                    egui::PopupAnchor::Pointer,
+                )
+                .show(|ui| {
+                    for problem in problems {
+                        ui.colored_label(severity_color(problem.severity), &problem.message);
+                    }
+                });
+            }
+        }
+
+        let color = if line == cursor_line {
+            text_color
+        } else {
+            weak
+        };
+        painter.text(
+            Pos2::new(numbers_right, center_y),
+            egui::Align2::RIGHT_CENTER,
+            line_label(line, cursor_line, mode),
+            font.clone(),
+            color,
+        );
+
+        if let Some(&folded) = marks.folds.and_then(|folds| folds.get(&line)) {
+            // Open regions only show their arrow while the gutter is hovered
+            if folded || hover.is_some() {
+                painter.add(fold_arrow(Pos2::new(fold_x, center_y), folded, weak));
+            }
+        }
+
+        if let Some(pos) = pointer.filter(|_| response.clicked()) {
+            if rect.y_range().contains(pos.y) {
+                let on_arrow = pos.x >= fold_x - FOLD_WIDTH / 2.0
+                    && marks.folds.is_some_and(|folds| folds.contains_key(&line));
+                action = Some(if on_arrow {
+                    GutterAction::ToggleFold(line)
+                } else {
+                    GutterAction::SelectLine(line)
+                });
+            }
+        }
+    }
+    action
+}
+
+/// `▶` when folded, `▼` when open
+fn fold_arrow(center: Pos2, folded: bool, color: Color32) -> egui::Shape {
+    let s = 4.0;
+    let points = if folded {
+        vec![
+            center + egui::vec2(-s / 2.0, -s),
+            center + egui::vec2(s, 0.0),
+            center + egui::vec2(-s / 2.0, s),
+        ]
+    } else {
+        vec![
+            center + egui::vec2(-s, -s / 2.0),
+            center + egui::vec2(s, -s / 2.0),
+            center + egui::vec2(0.0, s),
+        ]
+    };
+    egui::Shape::convex_polygon(points, color, Stroke::NONE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_labels() {
+        assert_eq!(line_label(0, 5, LineNumbers::Absolute), "1");
+        assert_eq!(line_label(5, 5, LineNumbers::Absolute), "6");
+        assert_eq!(line_label(2, 5, LineNumbers::Relative), "3");
+        assert_eq!(line_label(8, 5, LineNumbers::Relative), "3");
+        assert_eq!(line_label(5, 5, LineNumbers::Relative), "6");
+    }
+}
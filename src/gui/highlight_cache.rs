@@ -0,0 +1,762 @@
+//! Incremental syntax highlighting for the editor
+//!
+//! Highlighting a line needs the parser state left by the line before it,
+//! so for every line the [`Engine`] keeps the state it was highlighted from.
+//! After an edit only the lines from the first changed one are highlighted
+//! again, and only until the state at the start of an unchanged line
+//! matches the one kept for it: from there on nothing can differ.
+//!
+//! Work is ordered by what is on screen. In small files lines up to the end
+//! of the viewport are highlighted in the frame, within a time budget, and
+//! the rest over the following frames. Big files are highlighted on a
+//! background thread that owns the parser states (they cannot leave it) and
+//! sends back colors; visible lines it has not reached yet are colored from
+//! a guessed (initial) state until the real colors arrive.
+
+use egui::text::{LayoutJob, LayoutSection};
+use egui::{Color32, FontId, TextFormat};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use syntect::highlighting::{HighlightState, Highlighter, RangedHighlightIterator, Theme};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet};
+
+use super::highlighting::SyntaxHighlighter;
+
+/// Files with more lines than this are highlighted on a background thread
+pub const BACKGROUND_LINES: usize = 5_000;
+
+/// Time spent per frame on lines up to the end of the viewport
+const VIEWPORT_BUDGET: Duration = Duration::from_millis(20);
+
+/// Time spent per frame on the lines after the viewport
+const FRAME_BUDGET: Duration = Duration::from_millis(6);
+
+/// How long a frame waits for the background thread to color an edit
+const EDIT_WAIT: Duration = Duration::from_millis(8);
+
+/// The background thread reports after this many lines or this long
+const REPORT_LINES: usize = 512;
+const REPORT_INTERVAL: Duration = Duration::from_millis(4);
+
+/// Parser and highlighter state at the start of a line
+type LineState = (ParseState, HighlightState);
+
+/// Colored byte range within a line
+type Span = (Range<usize>, Color32);
+
+/// What highlighting a line needs
+struct Grammar<'a> {
+    syntax_set: &'a SyntaxSet,
+    syntax: &'a SyntaxReference,
+    highlighter: Highlighter<'a>,
+}
+
+impl<'a> Grammar<'a> {
+    fn new(syntax_set: &'a SyntaxSet, syntax: &'a SyntaxReference, theme: &'a Theme) -> Self {
+        Grammar {
+            syntax_set,
+            syntax,
+            highlighter: Highlighter::new(theme),
+        }
+    }
+
+    fn initial_state(&self) -> LineState {
+        (
+            ParseState::new(self.syntax),
+            HighlightState::new(&self.highlighter, ScopeStack::new()),
+        )
+    }
+
+    /// Spans of `line`, advancing `state` past it
+    fn highlight_line(&self, state: &mut LineState, line: &str) -> Vec<Span> {
+        let (parse, highlight) = state;
+        // A grammar error leaves the line in the default color
+        let Ok(ops) = parse.parse_line(line, self.syntax_set) else {
+            return Vec::new();
+        };
+        let mut spans: Vec<Span> = Vec::new();
+        for (style, _, range) in
+            RangedHighlightIterator::new(highlight, &ops, line, &self.highlighter)
+        {
+            let color = SyntaxHighlighter::style_to_color(style);
+            match spans.last_mut() {
+                Some((last, last_color)) if *last_color == color && last.end == range.start => {
+                    last.end = range.end;
+                }
+                _ => spans.push((range, color)),
+            }
+        }
+        spans
+    }
+}
+
+/// Lines replaced by an edit: `removed` lines at `at` became `inserted`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Edit {
+    at: usize,
+    removed: usize,
+    inserted: usize,
+}
+
+/// An [`Edit`] with the new lines, for the background thread
+struct Splice {
+    at: usize,
+    removed: usize,
+    inserted: Vec<String>,
+}
+
+/// Diff of two line lists by common prefix and suffix; `None` if equal
+fn diff(old: &[u64], new: &[u64]) -> Option<Edit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    if prefix == old.len() && prefix == new.len() {
+        return None;
+    }
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take(old.len().min(new.len()) - prefix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    Some(Edit {
+        at: prefix,
+        removed: old.len() - prefix - suffix,
+        inserted: new.len() - prefix - suffix,
+    })
+}
+
+/// Parser states of a buffer's lines, and how far they are known
+struct Engine {
+    lines: Vec<EngineLine>,
+    /// Lines before this one are valid
+    frontier: usize,
+    /// State at the start of the frontier line, `None` for the initial one
+    resume: Option<LineState>,
+}
+
+struct EngineLine {
+    /// State the line was last highlighted from
+    start: Option<LineState>,
+    /// Highlighted from the real end state of the previous line
+    valid: bool,
+}
+
+/// What [`Engine::step`] did
+enum Step {
+    /// Highlighted this line
+    Line(usize, Vec<Span>),
+    /// States converged; these lines keep their colors
+    Skipped,
+}
+
+impl Engine {
+    fn new(lines: usize) -> Self {
+        Engine {
+            lines: (0..lines).map(|_| EngineLine::unknown()).collect(),
+            frontier: 0,
+            resume: None,
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.frontier >= self.lines.len()
+    }
+
+    /// Replace lines; unchanged lines after the edit keep their states
+    fn splice(&mut self, at: usize, removed: usize, inserted: usize) {
+        if at < self.frontier {
+            // The first changed line starts where the old one at its
+            // position did, since everything before it is the same
+            self.resume = self.lines[at].start.clone();
+            self.frontier = at;
+        }
+        self.lines.splice(
+            at..at + removed,
+            (0..inserted).map(|_| EngineLine::unknown()),
+        );
+    }
+
+    /// Highlight the frontier line, or skip the run of valid lines that
+    /// starts there when its state has converged
+    fn step(&mut self, grammar: &Grammar, text_lines: &[impl AsRef<str>]) -> Step {
+        let index = self.frontier;
+        let mut state = self
+            .resume
+            .take()
+            .unwrap_or_else(|| grammar.initial_state());
+
+        let line = &self.lines[index];
+        if line.valid && line.start.as_ref() == Some(&state) {
+            let run_end = (index + 1..self.lines.len())
+                .find(|&next| !self.lines[next].valid)
+                .unwrap_or(self.lines.len());
+            // The state after the run is that after its last line
+            let last = run_end - 1;
+            let mut end = self.lines[last]
+                .start
+                .clone()
+                .unwrap_or_else(|| grammar.initial_state());
+            grammar.highlight_line(&mut end, text_lines[last].as_ref());
+            self.frontier = run_end;
+            self.resume = Some(end);
+            return Step::Skipped;
+        }
+
+        let start = state.clone();
+        let spans = grammar.highlight_line(&mut state, text_lines[index].as_ref());
+        self.lines[index] = EngineLine {
+            start: Some(start),
+            valid: true,
+        };
+        self.frontier += 1;
+        self.resume = Some(state);
+        Step::Line(index, spans)
+    }
+}
+
+impl EngineLine {
+    fn unknown() -> Self {
+        EngineLine {
+            start: None,
+            valid: false,
+        }
+    }
+}
+
+/// Where the parser states live
+enum Backend {
+    /// In this thread, for small files
+    Local(Engine),
+    Worker(Worker),
+}
+
+/// Background thread with its own [`Engine`] and copy of the text
+struct Worker {
+    edits: Sender<Splice>,
+    reports: Receiver<Report>,
+    /// Number of edits sent
+    generation: u64,
+    /// Generation and first line of edits the thread may not have seen
+    unseen: VecDeque<(u64, usize)>,
+    /// Frontier of the thread after the latest edit
+    frontier: usize,
+}
+
+/// Colors computed by the background thread after `generation` edits
+struct Report {
+    generation: u64,
+    lines: Vec<(usize, Vec<Span>)>,
+    frontier: usize,
+}
+
+impl Worker {
+    fn spawn(
+        syntax_set: Arc<SyntaxSet>,
+        syntax_name: String,
+        theme: Theme,
+        text_lines: Vec<String>,
+    ) -> Option<Worker> {
+        let (edits, edit_receiver) = mpsc::channel::<Splice>();
+        let (report_sender, reports) = mpsc::channel();
+
+        std::thread::Builder::new()
+            .name("highlight".to_string())
+            .spawn(move || {
+                let Some(syntax) = syntax_set.find_syntax_by_name(&syntax_name) else {
+                    return;
+                };
+                let grammar = Grammar::new(&syntax_set, syntax, &theme);
+                let mut text = text_lines;
+                let mut engine = Engine::new(text.len());
+                let mut generation = 0;
+
+                loop {
+                    // Apply edits first; wait for one when there is nothing to do
+                    let edit = if engine.is_complete() {
+                        edit_receiver.recv().ok()
+                    } else {
+                        match edit_receiver.try_recv() {
+                            Ok(edit) => Some(edit),
+                            Err(TryRecvError::Empty) => None,
+                            Err(TryRecvError::Disconnected) => return,
+                        }
+                    };
+                    if let Some(edit) = edit {
+                        engine.splice(edit.at, edit.removed, edit.inserted.len());
+                        text.splice(edit.at..edit.at + edit.removed, edit.inserted);
+                        generation += 1;
+                        continue;
+                    } else if engine.is_complete() {
+                        // The editor closed the buffer
+                        return;
+                    }
+
+                    let started = Instant::now();
+                    let mut lines = Vec::new();
+                    while !engine.is_complete()
+                        && lines.len() < REPORT_LINES
+                        && started.elapsed() < REPORT_INTERVAL
+                    {
+                        if let Step::Line(index, spans) = engine.step(&grammar, &text) {
+                            lines.push((index, spans));
+                        }
+                    }
+                    let report = Report {
+                        generation,
+                        lines,
+                        frontier: engine.frontier,
+                    };
+                    if report_sender.send(report).is_err() {
+                        return;
+                    }
+                }
+            })
+            .ok()?;
+
+        Some(Worker {
+            edits,
+            reports,
+            generation: 0,
+            unseen: VecDeque::new(),
+            frontier: 0,
+        })
+    }
+
+    fn send(&mut self, edit: Splice) {
+        self.generation += 1;
+        self.unseen.push_back((self.generation, edit.at));
+        // A closed channel shows as no progress; the colors just stay
+        let _ = self.edits.send(edit);
+    }
+}
+
+/// Highlighted lines of one buffer
+///
+/// Call [`HighlightCache::update`] with the current text before building
+/// the layout with [`HighlightCache::layout_job`].
+#[derive(Default)]
+pub struct HighlightCache {
+    /// Syntax and theme names the cache was built with
+    key: Option<(String, Option<String>)>,
+    hashes: Vec<u64>,
+    /// Colors of each line, `None` until it has been highlighted at all
+    spans: Vec<Option<Vec<Span>>>,
+    backend: Option<Backend>,
+}
+
+impl HighlightCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bring the cache up to date with `text`, visible lines first
+    ///
+    /// `visible` is the range of lines on screen. Returns `true` while lines
+    /// are still waiting to be highlighted; the caller should repaint soon
+    /// and call this again.
+    pub fn update(
+        &mut self,
+        highlighter: &SyntaxHighlighter,
+        text: &str,
+        syntax: &SyntaxReference,
+        theme: &Theme,
+        visible: Range<usize>,
+    ) -> bool {
+        let key = (syntax.name.clone(), theme.name.clone());
+        if self.key.as_ref() != Some(&key) {
+            *self = HighlightCache {
+                key: Some(key),
+                ..Default::default()
+            };
+        }
+
+        let text_lines: Vec<&str> = text.split_inclusive('\n').collect();
+        let hashes: Vec<u64> = text_lines.iter().map(|line| hash_line(line)).collect();
+        let edit = diff(&self.hashes, &hashes);
+        self.hashes = hashes;
+        if let Some(edit) = &edit {
+            self.spans.splice(
+                edit.at..edit.at + edit.removed,
+                (0..edit.inserted).map(|_| None),
+            );
+        }
+
+        let big = text_lines.len() > BACKGROUND_LINES;
+        let backend = match self.backend.take() {
+            // A small file that grew big moves to the background
+            Some(Backend::Local(engine)) if big && !engine.is_complete() => None,
+            backend => backend,
+        };
+        let backend = match backend {
+            Some(Backend::Local(mut engine)) => {
+                if let Some(edit) = &edit {
+                    engine.splice(edit.at, edit.removed, edit.inserted);
+                }
+                self.run_local(
+                    &mut engine,
+                    highlighter,
+                    syntax,
+                    theme,
+                    &text_lines,
+                    &visible,
+                );
+                Backend::Local(engine)
+            }
+            Some(Backend::Worker(mut worker)) => {
+                if let Some(edit) = &edit {
+                    worker.send(Splice {
+                        at: edit.at,
+                        removed: edit.removed,
+                        inserted: text_lines[edit.at..edit.at + edit.inserted]
+                            .iter()
+                            .map(|line| line.to_string())
+                            .collect(),
+                    });
+                }
+                let wait = edit.map(|edit| (Instant::now() + EDIT_WAIT, edit.at));
+                self.poll(&mut worker, wait);
+                Backend::Worker(worker)
+            }
+            None if big => {
+                let lines = text_lines.iter().map(|line| line.to_string()).collect();
+                let syntax_set = Arc::clone(highlighter.syntax_set());
+                match Worker::spawn(syntax_set, syntax.name.clone(), theme.clone(), lines) {
+                    Some(worker) => Backend::Worker(worker),
+                    None => Backend::Local(Engine::new(text_lines.len())),
+                }
+            }
+            None => {
+                let mut engine = Engine::new(text_lines.len());
+                self.run_local(
+                    &mut engine,
+                    highlighter,
+                    syntax,
+                    theme,
+                    &text_lines,
+                    &visible,
+                );
+                Backend::Local(engine)
+            }
+        };
+
+        let grammar = Grammar::new(highlighter.syntax_set(), syntax, theme);
+        self.guess(&grammar, &text_lines, visible);
+
+        let complete = match &backend {
+            Backend::Local(engine) => engine.is_complete(),
+            Backend::Worker(worker) => worker.frontier >= text_lines.len(),
+        };
+        self.backend = Some(backend);
+        !complete
+    }
+
+    /// Whether every line has been highlighted from its real state
+    pub fn is_complete(&self) -> bool {
+        match &self.backend {
+            Some(Backend::Local(engine)) => engine.is_complete(),
+            Some(Backend::Worker(worker)) => worker.frontier >= self.hashes.len(),
+            None => self.hashes.is_empty(),
+        }
+    }
+
+    /// Layout of `text` with the cached colors; lines not highlighted yet
+    /// are drawn in `default_color`
+    pub fn layout_job(&self, text: &str, font: FontId, default_color: Color32) -> LayoutJob {
+        let mut job = LayoutJob {
+            text: text.to_owned(),
+            ..Default::default()
+        };
+        let push = |job: &mut LayoutJob, byte_range: Range<usize>, color: Color32| {
+            if byte_range.is_empty() {
+                return;
+            }
+            // Neighbouring spans across lines share a section
+            if let Some(last) = job.sections.last_mut() {
+                if last.format.color == color && last.byte_range.end == byte_range.start {
+                    last.byte_range.end = byte_range.end;
+                    return;
+                }
+            }
+            job.sections.push(LayoutSection {
+                leading_space: 0.0,
+                byte_range,
+                format: TextFormat::simple(font.clone(), color),
+            });
+        };
+
+        let mut offset = 0;
+        for (index, line) in text.split_inclusive('\n').enumerate() {
+            let mut covered = 0;
+            let spans = self
+                .spans
+                .get(index)
+                .and_then(Option::as_deref)
+                .unwrap_or_default();
+            for (range, color) in spans {
+                let range = range.start.max(covered)..range.end.min(line.len());
+                if range.start >= range.end {
+                    continue;
+                }
+                push(
+                    &mut job,
+                    offset + covered..offset + range.start,
+                    default_color,
+                );
+                push(&mut job, offset + range.start..offset + range.end, *color);
+                covered = range.end;
+            }
+            push(
+                &mut job,
+                offset + covered..offset + line.len(),
+                default_color,
+            );
+            offset += line.len();
+        }
+        job
+    }
+
+    /// Highlight in this thread: the viewport, then the rest within budget
+    fn run_local(
+        &mut self,
+        engine: &mut Engine,
+        highlighter: &SyntaxHighlighter,
+        syntax: &SyntaxReference,
+        theme: &Theme,
+        text_lines: &[&str],
+        visible: &Range<usize>,
+    ) {
+        let grammar = Grammar::new(highlighter.syntax_set(), syntax, theme);
+        let started = Instant::now();
+        while !engine.is_complete() {
+            let budget = if engine.frontier < visible.end {
+                VIEWPORT_BUDGET
+            } else {
+                FRAME_BUDGET
+            };
+            if started.elapsed() > budget {
+                break;
+            }
+            if let Step::Line(index, spans) = engine.step(&grammar, text_lines) {
+                self.spans[index] = Some(spans);
+            }
+        }
+    }
+
+    /// Take the colors the background thread has sent; right after an edit
+    /// (`wait`), give it a moment to reach the edited line
+    fn poll(&mut self, worker: &mut Worker, wait: Option<(Instant, usize)>) {
+        loop {
+            let report = match wait {
+                Some((deadline, at)) if worker.frontier <= at || !worker.unseen.is_empty() => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    match worker.reports.recv_timeout(timeout) {
+                        Ok(report) => report,
+                        Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                _ => match worker.reports.try_recv() {
+                    Ok(report) => report,
+                    Err(_) => return,
+                },
+            };
+
+            // Line numbers in the report are those after its generation's
+            // edits; later edits only leave the lines before them in place
+            worker
+                .unseen
+                .retain(|(generation, _)| *generation > report.generation);
+            let unchanged = worker
+                .unseen
+                .iter()
+                .map(|(_, at)| *at)
+                .min()
+                .unwrap_or(usize::MAX);
+            for (index, spans) in report.lines {
+                if index < unchanged && index < self.spans.len() {
+                    self.spans[index] = Some(spans);
+                }
+            }
+            worker.frontier = if report.generation == worker.generation {
+                report.frontier
+            } else {
+                report.frontier.min(unchanged)
+            };
+        }
+    }
+
+    /// Highlight visible lines that have no colors at all from the initial
+    /// state, so the screen is colored right away
+    fn guess(&mut self, grammar: &Grammar, text_lines: &[&str], visible: Range<usize>) {
+        let end = visible.end.min(text_lines.len());
+        let Some(from) = (visible.start..end).find(|&index| self.spans[index].is_none()) else {
+            return;
+        };
+        let mut state = grammar.initial_state();
+        for (line, spans) in text_lines[from..end].iter().zip(&mut self.spans[from..end]) {
+            let guessed = grammar.highlight_line(&mut state, line);
+            spans.get_or_insert(guessed);
+        }
+    }
+}
+
+fn hash_line(line: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    line.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::highlighting::FALLBACK_THEME;
+
+    fn colors(cache: &HighlightCache, text: &str) -> Vec<(String, Color32)> {
+        let job = cache.layout_job(text, FontId::monospace(14.0), Color32::RED);
+        job.sections
+            .iter()
+            .map(|section| {
+                (
+                    text[section.byte_range.clone()].to_string(),
+                    section.format.color,
+                )
+            })
+            .collect()
+    }
+
+    fn full(highlighter: &SyntaxHighlighter, text: &str) -> Vec<(String, Color32)> {
+        let mut cache = HighlightCache::new();
+        let syntax = highlighter.find_language("Rust").unwrap();
+        let theme = highlighter.theme(FALLBACK_THEME);
+        while cache.update(highlighter, text, syntax, theme, 0..usize::MAX) {}
+        colors(&cache, text)
+    }
+
+    #[test]
+    fn test_matches_full_highlighting() {
+        let highlighter = SyntaxHighlighter::with_dirs(None, None);
+        let syntax = highlighter.find_language("Rust").unwrap();
+        let theme = highlighter.theme(FALLBACK_THEME);
+        let text = "fn main() {\n    let s = \"hi\";\n}\n";
+
+        let mut cache = HighlightCache::new();
+        assert!(!cache.update(&highlighter, text, syntax, theme, 0..10));
+        assert!(cache.is_complete());
+
+        let job = cache.layout_job(text, FontId::monospace(14.0), Color32::RED);
+        let reference = highlighter.highlight_with(text, syntax, theme, FontId::monospace(14.0));
+        assert_eq!(job.text, reference.text);
+        // Same color at every byte
+        let color_at = |job: &LayoutJob, byte: usize| {
+            job.sections
+                .iter()
+                .find(|section| section.byte_range.contains(&byte))
+                .map(|section| section.format.color)
+        };
+        for byte in 0..text.len() {
+            assert_eq!(
+                color_at(&job, byte),
+                color_at(&reference, byte),
+                "byte {byte}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_edit_rehighlights_until_states_converge() {
+        let highlighter = SyntaxHighlighter::with_dirs(None, None);
+        let syntax = highlighter.find_language("Rust").unwrap();
+        let theme = highlighter.theme(FALLBACK_THEME);
+        let grammar = Grammar::new(highlighter.syntax_set(), syntax, theme);
+        let lines: Vec<String> = (0..200).map(|i| format!("let x{i} = {i};\n")).collect();
+
+        let mut engine = Engine::new(lines.len());
+        while !engine.is_complete() {
+            engine.step(&grammar, &lines);
+        }
+
+        // Editing one line inside code converges on the next line
+        let mut edited = lines.clone();
+        edited[10] = "let x10 = 1000;\n".to_string();
+        engine.splice(10, 1, 1);
+        assert_eq!(engine.frontier, 10);
+        assert!(matches!(engine.step(&grammar, &edited), Step::Line(10, _)));
+        assert!(matches!(engine.step(&grammar, &edited), Step::Skipped));
+        assert!(engine.is_complete());
+
+        // Through the cache, colors match highlighting from scratch
+        let text = lines.concat();
+        let mut cache = HighlightCache::new();
+        while cache.update(&highlighter, &text, syntax, theme, 0..50) {}
+
+        // Opening a block comment changes every following line
+        let commented = text.replacen("let x5 = 5;", "/* let x5 = 5;", 1);
+        while cache.update(&highlighter, &commented, syntax, theme, 0..50) {}
+        assert_eq!(colors(&cache, &commented), full(&highlighter, &commented));
+
+        // Deleting lines shifts the cached ones after them
+        let deleted: String = lines[..20].concat() + &lines[40..].concat();
+        while cache.update(&highlighter, &deleted, syntax, theme, 0..50) {}
+        assert_eq!(colors(&cache, &deleted), full(&highlighter, &deleted));
+    }
+
+    #[test]
+    fn test_big_file_in_background() {
+        let highlighter = SyntaxHighlighter::with_dirs(None, None);
+        let syntax = highlighter.find_language("Rust").unwrap();
+        let theme = highlighter.theme(FALLBACK_THEME);
+        let line = "let a = 1;\n";
+        let text = "/* comment\n".to_string() + &line.repeat(BACKGROUND_LINES * 2);
+
+        let wait_until_complete =
+            |cache: &mut HighlightCache, text: &str, visible: Range<usize>| {
+                let deadline = Instant::now() + Duration::from_secs(120);
+                while cache.update(&highlighter, text, syntax, theme, visible.clone()) {
+                    assert!(Instant::now() < deadline, "background highlighting stalled");
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+            };
+        let color_at = |cache: &HighlightCache, text: &str, byte: usize| {
+            let job = cache.layout_job(text, FontId::monospace(14.0), Color32::RED);
+            job.sections
+                .iter()
+                .find(|section| section.byte_range.contains(&byte))
+                .map(|section| section.format.color)
+        };
+        let comment_color = {
+            let reference = highlighter.highlight_with(
+                &format!("/* comment\n{line}"),
+                syntax,
+                theme,
+                FontId::monospace(14.0),
+            );
+            reference.sections.last().unwrap().format.color
+        };
+
+        let mut cache = HighlightCache::new();
+        let far = BACKGROUND_LINES..BACKGROUND_LINES + 40;
+        cache.update(&highlighter, &text, syntax, theme, far.clone());
+        // The viewport far down is colored (from a guess) right away
+        assert!(cache.spans[BACKGROUND_LINES].is_some());
+
+        wait_until_complete(&mut cache, &text, far.clone());
+        // The unterminated comment reaches the last line
+        let last = text.len() - line.len();
+        assert_eq!(color_at(&cache, &text, last), Some(comment_color));
+
+        // Closing the comment recolors everything after it
+        let closed = text.replacen("/* comment\n", "/* comment */\n", 1);
+        wait_until_complete(&mut cache, &closed, far);
+        let last = closed.len() - line.len();
+        assert_ne!(color_at(&cache, &closed, last), Some(comment_color));
+        assert_eq!(
+            colors(&cache, &closed)[..8],
+            full(&highlighter, &closed)[..8]
+        );
+    }
+}
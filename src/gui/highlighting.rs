@@ -1,88 +1,186 @@
+//! Syntax highlighting for the editor
+//!
+//! Uses syntect's default syntaxes (the ones Sublime Text ships) plus any
+//! `.sublime-syntax` files in the user's `lala/syntaxes` config directory.
+//! The language of a buffer is taken from a modeline if there is one
+//! (`vim: ft=python`, `-*- mode: ruby -*-`), then from the file name, then
+//! from the first line (`#!/usr/bin/env python3`, `<?xml`).
+//!
+//! Colors come from syntect's default themes and the user's `.tmTheme` files
+//! in `lala/themes`; see [`super::theme`] for how they pair with UI colors.
+
 use egui::{text::LayoutJob, Color32, FontId, TextFormat};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use syntect::easy::HighlightLines;
 use syntect::highlighting::{Style, Theme, ThemeSet};
-use syntect::parsing::{SyntaxSet, SyntaxSetBuilder};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
 use syntect::util::LinesWithEndings;
 
+/// Lines at the start and end of a file searched for a modeline, as in vim
+const MODELINE_LINES: usize = 5;
+
+/// Syntax theme used when a theme name is unknown
+pub const FALLBACK_THEME: &str = "base16-ocean.dark";
+
 /// SyntaxHighlighter manages syntax highlighting for different file types
 pub struct SyntaxHighlighter {
-    syntax_set: SyntaxSet,
-    theme: Theme,
+    syntax_set: Arc<SyntaxSet>,
+    themes: ThemeSet,
 }
 
 impl SyntaxHighlighter {
-    /// Creates a new SyntaxHighlighter with default settings
+    /// Creates a highlighter with the default syntaxes and themes and the
+    /// user's own
     pub fn new() -> Self {
-        // Load default syntax definitions
-        let mut builder = SyntaxSetBuilder::new();
-
-        // Add Rust syntax
-        builder.add_plain_text_syntax();
-
-        let syntax_set = builder.build();
-
-        // Use a bundled theme (base16-ocean.dark)
-        let theme = Self::load_theme();
-
-        Self { syntax_set, theme }
-    }
-
-    /// Loads the theme for syntax highlighting
-    fn load_theme() -> Theme {
-        // Load from bundled themes
-        let ts = ThemeSet::load_defaults();
-        ts.themes["base16-ocean.dark"].clone()
-    }
-
-    /// Gets the syntax definition for a file based on its extension
-    pub fn load_syntax(&self, file_path: &str) -> &syntect::parsing::SyntaxReference {
-        let extension = std::path::Path::new(file_path)
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("");
-
-        match extension {
-            "rs" => self
-                .syntax_set
-                .find_syntax_by_extension("rs")
-                .or_else(|| self.syntax_set.find_syntax_by_name("Rust"))
-                .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text()),
-            "md" => self
-                .syntax_set
-                .find_syntax_by_extension("md")
-                .or_else(|| self.syntax_set.find_syntax_by_name("Markdown"))
-                .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text()),
-            _ => self.syntax_set.find_syntax_plain_text(),
+        Self::with_dirs(
+            user_config_dir("syntaxes").as_deref(),
+            user_config_dir("themes").as_deref(),
+        )
+    }
+
+    /// Creates a highlighter with the defaults, the `.sublime-syntax` files
+    /// found in `syntax_dir` and the `.tmTheme` files in `theme_dir`
+    pub fn with_dirs(syntax_dir: Option<&Path>, theme_dir: Option<&Path>) -> Self {
+        let defaults = SyntaxSet::load_defaults_newlines();
+        let syntax_set = match syntax_dir.filter(|dir| dir.is_dir()) {
+            Some(dir) => {
+                let mut builder = defaults.clone().into_builder();
+                match builder.add_from_folder(dir, true) {
+                    Ok(()) => builder.build(),
+                    Err(err) => {
+                        eprintln!("Failed to load syntaxes from {}: {err}", dir.display());
+                        defaults
+                    }
+                }
+            }
+            None => defaults,
+        };
+
+        let mut themes = ThemeSet::load_defaults();
+        if let Some(dir) = theme_dir.filter(|dir| dir.is_dir()) {
+            if let Err(err) = themes.add_from_folder(dir) {
+                eprintln!("Failed to load themes from {}: {err}", dir.display());
+            }
+        }
+
+        Self {
+            syntax_set: Arc::new(syntax_set),
+            themes,
         }
     }
 
+    /// Grammars, shared with background highlighting
+    pub(crate) fn syntax_set(&self) -> &Arc<SyntaxSet> {
+        &self.syntax_set
+    }
+
+    /// Names of all known languages, sorted, for the language picker
+    pub fn language_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .syntax_set
+            .syntaxes()
+            .iter()
+            .filter(|syntax| !syntax.hidden)
+            .map(|syntax| syntax.name.as_str())
+            .collect();
+        names.sort_by_key(|name| name.to_lowercase());
+        names.dedup();
+        names
+    }
+
+    /// Language by name, as chosen in the language picker
+    pub fn find_language(&self, name: &str) -> Option<&SyntaxReference> {
+        self.syntax_set.find_syntax_by_name(name)
+    }
+
+    /// Gets the syntax definition for a file based on its name
+    pub fn load_syntax(&self, file_path: &str) -> &SyntaxReference {
+        self.by_file_name(Path::new(file_path))
+            .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text())
+    }
+
+    /// Language of a buffer: modeline, then file name, then first line
+    pub fn detect(&self, file_path: Option<&Path>, text: &str) -> &SyntaxReference {
+        modeline_language(text)
+            .and_then(|token| self.syntax_set.find_syntax_by_token(&token))
+            .or_else(|| file_path.and_then(|path| self.by_file_name(path)))
+            .or_else(|| {
+                let first_line = text.lines().next()?;
+                self.syntax_set.find_syntax_by_first_line(first_line)
+            })
+            .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text())
+    }
+
+    /// Syntax by extension, or by whole file name for `Makefile` and co.
+    fn by_file_name(&self, path: &Path) -> Option<&SyntaxReference> {
+        let extension = path.extension().and_then(|ext| ext.to_str());
+        let file_name = path.file_name().and_then(|name| name.to_str());
+        extension.into_iter().chain(file_name).find_map(|key| {
+            self.syntax_set.find_syntax_by_extension(key).or_else(|| {
+                self.syntax_set
+                    .find_syntax_by_extension(&key.to_lowercase())
+            })
+        })
+    }
+
+    /// Names of the syntax themes, bundled and user ones, sorted
+    pub fn theme_names(&self) -> Vec<&str> {
+        // BTreeMap keys are sorted already
+        self.themes.themes.keys().map(String::as_str).collect()
+    }
+
+    /// Syntax theme by name, or the fallback theme
+    pub fn theme(&self, name: &str) -> &Theme {
+        self.themes
+            .themes
+            .get(name)
+            .unwrap_or_else(|| &self.themes.themes[FALLBACK_THEME])
+    }
+
     /// Highlights the given text and returns an egui LayoutJob
     pub fn highlight(&self, text: &str, file_path: Option<&str>) -> LayoutJob {
-        let syntax = if let Some(path) = file_path {
-            self.load_syntax(path)
-        } else {
-            self.syntax_set.find_syntax_plain_text()
-        };
+        let syntax = self.detect(file_path.map(Path::new), text);
+        self.highlight_with(
+            text,
+            syntax,
+            self.theme(FALLBACK_THEME),
+            FontId::monospace(14.0),
+        )
+    }
 
+    /// Highlights `text` as `syntax` in `font`
+    pub fn highlight_with(
+        &self,
+        text: &str,
+        syntax: &SyntaxReference,
+        theme: &Theme,
+        font: FontId,
+    ) -> LayoutJob {
         let mut layout_job = LayoutJob::default();
-        let mut highlighter = HighlightLines::new(syntax, &self.theme);
+        let mut highlighter = HighlightLines::new(syntax, theme);
 
         for line in LinesWithEndings::from(text) {
-            let ranges = highlighter
-                .highlight_line(line, &self.syntax_set)
-                .unwrap_or_default();
-
-            for (style, text_segment) in ranges {
-                let color = Self::style_to_color(style);
-                layout_job.append(
-                    text_segment,
+            match highlighter.highlight_line(line, &self.syntax_set) {
+                Ok(ranges) => {
+                    for (style, text_segment) in ranges {
+                        layout_job.append(
+                            text_segment,
+                            0.0,
+                            TextFormat {
+                                font_id: font.clone(),
+                                color: Self::style_to_color(style),
+                                ..Default::default()
+                            },
+                        );
+                    }
+                }
+                // Keep the text even if the grammar fails on it
+                Err(_) => layout_job.append(
+                    line,
                     0.0,
-                    TextFormat {
-                        font_id: FontId::monospace(14.0),
-                        color,
-                        ..Default::default()
-                    },
-                );
+                    TextFormat::simple(font.clone(), Self::default_color(theme)),
+                ),
             }
         }
 
@@ -90,14 +188,26 @@ impl SyntaxHighlighter {
     }
 
     /// Converts syntect Style to egui Color32
-    fn style_to_color(style: Style) -> Color32 {
+    pub(crate) fn style_to_color(style: Style) -> Color32 {
         Color32::from_rgb(style.foreground.r, style.foreground.g, style.foreground.b)
     }
 
-    /// Returns the list of supported extensions
-    #[allow(dead_code)]
-    pub fn supported_extensions() -> Vec<&'static str> {
-        vec!["rs", "md", "txt"]
+    /// Foreground color of `theme` for text no rule applies to
+    pub(crate) fn default_color(theme: &Theme) -> Color32 {
+        theme
+            .settings
+            .foreground
+            .map(|c| Color32::from_rgb(c.r, c.g, c.b))
+            .unwrap_or(Color32::GRAY)
+    }
+
+    /// File extensions of all known languages
+    pub fn supported_extensions(&self) -> Vec<&str> {
+        self.syntax_set
+            .syntaxes()
+            .iter()
+            .flat_map(|syntax| syntax.file_extensions.iter().map(String::as_str))
+            .collect()
     }
 }
 
@@ -107,35 +217,144 @@ impl Default for SyntaxHighlighter {
     }
 }
 
+/// `<config_dir>/lala/<name>`, where user syntaxes and themes live
+pub fn user_config_dir(name: &str) -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("lala").join(name))
+}
+
+/// Language named by a vim or emacs modeline in the first or last lines
+///
+/// Recognizes `vim: set ft=python:`, `vi: filetype=sh`, `-*- mode: ruby -*-`
+/// and `-*- perl -*-`.
+fn modeline_language(text: &str) -> Option<String> {
+    let head = text.lines().take(MODELINE_LINES);
+    let tail = text.lines().rev().take(MODELINE_LINES);
+    head.chain(tail)
+        .find_map(|line| vim_modeline(line).or_else(|| emacs_modeline(line)))
+}
+
+fn vim_modeline(line: &str) -> Option<String> {
+    let start = ["vim:", "vi:", "ex:"]
+        .iter()
+        .filter_map(|marker| Some(line.find(marker)? + marker.len()))
+        .min()?;
+    line[start..]
+        .split(|c: char| c == ':' || c.is_whitespace())
+        .find_map(|option| {
+            option
+                .strip_prefix("ft=")
+                .or_else(|| option.strip_prefix("filetype="))
+                .or_else(|| option.strip_prefix("syntax="))
+                .or_else(|| option.strip_prefix("syn="))
+        })
+        .filter(|language| !language.is_empty())
+        .map(str::to_string)
+}
+
+fn emacs_modeline(line: &str) -> Option<String> {
+    let start = line.find("-*-")? + 3;
+    let end = start + line[start..].find("-*-")?;
+    let body = line[start..end].trim();
+    if !body.contains(':') {
+        return Some(body.to_string()).filter(|language| !language.is_empty());
+    }
+    body.split(';').find_map(|pair| {
+        let (key, value) = pair.split_once(':')?;
+        (key.trim().eq_ignore_ascii_case("mode")).then(|| value.trim().to_string())
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn highlighter() -> SyntaxHighlighter {
+        SyntaxHighlighter::with_dirs(None, None)
+    }
+
     #[test]
     fn test_load_syntax_rust() {
-        let highlighter = SyntaxHighlighter::new();
-        let syntax = highlighter.load_syntax("foo.rs");
-        // Check that we get a syntax (even if it's plain text as fallback)
-        assert!(syntax.name == "Plain Text" || syntax.name.contains("Rust"));
+        let syntax = highlighter().load_syntax("foo.rs").name.clone();
+        assert_eq!(syntax, "Rust");
     }
 
     #[test]
     fn test_load_syntax_markdown() {
-        let highlighter = SyntaxHighlighter::new();
-        let syntax = highlighter.load_syntax("foo.md");
-        assert!(syntax.name == "Plain Text" || syntax.name.contains("Markdown"));
+        let syntax = highlighter().load_syntax("foo.md").name.clone();
+        assert_eq!(syntax, "Markdown");
     }
 
     #[test]
     fn test_load_syntax_unknown() {
-        let highlighter = SyntaxHighlighter::new();
+        let highlighter = highlighter();
         let syntax = highlighter.load_syntax("foo.xyz");
         assert_eq!(syntax.name, "Plain Text");
     }
 
+    #[test]
+    fn test_load_syntax_by_file_name() {
+        let highlighter = highlighter();
+        assert_eq!(highlighter.load_syntax("Makefile").name, "Makefile");
+        assert_eq!(highlighter.load_syntax("src/main.PY").name, "Python");
+    }
+
+    #[test]
+    fn test_detect_shebang_and_modeline() {
+        let highlighter = highlighter();
+        let detect = |path: Option<&str>, text: &str| {
+            highlighter.detect(path.map(Path::new), text).name.clone()
+        };
+        assert_eq!(
+            detect(Some("run"), "#!/usr/bin/env python3\nprint(1)\n"),
+            "Python"
+        );
+        assert_eq!(
+            detect(None, "#!/bin/bash\necho hi\n"),
+            "Bourne Again Shell (bash)"
+        );
+        // A modeline wins over the extension
+        assert_eq!(
+            detect(Some("notes.txt"), "x = 1\n# vim: set ft=ruby:\n"),
+            "Ruby"
+        );
+        assert_eq!(detect(None, "/* -*- mode: c++ -*- */\n"), "C++");
+        assert_eq!(detect(None, "hello\n"), "Plain Text");
+    }
+
+    #[test]
+    fn test_modelines() {
+        assert_eq!(
+            vim_modeline("# vim: set ft=python ts=4:"),
+            Some("python".into())
+        );
+        assert_eq!(vim_modeline("// vi: filetype=sh"), Some("sh".into()));
+        assert_eq!(vim_modeline("no modeline here"), None);
+        assert_eq!(emacs_modeline(";; -*- lisp -*-"), Some("lisp".into()));
+        assert_eq!(
+            emacs_modeline("# -*- coding: utf-8; mode: perl -*-"),
+            Some("perl".into())
+        );
+        assert_eq!(emacs_modeline("# -*- coding: utf-8 -*-"), None);
+    }
+
+    #[test]
+    fn test_user_syntaxes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("lala.sublime-syntax"),
+            "%YAML 1.2\n---\nname: Lala Test\nfile_extensions: [lalatest]\nscope: source.lalatest\ncontexts:\n  main:\n    - match: '\\bkw\\b'\n      scope: keyword.control\n",
+        )
+        .unwrap();
+        let highlighter = SyntaxHighlighter::with_dirs(Some(dir.path()), None);
+        assert_eq!(highlighter.load_syntax("a.lalatest").name, "Lala Test");
+        assert!(highlighter.language_names().contains(&"Lala Test"));
+        // Defaults are still there
+        assert_eq!(highlighter.load_syntax("a.rs").name, "Rust");
+    }
+
     #[test]
     fn test_highlight_rust_code() {
-        let highlighter = SyntaxHighlighter::new();
+        let highlighter = highlighter();
         let code = "let a = 1;";
         let layout_job = highlighter.highlight(code, Some("test.rs"));
 
@@ -144,11 +363,13 @@ mod tests {
         assert!(layout_job.text.contains("a"));
         assert!(layout_job.text.contains("="));
         assert!(layout_job.text.contains("1"));
+        // Keywords and numbers get different colors
+        assert!(layout_job.sections.len() > 1);
     }
 
     #[test]
     fn test_highlight_markdown() {
-        let highlighter = SyntaxHighlighter::new();
+        let highlighter = highlighter();
         let markdown = "# Header\n\n**bold**";
         let layout_job = highlighter.highlight(markdown, Some("test.md"));
 
@@ -158,7 +379,7 @@ mod tests {
 
     #[test]
     fn test_highlight_plaintext() {
-        let highlighter = SyntaxHighlighter::new();
+        let highlighter = highlighter();
         let text = "Hello, World!";
         let layout_job = highlighter.highlight(text, Some("test.txt"));
 
@@ -167,9 +388,11 @@ mod tests {
 
     #[test]
     fn test_supported_extensions() {
-        let extensions = SyntaxHighlighter::supported_extensions();
+        let highlighter = highlighter();
+        let extensions = highlighter.supported_extensions();
         assert!(extensions.contains(&"rs"));
         assert!(extensions.contains(&"md"));
         assert!(extensions.contains(&"txt"));
+        assert!(extensions.contains(&"py"));
     }
 }
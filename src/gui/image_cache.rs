@@ -0,0 +1,144 @@
+//! Texture cache for images shown in previews
+//!
+//! Images are decoded once (see [`crate::document::image`]) and kept as
+//! egui textures in the context's temporary memory. File entries are
+//! invalidated when the file's modification time changes, so editing an
+//! image on disk refreshes the preview; data URIs are keyed by content.
+
+use eframe::egui;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::document::image::{self, Picture, Source};
+
+#[derive(Clone, Default)]
+struct ImageCache {
+    entries: HashMap<egui::Id, CachedImage>,
+}
+
+#[derive(Clone)]
+struct CachedImage {
+    modified: Option<SystemTime>,
+    /// Errors are kept too, so we don't retry every frame
+    result: Result<(egui::TextureHandle, egui::Vec2), String>,
+}
+
+/// What a preview shows for an image `src`
+pub enum ImageState {
+    /// Texture and natural size in points
+    Loaded {
+        texture: egui::TextureHandle,
+        size: egui::Vec2,
+    },
+    /// Remote URL, shown as a placeholder since previews work offline
+    Remote(String),
+    Failed(String),
+}
+
+/// Load (or fetch from cache) the image `src` of a document
+pub fn load(ctx: &egui::Context, src: &str, base_dir: Option<&Path>) -> ImageState {
+    let result = match image::resolve(src, base_dir) {
+        None => Err(format!("invalid image source: {src}")),
+        Some(Source::Remote(url)) => return ImageState::Remote(url),
+        Some(Source::File(path)) => cached(ctx, &path, || image::load(src, base_dir)),
+        Some(source @ Source::Data { .. }) => {
+            let decode = || {
+                let (bytes, mime) = source.read()?;
+                image::decode(&bytes, &mime)
+            };
+            cached(ctx, src, decode)
+        }
+    };
+    match result {
+        Ok((texture, size)) => ImageState::Loaded { texture, size },
+        Err(err) => ImageState::Failed(err),
+    }
+}
+
+/// Load (or fetch from cache) the texture for a local image file
+pub fn load_texture(ctx: &egui::Context, path: &Path) -> Option<egui::TextureHandle> {
+    let src = path.to_string_lossy();
+    cached(ctx, path, || image::load(&src, None))
+        .ok()
+        .map(|(texture, _)| texture)
+}
+
+/// Cache lookup keyed by a file path (with its modification time) or by a
+/// data URI; `decode` runs on a miss
+fn cached(
+    ctx: &egui::Context,
+    key: impl AsRef<std::ffi::OsStr>,
+    decode: impl FnOnce() -> Result<Picture, String>,
+) -> Result<(egui::TextureHandle, egui::Vec2), String> {
+    let key = key.as_ref();
+    let cache_id = egui::Id::new("preview_image_cache");
+    let entry_id = egui::Id::new(key);
+    let modified = std::fs::metadata(key)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+
+    let cached = ctx.memory_mut(|mem| {
+        mem.data
+            .get_temp_mut_or_default::<ImageCache>(cache_id)
+            .entries
+            .get(&entry_id)
+            .filter(|entry| entry.modified == modified)
+            .map(|entry| entry.result.clone())
+    });
+    if let Some(result) = cached {
+        return result;
+    }
+
+    let result = decode().map(|picture| {
+        let size = [
+            picture.pixels.width() as usize,
+            picture.pixels.height() as usize,
+        ];
+        let color_image = egui::ColorImage::from_rgba_unmultiplied(size, &picture.pixels);
+        let name = format!("preview_image_{entry_id:?}");
+        let texture = ctx.load_texture(name, color_image, Default::default());
+        (texture, egui::vec2(picture.width, picture.height))
+    });
+
+    ctx.memory_mut(|mem| {
+        mem.data
+            .get_temp_mut_or_default::<ImageCache>(cache_id)
+            .entries
+            .insert(
+                entry_id,
+                CachedImage {
+                    modified,
+                    result: result.clone(),
+                },
+            );
+    });
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_states() {
+        let ctx = egui::Context::default();
+        let svg = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' width='30' height='10'/%3E";
+        match load(&ctx, svg, None) {
+            ImageState::Loaded { texture, size } => {
+                assert_eq!(size, egui::vec2(30.0, 10.0));
+                assert_eq!(texture.size(), [60, 20]);
+            }
+            _ => panic!("data URI should load"),
+        }
+        assert!(matches!(
+            load(&ctx, "https://example.com/x.png", None),
+            ImageState::Remote(_)
+        ));
+        assert!(matches!(
+            load(&ctx, "missing.png", Some(Path::new("/nonexistent"))),
+            ImageState::Failed(_)
+        ));
+    }
+}
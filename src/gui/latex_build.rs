@@ -0,0 +1,311 @@
+//! Compiling LaTeX with a local toolchain
+//!
+//! When `latexmk` or `tectonic` is installed, saving a `.tex` file compiles
+//! it on a background thread. The log becomes a problems panel and
+//! squiggles in the editor, and the PDF pages replace the built-in preview.
+//! Without a toolchain nothing is run and the built-in preview is used.
+
+use eframe::egui;
+use flume::Receiver;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use crate::latex::log::{self, Message};
+use crate::latex::toolchain::{Engine, Rasterizer};
+use crate::mermaid::{Diagnostic, Severity};
+
+use super::diagnostics_view;
+use super::image_cache;
+
+/// Resolution of page images; high enough to stay sharp when zoomed a bit
+const PAGE_DPI: u32 = 110;
+
+/// A finished compilation
+#[derive(Debug, Clone)]
+struct Outcome {
+    file: PathBuf,
+    messages: Vec<Message>,
+    pages: Vec<PathBuf>,
+    /// Why there are no pages to show
+    error: Option<String>,
+}
+
+pub struct LatexBuild {
+    engine: Option<Engine>,
+    rasterizer: Option<Rasterizer>,
+    pub compile_on_save: bool,
+    pub show_problems: bool,
+    /// Show PDF pages rather than the built-in preview
+    pub show_pdf: bool,
+    running: Option<Receiver<Outcome>>,
+    /// File saved while compiling, compiled when the current run ends
+    queued: Option<PathBuf>,
+    last: Option<Outcome>,
+}
+
+/// Where a problem in the panel points, for the editor to jump to
+pub struct ProblemLocation {
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+impl LatexBuild {
+    /// Look for a toolchain on `PATH`
+    pub fn new() -> Self {
+        let engine = Engine::detect();
+        Self {
+            compile_on_save: engine.is_some(),
+            engine,
+            rasterizer: Rasterizer::detect(),
+            show_problems: false,
+            show_pdf: true,
+            running: None,
+            queued: None,
+            last: None,
+        }
+    }
+
+    pub fn engine_name(&self) -> Option<&'static str> {
+        self.engine.as_ref().map(Engine::name)
+    }
+
+    /// Compile `file` if it is a LaTeX document and compiling on save is on
+    pub fn file_saved(&mut self, ctx: &egui::Context, file: &Path) {
+        let is_tex = file
+            .extension()
+            .is_some_and(|ext| ext == "tex" || ext == "latex");
+        if !is_tex || !self.compile_on_save {
+            return;
+        }
+        if self.running.is_some() {
+            self.queued = Some(file.to_path_buf());
+        } else {
+            self.start(ctx, file.to_path_buf());
+        }
+    }
+
+    fn start(&mut self, ctx: &egui::Context, file: PathBuf) {
+        let Some(engine) = self.engine.clone() else {
+            return;
+        };
+        let rasterizer = self.rasterizer.clone();
+        let (tx, rx) = flume::bounded(1);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(compile(&engine, rasterizer.as_ref(), file));
+            ctx.request_repaint();
+        });
+        self.running = Some(rx);
+    }
+
+    /// Collect a finished compilation (call once per frame)
+    pub fn poll(&mut self, ctx: &egui::Context) {
+        let Some(rx) = &self.running else {
+            return;
+        };
+        let outcome = match rx.try_recv() {
+            Ok(outcome) => outcome,
+            Err(flume::TryRecvError::Empty) => return,
+            Err(flume::TryRecvError::Disconnected) => {
+                self.running = None;
+                return;
+            }
+        };
+        self.running = None;
+        if log::has_errors(&outcome.messages) {
+            self.show_problems = true;
+        }
+        self.last = Some(outcome);
+        if let Some(file) = self.queued.take() {
+            self.start(ctx, file);
+        }
+    }
+
+    /// Build state for the status bar
+    pub fn status(&self) -> Option<(String, Option<Severity>)> {
+        let engine = self.engine_name()?;
+        if self.running.is_some() {
+            return Some((format!("{engine}: compiling…"), None));
+        }
+        let last = self.last.as_ref()?;
+        let severities = last.messages.iter().map(|message| message.severity);
+        match diagnostics_view::severity_summary(severities.clone()) {
+            Some(summary) => Some((format!("{engine}: {summary}"), severities.min())),
+            None => Some((format!("{engine}: {} pages", last.pages.len()), None)),
+        }
+    }
+
+    /// Squiggles for `file` from the last compilation
+    pub fn diagnostics(&self, file: &Path, text: &str) -> Vec<Diagnostic> {
+        let Some(last) = &self.last else {
+            return Vec::new();
+        };
+        last.messages
+            .iter()
+            .filter(|message| message.file.as_deref() == Some(file))
+            .filter_map(|message| message.diagnostic(text))
+            .collect()
+    }
+
+    /// Draw the PDF pages of `file`; `false` when the built-in preview
+    /// should be drawn instead
+    pub fn show_pages(&mut self, ui: &mut egui::Ui, file: Option<&Path>) -> bool {
+        let Some(last) = self
+            .last
+            .as_ref()
+            .filter(|last| Some(last.file.as_path()) == file)
+        else {
+            return false;
+        };
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.show_pdf, true, "PDF");
+            ui.selectable_value(&mut self.show_pdf, false, "Structure");
+            if let Some(error) = &last.error {
+                ui.colored_label(ui.visuals().warn_fg_color, error);
+            }
+        });
+        if !self.show_pdf || last.pages.is_empty() {
+            return false;
+        }
+        ui.separator();
+
+        let width = ui.available_width();
+        for (index, page) in last.pages.iter().enumerate() {
+            match image_cache::load_texture(ui.ctx(), page) {
+                Some(texture) => {
+                    let size = texture.size_vec2();
+                    let scale = (width / size.x).min(1.5);
+                    ui.add(egui::Image::new(&texture).fit_to_exact_size(size * scale));
+                }
+                None => {
+                    ui.weak(format!("page {} could not be loaded", index + 1));
+                }
+            }
+            ui.add_space(8.0);
+        }
+        true
+    }
+
+    /// The problems panel; returns the location of a clicked problem
+    pub fn show_problems_panel(&mut self, ctx: &egui::Context) -> Option<ProblemLocation> {
+        if !self.show_problems {
+            return None;
+        }
+        let mut clicked = None;
+        let mut open = true;
+        egui::TopBottomPanel::bottom("latex_problems")
+            .resizable(true)
+            .default_height(140.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong("Problems");
+                    if let Some(engine) = self.engine_name() {
+                        ui.weak(engine);
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.small_button("✕").clicked() {
+                            open = false;
+                        }
+                    });
+                });
+                ui.separator();
+
+                let messages = self.last.as_ref().map_or(&[][..], |last| &last.messages);
+                if messages.is_empty() {
+                    ui.weak("No problems");
+                    return;
+                }
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false; 2])
+                    .show(ui, |ui| {
+                        for message in messages {
+                            let location = location_label(message);
+                            let color = diagnostics_view::severity_color(message.severity);
+                            let response = ui
+                                .horizontal(|ui| {
+                                    ui.colored_label(color, message.severity.label());
+                                    ui.monospace(&location);
+                                    ui.label(&message.message);
+                                })
+                                .response
+                                .interact(egui::Sense::click());
+                            if let (true, Some(file), Some(line)) =
+                                (response.clicked(), &message.file, message.line)
+                            {
+                                clicked = Some(ProblemLocation {
+                                    file: file.clone(),
+                                    line,
+                                });
+                            }
+                        }
+                    });
+            });
+        self.show_problems = open;
+        clicked
+    }
+}
+
+impl Default for LatexBuild {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `chapter.tex:12`
+fn location_label(message: &Message) -> String {
+    let file = message
+        .file
+        .as_deref()
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    match message.line {
+        Some(line) => format!("{file}:{line}"),
+        None => file,
+    }
+}
+
+/// Compile and rasterize (runs on the build thread)
+fn compile(engine: &Engine, rasterizer: Option<&Rasterizer>, file: PathBuf) -> Outcome {
+    let build = match engine.compile(&file) {
+        Ok(build) => build,
+        Err(error) => {
+            return Outcome {
+                file,
+                messages: Vec::new(),
+                pages: Vec::new(),
+                error: Some(error),
+            }
+        }
+    };
+
+    let (pages, error) = match (&build.pdf, rasterizer) {
+        (None, _) => (Vec::new(), Some("no PDF was written".to_string())),
+        (Some(_), None) => (
+            Vec::new(),
+            Some("install pdftoppm (Poppler) or mutool (MuPDF) to show the PDF".to_string()),
+        ),
+        (Some(pdf), Some(rasterizer)) => {
+            match rasterizer.render(pdf, &pages_dir(&file), PAGE_DPI) {
+                Ok(pages) => (pages, None),
+                Err(error) => (Vec::new(), Some(error)),
+            }
+        }
+    };
+    Outcome {
+        file,
+        messages: build.messages,
+        pages,
+        error,
+    }
+}
+
+/// Per-document directory for page images under the system temp dir
+fn pages_dir(file: &Path) -> PathBuf {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    file.hash(&mut hasher);
+    std::env::temp_dir()
+        .join("lala-pdf")
+        .join(format!("{:016x}", hasher.finish()))
+}
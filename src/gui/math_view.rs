@@ -0,0 +1,154 @@
+//! egui painter for TeX math
+//!
+//! Lays a formula out with the box engine in [`crate::math`], measuring
+//! glyphs with the UI font, and paints the items. Bars, radical signs and
+//! stretched delimiters are drawn as strokes from [`math::geometry`] so
+//! they can grow to any height. The layout is cached in egui memory per formula and size.
+
+use eframe::egui::{self, text::LayoutJob, Color32, Pos2, Shape, Stroke, Vec2};
+
+use crate::math::layout::{self, Item, Layout, Metrics, Rect, RuleKind};
+use crate::math::{self, geometry, Font};
+
+/// Paint a formula at the cursor, `size` being the font size in points
+pub fn render_math(ui: &mut egui::Ui, tex: &str, display: bool, size: f32) -> egui::Response {
+    let layout = cached_layout(ui, tex, display, size);
+    let (rect, response) = ui.allocate_exact_size(
+        Vec2::new(layout.width, layout.height + layout.depth),
+        egui::Sense::hover(),
+    );
+    if ui.is_rect_visible(rect) {
+        Painter {
+            painter: ui.painter_at(rect.expand(2.0)),
+            origin: Pos2::new(rect.left(), rect.top() + layout.height),
+            size,
+            color: ui.visuals().text_color(),
+            rule: Metrics::pixels(size).rule,
+        }
+        .paint(&layout);
+    }
+    response
+}
+
+fn cached_layout(ui: &egui::Ui, tex: &str, display: bool, size: f32) -> Layout {
+    let id = egui::Id::new(("math_layout", tex, display, size.to_bits()));
+    if let Some(layout) = ui.ctx().data(|data| data.get_temp::<Layout>(id)) {
+        return layout;
+    }
+
+    let measure = |text: &str, font: Font, scale: f32| {
+        let job = job(text, font, size * scale, Color32::WHITE);
+        ui.fonts_mut(|fonts| fonts.layout_job(job).size().x)
+    };
+    let layout = layout::layout(&math::parse(tex), display, &measure, &Metrics::pixels(size));
+    ui.ctx()
+        .data_mut(|data| data.insert_temp(id, layout.clone()));
+    layout
+}
+
+fn job(text: &str, font: Font, size: f32, color: Color32) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    job.append(
+        text,
+        0.0,
+        egui::TextFormat {
+            font_id: egui::FontId::proportional(size),
+            color,
+            italics: font == Font::Italic,
+            ..Default::default()
+        },
+    );
+    job
+}
+
+struct Painter {
+    painter: egui::Painter,
+    /// Left end of the baseline
+    origin: Pos2,
+    size: f32,
+    color: Color32,
+    rule: f32,
+}
+
+impl Painter {
+    fn pos(&self, x: f32, y: f32) -> Pos2 {
+        self.origin + Vec2::new(x, y)
+    }
+
+    fn rect(&self, rect: Rect) -> egui::Rect {
+        egui::Rect::from_min_size(self.pos(rect.x, rect.y), Vec2::new(rect.width, rect.height))
+    }
+
+    fn paint(&self, layout: &Layout) {
+        for item in &layout.items {
+            match item {
+                Item::Glyph {
+                    text,
+                    x,
+                    y,
+                    scale,
+                    font,
+                } => self.glyph(text, self.pos(*x, *y), *scale, *font),
+                Item::Rule { rect, kind } => self.rule(*rect, *kind),
+                Item::Radical { rect } => self.radical(*rect),
+                Item::Delimiter { text, rect } => self.delimiter(text, *rect),
+            }
+        }
+    }
+
+    /// Text with its baseline at `at`
+    fn glyph(&self, text: &str, at: Pos2, scale: f32, font: Font) {
+        let galley = self
+            .painter
+            .layout_job(job(text, font, self.size * scale, self.color));
+        let ascent = galley
+            .rows
+            .first()
+            .and_then(|row| row.glyphs.first().map(|glyph| row.pos.y + glyph.pos.y))
+            .unwrap_or(self.size * scale * 0.8);
+        let top_left = Pos2::new(at.x, at.y - ascent);
+        if font == Font::Bold {
+            // No bold math font: overprint with a small offset
+            self.painter
+                .galley(top_left + Vec2::new(0.6, 0.0), galley.clone(), self.color);
+        }
+        self.painter.galley(top_left, galley, self.color);
+    }
+
+    fn stroke(&self) -> Stroke {
+        Stroke::new(self.rule.max(1.0), self.color)
+    }
+
+    fn rule(&self, rect: Rect, kind: RuleKind) {
+        if kind != RuleKind::Arrow {
+            self.painter.rect_filled(self.rect(rect), 0.0, self.color);
+            return;
+        }
+        self.lines(geometry::arrow(rect));
+    }
+
+    /// The check mark of a radical, ending where the overline starts
+    fn radical(&self, rect: Rect) {
+        self.lines(vec![geometry::radical(rect, self.rule)]);
+    }
+
+    /// Delimiters drawn to the full height of `rect`
+    fn delimiter(&self, text: &str, rect: Rect) {
+        match geometry::delimiter(text, rect) {
+            Some(lines) => self.lines(lines),
+            None => {
+                // No stroke shape: show the character at its natural size
+                let middle = rect.y + rect.height / 2.0;
+                let at = self.pos(rect.x, middle + self.size * 0.3);
+                self.glyph(text, at, 1.0, Font::Upright);
+            }
+        }
+    }
+
+    fn lines(&self, lines: Vec<Vec<geometry::Point>>) {
+        for line in lines {
+            let points = line.into_iter().map(|(x, y)| self.pos(x, y)).collect();
+            self.painter.add(Shape::line(points, self.stroke()));
+        }
+    }
+}
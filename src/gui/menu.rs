@@ -2,9 +2,15 @@ use eframe::egui;
 use std::collections::HashMap;
 
 use crate::core_engine::{Buffer, BufferId};
+use crate::export::Format;
 use crate::llm::GeminiClient;
 
+use super::folding::FoldCommand;
+use super::gutter::LineNumbers;
+use super::latex_build::LatexBuild;
+use super::panes::{PaneAction, SplitDirection};
 use super::previews::PreviewMode;
+use super::theme::ThemeSettings;
 
 /// Render the menu bar
 #[allow(clippy::too_many_arguments)]
@@ -14,13 +20,26 @@ pub fn render_menu_bar(
     open_file: &mut bool,
     save_file: &mut bool,
     save_as: &mut bool,
+    export: &mut Option<Format>,
     show_search: &mut bool,
     show_replace: &mut bool,
     _show_grep: &mut bool,
     show_settings: &mut bool,
-    is_light_theme: &mut bool,
+    show_chat: &mut bool,
+    show_outline: &mut bool,
+    go_to_symbol: &mut bool,
+    go_to_bracket: &mut bool,
+    pane_action: &mut Option<PaneAction>,
+    line_numbers: &mut LineNumbers,
+    show_minimap: &mut bool,
+    show_ruler: &mut bool,
+    fold_command: &mut Option<FoldCommand>,
+    theme_settings: &mut ThemeSettings,
+    theme_names: &[String],
+    system_theme: Option<egui::Theme>,
     show_preview: &mut bool,
     preview_mode: &mut PreviewMode,
+    latex_build: &mut LatexBuild,
     llm_status: &str,
     llm_client: &Option<GeminiClient>,
     ai_enabled: bool,
@@ -48,6 +67,19 @@ pub fn render_menu_bar(
                 *save_as = true;
                 ui.close();
             }
+            if ui.button("Close Tab (Ctrl+W)").clicked() {
+                *pane_action = Some(PaneAction::CloseFocusedTab);
+                ui.close();
+            }
+            ui.separator();
+            ui.menu_button("Export", |ui| {
+                for format in Format::ALL {
+                    if ui.button(format!("{}…", format.label())).clicked() {
+                        *export = Some(format);
+                        ui.close();
+                    }
+                }
+            });
         });
 
         // Edit menu
@@ -61,6 +93,15 @@ pub fn render_menu_bar(
                 *show_replace = true;
                 ui.close();
             }
+            ui.separator();
+            if ui.button("Go to Symbol… (Ctrl+Shift+O)").clicked() {
+                *go_to_symbol = true;
+                ui.close();
+            }
+            if ui.button("Go to Matching Bracket (Ctrl+M)").clicked() {
+                *go_to_bracket = true;
+                ui.close();
+            }
         });
 
         // Tools menu with AI features
@@ -71,6 +112,16 @@ pub fn render_menu_bar(
                 ui.close();
             }
 
+            let chat_label = if *show_chat {
+                "💬 Hide AI Chat (Ctrl+L)"
+            } else {
+                "💬 AI Chat (Ctrl+L)"
+            };
+            if ui.button(chat_label).clicked() {
+                *show_chat = !*show_chat;
+                ui.close();
+            }
+
             ui.separator();
             ui.label(llm_status);
             ui.separator();
@@ -121,9 +172,9 @@ pub fn render_menu_bar(
                 .clicked()
             {
                 if let Some(client) = llm_client {
-                    match client
-                        .improve_markdown(&format!("Summarize this text concisely:\n\n{current_text}"))
-                    {
+                    match client.improve_markdown(&format!(
+                        "Summarize this text concisely:\n\n{current_text}"
+                    )) {
                         Ok(summary) => {
                             *current_text = summary;
                             *text_changed = true;
@@ -143,16 +194,82 @@ pub fn render_menu_bar(
 
         // View menu
         ui.menu_button("View", |ui| {
-            // Theme toggle
-            let theme_label = if *is_light_theme {
-                "🌙 Dark Theme"
-            } else {
-                "☀️ Light Theme"
+            ui.menu_button("🎨 Theme", |ui| {
+                ui.checkbox(&mut theme_settings.follow_system, "Follow System");
+                if theme_settings.follow_system {
+                    let appearance = match system_theme {
+                        Some(egui::Theme::Light) => "Light appearance",
+                        Some(egui::Theme::Dark) => "Dark appearance",
+                        None => "System preference unknown",
+                    };
+                    ui.label(appearance);
+                }
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        let selected = theme_settings.selected(system_theme).to_string();
+                        for name in theme_names {
+                            if ui.radio(selected == *name, name).clicked() {
+                                theme_settings.select(name, system_theme);
+                                ui.close();
+                            }
+                        }
+                    });
+            });
+
+            ui.menu_button("Line Numbers", |ui| {
+                ui.radio_value(line_numbers, LineNumbers::Absolute, "Absolute");
+                ui.radio_value(line_numbers, LineNumbers::Relative, "Relative");
+            });
+            ui.checkbox(show_minimap, "Minimap");
+            ui.checkbox(show_ruler, "Overview Ruler");
+
+            ui.menu_button("Folding", |ui| {
+                let mut command = |ui: &mut egui::Ui, label: &str, value: FoldCommand| {
+                    if ui.button(label).clicked() {
+                        *fold_command = Some(value);
+                        ui.close();
+                    }
+                };
+                command(ui, "Fold (Ctrl+Shift+[)", FoldCommand::FoldAtCursor);
+                command(ui, "Unfold (Ctrl+Shift+])", FoldCommand::UnfoldAtCursor);
+                ui.separator();
+                command(ui, "Fold All", FoldCommand::FoldAll);
+                command(ui, "Unfold All", FoldCommand::UnfoldAll);
+                ui.separator();
+                for level in 1..=6 {
+                    command(
+                        ui,
+                        &format!("Fold Level {level}"),
+                        FoldCommand::FoldToLevel(level),
+                    );
+                }
+            });
+
+            ui.separator();
+            ui.checkbox(show_outline, "Outline");
+
+            ui.separator();
+            let mut layout = |ui: &mut egui::Ui, label: &str, action: PaneAction| {
+                if ui.button(label).clicked() {
+                    *pane_action = Some(action);
+                    ui.close();
+                }
             };
-            if ui.button(theme_label).clicked() {
-                *is_light_theme = !*is_light_theme;
-                ui.close();
-            }
+            layout(
+                ui,
+                "Split Right (Ctrl+\\)",
+                PaneAction::Split(SplitDirection::Right),
+            );
+            layout(
+                ui,
+                "Split Down (Ctrl+Shift+\\)",
+                PaneAction::Split(SplitDirection::Down),
+            );
+            layout(ui, "Close Split", PaneAction::CloseFocusedPane);
+            layout(ui, "Move Tab to New Window", PaneAction::DetachFocusedTab);
 
             ui.separator();
 
@@ -204,6 +321,24 @@ pub fn render_menu_bar(
                 *show_preview = true;
                 ui.close();
             }
+
+            ui.separator();
+            ui.checkbox(&mut latex_build.show_problems, "Problems");
+            match latex_build.engine_name() {
+                Some(engine) => {
+                    ui.checkbox(
+                        &mut latex_build.compile_on_save,
+                        format!("Compile LaTeX on Save ({engine})"),
+                    );
+                }
+                None => {
+                    ui.add_enabled(
+                        false,
+                        egui::Checkbox::new(&mut false, "Compile LaTeX on Save"),
+                    )
+                    .on_disabled_hover_text("Install latexmk or tectonic to compile LaTeX");
+                }
+            }
         });
 
         // Show file status
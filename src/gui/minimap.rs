@@ -0,0 +1,272 @@
+//! Minimap and overview ruler beside the main editor
+//!
+//! The minimap paints the editor's laid out text at a scale of one pixel
+//! per character and two per row, in the highlighted colors, with the
+//! visible part of the file marked. Files taller than the minimap scroll
+//! it along with the editor. Clicking or dragging scrolls the editor.
+//!
+//! The overview ruler maps the whole file onto its height and marks search
+//! matches, diagnostics, VCS changes and the cursor line.
+
+use std::collections::BTreeMap;
+
+use eframe::egui::{self, Color32, Rect};
+
+use super::diagnostics_view::severity_color;
+use super::gutter;
+use super::vcs::LineChange;
+use crate::mermaid::{Diagnostic, Severity};
+
+/// Width of the minimap
+pub const MINIMAP_WIDTH: f32 = 90.0;
+/// Width of the overview ruler
+pub const RULER_WIDTH: f32 = 14.0;
+/// Minimap pixels per character
+const CHAR_WIDTH: f32 = 1.0;
+/// Minimap pixels per row
+const ROW_HEIGHT: f32 = 2.0;
+
+const SEARCH_COLOR: Color32 = Color32::from_rgb(230, 160, 40);
+
+/// Per-line marks for the overview ruler, by 0-based line
+#[derive(Default)]
+pub struct RulerMarks<'a> {
+    pub search: &'a [usize],
+    /// Line of the selected search match
+    pub current_match: Option<usize>,
+    pub diagnostics: &'a [Diagnostic],
+    pub changes: Option<&'a BTreeMap<usize, LineChange>>,
+    pub cursor_line: usize,
+    pub line_count: usize,
+}
+
+/// First of `total` rows shown in a minimap holding `capacity` rows, with
+/// the editor scrolled `fraction` of the way down
+pub fn first_row(total: usize, capacity: usize, fraction: f32) -> usize {
+    let hidden = total.saturating_sub(capacity);
+    ((hidden as f32) * fraction.clamp(0.0, 1.0)).round() as usize
+}
+
+/// Vertical position of 0-based `line` of `line_count` in the ruler `rect`
+pub fn ruler_y(rect: Rect, line: usize, line_count: usize) -> f32 {
+    let fraction = (line as f32 + 0.5) / line_count.max(1) as f32;
+    rect.top() + rect.height() * fraction.min(1.0)
+}
+
+/// Editor scroll offset putting `y` (in content coordinates) in the middle
+/// of the viewport
+fn centered_offset(scroll: &egui::scroll_area::ScrollAreaOutput<impl Sized>, y: f32) -> f32 {
+    let max = (scroll.content_size.y - scroll.inner_rect.height()).max(0.0);
+    (y - scroll.inner_rect.height() / 2.0).clamp(0.0, max)
+}
+
+/// How far down the editor is scrolled, from 0 to 1
+fn scroll_fraction(scroll: &egui::scroll_area::ScrollAreaOutput<impl Sized>) -> f32 {
+    let max = scroll.content_size.y - scroll.inner_rect.height();
+    if max <= 0.0 {
+        0.0
+    } else {
+        scroll.state.offset.y / max
+    }
+}
+
+/// Draw the minimap in `rect` for the editor `scroll` area; returns the
+/// scroll offset to move the editor to when it is clicked or dragged
+pub fn show(
+    ui: &mut egui::Ui,
+    rect: Rect,
+    scroll: &egui::scroll_area::ScrollAreaOutput<egui::text_edit::TextEditOutput>,
+    text_color: Color32,
+) -> Option<f32> {
+    let output = &scroll.inner;
+    let galley = &output.galley;
+    let response = ui.interact(rect, ui.id().with("minimap"), egui::Sense::click_and_drag());
+    let painter = ui.painter_at(rect);
+
+    // Byte offset of each row's first character; folded rows have no
+    // height and are left out
+    let mut rows = Vec::new();
+    let mut offset = 0;
+    for placed in &galley.rows {
+        if placed.rect().height() > 0.0 {
+            rows.push((offset, placed));
+        }
+        offset += placed
+            .row
+            .glyphs
+            .iter()
+            .map(|glyph| glyph.chr.len_utf8())
+            .sum::<usize>();
+        if placed.row.ends_with_newline {
+            offset += 1;
+        }
+    }
+
+    let capacity = (rect.height() / ROW_HEIGHT) as usize;
+    let first = first_row(rows.len(), capacity, scroll_fraction(scroll));
+    let shown = &rows[first.min(rows.len())..(first + capacity).min(rows.len())];
+    let sections = &galley.job.sections;
+    let scale = CHAR_WIDTH
+        / ui.fonts_mut(|fonts| {
+            fonts.glyph_width(&egui::TextStyle::Monospace.resolve(ui.style()), '0')
+        });
+
+    for (index, (row_offset, placed)) in shown.iter().enumerate() {
+        let y = rect.top() + index as f32 * ROW_HEIGHT;
+        let mut byte = *row_offset;
+        // Runs of visible characters of one color become one rectangle
+        let mut run: Option<(f32, f32, Color32)> = None;
+        for glyph in &placed.row.glyphs {
+            let color = if glyph.chr.is_whitespace() {
+                None
+            } else {
+                let section = sections.partition_point(|s| s.byte_range.end <= byte);
+                Some(sections.get(section).map_or(text_color, |s| s.format.color))
+            };
+            byte += glyph.chr.len_utf8();
+            let left = rect.left() + 2.0 + glyph.pos.x * scale;
+            let right = left + glyph.advance_width * scale;
+            match (run, color) {
+                (Some((start, _, run_color)), Some(color)) if run_color == color => {
+                    run = Some((start, right, color));
+                }
+                (_, color) => {
+                    if let Some((start, end, run_color)) = run {
+                        paint_run(&painter, start, end, y, run_color);
+                    }
+                    run = color.map(|color| (left, right, color));
+                }
+            }
+        }
+        if let Some((start, end, color)) = run {
+            paint_run(&painter, start, end, y, color);
+        }
+    }
+
+    // The part of the file on screen
+    let viewport = scroll.inner_rect;
+    let galley_top = output.galley_pos.y;
+    let visible: Vec<usize> = shown
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, placed))| {
+            let row = placed.rect().translate(egui::vec2(0.0, galley_top));
+            row.bottom() > viewport.top() && row.top() < viewport.bottom()
+        })
+        .map(|(index, _)| index)
+        .collect();
+    if let (Some(top), Some(bottom)) = (visible.first(), visible.last()) {
+        let indicator = Rect::from_x_y_ranges(
+            rect.x_range(),
+            rect.top() + *top as f32 * ROW_HEIGHT..=rect.top() + (*bottom + 1) as f32 * ROW_HEIGHT,
+        );
+        let alpha = if response.hovered() || response.dragged() {
+            0.16
+        } else {
+            0.08
+        };
+        painter.rect_filled(indicator, 0.0, text_color.gamma_multiply(alpha));
+    }
+
+    // Scroll so the pointed row is in the middle of the editor
+    let pos = response
+        .interact_pointer_pos()
+        .filter(|_| response.clicked() || response.dragged())?;
+    let index = ((pos.y - rect.top()) / ROW_HEIGHT).max(0.0) as usize;
+    let (_, placed) = shown.get(index).or(shown.last())?;
+    Some(centered_offset(scroll, placed.rect().center().y))
+}
+
+fn paint_run(painter: &egui::Painter, left: f32, right: f32, y: f32, color: Color32) {
+    let rect = Rect::from_x_y_ranges(
+        left..=right.max(left + CHAR_WIDTH),
+        y..=y + ROW_HEIGHT - 0.5,
+    );
+    painter.rect_filled(rect, 0.0, color.gamma_multiply(0.7));
+}
+
+/// Draw the overview ruler in `rect`; returns the scroll offset to move
+/// the editor to when it is clicked
+pub fn show_ruler(
+    ui: &mut egui::Ui,
+    rect: Rect,
+    scroll: &egui::scroll_area::ScrollAreaOutput<egui::text_edit::TextEditOutput>,
+    marks: &RulerMarks,
+    text_color: Color32,
+) -> Option<f32> {
+    let response = ui.interact(
+        rect,
+        ui.id().with("overview_ruler"),
+        egui::Sense::click_and_drag(),
+    );
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, text_color.gamma_multiply(0.04));
+    let line_count = marks.line_count.max(1);
+    let mark = |lane: egui::Rangef, line: usize, height: f32, color: Color32| {
+        let y = ruler_y(rect, line, line_count);
+        let bar = Rect::from_x_y_ranges(lane, y - height / 2.0..=y + height / 2.0);
+        painter.rect_filled(bar, 0.0, color);
+    };
+
+    // Lanes: changes on the left, search in the middle, diagnostics right
+    let third = rect.width() / 3.0;
+    let left = egui::Rangef::new(rect.left(), rect.left() + third);
+    let middle = egui::Rangef::new(rect.left() + third, rect.right() - third);
+    let right = egui::Rangef::new(rect.right() - third, rect.right());
+
+    if let Some(changes) = marks.changes {
+        for (&line, &change) in changes {
+            mark(left, line, 3.0, gutter::change_color(change));
+        }
+    }
+    for &line in marks.search {
+        mark(middle, line, 2.0, SEARCH_COLOR);
+    }
+    if let Some(line) = marks.current_match {
+        mark(rect.x_range(), line, 3.0, SEARCH_COLOR);
+    }
+    let mut worst: BTreeMap<usize, Severity> = BTreeMap::new();
+    for diagnostic in marks.diagnostics {
+        let severity = worst
+            .entry(diagnostic.line.saturating_sub(1))
+            .or_insert(diagnostic.severity);
+        if diagnostic.severity == Severity::Error {
+            *severity = Severity::Error;
+        }
+    }
+    for (line, severity) in worst {
+        mark(right, line, 3.0, severity_color(severity));
+    }
+    mark(rect.x_range(), marks.cursor_line, 2.0, text_color);
+
+    let pos = response
+        .interact_pointer_pos()
+        .filter(|_| response.clicked() || response.dragged())?;
+    let fraction = ((pos.y - rect.top()) / rect.height()).clamp(0.0, 1.0);
+    Some(centered_offset(scroll, fraction * scroll.content_size.y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_row_follows_scrolling() {
+        // Everything fits: never scrolls
+        assert_eq!(first_row(50, 100, 0.7), 0);
+        assert_eq!(first_row(1000, 100, 0.0), 0);
+        assert_eq!(first_row(1000, 100, 0.5), 450);
+        assert_eq!(first_row(1000, 100, 1.0), 900);
+        assert_eq!(first_row(1000, 100, 2.0), 900);
+    }
+
+    #[test]
+    fn test_ruler_positions() {
+        let rect = Rect::from_x_y_ranges(0.0..=10.0, 100.0..=300.0);
+        assert_eq!(ruler_y(rect, 0, 4), 125.0);
+        assert_eq!(ruler_y(rect, 3, 4), 275.0);
+        // Lines past the end stay inside
+        assert_eq!(ruler_y(rect, 10, 4), 300.0);
+        assert_eq!(ruler_y(rect, 0, 0), 200.0);
+    }
+}
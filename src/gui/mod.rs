@@ -1,18 +1,38 @@
 mod app;
 mod app_state;
+mod chat_panel;
+mod diagnostics_view;
+mod diagram_view;
 mod dialogs;
+mod document_view;
+mod editing;
 mod editor;
+mod editor_cursor;
+mod editorconfig;
+mod folding;
 mod grep_panel;
+mod gutter;
+mod highlight_cache;
 mod highlighting;
+mod image_cache;
+mod latex_build;
 mod markdown_preview;
+mod math_view;
 mod menu;
+mod minimap;
+mod outline;
+mod panes;
 mod previews;
+mod scroll_sync;
 mod search_panel;
 mod tab;
+mod text_info;
 mod theme;
+mod vcs;
 
 pub use app::LalaApp;
 pub use app_state::AppState;
 pub use editor::EditorPanel;
+pub use highlight_cache::HighlightCache;
 pub use highlighting::SyntaxHighlighter;
 pub use tab::EditorTabState;
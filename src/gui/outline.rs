@@ -0,0 +1,571 @@
+//! Outline panel: the headings, sections or symbols of the active buffer
+//!
+//! Markdown, LaTeX and HTML outlines come from the document model's
+//! headings and source map. For code, symbols are the names syntect scopes
+//! as `entity.name.*` (functions, types, modules, ...), nested by the fold
+//! regions around them; parsing a whole file takes a while, so it runs on a
+//! background thread.
+//!
+//! The panel updates as the text changes, can be filtered, and the same
+//! entries back a "go to symbol" quick pick (Ctrl+Shift+O).
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use eframe::egui;
+use flume::Receiver;
+use syntect::parsing::{ParseState, Scope, ScopeStack, SyntaxSet};
+
+use super::folding;
+use crate::document::{self, Block};
+
+/// Scope kinds listed as code symbols, after `entity.name.`
+const SYMBOL_KINDS: &[&str] = &[
+    "function",
+    "class",
+    "struct",
+    "enum",
+    "union",
+    "trait",
+    "interface",
+    "impl",
+    "type",
+    "module",
+    "namespace",
+    "constant",
+    "macro",
+];
+
+/// One heading, section or symbol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlineEntry {
+    /// Nesting depth, 1 for top-level entries
+    pub level: usize,
+    pub label: String,
+    /// Symbol kind such as `function`, empty for headings
+    pub kind: String,
+    /// Byte offset in the source
+    pub offset: usize,
+}
+
+/// Outline of `text` for the syntax called `language`; `None` when it is
+/// code, whose symbols come from [`code_symbols`]
+pub fn document_outline(text: &str, language: &str) -> Option<Vec<OutlineEntry>> {
+    let doc = match language {
+        "Markdown" | "MultiMarkdown" => document::markdown::parse(text),
+        "LaTeX" | "TeX" => document::latex::parse(text),
+        "HTML" => document::html::parse(text),
+        "Plain Text" => return Some(Vec::new()),
+        _ => return None,
+    };
+    let entries = doc
+        .blocks
+        .iter()
+        .zip(&doc.source_map)
+        .filter_map(|(block, range)| match block {
+            Block::Heading { level, content } => Some(OutlineEntry {
+                level: *level as usize,
+                label: document::plain_text(content).trim().to_string(),
+                kind: String::new(),
+                offset: range.start,
+            }),
+            _ => None,
+        })
+        .collect();
+    Some(nest(entries))
+}
+
+/// Turn heading levels or fold depths into tree depths, so `#` then `###`
+/// nests one deep
+fn nest(mut entries: Vec<OutlineEntry>) -> Vec<OutlineEntry> {
+    let mut open: Vec<usize> = Vec::new();
+    for entry in &mut entries {
+        while open.last().is_some_and(|&level| level >= entry.level) {
+            open.pop();
+        }
+        open.push(entry.level);
+        entry.level = open.len();
+    }
+    entries
+}
+
+/// Names scoped `entity.name.<kind>` for the [`SYMBOL_KINDS`], nested by
+/// the fold regions containing them
+pub fn code_symbols(
+    text: &str,
+    syntax_set: &SyntaxSet,
+    language: &str,
+) -> Result<Vec<OutlineEntry>, String> {
+    let syntax = syntax_set
+        .find_syntax_by_name(language)
+        .ok_or_else(|| format!("Unknown language: {language}"))?;
+    let entity = Scope::new("entity.name").map_err(|e| format!("{e:?}"))?;
+    let regions = folding::regions(text, language);
+
+    let mut state = ParseState::new(syntax);
+    let mut stack = ScopeStack::new();
+    let mut entries: Vec<OutlineEntry> = Vec::new();
+    let mut line_start = 0;
+    for (line_index, line) in text.split_inclusive('\n').enumerate() {
+        let ops = state
+            .parse_line(line, syntax_set)
+            .map_err(|e| format!("Failed to parse line {}: {e}", line_index + 1))?;
+        let mut position = 0;
+        let mut previous_end = None;
+        // The text after the last operation runs to the end of the line
+        let ends = ops.iter().map(|(at, op)| (*at, Some(op)));
+        for (at, op) in ends.chain([(line.len(), None)]) {
+            if at > position {
+                if let Some(kind) = symbol_kind(&stack, entity) {
+                    let token = &line[position..at];
+                    let offset = line_start + position;
+                    // Names split over several tokens join up
+                    match entries.last_mut() {
+                        Some(last) if previous_end == Some(offset) && last.kind == kind => {
+                            last.label.push_str(token);
+                        }
+                        _ => {
+                            let depth = regions
+                                .iter()
+                                .filter(|r| r.start < line_index && line_index <= r.end)
+                                .count();
+                            entries.push(OutlineEntry {
+                                level: depth + 1,
+                                label: token.to_string(),
+                                kind,
+                                offset,
+                            });
+                        }
+                    }
+                    previous_end = Some(line_start + at);
+                }
+                position = at;
+            }
+            if let Some(op) = op {
+                stack
+                    .apply(op)
+                    .map_err(|e| format!("Failed to parse line {}: {e:?}", line_index + 1))?;
+            }
+        }
+        line_start += line.len();
+    }
+
+    entries.retain(|entry| !entry.label.trim().is_empty());
+    for entry in &mut entries {
+        entry.label = entry.label.trim().to_string();
+    }
+    Ok(nest(entries))
+}
+
+/// Kind of the innermost `entity.name.<kind>` scope on the stack, if listed
+fn symbol_kind(stack: &ScopeStack, entity: Scope) -> Option<String> {
+    let scope = stack
+        .as_slice()
+        .iter()
+        .rev()
+        .find(|scope| entity.is_prefix_of(**scope))?;
+    let name = scope.build_string();
+    let kind = name.split('.').nth(2)?;
+    SYMBOL_KINDS.contains(&kind).then(|| kind.to_string())
+}
+
+/// Whether `label` matches a filter: its words appear in order, ignoring case
+pub fn matches(label: &str, filter: &str) -> bool {
+    let label = label.to_lowercase();
+    let mut rest = label.as_str();
+    for word in filter.to_lowercase().split_whitespace() {
+        match rest.find(word) {
+            Some(at) => rest = &rest[at + word.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+fn text_key(text: &str, language: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    language.hash(&mut hasher);
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+type SymbolResult = Result<Vec<OutlineEntry>, String>;
+
+/// State of the "go to symbol" picker
+struct QuickPick {
+    query: String,
+    selected: usize,
+}
+
+/// Outline side panel and "go to symbol" quick pick
+#[derive(Default)]
+pub struct OutlinePanel {
+    filter: String,
+    entries: Arc<Vec<OutlineEntry>>,
+    /// Text and language the entries were made from
+    key: Option<u64>,
+    /// Code symbols being collected, and the key they are for
+    running: Option<(u64, Receiver<SymbolResult>)>,
+    error: Option<String>,
+    quick_pick: Option<QuickPick>,
+}
+
+impl OutlinePanel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bring the entries up to date with `text`; code symbols arrive a few
+    /// frames later
+    pub fn update(
+        &mut self,
+        ctx: &egui::Context,
+        text: &str,
+        language: &str,
+        syntax_set: &Arc<SyntaxSet>,
+    ) {
+        self.poll();
+        let key = text_key(text, language);
+        if self.key == Some(key) || self.running.as_ref().is_some_and(|(k, _)| *k == key) {
+            return;
+        }
+        if let Some(entries) = document_outline(text, language) {
+            self.entries = Arc::new(entries);
+            self.key = Some(key);
+            self.error = None;
+            return;
+        }
+        // One parse at a time; the next frame starts the latest text
+        if self.running.is_some() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(50));
+            return;
+        }
+        let (tx, rx) = flume::bounded(1);
+        let (text, language, syntax_set) =
+            (text.to_string(), language.to_string(), syntax_set.clone());
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(code_symbols(&text, &syntax_set, &language));
+            ctx.request_repaint();
+        });
+        self.running = Some((key, rx));
+    }
+
+    fn poll(&mut self) {
+        let Some((key, rx)) = &self.running else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(flume::TryRecvError::Empty) => return,
+            Err(flume::TryRecvError::Disconnected) => {
+                self.running = None;
+                return;
+            }
+        };
+        self.key = Some(*key);
+        self.running = None;
+        match result {
+            Ok(entries) => {
+                self.entries = Arc::new(entries);
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    /// Show the panel; returns the byte offset of a clicked entry
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        cursor: Option<usize>,
+        open: &mut bool,
+    ) -> Option<usize> {
+        let mut jump = None;
+        egui::SidePanel::left("outline_panel")
+            .default_width(240.0)
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading("Outline");
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.small_button("✕").clicked() {
+                            *open = false;
+                        }
+                        if self.running.is_some() {
+                            ui.spinner();
+                        }
+                    });
+                });
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.filter)
+                        .hint_text("Filter")
+                        .desired_width(f32::INFINITY),
+                );
+                ui.separator();
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                if self.entries.is_empty() {
+                    ui.weak("No headings or symbols");
+                }
+
+                // The entry containing the cursor
+                let current = cursor.and_then(|cursor| {
+                    self.entries
+                        .iter()
+                        .rposition(|entry| entry.offset <= cursor)
+                });
+                let entries = self.entries.clone();
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false; 2])
+                    .show(ui, |ui| {
+                        if self.filter.trim().is_empty() {
+                            jump = show_tree(ui, &entries, 0, entries.len(), current);
+                        } else {
+                            for (index, entry) in entries.iter().enumerate() {
+                                if matches(&entry.label, &self.filter)
+                                    && entry_label(ui, entry, Some(index) == current).clicked()
+                                {
+                                    jump = Some(entry.offset);
+                                }
+                            }
+                        }
+                    });
+            });
+        jump
+    }
+
+    /// Open the "go to symbol" picker
+    pub fn open_quick_pick(&mut self) {
+        self.quick_pick = Some(QuickPick {
+            query: String::new(),
+            selected: 0,
+        });
+    }
+
+    pub fn is_picking(&self) -> bool {
+        self.quick_pick.is_some()
+    }
+
+    /// Show the picker if open; returns the byte offset of the chosen entry
+    pub fn show_quick_pick(&mut self, ctx: &egui::Context) -> Option<usize> {
+        let pick = self.quick_pick.as_mut()?;
+        let found: Vec<&OutlineEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| matches(&entry.label, &pick.query))
+            .collect();
+        pick.selected = pick.selected.min(found.len().saturating_sub(1));
+
+        let (enter, escape, up, down) = ctx.input(|i| {
+            (
+                i.key_pressed(egui::Key::Enter),
+                i.key_pressed(egui::Key::Escape),
+                i.key_pressed(egui::Key::ArrowUp),
+                i.key_pressed(egui::Key::ArrowDown),
+            )
+        });
+        if down && pick.selected + 1 < found.len() {
+            pick.selected += 1;
+        }
+        if up {
+            pick.selected = pick.selected.saturating_sub(1);
+        }
+
+        let mut chosen = None;
+        egui::Window::new("Go to Symbol")
+            .collapsible(false)
+            .resizable(false)
+            .title_bar(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 60.0))
+            .fixed_size(egui::vec2(420.0, 0.0))
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut pick.query)
+                        .hint_text("Go to symbol…")
+                        .desired_width(f32::INFINITY),
+                );
+                response.request_focus();
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(320.0)
+                    .show(ui, |ui| {
+                        if found.is_empty() {
+                            ui.weak("No matching symbols");
+                        }
+                        for (index, entry) in found.iter().enumerate() {
+                            let response = entry_label(ui, entry, index == pick.selected);
+                            if index == pick.selected && (up || down) {
+                                response.scroll_to_me(None);
+                            }
+                            if response.clicked() {
+                                chosen = Some(entry.offset);
+                            }
+                        }
+                    });
+            });
+
+        if enter {
+            chosen = chosen.or_else(|| found.get(pick.selected).map(|entry| entry.offset));
+        }
+        if chosen.is_some() || escape {
+            self.quick_pick = None;
+        }
+        chosen
+    }
+}
+
+/// Entries `start..end` as a tree of collapsing rows; returns a clicked
+/// entry's offset
+fn show_tree(
+    ui: &mut egui::Ui,
+    entries: &[OutlineEntry],
+    start: usize,
+    end: usize,
+    current: Option<usize>,
+) -> Option<usize> {
+    let mut jump = None;
+    let mut index = start;
+    while index < end {
+        let entry = &entries[index];
+        // Children are the following deeper entries
+        let children_end = entries[index + 1..end]
+            .iter()
+            .position(|child| child.level <= entry.level)
+            .map_or(end, |at| index + 1 + at);
+
+        if children_end > index + 1 {
+            let id = ui.make_persistent_id(("outline", index, &entry.label));
+            egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, true)
+                .show_header(ui, |ui| {
+                    if entry_label(ui, entry, Some(index) == current).clicked() {
+                        jump = Some(entry.offset);
+                    }
+                })
+                .body(|ui| {
+                    if let Some(offset) = show_tree(ui, entries, index + 1, children_end, current) {
+                        jump = Some(offset);
+                    }
+                });
+        } else {
+            ui.horizontal(|ui| {
+                ui.add_space(ui.spacing().indent);
+                if entry_label(ui, entry, Some(index) == current).clicked() {
+                    jump = Some(entry.offset);
+                }
+            });
+        }
+        index = children_end;
+    }
+    jump
+}
+
+fn entry_label(ui: &mut egui::Ui, entry: &OutlineEntry, selected: bool) -> egui::Response {
+    let mut text = egui::text::LayoutJob::default();
+    let style = ui.style();
+    text.append(
+        &entry.label,
+        0.0,
+        egui::TextFormat::simple(
+            egui::TextStyle::Body.resolve(style),
+            style.visuals.text_color(),
+        ),
+    );
+    if !entry.kind.is_empty() {
+        text.append(
+            &entry.kind,
+            8.0,
+            egui::TextFormat::simple(
+                egui::TextStyle::Small.resolve(style),
+                style.visuals.weak_text_color(),
+            ),
+        );
+    }
+    ui.selectable_label(selected, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outline(text: &str, language: &str) -> Vec<(usize, String)> {
+        document_outline(text, language)
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.level, entry.label))
+            .collect()
+    }
+
+    #[test]
+    fn test_markdown_outline() {
+        let text = "# Title\n\nIntro\n\n### Deep\n\n## Second\n\n```\n# not a heading\n```\n";
+        assert_eq!(
+            outline(text, "Markdown"),
+            vec![
+                (1, "Title".to_string()),
+                (2, "Deep".to_string()),
+                (2, "Second".to_string())
+            ]
+        );
+        let entries = document_outline(text, "Markdown").unwrap();
+        assert_eq!(&text[entries[1].offset..entries[1].offset + 8], "### Deep");
+    }
+
+    #[test]
+    fn test_latex_outline() {
+        let text = "\\section{Intro}\nText\n\\subsection{Details}\nMore\n\\section{End}\n";
+        let entries = document_outline(text, "LaTeX").unwrap();
+        let levels: Vec<usize> = entries.iter().map(|entry| entry.level).collect();
+        assert_eq!(levels, vec![1, 2, 1]);
+        assert!(entries[1].label.contains("Details"));
+        assert!(text[entries[1].offset..].starts_with("\\subsection"));
+    }
+
+    #[test]
+    fn test_html_outline() {
+        let text =
+            "<h1 class=\"t\">Hello <em>world</em></h1>\n<p>x</p>\n<h2>A &amp; B</h2>\n<hr>\n";
+        assert_eq!(
+            outline(text, "HTML"),
+            vec![(1, "Hello world".to_string()), (2, "A & B".to_string())]
+        );
+        let entries = document_outline(text, "HTML").unwrap();
+        assert!(text[entries[1].offset..].starts_with("<h2>"));
+
+        // Headings in comments, scripts and code are not part of the page
+        let text = "<!-- <h1>Old</h1> -->\n<script>document.write('<h1>x</h1>')</script>\n\
+                    <pre>&lt;h2&gt;</pre>\n<h3>Real</h3>\n";
+        assert_eq!(outline(text, "HTML"), vec![(1, "Real".to_string())]);
+    }
+
+    #[test]
+    fn test_code_symbols() {
+        let syntax_set = SyntaxSet::load_defaults_newlines();
+        let text = "struct Point {\n    x: i32,\n}\n\nimpl Point {\n    fn new() -> Self {\n        todo!()\n    }\n}\n\nfn main() {}\n";
+        let symbols: Vec<(usize, String, String)> = code_symbols(text, &syntax_set, "Rust")
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.level, entry.label, entry.kind))
+            .collect();
+        assert_eq!(
+            symbols,
+            vec![
+                (1, "Point".to_string(), "struct".to_string()),
+                (1, "Point".to_string(), "impl".to_string()),
+                (2, "new".to_string(), "function".to_string()),
+                (1, "main".to_string(), "function".to_string()),
+            ]
+        );
+        assert!(document_outline(text, "Rust").is_none());
+    }
+
+    #[test]
+    fn test_filter() {
+        assert!(matches("Installation Guide", "inst guide"));
+        assert!(matches("anything", ""));
+        assert!(!matches("Installation Guide", "guide inst"));
+    }
+}
@@ -0,0 +1,652 @@
+//! Split editor layout: a tree of panes, each with its own tabs
+//!
+//! Splits divide a pane to the right or downwards and can be resized by
+//! dragging the bar between them. Any pane can show any buffer, and two
+//! panes showing the same `Buffer` are two views of one text: edits are
+//! shared, cursors and scroll positions are not.
+//!
+//! The focused pane's tab is the app's active buffer and is drawn with the
+//! main editor id, so cursor helpers, the outline and the preview follow it.
+//! Other views keep their `TextEdit` state under their own id; the states
+//! are swapped when the focus moves.
+//!
+//! Tabs can be dragged between panes or detached into a window of their
+//! own. Window panes live outside the tree and return their tabs to the
+//! focused pane when closed.
+
+use std::collections::BTreeMap;
+
+use eframe::egui::{self, Rect};
+
+use super::editor_cursor;
+use crate::core_engine::BufferId;
+
+/// Width of the bar between split panes
+const SPLITTER_WIDTH: f32 = 6.0;
+/// Smallest share of a split either side can be dragged to
+const MIN_FRACTION: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PaneId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitDirection {
+    /// Side by side
+    Right,
+    /// Stacked
+    Down,
+}
+
+/// Changes to the layout, from tab bars, menus and shortcuts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaneAction {
+    Focus(PaneId),
+    SelectTab(PaneId, usize),
+    CloseTab(PaneId, usize),
+    /// Close the focused pane's current tab
+    CloseFocusedTab,
+    MoveTab {
+        from: PaneId,
+        index: usize,
+        to: PaneId,
+    },
+    /// Split the focused pane, showing its buffer on both sides
+    Split(SplitDirection),
+    ClosePane(PaneId),
+    CloseFocusedPane,
+    /// Move a tab into a new window
+    Detach(PaneId, usize),
+    DetachFocusedTab,
+}
+
+/// Tab dragged between tab bars
+#[derive(Debug, Clone, Copy)]
+struct TabDrag {
+    pane: PaneId,
+    index: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Pane {
+    pub tabs: Vec<BufferId>,
+    pub active: usize,
+}
+
+impl Pane {
+    /// Buffer of the selected tab
+    pub fn buffer(&self) -> Option<BufferId> {
+        self.tabs.get(self.active).copied()
+    }
+
+    fn select(&mut self, buffer: BufferId) {
+        match self.tabs.iter().position(|&tab| tab == buffer) {
+            Some(index) => self.active = index,
+            None => {
+                self.tabs.push(buffer);
+                self.active = self.tabs.len() - 1;
+            }
+        }
+    }
+
+    fn remove(&mut self, index: usize) -> Option<BufferId> {
+        if index >= self.tabs.len() {
+            return None;
+        }
+        let buffer = self.tabs.remove(index);
+        if self.active > index || self.active >= self.tabs.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+        Some(buffer)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Pane(PaneId),
+    Split {
+        direction: SplitDirection,
+        /// Share of the first side
+        fraction: f32,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+impl Node {
+    fn leaves(&self, out: &mut Vec<PaneId>) {
+        match self {
+            Node::Pane(pane) => out.push(*pane),
+            Node::Split { first, second, .. } => {
+                first.leaves(out);
+                second.leaves(out);
+            }
+        }
+    }
+
+    /// Replace the leaf `pane` with a split showing it and `new`
+    fn split(&mut self, pane: PaneId, direction: SplitDirection, new: PaneId) -> bool {
+        match self {
+            Node::Pane(leaf) if *leaf == pane => {
+                *self = Node::Split {
+                    direction,
+                    fraction: 0.5,
+                    first: Box::new(Node::Pane(pane)),
+                    second: Box::new(Node::Pane(new)),
+                };
+                true
+            }
+            Node::Pane(_) => false,
+            Node::Split { first, second, .. } => {
+                first.split(pane, direction, new) || second.split(pane, direction, new)
+            }
+        }
+    }
+
+    /// The tree without the leaf `pane`; its sibling takes the split's place
+    fn without(self, pane: PaneId) -> Option<Node> {
+        match self {
+            Node::Pane(leaf) if leaf == pane => None,
+            Node::Pane(_) => Some(self),
+            Node::Split {
+                direction,
+                fraction,
+                first,
+                second,
+            } => match (first.without(pane), second.without(pane)) {
+                (Some(first), Some(second)) => Some(Node::Split {
+                    direction,
+                    fraction,
+                    first: Box::new(first),
+                    second: Box::new(second),
+                }),
+                (Some(node), None) | (None, Some(node)) => Some(node),
+                (None, None) => None,
+            },
+        }
+    }
+}
+
+/// Panes of the main window and of detached windows
+#[derive(Debug, Clone)]
+pub struct Panes {
+    root: Node,
+    panes: BTreeMap<PaneId, Pane>,
+    /// Panes shown in windows of their own, outside the tree
+    windows: Vec<PaneId>,
+    focused: PaneId,
+    next_id: usize,
+}
+
+impl Panes {
+    /// A single pane showing `buffer`
+    pub fn new(buffer: BufferId) -> Self {
+        let pane = PaneId(0);
+        let mut panes = BTreeMap::new();
+        panes.insert(
+            pane,
+            Pane {
+                tabs: vec![buffer],
+                active: 0,
+            },
+        );
+        Self {
+            root: Node::Pane(pane),
+            panes,
+            windows: Vec::new(),
+            focused: pane,
+            next_id: 1,
+        }
+    }
+
+    pub fn focused(&self) -> PaneId {
+        self.focused
+    }
+
+    pub fn focused_buffer(&self) -> Option<BufferId> {
+        self.panes.get(&self.focused)?.buffer()
+    }
+
+    pub fn pane(&self, pane: PaneId) -> Option<&Pane> {
+        self.panes.get(&pane)
+    }
+
+    /// Panes of the main window, left to right and top to bottom
+    pub fn leaves(&self) -> Vec<PaneId> {
+        let mut leaves = Vec::new();
+        self.root.leaves(&mut leaves);
+        leaves
+    }
+
+    pub fn windows(&self) -> &[PaneId] {
+        &self.windows
+    }
+
+    /// Whether any pane or window has a tab for `buffer`
+    pub fn shows(&self, buffer: BufferId) -> bool {
+        self.panes.values().any(|pane| pane.tabs.contains(&buffer))
+    }
+
+    /// Show `buffer` in the focused pane, reusing its tab if it has one
+    pub fn show_buffer(&mut self, buffer: BufferId) {
+        if let Some(pane) = self.panes.get_mut(&self.focused) {
+            pane.select(buffer);
+        }
+    }
+
+    fn new_pane(&mut self, tabs: Vec<BufferId>) -> PaneId {
+        let id = PaneId(self.next_id);
+        self.next_id += 1;
+        let active = tabs.len().saturating_sub(1);
+        self.panes.insert(id, Pane { tabs, active });
+        id
+    }
+
+    /// Remove `pane`, handing its tabs to the focused pane, or to the first
+    /// one left when it was focused itself. The last main pane stays.
+    fn close_pane(&mut self, pane: PaneId) {
+        if self.windows.contains(&pane) {
+            self.windows.retain(|&window| window != pane);
+        } else {
+            let leaves = self.leaves();
+            if leaves.len() <= 1 || !leaves.contains(&pane) {
+                return;
+            }
+            let root = std::mem::replace(&mut self.root, Node::Pane(pane));
+            self.root = root.without(pane).expect("another pane is left");
+        }
+        let Some(closed) = self.panes.remove(&pane) else {
+            return;
+        };
+        if self.focused == pane {
+            self.focused = self.leaves()[0];
+        }
+        if let Some(target) = self.panes.get_mut(&self.focused) {
+            let selected = target.active;
+            for buffer in closed.tabs {
+                if !target.tabs.contains(&buffer) {
+                    target.tabs.push(buffer);
+                }
+            }
+            target.active = selected;
+        }
+    }
+
+    /// Close a pane left without tabs, unless it is the last main pane
+    fn close_if_empty(&mut self, pane: PaneId) {
+        if self.panes.get(&pane).is_some_and(|p| p.tabs.is_empty()) {
+            self.close_pane(pane);
+        }
+    }
+
+    /// Apply a layout change; the focused pane may change with it
+    pub fn apply(&mut self, action: PaneAction) {
+        match action {
+            PaneAction::Focus(pane) => {
+                if self.leaves().contains(&pane) {
+                    self.focused = pane;
+                }
+            }
+            PaneAction::SelectTab(pane, index) => {
+                if let Some(p) = self.panes.get_mut(&pane) {
+                    if index < p.tabs.len() {
+                        p.active = index;
+                    }
+                }
+                self.apply(PaneAction::Focus(pane));
+            }
+            PaneAction::CloseTab(pane, index) => {
+                if let Some(p) = self.panes.get_mut(&pane) {
+                    p.remove(index);
+                }
+                self.close_if_empty(pane);
+            }
+            PaneAction::CloseFocusedTab => {
+                let index = self.panes.get(&self.focused).map_or(0, |p| p.active);
+                self.apply(PaneAction::CloseTab(self.focused, index));
+            }
+            PaneAction::MoveTab { from, index, to } => {
+                if from == to || !self.panes.contains_key(&to) {
+                    return;
+                }
+                let Some(buffer) = self.panes.get_mut(&from).and_then(|p| p.remove(index)) else {
+                    return;
+                };
+                if let Some(target) = self.panes.get_mut(&to) {
+                    target.select(buffer);
+                }
+                self.close_if_empty(from);
+                self.apply(PaneAction::Focus(to));
+            }
+            PaneAction::Split(direction) => {
+                let Some(buffer) = self.focused_buffer() else {
+                    return;
+                };
+                let new = self.new_pane(vec![buffer]);
+                self.root.split(self.focused, direction, new);
+                self.focused = new;
+            }
+            PaneAction::ClosePane(pane) => self.close_pane(pane),
+            PaneAction::CloseFocusedPane => self.close_pane(self.focused),
+            PaneAction::Detach(pane, index) => {
+                // The main window keeps at least one tab
+                let last_tab = self.leaves() == [pane]
+                    && self.panes.get(&pane).is_some_and(|p| p.tabs.len() <= 1);
+                if last_tab || self.windows.contains(&pane) {
+                    return;
+                }
+                let Some(buffer) = self.panes.get_mut(&pane).and_then(|p| p.remove(index)) else {
+                    return;
+                };
+                let window = self.new_pane(vec![buffer]);
+                self.windows.push(window);
+                self.close_if_empty(pane);
+            }
+            PaneAction::DetachFocusedTab => {
+                let index = self.panes.get(&self.focused).map_or(0, |p| p.active);
+                self.apply(PaneAction::Detach(self.focused, index));
+            }
+        }
+    }
+
+    /// Lay the main panes out in `rect`, handling splitter drags
+    pub fn layout(&mut self, ui: &mut egui::Ui, rect: Rect) -> Vec<(PaneId, Rect)> {
+        let mut rects = Vec::new();
+        layout_node(
+            ui,
+            &mut self.root,
+            rect,
+            egui::Id::new("pane_splits"),
+            &mut rects,
+        );
+        rects
+    }
+}
+
+/// The two sides of a split and the splitter bar between them
+pub fn split_rect(rect: Rect, direction: SplitDirection, fraction: f32) -> (Rect, Rect, Rect) {
+    let half = SPLITTER_WIDTH / 2.0;
+    match direction {
+        SplitDirection::Right => {
+            let x = rect.left() + rect.width() * fraction;
+            (
+                Rect::from_x_y_ranges(rect.left()..=x - half, rect.y_range()),
+                Rect::from_x_y_ranges(x - half..=x + half, rect.y_range()),
+                Rect::from_x_y_ranges(x + half..=rect.right(), rect.y_range()),
+            )
+        }
+        SplitDirection::Down => {
+            let y = rect.top() + rect.height() * fraction;
+            (
+                Rect::from_x_y_ranges(rect.x_range(), rect.top()..=y - half),
+                Rect::from_x_y_ranges(rect.x_range(), y - half..=y + half),
+                Rect::from_x_y_ranges(rect.x_range(), y + half..=rect.bottom()),
+            )
+        }
+    }
+}
+
+fn layout_node(
+    ui: &mut egui::Ui,
+    node: &mut Node,
+    rect: Rect,
+    id: egui::Id,
+    rects: &mut Vec<(PaneId, Rect)>,
+) {
+    let (direction, fraction, first, second) = match node {
+        Node::Pane(pane) => {
+            rects.push((*pane, rect));
+            return;
+        }
+        Node::Split {
+            direction,
+            fraction,
+            first,
+            second,
+        } => (*direction, fraction, first, second),
+    };
+
+    let (_, bar, _) = split_rect(rect, direction, *fraction);
+    let response = ui.interact(bar, id, egui::Sense::drag());
+    if let Some(pos) = response
+        .interact_pointer_pos()
+        .filter(|_| response.dragged())
+    {
+        let share = match direction {
+            SplitDirection::Right => (pos.x - rect.left()) / rect.width(),
+            SplitDirection::Down => (pos.y - rect.top()) / rect.height(),
+        };
+        *fraction = share.clamp(MIN_FRACTION, 1.0 - MIN_FRACTION);
+    }
+    if response.hovered() || response.dragged() {
+        ui.ctx().set_cursor_icon(match direction {
+            SplitDirection::Right => egui::CursorIcon::ResizeColumn,
+            SplitDirection::Down => egui::CursorIcon::ResizeRow,
+        });
+    }
+
+    let (first_rect, bar, second_rect) = split_rect(rect, direction, *fraction);
+    let stroke = if response.hovered() || response.dragged() {
+        ui.visuals().widgets.active.bg_stroke
+    } else {
+        ui.visuals().widgets.noninteractive.bg_stroke
+    };
+    let line = match direction {
+        SplitDirection::Right => [bar.center_top(), bar.center_bottom()],
+        SplitDirection::Down => [bar.left_center(), bar.right_center()],
+    };
+    ui.painter().line_segment(line, stroke);
+
+    layout_node(ui, first, first_rect, id.with(0), rects);
+    layout_node(ui, second, second_rect, id.with(1), rects);
+}
+
+/// Editor widget id of a view that is not focused
+pub fn editor_id(pane: PaneId) -> egui::Id {
+    egui::Id::new(("pane_editor", pane))
+}
+
+/// Keep each view's cursor when the focus moves from `from` to `to`: the
+/// main editor's state goes to `from`, and `to`'s own state becomes the
+/// main editor's
+pub fn move_editor_focus(ctx: &egui::Context, from: PaneId, to: PaneId) {
+    let main = editor_cursor::main_editor_id();
+    let focused_state = egui::TextEdit::load_state(ctx, main);
+    let state = egui::TextEdit::load_state(ctx, editor_id(to)).unwrap_or_default();
+    if let Some(focused_state) = focused_state {
+        focused_state.store(ctx, editor_id(from));
+    }
+    state.store(ctx, main);
+    ctx.memory_mut(|memory| memory.request_focus(main));
+}
+
+/// Tab bar of `pane`, accepting tabs dragged from other panes; `title`
+/// names a buffer's tab
+pub fn tab_bar(
+    ui: &mut egui::Ui,
+    pane_id: PaneId,
+    pane: &Pane,
+    focused: bool,
+    detachable: bool,
+    title: impl Fn(BufferId) -> String,
+) -> Option<PaneAction> {
+    let mut action = None;
+    let frame = egui::Frame::default().inner_margin(egui::Margin::symmetric(4, 2));
+    let bar = frame.show(ui, |ui| {
+        egui::ScrollArea::horizontal()
+            .id_salt(("tab_bar", pane_id))
+            .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysHidden)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    for (index, &buffer) in pane.tabs.iter().enumerate() {
+                        let selected = index == pane.active;
+                        let mut text = egui::RichText::new(title(buffer));
+                        if selected && focused {
+                            text = text.strong();
+                        }
+                        let tab = ui.add(
+                            egui::Button::selectable(selected, text)
+                                .sense(egui::Sense::click_and_drag()),
+                        );
+                        tab.dnd_set_drag_payload(TabDrag {
+                            pane: pane_id,
+                            index,
+                        });
+                        if tab.clicked() {
+                            action = Some(PaneAction::SelectTab(pane_id, index));
+                        }
+                        if tab.middle_clicked() {
+                            action = Some(PaneAction::CloseTab(pane_id, index));
+                        }
+                        tab.context_menu(|ui| {
+                            if detachable && ui.button("Move to New Window").clicked() {
+                                action = Some(PaneAction::Detach(pane_id, index));
+                                ui.close();
+                            }
+                            if ui.button("Close").clicked() {
+                                action = Some(PaneAction::CloseTab(pane_id, index));
+                                ui.close();
+                            }
+                        });
+                        if ui.small_button("×").on_hover_text("Close").clicked() {
+                            action = Some(PaneAction::CloseTab(pane_id, index));
+                        }
+                        ui.add_space(6.0);
+                    }
+                });
+            });
+    });
+
+    // Tabs dropped anywhere on the bar move here
+    let response = bar.response;
+    if let Some(drag) = response.dnd_hover_payload::<TabDrag>() {
+        if drag.pane != pane_id {
+            ui.painter().rect_stroke(
+                response.rect,
+                2.0,
+                ui.visuals().selection.stroke,
+                egui::StrokeKind::Inside,
+            );
+        }
+    }
+    if let Some(drag) = response.dnd_release_payload::<TabDrag>() {
+        action = Some(PaneAction::MoveTab {
+            from: drag.pane,
+            index: drag.index,
+            to: pane_id,
+        });
+    }
+    action
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tabs(panes: &Panes, pane: PaneId) -> Vec<usize> {
+        panes.pane(pane).unwrap().tabs.iter().map(|b| b.0).collect()
+    }
+
+    #[test]
+    fn test_split_and_close() {
+        let mut panes = Panes::new(BufferId(1));
+        let first = panes.focused();
+        panes.apply(PaneAction::Split(SplitDirection::Right));
+        let second = panes.focused();
+        assert_ne!(first, second);
+        assert_eq!(panes.leaves(), vec![first, second]);
+        // Both sides show the same buffer
+        assert_eq!(tabs(&panes, first), tabs(&panes, second));
+
+        panes.apply(PaneAction::Split(SplitDirection::Down));
+        assert_eq!(panes.leaves().len(), 3);
+
+        panes.apply(PaneAction::ClosePane(second));
+        assert_eq!(panes.leaves().len(), 2);
+        assert!(panes.pane(second).is_none());
+
+        // The last pane stays
+        for pane in panes.leaves() {
+            panes.apply(PaneAction::ClosePane(pane));
+        }
+        assert_eq!(panes.leaves().len(), 1);
+    }
+
+    #[test]
+    fn test_tabs() {
+        let mut panes = Panes::new(BufferId(1));
+        let pane = panes.focused();
+        panes.show_buffer(BufferId(2));
+        panes.show_buffer(BufferId(3));
+        panes.show_buffer(BufferId(2));
+        assert_eq!(tabs(&panes, pane), vec![1, 2, 3]);
+        assert_eq!(panes.focused_buffer(), Some(BufferId(2)));
+
+        panes.apply(PaneAction::CloseTab(pane, 0));
+        assert_eq!(tabs(&panes, pane), vec![2, 3]);
+        assert_eq!(panes.focused_buffer(), Some(BufferId(2)));
+        panes.apply(PaneAction::CloseFocusedTab);
+        assert_eq!(panes.focused_buffer(), Some(BufferId(3)));
+        assert!(!panes.shows(BufferId(2)));
+    }
+
+    #[test]
+    fn test_move_tab_between_panes() {
+        let mut panes = Panes::new(BufferId(1));
+        let left = panes.focused();
+        panes.show_buffer(BufferId(2));
+        panes.apply(PaneAction::Split(SplitDirection::Right));
+        let right = panes.focused();
+        assert_eq!(tabs(&panes, right), vec![2]);
+
+        panes.apply(PaneAction::MoveTab {
+            from: left,
+            index: 0,
+            to: right,
+        });
+        assert_eq!(tabs(&panes, left), vec![2]);
+        assert_eq!(tabs(&panes, right), vec![2, 1]);
+        assert_eq!(panes.focused_buffer(), Some(BufferId(1)));
+
+        // Moving the last tab out closes the pane
+        panes.apply(PaneAction::MoveTab {
+            from: left,
+            index: 0,
+            to: right,
+        });
+        assert_eq!(panes.leaves(), vec![right]);
+        assert_eq!(tabs(&panes, right), vec![2, 1]);
+    }
+
+    #[test]
+    fn test_detach_and_reattach() {
+        let mut panes = Panes::new(BufferId(1));
+        let main = panes.focused();
+        // The only tab stays in the main window
+        panes.apply(PaneAction::DetachFocusedTab);
+        assert!(panes.windows().is_empty());
+
+        panes.show_buffer(BufferId(2));
+        panes.apply(PaneAction::DetachFocusedTab);
+        let window = panes.windows()[0];
+        assert_eq!(tabs(&panes, main), vec![1]);
+        assert_eq!(tabs(&panes, window), vec![2]);
+        assert_eq!(panes.focused(), main);
+
+        // Closing the window brings its tabs back
+        panes.apply(PaneAction::ClosePane(window));
+        assert!(panes.windows().is_empty());
+        assert_eq!(tabs(&panes, main), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_split_rect() {
+        let rect = Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(200.0, 100.0));
+        let (left, bar, right) = split_rect(rect, SplitDirection::Right, 0.25);
+        assert_eq!(bar.center().x, 50.0);
+        assert_eq!(left.right(), bar.left());
+        assert_eq!(right.left(), bar.right());
+        assert_eq!(right.right(), 200.0);
+        let (top, _, bottom) = split_rect(rect, SplitDirection::Down, 0.5);
+        assert_eq!(top.height(), bottom.height());
+    }
+}
@@ -1,5 +1,13 @@
 use eframe::egui;
-use std::path::PathBuf;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::core_engine::BufferId;
+use crate::document::Document;
+
+use super::document_view::{self, DocumentAction, ViewOptions};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PreviewMode {
@@ -27,112 +35,92 @@ pub fn detect_preview_mode(file_path: Option<&PathBuf>) -> PreviewMode {
 }
 
 /// Render HTML preview
-pub fn render_html_preview(ui: &mut egui::Ui, text: &str) {
+///
+/// `options.base_dir` is the directory of the previewed file, used to
+/// resolve relative image paths.
+pub fn render_html_preview(
+    ui: &mut egui::Ui,
+    text: &str,
+    options: ViewOptions,
+) -> Option<DocumentAction> {
     ui.heading("HTML Preview");
     ui.separator();
 
-    // Parse HTML and display as formatted text
-    use scraper::{Html, Selector};
-
-    let document = Html::parse_document(text);
-
-    // Extract and display title
-    if let Ok(selector) = Selector::parse("title") {
-        for element in document.select(&selector) {
-            ui.heading(element.text().collect::<String>());
-            ui.separator();
-        }
-    }
-
-    // Extract and display body content
-    if let Ok(selector) = Selector::parse("body") {
-        for element in document.select(&selector) {
-            let text = element.text().collect::<Vec<_>>().join(" ");
-            ui.label(text);
-        }
-    } else {
-        // If no body tag, just show all text
-        ui.label(text);
-    }
+    let doc = crate::document::html::parse(text);
+    document_view::render_document(ui, &doc, options)
 }
 
 /// Render LaTeX preview
-pub fn render_latex_preview(ui: &mut egui::Ui, text: &str) {
+///
+/// `\input` files and `.bib` databases are read from `options.base_dir`.
+/// The parsed document is kept per buffer for a second so those files are
+/// not read on every frame.
+pub fn render_latex_preview(
+    ui: &mut egui::Ui,
+    text: &str,
+    buffer: Option<BufferId>,
+    options: ViewOptions,
+) -> Option<DocumentAction> {
     ui.heading("LaTeX Preview");
     ui.separator();
 
-    // Simple LaTeX rendering with Unicode substitution
-    let mut preview_text = text.to_string();
+    let doc = cached_latex(ui.ctx(), text, buffer, options.base_dir);
+    document_view::render_document(ui, &doc, options)
+}
 
-    // Common LaTeX symbols to Unicode
-    let substitutions = vec![
-        (r"\alpha", "α"),
-        (r"\beta", "β"),
-        (r"\gamma", "γ"),
-        (r"\delta", "δ"),
-        (r"\epsilon", "ε"),
-        (r"\theta", "θ"),
-        (r"\lambda", "λ"),
-        (r"\mu", "μ"),
-        (r"\pi", "π"),
-        (r"\sigma", "σ"),
-        (r"\phi", "φ"),
-        (r"\omega", "ω"),
-        (r"\sqrt", "√"),
-        (r"\int", "∫"),
-        (r"\sum", "∑"),
-        (r"\prod", "∏"),
-        (r"\partial", "∂"),
-        (r"\nabla", "∇"),
-        (r"\infty", "∞"),
-        (r"\pm", "±"),
-        (r"\times", "×"),
-        (r"\div", "÷"),
-        (r"\leq", "≤"),
-        (r"\geq", "≥"),
-        (r"\neq", "≠"),
-        (r"\approx", "≈"),
-    ];
+/// Parsed document of a buffer with the hash of the text it came from
+#[derive(Clone)]
+struct CachedLatex {
+    key: u64,
+    parsed_at: f64,
+    doc: Arc<Document>,
+}
 
-    for (latex, unicode) in substitutions {
-        preview_text = preview_text.replace(latex, unicode);
+fn cached_latex(
+    ctx: &egui::Context,
+    text: &str,
+    buffer: Option<BufferId>,
+    base_dir: Option<&Path>,
+) -> Arc<Document> {
+    const MAX_AGE: f64 = 1.0;
+
+    let id = egui::Id::new(("latex_document", buffer));
+    let mut hasher = DefaultHasher::new();
+    (text, base_dir).hash(&mut hasher);
+    let key = hasher.finish();
+    let now = ctx.input(|input| input.time);
+    let cached = ctx.data(|data| data.get_temp::<CachedLatex>(id));
+    if let Some(cached) =
+        cached.filter(|cached| cached.key == key && now - cached.parsed_at < MAX_AGE)
+    {
+        return cached.doc;
     }
 
-    ui.label(&preview_text);
+    let doc = Arc::new(crate::document::latex::parse_with_base(text, base_dir));
+    ctx.data_mut(|data| {
+        data.insert_temp(
+            id,
+            CachedLatex {
+                key,
+                parsed_at: now,
+                doc: doc.clone(),
+            },
+        )
+    });
+    // Pick up changes to included files while the preview is open
+    ctx.request_repaint_after(std::time::Duration::from_secs_f64(MAX_AGE));
+    doc
 }
 
 /// Render Mermaid preview
-pub fn render_mermaid_preview(ui: &mut egui::Ui, text: &str) {
+pub fn render_mermaid_preview(
+    ui: &mut egui::Ui,
+    text: &str,
+    options: ViewOptions,
+) -> Option<DocumentAction> {
     ui.heading("Mermaid Diagram");
     ui.separator();
 
-    // Simple ASCII art rendering
-    ui.label("Diagram Preview:");
-    ui.separator();
-
-    let lines: Vec<&str> = text.lines().collect();
-
-    for line in lines {
-        let trimmed = line.trim();
-        if trimmed.is_empty()
-            || trimmed.starts_with("graph")
-            || trimmed.starts_with("flowchart")
-        {
-            continue;
-        }
-
-        // Simple node rendering
-        if trimmed.contains("-->") || trimmed.contains("->") {
-            let parts: Vec<&str> = trimmed.split("-->").collect();
-            if parts.len() == 2 {
-                ui.horizontal(|ui| {
-                    ui.monospace(format!("[{}]", parts[0].trim()));
-                    ui.label("→");
-                    ui.monospace(format!("[{}]", parts[1].trim()));
-                });
-            }
-        } else {
-            ui.monospace(trimmed);
-        }
-    }
+    let doc = crate::document::mermaid::parse(text);
+    document_view::render_document(ui, &doc, options)
 }
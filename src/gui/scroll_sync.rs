@@ -0,0 +1,93 @@
+//! Editor ↔ preview scroll synchronization
+//!
+//! The editor reports its position (cursor and first visible character)
+//! after every frame. When either changes, the preview is asked to reveal
+//! the matching source offset through the document's source map. Clicks
+//! in the preview go the other way and schedule an editor jump.
+//!
+//! All offsets are byte offsets into the editor text.
+
+#[derive(Debug, Default)]
+pub struct ScrollSync {
+    cursor: Option<usize>,
+    top: Option<usize>,
+    /// Offset for the preview to reveal on its next frame
+    reveal: Option<usize>,
+    /// Offset for the editor to scroll to on its next frame
+    jump: Option<usize>,
+}
+
+impl ScrollSync {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record where the editor is after drawing it
+    ///
+    /// A moved cursor wins over scrolling, so typing keeps the preview on
+    /// the paragraph being edited.
+    pub fn editor_moved(&mut self, cursor: Option<usize>, top: usize) {
+        if cursor.is_some() && cursor != self.cursor {
+            self.reveal = cursor;
+        } else if Some(top) != self.top {
+            self.reveal = Some(top);
+        }
+        self.cursor = cursor;
+        self.top = Some(top);
+    }
+
+    /// Offset the preview should scroll to, if the editor moved
+    pub fn take_reveal(&mut self) -> Option<usize> {
+        self.reveal.take()
+    }
+
+    /// Ask the editor to scroll to `offset` (a preview block was clicked)
+    pub fn jump_to(&mut self, offset: usize) {
+        self.jump = Some(offset);
+    }
+
+    /// Offset the editor should scroll to, if a jump is pending
+    pub fn take_jump(&mut self) -> Option<usize> {
+        self.jump.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reveal_only_when_editor_moves() {
+        let mut sync = ScrollSync::new();
+        sync.editor_moved(None, 0);
+        assert_eq!(sync.take_reveal(), Some(0));
+
+        sync.editor_moved(None, 0);
+        assert_eq!(sync.take_reveal(), None);
+
+        sync.editor_moved(None, 120);
+        assert_eq!(sync.take_reveal(), Some(120));
+    }
+
+    #[test]
+    fn test_cursor_wins_over_scroll() {
+        let mut sync = ScrollSync::new();
+        sync.editor_moved(Some(40), 0);
+        assert_eq!(sync.take_reveal(), Some(40));
+
+        // Scrolling without moving the cursor follows the viewport
+        sync.editor_moved(Some(40), 80);
+        assert_eq!(sync.take_reveal(), Some(80));
+
+        sync.editor_moved(Some(50), 90);
+        assert_eq!(sync.take_reveal(), Some(50));
+    }
+
+    #[test]
+    fn test_jump_is_taken_once() {
+        let mut sync = ScrollSync::new();
+        sync.jump_to(7);
+        assert_eq!(sync.take_jump(), Some(7));
+        assert_eq!(sync.take_jump(), None);
+    }
+}
@@ -14,6 +14,8 @@ pub struct SearchPanel {
     // Results
     current_results: Vec<SearchResult>,
     current_match_index: usize,
+    /// The selected match changed and should be shown in the editor
+    reveal: bool,
 
     // Error state
     error_message: Option<String>,
@@ -29,6 +31,7 @@ impl SearchPanel {
             replace_mode: false,
             current_results: Vec::new(),
             current_match_index: 0,
+            reveal: false,
             error_message: None,
         }
     }
@@ -37,6 +40,34 @@ impl SearchPanel {
         self.replace_mode = enabled;
     }
 
+    /// Lines of all matches, for the overview ruler
+    pub fn match_lines(&self) -> Vec<usize> {
+        self.current_results
+            .iter()
+            .map(|result| result.range.start.line)
+            .collect()
+    }
+
+    pub fn current_match(&self) -> Option<&SearchResult> {
+        self.current_results.get(self.current_match_index)
+    }
+
+    /// The match to select in the editor, once after it changes
+    pub fn take_reveal(&mut self) -> Option<&SearchResult> {
+        if !std::mem::take(&mut self.reveal) {
+            return None;
+        }
+        self.current_match()
+    }
+
+    /// Search again after the text was edited, keeping the selected match
+    pub fn refresh(&mut self, buffer: &Buffer) {
+        let index = self.current_match_index;
+        self.perform_search(buffer);
+        self.current_match_index = index.min(self.current_results.len().saturating_sub(1));
+        self.reveal = false;
+    }
+
     pub fn show(&mut self, ctx: &egui::Context, buffer: &mut Buffer, open: &mut bool) {
         egui::Window::new(if self.replace_mode {
             "Replace"
@@ -169,6 +200,7 @@ impl SearchPanel {
             Ok(results) => {
                 self.current_results = results;
                 self.current_match_index = 0;
+                self.reveal = true;
                 self.error_message = None;
             }
             Err(e) => {
@@ -181,6 +213,7 @@ impl SearchPanel {
     fn go_to_next(&mut self) {
         if !self.current_results.is_empty() {
             self.current_match_index = (self.current_match_index + 1) % self.current_results.len();
+            self.reveal = true;
         }
     }
 
@@ -191,6 +224,7 @@ impl SearchPanel {
             } else {
                 self.current_match_index -= 1;
             }
+            self.reveal = true;
         }
     }
 
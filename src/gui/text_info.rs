@@ -0,0 +1,175 @@
+//! Facts about the edited text shown in the status bar: cursor position,
+//! selection, encoding, line endings and indentation
+
+use std::fmt;
+use std::ops::Range;
+
+/// Cursor position for the status bar, 1-based like other editors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CursorInfo {
+    pub line: usize,
+    pub column: usize,
+    /// Selected characters, 0 without a selection
+    pub selected: usize,
+}
+
+impl CursorInfo {
+    /// Position of the end of a char `selection` in `text`
+    pub fn new(text: &str, selection: Range<usize>) -> Self {
+        let (mut line, mut column) = (1, 1);
+        for c in text.chars().take(selection.end) {
+            if c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        Self {
+            line,
+            column,
+            selected: selection.len(),
+        }
+    }
+}
+
+impl fmt::Display for CursorInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Ln {}, Col {}", self.line, self.column)?;
+        if self.selected > 0 {
+            write!(f, " ({} selected)", self.selected)?;
+        }
+        Ok(())
+    }
+}
+
+/// Files are read as UTF-8; a byte order mark survives in the text
+pub fn encoding(text: &str) -> &'static str {
+    if text.starts_with('\u{feff}') {
+        "UTF-8 with BOM"
+    } else {
+        "UTF-8"
+    }
+}
+
+/// `LF`, `CRLF` or `Mixed`; texts without line breaks count as `LF`
+pub fn line_ending(text: &str) -> &'static str {
+    let lines = text.matches('\n').count();
+    let crlf = text.matches("\r\n").count();
+    match crlf {
+        0 => "LF",
+        n if n == lines => "CRLF",
+        _ => "Mixed",
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indentation {
+    Tabs,
+    Spaces(usize),
+}
+
+impl Indentation {
+    /// Guess from the indented lines: tabs if most use them, otherwise the
+    /// most common step between consecutive indentation levels
+    pub fn detect(text: &str) -> Self {
+        let mut tabs = 0;
+        let mut spaces = 0;
+        let mut steps = [0usize; 9];
+        let mut previous = 0;
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            if line.starts_with('\t') {
+                tabs += 1;
+                continue;
+            }
+            let indent = line.len() - line.trim_start_matches(' ').len();
+            if indent > 0 {
+                spaces += 1;
+            }
+            let step = indent.abs_diff(previous);
+            if (1..steps.len()).contains(&step) {
+                steps[step] += 1;
+            }
+            previous = indent;
+        }
+
+        if tabs > spaces {
+            return Self::Tabs;
+        }
+        // Ties go to the wider step: 4-space files often contain 2-space
+        // alignment
+        let width = (1..steps.len())
+            .filter(|&step| steps[step] > 0)
+            .max_by_key(|&step| (steps[step], step))
+            .unwrap_or(4);
+        Self::Spaces(width)
+    }
+
+    /// Text inserted for one indentation level
+    pub fn unit(&self) -> String {
+        match self {
+            Self::Tabs => "\t".to_string(),
+            Self::Spaces(width) => " ".repeat(*width),
+        }
+    }
+
+    /// Columns in one indentation level, tabs counting as four
+    pub fn width(&self) -> usize {
+        match self {
+            Self::Tabs => 4,
+            Self::Spaces(width) => *width,
+        }
+    }
+}
+
+impl fmt::Display for Indentation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tabs => write!(f, "Tabs"),
+            Self::Spaces(width) => write!(f, "Spaces: {width}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_info() {
+        let text = "ab\ncdé\nf";
+        assert_eq!(CursorInfo::new(text, 0..0).to_string(), "Ln 1, Col 1");
+        assert_eq!(CursorInfo::new(text, 6..6).to_string(), "Ln 2, Col 4");
+        assert_eq!(
+            CursorInfo::new(text, 1..7).to_string(),
+            "Ln 3, Col 1 (6 selected)"
+        );
+    }
+
+    #[test]
+    fn test_encoding_and_line_endings() {
+        assert_eq!(encoding("\u{feff}text"), "UTF-8 with BOM");
+        assert_eq!(encoding("text"), "UTF-8");
+        assert_eq!(line_ending("one line"), "LF");
+        assert_eq!(line_ending("a\nb\n"), "LF");
+        assert_eq!(line_ending("a\r\nb\r\n"), "CRLF");
+        assert_eq!(line_ending("a\r\nb\n"), "Mixed");
+    }
+
+    #[test]
+    fn test_indentation() {
+        assert_eq!(
+            Indentation::detect("fn a() {\n\tb();\n}\n"),
+            Indentation::Tabs
+        );
+        assert_eq!(
+            Indentation::detect("a:\n  b:\n    c: 1\n  d: 2\n"),
+            Indentation::Spaces(2)
+        );
+        assert_eq!(
+            Indentation::detect("fn a() {\n    if b {\n        c();\n    }\n}\n"),
+            Indentation::Spaces(4)
+        );
+        assert_eq!(Indentation::detect("plain\ntext\n"), Indentation::Spaces(4));
+    }
+}
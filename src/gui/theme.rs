@@ -1,4 +1,48 @@
-use eframe::egui;
+//! Named themes bundling UI visuals with a syntax highlighting theme.
+//!
+//! Besides the built-in "Lala Dark" and "Lala Light", every syntect theme,
+//! bundled or a user `.tmTheme` from `lala/themes`, can be selected as a UI
+//! theme: its background, foreground and selection colors are carried over
+//! to the egui visuals. The choice and any color overrides are stored in
+//! `<config_dir>/lala/theme.json`:
+//!
+//! ```json
+//! {
+//!   "theme": "Solarized (dark)",
+//!   "follow_system": true,
+//!   "light_theme": "Lala Light",
+//!   "dark_theme": "Lala Dark",
+//!   "colors": { "selection": "#3a5f8f", "editor_background": "#1e1e1e" }
+//! }
+//! ```
+
+use eframe::egui::{self, Color32};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use syntect::highlighting::Theme;
+
+use super::highlighting::{user_config_dir, SyntaxHighlighter, FALLBACK_THEME};
+
+/// Built-in dark theme: egui's dark visuals with base16-ocean.dark
+pub const LALA_DARK: &str = "Lala Dark";
+/// Built-in light theme: [`custom_light_theme`] with InspiredGitHub
+pub const LALA_LIGHT: &str = "Lala Light";
+
+/// Keys accepted in the `colors` table of the theme settings
+pub const COLOR_KEYS: &[&str] = &[
+    "window",
+    "panel",
+    "faint_background",
+    "extreme_background",
+    "code_background",
+    "selection",
+    "hyperlink",
+    "text",
+    "editor_background",
+    "editor_text",
+];
 
 /// Creates a custom light theme with improved visibility
 pub fn custom_light_theme() -> egui::Visuals {
@@ -34,3 +78,353 @@ pub fn custom_light_theme() -> egui::Visuals {
 
     visuals
 }
+
+/// Theme choice persisted in `theme.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeSettings {
+    /// Theme used when not following the system
+    pub theme: String,
+    /// Pick `light_theme` or `dark_theme` from the OS preference
+    pub follow_system: bool,
+    pub light_theme: String,
+    pub dark_theme: String,
+    /// Color overrides, `key -> "#rrggbb"`, see [`COLOR_KEYS`]
+    pub colors: BTreeMap<String, String>,
+}
+
+impl Default for ThemeSettings {
+    fn default() -> Self {
+        Self {
+            theme: LALA_DARK.to_string(),
+            follow_system: false,
+            light_theme: LALA_LIGHT.to_string(),
+            dark_theme: LALA_DARK.to_string(),
+            colors: BTreeMap::new(),
+        }
+    }
+}
+
+impl ThemeSettings {
+    /// Name of the theme in effect given the OS preference
+    pub fn selected(&self, system: Option<egui::Theme>) -> &str {
+        match (self.follow_system, system) {
+            (true, Some(egui::Theme::Light)) => &self.light_theme,
+            (true, Some(egui::Theme::Dark)) => &self.dark_theme,
+            _ => &self.theme,
+        }
+    }
+
+    /// Choose `name`; when following the system it replaces the theme for
+    /// the current appearance
+    pub fn select(&mut self, name: &str, system: Option<egui::Theme>) {
+        let slot = match (self.follow_system, system) {
+            (true, Some(egui::Theme::Light)) => &mut self.light_theme,
+            (true, Some(egui::Theme::Dark)) => &mut self.dark_theme,
+            _ => &mut self.theme,
+        };
+        *slot = name.to_string();
+    }
+
+    /// Load settings from a JSON file
+    pub fn load_from(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        serde_json::from_str(&json).map_err(|e| format!("Failed to parse theme settings: {e}"))
+    }
+
+    /// Save settings as JSON, creating parent directories
+    pub fn save_to(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize theme settings: {e}"))?;
+        std::fs::write(path, json).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+
+    /// Load the user's settings, or the defaults
+    pub fn load() -> Self {
+        match settings_path() {
+            Some(path) if path.exists() => Self::load_from(&path).unwrap_or_else(|err| {
+                eprintln!("{err}");
+                Self::default()
+            }),
+            _ => Self::default(),
+        }
+    }
+
+    /// Save the user's settings
+    pub fn save(&self) -> Result<(), String> {
+        let path =
+            settings_path().ok_or_else(|| "No configuration directory available".to_string())?;
+        self.save_to(&path)
+    }
+}
+
+/// Location of `theme.json`
+pub fn settings_path() -> Option<PathBuf> {
+    user_config_dir("theme.json")
+}
+
+/// A resolved theme, ready to apply
+#[derive(Debug, Clone)]
+pub struct ActiveTheme {
+    /// Syntect theme name for the editor and code blocks
+    pub syntax: String,
+    pub visuals: egui::Visuals,
+    pub editor_background: Color32,
+    pub editor_text: Color32,
+}
+
+impl ActiveTheme {
+    /// Build the theme called `name`, falling back to [`LALA_DARK`]
+    pub fn named(name: &str, highlighter: &SyntaxHighlighter) -> Self {
+        match name {
+            LALA_LIGHT => Self {
+                syntax: "InspiredGitHub".to_string(),
+                visuals: custom_light_theme(),
+                editor_background: Color32::WHITE,
+                editor_text: Color32::BLACK,
+            },
+            _ if highlighter.theme_names().contains(&name) => {
+                Self::from_syntax_theme(name, highlighter.theme(name))
+            }
+            _ => Self {
+                syntax: FALLBACK_THEME.to_string(),
+                visuals: egui::Visuals::dark(),
+                editor_background: Color32::from_rgb(30, 30, 30),
+                editor_text: Color32::WHITE,
+            },
+        }
+    }
+
+    /// UI visuals derived from a syntect theme's colors
+    fn from_syntax_theme(name: &str, theme: &Theme) -> Self {
+        let settings = &theme.settings;
+        let color = |c: syntect::highlighting::Color| Color32::from_rgb(c.r, c.g, c.b);
+        let background = settings.background.map(color);
+        let dark = background.is_none_or(|bg| luminance(bg) < 0.5);
+        let mut visuals = if dark {
+            egui::Visuals::dark()
+        } else {
+            custom_light_theme()
+        };
+        let background = background.unwrap_or(if dark {
+            Color32::from_rgb(30, 30, 30)
+        } else {
+            Color32::WHITE
+        });
+        let text = settings.foreground.map(color).unwrap_or(if dark {
+            Color32::WHITE
+        } else {
+            Color32::BLACK
+        });
+
+        // Panels sit slightly apart from the editor background
+        let shade = |amount: f32| {
+            let target = if dark { Color32::BLACK } else { Color32::WHITE };
+            background.lerp_to_gamma(target, amount)
+        };
+        visuals.panel_fill = shade(0.2);
+        visuals.window_fill = shade(0.1);
+        visuals.faint_bg_color = background;
+        visuals.extreme_bg_color = shade(0.3);
+        visuals.code_bg_color = background;
+        visuals.override_text_color = Some(text);
+        if let Some(selection) = settings.selection.map(color) {
+            visuals.selection.bg_fill = selection;
+        }
+
+        Self {
+            syntax: name.to_string(),
+            visuals,
+            editor_background: background,
+            editor_text: text,
+        }
+    }
+
+    /// Apply the `colors` overrides from the settings
+    pub fn apply_overrides(&mut self, colors: &BTreeMap<String, String>) -> Result<(), String> {
+        for (key, value) in colors {
+            let color = Color32::from_hex(value)
+                .map_err(|_| format!("Invalid color for {key}: {value}"))?;
+            let visuals = &mut self.visuals;
+            match key.as_str() {
+                "window" => visuals.window_fill = color,
+                "panel" => visuals.panel_fill = color,
+                "faint_background" => visuals.faint_bg_color = color,
+                "extreme_background" => visuals.extreme_bg_color = color,
+                "code_background" => visuals.code_bg_color = color,
+                "selection" => visuals.selection.bg_fill = color,
+                "hyperlink" => visuals.hyperlink_color = color,
+                "text" => visuals.override_text_color = Some(color),
+                "editor_background" => self.editor_background = color,
+                "editor_text" => self.editor_text = color,
+                _ => {
+                    return Err(format!(
+                        "Unknown theme color: {key} (expected one of {})",
+                        COLOR_KEYS.join(", ")
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Resolve the settings into the theme to apply; invalid overrides are
+/// reported alongside the theme without them
+pub fn resolve(
+    settings: &ThemeSettings,
+    system: Option<egui::Theme>,
+    highlighter: &SyntaxHighlighter,
+) -> (ActiveTheme, Option<String>) {
+    let theme = ActiveTheme::named(settings.selected(system), highlighter);
+    let mut overridden = theme.clone();
+    match overridden.apply_overrides(&settings.colors) {
+        Ok(()) => (overridden, None),
+        Err(err) => (theme, Some(err)),
+    }
+}
+
+/// All selectable theme names: the built-in ones, then the syntect themes
+pub fn theme_names(highlighter: &SyntaxHighlighter) -> Vec<String> {
+    [LALA_DARK, LALA_LIGHT]
+        .into_iter()
+        .chain(highlighter.theme_names())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Share the active syntax theme with code blocks in previews
+pub fn set_syntax_theme(ctx: &egui::Context, theme: Arc<Theme>) {
+    ctx.data_mut(|data| data.insert_temp(syntax_theme_id(), theme));
+}
+
+/// The syntax theme set by [`set_syntax_theme`]
+pub fn syntax_theme(ctx: &egui::Context) -> Option<Arc<Theme>> {
+    ctx.data(|data| data.get_temp(syntax_theme_id()))
+}
+
+fn syntax_theme_id() -> egui::Id {
+    egui::Id::new("lala_syntax_theme")
+}
+
+/// Relative luminance in 0..=1
+fn luminance(color: Color32) -> f32 {
+    let [r, g, b, _] = color.to_array();
+    (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) / 255.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlighter() -> SyntaxHighlighter {
+        SyntaxHighlighter::with_dirs(None, None)
+    }
+
+    #[test]
+    fn test_theme_names_include_syntect_defaults() {
+        let names = theme_names(&highlighter());
+        for name in [
+            LALA_DARK,
+            LALA_LIGHT,
+            "base16-ocean.dark",
+            "base16-eighties.dark",
+            "base16-mocha.dark",
+            "base16-ocean.light",
+            "InspiredGitHub",
+            "Solarized (dark)",
+            "Solarized (light)",
+        ] {
+            assert!(names.iter().any(|n| n == name), "missing {name}");
+        }
+    }
+
+    #[test]
+    fn test_builtin_and_syntect_themes() {
+        let highlighter = highlighter();
+        let light = ActiveTheme::named(LALA_LIGHT, &highlighter);
+        assert_eq!(light.syntax, "InspiredGitHub");
+        assert!(!light.visuals.dark_mode);
+
+        let solarized = ActiveTheme::named("Solarized (light)", &highlighter);
+        assert_eq!(solarized.syntax, "Solarized (light)");
+        assert!(!solarized.visuals.dark_mode);
+        assert_eq!(
+            solarized.editor_background,
+            Color32::from_rgb(0xfd, 0xf6, 0xe3)
+        );
+
+        let unknown = ActiveTheme::named("No Such Theme", &highlighter);
+        assert_eq!(unknown.syntax, FALLBACK_THEME);
+        assert!(unknown.visuals.dark_mode);
+    }
+
+    #[test]
+    fn test_follow_system() {
+        let mut settings = ThemeSettings {
+            follow_system: true,
+            ..Default::default()
+        };
+        assert_eq!(settings.selected(Some(egui::Theme::Light)), LALA_LIGHT);
+        assert_eq!(settings.selected(Some(egui::Theme::Dark)), LALA_DARK);
+        // Unknown preference falls back to the fixed choice
+        assert_eq!(settings.selected(None), LALA_DARK);
+
+        settings.select("Solarized (light)", Some(egui::Theme::Light));
+        assert_eq!(settings.light_theme, "Solarized (light)");
+        assert_eq!(settings.theme, LALA_DARK);
+
+        settings.follow_system = false;
+        settings.select("InspiredGitHub", Some(egui::Theme::Light));
+        assert_eq!(settings.theme, "InspiredGitHub");
+    }
+
+    #[test]
+    fn test_color_overrides() {
+        let highlighter = highlighter();
+        let mut settings = ThemeSettings::default();
+        settings
+            .colors
+            .insert("editor_background".to_string(), "#102030".to_string());
+        settings
+            .colors
+            .insert("selection".to_string(), "#ff0000".to_string());
+        let (theme, error) = resolve(&settings, None, &highlighter);
+        assert_eq!(error, None);
+        assert_eq!(theme.editor_background, Color32::from_rgb(0x10, 0x20, 0x30));
+        assert_eq!(theme.visuals.selection.bg_fill, Color32::RED);
+
+        settings
+            .colors
+            .insert("panel".to_string(), "not a color".to_string());
+        let (theme, error) = resolve(&settings, None, &highlighter);
+        assert!(error.unwrap().contains("panel"));
+        assert_eq!(theme.editor_background, Color32::from_rgb(30, 30, 30));
+    }
+
+    #[test]
+    fn test_settings_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lala").join("theme.json");
+        let mut settings = ThemeSettings {
+            theme: "Solarized (dark)".to_string(),
+            ..Default::default()
+        };
+        settings
+            .colors
+            .insert("hyperlink".to_string(), "#00aaff".to_string());
+        settings.save_to(&path).unwrap();
+        assert_eq!(ThemeSettings::load_from(&path).unwrap(), settings);
+
+        // Missing fields take their defaults
+        std::fs::write(&path, r#"{"follow_system": true}"#).unwrap();
+        let loaded = ThemeSettings::load_from(&path).unwrap();
+        assert!(loaded.follow_system);
+        assert_eq!(loaded.theme, LALA_DARK);
+    }
+}
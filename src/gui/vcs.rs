@@ -0,0 +1,204 @@
+//! Line changes against the last git commit, shown as gutter markers
+//!
+//! The committed version of a file is read once with `git show HEAD:./file`
+//! and compared line by line with the editor text whenever it changes.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::Path;
+use std::process::Command;
+
+/// Above this many compared line pairs, a changed region is marked as a
+/// whole instead of being aligned line by line
+const MAX_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineChange {
+    Added,
+    Modified,
+    /// Lines were deleted just above this one
+    Removed,
+}
+
+/// Contents of `path` in the `HEAD` commit, if it is tracked by git
+///
+/// Relative paths, such as a bare file name from the command line, are
+/// resolved against the current directory.
+pub fn head_text(path: &Path) -> Option<String> {
+    head_text_from(&std::env::current_dir().ok()?, path)
+}
+
+/// [`head_text`] with relative paths resolved against `cwd`
+fn head_text_from(cwd: &Path, path: &Path) -> Option<String> {
+    let path = cwd.join(path);
+    let dir = path.parent()?;
+    let name = path.file_name()?.to_str()?;
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(format!("HEAD:./{name}"))
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Changed lines of `text` compared with `base`, by 0-based line
+pub fn line_changes(base: &str, text: &str) -> BTreeMap<usize, LineChange> {
+    let old: Vec<&str> = base.lines().collect();
+    let new: Vec<&str> = text.lines().collect();
+
+    // Edits are usually local: only align what lies between the common
+    // prefix and suffix
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut changes = BTreeMap::new();
+    for (removed, inserted) in hunks(old_middle, new_middle) {
+        let start = prefix + inserted.start;
+        if inserted.is_empty() {
+            if removed > 0 {
+                let line = start.min(new.len().saturating_sub(1));
+                changes.entry(line).or_insert(LineChange::Removed);
+            }
+            continue;
+        }
+        for (k, line) in (start..prefix + inserted.end).enumerate() {
+            let change = if k < removed {
+                LineChange::Modified
+            } else {
+                LineChange::Added
+            };
+            changes.insert(line, change);
+        }
+    }
+    changes
+}
+
+/// Differing runs as (removed line count, inserted range in `new`), from a
+/// longest common subsequence alignment
+fn hunks(old: &[&str], new: &[&str]) -> Vec<(usize, Range<usize>)> {
+    if old.is_empty() && new.is_empty() {
+        return Vec::new();
+    }
+    if old.len() * new.len() > MAX_CELLS {
+        return vec![(old.len(), 0..new.len())];
+    }
+
+    // lcs[i][j]: common subsequence length of old[i..] and new[j..]
+    let width = new.len() + 1;
+    let mut lcs = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i * width + j] = if old[i] == new[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut hunks = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let mut removed = 0;
+    let mut inserted = 0..0;
+    let mut flush = |removed: &mut usize, inserted: &mut Range<usize>, j: usize| {
+        if *removed > 0 || !Range::is_empty(inserted) {
+            hunks.push((*removed, inserted.clone()));
+        }
+        *removed = 0;
+        *inserted = j..j;
+    };
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+            flush(&mut removed, &mut inserted, j);
+        } else if j == new.len()
+            || (i < old.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+        {
+            removed += 1;
+            i += 1;
+        } else {
+            j += 1;
+            inserted.end = j;
+        }
+    }
+    flush(&mut removed, &mut inserted, j);
+    hunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(base: &str, text: &str) -> Vec<(usize, LineChange)> {
+        line_changes(base, text).into_iter().collect()
+    }
+
+    #[test]
+    fn test_head_text_of_bare_file_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(["-c", "user.name=lala", "-c", "user.email=lala@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {args:?}");
+        };
+        git(&["init", "-q"]);
+        std::fs::write(dir.path().join("README.md"), "committed\n").unwrap();
+        git(&["add", "README.md"]);
+        git(&["commit", "-q", "--no-gpg-sign", "-m", "init"]);
+        std::fs::write(dir.path().join("README.md"), "edited\n").unwrap();
+
+        assert_eq!(
+            head_text_from(dir.path(), Path::new("README.md")).as_deref(),
+            Some("committed\n")
+        );
+        assert_eq!(head_text_from(dir.path(), Path::new("missing.md")), None);
+    }
+
+    #[test]
+    fn test_unchanged() {
+        assert!(changes("a\nb\n", "a\nb\n").is_empty());
+    }
+
+    #[test]
+    fn test_added_modified_removed() {
+        use LineChange::*;
+        assert_eq!(changes("a\nb\nc\n", "a\nx\nb\nc\n"), vec![(1, Added)]);
+        assert_eq!(changes("a\nb\nc\n", "a\nB\nc\n"), vec![(1, Modified)]);
+        assert_eq!(changes("a\nb\nc\n", "a\nc\n"), vec![(1, Removed)]);
+        // Deleting the last lines marks the new last line
+        assert_eq!(changes("a\nb\nc\n", "a\n"), vec![(0, Removed)]);
+        // A replaced line followed by an extra one
+        assert_eq!(
+            changes("a\nb\nc\n", "a\nB\nB2\nc\n"),
+            vec![(1, Modified), (2, Added)]
+        );
+    }
+
+    #[test]
+    fn test_separate_hunks() {
+        use LineChange::*;
+        assert_eq!(
+            changes("1\n2\n3\n4\n5\n6\n", "1\nX\n3\n4\n6\n7\n"),
+            vec![(1, Modified), (4, Removed), (5, Added)]
+        );
+    }
+}
@@ -0,0 +1,396 @@
+//! BibTeX reader
+//!
+//! Reads the entries of a `.bib` file for `\cite`. Field values are kept
+//! as LaTeX source (braces included) and converted by the caller; `@string`
+//! abbreviations and `#` concatenation are expanded here, while
+//! `@comment` and `@preamble` are skipped.
+
+use std::collections::HashMap;
+
+/// A bibliography entry such as `@article{key, ...}`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Entry {
+    /// Entry type in lower case (`article`, `book`, ...)
+    pub kind: String,
+    pub key: String,
+    /// Field names in lower case, in source order
+    pub fields: Vec<(String, String)>,
+}
+
+impl Entry {
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Authors (or editors) as "First Last"
+    pub fn authors(&self) -> Vec<String> {
+        self.names().iter().map(|name| name.full()).collect()
+    }
+
+    /// Last names of the authors, for author–year citations
+    pub fn last_names(&self) -> Vec<String> {
+        self.names().iter().map(|name| name.last.clone()).collect()
+    }
+
+    fn names(&self) -> Vec<Name> {
+        self.field("author")
+            .or_else(|| self.field("editor"))
+            .map(|names| {
+                split_names(names)
+                    .iter()
+                    .map(|name| Name::parse(name))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+struct Name {
+    first: String,
+    last: String,
+}
+
+impl Name {
+    /// `Last, First`, `Last, Jr, First` or `First Last`
+    fn parse(name: &str) -> Self {
+        let parts = split_top_level(name, |rest| rest.starts_with(','), 1);
+        match parts.as_slice() {
+            [last, first] => Name {
+                first: first.trim().to_string(),
+                last: last.trim().to_string(),
+            },
+            [last, junior, first] => Name {
+                first: first.trim().to_string(),
+                last: format!("{}, {}", last.trim(), junior.trim()),
+            },
+            _ => {
+                let words = split_top_level(name, |rest| rest.starts_with(' '), 1);
+                let words: Vec<&str> = words
+                    .iter()
+                    .map(|word| word.trim())
+                    .filter(|word| !word.is_empty())
+                    .collect();
+                let (last, first) = words.split_last().unwrap_or((&"", &[]));
+                Name {
+                    first: first.join(" "),
+                    last: last.to_string(),
+                }
+            }
+        }
+    }
+
+    fn full(&self) -> String {
+        if self.first.is_empty() {
+            self.last.clone()
+        } else {
+            format!("{} {}", self.first, self.last)
+        }
+    }
+}
+
+/// Split a name list on ` and ` outside braces
+fn split_names(names: &str) -> Vec<String> {
+    let names = names.split_whitespace().collect::<Vec<_>>().join(" ");
+    split_top_level(&names, |rest| rest.starts_with(" and "), 5)
+        .into_iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Split `text` where `at` matches outside braces, skipping `len` bytes
+fn split_top_level(text: &str, at: impl Fn(&str) -> bool, len: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut index = 0;
+    while index < text.len() {
+        let rest = &text[index..];
+        match rest.chars().next() {
+            Some('{') => depth += 1,
+            Some('}') => depth -= 1,
+            _ if depth == 0 && at(rest) => {
+                parts.push(text[start..index].to_string());
+                index += len;
+                start = index;
+                continue;
+            }
+            _ => {}
+        }
+        index += rest.chars().next().map_or(1, char::len_utf8);
+    }
+    parts.push(text[start..].to_string());
+    parts
+}
+
+/// Parse the entries of a `.bib` file
+pub fn parse(source: &str) -> Vec<Entry> {
+    let mut reader = Reader {
+        chars: source.chars().collect(),
+        pos: 0,
+        strings: HashMap::new(),
+    };
+    let mut entries = Vec::new();
+
+    while reader.skip_to('@') {
+        let kind = reader.identifier().to_lowercase();
+        reader.skip_whitespace();
+        let close = match reader.peek() {
+            Some('{') => '}',
+            Some('(') => ')',
+            _ => continue,
+        };
+        reader.pos += 1;
+
+        match kind.as_str() {
+            "comment" | "preamble" => {
+                reader.pos -= 1;
+                reader.braced();
+            }
+            "string" => {
+                for (name, value) in reader.fields(close) {
+                    reader.strings.insert(name, value);
+                }
+            }
+            _ => {
+                reader.skip_whitespace();
+                let key = reader.until(&[',', close]).trim().to_string();
+                let fields = if reader.peek() == Some(',') {
+                    reader.pos += 1;
+                    reader.fields(close)
+                } else {
+                    reader.pos += 1;
+                    Vec::new()
+                };
+                entries.push(Entry { kind, key, fields });
+            }
+        }
+    }
+
+    entries
+}
+
+struct Reader {
+    chars: Vec<char>,
+    pos: usize,
+    strings: HashMap<String, String>,
+}
+
+impl Reader {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    /// Move past the next `c`; `false` at the end of input
+    fn skip_to(&mut self, c: char) -> bool {
+        while let Some(next) = self.peek() {
+            self.pos += 1;
+            if next == c {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn identifier(&mut self) -> String {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || "_-:.+/".contains(c))
+        {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn until(&mut self, stops: &[char]) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| !stops.contains(&c)) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// `name = value` pairs up to the closing delimiter
+    fn fields(&mut self, close: char) -> Vec<(String, String)> {
+        let mut fields = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None => break,
+                Some(c) if c == close => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(',') => {
+                    self.pos += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            let name = self.identifier().to_lowercase();
+            self.skip_whitespace();
+            if name.is_empty() || self.peek() != Some('=') {
+                // Malformed field: skip to the next one
+                self.until(&[',', close]);
+                continue;
+            }
+            self.pos += 1;
+            let value = self.value();
+            fields.push((name, value));
+        }
+        fields
+    }
+
+    /// A value with `#` concatenation
+    fn value(&mut self) -> String {
+        let mut value = String::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('{') => value.push_str(&self.braced()),
+                Some('"') => value.push_str(&self.quoted()),
+                Some(c) if c.is_ascii_digit() => {
+                    value.push_str(&self.identifier());
+                }
+                Some(_) => {
+                    let name = self.identifier();
+                    if name.is_empty() {
+                        break;
+                    }
+                    let expanded = self
+                        .strings
+                        .get(&name.to_lowercase())
+                        .cloned()
+                        .or_else(|| month(&name).map(str::to_string))
+                        .unwrap_or(name);
+                    value.push_str(&expanded);
+                }
+                None => break,
+            }
+            self.skip_whitespace();
+            if self.peek() == Some('#') {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        value
+    }
+
+    /// Content of a balanced `{...}`, inner braces kept
+    fn braced(&mut self) -> String {
+        self.pos += 1;
+        let start = self.pos;
+        let mut depth = 1;
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return self.chars[start..self.pos - 1].iter().collect();
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.chars[start..].iter().collect()
+    }
+
+    fn quoted(&mut self) -> String {
+        self.pos += 1;
+        let start = self.pos;
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                '"' if depth == 0 => return self.chars[start..self.pos - 1].iter().collect(),
+                _ => {}
+            }
+        }
+        self.chars[start..].iter().collect()
+    }
+}
+
+/// The predefined month abbreviations
+fn month(name: &str) -> Option<&'static str> {
+    const MONTHS: &[(&str, &str)] = &[
+        ("jan", "January"),
+        ("feb", "February"),
+        ("mar", "March"),
+        ("apr", "April"),
+        ("may", "May"),
+        ("jun", "June"),
+        ("jul", "July"),
+        ("aug", "August"),
+        ("sep", "September"),
+        ("oct", "October"),
+        ("nov", "November"),
+        ("dec", "December"),
+    ];
+    MONTHS
+        .iter()
+        .find(|(short, _)| short.eq_ignore_ascii_case(name))
+        .map(|(_, long)| *long)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_and_fields() {
+        let entries = parse(
+            r#"@string{tug = "TUGboat"}
+@comment{ignored {x}}
+@Article{knuth84,
+  author = {Knuth, Donald E. and Leslie Lamport},
+  title  = "Literate {P}rogramming",
+  journal = tug # " Journal",
+  year = 1984, month = may,
+}
+@book(lamport, title = {\LaTeX})"#,
+        );
+        assert_eq!(entries.len(), 2);
+        let knuth = &entries[0];
+        assert_eq!(knuth.kind, "article");
+        assert_eq!(knuth.key, "knuth84");
+        assert_eq!(knuth.field("title"), Some("Literate {P}rogramming"));
+        assert_eq!(knuth.field("journal"), Some("TUGboat Journal"));
+        assert_eq!(knuth.field("year"), Some("1984"));
+        assert_eq!(knuth.field("month"), Some("May"));
+        assert_eq!(knuth.authors(), vec!["Donald E. Knuth", "Leslie Lamport"]);
+        assert_eq!(knuth.last_names(), vec!["Knuth", "Lamport"]);
+        assert_eq!(entries[1].key, "lamport");
+        assert_eq!(entries[1].field("title"), Some(r"\LaTeX"));
+    }
+
+    #[test]
+    fn test_braced_names() {
+        let entry = Entry {
+            fields: vec![(
+                "author".to_string(),
+                "{Barnes and Noble} and de la Cruz, Juan".to_string(),
+            )],
+            ..Default::default()
+        };
+        assert_eq!(
+            entry.authors(),
+            vec!["{Barnes and Noble}", "Juan de la Cruz"]
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! LaTeX counters
+//!
+//! Sections, equations, floats, theorems and list items are numbered with
+//! named counters as in LaTeX: stepping a counter resets the counters
+//! defined within it, and the printed label is prefixed with the parent's
+//! (`\thesubsection` is `1.2` when section 1 has a second subsection).
+
+use std::collections::HashMap;
+
+/// How a counter value is printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Arabic,
+    LowerAlpha,
+    UpperAlpha,
+    LowerRoman,
+    UpperRoman,
+}
+
+#[derive(Debug, Clone)]
+struct Counter {
+    value: u32,
+    parent: Option<String>,
+    style: Style,
+}
+
+#[derive(Debug, Clone)]
+pub struct Counters {
+    counters: HashMap<String, Counter>,
+}
+
+impl Counters {
+    /// The standard counters; with `chapters` (book and report classes)
+    /// sections, equations and floats are numbered within chapters
+    pub fn new(chapters: bool) -> Self {
+        let mut counters = Counters {
+            counters: HashMap::new(),
+        };
+        let top = chapters.then_some("chapter");
+        counters.define("part", None);
+        counters.set_style("part", Style::UpperRoman);
+        counters.define("chapter", None);
+        counters.define("section", top);
+        counters.define("subsection", Some("section"));
+        counters.define("subsubsection", Some("subsection"));
+        counters.define("paragraph", Some("subsubsection"));
+        counters.define("subparagraph", Some("paragraph"));
+        for name in ["equation", "figure", "table"] {
+            counters.define(name, top);
+        }
+        counters.define("footnote", None);
+        counters.define("enumi", None);
+        counters.define("enumii", Some("enumi"));
+        counters.define("enumiii", Some("enumii"));
+        counters.define("enumiv", Some("enumiii"));
+        counters.set_style("enumii", Style::LowerAlpha);
+        counters.set_style("enumiii", Style::LowerRoman);
+        counters.set_style("enumiv", Style::UpperAlpha);
+        counters.define("secnumdepth", None);
+        counters.set("secnumdepth", if chapters { 2 } else { 3 });
+        counters
+    }
+
+    /// Define (or redefine) a counter, reset whenever `parent` is stepped
+    pub fn define(&mut self, name: &str, parent: Option<&str>) {
+        self.counters.insert(
+            name.to_string(),
+            Counter {
+                value: 0,
+                parent: parent.map(str::to_string),
+                style: Style::Arabic,
+            },
+        );
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.counters.contains_key(name)
+    }
+
+    pub fn set_style(&mut self, name: &str, style: Style) {
+        if let Some(counter) = self.counters.get_mut(name) {
+            counter.style = style;
+        }
+    }
+
+    pub fn value(&self, name: &str) -> u32 {
+        self.counters.get(name).map_or(0, |counter| counter.value)
+    }
+
+    pub fn set(&mut self, name: &str, value: u32) {
+        if let Some(counter) = self.counters.get_mut(name) {
+            counter.value = value;
+        }
+    }
+
+    /// Increment a counter, reset its descendants and return its label
+    pub fn step(&mut self, name: &str) -> String {
+        if let Some(counter) = self.counters.get_mut(name) {
+            counter.value += 1;
+        }
+        self.reset_within(name);
+        self.label(name)
+    }
+
+    fn reset_within(&mut self, name: &str) {
+        let children: Vec<String> = self
+            .counters
+            .iter()
+            .filter(|(_, counter)| counter.parent.as_deref() == Some(name))
+            .map(|(child, _)| child.clone())
+            .collect();
+        for child in children {
+            self.set(&child, 0);
+            self.reset_within(&child);
+        }
+    }
+
+    /// The counter value alone in its style (`\arabic`, `\alph`, ...)
+    pub fn format(&self, name: &str) -> String {
+        let Some(counter) = self.counters.get(name) else {
+            return String::new();
+        };
+        let value = counter.value;
+        match counter.style {
+            Style::Arabic => value.to_string(),
+            Style::LowerAlpha => alpha(value).to_lowercase(),
+            Style::UpperAlpha => alpha(value),
+            Style::LowerRoman => roman(value).to_lowercase(),
+            Style::UpperRoman => roman(value),
+        }
+    }
+
+    /// Printed label including the parents (`\thesubsection`)
+    ///
+    /// A parent that has not been stepped yet is left out, so a section
+    /// before the first chapter is `1` rather than `0.1`.
+    pub fn label(&self, name: &str) -> String {
+        let own = self.format(name);
+        let parent = self
+            .counters
+            .get(name)
+            .and_then(|counter| counter.parent.as_deref())
+            .filter(|parent| self.value(parent) > 0);
+        match parent {
+            // Nested list items print as `1a`, `1(a)i`, ...
+            Some(parent) if name.starts_with("enum") => format!("{}{own}", self.label(parent)),
+            Some(parent) => format!("{}.{own}", self.label(parent)),
+            None => own,
+        }
+    }
+}
+
+/// `A`, `B`, ..., `Z`, `AA`, ...
+fn alpha(mut value: u32) -> String {
+    let mut letters = Vec::new();
+    while value > 0 {
+        value -= 1;
+        letters.push((b'A' + (value % 26) as u8) as char);
+        value /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
+pub fn roman(mut value: u32) -> String {
+    const NUMERALS: &[(u32, &str)] = &[
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut text = String::new();
+    for &(step, numeral) in NUMERALS {
+        while value >= step {
+            text.push_str(numeral);
+            value -= step;
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_section_labels() {
+        let mut counters = Counters::new(false);
+        assert_eq!(counters.step("section"), "1");
+        assert_eq!(counters.step("subsection"), "1.1");
+        assert_eq!(counters.step("subsection"), "1.2");
+        assert_eq!(counters.step("section"), "2");
+        assert_eq!(counters.step("subsection"), "2.1");
+        assert_eq!(counters.step("equation"), "1");
+    }
+
+    #[test]
+    fn test_chapters_and_styles() {
+        let mut counters = Counters::new(true);
+        assert_eq!(counters.step("section"), "1");
+        counters.step("chapter");
+        assert_eq!(counters.step("equation"), "1.1");
+        counters.set_style("chapter", Style::UpperAlpha);
+        assert_eq!(counters.label("equation"), "A.1");
+        assert_eq!(counters.step("part"), "I");
+        assert_eq!(counters.step("enumi"), "1");
+        assert_eq!(counters.step("enumii"), "1a");
+        assert_eq!(roman(1994), "MCMXCIV");
+        assert_eq!(alpha(27), "AA");
+    }
+}
@@ -0,0 +1,301 @@
+//! Tokenizer
+//!
+//! Splits LaTeX source into tokens with byte spans, following TeX's
+//! category codes closely enough for documents: control sequences, braces,
+//! math shifts, alignment tabs and ties. Comments are dropped, a run of
+//! whitespace becomes one [`TokenKind::Space`] and a blank line a
+//! [`TokenKind::Par`]. `\verb` and the URL argument of `\url` / `\href` are
+//! read raw, because `%` and `#` are ordinary characters there.
+//!
+//! The lexer is pulled by the parser one token at a time so that the
+//! parser can switch to raw reading for verbatim and math bodies.
+
+use std::ops::Range;
+
+/// A token and the source bytes it came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// `\name`, or a control symbol such as `\\` or `\%` (without the backslash)
+    Command(String),
+    BeginGroup,
+    EndGroup,
+    /// `$`, or `$$` when `display` is set
+    MathShift {
+        display: bool,
+    },
+    /// `&`
+    Tab,
+    /// `~`
+    Tie,
+    Space,
+    /// A blank line
+    Par,
+    /// Text of `\verb|...|` or the braced argument of `\url` / `\href`
+    Raw(String),
+    /// Ordinary characters; `[`, `]` and `*` are tokens of their own so
+    /// that optional arguments and starred forms can be recognized
+    Text(String),
+}
+
+pub struct Lexer<'a> {
+    source: &'a str,
+    pos: usize,
+    /// Position of a `{...}` argument to read as [`TokenKind::Raw`]
+    raw_group_at: Option<usize>,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Lexer {
+            source,
+            pos: 0,
+            raw_group_at: None,
+        }
+    }
+
+    /// Current byte offset
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Move back to `pos`, e.g. to re-read a token in raw mode
+    pub fn seek(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    /// Raw text up to the `close` delimiter, which is consumed
+    ///
+    /// Escaped characters (`\$`) never close. With `comments` set, `%`
+    /// comments are removed as in normal text.
+    pub fn raw_until(&mut self, close: &str, comments: bool) -> String {
+        let mut text = String::new();
+        while !self.rest().is_empty() {
+            if self.rest().starts_with(close) {
+                self.pos += close.len();
+                return text;
+            }
+            let c = self.bump().unwrap_or_default();
+            match c {
+                '\\' => {
+                    text.push(c);
+                    if let Some(next) = self.bump() {
+                        text.push(next);
+                    }
+                }
+                '%' if comments => {
+                    // As in TeX, the comment takes the line end and the
+                    // next line's indentation with it
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                    self.bump();
+                    while self.peek().is_some_and(|c| c == ' ' || c == '\t') {
+                        self.bump();
+                    }
+                }
+                c => text.push(c),
+            }
+        }
+        text
+    }
+
+    /// Next token, or `None` at the end of input
+    pub fn next_token(&mut self) -> Option<Token> {
+        loop {
+            let start = self.pos;
+            let c = self.peek()?;
+            if self.raw_group_at.take() == Some(start) {
+                let text = self.balanced_group();
+                return Some(Token {
+                    kind: TokenKind::Raw(text),
+                    span: start..self.pos,
+                });
+            }
+            let kind = match c {
+                '%' => {
+                    if self.comment() {
+                        TokenKind::Par
+                    } else {
+                        continue;
+                    }
+                }
+                '\\' => {
+                    self.bump();
+                    self.command()
+                }
+                '{' => {
+                    self.bump();
+                    TokenKind::BeginGroup
+                }
+                '}' => {
+                    self.bump();
+                    TokenKind::EndGroup
+                }
+                '$' => {
+                    self.bump();
+                    let display = self.peek() == Some('$');
+                    if display {
+                        self.bump();
+                    }
+                    TokenKind::MathShift { display }
+                }
+                '&' => {
+                    self.bump();
+                    TokenKind::Tab
+                }
+                '~' => {
+                    self.bump();
+                    TokenKind::Tie
+                }
+                '[' | ']' | '*' => {
+                    self.bump();
+                    TokenKind::Text(c.to_string())
+                }
+                c if c.is_whitespace() => self.whitespace(),
+                _ => {
+                    while self
+                        .peek()
+                        .is_some_and(|c| !is_special(c) && !c.is_whitespace())
+                    {
+                        self.bump();
+                    }
+                    TokenKind::Text(self.source[start..self.pos].to_string())
+                }
+            };
+            return Some(Token {
+                kind,
+                span: start..self.pos,
+            });
+        }
+    }
+
+    /// Skip a comment with its line end and the next line's indentation;
+    /// returns `true` when a blank line follows
+    fn comment(&mut self) -> bool {
+        while self.peek().is_some_and(|c| c != '\n') {
+            self.bump();
+        }
+        self.bump();
+        while self.peek().is_some_and(|c| c == ' ' || c == '\t') {
+            self.bump();
+        }
+        if self.peek() == Some('\n') {
+            self.whitespace();
+            return true;
+        }
+        false
+    }
+
+    fn whitespace(&mut self) -> TokenKind {
+        let mut newlines = 0;
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            if c == '\n' {
+                newlines += 1;
+            }
+            self.bump();
+        }
+        if newlines >= 2 {
+            TokenKind::Par
+        } else {
+            TokenKind::Space
+        }
+    }
+
+    /// Control sequence after the backslash
+    fn command(&mut self) -> TokenKind {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.bump();
+        }
+        if self.pos == start {
+            // Control symbol
+            return match self.bump() {
+                Some(c) => TokenKind::Command(c.to_string()),
+                None => TokenKind::Command(String::new()),
+            };
+        }
+        let name = self.source[start..self.pos].to_string();
+
+        // Spaces after a control word are not part of the text
+        while self.peek().is_some_and(|c| c == ' ' || c == '\t') {
+            self.bump();
+        }
+
+        match name.as_str() {
+            "verb" => {
+                let starred = self.peek() == Some('*');
+                if starred {
+                    self.bump();
+                }
+                match self.bump() {
+                    Some(delimiter) => {
+                        TokenKind::Raw(self.raw_until(&delimiter.to_string(), false))
+                    }
+                    None => TokenKind::Raw(String::new()),
+                }
+            }
+            // Keep the command; the raw argument follows as its own token
+            "url" | "href" if self.peek() == Some('{') => {
+                self.raw_group_at = Some(self.pos);
+                TokenKind::Command(name)
+            }
+            _ => TokenKind::Command(name),
+        }
+    }
+
+    /// Raw content of the balanced `{...}` at the current position
+    fn balanced_group(&mut self) -> String {
+        self.bump();
+        let mut depth = 1;
+        let mut text = String::new();
+        while let Some(c) = self.bump() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                '\\' => {
+                    text.push(c);
+                    if let Some(next) = self.bump() {
+                        text.push(next);
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            text.push(c);
+        }
+        text
+    }
+}
+
+/// Characters that end a text run
+fn is_special(c: char) -> bool {
+    matches!(
+        c,
+        '\\' | '{' | '}' | '$' | '&' | '~' | '%' | '[' | ']' | '*'
+    )
+}
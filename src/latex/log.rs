@@ -0,0 +1,398 @@
+//! TeX log reader
+//!
+//! Turns the `.log` written by pdfTeX, XeTeX, LuaTeX or Tectonic into
+//! [`Message`]s with the file and line they refer to. Both the classic
+//! `! error` / `l.12 context` form and `-file-line-error` output are
+//! understood. The current file is tracked through the `(./file.tex ... )`
+//! groups TeX writes when it opens and closes input files.
+
+use std::path::PathBuf;
+
+use crate::mermaid::{Diagnostic, Severity};
+
+/// pdfTeX breaks log lines at this many bytes (`max_print_line`)
+const LOG_WIDTH: usize = 79;
+
+/// An error or warning from a TeX run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub severity: Severity,
+    /// Input file as written in the log (usually relative to the build
+    /// directory); `None` when no file was open
+    pub file: Option<PathBuf>,
+    /// 1-based source line, when TeX reports one
+    pub line: Option<usize>,
+    pub message: String,
+    /// Source text up to the point of an error (the `l.12` line)
+    pub context: Option<String>,
+}
+
+impl Message {
+    /// Editor diagnostic for this message; `None` without a line
+    ///
+    /// An undefined control sequence is underlined itself, other problems
+    /// underline their whole line.
+    pub fn diagnostic(&self, source: &str) -> Option<Diagnostic> {
+        let line = self.line?;
+        let text = source.split('\n').nth(line - 1).unwrap_or_default();
+        let line_length = text.trim_end_matches('\r').chars().count().max(1);
+
+        let culprit = self
+            .context
+            .as_deref()
+            .filter(|context| !context.starts_with("..."))
+            .and_then(|context| {
+                let name_start = context.rfind('\\')?;
+                let name = &context[name_start..];
+                let is_word = name.len() > 1 && name[1..].chars().all(char::is_alphabetic);
+                (is_word && text.starts_with(context)).then(|| {
+                    let column = context[..name_start].chars().count() + 1;
+                    (column, name.chars().count())
+                })
+            });
+        let (column, length) = culprit.unwrap_or((1, line_length));
+
+        Some(Diagnostic {
+            severity: self.severity,
+            line,
+            column,
+            length,
+            message: self.message.clone(),
+        })
+    }
+}
+
+/// Read the messages of a TeX log in the order they were written
+pub fn parse(log: &str) -> Vec<Message> {
+    let lines = unwrap_lines(log);
+    let mut messages = Vec::new();
+    let mut files: Vec<Option<PathBuf>> = Vec::new();
+    let current = |files: &[Option<PathBuf>]| files.iter().rev().find_map(Clone::clone);
+
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index].as_str();
+        index += 1;
+
+        if let Some((file, line_number, text)) = file_line_error(line) {
+            let (context, next) = error_context(&lines, index);
+            index = next;
+            messages.push(Message {
+                severity: Severity::Error,
+                file: Some(PathBuf::from(file)),
+                line: Some(line_number),
+                message: text.to_string(),
+                context: context.map(|(_, context)| context),
+            });
+            continue;
+        }
+
+        if let Some(text) = line.strip_prefix("! ") {
+            let (context, next) = error_context(&lines, index);
+            index = next;
+            if is_consequence(text) {
+                continue;
+            }
+            messages.push(Message {
+                severity: Severity::Error,
+                file: current(&files),
+                line: context.as_ref().map(|(line, _)| *line),
+                message: text.trim().to_string(),
+                context: context.map(|(_, context)| context),
+            });
+            continue;
+        }
+
+        if let Some((source, text)) = warning(line) {
+            // Package warnings continue on lines starting with `(name)`
+            let mut text = text.to_string();
+            let prefix = format!("({source})");
+            while let Some(rest) = lines.get(index).and_then(|next| next.strip_prefix(&prefix)) {
+                text.push(' ');
+                text.push_str(rest.trim());
+                index += 1;
+            }
+            let line_number = input_line(&text);
+            messages.push(Message {
+                severity: Severity::Warning,
+                file: current(&files),
+                line: line_number,
+                message: text.trim().to_string(),
+                context: None,
+            });
+            continue;
+        }
+
+        if line.starts_with("Overfull \\hbox") || line.starts_with("Overfull \\vbox") {
+            messages.push(Message {
+                severity: Severity::Warning,
+                file: current(&files),
+                line: box_line(line),
+                message: line.trim().to_string(),
+                context: None,
+            });
+        }
+        if line.starts_with("Overfull \\") || line.starts_with("Underfull \\") {
+            // The box contents follow up to a blank line; their parentheses
+            // are not files
+            while lines.get(index).is_some_and(|next| !next.trim().is_empty()) {
+                index += 1;
+            }
+            continue;
+        }
+
+        track_files(line, &mut files);
+    }
+
+    messages
+}
+
+/// Whether any message is an error
+pub fn has_errors(messages: &[Message]) -> bool {
+    messages
+        .iter()
+        .any(|message| message.severity == Severity::Error)
+}
+
+/// Join lines TeX broke at [`LOG_WIDTH`]
+fn unwrap_lines(log: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut continued = false;
+    for line in log.lines() {
+        if continued {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(line);
+            }
+        } else {
+            lines.push(line.to_string());
+        }
+        continued = line.len() == LOG_WIDTH;
+    }
+    lines
+}
+
+/// `./main.tex:12: Undefined control sequence.` (`-file-line-error`)
+fn file_line_error(line: &str) -> Option<(&str, usize, &str)> {
+    let (file, rest) = line.split_once(':')?;
+    let (number, text) = rest.split_once(": ")?;
+    let number = number.parse().ok()?;
+    let looks_like_file =
+        !file.is_empty() && !file.contains(' ') && (file.contains('.') || file.contains('/'));
+    looks_like_file.then(|| (file.trim_start_matches('('), number, text.trim()))
+}
+
+/// The `l.12 text` line following an error, and the index after it
+fn error_context(lines: &[String], start: usize) -> (Option<(usize, String)>, usize) {
+    for (index, line) in lines.iter().enumerate().skip(start).take(12) {
+        if line.starts_with("! ") || file_line_error(line).is_some() {
+            break;
+        }
+        let Some(rest) = line.strip_prefix("l.") else {
+            continue;
+        };
+        let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+        let Ok(number) = digits.parse() else {
+            continue;
+        };
+        let context = rest[digits.len()..].strip_prefix(' ').unwrap_or_default();
+        return (Some((number, context.to_string())), index + 1);
+    }
+    (None, start)
+}
+
+/// Errors that only report that an earlier error stopped the run
+fn is_consequence(text: &str) -> bool {
+    text.starts_with("Emergency stop")
+        || text.starts_with(" ==> Fatal error occurred")
+        || text.starts_with("==> Fatal error occurred")
+}
+
+/// `LaTeX Warning: ...`, `Package natbib Warning: ...`, `Class article Warning: ...`
+///
+/// Returns the name continuation lines are prefixed with, and the text.
+fn warning(line: &str) -> Option<(&str, &str)> {
+    let (source, text) = line.split_once(" Warning: ")?;
+    let source = source.trim_end_matches(" Font");
+    if source == "LaTeX" || source == "pdfTeX" {
+        return Some((source, text));
+    }
+    let name = source
+        .strip_prefix("Package ")
+        .or_else(|| source.strip_prefix("Class "))?;
+    (!name.contains(' ')).then_some((name, text))
+}
+
+/// `... on input line 12.`
+fn input_line(text: &str) -> Option<usize> {
+    let rest = &text[text.rfind("input line ")? + "input line ".len()..];
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+/// `... in paragraph at lines 10--12` or `... detected at line 7`
+fn box_line(line: &str) -> Option<usize> {
+    let rest = line
+        .rsplit_once("at lines ")
+        .or_else(|| line.rsplit_once("at line "))?
+        .1;
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+/// Follow `(file` and `)` on a log line
+fn track_files(line: &str, files: &mut Vec<Option<PathBuf>>) {
+    let mut rest = line;
+    while let Some(index) = rest.find(['(', ')']) {
+        if rest[index..].starts_with(')') {
+            files.pop();
+            rest = &rest[index + 1..];
+            continue;
+        }
+        let after = &rest[index + 1..];
+        let end = after
+            .find(|c: char| c.is_whitespace() || c == '(' || c == ')')
+            .unwrap_or(after.len());
+        let name = &after[..end];
+        let is_file = name.starts_with("./")
+            || name.starts_with('/')
+            || name.starts_with("../")
+            || (name.contains('.') && name.chars().next().is_some_and(char::is_alphabetic));
+        files.push(is_file.then(|| PathBuf::from(name)));
+        rest = &after[end..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = r"This is pdfTeX, Version 3.141592653-2.6-1.40.25 (TeX Live 2023) (preloaded format=pdflatex)
+entering extended mode
+(./main.tex
+LaTeX2e <2022-11-01>
+(/usr/share/texlive/texmf-dist/tex/latex/base/article.cls
+Document Class: article 2022/07/02 v1.4n Standard LaTeX document class
+(/usr/share/texlive/texmf-dist/tex/latex/base/size10.clo))
+(./chapter.tex
+! Undefined control sequence.
+l.3 Some \foo
+             bar
+)
+Overfull \hbox (12.0pt too wide) in paragraph at lines 10--12
+[]\OT1/cmr/m/n/10 (text in a box
+ []
+
+LaTeX Warning: Reference `sec:missing' on page 1 undefined on input line 14.
+
+Package natbib Warning: Citation `knuth' on page 1 undefined on input line 15.
+
+! LaTeX Error: Environment foo undefined.
+
+See the LaTeX manual or LaTeX Companion for explanation.
+Type  H <return>  for immediate help.
+ ...
+
+l.20 \begin{foo}
+
+! Emergency stop.
+<*> main.tex
+
+*** (job aborted, no legal \end found)
+";
+
+    #[test]
+    fn test_errors_and_warnings() {
+        let messages = parse(LOG);
+        let summary: Vec<(Severity, Option<&str>, Option<usize>, &str)> = messages
+            .iter()
+            .map(|m| {
+                (
+                    m.severity,
+                    m.file.as_deref().and_then(|file| file.to_str()),
+                    m.line,
+                    m.message.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    Severity::Error,
+                    Some("./chapter.tex"),
+                    Some(3),
+                    "Undefined control sequence."
+                ),
+                (
+                    Severity::Warning,
+                    Some("./main.tex"),
+                    Some(10),
+                    "Overfull \\hbox (12.0pt too wide) in paragraph at lines 10--12"
+                ),
+                (
+                    Severity::Warning,
+                    Some("./main.tex"),
+                    Some(14),
+                    "Reference `sec:missing' on page 1 undefined on input line 14."
+                ),
+                (
+                    Severity::Warning,
+                    Some("./main.tex"),
+                    Some(15),
+                    "Citation `knuth' on page 1 undefined on input line 15."
+                ),
+                (
+                    Severity::Error,
+                    Some("./main.tex"),
+                    Some(20),
+                    "LaTeX Error: Environment foo undefined."
+                ),
+            ]
+        );
+        assert!(has_errors(&messages));
+    }
+
+    #[test]
+    fn test_file_line_errors_and_wrapped_lines() {
+        let long = format!(
+            "Package hyperref Warning: Token not allowed in a PDF string {}",
+            "x".repeat(79 - 60)
+        );
+        assert_eq!(long.len(), LOG_WIDTH);
+        let log = format!(
+            "(./paper.tex\n./paper.tex:7: Missing $ inserted.\nl.7 a^\n      b\n{long}\n(Unicode):\n(hyperref)    removing `math shift' on input line 9.\n)"
+        );
+        let messages = parse(&log);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].file, Some(PathBuf::from("./paper.tex")));
+        assert_eq!(messages[0].line, Some(7));
+        assert_eq!(messages[0].context.as_deref(), Some("a^"));
+        assert_eq!(messages[1].line, Some(9));
+        assert!(messages[1]
+            .message
+            .ends_with("(Unicode): removing `math shift' on input line 9."));
+    }
+
+    #[test]
+    fn test_diagnostic_underlines_the_undefined_command() {
+        let message = Message {
+            severity: Severity::Error,
+            file: None,
+            line: Some(2),
+            message: "Undefined control sequence.".to_string(),
+            context: Some("Some \\foo".to_string()),
+        };
+        let diagnostic = message.diagnostic("first\nSome \\foo bar\n").unwrap();
+        assert_eq!(
+            (diagnostic.line, diagnostic.column, diagnostic.length),
+            (2, 6, 4)
+        );
+
+        let message = Message {
+            context: None,
+            ..message
+        };
+        let diagnostic = message.diagnostic("first\nSome \\foo bar\n").unwrap();
+        assert_eq!((diagnostic.column, diagnostic.length), (1, 13));
+    }
+}
@@ -0,0 +1,69 @@
+//! # LaTeX engine
+//!
+//! LaTeX 文書をトークン列から構文木に組み立て、文書構造 (節番号・相互参照・
+//! 引用) を解決するためのモジュール。プレビューは
+//! [`crate::document::latex`] がこの構文木から [`Document`](crate::document::Document)
+//! を作って描画する。
+//!
+//! ```text
+//! source ─▶ lexer::Lexer ─▶ parser::parse ─▶ Vec<Node> ─▶ document::latex ─▶ Document
+//!                                                  ▲
+//!                          \input / \include ──────┤ (counters, bib)
+//! ```
+//!
+//! - [`lexer`] — 制御綴・波括弧・数式シフト・コメントを扱うトークナイザ
+//! - [`parser`] — 引数シグネチャに従ってコマンドと環境を入れ子の木にする
+//! - [`counters`] — 節・数式・図表・定理・箇条書きの番号付け
+//! - [`bib`] — `\cite` を解決するための `.bib` 読み込み
+//! - [`toolchain`] — インストール済みの `latexmk` / `tectonic` による PDF 生成（任意）
+//! - [`log`] — TeX の `.log` からエラー・警告を行番号付きで取り出す
+
+pub mod bib;
+pub mod counters;
+pub mod lexer;
+pub mod log;
+pub mod model;
+pub mod parser;
+pub mod toolchain;
+
+use std::path::{Path, PathBuf};
+
+pub use model::*;
+pub use parser::parse;
+
+/// File read by `\input{name}` / `\include{name}`
+///
+/// As in LaTeX, `.tex` is added when the name has no extension.
+pub fn input_path(base_dir: &Path, name: &str) -> PathBuf {
+    with_default_extension(base_dir, name, "tex")
+}
+
+/// File read by `\bibliography{name}` or `\addbibresource{name.bib}`
+pub fn bib_path(base_dir: &Path, name: &str) -> PathBuf {
+    with_default_extension(base_dir, name, "bib")
+}
+
+fn with_default_extension(base_dir: &Path, name: &str, extension: &str) -> PathBuf {
+    let path = base_dir.join(name.trim());
+    if path.extension().is_some() {
+        path
+    } else {
+        path.with_extension(extension)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_include_paths() {
+        let base = Path::new("/doc");
+        assert_eq!(
+            input_path(base, "chapters/intro"),
+            Path::new("/doc/chapters/intro.tex")
+        );
+        assert_eq!(input_path(base, "body.ltx"), Path::new("/doc/body.ltx"));
+        assert_eq!(bib_path(base, " refs "), Path::new("/doc/refs.bib"));
+    }
+}
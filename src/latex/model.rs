@@ -0,0 +1,128 @@
+//! LaTeX syntax tree
+
+use std::ops::Range;
+
+/// A node of the syntax tree with the source bytes it covers
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeKind {
+    Text(String),
+    Space,
+    /// Paragraph break (a blank line)
+    Par,
+    /// `&`
+    Tab,
+    /// `~`
+    Tie,
+    /// `{...}`
+    Group(Vec<Node>),
+    Command(Command),
+    Environment(Environment),
+    /// Inline (`$...$`, `\(...\)`) or display (`$$...$$`, `\[...\]`) math
+    /// in TeX notation, comments removed
+    Math {
+        display: bool,
+        tex: String,
+    },
+    /// `\verb` text or the URL argument of `\url` / `\href`
+    Raw(String),
+}
+
+/// `\name` with the arguments given by its [`signature`](super::parser::signature)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Command {
+    pub name: String,
+    /// One entry per signature letter; `None` for an absent star, optional
+    /// or (at the end of input) mandatory argument
+    pub args: Vec<Option<Vec<Node>>>,
+}
+
+/// `\begin{name}...\end{name}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Environment {
+    pub name: String,
+    /// Arguments after `\begin{name}`, as for [`Command::args`]
+    pub args: Vec<Option<Vec<Node>>>,
+    pub body: Body,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Body {
+    Nodes(Vec<Node>),
+    /// Verbatim and math environments keep their source text
+    Raw(String),
+}
+
+impl Node {
+    pub fn new(kind: NodeKind, span: Range<usize>) -> Self {
+        Node { kind, span }
+    }
+
+    /// The command, if this node is `\name`
+    pub fn command(&self, name: &str) -> Option<&Command> {
+        match &self.kind {
+            NodeKind::Command(command) if command.name == name => Some(command),
+            _ => None,
+        }
+    }
+
+    pub fn is_space(&self) -> bool {
+        matches!(self.kind, NodeKind::Space)
+    }
+}
+
+impl Command {
+    /// Argument `index`, if given
+    pub fn arg(&self, index: usize) -> Option<&[Node]> {
+        self.args.get(index)?.as_deref()
+    }
+
+    /// Whether the leading `s` argument of the signature was given
+    pub fn starred(&self) -> bool {
+        self.arg(0).is_some()
+    }
+
+    /// Plain text of argument `index` (e.g. a label key or a file name)
+    pub fn text_arg(&self, index: usize) -> Option<String> {
+        self.arg(index).map(text)
+    }
+}
+
+impl Environment {
+    pub fn arg(&self, index: usize) -> Option<&[Node]> {
+        self.args.get(index)?.as_deref()
+    }
+
+    pub fn text_arg(&self, index: usize) -> Option<String> {
+        self.arg(index).map(text)
+    }
+}
+
+/// Source-level text of nodes: characters without markup, spaces kept
+pub fn text(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    push_text(nodes, &mut out);
+    out.trim().to_string()
+}
+
+fn push_text(nodes: &[Node], out: &mut String) {
+    for node in nodes {
+        match &node.kind {
+            NodeKind::Text(text) | NodeKind::Raw(text) => out.push_str(text),
+            NodeKind::Space | NodeKind::Tie | NodeKind::Par => out.push(' '),
+            NodeKind::Group(nodes) => push_text(nodes, out),
+            NodeKind::Command(command) => {
+                for arg in command.args.iter().flatten() {
+                    push_text(arg, out);
+                }
+            }
+            NodeKind::Math { tex, .. } => out.push_str(tex),
+            NodeKind::Tab | NodeKind::Environment(_) => {}
+        }
+    }
+}
@@ -0,0 +1,548 @@
+//! Tokens → syntax tree
+//!
+//! Commands take the arguments listed in their [`signature`], written in
+//! the `xparse` letters: `s` for a star, `o` for an optional `[...]` and
+//! `m` for a mandatory argument. Unknown commands take none; a group that
+//! follows them stays in the tree as an ordinary [`NodeKind::Group`].
+//!
+//! `\end` closes the innermost matching environment. An `\end` for an
+//! outer environment closes the inner ones too, and an `\end` without a
+//! `\begin` is dropped, so a half-typed document still parses.
+
+use super::lexer::{Lexer, Token, TokenKind};
+use super::model::{Body, Command, Environment, Node, NodeKind};
+
+/// Argument signatures of known commands
+const COMMANDS: &[(&str, &str)] = &[
+    ("documentclass", "om"),
+    ("usepackage", "om"),
+    ("title", "om"),
+    ("author", "om"),
+    ("date", "m"),
+    ("thanks", "m"),
+    ("part", "som"),
+    ("chapter", "som"),
+    ("section", "som"),
+    ("subsection", "som"),
+    ("subsubsection", "som"),
+    ("paragraph", "som"),
+    ("subparagraph", "som"),
+    ("label", "m"),
+    ("ref", "sm"),
+    ("eqref", "m"),
+    ("pageref", "sm"),
+    ("autoref", "sm"),
+    ("cref", "sm"),
+    ("Cref", "sm"),
+    ("nameref", "sm"),
+    ("cite", "oom"),
+    ("citep", "oom"),
+    ("citet", "oom"),
+    ("parencite", "oom"),
+    ("textcite", "oom"),
+    ("autocite", "oom"),
+    ("citeauthor", "m"),
+    ("citeyear", "m"),
+    ("nocite", "m"),
+    ("bibliography", "m"),
+    ("bibliographystyle", "m"),
+    ("addbibresource", "om"),
+    ("printbibliography", "o"),
+    ("bibitem", "om"),
+    ("input", "m"),
+    ("include", "m"),
+    ("subfile", "m"),
+    ("includeonly", "m"),
+    ("item", "o"),
+    ("textbf", "m"),
+    ("textit", "m"),
+    ("textsl", "m"),
+    ("emph", "m"),
+    ("texttt", "m"),
+    ("textsc", "m"),
+    ("textrm", "m"),
+    ("textsf", "m"),
+    ("textup", "m"),
+    ("textmd", "m"),
+    ("underline", "m"),
+    ("sout", "m"),
+    ("st", "m"),
+    ("mbox", "m"),
+    ("href", "mm"),
+    ("url", "m"),
+    ("footnote", "om"),
+    ("caption", "om"),
+    ("includegraphics", "som"),
+    ("newtheorem", "smomo"),
+    ("newcommand", "smoom"),
+    ("renewcommand", "smoom"),
+    ("providecommand", "smoom"),
+    ("newenvironment", "smoomm"),
+    ("renewenvironment", "smoomm"),
+    ("vspace", "sm"),
+    ("hspace", "sm"),
+    ("setlength", "mm"),
+    ("setcounter", "mm"),
+    ("addtocounter", "mm"),
+    ("index", "m"),
+    ("hypersetup", "m"),
+    ("geometry", "m"),
+    ("graphicspath", "m"),
+    ("multicolumn", "mmm"),
+    ("multirow", "mmm"),
+    ("cline", "m"),
+    ("cmidrule", "om"),
+    ("\\", "so"),
+];
+
+/// Argument signatures of environments, after the name
+const ENVIRONMENTS: &[(&str, &str)] = &[
+    ("tabular", "om"),
+    ("tabular*", "mom"),
+    ("tabularx", "mom"),
+    ("longtable", "om"),
+    ("array", "om"),
+    ("minipage", "ooom"),
+    ("figure", "o"),
+    ("figure*", "o"),
+    ("table", "o"),
+    ("table*", "o"),
+    ("thebibliography", "m"),
+    ("itemize", "o"),
+    ("enumerate", "o"),
+    ("description", "o"),
+    ("lstlisting", "o"),
+    ("minted", "om"),
+];
+
+/// Environments whose body is read as raw text (math and verbatim)
+pub const RAW_ENVIRONMENTS: &[&str] = &[
+    "equation",
+    "equation*",
+    "align",
+    "align*",
+    "alignat",
+    "alignat*",
+    "flalign",
+    "flalign*",
+    "gather",
+    "gather*",
+    "multline",
+    "multline*",
+    "eqnarray",
+    "eqnarray*",
+    "displaymath",
+    "math",
+    "verbatim",
+    "verbatim*",
+    "Verbatim",
+    "lstlisting",
+    "minted",
+    "comment",
+];
+
+/// Argument signature of a command (empty for unknown commands)
+pub fn signature(name: &str) -> &'static str {
+    lookup(COMMANDS, name)
+}
+
+fn lookup(table: &[(&str, &'static str)], name: &str) -> &'static str {
+    table
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map_or("", |(_, signature)| signature)
+}
+
+/// Parse LaTeX source into a syntax tree
+pub fn parse(source: &str) -> Vec<Node> {
+    let mut parser = Parser {
+        lexer: Lexer::new(source),
+        peeked: None,
+        open: Vec::new(),
+        closing: None,
+    };
+    parser.nodes(Until::End)
+}
+
+/// Where a node sequence ends
+enum Until {
+    End,
+    Group,
+    Bracket,
+    /// `\end` of the innermost open environment
+    Environment,
+}
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    peeked: Option<Token>,
+    /// Names of the open environments, innermost last
+    open: Vec<String>,
+    /// `\end{name}` read by an inner level for an outer environment
+    closing: Option<String>,
+}
+
+impl Parser<'_> {
+    fn peek(&mut self) -> Option<&Token> {
+        if self.peeked.is_none() {
+            self.peeked = self.lexer.next_token();
+        }
+        self.peeked.as_ref()
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        self.peeked.take().or_else(|| self.lexer.next_token())
+    }
+
+    fn pos(&self) -> usize {
+        self.peeked
+            .as_ref()
+            .map_or(self.lexer.pos(), |token| token.span.start)
+    }
+
+    /// Switch to raw reading at the current position
+    fn raw_until(&mut self, close: &str, comments: bool) -> String {
+        if let Some(token) = self.peeked.take() {
+            self.lexer.seek(token.span.start);
+        }
+        self.lexer.raw_until(close, comments)
+    }
+
+    fn skip_spaces(&mut self) {
+        while self
+            .peek()
+            .is_some_and(|token| token.kind == TokenKind::Space)
+        {
+            self.next();
+        }
+    }
+
+    fn nodes(&mut self, until: Until) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        loop {
+            if self.closing.is_some() {
+                break;
+            }
+            let Some(token) = self.peek() else { break };
+            match (&token.kind, &until) {
+                (TokenKind::EndGroup, Until::Group) => {
+                    self.next();
+                    break;
+                }
+                // A stray `}` ends an optional argument, or is dropped
+                (TokenKind::EndGroup, Until::Bracket) => break,
+                (TokenKind::EndGroup, _) => {
+                    self.next();
+                    continue;
+                }
+                (TokenKind::Text(text), Until::Bracket) if text == "]" => {
+                    self.next();
+                    break;
+                }
+                _ => {}
+            }
+            let token = self.next().expect("peeked token");
+            if let Some(node) = self.node(token) {
+                nodes.push(node);
+            }
+        }
+        nodes
+    }
+
+    fn node(&mut self, token: Token) -> Option<Node> {
+        let start = token.span.start;
+        let kind = match token.kind {
+            TokenKind::Text(text) => NodeKind::Text(text),
+            TokenKind::Space => NodeKind::Space,
+            TokenKind::Par => NodeKind::Par,
+            TokenKind::Tab => NodeKind::Tab,
+            TokenKind::Tie => NodeKind::Tie,
+            TokenKind::Raw(text) => NodeKind::Raw(text),
+            TokenKind::BeginGroup => NodeKind::Group(self.nodes(Until::Group)),
+            TokenKind::EndGroup => return None,
+            TokenKind::MathShift { display } => {
+                let tex = self.raw_until(if display { "$$" } else { "$" }, true);
+                NodeKind::Math { display, tex }
+            }
+            TokenKind::Command(name) => match name.as_str() {
+                "(" | "[" => {
+                    let close = if name == "(" { r"\)" } else { r"\]" };
+                    let tex = self.raw_until(close, true);
+                    NodeKind::Math {
+                        display: name == "[",
+                        tex,
+                    }
+                }
+                "begin" => NodeKind::Environment(self.environment()),
+                "end" => {
+                    let name = self.name();
+                    if self.open.contains(&name) {
+                        self.closing = Some(name);
+                    }
+                    return None;
+                }
+                _ => {
+                    let args = self.args(signature(&name));
+                    NodeKind::Command(Command { name, args })
+                }
+            },
+        };
+        Some(Node::new(kind, start..self.pos()))
+    }
+
+    /// Name argument of `\begin` / `\end`
+    fn name(&mut self) -> String {
+        self.skip_spaces();
+        if self
+            .peek()
+            .is_some_and(|token| token.kind == TokenKind::BeginGroup)
+        {
+            self.next();
+            let nodes = self.nodes(Until::Group);
+            return super::model::text(&nodes);
+        }
+        String::new()
+    }
+
+    fn environment(&mut self) -> Environment {
+        let name = self.name();
+        let args = self.args(lookup(ENVIRONMENTS, &name));
+
+        let body = if RAW_ENVIRONMENTS.contains(&name.as_str()) {
+            let end = format!("\\end{{{name}}}");
+            let comments = !is_verbatim(&name);
+            Body::Raw(self.raw_until(&end, comments))
+        } else {
+            self.open.push(name.clone());
+            let nodes = self.nodes(Until::Environment);
+            self.open.pop();
+            if self.closing.as_ref() == Some(&name) {
+                self.closing = None;
+            }
+            Body::Nodes(nodes)
+        };
+        Environment { name, args, body }
+    }
+
+    fn args(&mut self, signature: &str) -> Vec<Option<Vec<Node>>> {
+        signature
+            .chars()
+            .map(|kind| match kind {
+                's' => self.star(),
+                'o' => self.optional(),
+                _ => self.mandatory(),
+            })
+            .collect()
+    }
+
+    fn star(&mut self) -> Option<Vec<Node>> {
+        let star =
+            matches!(self.peek(), Some(Token { kind: TokenKind::Text(text), .. }) if text == "*");
+        if star {
+            self.next();
+        }
+        star.then(Vec::new)
+    }
+
+    fn optional(&mut self) -> Option<Vec<Node>> {
+        self.skip_spaces();
+        let open =
+            matches!(self.peek(), Some(Token { kind: TokenKind::Text(text), .. }) if text == "[");
+        if !open {
+            return None;
+        }
+        self.next();
+        Some(self.nodes(Until::Bracket))
+    }
+
+    fn mandatory(&mut self) -> Option<Vec<Node>> {
+        self.skip_spaces();
+        let token = self.peek()?;
+        match &token.kind {
+            TokenKind::BeginGroup => {
+                self.next();
+                Some(self.nodes(Until::Group))
+            }
+            TokenKind::Text(_) | TokenKind::Raw(_) => {
+                let token = self.next()?;
+                self.node(token).map(|node| vec![node])
+            }
+            // A single command without its own arguments, e.g. `\input\file`
+            TokenKind::Command(name) if name != "end" && name != "begin" => {
+                let token = self.next()?;
+                let TokenKind::Command(name) = token.kind else {
+                    return None;
+                };
+                let node = Node::new(
+                    NodeKind::Command(Command {
+                        name,
+                        args: Vec::new(),
+                    }),
+                    token.span,
+                );
+                Some(vec![node])
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Verbatim environments keep `%` as an ordinary character
+pub fn is_verbatim(name: &str) -> bool {
+    matches!(
+        name,
+        "verbatim" | "verbatim*" | "Verbatim" | "lstlisting" | "minted" | "comment"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<NodeKind> {
+        parse(source).into_iter().map(|node| node.kind).collect()
+    }
+
+    fn text(s: &str) -> Node {
+        Node::new(NodeKind::Text(s.to_string()), 0..0)
+    }
+
+    /// Compare trees without spans
+    fn strip(nodes: &[Node]) -> Vec<Node> {
+        nodes
+            .iter()
+            .map(|node| {
+                let kind = match &node.kind {
+                    NodeKind::Group(nodes) => NodeKind::Group(strip(nodes)),
+                    NodeKind::Command(command) => NodeKind::Command(Command {
+                        name: command.name.clone(),
+                        args: command
+                            .args
+                            .iter()
+                            .map(|arg| arg.as_deref().map(strip))
+                            .collect(),
+                    }),
+                    NodeKind::Environment(env) => NodeKind::Environment(Environment {
+                        name: env.name.clone(),
+                        args: env
+                            .args
+                            .iter()
+                            .map(|arg| arg.as_deref().map(strip))
+                            .collect(),
+                        body: match &env.body {
+                            Body::Nodes(nodes) => Body::Nodes(strip(nodes)),
+                            raw => raw.clone(),
+                        },
+                    }),
+                    kind => kind.clone(),
+                };
+                Node::new(kind, 0..0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_command_arguments() {
+        let nodes = strip(&parse(r"\section*[Short]{Long title} % note"));
+        let NodeKind::Command(command) = &nodes[0].kind else {
+            panic!("expected command");
+        };
+        assert_eq!(command.name, "section");
+        assert!(command.starred());
+        assert_eq!(command.text_arg(1).as_deref(), Some("Short"));
+        assert_eq!(
+            command.arg(2),
+            Some(
+                &[
+                    text("Long"),
+                    Node::new(NodeKind::Space, 0..0),
+                    text("title")
+                ][..]
+            )
+        );
+        // The comment is gone; only the space before it remains
+        assert_eq!(nodes.len(), 2);
+    }
+
+    #[test]
+    fn test_multiline_arguments_and_unknown_commands() {
+        let nodes = parse("\\textbf{bold\nacross lines} \\foo{kept}");
+        let NodeKind::Command(command) = &nodes[0].kind else {
+            panic!("expected command");
+        };
+        assert_eq!(command.text_arg(0).as_deref(), Some("bold across lines"));
+        assert!(matches!(&nodes[2].kind, NodeKind::Command(c) if c.args.is_empty()));
+        assert!(matches!(&nodes[3].kind, NodeKind::Group(_)));
+    }
+
+    #[test]
+    fn test_nested_environments() {
+        let nodes = parse(
+            "\\begin{itemize}\n\\item A\n\\begin{enumerate}\\item B\\end{enumerate}\n\\end{itemize}",
+        );
+        assert_eq!(nodes.len(), 1);
+        let NodeKind::Environment(outer) = &nodes[0].kind else {
+            panic!("expected environment");
+        };
+        assert_eq!(outer.name, "itemize");
+        let Body::Nodes(body) = &outer.body else {
+            panic!("expected nodes");
+        };
+        assert!(body
+            .iter()
+            .any(|node| matches!(&node.kind, NodeKind::Environment(e) if e.name == "enumerate")));
+    }
+
+    #[test]
+    fn test_unbalanced_end_recovers() {
+        // `\end{itemize}` closes the unterminated `enumerate` as well
+        let nodes =
+            parse("\\begin{itemize}\\begin{enumerate}\\item x\\end{itemize} after\\end{quote}");
+        assert!(matches!(&nodes[0].kind, NodeKind::Environment(e) if e.name == "itemize"));
+        assert_eq!(
+            strip(&nodes[1..]),
+            vec![Node::new(NodeKind::Space, 0..0), text("after")]
+        );
+    }
+
+    #[test]
+    fn test_math_and_verbatim_stay_raw() {
+        assert_eq!(
+            kinds("$a % b$\n  $ \\[x\\] \\begin{verbatim}50% {\n\\end{verbatim}"),
+            vec![
+                NodeKind::Math {
+                    display: false,
+                    tex: "a ".to_string()
+                },
+                NodeKind::Space,
+                NodeKind::Math {
+                    display: true,
+                    tex: "x".to_string()
+                },
+                NodeKind::Space,
+                NodeKind::Environment(Environment {
+                    name: "verbatim".to_string(),
+                    args: Vec::new(),
+                    body: Body::Raw("50% {\n".to_string()),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_raw_urls_and_verb() {
+        assert_eq!(
+            kinds(r"\url{http://x.org/a%20b#c}\verb|\x{|"),
+            vec![
+                NodeKind::Command(Command {
+                    name: "url".to_string(),
+                    args: vec![Some(vec![Node::new(
+                        NodeKind::Raw("http://x.org/a%20b#c".to_string()),
+                        4..26
+                    )])],
+                }),
+                NodeKind::Raw(r"\x{".to_string()),
+            ]
+        );
+    }
+}
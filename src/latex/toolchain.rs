@@ -0,0 +1,270 @@
+//! Local TeX toolchain
+//!
+//! Runs an installed `latexmk` or `tectonic` on a document and reads the
+//! resulting log with [`super::log`]. The PDF is rasterized to one PNG per
+//! page with `pdftoppm` (Poppler) or `mutool` (MuPDF) so previews can show
+//! it as images. Nothing here is required: callers fall back to the
+//! built-in preview when [`Engine::detect`] finds no engine.
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::log::{self, Message};
+
+/// Program that compiles a document to PDF
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Engine {
+    Latexmk(PathBuf),
+    Tectonic(PathBuf),
+}
+
+/// Program that renders PDF pages to PNG images
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rasterizer {
+    Pdftoppm(PathBuf),
+    Mutool(PathBuf),
+}
+
+/// Result of compiling a document
+#[derive(Debug, Clone, Default)]
+pub struct Build {
+    /// The document's PDF, if there is one; after a failed run this is the
+    /// last one that was written, as in other TeX editors
+    pub pdf: Option<PathBuf>,
+    /// Log messages with their files resolved against the document directory
+    pub messages: Vec<Message>,
+}
+
+impl Engine {
+    /// The first engine found on `PATH`, `latexmk` preferred
+    pub fn detect() -> Option<Engine> {
+        let path = std::env::var_os("PATH")?;
+        Self::detect_in(&path)
+    }
+
+    /// The first engine found in a `PATH`-style list of directories
+    pub fn detect_in(path: &OsStr) -> Option<Engine> {
+        find_program("latexmk", path)
+            .map(Engine::Latexmk)
+            .or_else(|| find_program("tectonic", path).map(Engine::Tectonic))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Engine::Latexmk(_) => "latexmk",
+            Engine::Tectonic(_) => "tectonic",
+        }
+    }
+
+    /// Command compiling `file` in its own directory, in non-stop mode
+    pub fn command(&self, file: &Path) -> Command {
+        let dir = file.parent().filter(|dir| !dir.as_os_str().is_empty());
+        let name = file.file_name().unwrap_or(file.as_os_str());
+        let mut command = match self {
+            Engine::Latexmk(program) => {
+                let mut command = Command::new(program);
+                command.args([
+                    "-pdf",
+                    "-interaction=nonstopmode",
+                    "-file-line-error",
+                    "-halt-on-error",
+                ]);
+                command
+            }
+            Engine::Tectonic(program) => {
+                let mut command = Command::new(program);
+                command.args(["--keep-logs", "--keep-intermediates", "--chatter=minimal"]);
+                command
+            }
+        };
+        command.arg(name);
+        if let Some(dir) = dir {
+            command.current_dir(dir);
+        }
+        command
+    }
+
+    /// Compile `file` and read its log
+    ///
+    /// An `Err` means the engine could not be run or wrote no log at all;
+    /// TeX errors in the document are reported as [`Build::messages`].
+    pub fn compile(&self, file: &Path) -> Result<Build, String> {
+        let output = self
+            .command(file)
+            .output()
+            .map_err(|err| format!("failed to run {}: {err}", self.name()))?;
+
+        let log_path = file.with_extension("log");
+        let log = std::fs::read(&log_path).map_err(|_| {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let detail = stderr.lines().last().unwrap_or("no log was written");
+            format!("{} failed: {detail}", self.name())
+        })?;
+
+        let dir = file.parent().unwrap_or(Path::new(""));
+        let mut messages = log::parse(&String::from_utf8_lossy(&log));
+        for message in &mut messages {
+            message.file = Some(match message.file.take() {
+                Some(path) => dir.join(path.strip_prefix("./").unwrap_or(&path)),
+                None => file.to_path_buf(),
+            });
+        }
+
+        let pdf = Some(file.with_extension("pdf")).filter(|pdf| pdf.is_file());
+        Ok(Build { pdf, messages })
+    }
+}
+
+impl Rasterizer {
+    pub fn detect() -> Option<Rasterizer> {
+        let path = std::env::var_os("PATH")?;
+        Self::detect_in(&path)
+    }
+
+    pub fn detect_in(path: &OsStr) -> Option<Rasterizer> {
+        find_program("pdftoppm", path)
+            .map(Rasterizer::Pdftoppm)
+            .or_else(|| find_program("mutool", path).map(Rasterizer::Mutool))
+    }
+
+    /// Render every page of `pdf` into `out_dir` at `dpi`, returning the
+    /// images in page order
+    ///
+    /// Images from an earlier run in `out_dir` are removed first.
+    pub fn render(&self, pdf: &Path, out_dir: &Path, dpi: u32) -> Result<Vec<PathBuf>, String> {
+        std::fs::create_dir_all(out_dir).map_err(|err| err.to_string())?;
+        for old in page_images(out_dir) {
+            let _ = std::fs::remove_file(old);
+        }
+
+        let dpi = dpi.to_string();
+        let (program, status) = match self {
+            Rasterizer::Pdftoppm(program) => (
+                "pdftoppm",
+                Command::new(program)
+                    .args(["-png", "-r", &dpi])
+                    .arg(pdf)
+                    .arg(out_dir.join("page"))
+                    .status(),
+            ),
+            Rasterizer::Mutool(program) => (
+                "mutool",
+                Command::new(program)
+                    .args(["draw", "-q", "-r", &dpi, "-o"])
+                    .arg(out_dir.join("page-%d.png"))
+                    .arg(pdf)
+                    .status(),
+            ),
+        };
+        match status {
+            Ok(status) if status.success() => Ok(page_images(out_dir)),
+            Ok(status) => Err(format!("{program} exited with {status}")),
+            Err(err) => Err(format!("failed to run {program}: {err}")),
+        }
+    }
+}
+
+/// `page-N.png` files in `dir`, ordered by page number
+///
+/// `pdftoppm` pads the numbers to the width of the page count, `mutool`
+/// does not, so they are compared as numbers.
+fn page_images(dir: &Path) -> Vec<PathBuf> {
+    let mut pages: Vec<(u32, PathBuf)> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let stem = path.file_stem()?.to_str()?;
+            let number = stem.strip_prefix("page-")?.parse().ok()?;
+            (path.extension()? == "png").then_some((number, path))
+        })
+        .collect();
+    pages.sort();
+    pages.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Find an executable in a `PATH`-style list of directories
+fn find_program(name: &str, path: &OsStr) -> Option<PathBuf> {
+    let file_name = if cfg!(windows) {
+        format!("{name}.exe")
+    } else {
+        name.to_string()
+    };
+    std::env::split_paths(path)
+        .map(|dir| dir.join(&file_name))
+        .find(|candidate| candidate.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detection_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().as_os_str();
+        assert_eq!(Engine::detect_in(path), None);
+
+        std::fs::write(dir.path().join("tectonic"), "").unwrap();
+        assert_eq!(
+            Engine::detect_in(path),
+            Some(Engine::Tectonic(dir.path().join("tectonic")))
+        );
+        std::fs::write(dir.path().join("latexmk"), "").unwrap();
+        assert_eq!(Engine::detect_in(path).map(|e| e.name()), Some("latexmk"));
+
+        std::fs::write(dir.path().join("mutool"), "").unwrap();
+        assert!(matches!(
+            Rasterizer::detect_in(path),
+            Some(Rasterizer::Mutool(_))
+        ));
+    }
+
+    #[test]
+    fn test_command_runs_in_document_directory() {
+        let engine = Engine::Latexmk(PathBuf::from("latexmk"));
+        let command = engine.command(Path::new("/papers/draft/main.tex"));
+        let args: Vec<&OsStr> = command.get_args().collect();
+        assert_eq!(args.last(), Some(&OsStr::new("main.tex")));
+        assert!(args.contains(&OsStr::new("-interaction=nonstopmode")));
+        assert_eq!(command.get_current_dir(), Some(Path::new("/papers/draft")));
+    }
+
+    #[test]
+    fn test_page_images_in_page_order() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["page-10.png", "page-2.png", "page-1.png", "other.png"] {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
+        let names: Vec<String> = page_images(dir.path())
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, ["page-1.png", "page-2.png", "page-10.png"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_compile_reads_log() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // A stand-in engine that writes a log with one error
+        let dir = tempfile::tempdir().unwrap();
+        let program = dir.path().join("latexmk");
+        std::fs::write(
+            &program,
+            "#!/bin/sh\nfor f; do :; done\nprintf '(./%s\\n! Undefined control sequence.\\nl.2 \\\\oops\\n)\\n' \"$f\" > \"${f%.tex}.log\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let file = dir.path().join("doc.tex");
+        std::fs::write(&file, "x\n\\oops\n").unwrap();
+
+        let build = Engine::Latexmk(program).compile(&file).unwrap();
+        assert_eq!(build.pdf, None);
+        assert_eq!(build.messages.len(), 1);
+        assert_eq!(build.messages[0].file, Some(dir.path().join("doc.tex")));
+        assert_eq!(build.messages[0].line, Some(2));
+    }
+}
@@ -5,9 +5,14 @@
 pub mod cli;
 pub mod core;
 pub mod core_engine;
+pub mod document;
+pub mod export;
 pub mod file_tree;
 pub mod gui;
+pub mod latex;
 pub mod llm;
+pub mod math;
+pub mod mermaid;
 pub mod search; // LLM integration (optional feature)
 
 pub use gui::LalaApp;
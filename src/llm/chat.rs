@@ -0,0 +1,380 @@
+//! Chat conversations with the configured LLM
+//!
+//! A [`Conversation`] keeps the message history of the AI chat panel.
+//! Context attachments (current file, selection, grep results) are folded
+//! into the outgoing prompt with a token budget so large documents do not
+//! overflow the model's context window.
+//!
+//! Conversations are stored per workspace as JSON under
+//! `<config_dir>/lala/chats/`.
+
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Rough characters-per-token ratio used for budget estimation
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Marker inserted where context text was cut
+const TRUNCATION_MARKER: &str = "\n[... truncated ...]\n";
+
+/// Who sent a chat message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChatRole {
+    User,
+    Assistant,
+}
+
+/// A single message in a conversation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::User,
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::Assistant,
+            content: content.into(),
+        }
+    }
+}
+
+/// Kind of document context attached to a chat request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextKind {
+    File,
+    Selection,
+    GrepResults,
+}
+
+impl ContextKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ContextKind::File => "Current file",
+            ContextKind::Selection => "Selection",
+            ContextKind::GrepResults => "Grep results",
+        }
+    }
+}
+
+/// Document context sent along with the next user message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextAttachment {
+    pub kind: ContextKind,
+    /// File name or other short description shown to the model
+    pub name: String,
+    pub content: String,
+}
+
+/// A fenced code block found in an assistant reply
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeBlock {
+    pub lang: String,
+    pub code: String,
+}
+
+/// Estimate the number of tokens in `text`
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Truncate `text` to roughly `max_tokens`, keeping its head and tail
+///
+/// The middle is replaced with a marker so the model can tell that
+/// content is missing.
+pub fn truncate_to_budget(text: &str, max_tokens: usize) -> String {
+    if estimate_tokens(text) <= max_tokens {
+        return text.to_string();
+    }
+
+    let max_chars = max_tokens * CHARS_PER_TOKEN;
+    let marker_len = TRUNCATION_MARKER.chars().count();
+    if max_chars <= marker_len {
+        return text.chars().take(max_chars).collect();
+    }
+
+    let keep = max_chars - marker_len;
+    let head_len = keep * 2 / 3;
+    let tail_len = keep - head_len;
+    let total = text.chars().count();
+
+    let head: String = text.chars().take(head_len).collect();
+    let tail: String = text.chars().skip(total - tail_len).collect();
+    format!("{head}{TRUNCATION_MARKER}{tail}")
+}
+
+/// Build the message list sent to the model
+///
+/// Attachments are prepended to the last user message and may use at most
+/// half of `max_tokens`; the remaining budget is filled with history,
+/// newest messages first. The result always starts with a user message,
+/// as Gemini rejects conversations that open with a model turn.
+pub fn build_request(
+    history: &[ChatMessage],
+    attachments: &[ContextAttachment],
+    max_tokens: usize,
+) -> Vec<ChatMessage> {
+    let Some((last, earlier)) = history.split_last() else {
+        return Vec::new();
+    };
+
+    let mut last = last.clone();
+    if !attachments.is_empty() {
+        let per_attachment = (max_tokens / 2) / attachments.len();
+        let mut context = String::new();
+        for attachment in attachments {
+            context.push_str(&format!(
+                "--- {}: {} ---\n{}\n\n",
+                attachment.kind.label(),
+                attachment.name,
+                truncate_to_budget(&attachment.content, per_attachment)
+            ));
+        }
+        last.content = format!("{context}{}", last.content);
+    }
+
+    let mut remaining = max_tokens.saturating_sub(estimate_tokens(&last.content));
+    let mut messages = vec![last];
+
+    for message in earlier.iter().rev() {
+        let cost = estimate_tokens(&message.content);
+        if cost > remaining {
+            break;
+        }
+        remaining -= cost;
+        messages.push(message.clone());
+    }
+
+    // `messages` is newest first, so leading turns are at the end
+    while messages
+        .last()
+        .is_some_and(|message| message.role == ChatRole::Assistant)
+    {
+        messages.pop();
+    }
+
+    messages.reverse();
+    messages
+}
+
+/// Extract fenced code blocks from a Markdown reply
+pub fn extract_code_blocks(reply: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<CodeBlock> = None;
+
+    for event in Parser::new_ext(reply, Options::all()) {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let lang = match kind {
+                    CodeBlockKind::Fenced(lang) => lang.to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                current = Some(CodeBlock {
+                    lang,
+                    code: String::new(),
+                });
+            }
+            Event::Text(text) => {
+                if let Some(block) = current.as_mut() {
+                    block.code.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some(block) = current.take() {
+                    blocks.push(block);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    blocks
+}
+
+/// Chat history for one workspace
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Conversation {
+    pub messages: Vec<ChatMessage>,
+}
+
+impl Conversation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, message: ChatMessage) {
+        self.messages.push(message);
+    }
+
+    pub fn clear(&mut self) {
+        self.messages.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Load a conversation from a JSON file
+    pub fn load_from(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        serde_json::from_str(&json).map_err(|e| format!("Failed to parse conversation: {e}"))
+    }
+
+    /// Save the conversation as JSON, creating parent directories
+    pub fn save_to(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize conversation: {e}"))?;
+        std::fs::write(path, json).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+
+    /// Load the saved conversation for `workspace`, or start a new one
+    pub fn load_for_workspace(workspace: &Path) -> Self {
+        conversation_path(workspace)
+            .and_then(|path| Self::load_from(&path).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save the conversation for `workspace`
+    pub fn save_for_workspace(&self, workspace: &Path) -> Result<(), String> {
+        let path = conversation_path(workspace)
+            .ok_or_else(|| "No configuration directory available".to_string())?;
+        self.save_to(&path)
+    }
+}
+
+/// Location of the saved conversation for a workspace directory
+pub fn conversation_path(workspace: &Path) -> Option<PathBuf> {
+    let config_dir = dirs::config_dir()?;
+    Some(
+        config_dir
+            .join("lala")
+            .join("chats")
+            .join(format!("{}.json", workspace_key(workspace))),
+    )
+}
+
+/// File-name-safe key derived from the workspace path
+fn workspace_key(workspace: &Path) -> String {
+    let absolute = workspace
+        .canonicalize()
+        .unwrap_or_else(|_| workspace.to_path_buf());
+    absolute
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>()
+        .trim_matches('_')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_within_budget() {
+        assert_eq!(truncate_to_budget("short text", 100), "short text");
+    }
+
+    #[test]
+    fn test_truncate_keeps_head_and_tail() {
+        let text = format!("HEAD{}TAIL", "x".repeat(4000));
+        let truncated = truncate_to_budget(&text, 50);
+
+        assert!(truncated.starts_with("HEAD"));
+        assert!(truncated.ends_with("TAIL"));
+        assert!(truncated.contains("truncated"));
+        assert!(estimate_tokens(&truncated) <= 50);
+    }
+
+    #[test]
+    fn test_build_request_attaches_context_to_last_message() {
+        let history = vec![ChatMessage::user("Explain this file")];
+        let attachments = vec![ContextAttachment {
+            kind: ContextKind::File,
+            name: "main.rs".to_string(),
+            content: "fn main() {}".to_string(),
+        }];
+
+        let request = build_request(&history, &attachments, 1000);
+        assert_eq!(request.len(), 1);
+        assert!(request[0].content.contains("main.rs"));
+        assert!(request[0].content.contains("fn main() {}"));
+        assert!(request[0].content.ends_with("Explain this file"));
+    }
+
+    #[test]
+    fn test_build_request_drops_oldest_history() {
+        let history = vec![
+            ChatMessage::user("a".repeat(400)),
+            ChatMessage::assistant("b".repeat(400)),
+            ChatMessage::user("c".repeat(400)),
+            ChatMessage::assistant("d".repeat(40)),
+            ChatMessage::user("latest"),
+        ];
+
+        let request = build_request(&history, &[], 150);
+        assert_eq!(request.len(), 3);
+        assert_eq!(request[0].content, "c".repeat(400));
+        assert_eq!(request[2].content, "latest");
+    }
+
+    #[test]
+    fn test_build_request_starts_with_user_message() {
+        let history = vec![
+            ChatMessage::user("a".repeat(400)),
+            ChatMessage::assistant("b".repeat(400)),
+            ChatMessage::user("latest"),
+        ];
+
+        let request = build_request(&history, &[], 150);
+        assert_eq!(request.len(), 1);
+        assert_eq!(request[0].role, ChatRole::User);
+        assert_eq!(request[0].content, "latest");
+    }
+
+    #[test]
+    fn test_extract_code_blocks() {
+        let reply = "Try this:\n\n```rust\nfn main() {}\n```\n\nor\n\n```\nplain\n```\n";
+        let blocks = extract_code_blocks(reply);
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].lang, "rust");
+        assert_eq!(blocks[0].code, "fn main() {}\n");
+        assert_eq!(blocks[1].lang, "");
+    }
+
+    #[test]
+    fn test_conversation_roundtrip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("chats").join("test.json");
+
+        let mut conversation = Conversation::new();
+        conversation.push(ChatMessage::user("hello"));
+        conversation.push(ChatMessage::assistant("hi"));
+        conversation.save_to(&path).unwrap();
+
+        let loaded = Conversation::load_from(&path).unwrap();
+        assert_eq!(loaded, conversation);
+    }
+
+    #[test]
+    fn test_workspace_key_is_file_name_safe() {
+        let key = workspace_key(Path::new("/home/user/my project"));
+        assert!(key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+    }
+}
@@ -13,8 +13,14 @@ export GEMINI_API_KEY="your_api_key_here"
 ```
 
 This feature is optional and only enabled when the API key is set.
+
+The [`chat`] submodule holds the conversation model used by the AI chat panel.
 */
 
+pub mod chat;
+
+use chat::ChatMessage;
+
 #[cfg(feature = "llm")]
 use serde::{Deserialize, Serialize};
 
@@ -27,6 +33,8 @@ struct GeminiRequest {
 #[cfg(feature = "llm")]
 #[derive(Debug, Serialize)]
 struct Content {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
     parts: Vec<Part>,
 }
 
@@ -61,7 +69,7 @@ struct ResponsePart {
 }
 
 /// Gemini LLM client
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GeminiClient {
     #[allow(dead_code)]
     api_key: String,
@@ -138,21 +146,58 @@ impl GeminiClient {
         }
     }
 
+    /// Continue a multi-turn conversation with Gemini
+    pub fn chat(&self, messages: &[ChatMessage]) -> Result<String, String> {
+        #[cfg(feature = "llm")]
+        {
+            use chat::ChatRole;
+
+            let contents = messages
+                .iter()
+                .map(|message| Content {
+                    role: Some(
+                        match message.role {
+                            ChatRole::User => "user",
+                            ChatRole::Assistant => "model",
+                        }
+                        .to_string(),
+                    ),
+                    parts: vec![Part {
+                        text: message.content.clone(),
+                    }],
+                })
+                .collect();
+            self.send_request(GeminiRequest { contents })
+        }
+
+        #[cfg(not(feature = "llm"))]
+        {
+            let _ = messages; // Suppress unused warning
+            Err("LLM feature is not enabled. Build with --features llm".to_string())
+        }
+    }
+
     #[cfg(feature = "llm")]
     fn call_gemini(&self, prompt: &str) -> Result<String, String> {
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/gemini-1.5-flash:generateContent?key={}",
-            self.api_key
-        );
-
         let request = GeminiRequest {
             contents: vec![Content {
+                role: None,
                 parts: vec![Part {
                     text: prompt.to_string(),
                 }],
             }],
         };
 
+        self.send_request(request)
+    }
+
+    #[cfg(feature = "llm")]
+    fn send_request(&self, request: GeminiRequest) -> Result<String, String> {
+        let url = format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-1.5-flash:generateContent?key={}",
+            self.api_key
+        );
+
         let response = self
             .client
             .post(&url)
@@ -172,7 +217,8 @@ impl GeminiClient {
             .map_err(|e| format!("Failed to parse response: {e}"))?;
 
         gemini_response
-            .candidates.first()
+            .candidates
+            .first()
             .and_then(|c| c.content.parts.first())
             .map(|p| p.text.clone())
             .ok_or_else(|| "No response from Gemini".to_string())
@@ -88,9 +88,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             // Render to terminal
             if no_color {
-                println!("{content}");
+                markdown_view::render_markdown_plain(&content);
             } else {
-                markdown_view::render_markdown_to_terminal(&content);
+                markdown_view::render_markdown_to_terminal(&content, file.parent());
             }
 
             return Ok(());
@@ -107,19 +107,48 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             if no_color {
                 html_view::render_html_plain(&content);
             } else {
-                html_view::render_html_to_terminal(&content);
+                html_view::render_html_to_terminal(&content, file.parent());
             }
 
             return Ok(());
         }
 
-        StartupMode::MermaidPreview { file, no_color } => {
+        StartupMode::MermaidPreview {
+            file,
+            no_color,
+            svg,
+            check,
+        } => {
             // Read Mermaid file
             let content = fs::read_to_string(&file).unwrap_or_else(|err| {
                 eprintln!("Error reading file {file:?}: {err}");
                 process::exit(1);
             });
 
+            // Lint only, for CI
+            if check {
+                let (problems, ok) =
+                    mermaid_view::check_mermaid(&file.display().to_string(), &content);
+                for problem in &problems {
+                    eprintln!("{problem}");
+                }
+                if !ok {
+                    process::exit(1);
+                }
+                return Ok(());
+            }
+
+            // Export instead of printing
+            if let Some(out) = svg {
+                let written = mermaid_view::render_mermaid_svg(&content)
+                    .and_then(|svg| fs::write(&out, svg).map_err(|err| err.to_string()));
+                if let Err(err) = written {
+                    eprintln!("Error exporting {file:?} to {out:?}: {err}");
+                    process::exit(1);
+                }
+                return Ok(());
+            }
+
             // Render to terminal
             if no_color {
                 mermaid_view::render_mermaid_plain(&content);
@@ -137,16 +166,67 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 process::exit(1);
             });
 
-            // Render to terminal
+            // Render to terminal; includes are relative to the file
+            let base_dir = file.parent();
             if no_color {
-                latex_view::render_latex_plain(&content);
+                latex_view::render_latex_plain(&content, base_dir);
+            } else {
+                latex_view::render_latex_to_terminal(&content, base_dir);
+            }
+
+            return Ok(());
+        }
+
+        StartupMode::Export {
+            file,
+            format,
+            output,
+        } => {
+            let content = fs::read_to_string(&file).unwrap_or_else(|err| {
+                eprintln!("Error reading file {file:?}: {err}");
+                process::exit(1);
+            });
+
+            let exported = lala::export::export(&file, &content, format).unwrap_or_else(|err| {
+                eprintln!("Error exporting {file:?}: {err}");
+                process::exit(1);
+            });
+            for warning in &exported.warnings {
+                eprintln!("Warning: {warning}");
+            }
+            let bytes = exported.bytes;
+
+            // `-o -` writes to standard output for pipes
+            let out = output.unwrap_or_else(|| file.with_extension(format.extension()));
+            let written = if out.as_os_str() == "-" {
+                std::io::Write::write_all(&mut std::io::stdout(), &bytes)
             } else {
-                latex_view::render_latex_to_terminal(&content);
+                fs::write(&out, &bytes)
+            };
+            if let Err(err) = written {
+                eprintln!("Error writing {out:?}: {err}");
+                process::exit(1);
             }
 
             return Ok(());
         }
 
+        StartupMode::CheckLinks { path } => {
+            if !path.exists() {
+                eprintln!("Error: {path:?} does not exist");
+                process::exit(1);
+            }
+            let broken = lala::document::links::check_dir(&path);
+            for link in &broken {
+                println!("{link}");
+            }
+            if !broken.is_empty() {
+                eprintln!("{} broken link(s)", broken.len());
+                process::exit(1);
+            }
+            return Ok(());
+        }
+
         StartupMode::ViewFile { file, line_numbers } => {
             // Read file
             let content = fs::read_to_string(&file).unwrap_or_else(|err| {
@@ -0,0 +1,108 @@
+//! Stroke geometry shared by the drawing backends
+//!
+//! Radical signs, stretched delimiters and arrows are drawn as polylines so
+//! they can grow to any size. The egui painter and the export renderers
+//! both take their shapes from here, so a formula looks the same on screen
+//! and on paper.
+
+use super::layout::Rect;
+
+/// A point in layout coordinates (`y` grows downwards)
+pub type Point = (f32, f32);
+
+/// The check mark of a radical in `rect`, ending where the overline of
+/// thickness `rule` starts
+pub fn radical(rect: Rect, rule: f32) -> Vec<Point> {
+    let at = |fx: f32, fy: f32| (rect.x + fx * rect.width, rect.y + fy * rect.height);
+    vec![
+        at(0.0, 0.62),
+        at(0.18, 0.55),
+        at(0.45, 1.0),
+        (rect.x + rect.width, rect.y + rule / 2.0),
+    ]
+}
+
+/// A right arrow along the middle of `rect`: the shaft and the head
+pub fn arrow(rect: Rect) -> Vec<Vec<Point>> {
+    let y = rect.y + rect.height / 2.0;
+    let head = rect.height * 0.5;
+    let (left, right) = (rect.x, rect.x + rect.width);
+    vec![
+        vec![(left, y), (right, y)],
+        vec![
+            (right - head, y - head),
+            (right, y),
+            (right - head, y + head),
+        ],
+    ]
+}
+
+/// Polylines drawing the delimiter `text` to the full height of `rect`
+///
+/// `None` for delimiters without a stroke shape; backends draw those as a
+/// character at its natural size.
+pub fn delimiter(text: &str, rect: Rect) -> Option<Vec<Vec<Point>>> {
+    let x = |f: f32| rect.x + f * rect.width;
+    let (top, bottom) = (rect.y, rect.y + rect.height);
+    let middle = rect.y + rect.height / 2.0;
+    let hook = (rect.height * 0.08).min(rect.width);
+    let lines = match text {
+        "(" | ")" => {
+            let steps = 24;
+            let arc = (0..=steps)
+                .map(|i| {
+                    let t = i as f32 / steps as f32;
+                    let bulge = 1.0 - (2.0 * t - 1.0).powi(2);
+                    let fx = 0.8 - 0.55 * bulge;
+                    let fx = if text == ")" { 1.0 - fx } else { fx };
+                    (x(fx), top + t * rect.height)
+                })
+                .collect();
+            vec![arc]
+        }
+        "[" | "⌈" | "⌊" | "]" | "⌉" | "⌋" => {
+            let (inner, outer) = if matches!(text, "[" | "⌈" | "⌊") {
+                (x(0.8), x(0.3))
+            } else {
+                (x(0.2), x(0.7))
+            };
+            let mut line = Vec::new();
+            if text != "⌊" && text != "⌋" {
+                line.push((inner, top));
+            }
+            line.push((outer, top));
+            line.push((outer, bottom));
+            if text != "⌈" && text != "⌉" {
+                line.push((inner, bottom));
+            }
+            vec![line]
+        }
+        "{" | "}" => {
+            let flip = |fx: f32| if text == "}" { x(1.0 - fx) } else { x(fx) };
+            vec![vec![
+                (flip(0.85), top),
+                (flip(0.5), top + hook),
+                (flip(0.5), middle - hook),
+                (flip(0.15), middle),
+                (flip(0.5), middle + hook),
+                (flip(0.5), bottom - hook),
+                (flip(0.85), bottom),
+            ]]
+        }
+        "⟨" | "⟩" => {
+            let (outer, inner) = if text == "⟨" {
+                (x(0.8), x(0.2))
+            } else {
+                (x(0.2), x(0.8))
+            };
+            vec![vec![(outer, top), (inner, middle), (outer, bottom)]]
+        }
+        "|" => vec![vec![(x(0.5), top), (x(0.5), bottom)]],
+        "‖" => vec![
+            vec![(x(0.3), top), (x(0.3), bottom)],
+            vec![(x(0.7), top), (x(0.7), bottom)],
+        ],
+        _ => return None,
+    };
+    Some(lines)
+}
@@ -0,0 +1,231 @@
+//! Multi-line Unicode rendering for the terminal
+//!
+//! Lays a formula out in character cells and rasterizes the items:
+//! fraction bars become `─`, radicals `╲╱` with an `_` overline, tall
+//! delimiters and integrals are assembled from the bracket pieces
+//! (`⎛⎜⎝`, `⎡⎢⎣`, `⎧⎨⎩`, `⌠⎮⌡`).
+//!
+//! ```text
+//!           2
+//!  ∞  1    π
+//!  ∑  ── = ──
+//! n=1  2   6
+//!     n
+//! ```
+
+use unicode_width::UnicodeWidthChar;
+
+use super::layout::{self, Cells, Item, Layout, Metrics, Rect, RuleKind};
+use super::parser::parse;
+
+/// Render a formula as lines of text (without trailing spaces)
+pub fn render(tex: &str, display: bool) -> Vec<String> {
+    let layout = layout::layout(&parse(tex), display, &Cells, &Metrics::cells());
+    rasterize(&layout)
+}
+
+/// Character canvas; `None` marks the second column of a wide character
+struct Canvas {
+    cells: Vec<Vec<Option<String>>>,
+    /// Row of the baseline, added to item `y` coordinates
+    origin: f32,
+}
+
+impl Canvas {
+    fn put(&mut self, x: f32, row: f32, c: char) {
+        let (x, row) = (x as isize, (row + self.origin) as isize);
+        let Some(cells) = usize::try_from(row)
+            .ok()
+            .and_then(|r| self.cells.get_mut(r))
+        else {
+            return;
+        };
+        let Ok(x) = usize::try_from(x) else { return };
+        if c.width() == Some(0) {
+            // Combining marks join the previous character
+            let end = x.min(cells.len());
+            if let Some(cell) = cells[..end].iter_mut().rev().flatten().next() {
+                cell.push(c);
+            }
+            return;
+        }
+        if x >= cells.len() {
+            return;
+        }
+        cells[x] = Some(c.to_string());
+        if c.width() == Some(2) && x + 1 < cells.len() {
+            cells[x + 1] = None;
+        }
+    }
+
+    fn text(&mut self, x: f32, row: f32, text: &str) {
+        let mut x = x;
+        for c in text.chars() {
+            self.put(x, row, c);
+            x += c.width().unwrap_or(0) as f32;
+        }
+    }
+
+    fn lines(self) -> Vec<String> {
+        self.cells
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .flatten()
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+}
+
+fn rasterize(layout: &Layout) -> Vec<String> {
+    let rows = (layout.height + layout.depth).round().max(1.0) as usize;
+    let columns = layout.width.round().max(0.0) as usize;
+    let mut canvas = Canvas {
+        cells: vec![vec![Some(" ".to_string()); columns]; rows],
+        origin: layout.height.round(),
+    };
+
+    for item in &layout.items {
+        match item {
+            // A glyph on baseline `y` occupies the row above it
+            Item::Glyph { text, x, y, .. } => canvas.text(*x, y - 1.0, text),
+            Item::Rule { rect, kind } => rule(&mut canvas, *rect, *kind),
+            Item::Radical { rect } => radical(&mut canvas, *rect),
+            Item::Delimiter { text, rect } => delimiter(&mut canvas, text, *rect),
+        }
+    }
+    canvas.lines()
+}
+
+fn rule(canvas: &mut Canvas, rect: Rect, kind: RuleKind) {
+    let width = rect.width as usize;
+    let line = match kind {
+        RuleKind::Fraction => "─".repeat(width),
+        RuleKind::Over => "_".repeat(width),
+        RuleKind::Under => "‾".repeat(width),
+        RuleKind::Arrow => format!("{}→", "─".repeat(width.saturating_sub(1))),
+    };
+    canvas.text(rect.x, rect.y, &line);
+}
+
+/// `√` for one row, otherwise a `╲╱` tick with a rising `╱` stroke
+fn radical(canvas: &mut Canvas, rect: Rect) {
+    let rows = rect.height as usize;
+    let bottom = rect.y + rect.height - 1.0;
+    if rows <= 1 {
+        canvas.put(rect.x, bottom, '√');
+        return;
+    }
+    canvas.put(rect.x, bottom, '╲');
+    for i in 0..rows {
+        canvas.put(rect.x + 1.0 + i as f32, bottom - i as f32, '╱');
+    }
+}
+
+/// Stretched delimiter from top, middle, extension and bottom pieces
+fn delimiter(canvas: &mut Canvas, text: &str, rect: Rect) {
+    let rows = rect.height as usize;
+    let Some(first) = text.chars().next() else {
+        return;
+    };
+    if rows <= 1 {
+        canvas.text(rect.x, rect.y, text);
+        return;
+    }
+
+    let (top, extension, middle, bottom) = match first {
+        '(' => ('⎛', '⎜', None, '⎝'),
+        ')' => ('⎞', '⎟', None, '⎠'),
+        '[' => ('⎡', '⎢', None, '⎣'),
+        ']' => ('⎤', '⎥', None, '⎦'),
+        '{' => ('⎧', '⎪', Some('⎨'), '⎩'),
+        '}' => ('⎫', '⎪', Some('⎬'), '⎭'),
+        '⌈' => ('⎡', '⎢', None, '⎢'),
+        '⌉' => ('⎤', '⎥', None, '⎥'),
+        '⌊' => ('⎢', '⎢', None, '⎣'),
+        '⌋' => ('⎥', '⎥', None, '⎦'),
+        '|' => ('│', '│', None, '│'),
+        '‖' => ('║', '║', None, '║'),
+        '∫' | '∬' | '∭' | '∮' => ('⌠', '⎮', None, '⌡'),
+        '⟨' | '⟩' => {
+            // Two slanted halves meeting in the middle
+            let (upper, lower) = if first == '⟨' {
+                ('╱', '╲')
+            } else {
+                ('╲', '╱')
+            };
+            for i in 0..rows {
+                let c = if i < rows / 2 { upper } else { lower };
+                canvas.put(rect.x, rect.y + i as f32, c);
+            }
+            return;
+        }
+        c => (c, c, None, c),
+    };
+
+    let center = rows / 2;
+    for i in 0..rows {
+        let c = match i {
+            0 => top,
+            i if i == rows - 1 => bottom,
+            i if i == center => middle.unwrap_or(extension),
+            _ => extension,
+        };
+        // Several integral signs (`\iint`) are drawn side by side
+        let count = if matches!(first, '∫' | '∬' | '∭' | '∮') {
+            rect.width as usize
+        } else {
+            1
+        };
+        for column in 0..count {
+            canvas.put(rect.x + column as f32, rect.y + i as f32, c);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn art(tex: &str) -> String {
+        render(tex, true).join("\n")
+    }
+
+    #[test]
+    fn test_fraction_and_scripts() {
+        assert_eq!(art(r"x = \frac{-b}{2a}"), "    −b\nx = ──\n    2a");
+        assert_eq!(art("x_i^2 + 1"), " 2\nx  + 1\n i");
+    }
+
+    #[test]
+    fn test_radicals() {
+        assert_eq!(art(r"\sqrt{x+1}"), " _____\n√x + 1");
+        assert_eq!(art(r"\sqrt{\frac{a}{b}}"), "    _\n   ╱a\n  ╱ ─\n╲╱  b");
+        assert_eq!(art(r"\sqrt[3]{x}"), "3_\n√x");
+    }
+
+    #[test]
+    fn test_tall_delimiters_and_matrices() {
+        assert_eq!(art(r"\left(\frac{1}{2}\right)"), "⎛1⎞\n⎜─⎟\n⎝2⎠");
+        assert_eq!(
+            art(r"\begin{bmatrix} 1 & 0 \\ 0 & 1 \end{bmatrix}"),
+            "⎡1  0⎤\n⎣0  1⎦"
+        );
+    }
+
+    #[test]
+    fn test_display_operators() {
+        assert_eq!(art(r"\int_0^1 f"), "⌠1\n⎮  f\n⌡0");
+        assert_eq!(art(r"\iint f"), "⌠⌠\n⎮⎮ f\n⌡⌡");
+        assert_eq!(art(r"\sum_{i=1}^{n} i"), " n\n ∑  i\ni=1");
+    }
+
+    #[test]
+    fn test_accents() {
+        assert_eq!(art(r"\hat{x} + \overline{AB}"), "    __\nx̂ + AB");
+        assert_eq!(art(r"\vec{AB}"), "─→\nAB");
+    }
+}
@@ -0,0 +1,867 @@
+//! Box layout for formulas
+//!
+//! Every node becomes a box with a width, a height above the baseline and a
+//! depth below it, built bottom-up the way TeX does: rows add the spacing
+//! of their atom classes, scripts are raised and lowered against their
+//! base, fractions and matrices are centred on the math axis, delimiters
+//! and radicals grow with their contents.
+//!
+//! The result is a flat list of [`Item`]s relative to the left end of the
+//! baseline. All distances come from a [`Measure`] and a set of
+//! [`Metrics`], so the same code lays out pixels for the egui painter and
+//! whole character cells for the terminal.
+
+use unicode_width::UnicodeWidthStr;
+
+use super::model::{Class, ColumnAlign, Font, Mark, Node};
+
+/// Rectangle with `y` growing downwards from the baseline
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// Text measurement for the target backend
+pub trait Measure {
+    /// Advance width of `text` at `scale` times the base size
+    fn width(&self, text: &str, font: Font, scale: f32) -> f32;
+}
+
+impl<F: Fn(&str, Font, f32) -> f32> Measure for F {
+    fn width(&self, text: &str, font: Font, scale: f32) -> f32 {
+        self(text, font, scale)
+    }
+}
+
+/// Terminal measurement: one unit per display column
+#[derive(Debug, Clone, Copy)]
+pub struct Cells;
+
+impl Measure for Cells {
+    fn width(&self, text: &str, _font: Font, _scale: f32) -> f32 {
+        text.width() as f32
+    }
+}
+
+/// Distances used by the layout, in the unit of the [`Measure`]
+#[derive(Debug, Clone, Copy)]
+pub struct Metrics {
+    /// Size of one em
+    pub em: f32,
+    /// Height and depth of a line of text
+    pub ascent: f32,
+    pub descent: f32,
+    /// Height of the math axis (fraction bars) above the baseline
+    pub axis: f32,
+    /// Thickness of fraction bars and radical overlines
+    pub rule: f32,
+    /// Clearance around bars, overlines and limits
+    pub gap: f32,
+    /// Size of scripts relative to their base
+    pub script_scale: f32,
+    /// Minimum raise of superscripts and drop of subscripts
+    pub sup_raise: f32,
+    pub sub_drop: f32,
+    /// How far scripts reach into a tall base
+    pub script_drop: f32,
+    /// Thin, medium and thick spaces between atoms
+    pub spaces: [f32; 3],
+    /// Space between matrix rows
+    pub row_gap: f32,
+    /// Size of large operators in display style
+    pub operator_scale: f32,
+    /// Width of a stretched delimiter
+    pub delimiter_width: f32,
+    /// Whole character cells: positions are rounded and some shapes
+    /// (radicals, integrals) are built from several cells
+    pub cells: bool,
+}
+
+impl Metrics {
+    /// Pixel metrics for a font of `size` pixels
+    pub fn pixels(size: f32) -> Self {
+        Self {
+            em: size,
+            ascent: 0.78 * size,
+            descent: 0.22 * size,
+            axis: 0.25 * size,
+            rule: (0.05 * size).max(1.0),
+            gap: 0.12 * size,
+            script_scale: 0.7,
+            sup_raise: 0.4 * size,
+            sub_drop: 0.2 * size,
+            script_drop: 0.3 * size,
+            spaces: [size * 3.0 / 18.0, size * 4.0 / 18.0, size * 5.0 / 18.0],
+            row_gap: 0.3 * size,
+            operator_scale: 1.4,
+            delimiter_width: 0.4 * size,
+            cells: false,
+        }
+    }
+
+    /// Character cell metrics, measured with [`Cells`]
+    pub fn cells() -> Self {
+        Self {
+            em: 2.0,
+            ascent: 1.0,
+            descent: 0.0,
+            axis: 0.5,
+            rule: 1.0,
+            gap: 0.0,
+            script_scale: 1.0,
+            sup_raise: 1.0,
+            sub_drop: 1.0,
+            script_drop: 1.0,
+            spaces: [1.0, 1.0, 1.0],
+            row_gap: 0.0,
+            operator_scale: 1.0,
+            delimiter_width: 1.0,
+            cells: true,
+        }
+    }
+}
+
+/// How a [`Item::Rule`] is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleKind {
+    Fraction,
+    /// Radical overline and `\overline`
+    Over,
+    Under,
+    /// Right arrow over the base (`\vec` on several letters)
+    Arrow,
+}
+
+/// A drawing primitive of a laid out formula
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    /// Text starting at `x` on the baseline `y`
+    Glyph {
+        text: String,
+        x: f32,
+        y: f32,
+        scale: f32,
+        font: Font,
+    },
+    Rule {
+        rect: Rect,
+        kind: RuleKind,
+    },
+    /// Radical sign filling `rect`, left of the overline
+    Radical {
+        rect: Rect,
+    },
+    /// Delimiter (or integral sign) stretched over `rect`
+    Delimiter {
+        text: String,
+        rect: Rect,
+    },
+}
+
+impl Item {
+    fn translate(&mut self, dx: f32, dy: f32) {
+        match self {
+            Item::Glyph { x, y, .. } => {
+                *x += dx;
+                *y += dy;
+            }
+            Item::Rule { rect, .. } | Item::Radical { rect } | Item::Delimiter { rect, .. } => {
+                rect.x += dx;
+                rect.y += dy;
+            }
+        }
+    }
+}
+
+/// A laid out box; items are relative to the left end of its baseline
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Layout {
+    pub width: f32,
+    /// Extent above the baseline
+    pub height: f32,
+    /// Extent below the baseline
+    pub depth: f32,
+    pub items: Vec<Item>,
+}
+
+impl Layout {
+    /// Add `other` with its baseline origin at (`x`, `y`)
+    fn place(&mut self, other: Layout, x: f32, y: f32) {
+        self.height = self.height.max(other.height - y);
+        self.depth = self.depth.max(other.depth + y);
+        self.width = self.width.max(x + other.width);
+        self.items.extend(other.items.into_iter().map(|mut item| {
+            item.translate(x, y);
+            item
+        }));
+    }
+}
+
+/// Lay out a formula, in display style (`$$...$$`) or inline
+pub fn layout(node: &Node, display: bool, measure: &dyn Measure, metrics: &Metrics) -> Layout {
+    Engine { measure, metrics }.node(node, Style { display, level: 0 })
+}
+
+/// Space inserted between two atoms
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Gap {
+    Thin,
+    Medium,
+    Thick,
+}
+
+/// Space before each node of a row, following TeX's spacing table
+///
+/// Binary operators without a left operand (`-x`, `(+1)`) act as ordinary
+/// atoms. Medium and thick spaces are dropped in scripts.
+pub(crate) fn gaps(nodes: &[Node], script: bool) -> Vec<Option<Gap>> {
+    let is_space = |node: &Node| matches!(node, Node::Space(_));
+    let mut classes: Vec<Class> = nodes.iter().map(Node::class).collect();
+
+    let mut previous: Option<usize> = None;
+    for index in 0..nodes.len() {
+        if is_space(&nodes[index]) {
+            continue;
+        }
+        if classes[index] == Class::Bin {
+            let next = (index + 1..nodes.len()).find(|&i| !is_space(&nodes[i]));
+            let unary = previous.is_none_or(|p| {
+                matches!(
+                    classes[p],
+                    Class::Bin | Class::Op { .. } | Class::Rel | Class::Open | Class::Punct
+                )
+            });
+            let dangling =
+                next.is_none_or(|n| matches!(classes[n], Class::Rel | Class::Close | Class::Punct));
+            if unary || dangling {
+                classes[index] = Class::Ord;
+            }
+        }
+        previous = Some(index);
+    }
+
+    let mut result = vec![None; nodes.len()];
+    let mut previous: Option<Class> = None;
+    for (index, node) in nodes.iter().enumerate() {
+        if is_space(node) {
+            continue;
+        }
+        let class = classes[index];
+        if let Some(left) = previous {
+            result[index] = gap(left, class, script);
+        }
+        previous = Some(class);
+    }
+    result
+}
+
+fn gap(left: Class, right: Class, script: bool) -> Option<Gap> {
+    use Class::*;
+    let (gap, everywhere) = match (left, right) {
+        (Ord | Close, Op { .. }) | (Op { .. }, Ord | Op { .. }) => (Gap::Thin, true),
+        (Ord | Close | Op { .. }, Bin) | (Bin, Ord | Op { .. } | Open) => (Gap::Medium, false),
+        (Ord | Close | Op { .. }, Rel) | (Rel, Ord | Op { .. } | Open) => (Gap::Thick, false),
+        (Punct, Ord | Op { .. } | Rel | Open | Close | Punct) => (Gap::Thin, false),
+        _ => return None,
+    };
+    (everywhere || !script).then_some(gap)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Style {
+    display: bool,
+    /// Script depth: 0 for the formula, 1 for scripts, 2 for nested scripts
+    level: u8,
+}
+
+impl Style {
+    fn script(self) -> Self {
+        Style {
+            display: false,
+            level: (self.level + 1).min(2),
+        }
+    }
+
+    /// Style of a fraction's numerator and denominator
+    fn fraction(self) -> Self {
+        if self.display {
+            Style {
+                display: false,
+                ..self
+            }
+        } else {
+            self.script()
+        }
+    }
+}
+
+struct Engine<'a> {
+    measure: &'a dyn Measure,
+    metrics: &'a Metrics,
+}
+
+impl Engine<'_> {
+    fn scale(&self, style: Style) -> f32 {
+        self.metrics.script_scale.powi(style.level as i32)
+    }
+
+    /// Round to whole cells in cell mode
+    fn snap(&self, value: f32) -> f32 {
+        if self.metrics.cells {
+            (value + 0.5).floor()
+        } else {
+            value
+        }
+    }
+
+    /// Offset that centres `inner` in `outer`
+    fn center(&self, outer: f32, inner: f32) -> f32 {
+        if self.metrics.cells {
+            ((outer - inner) / 2.0).floor()
+        } else {
+            (outer - inner) / 2.0
+        }
+    }
+
+    fn node(&self, node: &Node, style: Style) -> Layout {
+        match node {
+            Node::Atom { text, class, font } => self.atom(text, *class, *font, style),
+            Node::Row(nodes) => self.row(nodes, style),
+            Node::Scripts { base, sub, sup } => {
+                self.scripts(base, sub.as_deref(), sup.as_deref(), style)
+            }
+            Node::Fraction {
+                numerator,
+                denominator,
+                bar,
+            } => self.fraction(numerator, denominator, *bar, style),
+            Node::Radical { index, radicand } => self.radical(index.as_deref(), radicand, style),
+            Node::Delimited { left, right, body } => self.delimited(left, right, body, style),
+            Node::Matrix {
+                rows,
+                columns,
+                column_gap,
+            } => self.matrix(rows, columns, *column_gap, style),
+            Node::Accent { base, mark } => self.accent(base, *mark, style),
+            Node::Space(em) => Layout {
+                width: self.snap(em * self.metrics.em * self.scale(style)),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn glyph(&self, text: &str, font: Font, scale: f32) -> Layout {
+        if text.is_empty() {
+            return Layout::default();
+        }
+        Layout {
+            width: self.measure.width(text, font, scale),
+            height: self.metrics.ascent * scale,
+            depth: self.metrics.descent * scale,
+            items: vec![Item::Glyph {
+                text: text.to_string(),
+                x: 0.0,
+                y: 0.0,
+                scale,
+                font,
+            }],
+        }
+    }
+
+    fn atom(&self, text: &str, class: Class, font: Font, style: Style) -> Layout {
+        let scale = self.scale(style);
+        let large = style.display && matches!(class, Class::Op { .. }) && is_large(text);
+        if !large {
+            return self.glyph(text, font, scale);
+        }
+
+        let m = self.metrics;
+        if m.cells && text.starts_with(['∫', '∬', '∭', '∮']) {
+            // ⌠ ⎮ ⌡ over three rows, centred on the baseline row
+            let width = match text.chars().next() {
+                Some('∬') => 2.0,
+                Some('∭') => 3.0,
+                _ => 1.0,
+            };
+            return Layout {
+                width,
+                height: 2.0,
+                depth: 1.0,
+                items: vec![Item::Delimiter {
+                    text: text.to_string(),
+                    rect: Rect::new(0.0, -2.0, width, 3.0),
+                }],
+            };
+        }
+
+        // Larger glyph centred on the axis
+        let mut layout = self.glyph(text, font, scale * m.operator_scale);
+        let shift = self.snap((layout.height - layout.depth) / 2.0 - m.axis * scale);
+        for item in &mut layout.items {
+            item.translate(0.0, shift);
+        }
+        layout.height -= shift;
+        layout.depth += shift;
+        layout
+    }
+
+    fn row(&self, nodes: &[Node], style: Style) -> Layout {
+        let mut layout = Layout::default();
+        let mut x = 0.0;
+        let scale = self.scale(style);
+        for (node, gap) in nodes.iter().zip(gaps(nodes, style.level > 0)) {
+            if let Some(gap) = gap {
+                let index = match gap {
+                    Gap::Thin => 0,
+                    Gap::Medium => 1,
+                    Gap::Thick => 2,
+                };
+                x += self.snap(self.metrics.spaces[index] * scale);
+            }
+            let child = self.node(node, style);
+            let width = child.width;
+            layout.place(child, x, 0.0);
+            x += width;
+        }
+        layout.width = x.max(0.0);
+        layout
+    }
+
+    fn scripts(&self, base: &Node, sub: Option<&Node>, sup: Option<&Node>, style: Style) -> Layout {
+        let m = self.metrics;
+        let scale = self.scale(style);
+        let base_layout = self.node(base, style);
+        let script_style = style.script();
+        let sub = sub.map(|node| self.node(node, script_style));
+        let sup_is_prime = sup.is_some_and(is_prime);
+        let sup = sup.map(|node| self.node(node, script_style));
+
+        if style.display && matches!(base.class(), Class::Op { limits: true }) {
+            return self.limits(base_layout, sub, sup);
+        }
+
+        let simple = matches!(base, Node::Atom { .. });
+        let mut raise = m.sup_raise * scale;
+        let mut drop = m.sub_drop * scale;
+        if !simple {
+            raise = raise.max(base_layout.height - m.script_drop * scale);
+            drop = drop.max(base_layout.depth + m.gap * scale);
+        }
+        if sup_is_prime {
+            // Primes sit next to the base (the glyph itself is raised)
+            raise = if m.cells { 0.0 } else { raise * 0.5 };
+        }
+        if let (Some(sup), Some(sub)) = (&sup, &sub) {
+            let clearance = 4.0 * m.rule * scale;
+            let space = (raise - sup.depth) - (sub.height - drop);
+            if !m.cells && space < clearance {
+                drop += clearance - space;
+            }
+        }
+
+        let x = base_layout.width + if m.cells { 0.0 } else { 0.05 * m.em * scale };
+        let mut layout = base_layout;
+        let mut width = layout.width;
+        if let Some(sup) = sup {
+            width = width.max(x + sup.width);
+            layout.place(sup, x, -self.snap(raise));
+        }
+        if let Some(sub) = sub {
+            width = width.max(x + sub.width);
+            layout.place(sub, x, self.snap(drop));
+        }
+        layout.width = width;
+        layout
+    }
+
+    /// Scripts of `\sum`-like operators centred above and below
+    fn limits(&self, base: Layout, sub: Option<Layout>, sup: Option<Layout>) -> Layout {
+        let gap = self.metrics.gap;
+        let width = [Some(&base), sub.as_ref(), sup.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(|layout| layout.width)
+            .fold(0.0, f32::max);
+
+        let mut layout = Layout::default();
+        let (base_height, base_depth) = (base.height, base.depth);
+        let x = self.center(width, base.width);
+        layout.place(base, x, 0.0);
+        if let Some(sup) = sup {
+            let (x, y) = (
+                self.center(width, sup.width),
+                -(base_height + gap + sup.depth),
+            );
+            layout.place(sup, x, y);
+        }
+        if let Some(sub) = sub {
+            let (x, y) = (self.center(width, sub.width), base_depth + gap + sub.height);
+            layout.place(sub, x, y);
+        }
+        layout.width = width;
+        layout
+    }
+
+    fn fraction(&self, numerator: &Node, denominator: &Node, bar: bool, style: Style) -> Layout {
+        let m = self.metrics;
+        let scale = self.scale(style);
+        let inner = style.fraction();
+        let numerator = self.node(numerator, inner);
+        let denominator = self.node(denominator, inner);
+
+        let padding = m.gap * scale;
+        let width = numerator.width.max(denominator.width) + 2.0 * padding;
+        let axis = m.axis * scale;
+        let rule = if m.cells {
+            m.rule
+        } else {
+            m.rule * scale.max(0.7)
+        };
+        let clearance = m.gap * scale * if style.display { 1.5 } else { 1.0 };
+
+        let mut layout = Layout {
+            width,
+            ..Default::default()
+        };
+        let bar_top = self.snap(-axis - rule / 2.0);
+        if bar {
+            layout.items.push(Item::Rule {
+                rect: Rect::new(0.0, bar_top, width, rule),
+                kind: RuleKind::Fraction,
+            });
+        }
+        let numerator_y = bar_top - clearance - numerator.depth;
+        let denominator_y = bar_top + rule + clearance + denominator.height;
+        let x = self.center(width, numerator.width);
+        layout.place(numerator, x, numerator_y);
+        let x = self.center(width, denominator.width);
+        layout.place(denominator, x, denominator_y);
+        layout
+    }
+
+    fn radical(&self, index: Option<&Node>, radicand: &Node, style: Style) -> Layout {
+        let m = self.metrics;
+        let scale = self.scale(style);
+        let radicand = self.node(radicand, style);
+        let index = index.map(|node| self.node(node, style.script().script()));
+        let clearance = m.gap * scale;
+        let rule = if m.cells {
+            m.rule
+        } else {
+            m.rule * scale.max(0.7)
+        };
+        let inner_height = radicand.height + radicand.depth;
+        let top = -(radicand.height + clearance + rule);
+
+        let (sign_width, sign_rect_y, sign_height) = if m.cells {
+            let rows = inner_height.max(1.0);
+            let width = if rows <= 1.0 { 1.0 } else { rows + 1.0 };
+            (width, -radicand.height, rows)
+        } else {
+            (
+                0.55 * m.em * scale + 0.05 * inner_height,
+                top,
+                inner_height + clearance + rule,
+            )
+        };
+
+        let mut layout = Layout::default();
+        let sign_x = match &index {
+            Some(index) if m.cells => (index.width - 1.0).max(0.0),
+            Some(index) => (index.width - 0.35 * sign_width).max(0.0),
+            None => 0.0,
+        };
+        if let Some(index) = index {
+            let y = if m.cells {
+                top + rule - index.depth
+            } else {
+                top + 0.45 * sign_height - index.depth
+            };
+            layout.place(index, 0.0, y);
+        }
+
+        layout.items.push(Item::Radical {
+            rect: Rect::new(sign_x, sign_rect_y, sign_width, sign_height),
+        });
+        let x = sign_x + sign_width + if m.cells { 0.0 } else { 0.05 * m.em * scale };
+        let overline_width = radicand.width + if m.cells { 0.0 } else { 0.1 * m.em * scale };
+        layout.items.push(Item::Rule {
+            rect: Rect::new(
+                sign_x + sign_width,
+                top,
+                x - sign_x - sign_width + overline_width,
+                rule,
+            ),
+            kind: RuleKind::Over,
+        });
+        layout.height = layout.height.max(-top);
+        layout.place(radicand, x, 0.0);
+        layout.width = x + overline_width;
+        layout
+    }
+
+    fn delimited(&self, left: &str, right: &str, body: &Node, style: Style) -> Layout {
+        let m = self.metrics;
+        let scale = self.scale(style);
+        let body = self.node(body, style);
+        let (height, depth) = (body.height, body.depth);
+        let line = body.height <= m.ascent * scale * 1.05 && body.depth <= m.descent * scale * 1.3;
+        let padding = if m.cells { 0.0 } else { 0.05 * m.em * scale };
+
+        let delimiter = |text: &str| -> Layout {
+            if text.is_empty() {
+                return Layout::default();
+            }
+            if line {
+                return self.glyph(text, Font::Upright, scale);
+            }
+            let (y, height) = if m.cells {
+                (-height, height + depth)
+            } else {
+                let axis = m.axis * scale;
+                let half = (height - axis).max(depth + axis) + 0.1 * m.em * scale;
+                (-axis - half, 2.0 * half)
+            };
+            let width = m.delimiter_width * if m.cells { 1.0 } else { scale };
+            Layout {
+                width,
+                height: -y,
+                depth: y + height,
+                items: vec![Item::Delimiter {
+                    text: text.to_string(),
+                    rect: Rect::new(0.0, y, width, height),
+                }],
+            }
+        };
+
+        let mut layout = Layout::default();
+        let left = delimiter(left);
+        let mut x = left.width;
+        layout.place(left, 0.0, 0.0);
+        if x > 0.0 {
+            x += padding;
+        }
+        let body_width = body.width;
+        layout.place(body, x, 0.0);
+        x += body_width;
+        let right = delimiter(right);
+        if right.width > 0.0 {
+            x += padding;
+        }
+        let right_width = right.width;
+        layout.place(right, x, 0.0);
+        layout.width = x + right_width;
+        layout
+    }
+
+    fn matrix(
+        &self,
+        rows: &[Vec<Node>],
+        columns: &[ColumnAlign],
+        column_gap: f32,
+        style: Style,
+    ) -> Layout {
+        let m = self.metrics;
+        let scale = self.scale(style);
+        let cells: Vec<Vec<Layout>> = rows
+            .iter()
+            .map(|row| row.iter().map(|cell| self.node(cell, style)).collect())
+            .collect();
+
+        let count = cells.iter().map(Vec::len).max().unwrap_or(0);
+        let mut widths = vec![0.0f32; count];
+        for row in &cells {
+            for (column, cell) in row.iter().enumerate() {
+                widths[column] = widths[column].max(cell.width);
+            }
+        }
+        let extents: Vec<(f32, f32)> = cells
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .fold((m.ascent * scale, m.descent * scale), |(h, d), cell| {
+                        (h.max(cell.height), d.max(cell.depth))
+                    })
+            })
+            .collect();
+
+        let gap = self.snap(column_gap * m.em * scale);
+        let row_gap = m.row_gap * scale;
+        let total: f32 = extents.iter().map(|(h, d)| h + d).sum::<f32>()
+            + row_gap * extents.len().saturating_sub(1) as f32;
+
+        let mut layout = Layout::default();
+        let mut y = self.snap(-m.axis * scale - total / 2.0);
+        for (row, (height, depth)) in cells.into_iter().zip(extents) {
+            let baseline = y + height;
+            let mut x = 0.0;
+            for (column, cell) in row.into_iter().enumerate() {
+                let offset = match columns.get(column).copied().unwrap_or_default() {
+                    ColumnAlign::Left => 0.0,
+                    ColumnAlign::Center => self.center(widths[column], cell.width),
+                    ColumnAlign::Right => widths[column] - cell.width,
+                };
+                layout.place(cell, x + offset, baseline);
+                x += widths[column] + gap;
+            }
+            y = baseline + depth + row_gap;
+        }
+        layout.width = widths.iter().sum::<f32>() + gap * count.saturating_sub(1) as f32;
+        layout
+    }
+
+    fn accent(&self, base: &Node, mark: Mark, style: Style) -> Layout {
+        let m = self.metrics;
+        let scale = self.scale(style);
+        let mut layout = self.node(base, style);
+        let width = layout.width;
+        let rule = if m.cells {
+            m.rule
+        } else {
+            m.rule * scale.max(0.7)
+        };
+
+        match mark {
+            Mark::Over(mark) => {
+                if m.cells {
+                    // Combining characters attach to a single glyph
+                    if let [Item::Glyph { text, .. }] = layout.items.as_mut_slice() {
+                        text.push(mark);
+                        return layout;
+                    }
+                    let text = spacing_accent(mark).to_string();
+                    let accent = self.glyph(&text, Font::Upright, scale);
+                    let x = self.center(width, accent.width);
+                    let y = -layout.height - accent.depth;
+                    layout.place(accent, x, y);
+                } else {
+                    let text = spacing_accent(mark).to_string();
+                    let accent = self.glyph(&text, Font::Upright, scale);
+                    let x = self.center(width, accent.width);
+                    let y = -layout.height + 0.55 * m.em * scale;
+                    layout.place(accent, x, y);
+                }
+            }
+            Mark::Overline | Mark::Arrow => {
+                let height = if mark == Mark::Arrow && !m.cells {
+                    0.3 * m.em * scale
+                } else {
+                    rule
+                };
+                let y = -layout.height - m.gap * scale - height;
+                layout.items.push(Item::Rule {
+                    rect: Rect::new(0.0, y, width, height),
+                    kind: if mark == Mark::Arrow {
+                        RuleKind::Arrow
+                    } else {
+                        RuleKind::Over
+                    },
+                });
+                layout.height = -y;
+            }
+            Mark::Underline => {
+                let y = layout.depth + m.gap * scale;
+                layout.items.push(Item::Rule {
+                    rect: Rect::new(0.0, y, width, rule),
+                    kind: RuleKind::Under,
+                });
+                layout.depth = y + rule;
+            }
+        }
+        layout.width = width;
+        layout
+    }
+}
+
+/// Operators drawn larger in display style
+fn is_large(text: &str) -> bool {
+    text.chars().count() == 1 || text.starts_with(['∫', '∬', '∭', '∮'])
+}
+
+fn is_prime(node: &Node) -> bool {
+    match node {
+        Node::Atom { text, .. } => text.chars().all(|c| c == '′'),
+        Node::Row(nodes) => nodes.len() == 1 && is_prime(&nodes[0]),
+        _ => false,
+    }
+}
+
+/// Free-standing form of a combining accent
+pub(crate) fn spacing_accent(mark: char) -> char {
+    match mark {
+        '\u{302}' => 'ˆ',
+        '\u{30C}' => 'ˇ',
+        '\u{303}' => '˜',
+        '\u{301}' => '´',
+        '\u{300}' => '`',
+        '\u{307}' => '˙',
+        '\u{308}' => '¨',
+        '\u{306}' => '˘',
+        '\u{304}' => '¯',
+        '\u{20D7}' => '→',
+        '\u{30A}' => '˚',
+        mark => mark,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::parser::parse;
+
+    fn cells(tex: &str) -> Layout {
+        layout(&parse(tex), true, &Cells, &Metrics::cells())
+    }
+
+    #[test]
+    fn test_row_spacing() {
+        let layout = cells("a+b=-c");
+        // a␣+␣b␣=␣−c
+        assert_eq!(layout.width, 10.0);
+        assert_eq!((layout.height, layout.depth), (1.0, 0.0));
+    }
+
+    #[test]
+    fn test_fraction_is_centred_on_the_baseline_row() {
+        let layout = cells(r"\frac{a+b}{2}");
+        assert_eq!((layout.width, layout.height, layout.depth), (5.0, 2.0, 1.0));
+        assert!(layout.items.contains(&Item::Rule {
+            rect: Rect::new(0.0, -1.0, 5.0, 1.0),
+            kind: RuleKind::Fraction,
+        }));
+    }
+
+    #[test]
+    fn test_scripts_and_limits() {
+        let layout = cells("x^2_i");
+        assert_eq!((layout.width, layout.height, layout.depth), (2.0, 2.0, 1.0));
+
+        let layout = cells(r"\sum_{i=1}^{n}");
+        assert_eq!((layout.width, layout.height, layout.depth), (3.0, 2.0, 1.0));
+    }
+
+    #[test]
+    fn test_pixel_layout_grows_with_nesting() {
+        let metrics = Metrics::pixels(20.0);
+        let measure = |text: &str, _: Font, scale: f32| text.chars().count() as f32 * 10.0 * scale;
+        let flat = layout(&parse("x"), true, &measure, &metrics);
+        let nested = layout(&parse(r"\frac{1}{\frac{1}{x}}"), true, &measure, &metrics);
+        assert!(nested.depth > flat.depth * 3.0);
+        let sqrt = layout(&parse(r"\sqrt{x}"), true, &measure, &metrics);
+        assert!(sqrt.width > flat.width && sqrt.height > flat.height);
+    }
+}
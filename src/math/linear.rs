@@ -0,0 +1,156 @@
+//! One-line Unicode rendering
+//!
+//! For places that cannot draw a two-dimensional formula: inline math in
+//! wrapped terminal text, HTML output and tooltips. Scripts use Unicode
+//! super/subscript letters when every character has one and fall back to
+//! `^(...)` otherwise; fractions become `a/b`.
+
+use super::layout::{gaps, spacing_accent};
+use super::model::{Mark, Node};
+use super::parser::parse;
+use super::symbols;
+
+/// Render TeX math as a single line of Unicode text
+pub fn to_unicode(tex: &str) -> String {
+    linear(&parse(tex), false).trim().to_string()
+}
+
+fn linear(node: &Node, script: bool) -> String {
+    match node {
+        Node::Atom { text, .. } => text.clone(),
+        Node::Row(nodes) => {
+            let mut text = String::new();
+            for (node, gap) in nodes.iter().zip(gaps(nodes, script)) {
+                if gap.is_some() && !text.ends_with(' ') {
+                    text.push(' ');
+                }
+                text.push_str(&linear(node, script));
+            }
+            text
+        }
+        Node::Scripts { base, sub, sup } => {
+            let mut text = linear(base, script);
+            if let Some(sub) = sub {
+                text.push_str(&script_text(sub, '_', symbols::subscript));
+            }
+            if let Some(sup) = sup {
+                text.push_str(&script_text(sup, '^', symbols::superscript));
+            }
+            text
+        }
+        Node::Fraction {
+            numerator,
+            denominator,
+            bar,
+        } => {
+            let separator = if *bar { "/" } else { " " };
+            format!(
+                "{}{separator}{}",
+                operand(numerator, script),
+                operand(denominator, script)
+            )
+        }
+        Node::Radical { index, radicand } => {
+            let sign = match index.as_deref().map(|index| linear(index, true)) {
+                None => "√".to_string(),
+                Some(index) if index == "3" => "∛".to_string(),
+                Some(index) if index == "4" => "∜".to_string(),
+                Some(index) => {
+                    let raised: Option<String> = index.chars().map(symbols::superscript).collect();
+                    format!("{}√", raised.unwrap_or(index))
+                }
+            };
+            format!("{sign}{}", operand(radicand, script))
+        }
+        Node::Delimited { left, right, body } => {
+            format!("{left}{}{right}", linear(body, script))
+        }
+        Node::Matrix {
+            rows, column_gap, ..
+        } => {
+            let separator = if *column_gap > 0.0 { "  " } else { " " };
+            rows.iter()
+                .map(|row| {
+                    let cells: Vec<String> = row
+                        .iter()
+                        .map(|cell| linear(cell, script).trim().to_string())
+                        .filter(|cell| !cell.is_empty())
+                        .collect();
+                    cells.join(separator)
+                })
+                .collect::<Vec<_>>()
+                .join("; ")
+        }
+        Node::Accent { base, mark } => {
+            let text = linear(base, script);
+            match mark {
+                Mark::Over(mark) if text.chars().count() == 1 => format!("{text}{mark}"),
+                Mark::Over(mark) => format!("{text}{}", spacing_accent(*mark)),
+                Mark::Overline => combine(&text, '\u{305}'),
+                Mark::Underline => combine(&text, '\u{332}'),
+                Mark::Arrow => format!("{text}\u{20D7}"),
+            }
+        }
+        Node::Space(em) if *em >= 0.9 => "  ".to_string(),
+        Node::Space(em) if *em >= 0.3 => " ".to_string(),
+        Node::Space(_) => String::new(),
+    }
+}
+
+/// A fraction or radical operand, parenthesized unless it is one unit
+fn operand(node: &Node, script: bool) -> String {
+    let text = linear(node, script);
+    if is_unit(node) {
+        text
+    } else {
+        format!("({text})")
+    }
+}
+
+fn is_unit(node: &Node) -> bool {
+    match node {
+        Node::Row(nodes) => nodes.len() == 1 && is_unit(&nodes[0]),
+        Node::Atom { .. } | Node::Delimited { .. } | Node::Radical { .. } => true,
+        Node::Scripts { base, .. } | Node::Accent { base, .. } => is_unit(base),
+        _ => false,
+    }
+}
+
+fn script_text(node: &Node, marker: char, map: fn(char) -> Option<char>) -> String {
+    let text = linear(node, true);
+    if let Some(mapped) = text.chars().map(map).collect::<Option<String>>() {
+        return mapped;
+    }
+    if text.chars().count() == 1 {
+        format!("{marker}{text}")
+    } else {
+        format!("{marker}({text})")
+    }
+}
+
+/// Put a combining mark after every character
+fn combine(text: &str, mark: char) -> String {
+    text.chars().flat_map(|c| [c, mark]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_unicode() {
+        assert_eq!(to_unicode(r"\alpha + \beta"), "α + β");
+        assert_eq!(to_unicode(r"\int \infty \in"), "∫ ∞ ∈");
+        assert_eq!(to_unicode(r"E = mc^2"), "E = mc²");
+        assert_eq!(to_unicode(r"\sum_{i=1}^n i"), "∑ᵢ₌₁ⁿ i");
+        assert_eq!(to_unicode(r"\frac{x+1}{2}"), "(x + 1)/2");
+        assert_eq!(to_unicode(r"\frac{\sqrt{\pi}}{2}"), "√π/2");
+        assert_eq!(to_unicode(r"e^{i\pi} = -1"), "e^(iπ) = −1");
+        assert_eq!(to_unicode(r"\sqrt[3]{x} + \hat{x}"), "∛x + x̂");
+        assert_eq!(to_unicode(r"f(x, y) \leq \sin x"), "f(x, y) ≤ sin x");
+        assert_eq!(
+            to_unicode(r"\begin{pmatrix} a & b \\ c & d \end{pmatrix}"),
+            "(a  b; c  d)"
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! MathML output
+//!
+//! Formulas in exported HTML are written as MathML Core, which current
+//! browsers typeset natively, so a page needs no script or web font. The
+//! TeX source is kept as an annotation for copy and paste.
+
+use super::model::{Class, ColumnAlign, Font, Mark, Node};
+
+/// Render TeX as a `<math>` element, in display style or inline
+pub fn render(tex: &str, display: bool) -> String {
+    let mut mathml = String::new();
+    node(&super::parse(tex), display, &mut mathml);
+    format!(
+        "<math{}><semantics><mrow>{mathml}</mrow><annotation encoding=\"application/x-tex\">{}</annotation></semantics></math>",
+        if display { " display=\"block\"" } else { "" },
+        escape(tex.trim())
+    )
+}
+
+fn node(node: &Node, display: bool, out: &mut String) {
+    match node {
+        Node::Atom { text, class, font } => atom(text, *class, *font, out),
+        Node::Row(nodes) if nodes.len() == 1 => self::node(&nodes[0], display, out),
+        Node::Row(nodes) => {
+            out.push_str("<mrow>");
+            for child in nodes {
+                self::node(child, display, out);
+            }
+            out.push_str("</mrow>");
+        }
+        Node::Scripts { base, sub, sup } => {
+            let limits = display && matches!(base.class(), Class::Op { limits: true });
+            let tag = match (sub.is_some(), sup.is_some(), limits) {
+                (true, true, true) => "munderover",
+                (true, false, true) => "munder",
+                (false, true, true) => "mover",
+                (true, true, false) => "msubsup",
+                (true, false, false) => "msub",
+                (false, true, false) => "msup",
+                (false, false, _) => return self::node(base, display, out),
+            };
+            out.push_str(&format!("<{tag}>"));
+            self::node(base, display, out);
+            for script in [sub, sup].into_iter().flatten() {
+                self::node(script, false, out);
+            }
+            out.push_str(&format!("</{tag}>"));
+        }
+        Node::Fraction {
+            numerator,
+            denominator,
+            bar,
+        } => {
+            out.push_str(if *bar {
+                "<mfrac>"
+            } else {
+                "<mfrac linethickness=\"0\">"
+            });
+            self::node(numerator, false, out);
+            self::node(denominator, false, out);
+            out.push_str("</mfrac>");
+        }
+        Node::Radical { index, radicand } => match index {
+            Some(index) => {
+                out.push_str("<mroot>");
+                self::node(radicand, display, out);
+                self::node(index, false, out);
+                out.push_str("</mroot>");
+            }
+            None => {
+                out.push_str("<msqrt>");
+                self::node(radicand, display, out);
+                out.push_str("</msqrt>");
+            }
+        },
+        Node::Delimited { left, right, body } => {
+            out.push_str("<mrow>");
+            fence(left, out);
+            self::node(body, display, out);
+            fence(right, out);
+            out.push_str("</mrow>");
+        }
+        Node::Matrix { rows, columns, .. } => {
+            out.push_str("<mtable>");
+            for row in rows {
+                out.push_str("<mtr>");
+                for (column, cell) in row.iter().enumerate() {
+                    let align = match columns.get(column).copied().unwrap_or_default() {
+                        ColumnAlign::Left => " columnalign=\"left\"",
+                        ColumnAlign::Center => "",
+                        ColumnAlign::Right => " columnalign=\"right\"",
+                    };
+                    out.push_str(&format!("<mtd{align}>"));
+                    self::node(cell, display, out);
+                    out.push_str("</mtd>");
+                }
+                out.push_str("</mtr>");
+            }
+            out.push_str("</mtable>");
+        }
+        Node::Accent { base, mark } => {
+            let (tag, mark) = match mark {
+                Mark::Over(c) => ("mover accent=\"true\"", c.to_string()),
+                Mark::Overline => ("mover accent=\"true\"", "‾".to_string()),
+                Mark::Underline => ("munder accentunder=\"true\"", "_".to_string()),
+                Mark::Arrow => ("mover accent=\"true\"", "→".to_string()),
+            };
+            let close = tag.split(' ').next().unwrap_or(tag);
+            out.push_str(&format!("<{tag}>"));
+            self::node(base, display, out);
+            out.push_str(&format!(
+                "<mo stretchy=\"true\">{}</mo></{close}>",
+                escape(&mark)
+            ));
+        }
+        Node::Space(em) => out.push_str(&format!("<mspace width=\"{em:.3}em\"/>")),
+    }
+}
+
+fn atom(text: &str, class: Class, font: Font, out: &mut String) {
+    let text = escape(text);
+    let variant = match font {
+        Font::Bold => " mathvariant=\"bold\"",
+        // Single letters are italic by default in MathML as in TeX
+        Font::Upright if text.chars().count() == 1 && text.chars().all(char::is_alphabetic) => {
+            " mathvariant=\"normal\""
+        }
+        _ => "",
+    };
+    let element = match class {
+        Class::Ord if text.contains(' ') => "mtext",
+        Class::Ord if text.chars().all(|c| c.is_ascii_digit() || c == '.') => "mn",
+        Class::Ord => "mi",
+        // Named functions (`\sin`, `\lim`) are identifiers, symbols operators
+        Class::Op { .. } if text.chars().all(char::is_alphabetic) => "mi",
+        _ => "mo",
+    };
+    let attributes = match class {
+        Class::Op { limits: true } if element == "mo" => " largeop=\"true\"",
+        _ => "",
+    };
+    out.push_str(&format!(
+        "<{element}{variant}{attributes}>{text}</{element}>"
+    ));
+}
+
+fn fence(delimiter: &str, out: &mut String) {
+    // `\left.` is an empty delimiter
+    if !delimiter.is_empty() && delimiter != "." {
+        out.push_str(&format!(
+            "<mo fence=\"true\" stretchy=\"true\">{}</mo>",
+            escape(delimiter)
+        ));
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fraction_and_scripts() {
+        let mathml = render(r"\frac{a}{b} + x^2", false);
+        assert!(mathml.starts_with("<math><semantics>"));
+        assert!(mathml.contains("<mfrac><mi>a</mi><mi>b</mi></mfrac>"));
+        assert!(mathml.contains("<mo>+</mo>"));
+        assert!(mathml.contains("<msup><mi>x</mi><mn>2</mn></msup>"));
+        assert!(mathml.contains(
+            "<annotation encoding=\"application/x-tex\">\\frac{a}{b} + x^2</annotation>"
+        ));
+    }
+
+    #[test]
+    fn test_display_limits_and_delimiters() {
+        let mathml = render(r"\sum_{i=1}^n \left( \sqrt{i} \right)", true);
+        assert!(mathml.starts_with("<math display=\"block\">"));
+        assert!(mathml.contains("<munderover><mo largeop=\"true\">∑</mo>"));
+        assert!(mathml.contains("<mo fence=\"true\" stretchy=\"true\">(</mo>"));
+        assert!(mathml.contains("<msqrt><mi>i</mi></msqrt>"));
+
+        // Inline, limits become scripts
+        assert!(render(r"\sum_{i=1}^n", false).contains("<msubsup>"));
+    }
+}
@@ -0,0 +1,32 @@
+//! # TeX math engine
+//!
+//! 数式 (`$...$`, `$$...$$`, `\begin{equation}` など) を解析し、TeX と同じ
+//! ボックスモデルでレイアウトするモジュール。
+//!
+//! ```text
+//! tex ─▶ parser::parse ─▶ Node ─▶ layout::layout ─▶ Layout ─┬─▶ gui::math_view (egui)
+//!                          │                                └─▶ grid::render (terminal)
+//!                          ├─▶ linear::to_unicode (1行表示: インライン・HTML)
+//!                          └─▶ mathml::render (HTML エクスポート)
+//! ```
+//!
+//! - [`parser`] — 分数・上付き/下付き・根号・`\left`/`\right`・行列環境などを解析
+//! - [`symbols`] — コマンド名 → Unicode の対応表 (全バックエンド共通)
+//! - [`layout`] — 幅・高さ・深さを持つボックスの組み立てと描画プリミティブ
+//! - [`geometry`] — 根号・伸縮する括弧・矢印のストローク形状 (全バックエンド共通)
+//! - [`grid`] — 罫線・括弧パーツによる端末向け複数行描画
+//! - [`linear`] — 1行の Unicode 近似
+//! - [`mathml`] — MathML 出力 (HTML エクスポート)
+
+pub mod geometry;
+pub mod grid;
+pub mod layout;
+pub mod linear;
+pub mod mathml;
+pub mod model;
+pub mod parser;
+pub mod symbols;
+
+pub use linear::to_unicode;
+pub use model::*;
+pub use parser::parse;
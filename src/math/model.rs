@@ -0,0 +1,126 @@
+//! Formula tree produced by the TeX math parser
+//!
+//! The tree is close to TeX's own math lists: atoms carry a [`Class`] that
+//! decides the spacing around them, and the structural nodes (fractions,
+//! scripts, radicals, delimiters, matrices) are what the layout turns into
+//! boxes.
+
+/// TeX atom class, which decides the space between neighbouring atoms
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    /// Letters, digits and other ordinary symbols
+    Ord,
+    /// Large operators (`\sum`, `\int`) and named functions (`\sin`, `\lim`)
+    Op {
+        /// Whether scripts go above and below in display style
+        limits: bool,
+    },
+    /// Binary operators such as `+` and `\times`
+    Bin,
+    /// Relations such as `=` and `\leq`
+    Rel,
+    Open,
+    Close,
+    Punct,
+}
+
+/// How the glyphs of an atom are drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Font {
+    /// Italic, the default for single letters
+    Italic,
+    /// Upright: digits, symbols, `\text{}` and function names
+    #[default]
+    Upright,
+    Bold,
+}
+
+/// A node of the formula tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Atom {
+        text: String,
+        class: Class,
+        font: Font,
+    },
+    /// Sequence of nodes laid out side by side
+    Row(Vec<Node>),
+    Scripts {
+        base: Box<Node>,
+        sub: Option<Box<Node>>,
+        sup: Option<Box<Node>>,
+    },
+    Fraction {
+        numerator: Box<Node>,
+        denominator: Box<Node>,
+        /// `false` for `\binom` and friends, which have no bar
+        bar: bool,
+    },
+    Radical {
+        index: Option<Box<Node>>,
+        radicand: Box<Node>,
+    },
+    /// Body between delimiters that grow with it (`\left( ... \right)`)
+    Delimited {
+        left: String,
+        right: String,
+        body: Box<Node>,
+    },
+    /// Rows of cells (`matrix` environments, `cases`, `aligned`, `\\`)
+    Matrix {
+        rows: Vec<Vec<Node>>,
+        columns: Vec<ColumnAlign>,
+        /// Extra space between columns, in em
+        column_gap: f32,
+    },
+    /// Mark above or below a base (`\hat`, `\vec`, `\overline`, `\underbrace`)
+    Accent { base: Box<Node>, mark: Mark },
+    /// Horizontal space in em (`\,`, `\quad`, ...)
+    Space(f32),
+}
+
+/// Horizontal alignment of a matrix column
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColumnAlign {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+/// Decoration drawn by [`Node::Accent`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mark {
+    /// A combining character placed over the base (`\hat`, `\dot`, ...)
+    Over(char),
+    /// A line spanning the base
+    Overline,
+    Underline,
+    /// A right arrow spanning the base (`\overrightarrow`, wide `\vec`)
+    Arrow,
+}
+
+impl Node {
+    pub(crate) fn atom(text: impl Into<String>, class: Class) -> Self {
+        Node::Atom {
+            text: text.into(),
+            class,
+            font: Font::Upright,
+        }
+    }
+
+    /// Class of the node for inter-atom spacing
+    pub fn class(&self) -> Class {
+        match self {
+            Node::Atom { class, .. } => *class,
+            Node::Scripts { base, .. } => base.class(),
+            // Groups, fractions, delimiters, ... behave like ordinary atoms
+            _ => Class::Ord,
+        }
+    }
+
+    /// Whether the node is empty (an empty group `{}`)
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Node::Row(nodes) if nodes.iter().all(Node::is_empty))
+    }
+}
@@ -0,0 +1,780 @@
+//! TeX math → [`Node`] tree
+//!
+//! A recursive descent parser over the characters of the formula. It
+//! understands the constructs that matter for layout (groups, scripts,
+//! `\frac`, `\sqrt`, `\left`/`\right`, matrix-like environments, accents
+//! and font switches) and keeps going on anything else: unknown commands
+//! become ordinary atoms showing the command, so a typo is visible in the
+//! preview instead of swallowing the formula.
+
+use super::model::{Class, ColumnAlign, Font, Mark, Node};
+use super::symbols;
+
+/// Parse a formula
+///
+/// Top-level `&` and `\\` (as written inside `align` and friends) turn the
+/// formula into an aligned [`Node::Matrix`].
+pub fn parse(tex: &str) -> Node {
+    let mut parser = Parser {
+        chars: tex.chars().collect(),
+        pos: 0,
+    };
+    let rows = parser.rows(&Until::End);
+    aligned(rows)
+}
+
+/// What ends the list being parsed
+enum Until {
+    End,
+    /// Closing `}` of a group
+    Group,
+    /// `\right` of a `\left` ... `\right` pair
+    Right,
+    /// `\end{name}`
+    Environment(String),
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Command name after a `\` (letters, or a single other character)
+    fn command_name(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            if let Some(c) = self.peek() {
+                self.pos += 1;
+                return c.to_string();
+            }
+        } else if self.peek() == Some('*') && self.pos - start > 2 {
+            // Starred forms such as `\operatorname*`
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// Raw text of a `{...}` group (or of the next character)
+    fn raw_group(&mut self) -> String {
+        self.skip_whitespace();
+        if !self.eat('{') {
+            return self.next_char_text();
+        }
+        let mut depth = 1;
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+            text.push(c);
+        }
+        text
+    }
+
+    fn next_char_text(&mut self) -> String {
+        match self.peek() {
+            Some(c) => {
+                self.pos += 1;
+                c.to_string()
+            }
+            None => String::new(),
+        }
+    }
+
+    /// Optional `[...]` argument
+    fn optional(&mut self) -> Option<String> {
+        let save = self.pos;
+        self.skip_whitespace();
+        if !self.eat('[') {
+            self.pos = save;
+            return None;
+        }
+        let mut text = String::new();
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                ']' if depth == 0 => break,
+                _ => {}
+            }
+            text.push(c);
+        }
+        Some(text)
+    }
+
+    /// A command argument: a group, a command or a single character
+    fn argument(&mut self) -> Node {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => {
+                self.pos += 1;
+                group(self.rows(&Until::Group))
+            }
+            Some('\\') => {
+                self.pos += 1;
+                let name = self.command_name();
+                let mut cell = Vec::new();
+                self.command(&name, &mut cell);
+                group(vec![vec![cell]])
+            }
+            Some(c) => {
+                self.pos += 1;
+                char_atom(c)
+            }
+            None => Node::Row(Vec::new()),
+        }
+    }
+
+    /// Delimiter token after `\left`, `\right` or `\big`
+    fn delimiter(&mut self) -> String {
+        self.skip_whitespace();
+        let token = if self.eat('\\') {
+            format!("\\{}", self.command_name())
+        } else {
+            self.next_char_text()
+        };
+        symbols::delimiter(&token)
+            .map(str::to_string)
+            .unwrap_or(token)
+    }
+
+    /// Rows of `&`-separated cells up to `until`
+    fn rows(&mut self, until: &Until) -> Vec<Vec<Vec<Node>>> {
+        let mut rows = vec![vec![Vec::new()]];
+
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            let row = rows.last_mut().unwrap();
+            let cell = row.last_mut().unwrap();
+            match c {
+                c if c.is_whitespace() => {}
+                '}' if matches!(until, Until::Group) => break,
+                '}' => {}
+                '{' => {
+                    let inner = self.rows(&Until::Group);
+                    cell.push(group(inner));
+                }
+                '&' => row.push(Vec::new()),
+                '^' | '_' => {
+                    let script = self.argument();
+                    attach(cell, c == '^', script);
+                }
+                '\'' => {
+                    let mut primes = "′".to_string();
+                    while self.eat('\'') {
+                        primes.push('′');
+                    }
+                    attach(cell, true, Node::atom(primes, Class::Ord));
+                }
+                '~' => cell.push(Node::Space(0.33)),
+                '0'..='9' | '.' => {
+                    let mut number = c.to_string();
+                    while let Some(next) = self.peek() {
+                        let decimal = next == '.'
+                            && self
+                                .chars
+                                .get(self.pos + 1)
+                                .is_some_and(char::is_ascii_digit);
+                        if !next.is_ascii_digit() && !decimal {
+                            break;
+                        }
+                        number.push(next);
+                        self.pos += 1;
+                    }
+                    cell.push(Node::atom(number, Class::Ord));
+                }
+                '\\' => {
+                    let name = self.command_name();
+                    match name.as_str() {
+                        "\\" | "cr" => {
+                            self.optional();
+                            rows.push(vec![Vec::new()]);
+                        }
+                        "right" if matches!(until, Until::Right) => break,
+                        "end" => {
+                            let name = self.raw_group();
+                            if matches!(until, Until::Environment(env) if *env == name) {
+                                break;
+                            }
+                        }
+                        _ => self.command(&name, cell),
+                    }
+                }
+                c => cell.push(char_atom(c)),
+            }
+        }
+
+        // A trailing `\\` does not start a row
+        if rows.len() > 1 && rows.last().is_some_and(|row| row.iter().all(Vec::is_empty)) {
+            rows.pop();
+        }
+        rows
+    }
+
+    /// Handle `\name`, pushing the result onto `cell`
+    fn command(&mut self, name: &str, cell: &mut Vec<Node>) {
+        match name {
+            "," | "thinspace" => cell.push(Node::Space(3.0 / 18.0)),
+            ":" | ">" | "medspace" => cell.push(Node::Space(4.0 / 18.0)),
+            ";" | "thickspace" => cell.push(Node::Space(5.0 / 18.0)),
+            "!" | "negthinspace" => cell.push(Node::Space(-3.0 / 18.0)),
+            " " | "space" => cell.push(Node::Space(0.33)),
+            "quad" => cell.push(Node::Space(1.0)),
+            "qquad" => cell.push(Node::Space(2.0)),
+            "{" | "lbrace" => cell.push(Node::atom("{", Class::Open)),
+            "}" | "rbrace" => cell.push(Node::atom("}", Class::Close)),
+            "|" | "Vert" => cell.push(Node::atom("‖", Class::Ord)),
+            "vert" => cell.push(Node::atom("|", Class::Ord)),
+            "%" | "#" | "&" | "_" | "$" => cell.push(Node::atom(name, Class::Ord)),
+            "frac" | "dfrac" | "tfrac" | "cfrac" => {
+                let numerator = self.argument();
+                let denominator = self.argument();
+                cell.push(Node::Fraction {
+                    numerator: Box::new(numerator),
+                    denominator: Box::new(denominator),
+                    bar: true,
+                });
+            }
+            "binom" | "dbinom" | "tbinom" => {
+                let numerator = self.argument();
+                let denominator = self.argument();
+                cell.push(Node::Delimited {
+                    left: "(".to_string(),
+                    right: ")".to_string(),
+                    body: Box::new(Node::Fraction {
+                        numerator: Box::new(numerator),
+                        denominator: Box::new(denominator),
+                        bar: false,
+                    }),
+                });
+            }
+            "sqrt" => {
+                let index = self.optional().map(|index| Box::new(parse(&index)));
+                let radicand = self.argument();
+                cell.push(Node::Radical {
+                    index,
+                    radicand: Box::new(radicand),
+                });
+            }
+            "left" => {
+                let left = self.delimiter();
+                let body = group(self.rows(&Until::Right));
+                let right = self.delimiter();
+                cell.push(Node::Delimited {
+                    left,
+                    right,
+                    body: Box::new(body),
+                });
+            }
+            "middle" => {
+                let delimiter = self.delimiter();
+                cell.push(Node::atom(delimiter, Class::Rel));
+            }
+            "big" | "Big" | "bigg" | "Bigg" | "bigl" | "Bigl" | "biggl" | "Biggl" | "bigr"
+            | "Bigr" | "biggr" | "Biggr" | "bigm" | "Bigm" => {
+                let delimiter = self.delimiter();
+                let class = match name.chars().last() {
+                    Some('l') => Class::Open,
+                    Some('r') => Class::Close,
+                    Some('m') => Class::Rel,
+                    _ => Class::Ord,
+                };
+                cell.push(Node::atom(delimiter, class));
+            }
+            "begin" => {
+                let env = self.raw_group();
+                cell.push(self.environment(&env));
+            }
+            "text" | "textrm" | "textnormal" | "textit" | "textbf" | "textsf" | "texttt"
+            | "mbox" | "hbox" => {
+                let text = self.raw_group();
+                let font = match name {
+                    "textit" => Font::Italic,
+                    "textbf" => Font::Bold,
+                    _ => Font::Upright,
+                };
+                cell.push(Node::Atom {
+                    text,
+                    class: Class::Ord,
+                    font,
+                });
+            }
+            "operatorname" | "operatorname*" => {
+                let text: String = self.raw_group().chars().filter(|c| *c != '\\').collect();
+                let limits = name.ends_with('*');
+                cell.push(Node::atom(text, Class::Op { limits }));
+            }
+            "mathrm" | "mathsf" | "mathtt" | "rm" => {
+                let node = self.argument();
+                cell.push(with_font(node, Font::Upright));
+            }
+            "mathit" => {
+                let node = self.argument();
+                cell.push(with_font(node, Font::Italic));
+            }
+            "mathbf" | "boldsymbol" | "bm" | "bf" => {
+                let node = self.argument();
+                cell.push(with_font(node, Font::Bold));
+            }
+            "mathbb" | "mathcal" | "mathscr" | "mathfrak" => {
+                let node = self.argument();
+                cell.push(with_alphabet(node, name));
+            }
+            "overline" | "underline" | "overrightarrow" | "overbrace" | "underbrace" => {
+                let base = self.argument();
+                let mark = match name {
+                    "overline" | "overbrace" => Mark::Overline,
+                    "underline" | "underbrace" => Mark::Underline,
+                    _ => Mark::Arrow,
+                };
+                cell.push(Node::Accent {
+                    base: Box::new(base),
+                    mark,
+                });
+            }
+            "not" => {
+                let mut negated = Vec::new();
+                self.skip_whitespace();
+                if self.eat('\\') {
+                    let name = self.command_name();
+                    self.command(&name, &mut negated);
+                } else if let Some(c) = self.peek() {
+                    self.pos += 1;
+                    negated.push(char_atom(c));
+                }
+                for mut node in negated {
+                    if let Node::Atom { text, .. } = &mut node {
+                        text.push('\u{338}');
+                    }
+                    cell.push(node);
+                }
+            }
+            "limits" | "nolimits" => {
+                if let Some(Node::Atom {
+                    class: Class::Op { limits },
+                    ..
+                }) = cell.last_mut()
+                {
+                    *limits = name == "limits";
+                }
+            }
+            "tag" | "tag*" => {
+                // Equation number, set off to the right of the row
+                let tag = self.raw_group();
+                let text = if name == "tag" {
+                    format!("({tag})")
+                } else {
+                    tag
+                };
+                cell.push(Node::Space(2.0));
+                cell.push(Node::atom(text, Class::Ord));
+            }
+            "label" | "phantom" | "hphantom" | "vphantom" => {
+                self.raw_group();
+            }
+            "nonumber" | "notag" | "displaystyle" | "textstyle" | "scriptstyle"
+            | "scriptscriptstyle" | "mathstrut" | "hline" | "nolinebreak" | "allowbreak" => {}
+            _ => cell.push(self.symbol(name)),
+        }
+    }
+
+    /// Operators, functions, symbols and accents looked up by name
+    fn symbol(&mut self, name: &str) -> Node {
+        if let Some(mark) = symbols::accent(name) {
+            let base = self.argument();
+            // A combining arrow only covers one letter
+            let mark = if mark == '\u{20D7}' && !is_single_atom(&base) {
+                Mark::Arrow
+            } else {
+                Mark::Over(mark)
+            };
+            return Node::Accent {
+                base: Box::new(base),
+                mark,
+            };
+        }
+        if let Some((text, limits)) = symbols::operator(name) {
+            return Node::atom(text, Class::Op { limits });
+        }
+        if let Some(limits) = symbols::function(name) {
+            let text = match name {
+                "liminf" => "lim inf",
+                "limsup" => "lim sup",
+                _ => name,
+            };
+            return Node::atom(text, Class::Op { limits });
+        }
+        if let Some((text, class)) = symbols::symbol(name) {
+            return Node::atom(text, class);
+        }
+        if let Some(delimiter) = symbols::delimiter(&format!("\\{name}")) {
+            let class = match name.chars().next() {
+                Some('l') => Class::Open,
+                Some('r') => Class::Close,
+                _ => Class::Ord,
+            };
+            return Node::atom(delimiter, class);
+        }
+        Node::atom(format!("\\{name}"), Class::Ord)
+    }
+
+    /// `\begin{env}` ... `\end{env}`
+    fn environment(&mut self, env: &str) -> Node {
+        let columns_spec =
+            (env == "array" || env == "alignat" || env == "alignat*").then(|| self.raw_group());
+        let rows = self.rows(&Until::Environment(env.to_string()));
+
+        let base = env.trim_end_matches('*');
+        let (left, right) = match base {
+            "pmatrix" => ("(", ")"),
+            "bmatrix" => ("[", "]"),
+            "Bmatrix" => ("{", "}"),
+            "vmatrix" => ("|", "|"),
+            "Vmatrix" => ("‖", "‖"),
+            "cases" | "dcases" => ("{", ""),
+            "rcases" => ("", "}"),
+            _ => ("", ""),
+        };
+
+        let count = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let (columns, column_gap) = match base {
+            "cases" | "dcases" | "rcases" => (vec![ColumnAlign::Left; count], 1.0),
+            "align" | "aligned" | "split" | "eqnarray" | "flalign" | "alignat" | "alignedat" => {
+                (alternating(count), 0.0)
+            }
+            "array" => {
+                let spec = columns_spec.unwrap_or_default();
+                let mut columns: Vec<ColumnAlign> = spec
+                    .chars()
+                    .filter_map(|c| match c {
+                        'l' => Some(ColumnAlign::Left),
+                        'c' => Some(ColumnAlign::Center),
+                        'r' => Some(ColumnAlign::Right),
+                        _ => None,
+                    })
+                    .collect();
+                columns.resize(count, ColumnAlign::Center);
+                (columns, 1.0)
+            }
+            _ => (vec![ColumnAlign::Center; count], 1.0),
+        };
+
+        let matrix = Node::Matrix {
+            rows: matrix_rows(rows, &columns),
+            columns,
+            column_gap,
+        };
+        if left.is_empty() && right.is_empty() {
+            matrix
+        } else {
+            Node::Delimited {
+                left: left.to_string(),
+                right: right.to_string(),
+                body: Box::new(matrix),
+            }
+        }
+    }
+}
+
+/// Atom for a single source character
+fn char_atom(c: char) -> Node {
+    let text = match c {
+        '-' => '−',
+        '*' => '∗',
+        c => c,
+    };
+    Node::Atom {
+        text: text.to_string(),
+        class: symbols::char_class(c),
+        font: if c.is_alphabetic() {
+            Font::Italic
+        } else {
+            Font::Upright
+        },
+    }
+}
+
+fn row_node(mut nodes: Vec<Node>) -> Node {
+    if nodes.len() == 1 {
+        nodes.pop().unwrap()
+    } else {
+        Node::Row(nodes)
+    }
+}
+
+/// A `{...}` group: one row node, or a matrix if it contains `\\`
+fn group(rows: Vec<Vec<Vec<Node>>>) -> Node {
+    if rows.len() == 1 && rows[0].len() == 1 {
+        let cell = rows.into_iter().next().unwrap().into_iter().next().unwrap();
+        return Node::Row(cell);
+    }
+    aligned(rows)
+}
+
+/// Top-level rows; alignment points become right/left column pairs
+fn aligned(rows: Vec<Vec<Vec<Node>>>) -> Node {
+    if rows.len() == 1 && rows[0].len() == 1 {
+        return row_node(rows.into_iter().next().unwrap().into_iter().next().unwrap());
+    }
+    let count = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let columns = if count == 1 {
+        vec![ColumnAlign::Center]
+    } else {
+        alternating(count)
+    };
+    Node::Matrix {
+        rows: matrix_rows(rows, &columns),
+        columns,
+        column_gap: 0.0,
+    }
+}
+
+/// Cells of a matrix
+///
+/// The left half of an `align` pair starts with an empty atom, like TeX's
+/// `&={}`, so a relation after `&` keeps its space.
+fn matrix_rows(rows: Vec<Vec<Vec<Node>>>, columns: &[ColumnAlign]) -> Vec<Vec<Node>> {
+    rows.into_iter()
+        .map(|row| {
+            row.into_iter()
+                .enumerate()
+                .map(|(index, mut cell)| {
+                    let pair = index > 0
+                        && columns.get(index) == Some(&ColumnAlign::Left)
+                        && columns.get(index - 1) == Some(&ColumnAlign::Right);
+                    if pair && !cell.is_empty() {
+                        cell.insert(0, Node::Row(Vec::new()));
+                    }
+                    row_node(cell)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// `align` columns: right-aligned before each `&`, left-aligned after it
+fn alternating(count: usize) -> Vec<ColumnAlign> {
+    (0..count)
+        .map(|i| {
+            if i % 2 == 0 {
+                ColumnAlign::Right
+            } else {
+                ColumnAlign::Left
+            }
+        })
+        .collect()
+}
+
+/// Attach a sub- or superscript to the last node of `cell`
+fn attach(cell: &mut Vec<Node>, superscript: bool, script: Node) {
+    let base = cell.pop().unwrap_or(Node::Row(Vec::new()));
+    let node = match base {
+        Node::Scripts { base, sub, sup } => {
+            let slot = if superscript { &sup } else { &sub };
+            match slot {
+                None if superscript => Node::Scripts {
+                    base,
+                    sub,
+                    sup: Some(Box::new(script)),
+                },
+                None => Node::Scripts {
+                    base,
+                    sub: Some(Box::new(script)),
+                    sup,
+                },
+                // `x^a^b` is an error in TeX; keep the first script
+                Some(_) => {
+                    cell.push(Node::Scripts { base, sub, sup });
+                    cell.push(Node::Scripts {
+                        base: Box::new(Node::Row(Vec::new())),
+                        sub: (!superscript).then(|| Box::new(script.clone())),
+                        sup: superscript.then(|| Box::new(script)),
+                    });
+                    return;
+                }
+            }
+        }
+        base => Node::Scripts {
+            base: Box::new(base),
+            sub: (!superscript).then(|| Box::new(script.clone())),
+            sup: superscript.then(|| Box::new(script)),
+        },
+    };
+    cell.push(node);
+}
+
+fn is_single_atom(node: &Node) -> bool {
+    match node {
+        Node::Atom { text, .. } => text.chars().count() == 1,
+        Node::Row(nodes) => nodes.len() == 1 && is_single_atom(&nodes[0]),
+        _ => false,
+    }
+}
+
+fn with_font(node: Node, font: Font) -> Node {
+    map_atoms(node, &|text, class, _| (text, class, font))
+}
+
+fn with_alphabet(node: Node, name: &str) -> Node {
+    map_atoms(node, &|text, class, _| {
+        let text = text.chars().map(|c| symbols::alphabet(name, c)).collect();
+        (text, class, Font::Upright)
+    })
+}
+
+type AtomMap<'a> = dyn Fn(String, Class, Font) -> (String, Class, Font) + 'a;
+
+fn map_atoms(node: Node, f: &AtomMap) -> Node {
+    let map = |node: Box<Node>| Box::new(map_atoms(*node, f));
+    match node {
+        Node::Atom { text, class, font } => {
+            let (text, class, font) = f(text, class, font);
+            Node::Atom { text, class, font }
+        }
+        Node::Row(nodes) => Node::Row(nodes.into_iter().map(|n| map_atoms(n, f)).collect()),
+        Node::Scripts { base, sub, sup } => Node::Scripts {
+            base: map(base),
+            sub: sub.map(map),
+            sup: sup.map(map),
+        },
+        Node::Fraction {
+            numerator,
+            denominator,
+            bar,
+        } => Node::Fraction {
+            numerator: map(numerator),
+            denominator: map(denominator),
+            bar,
+        },
+        Node::Radical { index, radicand } => Node::Radical {
+            index,
+            radicand: map(radicand),
+        },
+        Node::Accent { base, mark } => Node::Accent {
+            base: map(base),
+            mark,
+        },
+        node => node,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ord(text: &str, font: Font) -> Node {
+        Node::Atom {
+            text: text.to_string(),
+            class: Class::Ord,
+            font,
+        }
+    }
+
+    #[test]
+    fn test_atoms_and_classes() {
+        let Node::Row(nodes) = parse(r"x + 12.5 \leq \alpha") else {
+            panic!("expected row");
+        };
+        let classes: Vec<Class> = nodes.iter().map(Node::class).collect();
+        assert_eq!(
+            classes,
+            [Class::Ord, Class::Bin, Class::Ord, Class::Rel, Class::Ord]
+        );
+        assert_eq!(nodes[0], ord("x", Font::Italic));
+        assert_eq!(nodes[2], ord("12.5", Font::Upright));
+        assert_eq!(nodes[4], ord("α", Font::Upright));
+    }
+
+    #[test]
+    fn test_fraction_scripts_and_radical() {
+        let node = parse(r"\frac{a}{b^2} + \sqrt[3]{x_i'}");
+        let Node::Row(nodes) = node else {
+            panic!("expected row");
+        };
+        assert!(
+            matches!(&nodes[0], Node::Fraction { bar: true, denominator, .. }
+            if matches!(denominator.as_ref(), Node::Row(d) if matches!(d[0], Node::Scripts { .. })))
+        );
+        let Node::Radical { index, radicand } = &nodes[2] else {
+            panic!("expected radical");
+        };
+        assert!(index.is_some());
+        let Node::Row(inner) = radicand.as_ref() else {
+            panic!("expected group");
+        };
+        assert!(matches!(
+            &inner[0],
+            Node::Scripts {
+                sub: Some(_),
+                sup: Some(_),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_delimiters_and_matrices() {
+        let node = parse(r"\left( \frac{1}{2} \right]");
+        assert!(
+            matches!(&node, Node::Delimited { left, right, .. } if left == "(" && right == "]")
+        );
+
+        let node = parse(r"\begin{pmatrix} a & b \\ c & d \end{pmatrix}");
+        let Node::Delimited { left, body, .. } = node else {
+            panic!("expected delimited matrix");
+        };
+        assert_eq!(left, "(");
+        assert!(
+            matches!(body.as_ref(), Node::Matrix { rows, .. } if rows.len() == 2 && rows[1].len() == 2)
+        );
+
+        let node = parse("x &= 1 \\\\\n  &= 2 \\\\");
+        assert!(matches!(&node, Node::Matrix { rows, columns, .. }
+            if rows.len() == 2 && columns == &[ColumnAlign::Right, ColumnAlign::Left]));
+    }
+
+    #[test]
+    fn test_operators_fonts_and_unknown_commands() {
+        let Node::Row(nodes) = parse(r"\sum\nolimits_i \mathbb{R} \text{if } x \foo") else {
+            panic!("expected row");
+        };
+        assert!(matches!(&nodes[0], Node::Scripts { base, .. }
+            if base.class() == Class::Op { limits: false }));
+        assert_eq!(nodes[1], Node::Row(vec![ord("ℝ", Font::Upright)]));
+        assert_eq!(nodes[2], ord("if ", Font::Upright));
+        assert_eq!(nodes[4], ord("\\foo", Font::Upright));
+    }
+}
@@ -0,0 +1,480 @@
+//! Symbol tables shared by every math backend
+//!
+//! TeX command names map to Unicode here and nowhere else, so the egui
+//! painter, the terminal art and the one-line rendering always agree on
+//! what `\leq` or `\mathbb{R}` looks like.
+
+use super::model::Class;
+
+/// Symbol commands: name, Unicode text and atom class
+const SYMBOLS: &[(&str, &str, Class)] = &[
+    // Greek letters (lowercase)
+    ("alpha", "α", Class::Ord),
+    ("beta", "β", Class::Ord),
+    ("gamma", "γ", Class::Ord),
+    ("delta", "δ", Class::Ord),
+    ("epsilon", "ϵ", Class::Ord),
+    ("varepsilon", "ε", Class::Ord),
+    ("zeta", "ζ", Class::Ord),
+    ("eta", "η", Class::Ord),
+    ("theta", "θ", Class::Ord),
+    ("vartheta", "ϑ", Class::Ord),
+    ("iota", "ι", Class::Ord),
+    ("kappa", "κ", Class::Ord),
+    ("lambda", "λ", Class::Ord),
+    ("mu", "μ", Class::Ord),
+    ("nu", "ν", Class::Ord),
+    ("xi", "ξ", Class::Ord),
+    ("omicron", "ο", Class::Ord),
+    ("pi", "π", Class::Ord),
+    ("varpi", "ϖ", Class::Ord),
+    ("rho", "ρ", Class::Ord),
+    ("varrho", "ϱ", Class::Ord),
+    ("sigma", "σ", Class::Ord),
+    ("varsigma", "ς", Class::Ord),
+    ("tau", "τ", Class::Ord),
+    ("upsilon", "υ", Class::Ord),
+    ("phi", "ϕ", Class::Ord),
+    ("varphi", "φ", Class::Ord),
+    ("chi", "χ", Class::Ord),
+    ("psi", "ψ", Class::Ord),
+    ("omega", "ω", Class::Ord),
+    // Greek letters (uppercase)
+    ("Gamma", "Γ", Class::Ord),
+    ("Delta", "Δ", Class::Ord),
+    ("Theta", "Θ", Class::Ord),
+    ("Lambda", "Λ", Class::Ord),
+    ("Xi", "Ξ", Class::Ord),
+    ("Pi", "Π", Class::Ord),
+    ("Sigma", "Σ", Class::Ord),
+    ("Upsilon", "Υ", Class::Ord),
+    ("Phi", "Φ", Class::Ord),
+    ("Psi", "Ψ", Class::Ord),
+    ("Omega", "Ω", Class::Ord),
+    // Ordinary symbols
+    ("infty", "∞", Class::Ord),
+    ("partial", "∂", Class::Ord),
+    ("nabla", "∇", Class::Ord),
+    ("emptyset", "∅", Class::Ord),
+    ("varnothing", "∅", Class::Ord),
+    ("forall", "∀", Class::Ord),
+    ("exists", "∃", Class::Ord),
+    ("nexists", "∄", Class::Ord),
+    ("neg", "¬", Class::Ord),
+    ("lnot", "¬", Class::Ord),
+    ("aleph", "ℵ", Class::Ord),
+    ("hbar", "ℏ", Class::Ord),
+    ("ell", "ℓ", Class::Ord),
+    ("Re", "ℜ", Class::Ord),
+    ("Im", "ℑ", Class::Ord),
+    ("wp", "℘", Class::Ord),
+    ("angle", "∠", Class::Ord),
+    ("triangle", "△", Class::Ord),
+    ("top", "⊤", Class::Ord),
+    ("bot", "⊥", Class::Ord),
+    ("prime", "′", Class::Ord),
+    ("degree", "°", Class::Ord),
+    ("ldots", "…", Class::Ord),
+    ("dots", "…", Class::Ord),
+    ("cdots", "⋯", Class::Ord),
+    ("vdots", "⋮", Class::Ord),
+    ("ddots", "⋱", Class::Ord),
+    ("backslash", "∖", Class::Ord),
+    // Binary operators
+    ("pm", "±", Class::Bin),
+    ("mp", "∓", Class::Bin),
+    ("times", "×", Class::Bin),
+    ("cdot", "·", Class::Bin),
+    ("div", "÷", Class::Bin),
+    ("ast", "∗", Class::Bin),
+    ("star", "⋆", Class::Bin),
+    ("circ", "∘", Class::Bin),
+    ("bullet", "∙", Class::Bin),
+    ("cup", "∪", Class::Bin),
+    ("cap", "∩", Class::Bin),
+    ("wedge", "∧", Class::Bin),
+    ("land", "∧", Class::Bin),
+    ("vee", "∨", Class::Bin),
+    ("lor", "∨", Class::Bin),
+    ("setminus", "∖", Class::Bin),
+    ("oplus", "⊕", Class::Bin),
+    ("ominus", "⊖", Class::Bin),
+    ("otimes", "⊗", Class::Bin),
+    ("oslash", "⊘", Class::Bin),
+    ("odot", "⊙", Class::Bin),
+    ("sqcup", "⊔", Class::Bin),
+    ("sqcap", "⊓", Class::Bin),
+    ("uplus", "⊎", Class::Bin),
+    ("dagger", "†", Class::Bin),
+    // Relations and arrows
+    ("neq", "≠", Class::Rel),
+    ("ne", "≠", Class::Rel),
+    ("leq", "≤", Class::Rel),
+    ("le", "≤", Class::Rel),
+    ("geq", "≥", Class::Rel),
+    ("ge", "≥", Class::Rel),
+    ("ll", "≪", Class::Rel),
+    ("gg", "≫", Class::Rel),
+    ("approx", "≈", Class::Rel),
+    ("equiv", "≡", Class::Rel),
+    ("sim", "∼", Class::Rel),
+    ("simeq", "≃", Class::Rel),
+    ("cong", "≅", Class::Rel),
+    ("propto", "∝", Class::Rel),
+    ("doteq", "≐", Class::Rel),
+    ("coloneqq", "≔", Class::Rel),
+    ("in", "∈", Class::Rel),
+    ("notin", "∉", Class::Rel),
+    ("ni", "∋", Class::Rel),
+    ("subset", "⊂", Class::Rel),
+    ("supset", "⊃", Class::Rel),
+    ("subseteq", "⊆", Class::Rel),
+    ("supseteq", "⊇", Class::Rel),
+    ("prec", "≺", Class::Rel),
+    ("succ", "≻", Class::Rel),
+    ("preceq", "⪯", Class::Rel),
+    ("succeq", "⪰", Class::Rel),
+    ("perp", "⊥", Class::Rel),
+    ("parallel", "∥", Class::Rel),
+    ("mid", "∣", Class::Rel),
+    ("models", "⊨", Class::Rel),
+    ("vdash", "⊢", Class::Rel),
+    ("to", "→", Class::Rel),
+    ("rightarrow", "→", Class::Rel),
+    ("gets", "←", Class::Rel),
+    ("leftarrow", "←", Class::Rel),
+    ("leftrightarrow", "↔", Class::Rel),
+    ("Rightarrow", "⇒", Class::Rel),
+    ("Leftarrow", "⇐", Class::Rel),
+    ("Leftrightarrow", "⇔", Class::Rel),
+    ("longrightarrow", "⟶", Class::Rel),
+    ("longleftarrow", "⟵", Class::Rel),
+    ("Longrightarrow", "⟹", Class::Rel),
+    ("implies", "⟹", Class::Rel),
+    ("iff", "⟺", Class::Rel),
+    ("mapsto", "↦", Class::Rel),
+    ("uparrow", "↑", Class::Rel),
+    ("downarrow", "↓", Class::Rel),
+    // Punctuation
+    ("colon", ":", Class::Punct),
+];
+
+/// Large operators: name, symbol and whether limits go above/below
+const OPERATORS: &[(&str, &str, bool)] = &[
+    ("sum", "∑", true),
+    ("prod", "∏", true),
+    ("coprod", "∐", true),
+    ("bigcup", "⋃", true),
+    ("bigcap", "⋂", true),
+    ("bigvee", "⋁", true),
+    ("bigwedge", "⋀", true),
+    ("bigoplus", "⨁", true),
+    ("bigotimes", "⨂", true),
+    ("bigsqcup", "⨆", true),
+    ("int", "∫", false),
+    ("iint", "∬", false),
+    ("iiint", "∭", false),
+    ("oint", "∮", false),
+];
+
+/// Named functions set upright: name and whether limits go below
+const FUNCTIONS: &[(&str, bool)] = &[
+    ("sin", false),
+    ("cos", false),
+    ("tan", false),
+    ("cot", false),
+    ("sec", false),
+    ("csc", false),
+    ("arcsin", false),
+    ("arccos", false),
+    ("arctan", false),
+    ("sinh", false),
+    ("cosh", false),
+    ("tanh", false),
+    ("coth", false),
+    ("log", false),
+    ("ln", false),
+    ("lg", false),
+    ("exp", false),
+    ("dim", false),
+    ("ker", false),
+    ("deg", false),
+    ("arg", false),
+    ("hom", false),
+    ("lim", true),
+    ("liminf", true),
+    ("limsup", true),
+    ("max", true),
+    ("min", true),
+    ("sup", true),
+    ("inf", true),
+    ("det", true),
+    ("gcd", true),
+    ("Pr", true),
+];
+
+/// Accent commands and the combining character they put over the base
+const ACCENTS: &[(&str, char)] = &[
+    ("hat", '\u{302}'),
+    ("widehat", '\u{302}'),
+    ("check", '\u{30C}'),
+    ("tilde", '\u{303}'),
+    ("widetilde", '\u{303}'),
+    ("acute", '\u{301}'),
+    ("grave", '\u{300}'),
+    ("dot", '\u{307}'),
+    ("ddot", '\u{308}'),
+    ("breve", '\u{306}'),
+    ("bar", '\u{304}'),
+    ("vec", '\u{20D7}'),
+    ("mathring", '\u{30A}'),
+];
+
+/// Symbol for a command such as `alpha` or `leq`
+pub fn symbol(name: &str) -> Option<(&'static str, Class)> {
+    SYMBOLS
+        .iter()
+        .find(|(command, ..)| *command == name)
+        .map(|&(_, text, class)| (text, class))
+}
+
+/// Large operator symbol and its limits placement
+pub fn operator(name: &str) -> Option<(&'static str, bool)> {
+    OPERATORS
+        .iter()
+        .find(|(command, ..)| *command == name)
+        .map(|&(_, text, limits)| (text, limits))
+}
+
+/// Whether `name` is a named function; the flag is its limits placement
+pub fn function(name: &str) -> Option<bool> {
+    FUNCTIONS
+        .iter()
+        .find(|(command, _)| *command == name)
+        .map(|&(_, limits)| limits)
+}
+
+/// Combining character for an accent command
+pub fn accent(name: &str) -> Option<char> {
+    ACCENTS
+        .iter()
+        .find(|(command, _)| *command == name)
+        .map(|&(_, mark)| mark)
+}
+
+/// Class of a single source character
+pub fn char_class(c: char) -> Class {
+    match c {
+        '+' | '-' | '−' | '*' => Class::Bin,
+        '=' | '<' | '>' | ':' => Class::Rel,
+        '(' | '[' => Class::Open,
+        ')' | ']' => Class::Close,
+        ',' | ';' => Class::Punct,
+        _ => Class::Ord,
+    }
+}
+
+/// Delimiter drawn for `\left`/`\right` and matrix environments
+///
+/// `.` is the empty delimiter and maps to an empty string.
+pub fn delimiter(token: &str) -> Option<&'static str> {
+    Some(match token {
+        "." => "",
+        "(" => "(",
+        ")" => ")",
+        "[" | "\\lbrack" => "[",
+        "]" | "\\rbrack" => "]",
+        "\\{" | "\\lbrace" => "{",
+        "\\}" | "\\rbrace" => "}",
+        "|" | "\\vert" | "\\lvert" | "\\rvert" => "|",
+        "\\|" | "\\Vert" | "\\lVert" | "\\rVert" => "‖",
+        "\\langle" => "⟨",
+        "\\rangle" => "⟩",
+        "\\lceil" => "⌈",
+        "\\rceil" => "⌉",
+        "\\lfloor" => "⌊",
+        "\\rfloor" => "⌋",
+        "/" => "/",
+        _ => return None,
+    })
+}
+
+/// Unicode superscript form of a character, if there is one
+pub fn superscript(c: char) -> Option<char> {
+    Some(match c {
+        '0' => '⁰',
+        '1' => '¹',
+        '2' => '²',
+        '3' => '³',
+        '4' => '⁴',
+        '5' => '⁵',
+        '6' => '⁶',
+        '7' => '⁷',
+        '8' => '⁸',
+        '9' => '⁹',
+        '+' => '⁺',
+        '-' | '−' => '⁻',
+        '=' => '⁼',
+        '(' => '⁽',
+        ')' => '⁾',
+        'a' => 'ᵃ',
+        'b' => 'ᵇ',
+        'c' => 'ᶜ',
+        'd' => 'ᵈ',
+        'e' => 'ᵉ',
+        'f' => 'ᶠ',
+        'g' => 'ᵍ',
+        'h' => 'ʰ',
+        'i' => 'ⁱ',
+        'j' => 'ʲ',
+        'k' => 'ᵏ',
+        'l' => 'ˡ',
+        'm' => 'ᵐ',
+        'n' => 'ⁿ',
+        'o' => 'ᵒ',
+        'p' => 'ᵖ',
+        'r' => 'ʳ',
+        's' => 'ˢ',
+        't' => 'ᵗ',
+        'u' => 'ᵘ',
+        'v' => 'ᵛ',
+        'w' => 'ʷ',
+        'x' => 'ˣ',
+        'y' => 'ʸ',
+        'z' => 'ᶻ',
+        'T' => 'ᵀ',
+        '′' => '′',
+        _ => return None,
+    })
+}
+
+/// Unicode subscript form of a character, if there is one
+pub fn subscript(c: char) -> Option<char> {
+    Some(match c {
+        '0' => '₀',
+        '1' => '₁',
+        '2' => '₂',
+        '3' => '₃',
+        '4' => '₄',
+        '5' => '₅',
+        '6' => '₆',
+        '7' => '₇',
+        '8' => '₈',
+        '9' => '₉',
+        '+' => '₊',
+        '-' | '−' => '₋',
+        '=' => '₌',
+        '(' => '₍',
+        ')' => '₎',
+        'a' => 'ₐ',
+        'e' => 'ₑ',
+        'h' => 'ₕ',
+        'i' => 'ᵢ',
+        'j' => 'ⱼ',
+        'k' => 'ₖ',
+        'l' => 'ₗ',
+        'm' => 'ₘ',
+        'n' => 'ₙ',
+        'o' => 'ₒ',
+        'p' => 'ₚ',
+        'r' => 'ᵣ',
+        's' => 'ₛ',
+        't' => 'ₜ',
+        'u' => 'ᵤ',
+        'v' => 'ᵥ',
+        'x' => 'ₓ',
+        _ => return None,
+    })
+}
+
+/// Letter in a math alphabet (`\mathbb`, `\mathcal`, `\mathfrak`)
+///
+/// Characters without a styled form are returned unchanged.
+pub fn alphabet(font: &str, c: char) -> char {
+    let styled = match font {
+        "mathbb" => double_struck(c),
+        "mathcal" | "mathscr" => script(c),
+        "mathfrak" => fraktur(c),
+        _ => None,
+    };
+    styled.unwrap_or(c)
+}
+
+fn double_struck(c: char) -> Option<char> {
+    match c {
+        'C' => Some('ℂ'),
+        'H' => Some('ℍ'),
+        'N' => Some('ℕ'),
+        'P' => Some('ℙ'),
+        'Q' => Some('ℚ'),
+        'R' => Some('ℝ'),
+        'Z' => Some('ℤ'),
+        'A'..='Z' => offset(c, 'A', 0x1D538),
+        'a'..='z' => offset(c, 'a', 0x1D552),
+        '0'..='9' => offset(c, '0', 0x1D7D8),
+        _ => None,
+    }
+}
+
+fn script(c: char) -> Option<char> {
+    match c {
+        'B' => Some('ℬ'),
+        'E' => Some('ℰ'),
+        'F' => Some('ℱ'),
+        'H' => Some('ℋ'),
+        'I' => Some('ℐ'),
+        'L' => Some('ℒ'),
+        'M' => Some('ℳ'),
+        'R' => Some('ℛ'),
+        'e' => Some('ℯ'),
+        'g' => Some('ℊ'),
+        'o' => Some('ℴ'),
+        'A'..='Z' => offset(c, 'A', 0x1D49C),
+        'a'..='z' => offset(c, 'a', 0x1D4B6),
+        _ => None,
+    }
+}
+
+fn fraktur(c: char) -> Option<char> {
+    match c {
+        'C' => Some('ℭ'),
+        'H' => Some('ℌ'),
+        'I' => Some('ℑ'),
+        'R' => Some('ℜ'),
+        'Z' => Some('ℨ'),
+        'A'..='Z' => offset(c, 'A', 0x1D504),
+        'a'..='z' => offset(c, 'a', 0x1D51E),
+        _ => None,
+    }
+}
+
+fn offset(c: char, first: char, base: u32) -> Option<char> {
+    char::from_u32(base + (c as u32 - first as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookups() {
+        assert_eq!(symbol("leq"), Some(("≤", Class::Rel)));
+        assert_eq!(symbol("in"), Some(("∈", Class::Rel)));
+        assert_eq!(symbol("nope"), None);
+        assert_eq!(operator("sum"), Some(("∑", true)));
+        assert_eq!(operator("int"), Some(("∫", false)));
+        assert_eq!(function("lim"), Some(true));
+        assert_eq!(delimiter("\\langle"), Some("⟨"));
+        assert_eq!(delimiter("."), Some(""));
+    }
+
+    #[test]
+    fn test_alphabets() {
+        assert_eq!(alphabet("mathbb", 'R'), 'ℝ');
+        assert_eq!(alphabet("mathbb", 'A'), '𝔸');
+        assert_eq!(alphabet("mathcal", 'L'), 'ℒ');
+        assert_eq!(alphabet("mathcal", 'A'), '𝒜');
+        assert_eq!(alphabet("mathfrak", 'g'), '𝔤');
+        assert_eq!(alphabet("mathbb", '+'), '+');
+    }
+}
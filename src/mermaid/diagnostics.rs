@@ -0,0 +1,194 @@
+//! Syntax diagnostics for Mermaid sources
+//!
+//! The parser records a [`Diagnostic`] for every statement it cannot make
+//! sense of instead of silently dropping it. Positions are 1-based and
+//! counted in characters, like editors and compilers report them.
+
+use std::fmt;
+use std::ops::Range;
+
+use super::parser::{self, Statement};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// A problem at a position in the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column, in characters
+    pub column: usize,
+    /// Number of characters the problem spans (at least one)
+    pub length: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// An error spanning the byte `range` of a statement's text
+    pub(crate) fn error(statement: &Statement, range: Range<usize>, message: String) -> Self {
+        Self::at(Severity::Error, statement, range, message)
+    }
+
+    pub(crate) fn warning(statement: &Statement, range: Range<usize>, message: String) -> Self {
+        Self::at(Severity::Warning, statement, range, message)
+    }
+
+    fn at(severity: Severity, statement: &Statement, range: Range<usize>, message: String) -> Self {
+        let text = statement.text;
+        let start = range.start.min(text.len());
+        let end = range.end.clamp(start, text.len());
+        Self {
+            severity,
+            line: statement.line,
+            column: statement.column + text[..start].chars().count(),
+            length: text[start..end].chars().count().max(1),
+            message,
+        }
+    }
+
+    /// Char range of the problem within `source`, for editors
+    pub fn char_range(&self, source: &str) -> Range<usize> {
+        let mut start = 0;
+        for (index, line) in source.split('\n').enumerate() {
+            if index + 1 == self.line {
+                let line_chars = line.trim_end_matches('\r').chars().count();
+                let column = (self.column - 1).min(line_chars);
+                let end = (column + self.length).min(line_chars.max(column + 1));
+                return start + column..start + end;
+            }
+            start += line.chars().count() + 1;
+        }
+        start..start
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line,
+            self.column,
+            self.severity.label(),
+            self.message
+        )
+    }
+}
+
+/// Check a Mermaid source, returning diagnostics in source order
+pub fn check(source: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = parser::parse_with_diagnostics(source).1;
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+    diagnostics
+}
+
+/// Whether any diagnostic is an error
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(source: &str) -> Vec<String> {
+        check(source).iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_valid_diagrams_are_clean() {
+        for source in [
+            "graph TD\n  A[Start] --> B{Ok?}\n  B -->|yes| C\n  subgraph s [Group]\n    C\n  end\n",
+            "sequenceDiagram\n  participant A\n  participant B\n  A->>B: hi\n  loop again\n    B-->>A: yo\n  end\n",
+            "classDiagram\n  class Animal {\n    +name\n  }\n  Animal <|-- Dog\n",
+            "stateDiagram-v2\n  [*] --> Idle\n  state Busy {\n    [*] --> Work\n  }\n",
+            "erDiagram\n  A ||--o{ B : has\n  A {\n    string id\n  }\n",
+            "gantt\n  title Plan\n  section A\n  Task :a1, 2024-01-01, 3d\n",
+        ] {
+            assert_eq!(messages(source), Vec::<String>::new(), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_unknown_and_unsupported_types() {
+        assert_eq!(
+            messages("%% title\nflowchar TD\n  A --> B\n"),
+            ["2:1: error: unknown diagram type `flowchar`"]
+        );
+        assert_eq!(
+            messages("journey\n  title Day\n"),
+            ["1:1: warning: `journey` diagrams are not rendered; the source is shown instead"]
+        );
+        assert_eq!(messages("")[0], "1:1: error: empty diagram");
+    }
+
+    #[test]
+    fn test_flowchart_errors() {
+        assert_eq!(
+            messages("graph TD\n  A[Start --> B\n"),
+            ["2:4: error: unclosed `[`"]
+        );
+        assert_eq!(
+            messages("graph TD\n  A -> B\n"),
+            ["2:5: error: invalid link `->`; expected a link such as `-->`"]
+        );
+        assert_eq!(
+            messages("graph TD\n  A --> B]\n"),
+            ["2:10: error: unmatched `]`"]
+        );
+        assert_eq!(
+            messages("graph TD\n  subgraph one\n    A\n"),
+            ["2:3: error: `subgraph` is never closed with `end`"]
+        );
+    }
+
+    #[test]
+    fn test_sequence_errors() {
+        let source = "sequenceDiagram\n  participant Alice\n  participant Bob\n  Alice->>Bbo: hi\n  Alice=>Bob: no\n  end\n";
+        assert_eq!(
+            messages(source),
+            [
+                "4:11: warning: undeclared participant `Bbo`",
+                "5:8: error: invalid arrow `=>`; expected `->>`, `-->>`, `->`, `-->`, `-x`, `--x`, `-)` or `--)`",
+                "6:3: error: `end` without an open block",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mixed_participants_only_warn() {
+        let source = "sequenceDiagram\n  participant A as Alice\n  A->>C: hi\n  C-->>A: yo\n";
+        let diagnostics = check(source);
+        assert_eq!(
+            messages(source),
+            [
+                "3:7: warning: undeclared participant `C`",
+                "4:3: warning: undeclared participant `C`",
+            ]
+        );
+        assert!(!has_errors(&diagnostics));
+    }
+
+    #[test]
+    fn test_char_range() {
+        let source = "graph TD\r\n  A -> B\r\n";
+        let diagnostic = &check(source)[0];
+        let range = diagnostic.char_range(source);
+        let text: String = source.chars().skip(range.start).take(range.len()).collect();
+        assert_eq!(text, "->");
+    }
+}
@@ -0,0 +1,484 @@
+//! Shape geometry shared by the drawing backends
+//!
+//! Node outlines and edge markers are described as a few [`Primitive`]s so
+//! that the egui painter, the SVG writer and any later backend draw exactly
+//! the same picture from a [`Layout`](super::layout::Layout).
+
+use super::model::{Marker, NodeShape};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Point {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    fn offset(self, dx: f32, dy: f32) -> Self {
+        Self::new(self.x + dx, self.y + dy)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn from_center(center: Point, width: f32, height: f32) -> Self {
+        Self::new(
+            center.x - width / 2.0,
+            center.y - height / 2.0,
+            width,
+            height,
+        )
+    }
+
+    pub fn center(&self) -> Point {
+        Point::new(self.x + self.width / 2.0, self.y + self.height / 2.0)
+    }
+
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect::new(
+            x,
+            y,
+            self.right().max(other.right()) - x,
+            self.bottom().max(other.bottom()) - y,
+        )
+    }
+
+    pub fn expand(&self, amount: f32) -> Rect {
+        Rect::new(
+            self.x - amount,
+            self.y - amount,
+            self.width + amount * 2.0,
+            self.height + amount * 2.0,
+        )
+    }
+
+    pub fn translate(&self, dx: f32, dy: f32) -> Rect {
+        Rect::new(self.x + dx, self.y + dy, self.width, self.height)
+    }
+}
+
+/// How a closed primitive is filled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fill {
+    None,
+    /// Node background color
+    Background,
+    /// Same color as the stroke (arrow heads, start dots)
+    Solid,
+}
+
+/// Drawing primitive in layout coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub enum Primitive {
+    Polygon {
+        points: Vec<Point>,
+        fill: Fill,
+    },
+    Polyline(Vec<Point>),
+    Ellipse {
+        center: Point,
+        rx: f32,
+        ry: f32,
+        fill: Fill,
+    },
+    RoundedRect {
+        rect: Rect,
+        radius: f32,
+    },
+}
+
+/// Outline of a node shape within `rect`
+pub fn outline(shape: NodeShape, rect: Rect) -> Vec<Primitive> {
+    let Rect {
+        x,
+        y,
+        width: w,
+        height: h,
+    } = rect;
+    let c = rect.center();
+    let polygon = |points: Vec<Point>| Primitive::Polygon {
+        points,
+        fill: Fill::Background,
+    };
+    let slant = (h / 2.0).min(w / 4.0);
+
+    match shape {
+        NodeShape::Rectangle | NodeShape::Entity | NodeShape::Class => vec![polygon(vec![
+            Point::new(x, y),
+            Point::new(x + w, y),
+            Point::new(x + w, y + h),
+            Point::new(x, y + h),
+        ])],
+        NodeShape::Rounded => vec![Primitive::RoundedRect {
+            rect,
+            radius: (h / 4.0).min(8.0),
+        }],
+        NodeShape::Stadium => vec![Primitive::RoundedRect {
+            rect,
+            radius: h / 2.0,
+        }],
+        NodeShape::Subroutine => {
+            let inset = (w / 12.0).min(8.0);
+            vec![
+                polygon(vec![
+                    Point::new(x, y),
+                    Point::new(x + w, y),
+                    Point::new(x + w, y + h),
+                    Point::new(x, y + h),
+                ]),
+                Primitive::Polyline(vec![Point::new(x + inset, y), Point::new(x + inset, y + h)]),
+                Primitive::Polyline(vec![
+                    Point::new(x + w - inset, y),
+                    Point::new(x + w - inset, y + h),
+                ]),
+            ]
+        }
+        NodeShape::Cylinder => {
+            // Body with the lower half of the bottom ellipse, then the lid
+            let ry = (h / 8.0).min(w / 6.0);
+            let mut body = vec![Point::new(x, y + ry)];
+            body.extend(arc(Point::new(c.x, y + h - ry), w / 2.0, ry, 180.0, 0.0));
+            body.push(Point::new(x + w, y + ry));
+            vec![
+                polygon(body),
+                Primitive::Ellipse {
+                    center: Point::new(c.x, y + ry),
+                    rx: w / 2.0,
+                    ry,
+                    fill: Fill::Background,
+                },
+            ]
+        }
+        NodeShape::Circle => vec![Primitive::Ellipse {
+            center: c,
+            rx: w / 2.0,
+            ry: h / 2.0,
+            fill: Fill::Background,
+        }],
+        NodeShape::DoubleCircle => vec![
+            Primitive::Ellipse {
+                center: c,
+                rx: w / 2.0,
+                ry: h / 2.0,
+                fill: Fill::Background,
+            },
+            Primitive::Ellipse {
+                center: c,
+                rx: w / 2.0 - 4.0,
+                ry: h / 2.0 - 4.0,
+                fill: Fill::None,
+            },
+        ],
+        NodeShape::Asymmetric => vec![polygon(vec![
+            Point::new(x, y),
+            Point::new(x + w, y),
+            Point::new(x + w, y + h),
+            Point::new(x, y + h),
+            Point::new(x + slant, c.y),
+        ])],
+        NodeShape::Diamond => vec![polygon(vec![
+            Point::new(c.x, y),
+            Point::new(x + w, c.y),
+            Point::new(c.x, y + h),
+            Point::new(x, c.y),
+        ])],
+        NodeShape::Hexagon => vec![polygon(vec![
+            Point::new(x + slant, y),
+            Point::new(x + w - slant, y),
+            Point::new(x + w, c.y),
+            Point::new(x + w - slant, y + h),
+            Point::new(x + slant, y + h),
+            Point::new(x, c.y),
+        ])],
+        NodeShape::Parallelogram => vec![polygon(vec![
+            Point::new(x + slant, y),
+            Point::new(x + w, y),
+            Point::new(x + w - slant, y + h),
+            Point::new(x, y + h),
+        ])],
+        NodeShape::ParallelogramAlt => vec![polygon(vec![
+            Point::new(x, y),
+            Point::new(x + w - slant, y),
+            Point::new(x + w, y + h),
+            Point::new(x + slant, y + h),
+        ])],
+        NodeShape::Trapezoid => vec![polygon(vec![
+            Point::new(x + slant, y),
+            Point::new(x + w - slant, y),
+            Point::new(x + w, y + h),
+            Point::new(x, y + h),
+        ])],
+        NodeShape::TrapezoidAlt => vec![polygon(vec![
+            Point::new(x, y),
+            Point::new(x + w, y),
+            Point::new(x + w - slant, y + h),
+            Point::new(x + slant, y + h),
+        ])],
+        NodeShape::Actor => {
+            // Stick figure in the top part; the label sits below it
+            let figure = h * 0.6;
+            let head = figure / 6.0;
+            let top = y + 2.0;
+            let neck = top + head * 2.0;
+            let hip = neck + figure * 0.35;
+            let arms = neck + figure * 0.12;
+            let feet = top + figure - 2.0;
+            let spread = figure * 0.25;
+            vec![
+                Primitive::Ellipse {
+                    center: Point::new(c.x, top + head),
+                    rx: head,
+                    ry: head,
+                    fill: Fill::Background,
+                },
+                Primitive::Polyline(vec![Point::new(c.x, neck), Point::new(c.x, hip)]),
+                Primitive::Polyline(vec![
+                    Point::new(c.x - spread, arms),
+                    Point::new(c.x + spread, arms),
+                ]),
+                Primitive::Polyline(vec![
+                    Point::new(c.x - spread, feet),
+                    Point::new(c.x, hip),
+                    Point::new(c.x + spread, feet),
+                ]),
+            ]
+        }
+        NodeShape::Start => vec![Primitive::Ellipse {
+            center: c,
+            rx: w / 2.0,
+            ry: h / 2.0,
+            fill: Fill::Solid,
+        }],
+        NodeShape::End => vec![
+            Primitive::Ellipse {
+                center: c,
+                rx: w / 2.0,
+                ry: h / 2.0,
+                fill: Fill::Background,
+            },
+            Primitive::Ellipse {
+                center: c,
+                rx: w / 2.0 - 3.0,
+                ry: h / 2.0 - 3.0,
+                fill: Fill::Solid,
+            },
+        ],
+        NodeShape::Bar => vec![Primitive::Polygon {
+            points: vec![
+                Point::new(x, y),
+                Point::new(x + w, y),
+                Point::new(x + w, y + h),
+                Point::new(x, y + h),
+            ],
+            fill: Fill::Solid,
+        }],
+        NodeShape::Note => {
+            let fold = (h / 4.0).min(10.0);
+            vec![
+                polygon(vec![
+                    Point::new(x, y),
+                    Point::new(x + w - fold, y),
+                    Point::new(x + w, y + fold),
+                    Point::new(x + w, y + h),
+                    Point::new(x, y + h),
+                ]),
+                Primitive::Polyline(vec![
+                    Point::new(x + w - fold, y),
+                    Point::new(x + w - fold, y + fold),
+                    Point::new(x + w, y + fold),
+                ]),
+            ]
+        }
+    }
+}
+
+/// Points on an elliptical arc, angles in degrees (0° = +x, clockwise in
+/// screen coordinates)
+fn arc(center: Point, rx: f32, ry: f32, from: f32, to: f32) -> Vec<Point> {
+    const STEPS: usize = 12;
+    (0..=STEPS)
+        .map(|i| {
+            let angle = (from + (to - from) * i as f32 / STEPS as f32).to_radians();
+            center.offset(rx * angle.cos(), ry * angle.sin())
+        })
+        .collect()
+}
+
+/// Point where the line from the center of `rect` towards `toward` leaves
+/// the node outline
+pub fn clip(shape: NodeShape, rect: Rect, toward: Point) -> Point {
+    let c = rect.center();
+    let (dx, dy) = (toward.x - c.x, toward.y - c.y);
+    if dx == 0.0 && dy == 0.0 {
+        return c;
+    }
+    let (hw, hh) = (rect.width / 2.0, rect.height / 2.0);
+
+    let t = match shape {
+        NodeShape::Circle | NodeShape::DoubleCircle | NodeShape::Start | NodeShape::End => {
+            1.0 / ((dx / hw).powi(2) + (dy / hh).powi(2)).sqrt()
+        }
+        NodeShape::Diamond => 1.0 / (dx.abs() / hw + dy.abs() / hh),
+        _ => {
+            let tx = if dx == 0.0 {
+                f32::INFINITY
+            } else {
+                hw / dx.abs()
+            };
+            let ty = if dy == 0.0 {
+                f32::INFINITY
+            } else {
+                hh / dy.abs()
+            };
+            tx.min(ty)
+        }
+    };
+    let t = t.min(1.0);
+    c.offset(dx * t, dy * t)
+}
+
+/// Marker drawn at `tip`, for a line arriving from `from`
+pub fn marker(marker: Marker, tip: Point, from: Point, size: f32) -> Vec<Primitive> {
+    let length = ((tip.x - from.x).powi(2) + (tip.y - from.y).powi(2)).sqrt();
+    if length == 0.0 || marker == Marker::None {
+        return Vec::new();
+    }
+    // Unit vector pointing backwards along the line, and its normal
+    let (ux, uy) = ((from.x - tip.x) / length, (from.y - tip.y) / length);
+    let (nx, ny) = (-uy, ux);
+    let at =
+        |along: f32, across: f32| tip.offset(ux * along + nx * across, uy * along + ny * across);
+    let half = size * 0.45;
+
+    match marker {
+        Marker::None => Vec::new(),
+        Marker::Arrow => vec![Primitive::Polygon {
+            points: vec![tip, at(size, half), at(size, -half)],
+            fill: Fill::Solid,
+        }],
+        Marker::OpenArrow => vec![Primitive::Polyline(vec![
+            at(size, half),
+            tip,
+            at(size, -half),
+        ])],
+        Marker::Triangle => vec![Primitive::Polygon {
+            points: vec![tip, at(size * 1.2, size * 0.6), at(size * 1.2, -size * 0.6)],
+            fill: Fill::Background,
+        }],
+        Marker::Diamond | Marker::OpenDiamond => vec![Primitive::Polygon {
+            points: vec![
+                tip,
+                at(size * 0.8, half),
+                at(size * 1.6, 0.0),
+                at(size * 0.8, -half),
+            ],
+            fill: if marker == Marker::Diamond {
+                Fill::Solid
+            } else {
+                Fill::Background
+            },
+        }],
+        Marker::Cross => vec![
+            Primitive::Polyline(vec![at(0.0, half), at(size * 0.9, -half)]),
+            Primitive::Polyline(vec![at(0.0, -half), at(size * 0.9, half)]),
+        ],
+        Marker::Circle => vec![Primitive::Ellipse {
+            center: at(size * 0.4, 0.0),
+            rx: size * 0.4,
+            ry: size * 0.4,
+            fill: Fill::Background,
+        }],
+        // Crow's foot notation: bars mean "one", a circle "zero", a fork "many"
+        Marker::ExactlyOne => vec![bar(&at, size * 0.6, half), bar(&at, size, half)],
+        Marker::ZeroOrOne => vec![bar(&at, size * 0.6, half), ring(&at, size * 1.3, size)],
+        Marker::OneOrMore => vec![fork(&at, size, half), bar(&at, size * 1.2, half)],
+        Marker::ZeroOrMore => vec![fork(&at, size, half), ring(&at, size * 1.5, size)],
+    }
+}
+
+fn bar(at: &dyn Fn(f32, f32) -> Point, along: f32, half: f32) -> Primitive {
+    Primitive::Polyline(vec![at(along, half), at(along, -half)])
+}
+
+fn ring(at: &dyn Fn(f32, f32) -> Point, along: f32, size: f32) -> Primitive {
+    Primitive::Ellipse {
+        center: at(along, 0.0),
+        rx: size * 0.3,
+        ry: size * 0.3,
+        fill: Fill::Background,
+    }
+}
+
+fn fork(at: &dyn Fn(f32, f32) -> Point, size: f32, half: f32) -> Primitive {
+    let root = at(size * 0.8, 0.0);
+    Primitive::Polyline(vec![
+        at(0.0, half),
+        root,
+        at(0.0, -half),
+        root,
+        at(0.0, 0.0),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clip_rectangle_and_circle() {
+        let rect = Rect::new(0.0, 0.0, 40.0, 20.0);
+        assert_eq!(
+            clip(NodeShape::Rectangle, rect, Point::new(20.0, 100.0)),
+            Point::new(20.0, 20.0)
+        );
+        assert_eq!(
+            clip(NodeShape::Rectangle, rect, Point::new(100.0, 10.0)),
+            Point::new(40.0, 10.0)
+        );
+        let circle = Rect::new(0.0, 0.0, 20.0, 20.0);
+        let p = clip(NodeShape::Circle, circle, Point::new(10.0, -50.0));
+        assert!((p.y - 0.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_arrow_marker_points_at_tip() {
+        let tip = Point::new(10.0, 10.0);
+        let shapes = marker(Marker::Arrow, tip, Point::new(10.0, 0.0), 8.0);
+        let Primitive::Polygon { points, fill } = &shapes[0] else {
+            panic!("expected polygon");
+        };
+        assert_eq!(*fill, Fill::Solid);
+        assert_eq!(points[0], tip);
+        // The base of the head lies back along the line
+        assert!(points[1].y < tip.y && points[2].y < tip.y);
+    }
+}
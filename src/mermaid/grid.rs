@@ -0,0 +1,784 @@
+//! Character grid renderer
+//!
+//! Draws a diagram with box-drawing characters for terminals. The layout is
+//! the same layered layout as the vector backends, computed in cells;
+//! edges are re-routed orthogonally (turning in the gaps between ranks) and
+//! overlapping lines are merged into junction glyphs.
+
+use std::collections::HashMap;
+
+use unicode_width::UnicodeWidthChar;
+
+use super::layout::{layout, Layout, Monospace, PlacedNode, Point, Rect, Spacing};
+use super::model::{Diagram, Direction, LineStyle, Marker, NodeShape};
+
+const UP: u8 = 1;
+const DOWN: u8 = 2;
+const LEFT: u8 = 4;
+const RIGHT: u8 = 8;
+
+/// What a cell shows, for coloring
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ink {
+    Plain,
+    /// Node outlines
+    Border,
+    /// Node labels and members
+    Text,
+    /// Edge lines and markers
+    Edge,
+    /// Edge labels
+    Label,
+    /// Subgraph and fragment frames, lifelines
+    Frame,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Cell {
+    ch: char,
+    ink: Ink,
+    /// Line directions, merged into a junction glyph
+    lines: u8,
+    style: LineStyle,
+    /// Right half of a wide character
+    continuation: bool,
+}
+
+const BLANK: Cell = Cell {
+    ch: ' ',
+    ink: Ink::Plain,
+    lines: 0,
+    style: LineStyle::Solid,
+    continuation: false,
+};
+
+/// A rendered character grid
+#[derive(Debug, Clone)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+}
+
+/// Cell rectangle, inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cells {
+    x0: i32,
+    y0: i32,
+    x1: i32,
+    y1: i32,
+}
+
+impl Cells {
+    fn from_rect(rect: Rect) -> Self {
+        let (x0, y0) = (round(rect.x), round(rect.y));
+        Self {
+            x0,
+            y0,
+            x1: x0 + round(rect.width).max(1) - 1,
+            y1: y0 + round(rect.height).max(1) - 1,
+        }
+    }
+}
+
+/// Start cell and width of each line of text centered on `at`
+fn centered_lines(at: Point, text: &str) -> Vec<((i32, i32), &str, i32)> {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let top = round(at.y - lines.len() as f32 / 2.0);
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let width = line.chars().filter_map(|c| c.width()).sum::<usize>() as i32;
+            let start = (round(at.x - width as f32 / 2.0), top + i as i32);
+            (start, line, width)
+        })
+        .collect()
+}
+
+fn round(value: f32) -> i32 {
+    value.round() as i32
+}
+
+/// Cell containing a layout point
+fn cell(point: Point) -> (i32, i32) {
+    (point.x.floor() as i32, point.y.floor() as i32)
+}
+
+/// Render a diagram no wider than `max_width` columns
+///
+/// Horizontal diagrams that do not fit are retried top-down; `None` means
+/// the diagram cannot be drawn in the available width.
+pub fn render(diagram: &Diagram, max_width: usize) -> Option<Grid> {
+    let measure = Monospace {
+        char_width: 1.0,
+        line_height: 1.0,
+    };
+    let grid = Grid::draw(
+        &layout(diagram, &measure, &Spacing::cells()),
+        diagram.direction,
+    );
+    if grid.width <= max_width {
+        return Some(grid);
+    }
+    if diagram.direction.is_horizontal() {
+        let mut vertical = diagram.clone();
+        vertical.direction = Direction::TopDown;
+        let grid = Grid::draw(
+            &layout(&vertical, &measure, &Spacing::cells()),
+            Direction::TopDown,
+        );
+        if grid.width <= max_width {
+            return Some(grid);
+        }
+    }
+    None
+}
+
+impl Grid {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![BLANK; width * height],
+        }
+    }
+
+    fn cell(&mut self, (x, y): (i32, i32)) -> Option<&mut Cell> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+        self.cells.get_mut(y as usize * self.width + x as usize)
+    }
+
+    fn get(&self, (x, y): (i32, i32)) -> Option<Cell> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+        self.cells
+            .get(y as usize * self.width + x as usize)
+            .copied()
+    }
+
+    fn put(&mut self, at: (i32, i32), ch: char, ink: Ink) {
+        if let Some(cell) = self.cell(at) {
+            *cell = Cell { ch, ink, ..BLANK };
+        }
+    }
+
+    /// Write text starting at `at`; wide characters take two cells
+    fn text(&mut self, (mut x, y): (i32, i32), text: &str, ink: Ink) {
+        for ch in text.chars() {
+            let width = ch.width().unwrap_or(0) as i32;
+            if width == 0 {
+                continue;
+            }
+            self.put((x, y), ch, ink);
+            if width == 2 {
+                if let Some(cell) = self.cell((x + 1, y)) {
+                    *cell = Cell {
+                        continuation: true,
+                        ink,
+                        ..BLANK
+                    };
+                }
+            }
+            x += width;
+        }
+    }
+
+    /// Multi-line text centered on `at`
+    fn centered(&mut self, at: Point, text: &str, ink: Ink) {
+        for (start, line, _) in centered_lines(at, text) {
+            self.text(start, line, ink);
+        }
+    }
+
+    /// Add a line segment between two cells in the same row or column
+    fn line(&mut self, from: (i32, i32), to: (i32, i32), style: LineStyle, ink: Ink) {
+        let (dx, dy) = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+        if dx != 0 && dy != 0 {
+            return;
+        }
+        let (forward, backward) = match (dx, dy) {
+            (1, _) => (RIGHT, LEFT),
+            (-1, _) => (LEFT, RIGHT),
+            (_, 1) => (DOWN, UP),
+            _ => (UP, DOWN),
+        };
+        let mut at = from;
+        while at != to {
+            let next = (at.0 + dx, at.1 + dy);
+            self.connect(at, forward, style, ink);
+            self.connect(next, backward, style, ink);
+            at = next;
+        }
+    }
+
+    fn connect(&mut self, at: (i32, i32), direction: u8, style: LineStyle, ink: Ink) {
+        if let Some(cell) = self.cell(at) {
+            cell.lines |= direction;
+            cell.style = style;
+            cell.ink = ink;
+        }
+    }
+
+    /// Turn line masks into box-drawing characters
+    fn resolve_lines(&mut self) {
+        for cell in &mut self.cells {
+            if cell.lines != 0 {
+                cell.ch = junction(cell.lines, cell.style);
+            }
+        }
+    }
+
+    fn draw(layout: &Layout, direction: Direction) -> Self {
+        let width = layout.width.ceil() as usize + 2;
+        let height = layout.height.ceil() as usize + 2;
+        let mut grid = Grid::new(width, height);
+
+        for cluster in &layout.clusters {
+            let c = Cells::from_rect(cluster.rect);
+            let style = LineStyle::Solid;
+            grid.line((c.x0, c.y0), (c.x1, c.y0), style, Ink::Frame);
+            grid.line((c.x1, c.y0), (c.x1, c.y1), style, Ink::Frame);
+            grid.line((c.x1, c.y1), (c.x0, c.y1), style, Ink::Frame);
+            grid.line((c.x0, c.y1), (c.x0, c.y0), style, Ink::Frame);
+            for (y, _) in &cluster.dividers {
+                let y = round(*y);
+                grid.line((c.x0, y), (c.x1, y), LineStyle::Dotted, Ink::Frame);
+            }
+        }
+        for (top, bottom) in &layout.lifelines {
+            let (top, bottom) = (cell(*top), cell(*bottom));
+            grid.line(top, (bottom.0, bottom.1 - 1), LineStyle::Dotted, Ink::Frame);
+        }
+
+        let boxes: Vec<Cells> = layout
+            .nodes
+            .iter()
+            .map(|node| Cells::from_rect(node.rect))
+            .collect();
+        // Cells covered by edge labels, by edge
+        let mut labels = HashMap::new();
+        for (index, edge) in layout.edges.iter().enumerate() {
+            for (text, at) in [&edge.label, &edge.start_label, &edge.end_label]
+                .into_iter()
+                .flatten()
+            {
+                for ((x, y), _, width) in centered_lines(*at, text) {
+                    for dx in 0..width {
+                        labels.insert((x + dx, y), index);
+                    }
+                }
+            }
+        }
+
+        let mut ends = Vec::new();
+        for (index, edge) in layout.edges.iter().enumerate() {
+            if edge.line == LineStyle::Invisible {
+                continue;
+            }
+            let blocked = |at: (i32, i32)| labels.get(&at).is_some_and(|&other| other != index);
+            let path = route(&edge.points, &boxes, direction, &blocked);
+            if path.len() < 2 {
+                continue;
+            }
+            for pair in path.windows(2) {
+                grid.line(pair[0], pair[1], edge.line, Ink::Edge);
+            }
+            let count = path.len();
+            ends.push((path[0], path[1], edge.start));
+            ends.push((path[count - 1], path[count - 2], edge.end));
+        }
+        grid.resolve_lines();
+
+        for (node, cells) in layout.nodes.iter().zip(&boxes) {
+            grid.node(node, *cells);
+        }
+        // Lines leaving a box join its outline
+        for &(end, previous, marker) in &ends {
+            if marker == Marker::None {
+                grid.join_outline(end, previous);
+            }
+        }
+        for &(end, previous, marker) in &ends {
+            if marker != Marker::None {
+                let toward = (end.0 - previous.0, end.1 - previous.1);
+                grid.put(end, marker_glyph(marker, toward), Ink::Edge);
+            }
+        }
+
+        for cluster in &layout.clusters {
+            grid.centered(cluster.label_at, &cluster.label, Ink::Frame);
+            let c = Cells::from_rect(cluster.rect);
+            for (y, text) in &cluster.dividers {
+                grid.centered(
+                    Point::new((c.x0 + c.x1) as f32 / 2.0, *y + 1.5),
+                    text,
+                    Ink::Frame,
+                );
+            }
+        }
+        for edge in &layout.edges {
+            for (label, at) in [&edge.label, &edge.start_label, &edge.end_label]
+                .into_iter()
+                .flatten()
+            {
+                grid.centered(*at, label, Ink::Label);
+            }
+        }
+
+        grid.trim();
+        grid
+    }
+
+    fn node(&mut self, node: &PlacedNode, c: Cells) {
+        let rect = node.rect;
+        match node.shape {
+            NodeShape::Start => return self.put((c.x0, c.y0), '●', Ink::Border),
+            NodeShape::End => return self.put((c.x0, c.y0), '◉', Ink::Border),
+            NodeShape::Bar => {
+                for x in c.x0..=c.x1 {
+                    for y in c.y0..=c.y1 {
+                        self.put((x, y), '━', Ink::Border);
+                    }
+                }
+                return;
+            }
+            NodeShape::Diamond if c.x1 - c.x0 < 3 || c.y1 - c.y0 < 2 => {
+                return self.put((c.x0, c.y0), '◆', Ink::Border);
+            }
+            _ => {}
+        }
+
+        let mut outline = c;
+        if node.shape == NodeShape::Actor {
+            // Stick figure above a box holding the name
+            let x = (c.x0 + c.x1) / 2;
+            self.text((x, c.y0), "o", Ink::Border);
+            self.text((x - 1, c.y0 + 1), "/|\\", Ink::Border);
+            self.text((x - 1, c.y0 + 2), "/ \\", Ink::Border);
+            outline.y0 = c.y0 + 3;
+        }
+
+        let (tl, tr, bl, br, h, v) = border(node.shape);
+        for y in outline.y0..=outline.y1 {
+            for x in outline.x0..=outline.x1 {
+                let ch = match (
+                    y == outline.y0,
+                    y == outline.y1,
+                    x == outline.x0,
+                    x == outline.x1,
+                ) {
+                    (true, _, true, _) => tl,
+                    (true, _, _, true) => tr,
+                    (_, true, true, _) => bl,
+                    (_, true, _, true) => br,
+                    (true, _, _, _) | (_, true, _, _) => h,
+                    (_, _, true, _) | (_, _, _, true) => v,
+                    _ => ' ',
+                };
+                self.put((x, y), ch, Ink::Border);
+            }
+        }
+
+        // Positions are taken relative to the box so rounding stays consistent
+        let row = |y: f32| c.y0 + round(y - rect.y);
+        let column = |x: f32| c.x0 + round(x - rect.x);
+        let lines: Vec<&str> = node.label.split('\n').collect();
+        let top = row(node.label_at.y - lines.len() as f32 / 2.0);
+        for (i, line) in lines.iter().enumerate() {
+            let width = line.chars().filter_map(|ch| ch.width()).sum::<usize>() as f32;
+            self.text(
+                (column(node.label_at.x - width / 2.0), top + i as i32),
+                line,
+                Ink::Text,
+            );
+        }
+        // With one row of padding the header's lower padding row becomes the
+        // divider, and each section's padding row the divider below it
+        for section in &node.sections {
+            let y = row(section.rect.y) - 1;
+            for x in c.x0..=c.x1 {
+                let ch = if x == c.x0 {
+                    '├'
+                } else if x == c.x1 {
+                    '┤'
+                } else {
+                    '─'
+                };
+                self.put((x, y), ch, Ink::Border);
+            }
+            for (i, (line, at)) in section.lines.iter().enumerate() {
+                self.text((column(at.x), y + 1 + i as i32), line, Ink::Text);
+            }
+        }
+    }
+
+    /// Replace the outline character a line end points at with a tee
+    fn join_outline(&mut self, end: (i32, i32), previous: (i32, i32)) {
+        let (dx, dy) = ((end.0 - previous.0).signum(), (end.1 - previous.1).signum());
+        let tee = match (dx, dy) {
+            (0, -1) => '┬',
+            (0, 1) => '┴',
+            (-1, 0) => '├',
+            (1, 0) => '┤',
+            _ => return,
+        };
+        let neighbour = (end.0 + dx, end.1 + dy);
+        if let Some(border) = self.get(neighbour) {
+            let straight = matches!(border.ch, '─' | '│');
+            if border.ink == Ink::Border && straight {
+                self.put(neighbour, tee, Ink::Border);
+            }
+        }
+    }
+
+    /// Drop empty rows and columns around the drawing
+    fn trim(&mut self) {
+        let used = |cell: &Cell| cell.ch != ' ' || cell.continuation;
+        let rows: Vec<usize> = (0..self.height)
+            .filter(|&y| {
+                self.cells[y * self.width..(y + 1) * self.width]
+                    .iter()
+                    .any(used)
+            })
+            .collect();
+        let columns: Vec<usize> = (0..self.width)
+            .filter(|&x| (0..self.height).any(|y| used(&self.cells[y * self.width + x])))
+            .collect();
+        let (Some(&top), Some(&bottom)) = (rows.first(), rows.last()) else {
+            *self = Grid::new(0, 0);
+            return;
+        };
+        let (left, right) = (columns[0], *columns.last().unwrap());
+
+        let mut trimmed = Grid::new(right - left + 1, bottom - top + 1);
+        for y in top..=bottom {
+            for x in left..=right {
+                trimmed.cells[(y - top) * trimmed.width + (x - left)] =
+                    self.cells[y * self.width + x];
+            }
+        }
+        *self = trimmed;
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Lines as runs of equally colored text, without trailing spaces
+    pub fn lines(&self) -> Vec<Vec<(Ink, String)>> {
+        (0..self.height)
+            .map(|y| {
+                let row = &self.cells[y * self.width..(y + 1) * self.width];
+                let end = row
+                    .iter()
+                    .rposition(|cell| cell.ch != ' ' || cell.continuation)
+                    .map_or(0, |i| i + 1);
+                let mut runs: Vec<(Ink, String)> = Vec::new();
+                for cell in &row[..end] {
+                    if cell.continuation {
+                        continue;
+                    }
+                    let ink = if cell.ch == ' ' { Ink::Plain } else { cell.ink };
+                    match runs.last_mut() {
+                        Some((last, text)) if *last == ink => text.push(cell.ch),
+                        _ => runs.push((ink, cell.ch.to_string())),
+                    }
+                }
+                runs
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Grid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in self.lines() {
+            for (_, text) in line {
+                f.write_str(&text)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Corner, edge and side characters of a node outline
+fn border(shape: NodeShape) -> (char, char, char, char, char, char) {
+    match shape {
+        NodeShape::Rounded
+        | NodeShape::Stadium
+        | NodeShape::Circle
+        | NodeShape::Cylinder
+        | NodeShape::Actor => ('╭', '╮', '╰', '╯', '─', '│'),
+        NodeShape::DoubleCircle => ('╔', '╗', '╚', '╝', '═', '║'),
+        NodeShape::Subroutine => ('╓', '╖', '╙', '╜', '─', '║'),
+        NodeShape::Diamond | NodeShape::Hexagon => ('╱', '╲', '╲', '╱', '─', '│'),
+        NodeShape::Parallelogram | NodeShape::Trapezoid => ('╱', '╱', '╱', '╲', '─', '│'),
+        NodeShape::ParallelogramAlt | NodeShape::TrapezoidAlt => ('╲', '╲', '╲', '╱', '─', '│'),
+        NodeShape::Asymmetric => ('>', '┐', '>', '┘', '─', '│'),
+        _ => ('┌', '┐', '└', '┘', '─', '│'),
+    }
+}
+
+fn junction(lines: u8, style: LineStyle) -> char {
+    let thick = style == LineStyle::Thick;
+    match lines {
+        l if l & (UP | DOWN) == l => match style {
+            LineStyle::Dotted => '┆',
+            LineStyle::Thick => '┃',
+            _ => '│',
+        },
+        l if l & (LEFT | RIGHT) == l => match style {
+            LineStyle::Dotted => '┄',
+            LineStyle::Thick => '━',
+            _ => '─',
+        },
+        l if l == DOWN | RIGHT => pick(thick, '┌', '┏'),
+        l if l == DOWN | LEFT => pick(thick, '┐', '┓'),
+        l if l == UP | RIGHT => pick(thick, '└', '┗'),
+        l if l == UP | LEFT => pick(thick, '┘', '┛'),
+        l if l == UP | DOWN | RIGHT => pick(thick, '├', '┣'),
+        l if l == UP | DOWN | LEFT => pick(thick, '┤', '┫'),
+        l if l == LEFT | RIGHT | DOWN => pick(thick, '┬', '┳'),
+        l if l == LEFT | RIGHT | UP => pick(thick, '┴', '┻'),
+        _ => pick(thick, '┼', '╋'),
+    }
+}
+
+fn pick(thick: bool, thin: char, bold: char) -> char {
+    if thick {
+        bold
+    } else {
+        thin
+    }
+}
+
+/// Marker glyph for a line end travelling in direction `toward`
+fn marker_glyph(marker: Marker, toward: (i32, i32)) -> char {
+    let pointing = |down, up, right, left| match toward {
+        (0, dy) if dy > 0 => down,
+        (0, _) => up,
+        (dx, _) if dx > 0 => right,
+        _ => left,
+    };
+    match marker {
+        Marker::None => ' ',
+        Marker::Arrow | Marker::OpenArrow => pointing('▼', '▲', '▶', '◀'),
+        Marker::Triangle => pointing('▽', '△', '▷', '◁'),
+        Marker::Diamond => '◆',
+        Marker::OpenDiamond => '◇',
+        Marker::Circle => 'o',
+        Marker::Cross => '×',
+        // Cardinalities in regex notation
+        Marker::ExactlyOne => '1',
+        Marker::ZeroOrOne => '?',
+        Marker::OneOrMore => '+',
+        Marker::ZeroOrMore => '*',
+    }
+}
+
+/// Orthogonal cell path for an edge polyline
+///
+/// Ends that touch a box start from the cell just outside it. In graph
+/// layouts every odd point lies on a gap rank between node ranks; the path
+/// crosses over on those rows so sideways runs stay out of node rows.
+fn route(
+    points: &[Point],
+    boxes: &[Cells],
+    direction: Direction,
+    blocked: &dyn Fn((i32, i32)) -> bool,
+) -> Vec<(i32, i32)> {
+    let count = points.len();
+    let horizontal = direction.is_horizontal();
+    let mut cells: Vec<(i32, i32)> = points.iter().map(|p| cell(*p)).collect();
+
+    let owner = |p: Point| {
+        boxes.iter().find(|b| {
+            p.x >= b.x0 as f32 - 0.6
+                && p.x <= b.x1 as f32 + 1.6
+                && p.y >= b.y0 as f32 - 0.6
+                && p.y <= b.y1 as f32 + 1.6
+        })
+    };
+    for (end, next) in [(0, 1), (count - 1, count - 2)] {
+        match owner(points[end]) {
+            Some(b) => cells[end] = outside(b, cells[end], cells[next], horizontal),
+            // Sequence messages stop next to the lifeline
+            None => {
+                let (x, y) = cells[end];
+                let (nx, ny) = cells[next];
+                cells[end] = (x + (nx - x).signum(), y + (ny - y).signum());
+            }
+        }
+    }
+
+    let along = |c: (i32, i32)| if horizontal { c.0 } else { c.1 };
+    let with_along = |c: (i32, i32), value: i32| {
+        if horizontal {
+            (value, c.1)
+        } else {
+            (c.0, value)
+        }
+    };
+
+    let mut path = vec![cells[0]];
+    if count > 2 && count % 2 == 1 {
+        // Travel along to each gap point's rank, across in the gap, then on
+        for i in (1..count).step_by(2) {
+            let (a, gap, b) = (cells[i - 1], cells[i], cells[i + 1]);
+            let turn = turn_clear_of_labels(a, b, along(gap), horizontal, blocked);
+            path.push(with_along(a, turn));
+            path.push(with_along(b, turn));
+            path.push(b);
+        }
+    } else {
+        for pair in cells.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if a.0 != b.0 && a.1 != b.1 {
+                let turn = (along(a) + along(b)) / 2;
+                path.push(with_along(a, turn));
+                path.push(with_along(b, turn));
+            }
+            path.push(b);
+        }
+    }
+    path.dedup();
+    path
+}
+
+/// Row (or column) for the sideways run from `a` to `b`
+///
+/// Starts at `preferred` and moves away from it while the run would cross
+/// another edge's label.
+fn turn_clear_of_labels(
+    a: (i32, i32),
+    b: (i32, i32),
+    preferred: i32,
+    horizontal: bool,
+    blocked: &dyn Fn((i32, i32)) -> bool,
+) -> i32 {
+    let (along_a, across_a, along_b, across_b) = if horizontal {
+        (a.0, a.1, b.0, b.1)
+    } else {
+        (a.1, a.0, b.1, b.0)
+    };
+    let (mut low, mut high) = (along_a.min(along_b), along_a.max(along_b));
+    if high - low >= 2 {
+        (low, high) = (low + 1, high - 1);
+    }
+    let clear = |turn: i32| {
+        (across_a.min(across_b)..=across_a.max(across_b)).all(|across| {
+            let at = if horizontal {
+                (turn, across)
+            } else {
+                (across, turn)
+            };
+            !blocked(at)
+        })
+    };
+    let preferred = preferred.clamp(low, high);
+    (0..=high - low)
+        .flat_map(|step| [preferred + step, preferred - step])
+        .filter(|turn| (low..=high).contains(turn))
+        .find(|&turn| clear(turn))
+        .unwrap_or(preferred)
+}
+
+/// Cell next to box `b` on the side facing `next`
+///
+/// Positions along a side keep off the corners when the side is long enough.
+fn outside(b: &Cells, at: (i32, i32), next: (i32, i32), horizontal: bool) -> (i32, i32) {
+    let inner = |value: i32, low: i32, high: i32| {
+        if high - low >= 2 {
+            value.clamp(low + 1, high - 1)
+        } else {
+            value.clamp(low, high)
+        }
+    };
+    let x = inner(at.0, b.x0, b.x1);
+    let y = inner(at.1, b.y0, b.y1);
+    let vertical_side = if next.1 > b.y1 {
+        Some((x, b.y1 + 1))
+    } else if next.1 < b.y0 {
+        Some((x, b.y0 - 1))
+    } else {
+        None
+    };
+    let horizontal_side = if next.0 > b.x1 {
+        Some((b.x1 + 1, y))
+    } else if next.0 < b.x0 {
+        Some((b.x0 - 1, y))
+    } else {
+        None
+    };
+    let preferred = if horizontal {
+        horizontal_side.or(vertical_side)
+    } else {
+        vertical_side.or(horizontal_side)
+    };
+    preferred.unwrap_or(at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mermaid::parse;
+
+    fn draw(source: &str) -> String {
+        render(&parse(source).unwrap(), 200).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_boxes_by_rank() {
+        let text = draw("graph TD\n  A[Start] --> B[End]\n");
+        let lines: Vec<&str> = text.lines().collect();
+        let start = lines.iter().position(|l| l.contains("Start")).unwrap();
+        let end = lines.iter().position(|l| l.contains("End")).unwrap();
+        assert!(start < end);
+        assert!(text.contains('▼'));
+        assert!(
+            text.contains('┬'),
+            "line leaves the box through a tee:\n{text}"
+        );
+    }
+
+    #[test]
+    fn test_left_right_and_labels() {
+        let text = draw("graph LR\n  A -->|go| B\n");
+        let row = text.lines().find(|l| l.contains("go")).unwrap();
+        assert!(row.contains('▶') || text.contains('▶'));
+        let a = text.lines().find(|l| l.contains(" A ")).unwrap();
+        assert!(a.find(" A ").unwrap() < a.find(" B ").unwrap());
+    }
+
+    #[test]
+    fn test_branches_do_not_overlap_boxes() {
+        let text = draw("graph TD\n  A --> B & C\n  B --> D\n  C --> D\n");
+        for name in ["A", "B", "C", "D"] {
+            assert_eq!(text.matches(&format!("│ {name} │")).count(), 1, "{text}");
+        }
+        assert_eq!(text.matches('▼').count(), 4, "{text}");
+    }
+
+    #[test]
+    fn test_sequence_lifelines() {
+        let text = draw("sequenceDiagram\n  Alice->>Bob: Hi\n  Bob-->>Alice: Yo\n");
+        assert_eq!(text.matches("Alice").count(), 2);
+        assert!(text.contains('┆'));
+        assert!(text.contains('▶') && text.contains('◀'));
+        assert!(text.contains("Hi") && text.contains("Yo"));
+    }
+
+    #[test]
+    fn test_too_wide_returns_none() {
+        let diagram = parse("graph LR\n  A[a long label] --> B[another long label]\n").unwrap();
+        assert!(render(&diagram, 10).is_none());
+        // A horizontal diagram is retried top-down before giving up
+        let grid = render(&diagram, 30).unwrap();
+        assert!(grid.width() <= 30);
+    }
+}
@@ -0,0 +1,1608 @@
+//! Diagram layout
+//!
+//! Graph diagrams use a layered ("Sugiyama style") layout:
+//!
+//! 1. cycles are broken by reversing DFS back edges
+//! 2. nodes get longest-path ranks; every edge is stretched to span at
+//!    least two ranks so its label can sit on the rank in between
+//! 3. long edges are split into dummy nodes, one per rank
+//! 4. ranks are ordered by barycenter sweeps, keeping subgraphs contiguous
+//!    and the ordering with the fewest crossings
+//! 5. cross-axis positions are relaxed towards neighbours with an isotonic
+//!    regression per rank, which keeps the order and minimum separation
+//!
+//! The layout is computed in an abstract (cross, along) space and mapped
+//! to the diagram direction at the end. Sequence diagrams have their own
+//! column/row layout.
+//!
+//! All sizes come from a [`Measure`] and a [`Spacing`], so the same code
+//! lays out pixel graphics and terminal cells.
+
+use std::collections::HashMap;
+
+use unicode_width::UnicodeWidthStr;
+
+use super::geometry::clip;
+pub use super::geometry::{Point, Rect};
+use super::model::{
+    Diagram, DiagramKind, Direction, LineStyle, Marker, Node, NodeShape, NotePlacement, Step,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Size {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Size {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self { width, height }
+    }
+}
+
+/// Text measurement for the target backend
+pub trait Measure {
+    /// Size of `text`, which may contain `\n`
+    fn measure(&self, text: &str) -> Size;
+}
+
+impl<F: Fn(&str) -> Size> Measure for F {
+    fn measure(&self, text: &str) -> Size {
+        self(text)
+    }
+}
+
+/// Fixed-width font measurement (SVG export, terminal cells)
+#[derive(Debug, Clone, Copy)]
+pub struct Monospace {
+    pub char_width: f32,
+    pub line_height: f32,
+}
+
+impl Measure for Monospace {
+    fn measure(&self, text: &str) -> Size {
+        let lines: Vec<&str> = text.split('\n').collect();
+        let columns = lines.iter().map(|line| line.width()).max().unwrap_or(0);
+        Size::new(
+            columns as f32 * self.char_width,
+            lines.len() as f32 * self.line_height,
+        )
+    }
+}
+
+/// Distances used by the layout, in the same unit as [`Measure`]
+#[derive(Debug, Clone, Copy)]
+pub struct Spacing {
+    /// Space between a node label and its outline
+    pub padding: Size,
+    /// Space between neighbouring nodes of a rank
+    pub node_gap: f32,
+    /// Space between ranks
+    pub rank_gap: f32,
+    /// Space between a subgraph border and its contents
+    pub cluster_padding: f32,
+    /// Space around the whole diagram
+    pub margin: f32,
+    /// Diameter of state start/end points
+    pub point: f32,
+    /// Length and thickness of fork/join bars
+    pub bar: Size,
+    /// Arrow head length
+    pub marker: f32,
+    /// Extent of self loops
+    pub self_loop: f32,
+    /// Height that looks as long as one unit of width (cells are tall)
+    pub aspect: f32,
+}
+
+impl Spacing {
+    /// Defaults for pixel based backends (egui, SVG)
+    pub fn pixels() -> Self {
+        Self {
+            padding: Size::new(12.0, 8.0),
+            node_gap: 30.0,
+            rank_gap: 50.0,
+            cluster_padding: 14.0,
+            margin: 10.0,
+            point: 16.0,
+            bar: Size::new(60.0, 6.0),
+            marker: 9.0,
+            self_loop: 24.0,
+            aspect: 1.0,
+        }
+    }
+
+    /// Defaults for character grids, measured with `Monospace { 1, 1 }`
+    pub fn cells() -> Self {
+        Self {
+            padding: Size::new(2.0, 1.0),
+            node_gap: 4.0,
+            rank_gap: 4.0,
+            cluster_padding: 2.0,
+            margin: 0.0,
+            point: 1.0,
+            bar: Size::new(5.0, 1.0),
+            marker: 1.0,
+            self_loop: 3.0,
+            aspect: 0.5,
+        }
+    }
+}
+
+/// A laid out diagram
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Layout {
+    pub width: f32,
+    pub height: f32,
+    /// Subgraphs and sequence fragments, outermost first
+    pub clusters: Vec<PlacedCluster>,
+    pub nodes: Vec<PlacedNode>,
+    pub edges: Vec<PlacedEdge>,
+    /// Sequence diagram lifelines (drawn dashed)
+    pub lifelines: Vec<(Point, Point)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedNode {
+    pub id: String,
+    pub label: String,
+    pub shape: NodeShape,
+    pub rect: Rect,
+    /// Center of the label text
+    pub label_at: Point,
+    /// Class members / entity attributes below the header
+    pub sections: Vec<Section>,
+}
+
+/// Compartment of a class or entity box
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    /// Divider is drawn along the top edge
+    pub rect: Rect,
+    /// Left-aligned lines, anchored at the left end of their vertical center
+    pub lines: Vec<(String, Point)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedEdge {
+    /// Polyline from the source outline to the target outline
+    pub points: Vec<Point>,
+    pub line: LineStyle,
+    pub start: Marker,
+    pub end: Marker,
+    /// Texts centered at a point
+    pub label: Option<(String, Point)>,
+    pub start_label: Option<(String, Point)>,
+    pub end_label: Option<(String, Point)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedCluster {
+    pub label: String,
+    pub rect: Rect,
+    /// Center of the title
+    pub label_at: Point,
+    /// Horizontal dividers (sequence `else` branches) with their text
+    pub dividers: Vec<(f32, String)>,
+}
+
+impl Layout {
+    fn translate(&mut self, dx: f32, dy: f32) {
+        let shift = |p: &mut Point| {
+            p.x += dx;
+            p.y += dy;
+        };
+        for cluster in &mut self.clusters {
+            cluster.rect = cluster.rect.translate(dx, dy);
+            shift(&mut cluster.label_at);
+            for (y, _) in &mut cluster.dividers {
+                *y += dy;
+            }
+        }
+        for node in &mut self.nodes {
+            node.rect = node.rect.translate(dx, dy);
+            shift(&mut node.label_at);
+            for section in &mut node.sections {
+                section.rect = section.rect.translate(dx, dy);
+                section.lines.iter_mut().for_each(|(_, p)| shift(p));
+            }
+        }
+        for edge in &mut self.edges {
+            edge.points.iter_mut().for_each(shift);
+            for (_, p) in [&mut edge.label, &mut edge.start_label, &mut edge.end_label]
+                .into_iter()
+                .flatten()
+            {
+                shift(p);
+            }
+        }
+        for (a, b) in &mut self.lifelines {
+            shift(a);
+            shift(b);
+        }
+    }
+
+    /// Move everything to start at the margin and compute the total size
+    fn fit(&mut self, measure: &dyn Measure, margin: f32) {
+        let mut bounds: Option<Rect> = None;
+        let mut add = |rect: Rect| {
+            bounds = Some(match bounds {
+                Some(bounds) => bounds.union(&rect),
+                None => rect,
+            });
+        };
+        let text = |text: &str, at: Point| {
+            let size = measure.measure(text);
+            Rect::from_center(at, size.width, size.height)
+        };
+
+        self.clusters.iter().for_each(|c| add(c.rect));
+        self.nodes.iter().for_each(|n| add(n.rect));
+        for edge in &self.edges {
+            edge.points
+                .iter()
+                .for_each(|p| add(Rect::new(p.x, p.y, 0.0, 0.0)));
+            for (label, at) in [&edge.label, &edge.start_label, &edge.end_label]
+                .into_iter()
+                .flatten()
+            {
+                add(text(label, *at));
+            }
+        }
+
+        let bounds = bounds.unwrap_or_default();
+        self.translate(margin - bounds.x, margin - bounds.y);
+        self.width = bounds.width + margin * 2.0;
+        self.height = bounds.height + margin * 2.0;
+    }
+}
+
+/// Lay out a diagram
+pub fn layout(diagram: &Diagram, measure: &dyn Measure, spacing: &Spacing) -> Layout {
+    let mut layout = match diagram.kind {
+        DiagramKind::Sequence => sequence(diagram, measure, spacing),
+        _ => Graph::new(diagram, measure, spacing).layout(),
+    };
+    layout.fit(measure, spacing.margin);
+    layout
+}
+
+// ---------------------------------------------------------------------------
+// Node boxes
+
+/// Outline size of a node
+fn node_size(node: &Node, measure: &dyn Measure, spacing: &Spacing) -> Size {
+    let text = measure.measure(&node.label);
+    let (px, py) = (spacing.padding.width, spacing.padding.height);
+    let (w, h) = (text.width + px * 2.0, text.height + py * 2.0);
+
+    match node.shape {
+        NodeShape::Start | NodeShape::End => Size::new(spacing.point, spacing.point),
+        NodeShape::Bar => spacing.bar,
+        NodeShape::Circle => {
+            let d = w.max(h / spacing.aspect);
+            Size::new(d, d * spacing.aspect)
+        }
+        NodeShape::DoubleCircle => {
+            let d = w.max(h / spacing.aspect) + py;
+            Size::new(d, (d * spacing.aspect).max(h + py))
+        }
+        // A rhombus with twice the text box extents just contains it
+        NodeShape::Diamond => {
+            if node.label.is_empty() {
+                Size::new(spacing.point * 1.5, spacing.point * 1.5)
+            } else {
+                Size::new(text.width * 1.5 + px * 2.0, text.height * 2.0 + py * 2.0)
+            }
+        }
+        NodeShape::Stadium => Size::new(w + h / 2.0, h),
+        NodeShape::Hexagon | NodeShape::Trapezoid | NodeShape::TrapezoidAlt => {
+            Size::new(w + h.min(w / 2.0), h)
+        }
+        NodeShape::Parallelogram | NodeShape::ParallelogramAlt | NodeShape::Asymmetric => {
+            Size::new(w + h / 2.0, h)
+        }
+        NodeShape::Cylinder => Size::new(w, h + py * 2.0),
+        NodeShape::Actor => Size::new(w, h + text.height * 3.0),
+        NodeShape::Class | NodeShape::Entity => {
+            let mut size = Size::new(w, h);
+            for section in sections(node) {
+                let lines = measure.measure(&section.join("\n"));
+                size.width = size.width.max(lines.width + px * 2.0);
+                size.height += lines.height + py;
+            }
+            size
+        }
+        _ => Size::new(w, h),
+    }
+}
+
+/// Compartments below the name: attributes, then methods
+fn sections(node: &Node) -> Vec<Vec<String>> {
+    if node.details.is_empty() {
+        return Vec::new();
+    }
+    match node.shape {
+        NodeShape::Class => {
+            let (methods, attributes): (Vec<String>, Vec<String>) = node
+                .details
+                .iter()
+                .cloned()
+                .partition(|member| member.contains('('));
+            vec![attributes, methods]
+        }
+        _ => vec![node.details.clone()],
+    }
+}
+
+fn place_node(node: &Node, rect: Rect, measure: &dyn Measure, spacing: &Spacing) -> PlacedNode {
+    let text = measure.measure(&node.label);
+    let py = spacing.padding.height;
+    let center = rect.center();
+
+    let mut placed = PlacedNode {
+        id: node.id.clone(),
+        label: node.label.clone(),
+        shape: node.shape,
+        rect,
+        label_at: center,
+        sections: Vec::new(),
+    };
+
+    match node.shape {
+        NodeShape::Actor => {
+            placed.label_at = Point::new(center.x, rect.bottom() - py - text.height / 2.0);
+        }
+        NodeShape::Class | NodeShape::Entity => {
+            let header = text.height + py * 2.0;
+            placed.label_at = Point::new(center.x, rect.y + header / 2.0);
+            let line_height = measure.measure("Ag").height;
+            let mut y = rect.y + header;
+            for lines in sections(node) {
+                let height = lines.len() as f32 * line_height + py;
+                let top = y + py / 2.0;
+                placed.sections.push(Section {
+                    rect: Rect::new(rect.x, y, rect.width, height),
+                    lines: lines
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, line)| {
+                            let at = Point::new(
+                                rect.x + spacing.padding.width,
+                                top + line_height * (i as f32 + 0.5),
+                            );
+                            (line, at)
+                        })
+                        .collect(),
+                });
+                y += height;
+            }
+        }
+        _ => {}
+    }
+
+    placed
+}
+
+// ---------------------------------------------------------------------------
+// Layered graph layout
+
+/// Node of the layered graph: a diagram node or a dummy on a long edge
+struct Vertex {
+    /// Size across ranks / along the rank axis
+    across: f32,
+    along: f32,
+    /// Extra room across ranks for self loops
+    reserve: f32,
+    /// Innermost subgraph
+    cluster: Option<usize>,
+    /// Index into `Diagram::nodes`; `None` for dummies
+    node: Option<usize>,
+    rank: usize,
+}
+
+/// Diagram edge threaded through the layered graph
+struct Route {
+    edge: usize,
+    /// Vertices from the lower to the higher rank
+    vertices: Vec<usize>,
+    reversed: bool,
+    /// Vertex carrying the label
+    label: Option<usize>,
+    /// Subgraph drawn in place of an endpoint (`from`, `to`)
+    clusters: (Option<usize>, Option<usize>),
+}
+
+struct Graph<'a> {
+    diagram: &'a Diagram,
+    measure: &'a dyn Measure,
+    spacing: &'a Spacing,
+    horizontal: bool,
+    vertices: Vec<Vertex>,
+    /// Vertex of each diagram node (`None` for subgraph stand-ins)
+    vertex_of: Vec<Option<usize>>,
+    routes: Vec<Route>,
+    self_loops: Vec<usize>,
+    up: Vec<Vec<usize>>,
+    down: Vec<Vec<usize>>,
+    layers: Vec<Vec<usize>>,
+    /// Cross-axis centers
+    cross: Vec<f32>,
+}
+
+impl<'a> Graph<'a> {
+    fn new(diagram: &'a Diagram, measure: &'a dyn Measure, spacing: &'a Spacing) -> Self {
+        let mut graph = Self {
+            diagram,
+            measure,
+            spacing,
+            horizontal: diagram.direction.is_horizontal(),
+            vertices: Vec::new(),
+            vertex_of: vec![None; diagram.nodes.len()],
+            routes: Vec::new(),
+            self_loops: Vec::new(),
+            up: Vec::new(),
+            down: Vec::new(),
+            layers: Vec::new(),
+            cross: Vec::new(),
+        };
+        graph.add_nodes();
+        graph.rank();
+        graph.add_routes();
+        graph
+    }
+
+    fn layout(mut self) -> Layout {
+        self.order();
+        self.position();
+        let along = self.along_positions();
+        self.build(&along)
+    }
+
+    /// Subgraph index whose id is `id`, if it has contents to draw
+    fn cluster_named(&self, id: &str) -> Option<usize> {
+        let index = self.diagram.subgraphs.iter().position(|s| s.id == id)?;
+        self.representative(index).map(|_| index)
+    }
+
+    /// A vertex inside subgraph `index`, used to rank edges to the subgraph
+    fn representative(&self, index: usize) -> Option<usize> {
+        let subgraph = &self.diagram.subgraphs[index];
+        let direct = subgraph.nodes.iter().find_map(|id| {
+            let node = self.diagram.nodes.iter().position(|n| &n.id == id)?;
+            self.vertex_of.get(node).copied().flatten()
+        });
+        direct.or_else(|| {
+            (0..self.diagram.subgraphs.len())
+                .filter(|&child| self.diagram.subgraphs[child].parent == Some(index))
+                .find_map(|child| self.representative(child))
+        })
+    }
+
+    fn add_nodes(&mut self) {
+        let subgraph_ids: Vec<&str> = self
+            .diagram
+            .subgraphs
+            .iter()
+            .filter(|s| !s.nodes.is_empty())
+            .map(|s| s.id.as_str())
+            .collect();
+
+        for (index, node) in self.diagram.nodes.iter().enumerate() {
+            if subgraph_ids.contains(&node.id.as_str()) {
+                continue;
+            }
+            let size = node_size(node, self.measure, self.spacing);
+            let (across, along) = self.abstract_size(size);
+            let cluster = self
+                .diagram
+                .subgraphs
+                .iter()
+                .position(|s| s.nodes.contains(&node.id));
+            self.vertex_of[index] = Some(self.vertices.len());
+            self.vertices.push(Vertex {
+                across,
+                along,
+                reserve: 0.0,
+                cluster,
+                node: Some(index),
+                rank: 0,
+            });
+        }
+    }
+
+    fn abstract_size(&self, size: Size) -> (f32, f32) {
+        if self.horizontal {
+            (size.height, size.width)
+        } else {
+            (size.width, size.height)
+        }
+    }
+
+    /// Vertex and stand-in subgraph for an edge endpoint
+    fn endpoint(&self, id: &str) -> Option<(usize, Option<usize>)> {
+        if let Some(cluster) = self.cluster_named(id) {
+            return Some((self.representative(cluster)?, Some(cluster)));
+        }
+        let node = self.diagram.nodes.iter().position(|n| n.id == id)?;
+        Some((self.vertex_of[node]?, None))
+    }
+
+    /// Edges as (from, to, edge index) between vertices
+    fn vertex_edges(&self) -> Vec<(usize, usize, usize)> {
+        self.diagram
+            .edges
+            .iter()
+            .enumerate()
+            .filter_map(|(index, edge)| {
+                let (from, _) = self.endpoint(&edge.from)?;
+                let (to, _) = self.endpoint(&edge.to)?;
+                Some((from, to, index))
+            })
+            .collect()
+    }
+
+    /// Longest-path ranking on the graph with cycles broken
+    fn rank(&mut self) {
+        let n = self.vertices.len();
+        let edges: Vec<(usize, usize)> = acyclic(n, &self.vertex_edges())
+            .into_iter()
+            .filter(|(from, to, _)| from != to)
+            .map(|(from, to, _)| (from, to))
+            .collect();
+
+        let mut incoming = vec![0; n];
+        let mut outgoing: Vec<Vec<usize>> = vec![Vec::new(); n];
+        for &(from, to) in &edges {
+            incoming[to] += 1;
+            outgoing[from].push(to);
+        }
+
+        let mut rank = vec![0usize; n];
+        let mut remaining = incoming.clone();
+        let mut queue: Vec<usize> = (0..n).filter(|&v| remaining[v] == 0).collect();
+        let mut topological = Vec::with_capacity(n);
+        while let Some(v) = queue.pop() {
+            topological.push(v);
+            for &next in &outgoing[v] {
+                rank[next] = rank[next].max(rank[v] + 1);
+                remaining[next] -= 1;
+                if remaining[next] == 0 {
+                    queue.push(next);
+                }
+            }
+        }
+
+        // Pull sources down next to their first successor
+        for &v in topological.iter().rev() {
+            if incoming[v] == 0 {
+                if let Some(min) = outgoing[v].iter().map(|&next| rank[next]).min() {
+                    rank[v] = min - 1;
+                }
+            }
+        }
+
+        for (vertex, rank) in self.vertices.iter_mut().zip(rank) {
+            vertex.rank = rank * 2;
+        }
+    }
+
+    fn add_routes(&mut self) {
+        let edges = acyclic(self.vertices.len(), &self.vertex_edges());
+
+        for (from, to, index) in edges {
+            let edge = &self.diagram.edges[index];
+            let clusters = (
+                self.endpoint(&edge.from).and_then(|(_, c)| c),
+                self.endpoint(&edge.to).and_then(|(_, c)| c),
+            );
+            let reversed = self.endpoint(&edge.from).map(|(v, _)| v) != Some(from);
+
+            if from == to {
+                if clusters == (None, None) {
+                    self.self_loops.push(index);
+                    let label = edge
+                        .label
+                        .as_deref()
+                        .map(|l| self.abstract_size(self.measure.measure(l)).0)
+                        .unwrap_or(0.0);
+                    self.vertices[from].reserve += self.spacing.self_loop + label;
+                }
+                continue;
+            }
+
+            let cluster = common_cluster(
+                self.diagram,
+                self.vertices[from].cluster,
+                self.vertices[to].cluster,
+            );
+            let (from_rank, to_rank) = (self.vertices[from].rank, self.vertices[to].rank);
+            let span = to_rank - from_rank;
+            let mut label_step = span / 2;
+            if (from_rank + label_step) % 2 == 0 {
+                label_step -= 1;
+            }
+
+            let mut route = Route {
+                edge: index,
+                vertices: vec![from],
+                reversed,
+                label: None,
+                clusters,
+            };
+            for step in 1..span {
+                let (mut across, mut along) = (0.0, 0.0);
+                if step == label_step {
+                    if let Some(label) = &edge.label {
+                        let size = self.measure.measure(label);
+                        let pad = self.spacing.padding.height / 2.0;
+                        (across, along) = self
+                            .abstract_size(Size::new(size.width + pad * 2.0, size.height + pad));
+                        route.label = Some(self.vertices.len());
+                    }
+                }
+                route.vertices.push(self.vertices.len());
+                self.vertices.push(Vertex {
+                    across,
+                    along,
+                    reserve: 0.0,
+                    cluster,
+                    node: None,
+                    rank: from_rank + step,
+                });
+            }
+            route.vertices.push(to);
+            self.routes.push(route);
+        }
+
+        let n = self.vertices.len();
+        self.up = vec![Vec::new(); n];
+        self.down = vec![Vec::new(); n];
+        for route in &self.routes {
+            for pair in route.vertices.windows(2) {
+                self.down[pair[0]].push(pair[1]);
+                self.up[pair[1]].push(pair[0]);
+            }
+        }
+
+        let ranks = self.vertices.iter().map(|v| v.rank + 1).max().unwrap_or(0);
+        self.layers = vec![Vec::new(); ranks];
+        for (index, vertex) in self.vertices.iter().enumerate() {
+            self.layers[vertex.rank].push(index);
+        }
+    }
+
+    /// Subgraph chain of a vertex, outermost first
+    fn cluster_path(&self, vertex: usize) -> Vec<usize> {
+        let mut path = Vec::new();
+        let mut current = self.vertices[vertex].cluster;
+        while let Some(cluster) = current {
+            path.push(cluster);
+            current = self.diagram.subgraphs[cluster].parent;
+        }
+        path.reverse();
+        path
+    }
+
+    fn crossings(&self, layers: &[Vec<usize>]) -> usize {
+        let mut position = vec![0; self.vertices.len()];
+        for layer in layers {
+            for (i, &v) in layer.iter().enumerate() {
+                position[v] = i;
+            }
+        }
+        let mut total = 0;
+        for layer in layers {
+            let segments: Vec<(usize, usize)> = layer
+                .iter()
+                .flat_map(|&a| self.down[a].iter().map(move |&b| (a, b)))
+                .map(|(a, b)| (position[a], position[b]))
+                .collect();
+            for (i, &(a1, b1)) in segments.iter().enumerate() {
+                for &(a2, b2) in &segments[i + 1..] {
+                    if (a1 < a2 && b1 > b2) || (a1 > a2 && b1 < b2) {
+                        total += 1;
+                    }
+                }
+            }
+        }
+        total
+    }
+
+    /// Barycenter ordering, keeping the ordering with the fewest crossings
+    fn order(&mut self) {
+        let paths: Vec<Vec<usize>> = (0..self.vertices.len())
+            .map(|v| self.cluster_path(v))
+            .collect();
+        let mut position = vec![0.0f32; self.vertices.len()];
+        let mut layers = self.layers.clone();
+        for layer in &layers {
+            for (i, &v) in layer.iter().enumerate() {
+                position[v] = i as f32;
+            }
+        }
+        // Group subgraph members from the start
+        for layer in &mut layers {
+            sort_layer(layer, &position, &paths);
+        }
+
+        let mut best = layers.clone();
+        let mut best_crossings = self.crossings(&best);
+
+        for iteration in 0..8 {
+            let downward = iteration % 2 == 0;
+            let ranks: Vec<usize> = if downward {
+                (1..layers.len()).collect()
+            } else {
+                (0..layers.len().saturating_sub(1)).rev().collect()
+            };
+            for rank in ranks {
+                let mut barycenter = position.clone();
+                for &v in &layers[rank] {
+                    let neighbours = if downward { &self.up[v] } else { &self.down[v] };
+                    if !neighbours.is_empty() {
+                        barycenter[v] = neighbours.iter().map(|&u| position[u]).sum::<f32>()
+                            / neighbours.len() as f32;
+                    }
+                }
+                sort_layer(&mut layers[rank], &barycenter, &paths);
+                for (i, &v) in layers[rank].iter().enumerate() {
+                    position[v] = i as f32;
+                }
+            }
+            let crossings = self.crossings(&layers);
+            if crossings < best_crossings {
+                best_crossings = crossings;
+                best = layers.clone();
+            }
+        }
+
+        self.layers = best;
+    }
+
+    /// Minimum distance between the centers of neighbouring vertices
+    fn separation(&self, a: usize, b: usize) -> f32 {
+        let (va, vb) = (&self.vertices[a], &self.vertices[b]);
+        let gap = if va.node.is_none() && vb.node.is_none() {
+            self.spacing.node_gap / 3.0
+        } else {
+            self.spacing.node_gap
+        };
+        let (pa, pb) = (self.cluster_path(a), self.cluster_path(b));
+        let common = pa.iter().zip(&pb).take_while(|(x, y)| x == y).count();
+        let borders = (pa.len() - common + pb.len() - common) as f32;
+        (va.across + vb.across) / 2.0
+            + (va.reserve + vb.reserve)
+            + gap
+            + borders * self.spacing.cluster_padding
+    }
+
+    /// Cross-axis coordinates
+    fn position(&mut self) {
+        self.cross = vec![0.0; self.vertices.len()];
+        let layers = self.layers.clone();
+        for layer in &layers {
+            let mut x = 0.0;
+            for (i, &v) in layer.iter().enumerate() {
+                if i > 0 {
+                    x += self.separation(layer[i - 1], v);
+                }
+                self.cross[v] = x;
+            }
+        }
+
+        for iteration in 0..12 {
+            let (use_up, use_down) = match iteration % 3 {
+                0 => (true, false),
+                1 => (false, true),
+                _ => (true, true),
+            };
+            let order: Vec<&Vec<usize>> = if use_down && !use_up {
+                layers.iter().rev().collect()
+            } else {
+                layers.iter().collect()
+            };
+            for layer in order {
+                let desired: Vec<f32> = layer
+                    .iter()
+                    .map(|&v| {
+                        let mut neighbours: Vec<usize> = Vec::new();
+                        if use_up {
+                            neighbours.extend(&self.up[v]);
+                        }
+                        if use_down {
+                            neighbours.extend(&self.down[v]);
+                        }
+                        if neighbours.is_empty() {
+                            self.cross[v]
+                        } else {
+                            neighbours.iter().map(|&u| self.cross[u]).sum::<f32>()
+                                / neighbours.len() as f32
+                        }
+                    })
+                    .collect();
+                let gaps: Vec<f32> = layer
+                    .windows(2)
+                    .map(|pair| self.separation(pair[0], pair[1]))
+                    .collect();
+                for (&v, x) in layer.iter().zip(place(&desired, &gaps)) {
+                    self.cross[v] = x;
+                }
+            }
+        }
+    }
+
+    /// Along-axis centers of each rank
+    fn along_positions(&self) -> Vec<f32> {
+        let ranks = self.layers.len();
+        let mut thickness = vec![0.0f32; ranks];
+        for vertex in &self.vertices {
+            thickness[vertex.rank] = thickness[vertex.rank].max(vertex.along);
+        }
+
+        // Room for subgraph borders and titles
+        let mut first = vec![usize::MAX; self.diagram.subgraphs.len()];
+        let mut last = vec![0; self.diagram.subgraphs.len()];
+        for (v, vertex) in self.vertices.iter().enumerate() {
+            if vertex.node.is_some() {
+                for cluster in self.cluster_path(v) {
+                    first[cluster] = first[cluster].min(vertex.rank);
+                    last[cluster] = last[cluster].max(vertex.rank);
+                }
+            }
+        }
+        let padding = self.spacing.cluster_padding;
+        let mut before = vec![0.0f32; ranks];
+        let mut after = vec![0.0f32; ranks];
+        for (v, vertex) in self.vertices.iter().enumerate() {
+            let (mut start, mut end) = (0.0, 0.0);
+            for cluster in self.cluster_path(v) {
+                let title = self.title_height(cluster);
+                let (head, tail) = match self.diagram.direction {
+                    Direction::TopDown => (title + padding, padding),
+                    Direction::BottomUp => (padding, title + padding),
+                    _ => (padding, padding),
+                };
+                if first[cluster] == vertex.rank {
+                    start += head;
+                }
+                if last[cluster] == vertex.rank {
+                    end += tail;
+                }
+            }
+            before[vertex.rank] = before[vertex.rank].max(start);
+            after[vertex.rank] = after[vertex.rank].max(end);
+        }
+
+        let gap = self.spacing.rank_gap / 2.0;
+        let mut along = vec![0.0; ranks];
+        for rank in 0..ranks {
+            along[rank] = if rank == 0 {
+                before[0] + thickness[0] / 2.0
+            } else {
+                along[rank - 1]
+                    + thickness[rank - 1] / 2.0
+                    + after[rank - 1]
+                    + gap
+                    + before[rank]
+                    + thickness[rank] / 2.0
+            };
+        }
+        along
+    }
+
+    fn title_height(&self, cluster: usize) -> f32 {
+        let label = &self.diagram.subgraphs[cluster].label;
+        if label.is_empty() {
+            0.0
+        } else {
+            self.measure.measure(label).height
+        }
+    }
+
+    fn build(&self, along: &[f32]) -> Layout {
+        let extent = along.last().copied().unwrap_or(0.0) * 2.0;
+        let point = |cross: f32, along: f32| match self.diagram.direction {
+            Direction::TopDown => Point::new(cross, along),
+            Direction::BottomUp => Point::new(cross, extent - along),
+            Direction::LeftRight => Point::new(along, cross),
+            Direction::RightLeft => Point::new(extent - along, cross),
+        };
+        let center = |v: usize| point(self.cross[v], along[self.vertices[v].rank]);
+        let rect = |v: usize| {
+            let vertex = &self.vertices[v];
+            let (w, h) = if self.horizontal {
+                (vertex.along, vertex.across)
+            } else {
+                (vertex.across, vertex.along)
+            };
+            Rect::from_center(center(v), w, h)
+        };
+
+        let mut layout = Layout::default();
+        let mut node_rects: HashMap<usize, Rect> = HashMap::new();
+        for (v, vertex) in self.vertices.iter().enumerate() {
+            if let Some(index) = vertex.node {
+                let node = &self.diagram.nodes[index];
+                node_rects.insert(index, rect(v));
+                layout
+                    .nodes
+                    .push(place_node(node, rect(v), self.measure, self.spacing));
+            }
+        }
+
+        let cluster_rects = self.cluster_rects(&node_rects);
+        for (index, rect) in cluster_rects.iter().enumerate() {
+            if let Some(rect) = rect {
+                let subgraph = &self.diagram.subgraphs[index];
+                let title = self.title_height(index);
+                layout.clusters.push(PlacedCluster {
+                    label: subgraph.label.clone(),
+                    rect: *rect,
+                    label_at: Point::new(
+                        rect.center().x,
+                        rect.y + self.spacing.cluster_padding / 2.0 + title / 2.0,
+                    ),
+                    dividers: Vec::new(),
+                });
+            }
+        }
+
+        for route in &self.routes {
+            let edge = &self.diagram.edges[route.edge];
+            if edge.line == LineStyle::Invisible {
+                continue;
+            }
+            let mut points: Vec<Point> = route.vertices.iter().map(|&v| center(v)).collect();
+            let (mut first, mut last) = route.clusters;
+            if route.reversed {
+                std::mem::swap(&mut first, &mut last);
+            }
+            let outline =
+                |v: usize, cluster: Option<usize>| match cluster.and_then(|c| cluster_rects[c]) {
+                    Some(rect) => (NodeShape::Rectangle, rect),
+                    None => (self.shape_of(v), rect(v)),
+                };
+            let count = points.len();
+            let (shape, bounds) = outline(route.vertices[0], first);
+            points[0] = clip(shape, bounds, points[1]);
+            let (shape, bounds) = outline(route.vertices[count - 1], last);
+            points[count - 1] = clip(shape, bounds, points[count - 2]);
+            if route.reversed {
+                points.reverse();
+            }
+            let label = route
+                .label
+                .zip(edge.label.clone())
+                .map(|(v, label)| (label, center(v)));
+            layout.edges.push(self.placed_edge(edge, points, label));
+        }
+
+        for &index in &self.self_loops {
+            let edge = &self.diagram.edges[index];
+            let Some((v, _)) = self.endpoint(&edge.from) else {
+                continue;
+            };
+            let r = rect(v);
+            let c = r.center();
+            let reach = self.spacing.self_loop;
+            let (points, label) = if self.horizontal {
+                let y = r.bottom() + reach;
+                let label = edge.label.as_ref().map(|label| {
+                    let size = self.measure.measure(label);
+                    (label.clone(), Point::new(c.x, y + size.height / 2.0 + 2.0))
+                });
+                let (x1, x2) = (c.x - r.width / 4.0, c.x + r.width / 4.0);
+                (
+                    vec![
+                        Point::new(x1, r.bottom()),
+                        Point::new(x1, y),
+                        Point::new(x2, y),
+                        Point::new(x2, r.bottom()),
+                    ],
+                    label,
+                )
+            } else {
+                let x = r.right() + reach;
+                let label = edge.label.as_ref().map(|label| {
+                    let size = self.measure.measure(label);
+                    (label.clone(), Point::new(x + size.width / 2.0 + 4.0, c.y))
+                });
+                let (y1, y2) = (c.y - r.height / 4.0, c.y + r.height / 4.0);
+                (
+                    vec![
+                        Point::new(r.right(), y1),
+                        Point::new(x, y1),
+                        Point::new(x, y2),
+                        Point::new(r.right(), y2),
+                    ],
+                    label,
+                )
+            };
+            layout.edges.push(self.placed_edge(edge, points, label));
+        }
+
+        layout
+    }
+
+    fn shape_of(&self, v: usize) -> NodeShape {
+        self.vertices[v]
+            .node
+            .map(|index| self.diagram.nodes[index].shape)
+            .unwrap_or_default()
+    }
+
+    fn placed_edge(
+        &self,
+        edge: &super::model::Edge,
+        points: Vec<Point>,
+        label: Option<(String, Point)>,
+    ) -> PlacedEdge {
+        let end_label = |text: &Option<String>, tip: Point, from: Point| {
+            text.as_ref().map(|text| {
+                let size = self.measure.measure(text);
+                let length = ((from.x - tip.x).powi(2) + (from.y - tip.y).powi(2))
+                    .sqrt()
+                    .max(f32::EPSILON);
+                let (ux, uy) = ((from.x - tip.x) / length, (from.y - tip.y) / length);
+                let along = self.spacing.marker * 1.5 + size.height / 2.0;
+                let aside = size.width / 2.0 + self.spacing.padding.height / 2.0;
+                (
+                    text.clone(),
+                    Point::new(
+                        tip.x + ux * along - uy * aside,
+                        tip.y + uy * along + ux * aside,
+                    ),
+                )
+            })
+        };
+        let count = points.len();
+        PlacedEdge {
+            start_label: end_label(&edge.start_label, points[0], points[1]),
+            end_label: end_label(&edge.end_label, points[count - 1], points[count - 2]),
+            points,
+            line: edge.line,
+            start: edge.start,
+            end: edge.end,
+            label,
+        }
+    }
+
+    /// Bounding boxes of subgraphs, innermost computed first
+    fn cluster_rects(&self, node_rects: &HashMap<usize, Rect>) -> Vec<Option<Rect>> {
+        let subgraphs = &self.diagram.subgraphs;
+        let mut rects: Vec<Option<Rect>> = vec![None; subgraphs.len()];
+        for index in (0..subgraphs.len()).rev() {
+            let members = subgraphs[index].nodes.iter().filter_map(|id| {
+                let node = self.diagram.nodes.iter().position(|n| &n.id == id)?;
+                node_rects.get(&node).copied()
+            });
+            let children = (0..subgraphs.len()).filter_map(|child| match subgraphs[child].parent {
+                Some(parent) if parent == index => rects[child],
+                _ => None,
+            });
+            let Some(content) = members.chain(children).reduce(|a, b| a.union(&b)) else {
+                continue;
+            };
+            let padding = self.spacing.cluster_padding;
+            let title = self.title_height(index);
+            let mut rect = content.expand(padding);
+            rect.y -= title;
+            rect.height += title;
+            // Keep the title readable when the contents are narrow
+            let width = self.measure.measure(&subgraphs[index].label).width + padding * 2.0;
+            if rect.width < width {
+                rect.x -= (width - rect.width) / 2.0;
+                rect.width = width;
+            }
+            rects[index] = Some(rect);
+        }
+        rects
+    }
+}
+
+/// Reverse DFS back edges so the graph becomes acyclic
+fn acyclic(n: usize, edges: &[(usize, usize, usize)]) -> Vec<(usize, usize, usize)> {
+    let mut outgoing: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for (index, &(from, _, _)) in edges.iter().enumerate() {
+        outgoing[from].push(index);
+    }
+
+    // 0 = unvisited, 1 = on the stack, 2 = done
+    let mut state = vec![0u8; n];
+    let mut reversed = vec![false; edges.len()];
+    for root in 0..n {
+        if state[root] != 0 {
+            continue;
+        }
+        let mut stack = vec![(root, 0)];
+        state[root] = 1;
+        while let Some(&mut (v, ref mut next)) = stack.last_mut() {
+            if let Some(&edge) = outgoing[v].get(*next) {
+                *next += 1;
+                let to = edges[edge].1;
+                match state[to] {
+                    0 => {
+                        state[to] = 1;
+                        stack.push((to, 0));
+                    }
+                    1 if to != v => reversed[edge] = true,
+                    _ => {}
+                }
+            } else {
+                state[v] = 2;
+                stack.pop();
+            }
+        }
+    }
+
+    edges
+        .iter()
+        .zip(reversed)
+        .map(|(&(from, to, index), reversed)| {
+            if reversed {
+                (to, from, index)
+            } else {
+                (from, to, index)
+            }
+        })
+        .collect()
+}
+
+/// Innermost subgraph containing both clusters
+fn common_cluster(diagram: &Diagram, a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    let chain = |mut current: Option<usize>| {
+        let mut chain = Vec::new();
+        while let Some(cluster) = current {
+            chain.push(cluster);
+            current = diagram.subgraphs[cluster].parent;
+        }
+        chain
+    };
+    let b_chain = chain(b);
+    chain(a)
+        .into_iter()
+        .find(|cluster| b_chain.contains(cluster))
+}
+
+/// Sort a rank by barycenter, keeping subgraph members next to each other
+fn sort_layer(layer: &mut [usize], barycenter: &[f32], paths: &[Vec<usize>]) {
+    // Mean barycenter of each subgraph's members in this rank
+    let mut groups: HashMap<usize, (f32, usize)> = HashMap::new();
+    for &v in layer.iter() {
+        for &cluster in &paths[v] {
+            let entry = groups.entry(cluster).or_default();
+            entry.0 += barycenter[v];
+            entry.1 += 1;
+        }
+    }
+    let key = |v: usize| -> Vec<(f32, usize)> {
+        let mut key: Vec<(f32, usize)> = paths[v]
+            .iter()
+            .map(|cluster| {
+                let (sum, count) = groups[cluster];
+                (sum / count as f32, *cluster)
+            })
+            .collect();
+        key.push((barycenter[v], usize::MAX / 2 + v));
+        key
+    };
+    let keys: HashMap<usize, Vec<(f32, usize)>> = layer.iter().map(|&v| (v, key(v))).collect();
+    layer.sort_by(|a, b| {
+        keys[a]
+            .partial_cmp(&keys[b])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
+
+/// Positions closest to `desired` (least squares) that keep the order and
+/// at least `gaps[i]` between item `i` and `i + 1`
+fn place(desired: &[f32], gaps: &[f32]) -> Vec<f32> {
+    let mut offsets = Vec::with_capacity(desired.len());
+    let mut offset = 0.0;
+    for i in 0..desired.len() {
+        if i > 0 {
+            offset += gaps[i - 1];
+        }
+        offsets.push(offset);
+    }
+
+    // Pool adjacent violators on the shifted targets
+    let mut blocks: Vec<(f32, usize)> = Vec::new();
+    for (target, offset) in desired.iter().zip(&offsets) {
+        blocks.push((target - offset, 1));
+        while blocks.len() > 1 {
+            let (sum_b, count_b) = blocks[blocks.len() - 1];
+            let (sum_a, count_a) = blocks[blocks.len() - 2];
+            if sum_a / count_a as f32 <= sum_b / count_b as f32 {
+                break;
+            }
+            blocks.pop();
+            *blocks.last_mut().unwrap() = (sum_a + sum_b, count_a + count_b);
+        }
+    }
+
+    blocks
+        .iter()
+        .flat_map(|&(sum, count)| std::iter::repeat_n(sum / count as f32, count))
+        .zip(offsets)
+        .map(|(value, offset)| value + offset)
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Sequence diagram
+
+/// Sequence fragment that has not seen its `end` yet
+struct Fragment {
+    top: f32,
+    label: String,
+    dividers: Vec<(f32, String)>,
+}
+
+fn sequence(diagram: &Diagram, measure: &dyn Measure, spacing: &Spacing) -> Layout {
+    let (px, py) = (spacing.padding.width, spacing.padding.height);
+    let sizes: Vec<Size> = diagram
+        .nodes
+        .iter()
+        .map(|node| node_size(node, measure, spacing))
+        .collect();
+    let column = |id: &str| diagram.nodes.iter().position(|n| n.id == id);
+    let count = diagram.nodes.len();
+
+    // Minimum distance between neighbouring lifelines
+    let mut gaps: Vec<f32> = (1..count)
+        .map(|i| (sizes[i - 1].width + sizes[i].width) / 2.0 + spacing.node_gap)
+        .collect();
+    let mut spans: Vec<(usize, usize, f32)> = Vec::new();
+    for step in &diagram.steps {
+        match step {
+            Step::Message(index) => {
+                let edge = &diagram.edges[*index];
+                let (Some(a), Some(b)) = (column(&edge.from), column(&edge.to)) else {
+                    continue;
+                };
+                let label = edge
+                    .label
+                    .as_deref()
+                    .map(|l| measure.measure(l).width)
+                    .unwrap_or(0.0);
+                if a == b {
+                    spans.push((a, a + 1, label + spacing.self_loop + px));
+                } else {
+                    spans.push((a.min(b), a.max(b), label + px * 2.0));
+                }
+            }
+            Step::Note {
+                placement,
+                participants,
+                text,
+            } => {
+                let width = measure.measure(text).width + px * 2.0 + py;
+                let Some(a) = participants.first().and_then(|p| column(p)) else {
+                    continue;
+                };
+                match placement {
+                    NotePlacement::RightOf => spans.push((a, a + 1, width + px)),
+                    NotePlacement::LeftOf if a > 0 => spans.push((a - 1, a, width + px)),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    spans.sort_by_key(|&(a, b, _)| b - a);
+    for (a, b, needed) in spans {
+        if b >= count {
+            continue;
+        }
+        let current: f32 = gaps[a..b].iter().sum();
+        if current < needed {
+            gaps[b - 1] += needed - current;
+        }
+    }
+
+    let mut xs = vec![sizes.first().map(|s| s.width / 2.0).unwrap_or(0.0)];
+    for gap in &gaps {
+        xs.push(xs.last().unwrap() + gap);
+    }
+
+    let header = sizes.iter().map(|s| s.height).fold(0.0, f32::max);
+    let line_height = measure.measure("Ag").height;
+    let mut layout = Layout::default();
+    let mut y = header + spacing.rank_gap / 2.0;
+    let left = xs.first().copied().unwrap_or(0.0) - sizes.first().map_or(0.0, |s| s.width / 2.0);
+    let right = xs.last().copied().unwrap_or(0.0) + sizes.last().map_or(0.0, |s| s.width / 2.0);
+    let mut fragments: Vec<Fragment> = Vec::new();
+    let mut finished: Vec<(usize, PlacedCluster)> = Vec::new();
+
+    for step in &diagram.steps {
+        match step {
+            Step::Message(index) => {
+                let edge = &diagram.edges[*index];
+                let (Some(a), Some(b)) = (column(&edge.from), column(&edge.to)) else {
+                    continue;
+                };
+                let label_height = edge
+                    .label
+                    .as_deref()
+                    .map(|l| measure.measure(l).height)
+                    .unwrap_or(0.0);
+                let arrow_y = y + label_height + py / 2.0;
+                let (points, label_at) = if a == b {
+                    let x = xs[a];
+                    let reach = spacing.self_loop;
+                    let bottom = arrow_y + reach * 0.75;
+                    let width = edge
+                        .label
+                        .as_deref()
+                        .map(|l| measure.measure(l).width)
+                        .unwrap_or(0.0);
+                    y = bottom;
+                    (
+                        vec![
+                            Point::new(x, arrow_y),
+                            Point::new(x + reach, arrow_y),
+                            Point::new(x + reach, bottom),
+                            Point::new(x, bottom),
+                        ],
+                        Point::new(x + reach + width / 2.0 + py / 2.0, (arrow_y + bottom) / 2.0),
+                    )
+                } else {
+                    y = arrow_y;
+                    (
+                        vec![Point::new(xs[a], arrow_y), Point::new(xs[b], arrow_y)],
+                        Point::new(
+                            (xs[a] + xs[b]) / 2.0,
+                            arrow_y - py / 2.0 - label_height / 2.0,
+                        ),
+                    )
+                };
+                layout.edges.push(PlacedEdge {
+                    points,
+                    line: edge.line,
+                    start: edge.start,
+                    end: edge.end,
+                    label: edge.label.clone().map(|label| (label, label_at)),
+                    start_label: None,
+                    end_label: None,
+                });
+                y += spacing.rank_gap / 2.0;
+            }
+            Step::Note {
+                placement,
+                participants,
+                text,
+            } => {
+                let columns: Vec<usize> = participants.iter().filter_map(|p| column(p)).collect();
+                let (Some(&first), Some(&last)) = (columns.iter().min(), columns.iter().max())
+                else {
+                    continue;
+                };
+                let size = measure.measure(text);
+                let width = size.width + px * 2.0 + py;
+                let height = size.height + py * 2.0;
+                let x = match placement {
+                    NotePlacement::LeftOf => xs[first] - px - width,
+                    NotePlacement::RightOf => xs[first] + px,
+                    NotePlacement::Over => {
+                        let span = xs[last] - xs[first] + px * 2.0;
+                        let width = width.max(span);
+                        (xs[first] + xs[last]) / 2.0 - width / 2.0
+                    }
+                };
+                let width = match placement {
+                    NotePlacement::Over => width.max(xs[last] - xs[first] + px * 2.0),
+                    _ => width,
+                };
+                let rect = Rect::new(x, y, width, height);
+                layout.nodes.push(PlacedNode {
+                    id: String::new(),
+                    label: text.clone(),
+                    shape: NodeShape::Note,
+                    rect,
+                    label_at: rect.center(),
+                    sections: Vec::new(),
+                });
+                y += height + spacing.rank_gap / 2.0;
+            }
+            Step::FragmentStart { kind, text } => {
+                let label = match (kind.as_str(), text.is_empty()) {
+                    ("rect", _) => String::new(),
+                    (_, true) => kind.clone(),
+                    (_, false) => format!("{kind} [{text}]"),
+                };
+                fragments.push(Fragment {
+                    top: y,
+                    label,
+                    dividers: Vec::new(),
+                });
+                y += line_height + py * 2.0;
+            }
+            Step::FragmentDivider { text } => {
+                if let Some(Fragment { dividers, .. }) = fragments.last_mut() {
+                    let text = if text.is_empty() {
+                        String::new()
+                    } else {
+                        format!("[{text}]")
+                    };
+                    dividers.push((y, text));
+                    y += line_height + py * 2.0;
+                }
+            }
+            Step::FragmentEnd => {
+                if let Some(Fragment {
+                    top,
+                    label,
+                    dividers,
+                }) = fragments.pop()
+                {
+                    let depth = fragments.len();
+                    let inset = depth as f32 * py;
+                    let x = left - spacing.cluster_padding + inset;
+                    let rect = Rect::new(
+                        x,
+                        top,
+                        right - left + spacing.cluster_padding * 2.0 - inset * 2.0,
+                        y - top,
+                    );
+                    finished.push((
+                        depth,
+                        PlacedCluster {
+                            label,
+                            rect,
+                            label_at: Point::new(rect.center().x, top + py + line_height / 2.0),
+                            dividers,
+                        },
+                    ));
+                    y += py;
+                }
+            }
+        }
+    }
+
+    // Outermost fragments first so inner ones are drawn on top
+    finished.sort_by_key(|(depth, _)| *depth);
+    layout.clusters = finished.into_iter().map(|(_, cluster)| cluster).collect();
+
+    let bottom = y + spacing.rank_gap / 4.0;
+    for (node, (size, x)) in diagram.nodes.iter().zip(sizes.iter().zip(&xs)) {
+        let top = Rect::new(
+            x - size.width / 2.0,
+            header - size.height,
+            size.width,
+            size.height,
+        );
+        let below = Rect::new(top.x, bottom, size.width, size.height);
+        layout
+            .lifelines
+            .push((Point::new(*x, top.bottom()), Point::new(*x, below.y)));
+        layout
+            .nodes
+            .insert(0, place_node(node, below, measure, spacing));
+        layout
+            .nodes
+            .insert(0, place_node(node, top, measure, spacing));
+    }
+
+    layout
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mermaid::parse;
+
+    const MONO: Monospace = Monospace {
+        char_width: 8.0,
+        line_height: 16.0,
+    };
+
+    fn lay_out(source: &str) -> Layout {
+        layout(&parse(source).unwrap(), &MONO, &Spacing::pixels())
+    }
+
+    fn node<'a>(layout: &'a Layout, id: &str) -> &'a PlacedNode {
+        layout.nodes.iter().find(|n| n.id == id).unwrap()
+    }
+
+    fn overlaps(a: &Rect, b: &Rect) -> bool {
+        a.x < b.right() && b.x < a.right() && a.y < b.bottom() && b.y < a.bottom()
+    }
+
+    #[test]
+    fn test_ranks_follow_direction() {
+        let layout = lay_out("graph TD\n  A --> B --> C\n  A --> C\n");
+        let (a, b, c) = (node(&layout, "A"), node(&layout, "B"), node(&layout, "C"));
+        assert!(a.rect.bottom() < b.rect.y);
+        assert!(b.rect.bottom() < c.rect.y);
+
+        let layout = lay_out("graph LR\n  A --> B\n");
+        assert!(node(&layout, "A").rect.right() < node(&layout, "B").rect.x);
+
+        let layout = lay_out("graph BT\n  A --> B\n");
+        assert!(node(&layout, "A").rect.y > node(&layout, "B").rect.bottom());
+    }
+
+    #[test]
+    fn test_nodes_do_not_overlap() {
+        let layout = lay_out(
+            "flowchart TD\n  A --> B & C & D\n  B --> E\n  C --> E\n  D --> F\n  E --> F\n  F --> A\n",
+        );
+        for (i, a) in layout.nodes.iter().enumerate() {
+            for b in &layout.nodes[i + 1..] {
+                assert!(!overlaps(&a.rect, &b.rect), "{} overlaps {}", a.id, b.id);
+            }
+        }
+        // The cycle is kept: every edge is drawn
+        assert_eq!(layout.edges.len(), 8);
+    }
+
+    #[test]
+    fn test_edges_end_on_outlines() {
+        let layout = lay_out("graph TD\n  A[Start] -->|go| B[End]\n");
+        let edge = &layout.edges[0];
+        let (a, b) = (node(&layout, "A").rect, node(&layout, "B").rect);
+        let first = edge.points[0];
+        let last = *edge.points.last().unwrap();
+        assert!((first.y - a.bottom()).abs() < 0.5);
+        assert!((last.y - b.y).abs() < 0.5);
+        // The label sits between the two nodes
+        let (_, at) = edge.label.as_ref().unwrap();
+        assert!(at.y > a.bottom() && at.y < b.y);
+    }
+
+    #[test]
+    fn test_subgraph_contains_members() {
+        let layout =
+            lay_out("graph TD\n  X --> A\n  subgraph one [Group]\n    A --> B\n  end\n  B --> Y\n");
+        let cluster = &layout.clusters[0];
+        assert_eq!(cluster.label, "Group");
+        for id in ["A", "B"] {
+            let rect = node(&layout, id).rect;
+            assert!(cluster.rect.x < rect.x && rect.right() < cluster.rect.right());
+            assert!(cluster.rect.y < rect.y && rect.bottom() < cluster.rect.bottom());
+        }
+        for id in ["X", "Y"] {
+            assert!(!overlaps(&cluster.rect, &node(&layout, id).rect));
+        }
+    }
+
+    #[test]
+    fn test_class_sections() {
+        let layout = lay_out("classDiagram\n  class Animal {\n    +name\n    +eat()\n  }\n");
+        let animal = node(&layout, "Animal");
+        assert_eq!(animal.sections.len(), 2);
+        assert_eq!(animal.sections[0].lines[0].0, "+name");
+        assert_eq!(animal.sections[1].lines[0].0, "+eat()");
+        assert!(animal.sections[1].rect.bottom() <= animal.rect.bottom() + 0.5);
+    }
+
+    #[test]
+    fn test_sequence_rows() {
+        let layout = lay_out(
+            "sequenceDiagram\n  participant A as Alice\n  A->>B: a rather long message\n  loop Every minute\n    B-->>A: ok\n  end\n",
+        );
+        // Top and bottom participant boxes
+        assert_eq!(layout.nodes.iter().filter(|n| n.id == "A").count(), 2);
+        assert_eq!(layout.lifelines.len(), 2);
+        let (first, second) = (&layout.edges[0], &layout.edges[1]);
+        assert!(first.points[0].y < second.points[0].y);
+        // Columns are wide enough for the label
+        let width = first.points[1].x - first.points[0].x;
+        assert!(width >= MONO.measure("a rather long message").width);
+        let fragment = &layout.clusters[0];
+        assert_eq!(fragment.label, "loop [Every minute]");
+        assert!(
+            fragment.rect.y < second.points[0].y && second.points[0].y < fragment.rect.bottom()
+        );
+    }
+
+    #[test]
+    fn test_place_keeps_order_and_gaps() {
+        let placed = place(&[10.0, 0.0, 30.0], &[5.0, 5.0]);
+        assert!(placed[1] - placed[0] >= 5.0 - 1e-4);
+        assert!(placed[2] - placed[1] >= 5.0 - 1e-4);
+        assert!((placed[2] - 30.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,28 @@
+//! # Native Mermaid engine
+//!
+//! Mermaid ダイアグラムをブラウザや Node.js なしで描画するためのモジュール。
+//!
+//! ```text
+//! source ─▶ parser::parse ─▶ Diagram ─▶ layout::layout ─▶ Layout ─┬─▶ svg::render_layout
+//!                                                                 ├─▶ gui::diagram_view (egui)
+//!                                                                 └─▶ grid::render (terminal)
+//! ```
+//!
+//! - [`parser`] — flowchart / sequence / class / state / ER の構文を解析
+//! - [`diagnostics`] — 構文エラーを行・桁付きで報告 (エディタの波線と `lala mermaid --check`)
+//! - [`layout`] — 階層型 (Sugiyama 風) レイアウトとシーケンス図のレイアウト
+//! - [`geometry`] — ノード形状と矢印マーカーの描画プリミティブ (全バックエンド共通)
+//! - [`svg`] — SVG 出力
+//! - [`grid`] — 罫線文字による端末向け描画
+
+pub mod diagnostics;
+pub mod geometry;
+pub mod grid;
+pub mod layout;
+pub mod model;
+pub mod parser;
+pub mod svg;
+
+pub use diagnostics::{check, Diagnostic, Severity};
+pub use model::*;
+pub use parser::parse;
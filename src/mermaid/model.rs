@@ -0,0 +1,297 @@
+//! Diagram model produced by the Mermaid parser
+//!
+//! One structure covers every supported diagram type. Flowcharts, class,
+//! state and ER diagrams are graphs of [`Node`]s and [`Edge`]s; sequence
+//! diagrams use the nodes as participants and the edges as messages, with
+//! [`Diagram::steps`] recording the order of messages, notes and fragments.
+
+/// Kind of graph diagram
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagramKind {
+    Flowchart,
+    Sequence,
+    Class,
+    State,
+    EntityRelationship,
+}
+
+impl DiagramKind {
+    /// Human readable name
+    pub fn label(&self) -> &'static str {
+        match self {
+            DiagramKind::Flowchart => "Flowchart",
+            DiagramKind::Sequence => "Sequence Diagram",
+            DiagramKind::Class => "Class Diagram",
+            DiagramKind::State => "State Diagram",
+            DiagramKind::EntityRelationship => "Entity-Relationship Diagram",
+        }
+    }
+}
+
+/// Flow direction of a graph diagram
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Direction {
+    /// Top to bottom (`TD` / `TB`)
+    #[default]
+    TopDown,
+    BottomUp,
+    LeftRight,
+    RightLeft,
+}
+
+impl Direction {
+    /// Parse a Mermaid direction keyword
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "TD" | "TB" => Some(Direction::TopDown),
+            "BT" => Some(Direction::BottomUp),
+            "LR" => Some(Direction::LeftRight),
+            "RL" => Some(Direction::RightLeft),
+            _ => None,
+        }
+    }
+
+    /// Whether ranks advance horizontally
+    pub fn is_horizontal(&self) -> bool {
+        matches!(self, Direction::LeftRight | Direction::RightLeft)
+    }
+}
+
+/// A parsed diagram
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagram {
+    pub kind: DiagramKind,
+    /// Original Mermaid source, for backends that render it natively
+    pub source: String,
+    pub direction: Direction,
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    /// Flowchart subgraphs and composite states
+    pub subgraphs: Vec<Subgraph>,
+    /// Sequence diagram timeline; empty for other kinds
+    pub steps: Vec<Step>,
+}
+
+/// Outline drawn around a node
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NodeShape {
+    /// `A[text]`
+    #[default]
+    Rectangle,
+    /// `A(text)`
+    Rounded,
+    /// `A([text])`
+    Stadium,
+    /// `A[[text]]`
+    Subroutine,
+    /// `A[(text)]`
+    Cylinder,
+    /// `A((text))`
+    Circle,
+    /// `A(((text)))`
+    DoubleCircle,
+    /// `A>text]`
+    Asymmetric,
+    /// `A{text}`
+    Diamond,
+    /// `A{{text}}`
+    Hexagon,
+    /// `A[/text/]`
+    Parallelogram,
+    /// `A[\text\]`
+    ParallelogramAlt,
+    /// `A[/text\]`
+    Trapezoid,
+    /// `A[\text/]`
+    TrapezoidAlt,
+    /// Sequence diagram `actor`
+    Actor,
+    /// Class box with a member compartment
+    Class,
+    /// ER entity with an attribute table
+    Entity,
+    /// State diagram `[*]` as a source
+    Start,
+    /// State diagram `[*]` as a target
+    End,
+    /// State diagram `<<fork>>` / `<<join>>`
+    Bar,
+    /// Sequence diagram note
+    Note,
+}
+
+/// Diagram node (flowchart box, participant, class, state, entity)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub id: String,
+    pub label: String,
+    pub shape: NodeShape,
+    /// Class members or entity attributes
+    pub details: Vec<String>,
+}
+
+/// Stroke of an edge
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineStyle {
+    #[default]
+    Solid,
+    Dotted,
+    Thick,
+    /// `~~~`: affects layout only
+    Invisible,
+}
+
+/// Decoration at either end of an edge
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Marker {
+    #[default]
+    None,
+    /// Filled arrow head
+    Arrow,
+    /// Open arrow head (`-)` async messages)
+    OpenArrow,
+    /// `x`
+    Cross,
+    /// `o`
+    Circle,
+    /// Hollow triangle (inheritance / realization)
+    Triangle,
+    /// Filled diamond (composition)
+    Diamond,
+    /// Hollow diamond (aggregation)
+    OpenDiamond,
+    /// ER `||`
+    ExactlyOne,
+    /// ER `|o` / `o|`
+    ZeroOrOne,
+    /// ER `}|` / `|{`
+    OneOrMore,
+    /// ER `}o` / `o{`
+    ZeroOrMore,
+}
+
+/// Connection between two nodes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub label: Option<String>,
+    pub line: LineStyle,
+    /// Marker at the `from` end
+    pub start: Marker,
+    /// Marker at the `to` end
+    pub end: Marker,
+    /// Multiplicity near the `from` end (class diagrams)
+    pub start_label: Option<String>,
+    /// Multiplicity near the `to` end (class diagrams)
+    pub end_label: Option<String>,
+}
+
+impl Edge {
+    /// Plain arrow `from --> to`
+    pub fn arrow(from: &str, to: &str, label: Option<&str>) -> Self {
+        Self {
+            from: from.to_string(),
+            to: to.to_string(),
+            label: label
+                .map(str::trim)
+                .filter(|label| !label.is_empty())
+                .map(str::to_string),
+            line: LineStyle::Solid,
+            start: Marker::None,
+            end: Marker::Arrow,
+            start_label: None,
+            end_label: None,
+        }
+    }
+}
+
+/// Group of nodes drawn inside a titled box
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subgraph {
+    pub id: String,
+    pub label: String,
+    /// Ids of the nodes directly inside this subgraph
+    pub nodes: Vec<String>,
+    /// Index of the enclosing subgraph
+    pub parent: Option<usize>,
+}
+
+/// Where a sequence diagram note is attached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotePlacement {
+    LeftOf,
+    RightOf,
+    Over,
+}
+
+/// One row of a sequence diagram
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// Index into [`Diagram::edges`]
+    Message(usize),
+    Note {
+        placement: NotePlacement,
+        participants: Vec<String>,
+        text: String,
+    },
+    /// `loop`, `alt`, `opt`, `par`, `critical`, `break` or `rect`
+    FragmentStart {
+        kind: String,
+        text: String,
+    },
+    /// `else` / `and` / `option` inside a fragment
+    FragmentDivider {
+        text: String,
+    },
+    FragmentEnd,
+}
+
+impl Diagram {
+    pub fn new(kind: DiagramKind, source: &str) -> Self {
+        Self {
+            kind,
+            source: source.to_string(),
+            direction: Direction::default(),
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            subgraphs: Vec::new(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Label of the node with `id`, falling back to the id itself
+    pub fn label_of<'a>(&'a self, id: &'a str) -> &'a str {
+        self.node_by_id(id)
+            .map(|node| node.label.as_str())
+            .unwrap_or(id)
+    }
+
+    pub fn node_by_id(&self, id: &str) -> Option<&Node> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// Add a node, or return the existing one with this id
+    pub fn node(&mut self, id: &str) -> &mut Node {
+        let index = match self.nodes.iter().position(|node| node.id == id) {
+            Some(index) => index,
+            None => {
+                self.nodes.push(Node {
+                    id: id.to_string(),
+                    label: id.to_string(),
+                    shape: NodeShape::default(),
+                    details: Vec::new(),
+                });
+                self.nodes.len() - 1
+            }
+        };
+        &mut self.nodes[index]
+    }
+
+    /// Add an edge, creating missing endpoint nodes
+    pub fn edge(&mut self, edge: Edge) {
+        self.node(&edge.from);
+        self.node(&edge.to);
+        self.edges.push(edge);
+    }
+}
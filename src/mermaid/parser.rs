@@ -0,0 +1,1367 @@
+//! Mermaid source → [`Diagram`]
+//!
+//! A statement-per-line parser for the graph-like diagram types. Each
+//! diagram kind has its own small grammar; statements that are only about
+//! styling (`classDef`, `style`, `click`, ...) are accepted and ignored.
+//! Statements that fit no rule are reported as [`Diagnostic`]s.
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+use regex::Regex;
+
+use super::diagnostics::{Diagnostic, Severity};
+use super::model::{
+    Diagram, DiagramKind, Direction, Edge, LineStyle, Marker, NodeShape, NotePlacement, Step,
+    Subgraph,
+};
+
+/// A statement with its position in the source
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Statement<'a> {
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column of the first character, in characters
+    pub column: usize,
+    pub text: &'a str,
+}
+
+/// Parse a graph-like Mermaid diagram
+///
+/// Returns `None` when the header does not name a flowchart, sequence,
+/// class, state or ER diagram.
+pub fn parse(source: &str) -> Option<Diagram> {
+    parse_with_diagnostics(source).0
+}
+
+/// Mermaid diagram types that are recognized but not laid out natively
+const OTHER_TYPES: &[&str] = &[
+    "journey",
+    "gitGraph",
+    "mindmap",
+    "timeline",
+    "quadrantChart",
+    "requirementDiagram",
+    "C4Context",
+    "C4Container",
+    "C4Component",
+    "C4Dynamic",
+    "C4Deployment",
+    "xychart-beta",
+    "sankey-beta",
+    "block-beta",
+    "packet-beta",
+    "architecture-beta",
+    "kanban",
+    "radar-beta",
+    "zenuml",
+];
+
+/// Parse a diagram, reporting every statement that could not be understood
+///
+/// Gantt and pie charts yield no diagram and no diagnostics; the document
+/// model shows them as tables.
+pub fn parse_with_diagnostics(source: &str) -> (Option<Diagram>, Vec<Diagnostic>) {
+    let mut report = Vec::new();
+    let statements = statements(source);
+    let Some((header, body)) = statements.split_first() else {
+        report.push(Diagnostic {
+            severity: Severity::Error,
+            line: 1,
+            column: 1,
+            length: 1,
+            message: "empty diagram".to_string(),
+        });
+        return (None, report);
+    };
+    let mut words = header.text.split_whitespace();
+    let keyword = words.next().unwrap_or_default();
+    let keyword_range = 0..keyword.len();
+
+    let diagram = match keyword {
+        "graph" | "flowchart" => {
+            let mut diagram = Diagram::new(DiagramKind::Flowchart, source);
+            if let Some(word) = words.next() {
+                let word = word.trim_end_matches(';');
+                match Direction::from_keyword(word) {
+                    Some(direction) => diagram.direction = direction,
+                    None => {
+                        let start =
+                            keyword.len() + header.text[keyword.len()..].find(word).unwrap_or(0);
+                        report.push(Diagnostic::error(
+                            header,
+                            start..start + word.len(),
+                            format!("unknown direction `{word}`; expected TD, TB, BT, LR or RL"),
+                        ));
+                    }
+                }
+            }
+            flowchart(&mut diagram, &split_semicolons(body), &mut report);
+            diagram
+        }
+        "sequenceDiagram" => {
+            let mut diagram = Diagram::new(DiagramKind::Sequence, source);
+            sequence(&mut diagram, body, &mut report);
+            diagram
+        }
+        "classDiagram" | "classDiagram-v2" => {
+            let mut diagram = Diagram::new(DiagramKind::Class, source);
+            class(&mut diagram, body, &mut report);
+            diagram
+        }
+        "stateDiagram" | "stateDiagram-v2" => {
+            let mut diagram = Diagram::new(DiagramKind::State, source);
+            state(&mut diagram, body, &mut report);
+            diagram
+        }
+        "erDiagram" => {
+            let mut diagram = Diagram::new(DiagramKind::EntityRelationship, source);
+            entity_relationship(&mut diagram, body, &mut report);
+            diagram
+        }
+        "gantt" | "pie" => return (None, report),
+        _ if OTHER_TYPES.contains(&keyword) => {
+            report.push(Diagnostic::warning(
+                header,
+                keyword_range,
+                format!("`{keyword}` diagrams are not rendered; the source is shown instead"),
+            ));
+            return (None, report);
+        }
+        _ => {
+            report.push(Diagnostic::error(
+                header,
+                keyword_range,
+                format!("unknown diagram type `{keyword}`"),
+            ));
+            return (None, report);
+        }
+    };
+
+    (Some(diagram), report)
+}
+
+/// Non-empty statements, skipping `%%` comments and YAML front matter
+pub(crate) fn statements(source: &str) -> Vec<Statement<'_>> {
+    let mut statements = Vec::new();
+    let mut in_front_matter = false;
+
+    for (index, line) in source.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed == "---" && (in_front_matter || statements.is_empty()) {
+            in_front_matter = !in_front_matter;
+            continue;
+        }
+        if in_front_matter || trimmed.is_empty() || trimmed.starts_with("%%") {
+            continue;
+        }
+        statements.push(Statement {
+            line: index + 1,
+            column: line.chars().take_while(|c| c.is_whitespace()).count() + 1,
+            text: trimmed,
+        });
+    }
+
+    statements
+}
+
+/// Split flowchart statements on `;` outside quotes
+fn split_semicolons<'a>(statements: &[Statement<'a>]) -> Vec<Statement<'a>> {
+    let mut split = Vec::new();
+
+    for statement in statements {
+        let mut start = 0;
+        let mut quoted = false;
+        for (index, c) in statement.text.char_indices() {
+            match c {
+                '"' => quoted = !quoted,
+                ';' if !quoted => {
+                    push_part(&mut split, statement, start, index);
+                    start = index + 1;
+                }
+                _ => {}
+            }
+        }
+        push_part(&mut split, statement, start, statement.text.len());
+    }
+
+    split
+}
+
+fn push_part<'a>(
+    out: &mut Vec<Statement<'a>>,
+    statement: &Statement<'a>,
+    start: usize,
+    end: usize,
+) {
+    let part = &statement.text[start..end];
+    let text = part.trim();
+    if !text.is_empty() {
+        out.push(Statement {
+            line: statement.line,
+            column: statement.column
+                + statement.text[..start].chars().count()
+                + part.chars().take_while(|c| c.is_whitespace()).count(),
+            text,
+        });
+    }
+}
+
+/// First arrow-like run of `-=.~<>` characters at least two long
+fn arrow_token(text: &str) -> Option<Range<usize>> {
+    let arrow_re = Regex::new(r"[-=.~<>]*[-=~<>][-=.~<>]*").unwrap();
+    let token = arrow_re.find_iter(text).find(|m| m.len() >= 2);
+    token.map(|m| m.range())
+}
+
+/// Report an unrecognized statement, pointing at an arrow when it has one
+fn unrecognized(statement: &Statement, report: &mut Vec<Diagnostic>, arrow_hint: &str) {
+    let text = statement.text;
+    let diagnostic = match arrow_token(text) {
+        Some(range) => {
+            let token = &text[range.clone()];
+            Diagnostic::error(
+                statement,
+                range,
+                format!("invalid arrow `{token}`; {arrow_hint}"),
+            )
+        }
+        None => {
+            let word = text.split_whitespace().next().unwrap_or(text);
+            Diagnostic::error(
+                statement,
+                0..word.len(),
+                format!("unrecognized statement `{word}`"),
+            )
+        }
+    };
+    report.push(diagnostic);
+}
+
+/// Report blocks still open at the end of the diagram
+fn unclosed(open: &[(Statement, Range<usize>)], report: &mut Vec<Diagnostic>, closer: &str) {
+    for (statement, range) in open {
+        let opener = &statement.text[range.clone()];
+        let message = if opener == "{" {
+            "unclosed `{`".to_string()
+        } else {
+            format!("`{opener}` is never closed with `{closer}`")
+        };
+        report.push(Diagnostic::error(statement, range.clone(), message));
+    }
+}
+
+/// Turn `"quoted"`, `` `markdown` `` and `<br>` label syntax into plain text
+fn clean_label(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .unwrap_or(text);
+    let text = text
+        .strip_prefix('`')
+        .and_then(|t| t.strip_suffix('`'))
+        .unwrap_or(text);
+    let break_re = Regex::new(r"(?i)<br\s*/?>").unwrap();
+    break_re
+        .replace_all(text, "\n")
+        .replace("#quot;", "\"")
+        .trim()
+        .to_string()
+}
+
+// ---------------------------------------------------------------------------
+// Flowchart
+
+/// Statements that only affect styling or interaction
+const FLOWCHART_IGNORED: &[&str] = &["classDef", "class", "style", "linkStyle", "click"];
+
+fn flowchart(diagram: &mut Diagram, body: &[Statement], report: &mut Vec<Diagnostic>) {
+    let mut open: Vec<usize> = Vec::new();
+    let mut blocks: Vec<(Statement, Range<usize>)> = Vec::new();
+
+    for statement in body {
+        let text = statement.text;
+        let keyword = text.split_whitespace().next().unwrap_or("");
+
+        if keyword == "subgraph" {
+            blocks.push((*statement, 0..keyword.len()));
+            let (id, label) = subgraph_title(text["subgraph".len()..].trim());
+            diagram.subgraphs.push(Subgraph {
+                id,
+                label,
+                nodes: Vec::new(),
+                parent: open.last().copied(),
+            });
+            open.push(diagram.subgraphs.len() - 1);
+            continue;
+        }
+        if text == "end" {
+            if blocks.pop().is_none() {
+                report.push(end_without_block(statement));
+            }
+            open.pop();
+            continue;
+        }
+        if keyword == "direction" || FLOWCHART_IGNORED.contains(&keyword) {
+            continue;
+        }
+
+        for id in chain(diagram, statement, report) {
+            if let Some(&subgraph) = open.last() {
+                // A node belongs to the innermost subgraph it first appears in
+                let claimed = diagram.subgraphs.iter().any(|s| s.nodes.contains(&id));
+                if !claimed {
+                    diagram.subgraphs[subgraph].nodes.push(id);
+                }
+            }
+        }
+    }
+
+    unclosed(&blocks, report, "end");
+
+    // Subgraph ids used as nodes are clusters, not boxes
+    let subgraph_ids: Vec<String> = diagram.subgraphs.iter().map(|s| s.id.clone()).collect();
+    let referenced = |id: &String| diagram.edges.iter().any(|e| &e.from == id || &e.to == id);
+    let unused: Vec<String> = subgraph_ids
+        .into_iter()
+        .filter(|id| !referenced(id))
+        .collect();
+    diagram.nodes.retain(|node| !unused.contains(&node.id));
+    for subgraph in &mut diagram.subgraphs {
+        subgraph.nodes.retain(|id| !unused.contains(id));
+    }
+}
+
+/// `id [Title]`, `id["Title"]`, `"Title"` or `Title with spaces`
+fn subgraph_title(rest: &str) -> (String, String) {
+    if let Some(open) = rest.find('[') {
+        if rest.ends_with(']') {
+            let id = rest[..open].trim().to_string();
+            let label = clean_label(&rest[open + 1..rest.len() - 1]);
+            return (id, label);
+        }
+    }
+    let label = clean_label(rest);
+    let id = if rest.contains(char::is_whitespace) || rest.starts_with('"') {
+        label.clone()
+    } else {
+        rest.to_string()
+    };
+    (id, label)
+}
+
+/// Parse `a & b --> c -->|x| d`; returns the ids of every node mentioned
+///
+/// Parsing stops at the first problem, which is reported; edges before it
+/// are kept.
+fn chain(
+    diagram: &mut Diagram,
+    statement: &Statement,
+    report: &mut Vec<Diagnostic>,
+) -> Vec<String> {
+    let text = statement.text;
+    let mut mentioned = Vec::new();
+    let mut rest = text;
+    let mut previous: Vec<String> = Vec::new();
+    let mut link: Option<(Link, Range<usize>)> = None;
+    let offset = |rest: &str| text.len() - rest.len();
+
+    loop {
+        // One group: node (& node)*
+        let mut group = Vec::new();
+        loop {
+            let Some((id, consumed)) = node(diagram, rest) else {
+                let at = offset(rest);
+                let diagnostic = match (rest.chars().next(), &link) {
+                    (None, Some((_, range))) => Diagnostic::error(
+                        statement,
+                        range.clone(),
+                        "link has no target node".to_string(),
+                    ),
+                    (Some('|'), _) => Diagnostic::error(
+                        statement,
+                        at..at + 1,
+                        "unclosed `|` in link label".to_string(),
+                    ),
+                    (Some(c), _) if "])}".contains(c) => unmatched(statement, at, c),
+                    (Some(c), _) if "[({".contains(c) => Diagnostic::error(
+                        statement,
+                        at..at + 1,
+                        format!("missing node id before `{c}`"),
+                    ),
+                    _ => Diagnostic::error(
+                        statement,
+                        at..at + rest.len(),
+                        "expected a node id".to_string(),
+                    ),
+                };
+                report.push(diagnostic);
+                return mentioned;
+            };
+            let after = &rest[consumed..];
+            if let Some(c) = after.chars().next().filter(|c| "[({".contains(*c)) {
+                let at = offset(after);
+                report.push(Diagnostic::error(
+                    statement,
+                    at..at + 1,
+                    format!("unclosed `{c}`"),
+                ));
+                diagram.node(&id);
+                mentioned.push(id);
+                return mentioned;
+            }
+            rest = after.trim_start();
+            mentioned.push(id.clone());
+            group.push(id);
+            match rest.strip_prefix('&') {
+                Some(after) => rest = after.trim_start(),
+                None => break,
+            }
+        }
+
+        if let Some((link, _)) = link.take() {
+            for from in &previous {
+                for to in &group {
+                    diagram.edge(link.edge(from, to));
+                }
+            }
+        }
+
+        if rest.is_empty() {
+            return mentioned;
+        }
+        let Some((next, consumed)) = parse_link(rest) else {
+            let at = offset(rest);
+            let diagnostic = match rest.chars().next() {
+                Some(c) if "])}".contains(c) => unmatched(statement, at, c),
+                _ => match arrow_token(rest).filter(|range| range.start == 0) {
+                    Some(range) => Diagnostic::error(
+                        statement,
+                        at..at + range.end,
+                        format!(
+                            "invalid link `{}`; expected a link such as `-->`",
+                            &rest[range]
+                        ),
+                    ),
+                    None => {
+                        let word = rest.split_whitespace().next().unwrap_or(rest);
+                        Diagnostic::error(
+                            statement,
+                            at..at + word.len(),
+                            format!("unexpected `{word}`; expected a link such as `-->`"),
+                        )
+                    }
+                },
+            };
+            report.push(diagnostic);
+            return mentioned;
+        };
+        let at = offset(rest);
+        link = Some((next, at..at + consumed));
+        rest = rest[consumed..].trim_start();
+        previous = group;
+    }
+}
+
+fn unmatched(statement: &Statement, at: usize, bracket: char) -> Diagnostic {
+    Diagnostic::error(statement, at..at + 1, format!("unmatched `{bracket}`"))
+}
+
+fn end_without_block(statement: &Statement) -> Diagnostic {
+    Diagnostic::error(statement, 0..3, "`end` without an open block".to_string())
+}
+
+/// Shape delimiters, longest opener first
+const SHAPES: &[(&str, &str, NodeShape)] = &[
+    ("(((", ")))", NodeShape::DoubleCircle),
+    ("([", "])", NodeShape::Stadium),
+    ("((", "))", NodeShape::Circle),
+    ("[[", "]]", NodeShape::Subroutine),
+    ("[(", ")]", NodeShape::Cylinder),
+    ("{{", "}}", NodeShape::Hexagon),
+    ("[/", "/]", NodeShape::Parallelogram),
+    ("[/", "\\]", NodeShape::Trapezoid),
+    ("[\\", "\\]", NodeShape::ParallelogramAlt),
+    ("[\\", "/]", NodeShape::TrapezoidAlt),
+    ("[", "]", NodeShape::Rectangle),
+    ("(", ")", NodeShape::Rounded),
+    ("{", "}", NodeShape::Diamond),
+    (">", "]", NodeShape::Asymmetric),
+];
+
+/// Parse `id`, `id[label]`, `id(label):::class`, ...; returns the id and
+/// the number of bytes consumed
+fn node(diagram: &mut Diagram, text: &str) -> Option<(String, usize)> {
+    let id_len = text
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(text.len());
+    if id_len == 0 {
+        return None;
+    }
+    let id = &text[..id_len];
+    let mut consumed = id_len;
+
+    if let Some((label, shape, len)) = shape(&text[id_len..]) {
+        let node = diagram.node(id);
+        node.label = label;
+        node.shape = shape;
+        consumed += len;
+    } else {
+        diagram.node(id);
+    }
+
+    // `:::className` styling suffix
+    if let Some(after) = text[consumed..].strip_prefix(":::") {
+        consumed += 3 + after
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(after.len());
+    }
+
+    Some((id.to_string(), consumed))
+}
+
+fn shape(text: &str) -> Option<(String, NodeShape, usize)> {
+    for (open, close, shape) in SHAPES {
+        let Some(inner) = text.strip_prefix(open) else {
+            continue;
+        };
+        // Quoted labels may contain the closing delimiter
+        let search_from = match inner.trim_start().strip_prefix('"') {
+            Some(quoted) => {
+                let leading = inner.len() - quoted.len();
+                leading + quoted.find('"').map(|i| i + 1).unwrap_or(0)
+            }
+            None => 0,
+        };
+        let Some(end) = inner[search_from..].find(close).map(|i| i + search_from) else {
+            continue;
+        };
+        // `[/a/]` and `[/a\]` share an opener; take the nearer closer
+        let other_closer_first = SHAPES.iter().any(|(o, c, _)| {
+            o == open
+                && c != close
+                && inner[search_from..]
+                    .find(c)
+                    .is_some_and(|i| i + search_from < end)
+        });
+        if other_closer_first {
+            continue;
+        }
+        let label = clean_label(&inner[..end]);
+        return Some((label, *shape, open.len() + end + close.len()));
+    }
+    None
+}
+
+/// An edge between two groups, before the endpoints are known
+#[derive(Debug, Clone)]
+struct Link {
+    line: LineStyle,
+    start: Marker,
+    end: Marker,
+    label: Option<String>,
+}
+
+impl Link {
+    fn edge(&self, from: &str, to: &str) -> Edge {
+        Edge {
+            line: self.line,
+            start: self.start,
+            end: self.end,
+            ..Edge::arrow(from, to, self.label.as_deref())
+        }
+    }
+}
+
+/// Parse a link such as `-->`, `-.->|label|`, `== text ==>` or `<-->`
+fn parse_link(text: &str) -> Option<(Link, usize)> {
+    let with_text =
+        Regex::new(r"^([<xo]?)(--|==|-\.)\s+(.+?)\s+(-{2,}|={2,}|\.+-)([>xo]?)(?:\s|$)").unwrap();
+    let plain = Regex::new(r"^([<xo]?)(-{2,}|={2,}|-\.+-|~{3,})([>xo]?)").unwrap();
+    let pipe_label = Regex::new(r"^\s*\|([^|]*)\|").unwrap();
+
+    let marker = |m: &str| match m {
+        "<" | ">" => Marker::Arrow,
+        "x" => Marker::Cross,
+        "o" => Marker::Circle,
+        _ => Marker::None,
+    };
+    let line = |body: &str| {
+        if body.starts_with('=') {
+            LineStyle::Thick
+        } else if body.starts_with('~') {
+            LineStyle::Invisible
+        } else if body.contains('.') {
+            LineStyle::Dotted
+        } else {
+            LineStyle::Solid
+        }
+    };
+
+    if let Some(cap) = with_text.captures(text) {
+        let consumed = cap[0].trim_end().len();
+        let link = Link {
+            line: line(&format!("{}{}", &cap[2], &cap[4])),
+            start: marker(&cap[1]),
+            end: marker(&cap[5]),
+            label: Some(clean_label(&cap[3])),
+        };
+        return Some((link, consumed));
+    }
+
+    let cap = plain.captures(text)?;
+    let mut consumed = cap[0].len();
+    let mut end = &cap[3];
+    // `--oNode` is a link followed by a node named `oNode`
+    if matches!(end, "x" | "o")
+        && text[consumed..]
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_')
+    {
+        consumed -= 1;
+        end = "";
+    }
+
+    let mut link = Link {
+        line: line(&cap[2]),
+        start: marker(&cap[1]),
+        end: marker(end),
+        label: None,
+    };
+    if let Some(label) = pipe_label.captures(&text[consumed..]) {
+        link.label = Some(clean_label(&label[1])).filter(|l| !l.is_empty());
+        consumed += label[0].len();
+    }
+    Some((link, consumed))
+}
+
+// ---------------------------------------------------------------------------
+// Sequence diagram
+
+/// Message arrows, as listed in diagnostics
+const SEQUENCE_ARROWS: &str = "`->>`, `-->>`, `->`, `-->`, `-x`, `--x`, `-)` or `--)`";
+
+/// Statements that only affect numbering, activation bars or metadata
+const SEQUENCE_IGNORED: &[&str] = &[
+    "autonumber",
+    "activate",
+    "deactivate",
+    "destroy",
+    "title",
+    "accTitle",
+    "accDescr",
+    "link",
+    "links",
+    "properties",
+    "details",
+];
+
+/// Sequence diagrams allow undeclared participants, but once any are
+/// declared every other name gets a warning, as it is often a typo. Mixing
+/// both is valid Mermaid, so it is not an error.
+fn sequence(diagram: &mut Diagram, body: &[Statement], report: &mut Vec<Diagnostic>) {
+    let participant_re =
+        Regex::new(r"^(?:create\s+)?(participant|actor)\s+(.+?)(?:\s+as\s+(.+))?$").unwrap();
+    let note_re =
+        Regex::new(r"(?i)^note\s+(left of|right of|over)\s+([^:]+?)\s*:\s*(.*)$").unwrap();
+    let message_re = Regex::new(
+        r"^([^\s:<>+-][^:<>]*?)\s*(<<-->>|<<->>|-->>|->>|--x|-x|--\)|-\)|-->|->)\s*([+-]?)\s*([^:]+?)\s*:(.*)$",
+    )
+    .unwrap();
+
+    let mut declared = HashSet::new();
+    // Participant references: statement and byte range of the name
+    let mut references: Vec<(Statement, Range<usize>)> = Vec::new();
+    let mut blocks: Vec<(Statement, Range<usize>)> = Vec::new();
+
+    for statement in body {
+        let text = statement.text;
+        let keyword = text.split_whitespace().next().unwrap_or("");
+
+        if let Some(cap) = participant_re.captures(text) {
+            let id = cap[2].trim();
+            declared.insert(id.to_string());
+            let node = diagram.node(id);
+            if let Some(alias) = cap.get(3) {
+                node.label = clean_label(alias.as_str());
+            }
+            if &cap[1] == "actor" {
+                node.shape = NodeShape::Actor;
+            }
+        } else if let Some(cap) = note_re.captures(text) {
+            let placement = match cap[1].to_lowercase().as_str() {
+                "left of" => NotePlacement::LeftOf,
+                "right of" => NotePlacement::RightOf,
+                _ => NotePlacement::Over,
+            };
+            let names = cap.get(2).unwrap();
+            let mut participants = Vec::new();
+            let mut at = names.start();
+            for part in names.as_str().split(',') {
+                let name = part.trim();
+                let start = at + part.find(name).unwrap_or(0);
+                references.push((*statement, start..start + name.len()));
+                participants.push(name.to_string());
+                at += part.len() + 1;
+            }
+            for participant in &participants {
+                diagram.node(participant);
+            }
+            diagram.steps.push(Step::Note {
+                placement,
+                participants,
+                text: clean_label(&cap[3]),
+            });
+        } else if let Some(cap) = message_re.captures(text) {
+            let (line, start, end) = match &cap[2] {
+                "->>" => (LineStyle::Solid, Marker::None, Marker::Arrow),
+                "-->>" => (LineStyle::Dotted, Marker::None, Marker::Arrow),
+                "->" => (LineStyle::Solid, Marker::None, Marker::None),
+                "-->" => (LineStyle::Dotted, Marker::None, Marker::None),
+                "-x" => (LineStyle::Solid, Marker::None, Marker::Cross),
+                "--x" => (LineStyle::Dotted, Marker::None, Marker::Cross),
+                "-)" => (LineStyle::Solid, Marker::None, Marker::OpenArrow),
+                "--)" => (LineStyle::Dotted, Marker::None, Marker::OpenArrow),
+                "<<->>" => (LineStyle::Solid, Marker::Arrow, Marker::Arrow),
+                _ => (LineStyle::Dotted, Marker::Arrow, Marker::Arrow),
+            };
+            for group in [1, 4] {
+                let name = cap.get(group).unwrap();
+                let trimmed = name.as_str().trim();
+                let start = name.start() + name.as_str().find(trimmed).unwrap_or(0);
+                references.push((*statement, start..start + trimmed.len()));
+            }
+            diagram.edge(Edge {
+                line,
+                start,
+                end,
+                ..Edge::arrow(cap[1].trim(), cap[4].trim(), Some(&clean_label(&cap[5])))
+            });
+            diagram.steps.push(Step::Message(diagram.edges.len() - 1));
+        } else if matches!(
+            keyword,
+            "loop" | "alt" | "opt" | "par" | "critical" | "break" | "rect"
+        ) {
+            blocks.push((*statement, 0..keyword.len()));
+            diagram.steps.push(Step::FragmentStart {
+                kind: keyword.to_string(),
+                text: text[keyword.len()..].trim().to_string(),
+            });
+        } else if keyword == "box" {
+            // Participant groups; drawn without a frame
+            blocks.push((*statement, 0..keyword.len()));
+        } else if matches!(keyword, "else" | "and" | "option") {
+            if blocks.is_empty() {
+                report.push(Diagnostic::error(
+                    statement,
+                    0..keyword.len(),
+                    format!("`{keyword}` outside of a block"),
+                ));
+            }
+            diagram.steps.push(Step::FragmentDivider {
+                text: text[keyword.len()..].trim().to_string(),
+            });
+        } else if text == "end" {
+            match blocks.pop() {
+                Some((opener, _)) if opener.text.starts_with("box") => {}
+                Some(_) => diagram.steps.push(Step::FragmentEnd),
+                None => report.push(end_without_block(statement)),
+            }
+        } else if SEQUENCE_IGNORED.contains(&keyword) {
+            // Activation names still have to be participants
+            if matches!(keyword, "activate" | "deactivate" | "destroy") {
+                let name = text[keyword.len()..].trim();
+                let start = text.len() - text[keyword.len()..].trim_start().len();
+                if !name.is_empty() {
+                    references.push((*statement, start..start + name.len()));
+                }
+            }
+        } else if message_re.is_match(&format!("{text}:")) {
+            let end = text.len();
+            report.push(Diagnostic::error(
+                statement,
+                end..end,
+                "message is missing `: text`".to_string(),
+            ));
+        } else {
+            let hint = format!("expected {SEQUENCE_ARROWS}");
+            unrecognized(statement, report, &hint);
+        }
+    }
+
+    unclosed(&blocks, report, "end");
+    if !declared.is_empty() {
+        for (statement, range) in references {
+            let name = &statement.text[range.clone()];
+            if !declared.contains(name) {
+                report.push(Diagnostic::warning(
+                    &statement,
+                    range,
+                    format!("undeclared participant `{name}`"),
+                ));
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Class diagram
+
+/// Class statements that only affect styling, interaction or notes
+const CLASS_IGNORED: &[&str] = &[
+    "classDef", "cssClass", "style", "click", "callback", "link", "note", "title", "accTitle",
+    "accDescr",
+];
+
+fn class(diagram: &mut Diagram, body: &[Statement], report: &mut Vec<Diagnostic>) {
+    let class_re = Regex::new(
+        r#"^class\s+([\w-]+)(?:~([^~]+)~)?(?:\["([^"]*)"\])?(?::::[\w-]+)?\s*(\{)?\s*(\})?$"#,
+    )
+    .unwrap();
+    let annotation_re = Regex::new(r"^<<([^>]+)>>\s*([\w-]*)$").unwrap();
+    let member_re = Regex::new(r"^([\w-]+)\s*:\s*(.+)$").unwrap();
+    let relation_re = Regex::new(
+        r#"^([\w-]+)\s*(?:"([^"]*)"\s*)?(<\||\*|o|<)?(--|\.\.)(\|>|\*|o|>)?\s*(?:"([^"]*)"\s*)?([\w-]+)\s*(?::\s*(.+))?$"#,
+    )
+    .unwrap();
+
+    let mut current: Option<String> = None;
+    // Open `{` of class bodies and namespaces
+    let mut blocks: Vec<(Statement, Range<usize>)> = Vec::new();
+    for statement in body {
+        let text = statement.text;
+        let keyword = text.split_whitespace().next().unwrap_or("");
+
+        if let Some(class_name) = current.clone() {
+            if text == "}" {
+                current = None;
+                blocks.pop();
+            } else if let Some(cap) = annotation_re.captures(text) {
+                annotate(diagram, &class_name, &cap[1]);
+            } else {
+                diagram.node(&class_name).details.push(text.to_string());
+            }
+            continue;
+        }
+
+        if let Some(cap) = class_re.captures(text) {
+            let node = diagram.node(&cap[1]);
+            node.shape = NodeShape::Class;
+            if let Some(generic) = cap.get(2) {
+                node.label = format!("{}<{}>", &cap[1], generic.as_str());
+            }
+            if let Some(label) = cap.get(3) {
+                node.label = label.as_str().to_string();
+            }
+            if let (Some(brace), None) = (cap.get(4), cap.get(5)) {
+                current = Some(cap[1].to_string());
+                blocks.push((*statement, brace.range()));
+            }
+        } else if let Some(cap) = annotation_re.captures(text) {
+            if !cap[2].is_empty() {
+                annotate(diagram, &cap[2], &cap[1]);
+            }
+        } else if let Some(cap) = relation_re.captures(text) {
+            let start = match cap.get(3).map(|m| m.as_str()) {
+                Some("<|") => Marker::Triangle,
+                Some("*") => Marker::Diamond,
+                Some("o") => Marker::OpenDiamond,
+                Some("<") => Marker::Arrow,
+                _ => Marker::None,
+            };
+            let end = match cap.get(5).map(|m| m.as_str()) {
+                Some("|>") => Marker::Triangle,
+                Some("*") => Marker::Diamond,
+                Some("o") => Marker::OpenDiamond,
+                Some(">") => Marker::Arrow,
+                _ => Marker::None,
+            };
+            let line = if &cap[4] == ".." {
+                LineStyle::Dotted
+            } else {
+                LineStyle::Solid
+            };
+            diagram.edge(Edge {
+                line,
+                start,
+                end,
+                start_label: cap.get(2).map(|m| m.as_str().to_string()),
+                end_label: cap.get(6).map(|m| m.as_str().to_string()),
+                ..Edge::arrow(&cap[1], &cap[7], cap.get(8).map(|m| m.as_str()))
+            });
+            for id in [&cap[1], &cap[7]] {
+                diagram.node(id).shape = NodeShape::Class;
+            }
+        } else if let Some(cap) = member_re.captures(text) {
+            let node = diagram.node(&cap[1]);
+            node.shape = NodeShape::Class;
+            node.details.push(cap[2].trim().to_string());
+        } else if let Some(direction) = text
+            .strip_prefix("direction")
+            .and_then(|d| Direction::from_keyword(d.trim()))
+        {
+            diagram.direction = direction;
+        } else if keyword == "namespace" && text.ends_with('{') {
+            blocks.push((*statement, text.len() - 1..text.len()));
+        } else if text == "}" {
+            if blocks.pop().is_none() {
+                report.push(unmatched(statement, 0, '}'));
+            }
+        } else if !CLASS_IGNORED.contains(&keyword) {
+            unrecognized(
+                statement,
+                report,
+                "expected a relation such as `<|--`, `*--`, `o--`, `-->` or `..>`",
+            );
+        }
+    }
+
+    unclosed(&blocks, report, "}");
+}
+
+/// Show `<<interface>>` style annotations above the class name
+fn annotate(diagram: &mut Diagram, class_name: &str, annotation: &str) {
+    let node = diagram.node(class_name);
+    node.shape = NodeShape::Class;
+    node.label = format!("«{annotation}»\n{}", node.label);
+}
+
+// ---------------------------------------------------------------------------
+// State diagram
+
+/// State statements that only affect styling or metadata
+const STATE_IGNORED: &[&str] = &[
+    "classDef", "class", "style", "hide", "scale", "title", "accTitle", "accDescr",
+];
+
+fn state(diagram: &mut Diagram, body: &[Statement], report: &mut Vec<Diagnostic>) {
+    let transition_re =
+        Regex::new(r"^(\[\*\]|[\w.-]+)\s*-->\s*(\[\*\]|[\w.-]+)\s*(?::\s*(.+))?$").unwrap();
+    let alias_re = Regex::new(r#"^state\s+"([^"]+)"\s+as\s+([\w-]+)\s*(\{)?$"#).unwrap();
+    let composite_re = Regex::new(r"^state\s+([\w-]+)\s*\{$").unwrap();
+    let special_re = Regex::new(r"^state\s+([\w-]+)\s+<<(choice|fork|join)>>$").unwrap();
+    let description_re = Regex::new(r"^([\w-]+)\s*:\s*(.+)$").unwrap();
+
+    let mut open: Vec<usize> = Vec::new();
+    let mut blocks: Vec<(Statement, Range<usize>)> = Vec::new();
+    let mut note: Option<Statement> = None;
+
+    for statement in body {
+        let text = statement.text;
+        let keyword = text.split_whitespace().next().unwrap_or("");
+
+        if note.is_some() {
+            if text == "end note" {
+                note = None;
+            }
+            continue;
+        }
+        if keyword == "note" {
+            // Multi-line notes run until `end note`
+            if !text.contains(':') {
+                note = Some(*statement);
+            }
+            continue;
+        }
+
+        let scope = open.last().map(|&i| diagram.subgraphs[i].id.clone());
+        let member = |diagram: &mut Diagram, id: &str| {
+            if let Some(&subgraph) = open.last() {
+                let claimed = diagram
+                    .subgraphs
+                    .iter()
+                    .any(|s| s.nodes.iter().any(|n| n == id));
+                if !claimed && !diagram.subgraphs.iter().any(|s| s.id == id) {
+                    diagram.subgraphs[subgraph].nodes.push(id.to_string());
+                }
+            }
+        };
+
+        if let Some(cap) = transition_re.captures(text) {
+            let from = state_endpoint(diagram, &cap[1], scope.as_deref(), true);
+            let to = state_endpoint(diagram, &cap[2], scope.as_deref(), false);
+            member(diagram, &from);
+            member(diagram, &to);
+            diagram.edge(Edge::arrow(&from, &to, cap.get(3).map(|m| m.as_str())));
+        } else if let Some(cap) = alias_re.captures(text) {
+            diagram.node(&cap[2]).label = cap[1].to_string();
+            if let Some(brace) = cap.get(3) {
+                blocks.push((*statement, brace.range()));
+                open_composite(diagram, &mut open, &cap[2], &cap[1]);
+            } else {
+                member(diagram, &cap[2]);
+            }
+        } else if let Some(cap) = composite_re.captures(text) {
+            blocks.push((*statement, text.len() - 1..text.len()));
+            open_composite(diagram, &mut open, &cap[1], &cap[1]);
+        } else if let Some(cap) = special_re.captures(text) {
+            let node = diagram.node(&cap[1]);
+            node.label = String::new();
+            node.shape = match &cap[2] {
+                "choice" => NodeShape::Diamond,
+                _ => NodeShape::Bar,
+            };
+            member(diagram, &cap[1]);
+        } else if text == "}" {
+            if blocks.pop().is_none() {
+                report.push(unmatched(statement, 0, '}'));
+            }
+            open.pop();
+        } else if let Some(direction) = text
+            .strip_prefix("direction")
+            .and_then(|d| Direction::from_keyword(d.trim()))
+        {
+            if open.is_empty() {
+                diagram.direction = direction;
+            }
+        } else if let Some(cap) = description_re.captures(text) {
+            diagram.node(&cap[1]).label = cap[2].trim().to_string();
+            member(diagram, &cap[1]);
+        } else if text != "--" && !STATE_IGNORED.contains(&keyword) {
+            // A bare state name
+            let id = text.trim_start_matches("state ").trim();
+            if !id.is_empty()
+                && id
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+            {
+                diagram.node(id);
+                member(diagram, id);
+            } else {
+                unrecognized(statement, report, "expected `-->`");
+            }
+        }
+    }
+
+    unclosed(&blocks, report, "}");
+    if let Some(statement) = note {
+        report.push(Diagnostic::error(
+            &statement,
+            0..4,
+            "`note` is never closed with `end note`".to_string(),
+        ));
+    }
+
+    // Composite states are drawn as clusters rather than boxes
+    let composites: Vec<String> = diagram.subgraphs.iter().map(|s| s.id.clone()).collect();
+    let referenced = |id: &String| diagram.edges.iter().any(|e| &e.from == id || &e.to == id);
+    let unused: Vec<String> = composites
+        .into_iter()
+        .filter(|id| !referenced(id))
+        .collect();
+    diagram.nodes.retain(|node| !unused.contains(&node.id));
+}
+
+fn open_composite(diagram: &mut Diagram, open: &mut Vec<usize>, id: &str, label: &str) {
+    diagram.subgraphs.push(Subgraph {
+        id: id.to_string(),
+        label: label.to_string(),
+        nodes: Vec::new(),
+        parent: open.last().copied(),
+    });
+    open.push(diagram.subgraphs.len() - 1);
+}
+
+/// `[*]` is a start node as a source and an end node as a target, scoped
+/// to the enclosing composite state
+fn state_endpoint(diagram: &mut Diagram, id: &str, scope: Option<&str>, source: bool) -> String {
+    if id != "[*]" {
+        diagram.node(id);
+        return id.to_string();
+    }
+    let (suffix, shape) = if source {
+        ("start", NodeShape::Start)
+    } else {
+        ("end", NodeShape::End)
+    };
+    let id = match scope {
+        Some(scope) => format!("{scope}/[*]{suffix}"),
+        None => format!("[*]{suffix}"),
+    };
+    let node = diagram.node(&id);
+    node.label = String::new();
+    node.shape = shape;
+    id
+}
+
+// ---------------------------------------------------------------------------
+// Entity-relationship diagram
+
+/// ER statements that only affect styling or metadata
+const ER_IGNORED: &[&str] = &[
+    "classDef", "class", "style", "title", "accTitle", "accDescr",
+];
+
+fn entity_relationship(diagram: &mut Diagram, body: &[Statement], report: &mut Vec<Diagnostic>) {
+    let entity_re = Regex::new(r#"^([\w-]+)(?:\["?([^"\]]*)"?\])?\s*\{$"#).unwrap();
+    let relation_re = Regex::new(
+        r#"^([\w-]+)\s*(\|\||\|o|o\||\}\||\}o)(--|\.\.)(\|\||o\||\|o|\|\{|o\{)\s*([\w-]+)\s*:\s*(.+)$"#,
+    )
+    .unwrap();
+
+    let cardinality = |symbol: &str| match symbol {
+        "||" => Marker::ExactlyOne,
+        "|o" | "o|" => Marker::ZeroOrOne,
+        "}|" | "|{" => Marker::OneOrMore,
+        _ => Marker::ZeroOrMore,
+    };
+
+    let mut current: Option<(String, Statement)> = None;
+    for statement in body {
+        let text = statement.text;
+        let keyword = text.split_whitespace().next().unwrap_or("");
+
+        if let Some((entity, _)) = current.clone() {
+            if text == "}" {
+                current = None;
+            } else {
+                diagram.node(&entity).details.push(text.to_string());
+            }
+            continue;
+        }
+
+        if let Some(cap) = entity_re.captures(text) {
+            let node = diagram.node(&cap[1]);
+            node.shape = NodeShape::Entity;
+            if let Some(label) = cap.get(2) {
+                node.label = label.as_str().to_string();
+            }
+            current = Some((cap[1].to_string(), *statement));
+        } else if let Some(cap) = relation_re.captures(text) {
+            diagram.edge(Edge {
+                line: if &cap[3] == ".." {
+                    LineStyle::Dotted
+                } else {
+                    LineStyle::Solid
+                },
+                start: cardinality(&cap[2]),
+                end: cardinality(&cap[4]),
+                ..Edge::arrow(&cap[1], &cap[5], Some(&clean_label(&cap[6])))
+            });
+            for id in [&cap[1], &cap[5]] {
+                diagram.node(id).shape = NodeShape::Entity;
+            }
+        } else if let Some(direction) = text
+            .strip_prefix("direction")
+            .and_then(|d| Direction::from_keyword(d.trim()))
+        {
+            diagram.direction = direction;
+        } else if text == "}" {
+            report.push(unmatched(statement, 0, '}'));
+        } else if relation_re.is_match(&format!("{text} : label")) {
+            let end = text.len();
+            report.push(Diagnostic::error(
+                statement,
+                end..end,
+                "relationship is missing `: label`".to_string(),
+            ));
+        } else if !ER_IGNORED.contains(&keyword) {
+            unrecognized(statement, report, "expected cardinalities such as `||--o{`");
+        }
+    }
+
+    if let Some((_, statement)) = current {
+        let end = statement.text.len();
+        report.push(Diagnostic::error(
+            &statement,
+            end - 1..end,
+            "unclosed `{`".to_string(),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge_ends(diagram: &Diagram) -> Vec<(&str, &str)> {
+        diagram
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_flowchart_shapes() {
+        let d = parse(
+            "flowchart LR\n  a[Rect] --> b(Round) --> c([Stadium]) --> d[[Sub]]\n  e[(Db)] --> f((Circle)) --> g{Choice} --> h{{Hex}}\n  i[/In/] --> j[\\Alt\\] --> k[/Trap\\] --> l>Flag]\n  m(((Stop)))\n",
+        )
+        .unwrap();
+        assert_eq!(d.direction, Direction::LeftRight);
+        let shapes: Vec<NodeShape> = d.nodes.iter().map(|n| n.shape).collect();
+        assert_eq!(
+            shapes,
+            vec![
+                NodeShape::Rectangle,
+                NodeShape::Rounded,
+                NodeShape::Stadium,
+                NodeShape::Subroutine,
+                NodeShape::Cylinder,
+                NodeShape::Circle,
+                NodeShape::Diamond,
+                NodeShape::Hexagon,
+                NodeShape::Parallelogram,
+                NodeShape::ParallelogramAlt,
+                NodeShape::Trapezoid,
+                NodeShape::Asymmetric,
+                NodeShape::DoubleCircle,
+            ]
+        );
+        assert_eq!(d.label_of("g"), "Choice");
+    }
+
+    #[test]
+    fn test_flowchart_links_and_labels() {
+        let d = parse(
+            "graph TD\n  A -->|yes| B\n  A -- no --> C\n  B -.-> D\n  C ==> D\n  D --- E\n  E --o F\n  F <--> G\n  A -. maybe .-> G\n",
+        )
+        .unwrap();
+        assert_eq!(
+            edge_ends(&d),
+            vec![
+                ("A", "B"),
+                ("A", "C"),
+                ("B", "D"),
+                ("C", "D"),
+                ("D", "E"),
+                ("E", "F"),
+                ("F", "G"),
+                ("A", "G")
+            ]
+        );
+        let labels: Vec<Option<&str>> = d.edges.iter().map(|e| e.label.as_deref()).collect();
+        assert_eq!(labels[0], Some("yes"));
+        assert_eq!(labels[1], Some("no"));
+        assert_eq!(labels[7], Some("maybe"));
+        assert_eq!(d.edges[2].line, LineStyle::Dotted);
+        assert_eq!(d.edges[3].line, LineStyle::Thick);
+        assert_eq!(d.edges[4].end, Marker::None);
+        assert_eq!(d.edges[5].end, Marker::Circle);
+        assert_eq!(
+            (d.edges[6].start, d.edges[6].end),
+            (Marker::Arrow, Marker::Arrow)
+        );
+    }
+
+    #[test]
+    fn test_flowchart_groups_and_subgraphs() {
+        let d = parse(
+            "graph TD\n  subgraph one [First]\n    a1 --> a2\n    subgraph inner\n      b1\n    end\n  end\n  a1 & a2 --> c; c --> d\n",
+        )
+        .unwrap();
+        assert_eq!(d.subgraphs.len(), 2);
+        assert_eq!(d.subgraphs[0].label, "First");
+        assert_eq!(d.subgraphs[0].nodes, vec!["a1", "a2"]);
+        assert_eq!(d.subgraphs[1].nodes, vec!["b1"]);
+        assert_eq!(d.subgraphs[1].parent, Some(0));
+        assert_eq!(
+            edge_ends(&d),
+            vec![("a1", "a2"), ("a1", "c"), ("a2", "c"), ("c", "d")]
+        );
+    }
+
+    #[test]
+    fn test_quoted_labels_and_breaks() {
+        let d = parse("graph TD\n  A[\"Say [hi]\"] --> B[one<br>two]\n").unwrap();
+        assert_eq!(d.label_of("A"), "Say [hi]");
+        assert_eq!(d.label_of("B"), "one\ntwo");
+    }
+
+    #[test]
+    fn test_sequence_steps() {
+        let d = parse(
+            "sequenceDiagram\n  actor U as User\n  participant S\n  U->>S: request\n  loop every second\n    S--)U: ping\n  end\n  Note over U,S: done\n",
+        )
+        .unwrap();
+        assert_eq!(d.node_by_id("U").unwrap().shape, NodeShape::Actor);
+        assert_eq!(d.label_of("U"), "User");
+        assert_eq!(d.edges[1].end, Marker::OpenArrow);
+        assert_eq!(d.edges[1].line, LineStyle::Dotted);
+        assert_eq!(
+            d.steps,
+            vec![
+                Step::Message(0),
+                Step::FragmentStart {
+                    kind: "loop".to_string(),
+                    text: "every second".to_string()
+                },
+                Step::Message(1),
+                Step::FragmentEnd,
+                Step::Note {
+                    placement: NotePlacement::Over,
+                    participants: vec!["U".to_string(), "S".to_string()],
+                    text: "done".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_class_relations() {
+        let d = parse(
+            "classDiagram\n  class Animal {\n    <<abstract>>\n    +String name\n    +eat()\n  }\n  Animal <|-- Dog\n  Owner \"1\" *-- \"many\" Dog : owns\n  Dog : +bark()\n",
+        )
+        .unwrap();
+        let animal = d.node_by_id("Animal").unwrap();
+        assert_eq!(animal.label, "«abstract»\nAnimal");
+        assert_eq!(animal.details, vec!["+String name", "+eat()"]);
+        assert_eq!(d.edges[0].start, Marker::Triangle);
+        assert_eq!(d.edges[1].start, Marker::Diamond);
+        assert_eq!(d.edges[1].start_label.as_deref(), Some("1"));
+        assert_eq!(d.edges[1].end_label.as_deref(), Some("many"));
+        assert_eq!(d.node_by_id("Dog").unwrap().details, vec!["+bark()"]);
+    }
+
+    #[test]
+    fn test_state_start_end_and_composites() {
+        let d = parse(
+            "stateDiagram-v2\n  [*] --> Idle\n  Idle --> Active : go\n  state Active {\n    [*] --> Working\n    Working --> [*]\n  }\n  Active --> [*]\n",
+        )
+        .unwrap();
+        assert_eq!(d.node_by_id("[*]start").unwrap().shape, NodeShape::Start);
+        assert_eq!(d.node_by_id("[*]end").unwrap().shape, NodeShape::End);
+        assert_eq!(d.subgraphs[0].id, "Active");
+        assert_eq!(
+            d.subgraphs[0].nodes,
+            vec!["Active/[*]start", "Working", "Active/[*]end"]
+        );
+        assert_eq!(d.edges[1].label.as_deref(), Some("go"));
+    }
+
+    #[test]
+    fn test_er_cardinalities() {
+        let d = parse(
+            "erDiagram\n  CUSTOMER ||--o{ ORDER : places\n  ORDER }|..|| PRODUCT : \"contains\"\n  CUSTOMER {\n    string name PK\n  }\n",
+        )
+        .unwrap();
+        assert_eq!(
+            (d.edges[0].start, d.edges[0].end),
+            (Marker::ExactlyOne, Marker::ZeroOrMore)
+        );
+        assert_eq!(d.edges[1].line, LineStyle::Dotted);
+        assert_eq!(d.edges[1].label.as_deref(), Some("contains"));
+        assert_eq!(
+            d.node_by_id("CUSTOMER").unwrap().details,
+            vec!["string name PK"]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_header() {
+        assert!(parse("gantt\n  title x\n").is_none());
+        assert!(parse("").is_none());
+    }
+
+    #[test]
+    fn test_statement_positions() {
+        let statements = statements("%% comment\ngraph TD\n    A --> B; B --> C\n");
+        let positions: Vec<(usize, usize, &str)> = split_semicolons(&statements[1..])
+            .iter()
+            .map(|s| (s.line, s.column, s.text))
+            .collect();
+        assert_eq!(positions, vec![(3, 5, "A --> B"), (3, 14, "B --> C")]);
+    }
+}
@@ -0,0 +1,225 @@
+//! SVG export
+//!
+//! Text is measured as a monospace font so the output does not depend on
+//! fonts installed on the machine that renders it.
+
+use std::fmt::Write;
+
+use super::geometry::{marker, outline, Fill, Point, Primitive};
+use super::layout::{layout, Layout, Monospace, Spacing};
+use super::model::{Diagram, LineStyle, Marker};
+
+const FONT_SIZE: f32 = 14.0;
+const MEASURE: Monospace = Monospace {
+    char_width: FONT_SIZE * 0.6,
+    line_height: FONT_SIZE * 1.3,
+};
+
+const STROKE: &str = "#333333";
+const BACKGROUND: &str = "#ECECFF";
+const CLUSTER: &str = "#FFFFDE";
+const NOTE: &str = "#FFF5AD";
+
+/// Render a diagram as a standalone SVG document
+pub fn render(diagram: &Diagram) -> String {
+    render_layout(&layout(diagram, &MEASURE, &Spacing::pixels()))
+}
+
+/// Render an already computed layout (measured with a monospace font)
+pub fn render_layout(layout: &Layout) -> String {
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.0}" height="{h:.0}" viewBox="0 0 {w:.0} {h:.0}" font-family="monospace" font-size="{FONT_SIZE}">"#,
+        w = layout.width.ceil(),
+        h = layout.height.ceil(),
+    );
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+
+    for cluster in &layout.clusters {
+        let r = cluster.rect;
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{CLUSTER}" fill-opacity="0.6" stroke="{STROKE}" stroke-width="1"/>"#,
+            r.x, r.y, r.width, r.height
+        );
+        text(&mut svg, &cluster.label, cluster.label_at, "middle");
+        for (y, label) in &cluster.dividers {
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{:.1}" y1="{y:.1}" x2="{:.1}" y2="{y:.1}" stroke="{STROKE}" stroke-dasharray="4 3"/>"#,
+                r.x,
+                r.right()
+            );
+            let at = Point::new(r.center().x, y + MEASURE.line_height / 2.0 + 4.0);
+            text(&mut svg, label, at, "middle");
+        }
+    }
+
+    for (top, bottom) in &layout.lifelines {
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{STROKE}" stroke-dasharray="4 3"/>"#,
+            top.x, top.y, bottom.x, bottom.y
+        );
+    }
+
+    for edge in &layout.edges {
+        let style = match edge.line {
+            LineStyle::Solid | LineStyle::Invisible => "stroke-width=\"1.2\"",
+            LineStyle::Dotted => "stroke-width=\"1.2\" stroke-dasharray=\"3 3\"",
+            LineStyle::Thick => "stroke-width=\"3\"",
+        };
+        let _ = writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{STROKE}" {style}/>"#,
+            points(&edge.points)
+        );
+        let count = edge.points.len();
+        let ends = [
+            (edge.start, edge.points[0], edge.points[1]),
+            (edge.end, edge.points[count - 1], edge.points[count - 2]),
+        ];
+        for (kind, tip, from) in ends {
+            if kind != Marker::None {
+                for primitive in marker(kind, tip, from, 9.0) {
+                    shape(&mut svg, &primitive, BACKGROUND);
+                }
+            }
+        }
+        for (label, at) in [&edge.label, &edge.start_label, &edge.end_label]
+            .into_iter()
+            .flatten()
+        {
+            let size = super::layout::Measure::measure(&MEASURE, label);
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="white" fill-opacity="0.85"/>"#,
+                at.x - size.width / 2.0 - 2.0,
+                at.y - size.height / 2.0,
+                size.width + 4.0,
+                size.height
+            );
+            text(&mut svg, label, *at, "middle");
+        }
+    }
+
+    for node in &layout.nodes {
+        let fill = if node.shape == super::model::NodeShape::Note {
+            NOTE
+        } else {
+            BACKGROUND
+        };
+        for primitive in outline(node.shape, node.rect) {
+            shape(&mut svg, &primitive, fill);
+        }
+        text(&mut svg, &node.label, node.label_at, "middle");
+        for section in &node.sections {
+            let r = section.rect;
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{STROKE}"/>"#,
+                r.x,
+                r.y,
+                r.right(),
+                r.y
+            );
+            for (line, at) in &section.lines {
+                text(&mut svg, line, *at, "start");
+            }
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn points(points: &[Point]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{:.1},{:.1}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn shape(svg: &mut String, primitive: &Primitive, background: &str) {
+    let fill = |fill: &Fill| match fill {
+        Fill::None => "none",
+        Fill::Background => background,
+        Fill::Solid => STROKE,
+    };
+    let _ = match primitive {
+        Primitive::Polygon { points: p, fill: f } => writeln!(
+            svg,
+            r#"<polygon points="{}" fill="{}" stroke="{STROKE}"/>"#,
+            points(p),
+            fill(f)
+        ),
+        Primitive::Polyline(p) => writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{STROKE}"/>"#,
+            points(p)
+        ),
+        Primitive::Ellipse {
+            center,
+            rx,
+            ry,
+            fill: f,
+        } => writeln!(
+            svg,
+            r#"<ellipse cx="{:.1}" cy="{:.1}" rx="{:.1}" ry="{:.1}" fill="{}" stroke="{STROKE}"/>"#,
+            center.x,
+            center.y,
+            rx,
+            ry,
+            fill(f)
+        ),
+        Primitive::RoundedRect { rect, radius } => writeln!(
+            svg,
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" rx="{:.1}" fill="{background}" stroke="{STROKE}"/>"#,
+            rect.x, rect.y, rect.width, rect.height, radius
+        ),
+    };
+}
+
+/// Text centered vertically on `at`; multi-line text is centered as a block
+fn text(svg: &mut String, text: &str, at: Point, anchor: &str) {
+    if text.is_empty() {
+        return;
+    }
+    let lines: Vec<&str> = text.split('\n').collect();
+    let first = at.y - (lines.len() - 1) as f32 * MEASURE.line_height / 2.0;
+    for (i, line) in lines.iter().enumerate() {
+        let _ = writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="{anchor}" dominant-baseline="central">{}</text>"#,
+            at.x,
+            first + i as f32 * MEASURE.line_height,
+            escape(line)
+        );
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mermaid::parse;
+
+    #[test]
+    fn test_render_flowchart() {
+        let svg = render(&parse("graph LR\n  A[Start] -->|a < b| B((End))\n").unwrap());
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains(">Start</text>"));
+        assert!(svg.contains(">a &lt; b</text>"));
+        assert!(svg.contains("<ellipse"));
+        assert!(svg.contains("<polyline"));
+    }
+}
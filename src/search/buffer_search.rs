@@ -38,16 +38,21 @@ pub fn search_in_buffer(
     let content = buffer.content();
     let mut results = Vec::new();
 
-    if options.use_regex {
-        // Regex search
-        let regex = build_regex(pattern, options)?;
+    if options.use_regex || !options.case_sensitive {
+        // Regex search; case-insensitive literals are escaped and matched
+        // in `content` itself, as lowercasing may change byte lengths
+        let regex = if options.use_regex {
+            build_regex(pattern, options)?
+        } else {
+            build_regex(&regex::escape(pattern), options)?
+        };
 
         for mat in regex.find_iter(&content) {
             let start_pos = buffer
-                .char_idx_to_position(mat.start())
+                .char_idx_to_position(buffer.rope().byte_to_char(mat.start()))
                 .map_err(|e| format!("Failed to convert start position: {e}"))?;
             let end_pos = buffer
-                .char_idx_to_position(mat.end())
+                .char_idx_to_position(buffer.rope().byte_to_char(mat.end()))
                 .map_err(|e| format!("Failed to convert end position: {e}"))?;
 
             results.push(SearchResult {
@@ -56,29 +61,17 @@ pub fn search_in_buffer(
             });
         }
     } else {
-        // Literal search
-        let search_pattern = if options.case_sensitive {
-            pattern.to_string()
-        } else {
-            pattern.to_lowercase()
-        };
-
-        let search_content = if options.case_sensitive {
-            content.clone()
-        } else {
-            content.to_lowercase()
-        };
-
+        // Case-sensitive literal search
         let mut start = 0;
-        while let Some(pos) = search_content[start..].find(&search_pattern) {
+        while let Some(pos) = content[start..].find(pattern) {
             let match_start = start + pos;
             let match_end = match_start + pattern.len();
 
             let start_pos = buffer
-                .char_idx_to_position(match_start)
+                .char_idx_to_position(buffer.rope().byte_to_char(match_start))
                 .map_err(|e| format!("Failed to convert start position: {e}"))?;
             let end_pos = buffer
-                .char_idx_to_position(match_end)
+                .char_idx_to_position(buffer.rope().byte_to_char(match_end))
                 .map_err(|e| format!("Failed to convert end position: {e}"))?;
 
             results.push(SearchResult {
@@ -209,6 +202,37 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_search_positions_count_chars() {
+        let buffer = Buffer::from_string(BufferId(0), "日本語\nテスト 日本\n".to_string(), None);
+        let results = search_in_buffer(&buffer, "日本", &SearchOptions::default()).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].range.start.line, 1);
+        assert_eq!(results[1].range.start.col, 4);
+        assert_eq!(results[1].range.end.col, 6);
+
+        let mut buffer = buffer;
+        replace_in_buffer(&mut buffer, "日本", "英", &SearchOptions::default(), true).unwrap();
+        assert_eq!(buffer.content(), "英語\nテスト 英\n");
+    }
+
+    #[test]
+    fn test_search_case_insensitive_keeps_offsets() {
+        // `İ` lowercases to three bytes and the Kelvin sign to one
+        let buffer = Buffer::from_string(BufferId(0), "İx \u{212a}elvin kelvin".to_string(), None);
+        let options = SearchOptions {
+            case_sensitive: false,
+            ..SearchOptions::default()
+        };
+
+        let results = search_in_buffer(&buffer, "kelvin", &options).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].match_text, "\u{212a}elvin");
+        assert_eq!(results[0].range.start.col, 3);
+        assert_eq!(results[1].match_text, "kelvin");
+        assert_eq!(results[1].range.start.col, 10);
+    }
+
     #[test]
     fn test_replace_single() {
         let mut buffer =
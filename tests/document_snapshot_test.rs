@@ -0,0 +1,106 @@
+/// Snapshot tests for the shared document model
+///
+/// Every fixture is parsed into a `Document` and rendered by the plain text
+/// and HTML backends. The output is compared with the files in
+/// `tests/snapshots/`, so a rendering change shows up as a reviewable diff.
+///
+/// To accept intended changes, run:
+/// `UPDATE_SNAPSHOTS=1 cargo test --test document_snapshot_test`
+use lala::document::{self, Document};
+use std::fs;
+use std::path::Path;
+
+fn fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    fs::read_to_string(&path).unwrap_or_else(|err| panic!("failed to read {path:?}: {err}"))
+}
+
+fn assert_snapshot(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(name);
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!("missing snapshot {path:?}; run with UPDATE_SNAPSHOTS=1 to create it")
+    });
+    assert!(
+        expected == actual,
+        "snapshot {name} differs\n--- expected\n{expected}\n--- actual\n{actual}"
+    );
+}
+
+fn assert_backends(name: &str, doc: &Document) {
+    // A fixed width keeps diagram layouts independent of the terminal
+    let text = document::plain::render_with_width(doc, 80);
+    assert_snapshot(&format!("{name}.txt"), &text);
+    assert_snapshot(&format!("{name}.html"), &document::html_writer::render(doc));
+}
+
+#[test]
+fn test_markdown_snapshot() {
+    let doc = document::markdown::parse(&fixture("test_sample.md"));
+    assert_backends("test_sample.md", &doc);
+}
+
+#[test]
+fn test_markdown_embedded_snapshot() {
+    // Mermaid and math fences render like GitHub shows them
+    let doc = document::markdown::parse(&fixture("embedded.md"));
+    assert_backends("embedded.md", &doc);
+}
+
+#[test]
+fn test_html_snapshot() {
+    let doc = document::html::parse(&fixture("sample.html"));
+    assert_backends("sample.html", &doc);
+}
+
+#[test]
+fn test_latex_snapshot() {
+    let doc = document::latex::parse(&fixture("document.tex"));
+    assert_backends("document.tex", &doc);
+}
+
+#[test]
+fn test_latex_structure_snapshot() {
+    // Numbering, cross-references, an `\input` file and a `.bib` database
+    let base_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let doc = document::latex::parse_with_base(&fixture("structure.tex"), Some(&base_dir));
+    assert_backends("structure.tex", &doc);
+}
+
+#[test]
+fn test_mermaid_flowchart_snapshot() {
+    let doc = document::mermaid::parse(&fixture("diagram.mmd"));
+    assert_backends("diagram.mmd", &doc);
+}
+
+#[test]
+fn test_mermaid_sequence_snapshot() {
+    let doc = document::mermaid::parse(&fixture("sequence.mmd"));
+    assert_backends("sequence.mmd", &doc);
+}
+
+#[test]
+fn test_markdown_and_html_agree() {
+    // The same content written in Markdown and HTML yields the same document
+    // (only Markdown tracks source positions)
+    let markdown = document::markdown::parse(
+        "# Title\n\nSome **bold** and `code`.\n\n1. one\n2. two\n\n> quoted\n",
+    );
+    let html = document::html::parse(
+        "<h1>Title</h1><p>Some <strong>bold</strong> and <code>code</code>.</p>\
+         <ol><li>one</li><li>two</li></ol><blockquote><p>quoted</p></blockquote>",
+    );
+    assert_eq!(markdown.title, html.title);
+    assert_eq!(markdown.metadata, html.metadata);
+    assert_eq!(markdown.blocks, html.blocks);
+}
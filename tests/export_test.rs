@@ -0,0 +1,86 @@
+/// Tests for `lala export`
+///
+/// Every fixture kind is exported to every format and the output is
+/// checked for the right file signature and the expected content.
+use std::path::Path;
+
+use lala::export::{self, Format};
+
+fn export_fixture(name: &str, format: Format) -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    let source = std::fs::read_to_string(&path).unwrap();
+    export::export(&path, &source, format)
+        .unwrap_or_else(|err| panic!("exporting {name} to {format:?}: {err}"))
+        .bytes
+}
+
+#[test]
+fn test_export_all_fixtures_to_all_formats() {
+    for name in [
+        "test_sample.md",
+        "embedded.md",
+        "sample.html",
+        "document.tex",
+        "diagram.mmd",
+    ] {
+        for format in Format::ALL {
+            let bytes = export_fixture(name, format);
+            let signature: &[u8] = match format {
+                Format::Html => b"<!DOCTYPE html>",
+                Format::Pdf => b"%PDF-",
+                Format::Svg => b"<svg",
+                Format::Png => b"\x89PNG",
+            };
+            assert!(
+                bytes.starts_with(signature),
+                "{name} as {format:?} starts with {:?}",
+                String::from_utf8_lossy(&bytes[..bytes.len().min(16)])
+            );
+        }
+    }
+}
+
+#[test]
+fn test_html_export_is_self_contained() {
+    let html = String::from_utf8(export_fixture("embedded.md", Format::Html)).unwrap();
+    assert!(html.contains("<style>"));
+    assert!(!html.contains("<link"));
+    assert!(!html.contains("<script"));
+    // Diagrams become SVG, math MathML
+    assert!(html.contains("<svg"));
+    assert!(html.contains("<math"));
+    assert!(html.contains("<msqrt>"));
+
+    let html = String::from_utf8(export_fixture("test_sample.md", Format::Html)).unwrap();
+    // Code is highlighted with classes styled by the inlined CSS
+    assert!(html.contains(r#"<span class="hl-"#));
+    assert!(html.contains(".hl-"));
+}
+
+#[test]
+fn test_pdf_export_embeds_fonts() {
+    let pdf = export_fixture("test_sample.md", Format::Pdf);
+    let text = String::from_utf8_lossy(&pdf);
+    assert!(text.contains("/FontFile2"));
+    assert!(text.contains("/ToUnicode"));
+    assert!(text.trim_end().ends_with("%%EOF"));
+}
+
+#[test]
+fn test_diagram_svg_export_is_the_diagram() {
+    let svg = String::from_utf8(export_fixture("diagram.mmd", Format::Svg)).unwrap();
+    let expected = {
+        let source = std::fs::read_to_string(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/diagram.mmd"),
+        )
+        .unwrap();
+        let doc = export::parse(Path::new("diagram.mmd"), &source).unwrap();
+        match doc.blocks.as_slice() {
+            [lala::document::Block::Diagram(diagram)] => lala::mermaid::svg::render(diagram),
+            _ => panic!("diagram.mmd is a single diagram"),
+        }
+    };
+    assert_eq!(svg, expected);
+}